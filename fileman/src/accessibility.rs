@@ -0,0 +1,152 @@
+//! Accessibility bridge
+//!
+//! Exposes the accessibility trees published by the toolbar, sidebar, file list and
+//! status bar to assistive technologies over AT-SPI. The trees are polled for
+//! changes a few times a second and only sent while a screen reader is listening.
+
+use accesskit::{
+    ActionHandler, ActionRequest, ActivationHandler, DeactivationHandler, Live, Node, NodeId, Role,
+    Tree, TreeId, TreeUpdate,
+};
+use accesskit_unix::Adapter;
+use nptk_fileman_widgets::accessibility::{AccessibleNode, AccessibleRole, AccessibleSubtree};
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Duration;
+
+/// How often the published trees are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const WINDOW_ID: NodeId = NodeId(0);
+
+fn role(role: AccessibleRole) -> Role {
+    match role {
+        AccessibleRole::List => Role::List,
+        AccessibleRole::ListItem => Role::ListItem,
+        AccessibleRole::Toolbar => Role::Toolbar,
+        AccessibleRole::Button => Role::Button,
+        AccessibleRole::Navigation => Role::Navigation,
+        AccessibleRole::Group => Role::Group,
+        AccessibleRole::StatusBar => Role::Status,
+    }
+}
+
+/// Id of a node under `parent`, stable while the tree around it changes (screen
+/// readers track nodes by id).
+///
+/// Items are identified by role and name. Containers and live regions, whose
+/// names change with their content (folder, status text), keep their position.
+fn node_id(
+    parent: NodeId,
+    node: &AccessibleNode,
+    index: usize,
+    taken: &mut HashSet<NodeId>,
+) -> NodeId {
+    let mut hasher = DefaultHasher::new();
+    (parent.0, node.role).hash(&mut hasher);
+    if node.children.is_empty() && !node.live {
+        node.name.hash(&mut hasher);
+        let id = NodeId(hasher.finish());
+        if taken.insert(id) {
+            return id;
+        }
+    }
+    // Siblings with the same name and role are told apart by position
+    index.hash(&mut hasher);
+    let id = NodeId(hasher.finish());
+    taken.insert(id);
+    id
+}
+
+/// Ids of `children` under `parent`.
+fn child_ids(parent: NodeId, children: &[AccessibleNode]) -> Vec<(NodeId, &AccessibleNode)> {
+    let mut taken = HashSet::new();
+    children
+        .iter()
+        .enumerate()
+        .map(|(index, child)| (node_id(parent, child, index, &mut taken), child))
+        .collect()
+}
+
+fn add_node(nodes: &mut Vec<(NodeId, Node)>, id: NodeId, accessible: &AccessibleNode) {
+    let mut node = Node::new(role(accessible.role));
+    node.set_label(accessible.name.clone());
+    if let Some(description) = &accessible.description {
+        node.set_description(description.clone());
+    }
+    if let Some(selected) = accessible.selected {
+        node.set_selected(selected);
+    }
+    if accessible.disabled {
+        node.set_disabled();
+    }
+    if accessible.live {
+        node.set_live(Live::Polite);
+    }
+
+    let children = child_ids(id, &accessible.children);
+    node.set_children(children.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+    nodes.push((id, node));
+    for (child_id, child) in children {
+        add_node(nodes, child_id, child);
+    }
+}
+
+/// The whole tree: the window with the published subtrees in order.
+fn build_tree(subtrees: &[AccessibleSubtree]) -> TreeUpdate {
+    let roots: Vec<AccessibleNode> = subtrees.iter().filter_map(AccessibleSubtree::get).collect();
+    let children = child_ids(WINDOW_ID, &roots);
+
+    let mut window = Node::new(Role::Window);
+    window.set_label("fileman");
+    window.set_children(children.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+    let mut nodes = vec![(WINDOW_ID, window)];
+    for (id, root) in children {
+        add_node(&mut nodes, id, root);
+    }
+
+    let mut tree = Tree::new(WINDOW_ID);
+    tree.toolkit_name = Some("nptk".to_string());
+    TreeUpdate {
+        nodes,
+        tree: Some(tree),
+        tree_id: TreeId::ROOT,
+        focus: WINDOW_ID,
+    }
+}
+
+struct Activation(Vec<AccessibleSubtree>);
+
+impl ActivationHandler for Activation {
+    fn request_initial_tree(&mut self) -> Option<TreeUpdate> {
+        Some(build_tree(&self.0))
+    }
+}
+
+/// The tree is read-only; actions are done through the keyboard.
+struct ReadOnly;
+
+impl ActionHandler for ReadOnly {
+    fn do_action(&mut self, _request: ActionRequest) {}
+}
+
+impl DeactivationHandler for ReadOnly {
+    fn deactivate_accessibility(&mut self) {}
+}
+
+/// Expose `subtrees` (in reading order) to assistive technologies.
+pub fn spawn(subtrees: Vec<AccessibleSubtree>) {
+    tokio::spawn(async move {
+        let mut adapter = Adapter::new(Activation(subtrees.clone()), ReadOnly, ReadOnly);
+        adapter.update_window_focus_state(true);
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            // Clear every flag, not only up to the first changed subtree
+            let changed = subtrees
+                .iter()
+                .fold(false, |changed, subtree| subtree.take_changed() || changed);
+            if changed {
+                adapter.update_if_active(|| build_tree(&subtrees));
+            }
+        }
+    });
+}
@@ -0,0 +1,247 @@
+//! What double-clicking a file does
+//!
+//! Files are grouped into broad categories by MIME type. Each category can open
+//! the file in its default application, show it in the built-in preview, or ask
+//! which of the two to do. The choice is stored in the settings.
+//! `.desktop` launchers are started instead of opened.
+
+mod ask;
+mod launcher;
+mod preview;
+mod trust;
+
+pub use ask::ActivationChoiceDialog;
+pub use preview::PreviewDialog;
+pub use trust::LauncherTrustDialog;
+
+use crate::settings;
+use npio::service::filesystem::mime_detector::MimeDetector;
+use nptk::prelude::AppContext;
+use nptk_fileman_widgets::desktop_entries;
+use nptk_fileman_widgets::file_list::open_with_default_app;
+use nptk_fileman_widgets::special_files::SpecialKind;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MimeCategory {
+    Image,
+    Video,
+    Audio,
+    Text,
+    Document,
+    Archive,
+    Other,
+}
+
+impl MimeCategory {
+    pub const ALL: [MimeCategory; 7] = [
+        MimeCategory::Image,
+        MimeCategory::Video,
+        MimeCategory::Audio,
+        MimeCategory::Text,
+        MimeCategory::Document,
+        MimeCategory::Archive,
+        MimeCategory::Other,
+    ];
+
+    /// Name used in the settings file
+    pub fn key(self) -> &'static str {
+        match self {
+            MimeCategory::Image => "image",
+            MimeCategory::Video => "video",
+            MimeCategory::Audio => "audio",
+            MimeCategory::Text => "text",
+            MimeCategory::Document => "document",
+            MimeCategory::Archive => "archive",
+            MimeCategory::Other => "other",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|category| category.key() == key)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MimeCategory::Image => "Images",
+            MimeCategory::Video => "Videos",
+            MimeCategory::Audio => "Audio",
+            MimeCategory::Text => "Text files",
+            MimeCategory::Document => "Documents",
+            MimeCategory::Archive => "Archives",
+            MimeCategory::Other => "Other files",
+        }
+    }
+
+    pub fn from_mime(mime: &str) -> Self {
+        let (kind, subtype) = mime.split_once('/').unwrap_or((mime, ""));
+        match kind {
+            "image" => MimeCategory::Image,
+            "video" => MimeCategory::Video,
+            "audio" => MimeCategory::Audio,
+            "text" => MimeCategory::Text,
+            "application" => match subtype {
+                "pdf" | "msword" | "rtf" | "epub+zip" | "postscript" => MimeCategory::Document,
+                s if s.starts_with("vnd.oasis.opendocument")
+                    || s.starts_with("vnd.openxmlformats-officedocument")
+                    || s.starts_with("vnd.ms-") =>
+                {
+                    MimeCategory::Document
+                },
+                "zip"
+                | "gzip"
+                | "zstd"
+                | "x-tar"
+                | "x-compressed-tar"
+                | "x-bzip2"
+                | "x-bzip2-compressed-tar"
+                | "x-xz"
+                | "x-xz-compressed-tar"
+                | "x-7z-compressed"
+                | "x-rar"
+                | "vnd.rar" => MimeCategory::Archive,
+                s if s.contains("json")
+                    || s.contains("xml")
+                    || s.contains("yaml")
+                    || s.contains("toml")
+                    || s.contains("javascript")
+                    || s == "x-shellscript" =>
+                {
+                    MimeCategory::Text
+                },
+                _ => MimeCategory::Other,
+            },
+            _ => MimeCategory::Other,
+        }
+    }
+
+    /// Category of the file at `path`.
+    pub fn of(path: &Path) -> Self {
+        smol::block_on(MimeDetector::detect_mime_type(path))
+            .map(|mime| Self::from_mime(&mime))
+            .unwrap_or(MimeCategory::Other)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Activation {
+    /// Open in the default application
+    #[default]
+    Open,
+    /// Show in the built-in preview
+    Preview,
+    /// Ask every time
+    Ask,
+}
+
+impl Activation {
+    /// Name used in the settings file
+    pub fn key(self) -> &'static str {
+        match self {
+            Activation::Open => "open",
+            Activation::Preview => "preview",
+            Activation::Ask => "ask",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "open" => Some(Activation::Open),
+            "preview" => Some(Activation::Preview),
+            "ask" => Some(Activation::Ask),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Activation::Open => "Open",
+            Activation::Preview => "Preview",
+            Activation::Ask => "Ask",
+        }
+    }
+
+    /// The next choice, for cycling through them with one button
+    pub fn next(self) -> Self {
+        match self {
+            Activation::Open => Activation::Preview,
+            Activation::Preview => Activation::Ask,
+            Activation::Ask => Activation::Open,
+        }
+    }
+}
+
+/// Handle a double-clicked file as configured for its category.
+///
+/// Pipes, sockets and devices are refused: opening or previewing them would
+/// block on them or read a device. Launchers are started, untrusted ones after
+/// asking.
+pub fn activate(path: PathBuf, context: &AppContext) -> Result<(), String> {
+    if let Some(kind) = SpecialKind::of_path(&path) {
+        return Err(format!(
+            "{} is a {} and can't be opened",
+            path.display(),
+            kind.label().to_lowercase()
+        ));
+    }
+    if desktop_entries::is_launcher(&path) {
+        if let Some(entry) = desktop_entries::load(&path) {
+            if !launcher::is_trusted(&path) {
+                LauncherTrustDialog::new(path, entry).show(context);
+                return Ok(());
+            }
+            return launcher::launch(&path, &entry);
+        }
+    }
+    let category = MimeCategory::of(&path);
+    match settings::get().activation_for(category) {
+        Activation::Open => open_with_default_app(path),
+        Activation::Preview => PreviewDialog::new(path, category).show(context),
+        Activation::Ask => ActivationChoiceDialog::new(path, category).show(context),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_mime_types() {
+        for (mime, category) in [
+            ("image/png", MimeCategory::Image),
+            ("video/mp4", MimeCategory::Video),
+            ("audio/flac", MimeCategory::Audio),
+            ("text/plain", MimeCategory::Text),
+            ("application/json", MimeCategory::Text),
+            ("application/x-shellscript", MimeCategory::Text),
+            ("application/pdf", MimeCategory::Document),
+            ("application/vnd.oasis.opendocument.text", MimeCategory::Document),
+            ("application/vnd.ms-excel", MimeCategory::Document),
+            ("application/x-xz-compressed-tar", MimeCategory::Archive),
+            ("application/zip", MimeCategory::Archive),
+            ("application/octet-stream", MimeCategory::Other),
+            ("inode/directory", MimeCategory::Other),
+            ("garbage", MimeCategory::Other),
+        ] {
+            assert_eq!(MimeCategory::from_mime(mime), category, "{}", mime);
+        }
+    }
+
+    #[test]
+    fn keys_read_back() {
+        for category in MimeCategory::ALL {
+            assert_eq!(MimeCategory::from_key(category.key()), Some(category));
+        }
+        assert_eq!(MimeCategory::from_key("fonts"), None);
+
+        let mut activation = Activation::default();
+        for _ in 0..3 {
+            assert_eq!(Activation::from_key(activation.key()), Some(activation));
+            activation = activation.next();
+        }
+        // Cycling comes back around after every choice
+        assert_eq!(activation, Activation::default());
+        assert_eq!(Activation::from_key("edit"), None);
+    }
+}
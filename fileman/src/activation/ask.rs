@@ -0,0 +1,181 @@
+//! Popup asking whether to open or preview a double-clicked file.
+
+use super::{Activation, MimeCategory, PreviewDialog};
+use crate::settings;
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk_fileman_widgets::file_list::open_with_default_app;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Which button was pressed, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChoiceRequest {
+    ToggleRemember,
+    Choose(Activation),
+}
+
+/// Open and Preview buttons, with a toggle to remember the choice for the category.
+pub struct ActivationChoiceDialog {
+    path: PathBuf,
+    category: MimeCategory,
+    remember: bool,
+    inner: Container,
+    message_text: StateSignal<String>,
+    request: Arc<Mutex<Option<ChoiceRequest>>>,
+    signals_hooked: bool,
+}
+
+impl ActivationChoiceDialog {
+    pub fn new(path: PathBuf, category: MimeCategory) -> Self {
+        let mut dialog = Self {
+            path,
+            category,
+            remember: false,
+            inner: Container::new(vec![]),
+            message_text: StateSignal::new(String::new()),
+            request: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        };
+        dialog.rebuild();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), "Open File", (400, 200), (300, 200));
+    }
+
+    fn button(&self, label: &str, request: ChoiceRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn rebuild(&mut self) {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string());
+        let remember = format!(
+            "Remember for {}: {}",
+            self.category.label().to_lowercase(),
+            if self.remember { "On" } else { "Off" }
+        );
+
+        self.inner = Container::new(vec![
+            Box::new(Text::new(format!("What should be done with \"{}\"?", name))),
+            Box::new(self.button(&remember, ChoiceRequest::ToggleRemember)),
+            Box::new(Text::new(self.message_text.maybe()).with_font_size(12.0)),
+            Box::new(
+                Container::new(vec![
+                    Box::new(
+                        Button::new(Text::new("Cancel".to_string()))
+                            .with_on_pressed(MaybeSignal::value(Update::DRAW)),
+                    ),
+                    Box::new(self.button("Preview", ChoiceRequest::Choose(Activation::Preview))),
+                    Box::new(self.button("Open", ChoiceRequest::Choose(Activation::Open))),
+                ])
+                .with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Row,
+                    gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                    justify_content: Some(JustifyContent::FlexEnd),
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    ..Default::default()
+                }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(
+                LengthPercentage::length(0.0),
+                LengthPercentage::length(10.0),
+            ),
+            ..Default::default()
+        });
+    }
+
+    fn choose(&mut self, activation: Activation, context: &AppContext) {
+        if self.remember {
+            let category = self.category;
+            if let Err(e) = settings::update(|s| {
+                s.activation.insert(category, activation);
+            }) {
+                log::error!("{}", e);
+                self.message_text.set(e);
+            }
+        }
+        match activation {
+            Activation::Preview => {
+                PreviewDialog::new(self.path.clone(), self.category).show(context)
+            },
+            _ => open_with_default_app(self.path.clone()),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for ActivationChoiceDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(
+        &mut self,
+        layout: &LayoutNode,
+        context: AppContext,
+        info: &mut AppInfo,
+    ) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.message_text);
+            self.signals_hooked = true;
+        }
+
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        if let Some(request) = request {
+            match request {
+                ChoiceRequest::ToggleRemember => {
+                    self.remember = !self.remember;
+                    self.rebuild();
+                },
+                ChoiceRequest::Choose(activation) => self.choose(activation, &context),
+            }
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,79 @@
+//! Starting `.desktop` launchers
+//!
+//! A launcher can run any command under a harmless name and icon, so only
+//! trusted ones are started right away: those in the application folders of
+//! the system and the user, and those marked executable. Others, e.g.
+//! downloaded ones, are marked executable once the user confirms it.
+
+pub use nptk_fileman_widgets::desktop_entries::is_trusted;
+use nptk_fileman_widgets::desktop_entries::{DesktopEntry, DesktopEntryKind};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+/// Terminals tried for launchers that ask for one, after `$TERMINAL`
+const TERMINALS: &[&str] = &["x-terminal-emulator", "xdg-terminal-exec", "foot", "alacritty", "kitty", "xterm"];
+
+/// Mark the launcher at `path` executable, so it is trusted from now on.
+pub fn trust(path: &Path) -> Result<(), String> {
+    let mut permissions = fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .permissions();
+    permissions.set_mode(permissions.mode() | 0o100);
+    fs::set_permissions(path, permissions).map_err(|e| format!("Failed to trust {}: {}", path.display(), e))
+}
+
+/// Wrap `args` to run in a terminal window.
+fn in_terminal(args: Vec<String>) -> Result<Vec<String>, String> {
+    let terminal = std::env::var("TERMINAL")
+        .ok()
+        .filter(|terminal| !terminal.is_empty())
+        .into_iter()
+        .chain(TERMINALS.iter().map(|terminal| terminal.to_string()))
+        .find(|terminal| which(terminal))
+        .ok_or_else(|| "No terminal found; set $TERMINAL".to_string())?;
+    // xdg-terminal-exec takes the command without -e
+    let mut wrapped = vec![terminal.clone()];
+    if terminal != "xdg-terminal-exec" {
+        wrapped.push("-e".to_string());
+    }
+    wrapped.extend(args);
+    Ok(wrapped)
+}
+
+/// Whether `program` can be run, as a path or from `$PATH`
+fn which(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+/// Start what the launcher at `path` describes: run its program, or open its link.
+pub fn launch(path: &Path, launcher: &DesktopEntry) -> Result<(), String> {
+    match &launcher.kind {
+        DesktopEntryKind::Application { terminal, working_dir, .. } => {
+            let mut args = launcher.command(path)?;
+            if *terminal {
+                args = in_terminal(args)?;
+            }
+            let mut command = Command::new(&args[0]);
+            command.args(&args[1..]);
+            if let Some(dir) = working_dir {
+                command.current_dir(dir);
+            }
+            command
+                .spawn()
+                .map(|_| ())
+                .map_err(|e| format!("Failed to start {}: {}", launcher.name, e))
+        },
+        DesktopEntryKind::Link { url } => Command::new("xdg-open")
+            .arg(url)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to open {}: {}", url, e)),
+        DesktopEntryKind::Directory => Err(format!("{} describes a folder and starts nothing", launcher.name)),
+    }
+}
@@ -0,0 +1,302 @@
+//! Built-in preview of a file: images are drawn, text files show their
+//! beginning, other files what a plugin previews of them, and anything else
+//! shows its type.
+
+use super::MimeCategory;
+use crate::plugins;
+use async_trait::async_trait;
+use npio::service::filesystem::mime_detector::MimeDetector;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::vg::kurbo::{Affine, Vec2};
+use nptk::core::vg::peniko::{Blob, ImageAlphaType, ImageBrush, ImageData, ImageFormat};
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::scroll_container::{ScrollContainer, ScrollDirection};
+use nptk_fileman_widgets::file_list::open_with_default_app;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Height of the area an image is fitted into
+const IMAGE_PREVIEW_HEIGHT: f32 = 400.0;
+/// Larger images are scaled down to this many pixels on their longest side when decoded
+const MAX_DECODED_SIZE: u32 = 2048;
+/// How much of a text file is shown
+const TEXT_PREVIEW_BYTES: u64 = 64 * 1024;
+const TEXT_PREVIEW_LINES: usize = 200;
+
+type DecodedImage = Result<ImageData, String>;
+
+fn decode_image(path: &Path) -> DecodedImage {
+    let mut image = image::open(path).map_err(|e| format!("Failed to decode image: {}", e))?;
+    if image.width() > MAX_DECODED_SIZE || image.height() > MAX_DECODED_SIZE {
+        image = image.thumbnail(MAX_DECODED_SIZE, MAX_DECODED_SIZE);
+    }
+    let rgba = image.to_rgba8();
+    Ok(ImageData {
+        width: rgba.width(),
+        height: rgba.height(),
+        data: Blob::from(rgba.into_raw()),
+        format: ImageFormat::Rgba8,
+        alpha_type: ImageAlphaType::Alpha,
+    })
+}
+
+/// Draws an image scaled to fit, decoding it on a background thread.
+struct ImageView {
+    decoded: Arc<Mutex<Option<DecodedImage>>>,
+    image: Option<ImageBrush>,
+    error: Option<Text>,
+}
+
+impl ImageView {
+    fn new(path: PathBuf) -> Self {
+        let decoded = Arc::new(Mutex::new(None));
+        let result = decoded.clone();
+        std::thread::spawn(move || {
+            let image = decode_image(&path);
+            if let Ok(mut result) = result.lock() {
+                *result = Some(image);
+            }
+        });
+        Self {
+            decoded,
+            image: None,
+            error: None,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for ImageView {
+    fn layout_style(&self, _context: &LayoutContext) -> StyleNode {
+        StyleNode {
+            style: LayoutStyle {
+                size: Vector2::new(
+                    Dimension::percent(1.0),
+                    Dimension::length(IMAGE_PREVIEW_HEIGHT),
+                ),
+                flex_shrink: 0.0,
+                ..Default::default()
+            },
+            children: vec![],
+            measure_func: None,
+        }
+    }
+
+    async fn update(
+        &mut self,
+        _layout: &LayoutNode,
+        _context: AppContext,
+        _info: &mut AppInfo,
+    ) -> Update {
+        let decoded = self.decoded.lock().ok().and_then(|mut d| d.take());
+        match decoded {
+            Some(Ok(image)) => {
+                self.image = Some(ImageBrush::new(image));
+                Update::DRAW
+            },
+            Some(Err(e)) => {
+                log::warn!("{}", e);
+                self.error = Some(Text::new(e));
+                Update::LAYOUT | Update::DRAW
+            },
+            None => Update::empty(),
+        }
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        if let Some(error) = &mut self.error {
+            error.render(graphics, layout, info, context);
+            return;
+        }
+        let Some(brush) = &self.image else {
+            return;
+        };
+        let (width, height) = (brush.image.width as f64, brush.image.height as f64);
+        let (area_width, area_height) = (
+            layout.layout.size.width as f64,
+            layout.layout.size.height as f64,
+        );
+        // Fit inside the area without enlarging small images
+        let scale = (area_width / width).min(area_height / height).min(1.0);
+        let x = layout.layout.location.x as f64 + (area_width - width * scale) / 2.0;
+        let y = layout.layout.location.y as f64 + (area_height - height * scale) / 2.0;
+        if let Some(scene) = graphics.as_scene_mut() {
+            scene.draw_image(brush, Affine::scale(scale).then_translate(Vec2::new(x, y)));
+        }
+    }
+}
+
+/// The beginning of a text file, cut at [TEXT_PREVIEW_BYTES] or [TEXT_PREVIEW_LINES].
+fn read_text_head(path: &Path) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)
+        .and_then(|file| file.take(TEXT_PREVIEW_BYTES).read_to_end(&mut bytes))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let mut head = text
+        .lines()
+        .take(TEXT_PREVIEW_LINES)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if head.len() < text.trim_end().len() {
+        head.push_str("\n…");
+    }
+    Ok(head)
+}
+
+/// Shows a preview of one file with a button to open it in its application.
+pub struct PreviewDialog {
+    path: PathBuf,
+    inner: Container,
+    open_requested: Arc<Mutex<bool>>,
+}
+
+impl PreviewDialog {
+    pub fn new(path: PathBuf, category: MimeCategory) -> Self {
+        let mut dialog = Self {
+            path,
+            inner: Container::new(vec![]),
+            open_requested: Arc::new(Mutex::new(false)),
+        };
+        dialog.build(category);
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        let title = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Preview".to_string());
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), &title, (640, 520), (200, 100));
+    }
+
+    fn text_view(text: String) -> Box<dyn Widget> {
+        Box::new(
+            ScrollContainer::new()
+                .with_scroll_direction(ScrollDirection::Vertical)
+                .with_child(Text::new(text).with_font_size(12.0))
+                .with_layout_style(LayoutStyle {
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    flex_grow: 1.0,
+                    ..Default::default()
+                }),
+        )
+    }
+
+    fn body(&self, category: MimeCategory) -> Box<dyn Widget> {
+        match category {
+            MimeCategory::Image => Box::new(ImageView::new(self.path.clone())),
+            MimeCategory::Text => Self::text_view(read_text_head(&self.path).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                e
+            })),
+            // Plugins preview what fileman can't
+            _ => match plugins::preview(&self.path) {
+                Some(text) => Self::text_view(text.unwrap_or_else(|e| {
+                    log::warn!("{}", e);
+                    e
+                })),
+                None => {
+                    let mime = smol::block_on(MimeDetector::detect_mime_type(&self.path))
+                        .unwrap_or_else(|| "unknown".to_string());
+                    Box::new(Text::new(format!(
+                        "No preview is available for this kind of file ({}).",
+                        mime
+                    )))
+                },
+            },
+        }
+    }
+
+    fn build(&mut self, category: MimeCategory) {
+        let open_requested = self.open_requested.clone();
+        self.inner = Container::new(vec![
+            self.body(category),
+            Box::new(
+                Container::new(vec![
+                    Box::new(
+                        Button::new(Text::new("Close".to_string()))
+                            .with_on_pressed(MaybeSignal::value(Update::DRAW)),
+                    ),
+                    Box::new(Button::new(Text::new("Open".to_string())).with_on_pressed(
+                        MaybeSignal::signal(Box::new(EvalSignal::new(move || {
+                            if let Ok(mut flag) = open_requested.lock() {
+                                *flag = true;
+                            }
+                            Update::DRAW
+                        }))),
+                    )),
+                ])
+                .with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Row,
+                    gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                    justify_content: Some(JustifyContent::FlexEnd),
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    ..Default::default()
+                }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(
+                LengthPercentage::length(0.0),
+                LengthPercentage::length(10.0),
+            ),
+            ..Default::default()
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for PreviewDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(
+        &mut self,
+        layout: &LayoutNode,
+        context: AppContext,
+        info: &mut AppInfo,
+    ) -> Update {
+        let open = self
+            .open_requested
+            .lock()
+            .map(|mut f| std::mem::take(&mut *f))
+            .unwrap_or(false);
+        if open {
+            open_with_default_app(self.path.clone());
+        }
+        self.inner.update(layout, context, info).await
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
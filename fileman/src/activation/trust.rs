@@ -0,0 +1,149 @@
+//! Popup asking whether to trust and start an untrusted launcher.
+
+use super::launcher;
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk_fileman_widgets::desktop_entries::{DesktopEntry, DesktopEntryKind};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Shows what the launcher would run, with Trust and Launch.
+pub struct LauncherTrustDialog {
+    path: PathBuf,
+    launcher: DesktopEntry,
+    inner: Container,
+    message_text: StateSignal<String>,
+    launch_requested: Arc<Mutex<bool>>,
+    signals_hooked: bool,
+}
+
+impl LauncherTrustDialog {
+    pub fn new(path: PathBuf, launcher: DesktopEntry) -> Self {
+        let mut dialog = Self {
+            path,
+            launcher,
+            inner: Container::new(vec![]),
+            message_text: StateSignal::new(String::new()),
+            launch_requested: Arc::new(Mutex::new(false)),
+            signals_hooked: false,
+        };
+        dialog.rebuild();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), "Untrusted Launcher", (460, 220), (300, 200));
+    }
+
+    fn rebuild(&mut self) {
+        let file_name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string());
+        // Show what would really run, not only the name it claims
+        let target = match &self.launcher.kind {
+            DesktopEntryKind::Application { exec, .. } => format!("Command: {}", exec),
+            DesktopEntryKind::Link { url } => format!("Address: {}", url),
+            DesktopEntryKind::Directory => String::new(),
+        };
+
+        let pending = self.launch_requested.clone();
+        let launch = Button::new(Text::new("Trust and Launch".to_string())).with_on_pressed(MaybeSignal::signal(
+            Box::new(EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = true;
+                }
+                Update::DRAW
+            })),
+        ));
+        let cancel = Button::new(Text::new("Cancel".to_string())).with_on_pressed(MaybeSignal::value(Update::DRAW));
+
+        self.inner = Container::new(vec![
+            Box::new(Text::new(format!(
+                "\"{}\" ({}) isn't trusted. Only start it if you know where it comes from.",
+                self.launcher.name, file_name
+            ))),
+            Box::new(Text::new(target).with_font_size(12.0)),
+            Box::new(Text::new(self.message_text.maybe()).with_font_size(12.0)),
+            Box::new(
+                Container::new(vec![Box::new(cancel), Box::new(launch)]).with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Row,
+                    gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                    justify_content: Some(JustifyContent::FlexEnd),
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    ..Default::default()
+                }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(10.0)),
+            ..Default::default()
+        });
+    }
+
+    fn trust_and_launch(&mut self) {
+        let result = launcher::trust(&self.path).and_then(|()| launcher::launch(&self.path, &self.launcher));
+        match result {
+            Ok(()) => self.message_text.set(format!("Started {}", self.launcher.name)),
+            Err(e) => {
+                log::error!("{}", e);
+                self.message_text.set(e);
+            },
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for LauncherTrustDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.message_text);
+            self.signals_hooked = true;
+        }
+
+        let requested = self
+            .launch_requested
+            .lock()
+            .map(|mut r| std::mem::take(&mut *r))
+            .unwrap_or(false);
+        if requested {
+            self.trust_and_launch();
+            update.insert(Update::DRAW);
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -2,8 +2,14 @@ use nptk::prelude::*;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
+use tokio::sync::mpsc;
+use crate::bus::AppBus;
+use crate::dbus::ExternalRequest;
 use crate::navigation::NavigationState;
+use crate::operations::undo::UndoStack;
 use crate::window::build_window;
+use crate::window_state::WindowState;
+use nptk::core::config::MayConfig;
 
 pub struct FilemanApp;
 
@@ -13,13 +19,25 @@ impl Application for FilemanApp {
     fn build(context: AppContext, state: Self::State) -> impl Widget {
         build_window(context, state)
     }
+
+    fn config(&self) -> MayConfig {
+        let mut config = MayConfig::default();
+        if let Some(saved) = WindowState::load() {
+            saved.apply(&mut config);
+        }
+        config
+    }
 }
 
 impl FilemanApp {
-    pub fn run(initial_path: PathBuf) {
-        let navigation = Arc::new(Mutex::new(NavigationState::new(initial_path)));
+    /// Run the application with one tab per path (the first one active)
+    pub fn run(tabs: Vec<PathBuf>, external_rx: mpsc::UnboundedReceiver<ExternalRequest>) {
+        let navigation = Arc::new(Mutex::new(NavigationState::with_tabs(tabs)));
         let state = AppState {
             navigation: navigation.clone(),
+            bus: AppBus::new(),
+            undo: Arc::new(Mutex::new(UndoStack::default())),
+            external_requests: Arc::new(Mutex::new(Some(external_rx))),
         };
         FilemanApp.run(state);
     }
@@ -27,4 +45,10 @@ impl FilemanApp {
 
 pub struct AppState {
     pub navigation: Arc<Mutex<NavigationState>>,
+    /// Commands and events exchanged between the parts of the window
+    pub bus: AppBus,
+    /// File operations of the window that can be undone and redone
+    pub undo: Arc<Mutex<UndoStack>>,
+    /// Requests from other applications, taken by the window on build
+    pub external_requests: Arc<Mutex<Option<mpsc::UnboundedReceiver<ExternalRequest>>>>,
 }
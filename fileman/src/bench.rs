@@ -0,0 +1,134 @@
+//! Benchmark mode (`fileman --bench DIR`)
+//!
+//! Lists a directory through the same filesystem model as the file list, detects
+//! the MIME types of its files and generates their thumbnails, all without opening
+//! a window, and prints how long each step took. The first run generates
+//! thumbnails; later runs read them from the thumbnail cache.
+
+use npio::backend::local::LocalBackend;
+use npio::service::filesystem::mime_detector::MimeDetector;
+use npio::{ThumbnailService, get_file_for_uri, register_backend};
+use nptk::services::filesystem::entry::FileEntry;
+use nptk::services::filesystem::model::{FileSystemEvent, FileSystemModel};
+use nptk::services::thumbnail::npio_adapter::{file_entry_to_uri, u32_to_thumbnail_size};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Longest a single listing may take before the benchmark gives up
+const LISTING_TIMEOUT: Duration = Duration::from_secs(120);
+/// Size of the generated thumbnails (the file list's default)
+const THUMBNAIL_SIZE: u32 = 128;
+
+/// Run the benchmark on `dir` `runs` times and return the process exit code.
+pub async fn run(dir: &Path, runs: usize) -> i32 {
+    let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    if !dir.is_dir() {
+        eprintln!("fileman: {} is not a folder", dir.display());
+        return 1;
+    }
+    let model = match FileSystemModel::new(dir.clone()) {
+        Ok(model) => model,
+        Err(e) => {
+            eprintln!("fileman: Failed to open {}: {}", dir.display(), e);
+            return 1;
+        },
+    };
+    register_backend(Arc::new(LocalBackend::new()));
+    let thumbnail_service = ThumbnailService::new();
+
+    let runs = runs.max(1);
+    println!("Benchmark of {} ({} runs)", dir.display(), runs);
+    let mut listing_times = Vec::new();
+    let mut entries = Vec::new();
+    for run in 1..=runs {
+        let started = Instant::now();
+        entries = match list(&model, &dir).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("fileman: {}", e);
+                return 1;
+            },
+        };
+        listing_times.push(started.elapsed());
+
+        let files: Vec<&FileEntry> = entries.iter().filter(|e| e.is_file()).collect();
+        let started = Instant::now();
+        for file in &files {
+            let _ = MimeDetector::detect_mime_type(&file.path).await;
+        }
+        let mime_time = started.elapsed();
+
+        let started = Instant::now();
+        let mut thumbnails = 0;
+        for file in &files {
+            let Ok(handle) = get_file_for_uri(&file_entry_to_uri(file)) else {
+                continue;
+            };
+            let generated = thumbnail_service
+                .get_or_generate_thumbnail(&*handle, u32_to_thumbnail_size(THUMBNAIL_SIZE), None)
+                .await;
+            if generated.is_ok() {
+                thumbnails += 1;
+            }
+        }
+        let thumbnail_time = started.elapsed();
+
+        println!(
+            "run {}: listed {} entries in {} ({}), MIME types of {} files in {} ({}), {} thumbnails in {} ({})",
+            run,
+            entries.len(),
+            format_time(listing_times[run - 1]),
+            format_rate(entries.len(), listing_times[run - 1]),
+            files.len(),
+            format_time(mime_time),
+            format_rate(files.len(), mime_time),
+            thumbnails,
+            format_time(thumbnail_time),
+            format_rate(thumbnails, thumbnail_time),
+        );
+    }
+
+    listing_times.sort();
+    let median = listing_times[listing_times.len() / 2];
+    println!(
+        "listing: min {}, median {}, max {} ({} at the median)",
+        format_time(listing_times[0]),
+        format_time(median),
+        format_time(listing_times[listing_times.len() - 1]),
+        format_rate(entries.len(), median),
+    );
+    0
+}
+
+/// List `dir` through the model and wait for the result.
+async fn list(model: &FileSystemModel, dir: &Path) -> Result<Vec<FileEntry>, String> {
+    let mut events = model.subscribe_events();
+    model
+        .refresh(dir)
+        .map_err(|e| format!("Failed to list {}: {}", dir.display(), e))?;
+    tokio::time::timeout(LISTING_TIMEOUT, async {
+        loop {
+            match events.recv().await {
+                Ok(FileSystemEvent::DirectoryLoaded { path, entries }) if path == dir => {
+                    return Ok(entries);
+                },
+                Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {},
+                Err(e) => return Err(format!("Failed to list {}: {}", dir.display(), e)),
+            }
+        }
+    })
+    .await
+    .map_err(|_| format!("Listing {} timed out", dir.display()))?
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{:.1} ms", time.as_secs_f64() * 1000.0)
+}
+
+fn format_rate(count: usize, time: Duration) -> String {
+    format!(
+        "{:.0}/s",
+        count as f64 / time.as_secs_f64().max(f64::EPSILON)
+    )
+}
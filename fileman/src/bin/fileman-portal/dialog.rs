@@ -0,0 +1,162 @@
+//! Chooser dialog process: shows one [FileChooser] and prints the chosen paths.
+
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk_fileman_widgets::file_chooser::{
+    FileChooser, FileChooserHandle, FileChooserMode, FileChooserResponse,
+};
+use nptk_fileman_widgets::xdg;
+use std::path::PathBuf;
+
+/// What to show; passed to the dialog process on its command line.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct DialogArgs {
+    /// Pick a location to save to instead of files to open
+    #[arg(long)]
+    pub save: bool,
+    #[arg(long)]
+    pub title: Option<String>,
+    #[arg(long)]
+    pub accept_label: Option<String>,
+    #[arg(long)]
+    pub multiple: bool,
+    /// Pick folders instead of files
+    #[arg(long)]
+    pub directory: bool,
+    #[arg(long)]
+    pub current_folder: Option<PathBuf>,
+    #[arg(long)]
+    pub current_name: Option<String>,
+}
+
+impl DialogArgs {
+    fn to_args(&self) -> Vec<std::ffi::OsString> {
+        let mut args: Vec<std::ffi::OsString> = vec!["--dialog".into()];
+        for (flag, set) in [("--save", self.save), ("--multiple", self.multiple), ("--directory", self.directory)] {
+            if set {
+                args.push(flag.into());
+            }
+        }
+        if let Some(title) = &self.title {
+            args.extend(["--title".into(), title.into()]);
+        }
+        if let Some(label) = &self.accept_label {
+            args.extend(["--accept-label".into(), label.into()]);
+        }
+        if let Some(folder) = &self.current_folder {
+            args.extend(["--current-folder".into(), folder.into()]);
+        }
+        if let Some(name) = &self.current_name {
+            args.extend(["--current-name".into(), name.into()]);
+        }
+        args
+    }
+}
+
+/// Show a dialog in a child process. `Ok(None)` means the user cancelled.
+pub async fn run_child(args: &DialogArgs) -> Result<Option<Vec<PathBuf>>, String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate fileman-portal: {}", e))?;
+    let child_args = args.to_args();
+    let output = tokio::task::spawn_blocking(move || {
+        std::process::Command::new(exe)
+            .args(child_args)
+            .stdin(std::process::Stdio::null())
+            .output()
+    })
+    .await
+    .map_err(|e| format!("File chooser task failed: {}", e))?
+    .map_err(|e| format!("Failed to run file chooser: {}", e))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let paths = output
+        .stdout
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| PathBuf::from(std::ffi::OsStr::from_bytes(line)))
+        .collect();
+    Ok(Some(paths))
+}
+
+struct ChooserApp;
+
+impl Application for ChooserApp {
+    type State = DialogArgs;
+
+    fn build(_context: AppContext, args: Self::State) -> impl Widget {
+        let mode = if args.save { FileChooserMode::Save } else { FileChooserMode::Open };
+        let start_dir = args
+            .current_folder
+            .clone()
+            .filter(|p| p.is_dir())
+            .unwrap_or_else(xdg::home);
+        let mut chooser = FileChooser::new(mode, start_dir)
+            .with_multiple(args.multiple)
+            .with_directories(args.directory);
+        if let Some(label) = args.accept_label {
+            chooser = chooser.with_accept_label(label);
+        }
+        if let Some(name) = args.current_name {
+            chooser = chooser.with_current_name(name);
+        }
+        ChooserWindow {
+            handle: chooser.handle(),
+            chooser,
+        }
+    }
+}
+
+/// Root widget that ends the process once the user has answered.
+struct ChooserWindow {
+    chooser: FileChooser,
+    handle: FileChooserHandle,
+}
+
+#[async_trait(?Send)]
+impl Widget for ChooserWindow {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.chooser.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let update = self.chooser.update(layout, context, info).await;
+        match self.handle.try_take() {
+            Some(FileChooserResponse::Accepted(paths)) => {
+                use std::io::Write;
+                use std::os::unix::ffi::OsStrExt;
+                let mut stdout = std::io::stdout().lock();
+                for path in paths {
+                    let _ = stdout.write_all(path.as_os_str().as_bytes());
+                    let _ = stdout.write_all(b"\n");
+                }
+                let _ = stdout.flush();
+                std::process::exit(0);
+            },
+            Some(FileChooserResponse::Cancelled) => std::process::exit(1),
+            None => update,
+        }
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.chooser.render(graphics, layout, info, context)
+    }
+}
+
+/// Run the dialog in this process until the user answers.
+pub fn run(args: DialogArgs) {
+    // The window title isn't configurable yet; log it for context
+    if let Some(title) = &args.title {
+        log::info!("File chooser: {}", title);
+    }
+    ChooserApp.run(args);
+}
@@ -0,0 +1,229 @@
+//! `org.freedesktop.impl.portal.FileChooser` backend
+//!
+//! Lets sandboxed applications pick files with fileman's file chooser through
+//! xdg-desktop-portal. Every request is shown by a child process
+//! (`fileman-portal --dialog ...`) so each dialog gets its own event loop.
+//!
+//! Built with `--features portal`. Install `data/fileman.portal` into
+//! `/usr/share/xdg-desktop-portal/portals/` and the D-Bus service file into
+//! `/usr/share/dbus-1/services/`, then select the backend in `portals.conf` with
+//! `org.freedesktop.impl.portal.FileChooser=fileman`.
+
+mod dialog;
+
+use clap::Parser;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+const BUS_NAME: &str = "org.freedesktop.impl.portal.desktop.fileman";
+const OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+
+/// Portal response codes
+const RESPONSE_SUCCESS: u32 = 0;
+const RESPONSE_CANCELLED: u32 = 1;
+const RESPONSE_OTHER: u32 = 2;
+
+#[derive(Debug, Parser)]
+#[command(name = "fileman-portal", about = "File chooser portal backend for fileman")]
+struct Cli {
+    /// Show a single dialog and print the chosen paths (used internally)
+    #[arg(long, hide = true)]
+    dialog: bool,
+    #[command(flatten)]
+    request: dialog::DialogArgs,
+}
+
+type Options = HashMap<String, OwnedValue>;
+type Results = HashMap<String, OwnedValue>;
+
+fn option_bool(options: &Options, key: &str) -> bool {
+    options
+        .get(key)
+        .and_then(|v| bool::try_from(&**v).ok())
+        .unwrap_or(false)
+}
+
+fn option_string(options: &Options, key: &str) -> Option<String> {
+    options
+        .get(key)
+        .and_then(|v| <&str>::try_from(&**v).ok())
+        .map(str::to_string)
+}
+
+/// Decode a NUL-terminated byte array option (`ay`) into a path.
+fn option_path(options: &Options, key: &str) -> Option<PathBuf> {
+    let bytes = options
+        .get(key)
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| Vec::<u8>::try_from(v).ok())?;
+    path_from_bytes(bytes)
+}
+
+fn path_from_bytes(mut bytes: Vec<u8>) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+    if bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+    (!bytes.is_empty()).then(|| PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+}
+
+/// Percent-encode a path into a `file://` URI, keeping `/` and unreserved characters.
+fn path_to_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                uri.push(byte as char)
+            },
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Turn a dialog outcome into the portal's (response, results) pair.
+fn respond(outcome: Result<Option<Vec<PathBuf>>, String>) -> (u32, Results) {
+    match outcome {
+        Ok(Some(paths)) => {
+            let uris: Vec<String> = paths.iter().map(|p| path_to_uri(p)).collect();
+            let mut results = Results::new();
+            if let Ok(value) = OwnedValue::try_from(Value::from(uris)) {
+                results.insert("uris".to_string(), value);
+            }
+            if let Ok(value) = OwnedValue::try_from(Value::from(true)) {
+                results.insert("writable".to_string(), value);
+            }
+            (RESPONSE_SUCCESS, results)
+        },
+        Ok(None) => (RESPONSE_CANCELLED, Results::new()),
+        Err(e) => {
+            log::error!("{}", e);
+            (RESPONSE_OTHER, Results::new())
+        },
+    }
+}
+
+struct FileChooserPortal;
+
+/// Filters, choices and modality are not supported by the chooser yet and are ignored.
+#[zbus::interface(name = "org.freedesktop.impl.portal.FileChooser")]
+impl FileChooserPortal {
+    async fn open_file(
+        &self,
+        _handle: ObjectPath<'_>,
+        app_id: String,
+        _parent_window: String,
+        title: String,
+        options: Options,
+    ) -> (u32, Results) {
+        log::info!("OpenFile requested by '{}'", app_id);
+        let args = dialog::DialogArgs {
+            save: false,
+            title: Some(title),
+            accept_label: option_string(&options, "accept_label"),
+            multiple: option_bool(&options, "multiple"),
+            directory: option_bool(&options, "directory"),
+            current_folder: option_path(&options, "current_folder"),
+            current_name: None,
+        };
+        respond(dialog::run_child(&args).await)
+    }
+
+    async fn save_file(
+        &self,
+        _handle: ObjectPath<'_>,
+        app_id: String,
+        _parent_window: String,
+        title: String,
+        options: Options,
+    ) -> (u32, Results) {
+        log::info!("SaveFile requested by '{}'", app_id);
+        let current_file = option_path(&options, "current_file");
+        let args = dialog::DialogArgs {
+            save: true,
+            title: Some(title),
+            accept_label: option_string(&options, "accept_label"),
+            multiple: false,
+            directory: false,
+            current_folder: option_path(&options, "current_folder")
+                .or_else(|| current_file.as_ref().and_then(|f| f.parent()).map(PathBuf::from)),
+            current_name: option_string(&options, "current_name").or_else(|| {
+                current_file
+                    .as_ref()
+                    .and_then(|f| f.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+            }),
+        };
+        respond(dialog::run_child(&args).await)
+    }
+
+    /// Pick a folder; the given file names are placed inside it.
+    async fn save_files(
+        &self,
+        _handle: ObjectPath<'_>,
+        app_id: String,
+        _parent_window: String,
+        title: String,
+        options: Options,
+    ) -> (u32, Results) {
+        log::info!("SaveFiles requested by '{}'", app_id);
+        let files: Vec<PathBuf> = options
+            .get("files")
+            .and_then(|v| v.try_clone().ok())
+            .and_then(|v| Vec::<Vec<u8>>::try_from(v).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(path_from_bytes)
+            .collect();
+        let args = dialog::DialogArgs {
+            save: false,
+            title: Some(title),
+            accept_label: option_string(&options, "accept_label"),
+            multiple: false,
+            directory: true,
+            current_folder: option_path(&options, "current_folder"),
+            current_name: None,
+        };
+        let outcome = dialog::run_child(&args).await.map(|chosen| {
+            chosen.and_then(|folders| folders.into_iter().next()).map(|folder| {
+                files
+                    .iter()
+                    .filter_map(|f| f.file_name())
+                    .map(|name| folder.join(name))
+                    .collect()
+            })
+        });
+        respond(outcome)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let cli = Cli::parse();
+
+    if cli.dialog {
+        dialog::run(cli.request);
+        return;
+    }
+
+    let connection = zbus::connection::Builder::session()
+        .and_then(|b| b.name(BUS_NAME))
+        .and_then(|b| b.serve_at(OBJECT_PATH, FileChooserPortal));
+    let connection = match connection {
+        Ok(builder) => builder.build().await,
+        Err(e) => Err(e),
+    };
+    match connection {
+        Ok(_connection) => {
+            log::info!("Serving {} on the session bus", BUS_NAME);
+            std::future::pending::<()>().await;
+        },
+        Err(e) => {
+            eprintln!("fileman-portal: failed to register {}: {}", BUS_NAME, e);
+            std::process::exit(1);
+        },
+    }
+}
@@ -0,0 +1,162 @@
+//! Application command bus
+//!
+//! The toolbar, the file list, keyboard shortcuts, the sidebar and background
+//! tasks talk to each other over one bus owned by [crate::app::AppState]
+//! instead of a channel per pair of them. Anyone holding the bus can send an
+//! [AppCommand] (something to do) or publish an [AppEvent] (something to show);
+//! every subscriber sees every message and acts on the ones it handles:
+//!
+//! - navigation commands are carried out by the toolbar, which owns the
+//!   navigation buttons
+//! - file operations are carried out by the file list
+//! - status messages and toasts are shown by the status bar
+//!
+//! Subscribers only see what is sent after they subscribed, so the window
+//! subscribes everything while it is built.
+
+use crate::toolbar::NavigationAction;
+use crate::window::FileOperationRequest;
+use nptk_fileman_widgets::status_bar::Toast;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tokio::sync::mpsc;
+
+/// Messages a subscriber can fall behind by before it misses some
+const CAPACITY: usize = 256;
+
+/// Something to do, sent by whoever the user asked
+#[derive(Debug, Clone)]
+pub enum AppCommand {
+    /// Navigate the current tab
+    Navigate(NavigationAction),
+    /// Carry out a file operation
+    Operation(FileOperationRequest),
+}
+
+/// Something to show the user
+#[derive(Clone)]
+pub enum AppEvent {
+    /// Temporary message in the status bar, e.g. the result of an operation
+    Status(String),
+    /// Longer lasting message, possibly with an action
+    Toast(Toast),
+}
+
+/// Handle to the bus. Clones share it.
+#[derive(Clone)]
+pub struct AppBus {
+    commands: broadcast::Sender<AppCommand>,
+    events: broadcast::Sender<AppEvent>,
+}
+
+impl AppBus {
+    pub fn new() -> Self {
+        Self {
+            commands: broadcast::channel(CAPACITY).0,
+            events: broadcast::channel(CAPACITY).0,
+        }
+    }
+
+    /// Send `command` to the subscribers.
+    pub fn send(&self, command: AppCommand) {
+        // Nobody subscribed (yet) is not an error
+        let _ = self.commands.send(command);
+    }
+
+    /// Ask for navigation.
+    pub fn navigate(&self, action: NavigationAction) {
+        self.send(AppCommand::Navigate(action));
+    }
+
+    /// Ask for a file operation.
+    pub fn operation(&self, request: FileOperationRequest) {
+        self.send(AppCommand::Operation(request));
+    }
+
+    /// Publish `event` to the subscribers.
+    pub fn publish(&self, event: AppEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Show `message` in the status bar.
+    pub fn status(&self, message: impl Into<String>) {
+        self.publish(AppEvent::Status(message.into()));
+    }
+
+    /// Show `toast` in the status bar.
+    pub fn toast(&self, toast: Toast) {
+        self.publish(AppEvent::Toast(toast));
+    }
+
+    /// Receive the commands sent from now on.
+    pub fn subscribe(&self) -> Subscription<AppCommand> {
+        Subscription {
+            rx: self.commands.subscribe(),
+        }
+    }
+
+    /// Receive the events published from now on.
+    pub fn subscribe_events(&self) -> Subscription<AppEvent> {
+        Subscription {
+            rx: self.events.subscribe(),
+        }
+    }
+
+    /// Status messages and toasts from now on, for the status bar (which
+    /// takes plain channels, as it doesn't know the bus).
+    pub fn status_receivers(&self) -> (mpsc::UnboundedReceiver<String>, mpsc::UnboundedReceiver<Toast>) {
+        let (message_tx, message_rx) = mpsc::unbounded_channel();
+        let (toast_tx, toast_rx) = mpsc::unbounded_channel();
+        let mut events = self.subscribe_events();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                let sent = match event {
+                    AppEvent::Status(message) => message_tx.send(message).is_ok(),
+                    AppEvent::Toast(toast) => toast_tx.send(toast).is_ok(),
+                };
+                if !sent {
+                    break;
+                }
+            }
+        });
+        (message_rx, toast_rx)
+    }
+}
+
+impl Default for AppBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Messages of the bus received by one subscriber.
+pub struct Subscription<T> {
+    rx: broadcast::Receiver<T>,
+}
+
+impl<T: Clone> Subscription<T> {
+    /// Next waiting message, without waiting.
+    pub fn try_recv(&mut self) -> Option<T> {
+        loop {
+            match self.rx.try_recv() {
+                Ok(message) => return Some(message),
+                Err(TryRecvError::Lagged(missed)) => {
+                    log::warn!("Fell behind on the command bus, missed {} message(s)", missed);
+                },
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Next message, waiting for it; `None` once the bus is gone.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            match self.rx.recv().await {
+                Ok(message) => return Some(message),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    log::warn!("Fell behind on the command bus, missed {} message(s)", missed);
+                },
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
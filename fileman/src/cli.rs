@@ -0,0 +1,211 @@
+use crate::operations::throttle;
+use clap::{Parser, Subcommand, ValueEnum};
+use nptk_fileman_widgets::conflict_dialog::OverwritePolicy;
+use std::path::PathBuf;
+
+/// Command line arguments
+#[derive(Debug, Parser)]
+#[command(name = "fileman", version, about = "A file manager")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Run a file operation in the terminal instead of opening a window
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Locations to open, each in its own tab. Files open their folder with the file selected.
+    pub paths: Vec<PathBuf>,
+
+    /// Open the folder containing FILE and select it (can be repeated)
+    #[arg(long, value_name = "FILE")]
+    pub select: Vec<PathBuf>,
+
+    /// Open a separate window in a new process instead of using the running instance
+    #[arg(long, alias = "new-instance", conflicts_with = "tab")]
+    pub new_window: bool,
+
+    /// Open the locations as tabs of the running instance (the default)
+    #[arg(long)]
+    pub tab: bool,
+
+    /// Show performance counters (frame time, listing speed, thumbnails, queue depth) over the
+    /// file list of a new window
+    #[arg(long)]
+    pub perf: bool,
+
+    /// List DIR without opening a window and print timings
+    #[arg(long, value_name = "DIR")]
+    pub bench: Option<PathBuf>,
+
+    /// Number of benchmark runs
+    #[arg(long, value_name = "N", default_value_t = 5, requires = "bench")]
+    pub bench_runs: usize,
+}
+
+impl Cli {
+    /// Split the arguments into folders to open and files to select, as absolute paths.
+    ///
+    /// Paths are made absolute because a running instance may have a different
+    /// working directory.
+    pub fn resolve(&self) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let absolute = |path: &PathBuf| std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+
+        let mut folders = Vec::new();
+        let mut select: Vec<PathBuf> = self.select.iter().map(absolute).collect();
+        for path in self.paths.iter().map(absolute) {
+            if path.is_file() {
+                select.push(path);
+            } else {
+                folders.push(path);
+            }
+        }
+        (folders, select)
+    }
+}
+
+/// How conflicts are resolved by the headless subcommands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictArg {
+    /// Ask for every conflict (the default on a terminal)
+    Ask,
+    Overwrite,
+    Skip,
+    KeepBoth,
+    /// Overwrite only if the source is newer
+    Newer,
+}
+
+impl ConflictArg {
+    /// The fixed policy, or `None` when the user should be asked.
+    pub fn policy(self) -> Option<OverwritePolicy> {
+        match self {
+            ConflictArg::Ask => None,
+            ConflictArg::Overwrite => Some(OverwritePolicy::Overwrite),
+            ConflictArg::Skip => Some(OverwritePolicy::Skip),
+            ConflictArg::KeepBoth => Some(OverwritePolicy::KeepBoth),
+            ConflictArg::Newer => Some(OverwritePolicy::OverwriteIfNewer),
+        }
+    }
+}
+
+/// Headless file operations
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Copy files and folders into a directory
+    Cp {
+        #[arg(required = true, num_args = 1..)]
+        sources: Vec<PathBuf>,
+        destination: PathBuf,
+        /// What to do when a destination exists
+        #[arg(long, value_enum, default_value = "ask")]
+        on_conflict: ConflictArg,
+        /// Maximum transfer speed in bytes per second, e.g. 500K or 2M
+        #[arg(long, value_name = "RATE", value_parser = throttle::parse_rate, default_value = "unlimited")]
+        limit: u64,
+        /// Copy what symbolic links point to instead of the links
+        #[arg(short = 'L', long)]
+        dereference: bool,
+    },
+    /// Move files and folders into a directory
+    Mv {
+        #[arg(required = true, num_args = 1..)]
+        sources: Vec<PathBuf>,
+        destination: PathBuf,
+        /// What to do when a destination exists
+        #[arg(long, value_enum, default_value = "ask")]
+        on_conflict: ConflictArg,
+        /// Maximum transfer speed in bytes per second, e.g. 500K or 2M
+        #[arg(long, value_name = "RATE", value_parser = throttle::parse_rate, default_value = "unlimited")]
+        limit: u64,
+    },
+    /// Delete files and folders permanently
+    Rm {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Move files and folders to the trash
+    Trash {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Make a destination folder match a source folder
+    Sync {
+        source: PathBuf,
+        destination: PathBuf,
+        /// Also delete entries that only exist in the destination
+        #[arg(long)]
+        delete: bool,
+        /// Only list what would be done
+        #[arg(long)]
+        dry_run: bool,
+        /// Maximum transfer speed in bytes per second, e.g. 500K or 2M
+        #[arg(long, value_name = "RATE", value_parser = throttle::parse_rate, default_value = "unlimited")]
+        limit: u64,
+    },
+    /// Undo the last cp, mv or trash run from the command line
+    Undo,
+    /// Run queued operations in the background (started on demand by the GUI)
+    #[command(hide = true)]
+    Daemon,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("fileman").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn arguments_are_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_headless_commands() {
+        let cli = parse(&["cp", "-L", "--on-conflict", "keep-both", "--limit", "2M", "a", "b", "dest"]).unwrap();
+        match cli.command {
+            Some(Command::Cp { sources, destination, on_conflict, limit, dereference }) => {
+                assert_eq!(sources, [PathBuf::from("a"), PathBuf::from("b")]);
+                assert_eq!(destination, PathBuf::from("dest"));
+                assert_eq!(on_conflict.policy(), Some(OverwritePolicy::KeepBoth));
+                assert_eq!(limit, 2 * 1024 * 1024);
+                assert!(dereference);
+            },
+            other => panic!("{:?}", other),
+        }
+
+        match parse(&["mv", "a", "dest"]).unwrap().command {
+            Some(Command::Mv { on_conflict, limit, .. }) => {
+                assert_eq!(on_conflict.policy(), None);
+                assert_eq!(limit, throttle::UNLIMITED);
+            },
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(
+            parse(&["sync", "--delete", "--dry-run", "src", "dest"]).unwrap().command,
+            Some(Command::Sync { delete: true, dry_run: true, .. })
+        ));
+        assert!(matches!(parse(&["undo"]).unwrap().command, Some(Command::Undo)));
+    }
+
+    #[test]
+    fn rejects_incomplete_commands() {
+        assert!(parse(&["cp", "dest"]).is_err());
+        assert!(parse(&["rm"]).is_err());
+        assert!(parse(&["cp", "--on-conflict", "maybe", "a", "dest"]).is_err());
+        assert!(parse(&["cp", "--limit", "fast", "a", "dest"]).is_err());
+        assert!(parse(&["--new-window", "--tab"]).is_err());
+        assert!(parse(&["--bench-runs", "3"]).is_err());
+    }
+
+    #[test]
+    fn paths_open_a_window() {
+        let cli = parse(&["/tmp", "--select", "/tmp/a", "--new-window"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.paths, [PathBuf::from("/tmp")]);
+        assert_eq!(cli.select, [PathBuf::from("/tmp/a")]);
+        assert!(cli.new_window);
+    }
+}
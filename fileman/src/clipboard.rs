@@ -0,0 +1,118 @@
+//! System clipboard for files
+//!
+//! Copied and cut files are offered as `text/uri-list`, GNOME's
+//! `x-special/gnome-copied-files` (which also records copy vs. cut), KDE's cut marker
+//! and plain text, so other file managers and applications can paste them. Pasting
+//! accepts the same formats from other applications. Wayland is used through the
+//! data-control protocol when available, falling back to X11 (including XWayland).
+
+mod wayland;
+mod x11;
+
+use crate::uri::encode_path;
+use nptk::services::thumbnail::npio_adapter::uri_to_path;
+use std::path::{Path, PathBuf};
+
+const URI_LIST: &str = "text/uri-list";
+const GNOME_COPIED_FILES: &str = "x-special/gnome-copied-files";
+const KDE_CUT_SELECTION: &str = "application/x-kde-cutselection";
+const PLAIN_TEXT: &[&str] = &["text/plain;charset=utf-8", "UTF8_STRING", "text/plain"];
+
+/// Formats looked for when pasting, most specific first
+const PASTE_PREFERENCE: &[&str] = &[GNOME_COPIED_FILES, URI_LIST];
+
+/// Whether pasted files should be copied or moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardAction {
+    Copy,
+    Cut,
+}
+
+/// Files found on the clipboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardFiles {
+    pub action: ClipboardAction,
+    pub paths: Vec<PathBuf>,
+}
+
+fn path_to_uri(path: &Path) -> String {
+    format!("file://{}", encode_path(path))
+}
+
+/// Put files on the clipboard, taking ownership of it.
+pub fn set_files(paths: &[PathBuf], action: ClipboardAction) -> Result<(), String> {
+    let uris: Vec<String> = paths.iter().map(|p| path_to_uri(p)).collect();
+    let text = paths
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let verb = match action {
+        ClipboardAction::Copy => "copy",
+        ClipboardAction::Cut => "cut",
+    };
+
+    let mut offers = vec![
+        (URI_LIST.to_string(), format!("{}\r\n", uris.join("\r\n")).into_bytes()),
+        (GNOME_COPIED_FILES.to_string(), format!("{}\n{}", verb, uris.join("\n")).into_bytes()),
+    ];
+    if action == ClipboardAction::Cut {
+        offers.push((KDE_CUT_SELECTION.to_string(), b"1".to_vec()));
+    }
+    offers.extend(PLAIN_TEXT.iter().map(|mime| (mime.to_string(), text.clone().into_bytes())));
+    set(offers)
+}
+
+/// Put plain `text` on the clipboard, such as the paths of files, taking
+/// ownership of it.
+pub fn set_text(text: &str) -> Result<(), String> {
+    set(PLAIN_TEXT
+        .iter()
+        .map(|mime| (mime.to_string(), text.as_bytes().to_vec()))
+        .collect())
+}
+
+fn set(offers: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    if wayland::available() {
+        match wayland::set(offers.clone()) {
+            Ok(()) => return Ok(()),
+            Err(e) => log::warn!("{}; trying X11", e),
+        }
+    }
+    x11::set(offers)
+}
+
+/// Read files from the clipboard. `Ok(None)` if it holds no files.
+pub fn get_files() -> Result<Option<ClipboardFiles>, String> {
+    let contents = if wayland::available() {
+        match wayland::get(PASTE_PREFERENCE) {
+            Ok(contents) => contents,
+            Err(e) => {
+                log::warn!("{}; trying X11", e);
+                x11::get(PASTE_PREFERENCE)?
+            },
+        }
+    } else {
+        x11::get(PASTE_PREFERENCE)?
+    };
+    let Some((mime, data)) = contents else {
+        return Ok(None);
+    };
+
+    let text = String::from_utf8_lossy(&data);
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+    let action = if mime == GNOME_COPIED_FILES {
+        match lines.next() {
+            Some("cut") => ClipboardAction::Cut,
+            _ => ClipboardAction::Copy,
+        }
+    } else {
+        ClipboardAction::Copy
+    };
+    // `text/uri-list` may contain comment lines
+    let paths: Vec<PathBuf> = lines
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(uri_to_path)
+        .collect();
+    Ok((!paths.is_empty()).then_some(ClipboardFiles { action, paths }))
+}
@@ -0,0 +1,48 @@
+//! Wayland clipboard through the data-control protocol (wl-clipboard-rs).
+
+use std::io::Read;
+use wl_clipboard_rs::copy::{self, MimeSource, Source};
+use wl_clipboard_rs::paste::{self, ClipboardType, Seat};
+
+pub(super) fn available() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Take ownership of the clipboard, offering each (MIME type, data) pair.
+///
+/// The selection is served from a background thread until another client replaces it.
+pub(super) fn set(offers: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    let sources = offers
+        .into_iter()
+        .map(|(mime, data)| MimeSource {
+            source: Source::Bytes(data.into_boxed_slice()),
+            mime_type: copy::MimeType::Specific(mime),
+        })
+        .collect();
+    copy::Options::new()
+        .copy_multi(sources)
+        .map_err(|e| format!("Failed to set Wayland clipboard: {}", e))
+}
+
+/// Read the first offered MIME type from `preference`.
+pub(super) fn get(preference: &[&str]) -> Result<Option<(String, Vec<u8>)>, String> {
+    let offered = match paste::get_mime_types(ClipboardType::Regular, Seat::Unspecified) {
+        Ok(offered) => offered,
+        Err(paste::Error::ClipboardEmpty | paste::Error::NoSeats) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read Wayland clipboard: {}", e)),
+    };
+    let Some(mime) = preference.iter().find(|m| offered.contains(**m)) else {
+        return Ok(None);
+    };
+
+    let (mut pipe, _) = paste::get_contents(
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        paste::MimeType::Specific(mime),
+    )
+    .map_err(|e| format!("Failed to read Wayland clipboard: {}", e))?;
+    let mut data = Vec::new();
+    pipe.read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read Wayland clipboard: {}", e))?;
+    Ok(Some((mime.to_string(), data)))
+}
@@ -0,0 +1,364 @@
+//! X11 CLIPBOARD selection (x11rb).
+//!
+//! Ownership is kept by a background thread with its own connection and window,
+//! answering `TARGETS` and conversion requests until another client takes over.
+//! Data too large for one request is sent and received in chunks (INCR).
+//! Clients are expected to name the time of the event that triggered them;
+//! there is none here, so a timestamp is taken from the server through a
+//! PropertyNotify instead.
+
+use std::time::{Duration, Instant};
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux, EventMask, PropMode,
+    Property, SELECTION_NOTIFY_EVENT, SelectionNotifyEvent, SelectionRequestEvent, Timestamp, Window,
+    WindowClass,
+};
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+/// How long to wait for the selection owner (or the server) to answer
+const CONVERT_TIMEOUT: Duration = Duration::from_secs(1);
+/// Property on our window that receives converted data
+const TRANSFER_PROPERTY: &str = "FILEMAN_CLIPBOARD";
+/// Property on our window appended to for a server timestamp
+const TIMESTAMP_PROPERTY: &str = "FILEMAN_TIMESTAMP";
+/// Largest chunk of data sent in one request; larger data is sent with INCR
+const CHUNK_SIZE: usize = 256 * 1024;
+
+fn connect() -> Result<(RustConnection, Window), String> {
+    let (conn, screen_num) =
+        RustConnection::connect(None).map_err(|e| format!("Failed to connect to X11: {}", e))?;
+    let root = conn.setup().roots[screen_num].root;
+    let window = conn
+        .generate_id()
+        .map_err(|e| format!("Failed to create X11 window: {}", e))?;
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        COPY_FROM_PARENT,
+        // For server timestamps and chunks of received data
+        &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )
+    .map_err(|e| format!("Failed to create X11 window: {}", e))?;
+    Ok((conn, window))
+}
+
+fn intern(conn: &RustConnection, name: &str) -> Result<Atom, String> {
+    conn.intern_atom(false, name.as_bytes())
+        .map_err(|e| format!("Failed to intern atom {}: {}", name, e))?
+        .reply()
+        .map(|r| r.atom)
+        .map_err(|e| format!("Failed to intern atom {}: {}", name, e))
+}
+
+/// Wait up to [CONVERT_TIMEOUT] for an event `pick` takes something from.
+/// `None` if none came in time.
+fn wait_for<T>(conn: &RustConnection, mut pick: impl FnMut(Event) -> Option<T>) -> Result<Option<T>, String> {
+    let deadline = Instant::now() + CONVERT_TIMEOUT;
+    loop {
+        let event = conn
+            .poll_for_event()
+            .map_err(|e| format!("X11 connection failed: {}", e))?;
+        match event {
+            Some(event) => {
+                if let Some(picked) = pick(event) {
+                    return Ok(Some(picked));
+                }
+            },
+            None if Instant::now() >= deadline => return Ok(None),
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+/// The current server time: appending nothing to a property of `window`
+/// still makes the server report the change, with its time.
+fn server_time(conn: &RustConnection, window: Window) -> Result<Timestamp, String> {
+    let property = intern(conn, TIMESTAMP_PROPERTY)?;
+    conn.change_property8(PropMode::APPEND, window, property, AtomEnum::STRING, &[])
+        .map_err(|e| format!("Failed to get the X11 server time: {}", e))?;
+    conn.flush()
+        .map_err(|e| format!("Failed to get the X11 server time: {}", e))?;
+    wait_for(conn, |event| match event {
+        Event::PropertyNotify(notify) if notify.window == window && notify.atom == property => Some(notify.time),
+        _ => None,
+    })?
+    .ok_or_else(|| "Timed out getting the X11 server time".to_string())
+}
+
+/// Take ownership of CLIPBOARD, offering each (MIME type, data) pair as a target.
+pub(super) fn set(offers: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    let (conn, window) = connect()?;
+    let atoms = OwnerAtoms {
+        targets: intern(&conn, "TARGETS")?,
+        timestamp: intern(&conn, "TIMESTAMP")?,
+        incr: intern(&conn, "INCR")?,
+    };
+    let clipboard = intern(&conn, "CLIPBOARD")?;
+    let offers = offers
+        .into_iter()
+        .map(|(mime, data)| intern(&conn, &mime).map(|atom| (atom, data)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let time = server_time(&conn, window)?;
+    conn.set_selection_owner(window, clipboard, time)
+        .map_err(|e| format!("Failed to own the X11 clipboard: {}", e))?;
+    let owner = conn
+        .get_selection_owner(clipboard)
+        .map_err(|e| format!("Failed to own the X11 clipboard: {}", e))?
+        .reply()
+        .map_err(|e| format!("Failed to own the X11 clipboard: {}", e))?
+        .owner;
+    if owner != window {
+        return Err("Failed to own the X11 clipboard".to_string());
+    }
+
+    std::thread::spawn(move || Owner { conn, atoms, offers, time, transfers: Vec::new() }.serve());
+    Ok(())
+}
+
+/// Atoms the owner answers requests with
+struct OwnerAtoms {
+    targets: Atom,
+    timestamp: Atom,
+    incr: Atom,
+}
+
+/// An offer sent to a requestor in chunks. Each chunk is written once the
+/// requestor deleted the one before; an empty chunk ends the transfer.
+struct Transfer {
+    requestor: Window,
+    property: Atom,
+    target: Atom,
+    offer: usize,
+    sent: usize,
+}
+
+/// The clipboard while we own it
+struct Owner {
+    conn: RustConnection,
+    atoms: OwnerAtoms,
+    offers: Vec<(Atom, Vec<u8>)>,
+    /// When ownership was taken; older requests are refused
+    time: Timestamp,
+    transfers: Vec<Transfer>,
+}
+
+impl Owner {
+    /// Answer requests until another client owns the clipboard and the
+    /// transfers started before are done.
+    fn serve(mut self) {
+        let mut owned = true;
+        while owned || !self.transfers.is_empty() {
+            let event = match self.conn.wait_for_event() {
+                Ok(event) => event,
+                Err(e) => {
+                    log::warn!("X11 clipboard connection lost: {}", e);
+                    return;
+                },
+            };
+            match event {
+                Event::SelectionRequest(request) => {
+                    let property = if owned { self.answer(&request) } else { None };
+                    let notify = SelectionNotifyEvent {
+                        response_type: SELECTION_NOTIFY_EVENT,
+                        sequence: 0,
+                        time: request.time,
+                        requestor: request.requestor,
+                        selection: request.selection,
+                        target: request.target,
+                        property: property.unwrap_or(NONE),
+                    };
+                    let _ = self.conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify);
+                },
+                Event::PropertyNotify(notify) if notify.state == Property::DELETE => {
+                    self.send_chunk(notify.window, notify.atom);
+                },
+                // The requestor is gone, along with the property to write to
+                Event::DestroyNotify(destroyed) => {
+                    self.transfers.retain(|transfer| transfer.requestor != destroyed.window);
+                },
+                // Another client owns the clipboard now
+                Event::SelectionClear(_) => owned = false,
+                _ => {},
+            }
+            let _ = self.conn.flush();
+        }
+    }
+
+    /// Store what `request` asks for on its property. Returns the property,
+    /// `None` if the request can't be answered.
+    fn answer(&mut self, request: &SelectionRequestEvent) -> Option<Atom> {
+        if request.time != CURRENT_TIME && request.time < self.time {
+            return None;
+        }
+        // Obsolete clients may leave the property unset
+        let property = if request.property == NONE { request.target } else { request.property };
+        let (requestor, target) = (request.requestor, request.target);
+        let stored = if target == self.atoms.targets {
+            let mut atoms: Vec<Atom> = self.offers.iter().map(|(atom, _)| *atom).collect();
+            atoms.extend([self.atoms.targets, self.atoms.timestamp]);
+            self.conn
+                .change_property32(PropMode::REPLACE, requestor, property, AtomEnum::ATOM, &atoms)
+                .is_ok()
+        } else if target == self.atoms.timestamp {
+            self.conn
+                .change_property32(PropMode::REPLACE, requestor, property, AtomEnum::INTEGER, &[self.time])
+                .is_ok()
+        } else if let Some(offer) = self.offers.iter().position(|(atom, _)| *atom == target) {
+            let data = &self.offers[offer].1;
+            if data.len() <= self.chunk_size() {
+                self.conn
+                    .change_property8(PropMode::REPLACE, requestor, property, target, data)
+                    .is_ok()
+            } else {
+                // The requestor deletes the INCR property to ask for the first chunk
+                let events = ChangeWindowAttributesAux::new()
+                    .event_mask(EventMask::PROPERTY_CHANGE | EventMask::STRUCTURE_NOTIFY);
+                let started = self.conn.change_window_attributes(requestor, &events).is_ok()
+                    && self
+                        .conn
+                        .change_property32(PropMode::REPLACE, requestor, property, self.atoms.incr, &[data.len() as u32])
+                        .is_ok();
+                if started {
+                    self.transfers.retain(|t| (t.requestor, t.property) != (requestor, property));
+                    self.transfers.push(Transfer { requestor, property, target, offer, sent: 0 });
+                }
+                started
+            }
+        } else {
+            false
+        };
+        stored.then_some(property)
+    }
+
+    /// Write the next chunk of the transfer to `property` of `requestor`, if
+    /// there is one, after the requestor deleted the last one.
+    fn send_chunk(&mut self, requestor: Window, property: Atom) {
+        let chunk_size = self.chunk_size();
+        let Some(index) = self
+            .transfers
+            .iter()
+            .position(|t| t.requestor == requestor && t.property == property)
+        else {
+            return;
+        };
+        let transfer = &mut self.transfers[index];
+        let data = &self.offers[transfer.offer].1;
+        let end = (transfer.sent + chunk_size).min(data.len());
+        let chunk = &data[transfer.sent..end];
+        let written = self
+            .conn
+            .change_property8(PropMode::REPLACE, requestor, property, transfer.target, chunk)
+            .is_ok();
+        transfer.sent = end;
+        if chunk.is_empty() || !written {
+            self.transfers.remove(index);
+        }
+    }
+
+    /// Largest data sent in one request
+    fn chunk_size(&self) -> usize {
+        // Leave room for the request's header
+        CHUNK_SIZE.min(self.conn.maximum_request_bytes().saturating_sub(64))
+    }
+}
+
+/// Ask the owner to convert CLIPBOARD to `target` at `time` and read the
+/// result, chunk by chunk if the owner sends it with INCR.
+fn convert(
+    conn: &RustConnection,
+    window: Window,
+    selection: Atom,
+    target: Atom,
+    property: Atom,
+    incr: Atom,
+    time: Timestamp,
+) -> Result<Option<Vec<u8>>, String> {
+    let read_error = |e: &dyn std::fmt::Display| format!("Failed to read the X11 clipboard: {}", e);
+    conn.convert_selection(window, selection, target, property, time)
+        .map_err(|e| read_error(&e))?;
+    conn.flush().map_err(|e| read_error(&e))?;
+
+    let notified = wait_for(conn, |event| match event {
+        Event::SelectionNotify(notify) if notify.requestor == window => Some(notify.property),
+        _ => None,
+    })?;
+    match notified {
+        None => return Err("Timed out reading the X11 clipboard".to_string()),
+        Some(NONE) => return Ok(None),
+        Some(_) => {},
+    }
+    // Deleting the property asks an INCR owner for the first chunk
+    let reply = conn
+        .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX)
+        .map_err(|e| read_error(&e))?
+        .reply()
+        .map_err(|e| read_error(&e))?;
+    if reply.type_ != incr {
+        return Ok(Some(reply.value));
+    }
+
+    let mut data = Vec::new();
+    loop {
+        let chunk_ready = wait_for(conn, |event| match event {
+            Event::PropertyNotify(notify)
+                if notify.window == window && notify.atom == property && notify.state == Property::NEW_VALUE =>
+            {
+                Some(())
+            },
+            _ => None,
+        })?;
+        if chunk_ready.is_none() {
+            return Err("Timed out reading the X11 clipboard".to_string());
+        }
+        let chunk = conn
+            .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX)
+            .map_err(|e| read_error(&e))?
+            .reply()
+            .map_err(|e| read_error(&e))?;
+        if chunk.value.is_empty() {
+            return Ok(Some(data));
+        }
+        data.extend_from_slice(&chunk.value);
+    }
+}
+
+/// Read the first offered MIME type from `preference`.
+pub(super) fn get(preference: &[&str]) -> Result<Option<(String, Vec<u8>)>, String> {
+    let (conn, window) = connect()?;
+    let clipboard = intern(&conn, "CLIPBOARD")?;
+    let targets = intern(&conn, "TARGETS")?;
+    let incr = intern(&conn, "INCR")?;
+    let property = intern(&conn, TRANSFER_PROPERTY)?;
+    let time = server_time(&conn, window)?;
+
+    let Some(list) = convert(&conn, window, clipboard, targets, property, incr, time)? else {
+        return Ok(None);
+    };
+    let offered: Vec<Atom> = list
+        .chunks_exact(4)
+        .map(|atom| Atom::from_ne_bytes([atom[0], atom[1], atom[2], atom[3]]))
+        .collect();
+
+    for mime in preference {
+        let atom = intern(&conn, mime)?;
+        if !offered.contains(&atom) {
+            continue;
+        }
+        if let Some(data) = convert(&conn, window, clipboard, atom, property, incr, time)? {
+            return Ok(Some((mime.to_string(), data)));
+        }
+    }
+    Ok(None)
+}
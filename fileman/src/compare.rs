@@ -0,0 +1,156 @@
+//! Folder comparison
+//!
+//! Compares the entries of two folders by name. Files differ when their size
+//! differs, or when their modification times differ and so does their content;
+//! folders are compared recursively.
+
+mod dialog;
+
+pub use dialog::CompareDialog;
+
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Why two entries with the same name differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffReason {
+    /// One is a folder, the other isn't
+    Kind,
+    Size,
+    Content,
+    /// Same content, different modification time
+    Modified,
+    /// Folders whose contents differ
+    FolderContents,
+}
+
+/// How an entry compares between the two sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difference {
+    OnlyLeft,
+    OnlyRight,
+    Differs(DiffReason),
+}
+
+impl Difference {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Difference::OnlyLeft => "only on the left",
+            Difference::OnlyRight => "only on the right",
+            Difference::Differs(DiffReason::Kind) => "folder on one side, file on the other",
+            Difference::Differs(DiffReason::Size) => "size differs",
+            Difference::Differs(DiffReason::Content) => "content differs",
+            Difference::Differs(DiffReason::Modified) => "modification time differs",
+            Difference::Differs(DiffReason::FolderContents) => "folder contents differ",
+        }
+    }
+}
+
+/// An entry that isn't the same on both sides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComparedEntry {
+    pub name: String,
+    pub difference: Difference,
+}
+
+/// Result of comparing two folders.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub left: PathBuf,
+    pub right: PathBuf,
+    pub entries: Vec<ComparedEntry>,
+}
+
+fn entry_names(dir: &Path) -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+fn same_content(left: &Path, right: &Path) -> Result<bool, String> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+    };
+    let (mut left_reader, mut right_reader) = (open(left)?, open(right)?);
+    let mut left_buf = vec![0u8; 64 * 1024];
+    let mut right_buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = left_reader
+            .read(&mut left_buf)
+            .map_err(|e| format!("Failed to read {}: {}", left.display(), e))?;
+        if read == 0 {
+            return Ok(true);
+        }
+        right_reader
+            .read_exact(&mut right_buf[..read])
+            .map_err(|e| format!("Failed to read {}: {}", right.display(), e))?;
+        if left_buf[..read] != right_buf[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Compare two entries that exist on both sides. `None` if they are the same.
+fn compare_entry(left: &Path, right: &Path) -> Result<Option<DiffReason>, String> {
+    let metadata = |path: &Path| {
+        fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    };
+    let (left_meta, right_meta) = (metadata(left)?, metadata(right)?);
+
+    match (left_meta.is_dir(), right_meta.is_dir()) {
+        (true, true) => {
+            let same = compare_folders(left, right)?.entries.is_empty();
+            return Ok((!same).then_some(DiffReason::FolderContents));
+        },
+        (false, false) => {},
+        _ => return Ok(Some(DiffReason::Kind)),
+    }
+
+    if left_meta.len() != right_meta.len() {
+        return Ok(Some(DiffReason::Size));
+    }
+    let modified = |meta: &fs::Metadata| meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    if modified(&left_meta) == modified(&right_meta) {
+        return Ok(None);
+    }
+    if same_content(left, right)? {
+        Ok(Some(DiffReason::Modified))
+    } else {
+        Ok(Some(DiffReason::Content))
+    }
+}
+
+/// Compare the entries of `left` and `right`. Blocking; run it on a worker.
+pub fn compare_folders(left: &Path, right: &Path) -> Result<Comparison, String> {
+    let left_names = entry_names(left)?;
+    let right_names = entry_names(right)?;
+
+    let mut entries = Vec::new();
+    for name in &left_names {
+        if right_names.binary_search(name).is_err() {
+            entries.push(ComparedEntry { name: name.clone(), difference: Difference::OnlyLeft });
+        } else if let Some(reason) = compare_entry(&left.join(name), &right.join(name))? {
+            entries.push(ComparedEntry { name: name.clone(), difference: Difference::Differs(reason) });
+        }
+    }
+    for name in &right_names {
+        if left_names.binary_search(name).is_err() {
+            entries.push(ComparedEntry { name: name.clone(), difference: Difference::OnlyRight });
+        }
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(Comparison {
+        left: left.to_path_buf(),
+        right: right.to_path_buf(),
+        entries,
+    })
+}
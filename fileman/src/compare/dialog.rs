@@ -0,0 +1,181 @@
+//! Popup listing the differences between two folders.
+
+use super::{Comparison, Difference};
+use crate::daemon;
+use crate::operations::engine::{Operation, OperationKind};
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::scroll_container::{ScrollContainer, ScrollDirection};
+use nptk_fileman_widgets::conflict_dialog::OverwritePolicy;
+use std::sync::{Arc, Mutex};
+
+/// Which way to copy an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    LeftToRight,
+    RightToLeft,
+}
+
+/// Lists the entries that differ, each with buttons copying it to the other side.
+pub struct CompareDialog {
+    comparison: Comparison,
+    inner: Container,
+    requested: Arc<Mutex<Option<(usize, Direction)>>>,
+}
+
+impl CompareDialog {
+    pub fn new(comparison: Comparison) -> Self {
+        let requested = Arc::new(Mutex::new(None));
+        let inner = Self::build(&comparison, &requested);
+        Self {
+            comparison,
+            inner,
+            requested,
+        }
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        let title = format!(
+            "Compare {} and {}",
+            self.comparison.left.display(),
+            self.comparison.right.display()
+        );
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), &title, (640, 420), (200, 150));
+    }
+
+    fn copy_button(
+        label: &str,
+        index: usize,
+        direction: Direction,
+        requested: &Arc<Mutex<Option<(usize, Direction)>>>,
+    ) -> Button {
+        let requested = requested.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut requested) = requested.lock() {
+                    *requested = Some((index, direction));
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn build(comparison: &Comparison, requested: &Arc<Mutex<Option<(usize, Direction)>>>) -> Container {
+        let mut rows: Vec<Box<dyn Widget>> = Vec::new();
+        for (index, entry) in comparison.entries.iter().enumerate() {
+            let mut row: Vec<Box<dyn Widget>> = vec![Box::new(Text::new(format!(
+                "{} — {}",
+                entry.name,
+                entry.difference.describe()
+            )))];
+            if entry.difference != Difference::OnlyRight {
+                row.push(Box::new(Self::copy_button("Copy →", index, Direction::LeftToRight, requested)));
+            }
+            if entry.difference != Difference::OnlyLeft {
+                row.push(Box::new(Self::copy_button("← Copy", index, Direction::RightToLeft, requested)));
+            }
+            rows.push(Box::new(Container::new(row).with_layout_style(LayoutStyle {
+                flex_direction: FlexDirection::Row,
+                gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                ..Default::default()
+            })));
+        }
+        if rows.is_empty() {
+            rows.push(Box::new(Text::new("The folders are identical".to_string())));
+        }
+
+        let list = Container::new(rows).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(6.0)),
+            ..Default::default()
+        });
+
+        Container::new(vec![
+            Box::new(Text::new(format!("Left: {}", comparison.left.display()))),
+            Box::new(Text::new(format!("Right: {}", comparison.right.display()))),
+            Box::new(
+                ScrollContainer::new()
+                    .with_scroll_direction(ScrollDirection::Vertical)
+                    .with_child(list)
+                    .with_layout_style(LayoutStyle {
+                        size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                        flex_grow: 1.0,
+                        ..Default::default()
+                    }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(8.0)),
+            ..Default::default()
+        })
+    }
+
+    /// Queue copying an entry to the other side, replacing what's there.
+    fn resolve(&self, index: usize, direction: Direction) {
+        let Some(entry) = self.comparison.entries.get(index) else {
+            return;
+        };
+        let (from, to) = match direction {
+            Direction::LeftToRight => (&self.comparison.left, &self.comparison.right),
+            Direction::RightToLeft => (&self.comparison.right, &self.comparison.left),
+        };
+        let operation = Operation::new(OperationKind::Copy {
+            sources: vec![from.join(&entry.name)],
+            destination: to.clone(),
+        })
+        .with_policy(OverwritePolicy::Overwrite);
+        tokio::spawn(async move {
+            if let Err(e) = daemon::client::queue(&operation).await {
+                log::error!("{}", e);
+            }
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for CompareDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+        let requested = self.requested.lock().ok().and_then(|mut r| r.take());
+        if let Some((index, direction)) = requested {
+            self.resolve(index, direction);
+            if index < self.comparison.entries.len() {
+                self.comparison.entries.remove(index);
+            }
+            self.inner = Self::build(&self.comparison, &self.requested);
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,1138 @@
+//! Background operation daemon (`fileman daemon`)
+//!
+//! Long transfers are queued to a separate process so they keep running when the
+//! window is closed. The daemon owns `io.github.Nepsod.Fileman.Operations` on the
+//! session bus, runs queued jobs one after another with the shared operation engine,
+//! and exits once it has been idle for a while. Any GUI instance can list the jobs,
+//! which is how a new window reattaches to transfers started by a previous one.
+
+use crate::operations::activity::{self, ActivityRecord, Outcome};
+use crate::operations::engine::{self, Conflict, ItemError, Operation, OperationEvent, OperationKind};
+use crate::operations::permissions::PermissionChange;
+use crate::operations::throttle::{self, SpeedLimit};
+use crate::operations::undo;
+use nptk_fileman_widgets::conflict_dialog::{ConflictResolution, OverwritePolicy};
+use nptk_fileman_widgets::error_dialog::ErrorAction;
+use nptk_fileman_widgets::sizes;
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{mpsc, Notify};
+
+const BUS_NAME: &str = "io.github.Nepsod.Fileman.Operations";
+const OBJECT_PATH: &str = "/io/github/Nepsod/Fileman/Operations";
+const INTERFACE: &str = "io.github.Nepsod.Fileman1.Operations";
+
+/// Exit after being idle (no queued or running jobs) for this long
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Finished jobs are kept this long so windows can show the result and retry them
+const FINISHED_JOB_RETENTION: Duration = Duration::from_secs(10 * 60);
+/// Wire value of a permission mode that is left unchanged
+const MODE_UNCHANGED: i32 = -1;
+
+/// State of a job as reported to clients.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    Queued,
+    Running,
+    /// Held in the queue, or stopped part way until resumed
+    Paused,
+    /// Waiting for [resolve_conflict] on this job
+    Conflict { source: PathBuf, destination: PathBuf, remaining: usize },
+    /// An item failed; waiting for [resolve_error] on this job
+    Error { path: PathBuf, message: String, remaining: usize },
+    Finished,
+    Cancelled,
+    Failed(String),
+}
+
+impl JobState {
+    /// Whether the job is waiting, running or paused.
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            JobState::Queued | JobState::Running | JobState::Paused | JobState::Conflict { .. } | JobState::Error { .. }
+        )
+    }
+}
+
+/// A job as reported to clients.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobInfo {
+    pub id: u32,
+    /// E.g. "Copying 3 item(s) to Documents"
+    pub description: String,
+    pub state: JobState,
+    /// Completed fraction between 0.0 and 1.0
+    pub fraction: f64,
+    /// Bytes transferred so far and in total (0 for operations that don't copy)
+    pub done_bytes: u64,
+    pub total_bytes: u64,
+    /// Bytes per second, or [throttle::UNLIMITED]
+    pub speed_limit: u64,
+    /// Items done, skipped because of conflicts and failed, counted when the
+    /// job ends
+    pub completed: u32,
+    pub skipped: u32,
+    pub failed: u32,
+}
+
+impl JobInfo {
+    /// E.g. "42%" or, for transfers, "42% (1.2 GiB of 3 GiB)".
+    pub fn progress(&self) -> String {
+        let percent = (self.fraction * 100.0) as u32;
+        if self.total_bytes > 0 {
+            format!(
+                "{}% ({} of {})",
+                percent,
+                sizes::format(self.done_bytes),
+                sizes::format(self.total_bytes)
+            )
+        } else {
+            format!("{}%", percent)
+        }
+    }
+
+    /// What an ended job did, e.g. "29988 done, 12 failed".
+    pub fn counts(&self) -> String {
+        let mut counts = vec![format!("{} done", self.completed)];
+        if self.skipped > 0 {
+            counts.push(format!("{} skipped", self.skipped));
+        }
+        if self.failed > 0 {
+            counts.push(format!("{} failed", self.failed));
+        }
+        counts.join(", ")
+    }
+}
+
+struct Job {
+    info: JobInfo,
+    operation: Option<Operation>,
+    // What to run again on retry
+    kind: OperationKind,
+    policy: Option<OverwritePolicy>,
+    follow_symlinks: bool,
+    // Shared with the operation, which is moved to the engine when the job starts
+    cancel: Arc<AtomicBool>,
+    pause: Arc<AtomicBool>,
+    speed_limit: SpeedLimit,
+    conflict: Option<Conflict>,
+    error: Option<ItemError>,
+    finished_at: Option<Instant>,
+    // How to revert the job once it is done
+    undo: Option<undo::UndoRecord>,
+}
+
+impl Job {
+    /// State once the pending conflict or error is answered: paused or
+    /// cancelled if that was asked for meanwhile, running otherwise.
+    fn answered_state(&self) -> JobState {
+        if self.cancel.load(Ordering::Relaxed) {
+            JobState::Cancelled
+        } else if self.pause.load(Ordering::Relaxed) {
+            JobState::Paused
+        } else {
+            JobState::Running
+        }
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: Vec<Job>,
+    pending: VecDeque<u32>,
+    next_id: u32,
+    last_activity: Option<Instant>,
+}
+
+impl Queue {
+    fn job_mut(&mut self, id: u32) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|j| j.info.id == id)
+    }
+
+    /// Add a job for `operation` at the end of the queue; returns its id.
+    fn push(&mut self, operation: Operation) -> u32 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.jobs.push(Job {
+            info: JobInfo {
+                id,
+                description: describe(&operation),
+                state: JobState::Queued,
+                fraction: 0.0,
+                done_bytes: 0,
+                total_bytes: 0,
+                speed_limit: operation.speed_limit.get(),
+                completed: 0,
+                skipped: 0,
+                failed: 0,
+            },
+            kind: operation.kind.clone(),
+            policy: operation.policy,
+            follow_symlinks: operation.follow_symlinks,
+            cancel: operation.cancel.clone(),
+            pause: operation.pause.clone(),
+            speed_limit: operation.speed_limit.clone(),
+            operation: Some(operation),
+            conflict: None,
+            error: None,
+            finished_at: None,
+            undo: None,
+        });
+        self.pending.push_back(id);
+        id
+    }
+
+    /// Move a waiting job `steps` places through the queue (negative runs it sooner).
+    fn move_pending(&mut self, id: u32, steps: i32) -> Result<(), String> {
+        let index = self
+            .pending
+            .iter()
+            .position(|pending| *pending == id)
+            .ok_or_else(|| format!("Job {} is not waiting", id))?;
+        self.pending.remove(index);
+        let position = (index as i64 + steps as i64).clamp(0, self.pending.len() as i64) as usize;
+        self.pending.insert(position, id);
+        Ok(())
+    }
+
+    /// Take the first pending job that isn't paused.
+    fn next_pending(&mut self) -> Option<u32> {
+        let index = self.pending.iter().position(|id| {
+            self.jobs
+                .iter()
+                .find(|j| j.info.id == *id)
+                .is_some_and(|job| !job.pause.load(Ordering::Relaxed))
+        })?;
+        self.pending.remove(index)
+    }
+}
+
+/// Paths go over the bus as bytes (`ay`), as they needn't be valid UTF-8.
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.as_os_str().as_bytes().to_vec()
+}
+
+fn bytes_path(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+fn no_job(id: u32) -> zbus::fdo::Error {
+    zbus::fdo::Error::InvalidArgs(format!("No job {}", id))
+}
+
+fn describe(operation: &Operation) -> String {
+    let target = |destination: &PathBuf| {
+        destination
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| destination.display().to_string())
+    };
+    match &operation.kind {
+        OperationKind::Copy { sources, destination } => {
+            format!("Copying {} item(s) to {}", sources.len(), target(destination))
+        },
+        OperationKind::Move { sources, destination } => {
+            format!("Moving {} item(s) to {}", sources.len(), target(destination))
+        },
+        OperationKind::Delete(paths) => format!("Deleting {} item(s)", paths.len()),
+        OperationKind::Trash(paths) => format!("Moving {} item(s) to the trash", paths.len()),
+        OperationKind::Sync { source, destination, mirror } => {
+            let verb = if *mirror { "Mirroring" } else { "Synchronizing" };
+            format!("{} {} to {}", verb, target(source), target(destination))
+        },
+        OperationKind::SetPermissions { paths, .. } => {
+            format!("Changing permissions of {} item(s)", paths.len())
+        },
+        OperationKind::CreateHardLink { sources, destination } => {
+            format!("Linking {} item(s) into {}", sources.len(), target(destination))
+        },
+        OperationKind::WriteImage { image, device, .. } => {
+            format!("Writing {} to {}", target(image), device.display())
+        },
+    }
+}
+
+/// The operation named `kind` on the wire, e.g. "copy", of `sources`.
+fn parse_kind(kind: &str, sources: Vec<PathBuf>, destination: PathBuf) -> Result<OperationKind, String> {
+    Ok(match kind {
+        "copy" => OperationKind::Copy { sources, destination },
+        "move" => OperationKind::Move { sources, destination },
+        "hardlink" => OperationKind::CreateHardLink { sources, destination },
+        "delete" => OperationKind::Delete(sources),
+        "trash" => OperationKind::Trash(sources),
+        "sync" | "mirror" => match <[PathBuf; 1]>::try_from(sources) {
+            Ok([source]) => OperationKind::Sync { source, destination, mirror: kind == "mirror" },
+            Err(_) => return Err("Sync takes exactly one source".to_string()),
+        },
+        other => return Err(format!("Unknown operation '{}'", other)),
+    })
+}
+
+fn parse_policy(policy: &str) -> Option<OverwritePolicy> {
+    match policy {
+        "overwrite" => Some(OverwritePolicy::Overwrite),
+        "skip" => Some(OverwritePolicy::Skip),
+        "keep-both" => Some(OverwritePolicy::KeepBoth),
+        "newer" => Some(OverwritePolicy::OverwriteIfNewer),
+        "cancel" => Some(OverwritePolicy::Cancel),
+        _ => None,
+    }
+}
+
+fn parse_error_action(action: &str) -> Option<ErrorAction> {
+    match action {
+        "retry" => Some(ErrorAction::Retry),
+        "skip" => Some(ErrorAction::Skip),
+        "skip-all" => Some(ErrorAction::SkipAll),
+        "abort" => Some(ErrorAction::Abort),
+        _ => None,
+    }
+}
+
+fn error_action_name(action: ErrorAction) -> &'static str {
+    match action {
+        ErrorAction::Retry => "retry",
+        ErrorAction::Skip => "skip",
+        ErrorAction::SkipAll => "skip-all",
+        ErrorAction::Abort => "abort",
+    }
+}
+
+fn policy_name(policy: Option<OverwritePolicy>) -> &'static str {
+    match policy {
+        None => "ask",
+        Some(OverwritePolicy::Overwrite) => "overwrite",
+        Some(OverwritePolicy::Skip) => "skip",
+        Some(OverwritePolicy::KeepBoth) => "keep-both",
+        Some(OverwritePolicy::OverwriteIfNewer) => "newer",
+        Some(OverwritePolicy::Cancel) => "cancel",
+    }
+}
+
+/// Wire format of a job as returned by `Jobs`
+type JobTuple = (u32, String, String, f64, Vec<u8>, Vec<u8>, String, u64, u64, u64, u32, u32, u32);
+
+struct OperationsService {
+    queue: Arc<Mutex<Queue>>,
+    wake: Arc<Notify>,
+}
+
+#[zbus::interface(name = "io.github.Nepsod.Fileman1.Operations")]
+impl OperationsService {
+    /// Queue an operation; returns its job id.
+    async fn queue(
+        &self,
+        kind: String,
+        sources: Vec<Vec<u8>>,
+        destination: Vec<u8>,
+        policy: String,
+        follow_symlinks: bool,
+    ) -> zbus::fdo::Result<u32> {
+        let sources = sources.into_iter().map(bytes_path).collect();
+        let kind = parse_kind(&kind, sources, bytes_path(destination)).map_err(zbus::fdo::Error::InvalidArgs)?;
+        let mut operation = Operation::new(kind).with_follow_symlinks(follow_symlinks);
+        operation.policy = parse_policy(&policy);
+        Ok(self.push(operation))
+    }
+
+    /// Queue a permission change; a mode of -1 leaves that kind of entry unchanged.
+    async fn queue_permissions(
+        &self,
+        paths: Vec<Vec<u8>>,
+        file_mode: i32,
+        dir_mode: i32,
+        recursive: bool,
+    ) -> zbus::fdo::Result<u32> {
+        let mode = |mode: i32| (mode != MODE_UNCHANGED).then_some(mode as u32);
+        let change = PermissionChange {
+            file_mode: mode(file_mode),
+            dir_mode: mode(dir_mode),
+            recursive,
+        };
+        let paths = paths.into_iter().map(bytes_path).collect();
+        Ok(self.push(Operation::new(OperationKind::SetPermissions { paths, change })))
+    }
+
+    /// Stop a queued or running job.
+    async fn cancel(&self, id: u32) -> zbus::fdo::Result<()> {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        queue.pending.retain(|pending| *pending != id);
+        let job = queue.job_mut(id).ok_or_else(|| no_job(id))?;
+        job.cancel.store(true, Ordering::Relaxed);
+        if job.operation.take().is_some() {
+            // Never started
+            job.info.state = JobState::Cancelled;
+            job.finished_at = Some(Instant::now());
+        }
+        if let Some(conflict) = job.conflict.take() {
+            conflict.respond(ConflictResolution { policy: OverwritePolicy::Cancel, apply_to_all: true });
+        }
+        if let Some(error) = job.error.take() {
+            error.respond(ErrorAction::Abort);
+        }
+        Ok(())
+    }
+
+    /// Hold a queued job or suspend a running one.
+    async fn pause(&self, id: u32) -> zbus::fdo::Result<()> {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = queue.job_mut(id).ok_or_else(|| no_job(id))?;
+        match job.info.state {
+            JobState::Queued | JobState::Running => {
+                job.pause.store(true, Ordering::Relaxed);
+                job.info.state = JobState::Paused;
+            },
+            // Still shown as asking; paused once answered
+            JobState::Conflict { .. } | JobState::Error { .. } => job.pause.store(true, Ordering::Relaxed),
+            _ => {},
+        }
+        Ok(())
+    }
+
+    /// Continue a paused job.
+    async fn resume(&self, id: u32) -> zbus::fdo::Result<()> {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = queue.job_mut(id).ok_or_else(|| no_job(id))?;
+        match job.info.state {
+            JobState::Paused => {
+                job.pause.store(false, Ordering::Relaxed);
+                job.info.state = if job.operation.is_some() { JobState::Queued } else { JobState::Running };
+            },
+            JobState::Conflict { .. } | JobState::Error { .. } => job.pause.store(false, Ordering::Relaxed),
+            _ => {},
+        }
+        drop(queue);
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    /// Move a waiting job `steps` places through the queue (negative runs it sooner).
+    async fn move_job(&self, id: u32, steps: i32) -> zbus::fdo::Result<()> {
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .move_pending(id, steps)
+            .map_err(zbus::fdo::Error::Failed)
+    }
+
+    /// Queue a failed or cancelled job again; returns the new job's id.
+    async fn retry(&self, id: u32) -> zbus::fdo::Result<u32> {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = queue.job_mut(id).ok_or_else(|| no_job(id))?;
+        if !matches!(job.info.state, JobState::Failed(_) | JobState::Cancelled) {
+            return Err(zbus::fdo::Error::Failed(format!("Job {} did not fail", id)));
+        }
+        let mut operation = Operation::new(job.kind.clone())
+            .with_speed_limit(job.speed_limit.get())
+            .with_follow_symlinks(job.follow_symlinks);
+        operation.policy = job.policy;
+        // The new job takes its place in the listing
+        queue.jobs.retain(|job| job.info.id != id);
+        drop(queue);
+        Ok(self.push(operation))
+    }
+
+    /// Forget finished, failed and cancelled jobs.
+    async fn clear_finished(&self) {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        queue.jobs.retain(|job| job.finished_at.is_none());
+    }
+
+    /// Change the speed limit of a job (bytes per second, 0 for none).
+    async fn set_speed_limit(&self, id: u32, bytes_per_second: u64) -> zbus::fdo::Result<()> {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = queue.job_mut(id).ok_or_else(|| no_job(id))?;
+        job.speed_limit.set(bytes_per_second);
+        Ok(())
+    }
+
+    /// Speed limit shared by all jobs (bytes per second, 0 for none).
+    async fn global_speed_limit(&self) -> u64 {
+        throttle::global_limit()
+    }
+
+    /// Change the speed limit shared by all jobs.
+    async fn set_global_speed_limit(&self, bytes_per_second: u64) {
+        throttle::set_global_limit(bytes_per_second);
+    }
+
+    /// All known jobs: (id, description, state, fraction, conflict source or failed item,
+    /// conflict destination, error, speed limit, bytes done, bytes in total, items done,
+    /// skipped and failed). Started jobs come first, then waiting ones in queue order, then
+    /// finished ones.
+    async fn jobs(&self) -> Vec<JobTuple> {
+        let queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut jobs: Vec<&Job> = queue.jobs.iter().collect();
+        jobs.sort_by_key(|job| match queue.pending.iter().position(|id| *id == job.info.id) {
+            Some(position) => (1, position),
+            None if job.finished_at.is_none() => (0, 0),
+            None => (2, 0),
+        });
+        jobs.into_iter()
+            .map(|job| {
+                let (state, source, destination, message) = match &job.info.state {
+                    JobState::Queued => ("queued".to_string(), Vec::new(), Vec::new(), String::new()),
+                    JobState::Running => ("running".to_string(), Vec::new(), Vec::new(), String::new()),
+                    JobState::Paused => ("paused".to_string(), Vec::new(), Vec::new(), String::new()),
+                    JobState::Conflict { source, destination, .. } => {
+                        ("conflict".to_string(), path_bytes(source), path_bytes(destination), String::new())
+                    },
+                    JobState::Error { path, message, remaining } => {
+                        (format!("error:{}", remaining), path_bytes(path), Vec::new(), message.clone())
+                    },
+                    JobState::Finished => ("finished".to_string(), Vec::new(), Vec::new(), String::new()),
+                    JobState::Cancelled => ("cancelled".to_string(), Vec::new(), Vec::new(), String::new()),
+                    JobState::Failed(e) => (format!("failed:{}", e), Vec::new(), Vec::new(), String::new()),
+                };
+                (
+                    job.info.id,
+                    job.info.description.clone(),
+                    state,
+                    job.info.fraction,
+                    source,
+                    destination,
+                    message,
+                    job.speed_limit.get(),
+                    job.info.done_bytes,
+                    job.info.total_bytes,
+                    job.info.completed,
+                    job.info.skipped,
+                    job.info.failed,
+                )
+            })
+            .collect()
+    }
+
+    /// How to revert a done job: the kind of operation and the paths of each
+    /// item; an empty kind if it can't be reverted.
+    async fn undo_record(&self, id: u32) -> zbus::fdo::Result<(String, Vec<Vec<Vec<u8>>>)> {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = queue.job_mut(id).ok_or_else(|| no_job(id))?;
+        let Some(record) = &job.undo else {
+            return Ok((String::new(), Vec::new()));
+        };
+        let (kind, items) = record.to_items();
+        let items = items
+            .into_iter()
+            .map(|paths| paths.into_iter().map(path_bytes).collect())
+            .collect();
+        Ok((kind.to_string(), items))
+    }
+
+    /// Answer the pending conflict of a job.
+    async fn resolve_conflict(&self, id: u32, policy: String, apply_to_all: bool) -> zbus::fdo::Result<()> {
+        let policy = parse_policy(&policy)
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Unknown policy '{}'", policy)))?;
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = queue.job_mut(id).ok_or_else(|| no_job(id))?;
+        let conflict = job
+            .conflict
+            .take()
+            .ok_or_else(|| zbus::fdo::Error::Failed(format!("Job {} has no pending conflict", id)))?;
+        conflict.respond(ConflictResolution { policy, apply_to_all });
+        job.info.state = job.answered_state();
+        Ok(())
+    }
+
+    /// Answer the pending error of a job: "retry", "skip", "skip-all" or "abort".
+    async fn resolve_error(&self, id: u32, action: String) -> zbus::fdo::Result<()> {
+        let action = parse_error_action(&action)
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Unknown action '{}'", action)))?;
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = queue.job_mut(id).ok_or_else(|| no_job(id))?;
+        let error = job
+            .error
+            .take()
+            .ok_or_else(|| zbus::fdo::Error::Failed(format!("Job {} has no pending error", id)))?;
+        error.respond(action);
+        job.info.state = job.answered_state();
+        Ok(())
+    }
+}
+
+impl OperationsService {
+    fn push(&self, operation: Operation) -> u32 {
+        let id = self.queue.lock().unwrap_or_else(PoisonError::into_inner).push(operation);
+        self.wake.notify_one();
+        id
+    }
+}
+
+/// Run one job to completion, mirroring engine events into the queue.
+async fn run_job(queue: &Arc<Mutex<Queue>>, id: u32) {
+    let Some(operation) = queue.lock().unwrap_or_else(PoisonError::into_inner).job_mut(id).and_then(|j| j.operation.take()) else {
+        return;
+    };
+    if let Some(job) = queue.lock().unwrap_or_else(PoisonError::into_inner).job_mut(id) {
+        job.info.state = JobState::Running;
+    }
+    let started = SystemTime::now();
+    let kind = operation.kind.clone();
+
+    let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+    let task = tokio::spawn(engine::spawn(operation, events_tx));
+    while let Some(event) = events_rx.recv().await {
+        let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(job) = queue.job_mut(id) else {
+            continue;
+        };
+        match event {
+            OperationEvent::Progress(progress) => {
+                job.info.fraction = progress.fraction();
+                job.info.done_bytes = progress.done_bytes;
+                job.info.total_bytes = progress.total_bytes;
+            },
+            OperationEvent::Conflict(conflict) => {
+                job.info.state = JobState::Conflict {
+                    source: conflict.source.clone(),
+                    destination: conflict.destination.clone(),
+                    remaining: conflict.remaining,
+                };
+                job.conflict = Some(conflict);
+            },
+            OperationEvent::Error(error) => {
+                job.info.state = JobState::Error {
+                    path: error.path.clone(),
+                    message: error.message.clone(),
+                    remaining: error.remaining,
+                };
+                job.error = Some(error);
+            },
+        }
+    }
+
+    let result = task
+        .await
+        .map_err(|e| format!("Operation task failed: {}", e))
+        .and_then(|r| r);
+    let (sources, destination) = activity::operation_paths(&kind);
+    let mut entry = ActivityRecord {
+        started,
+        finished: SystemTime::now(),
+        operation: activity::operation_name(&kind).to_string(),
+        sources,
+        destination,
+        bytes: 0,
+        completed: 0,
+        skipped: 0,
+        outcome: Outcome::Finished,
+        error: String::new(),
+    };
+    let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+    queue.last_activity = Some(Instant::now());
+    if let Some(job) = queue.job_mut(id) {
+        job.finished_at = Some(Instant::now());
+        job.info.fraction = 1.0;
+        entry.bytes = job.info.done_bytes;
+        match result {
+            Ok(outcome) => {
+                entry.completed = outcome.completed;
+                entry.skipped = outcome.skipped;
+                job.info.completed = outcome.completed as u32;
+                job.info.skipped = outcome.skipped as u32;
+                job.info.failed = outcome.errors.len() as u32;
+                job.info.state = match outcome.errors.first() {
+                    _ if outcome.cancelled => JobState::Cancelled,
+                    // The error that stopped it
+                    _ if outcome.aborted => JobState::Failed(outcome.errors.last().cloned().unwrap_or_default()),
+                    None => JobState::Finished,
+                    Some(first) if outcome.errors.len() == 1 => JobState::Failed(first.clone()),
+                    Some(first) => JobState::Failed(format!("{} (and {} more errors)", first, outcome.errors.len() - 1)),
+                };
+                // Make the last finished job undoable with `fileman undo`
+                if let Some(record) = &outcome.undo {
+                    if let Err(e) = undo::save_last(record) {
+                        log::warn!("{}", e);
+                    }
+                }
+                job.undo = outcome.undo;
+            },
+            Err(e) => {
+                log::error!("Job {} failed: {}", id, e);
+                job.info.state = JobState::Failed(e);
+            },
+        }
+        match &job.info.state {
+            JobState::Cancelled => entry.outcome = Outcome::Cancelled,
+            JobState::Failed(e) => {
+                entry.outcome = Outcome::Failed;
+                entry.error = e.clone();
+            },
+            _ => {},
+        }
+    }
+    drop(queue);
+    if let Err(e) = activity::record(&entry) {
+        log::warn!("{}", e);
+    }
+}
+
+/// Run the daemon until it has been idle for [IDLE_TIMEOUT].
+pub async fn run() -> Result<(), String> {
+    let queue = Arc::new(Mutex::new(Queue {
+        last_activity: Some(Instant::now()),
+        ..Default::default()
+    }));
+    let wake = Arc::new(Notify::new());
+    throttle::set_global_limit(throttle::load_global_limit());
+
+    let _connection = zbus::connection::Builder::session()
+        .and_then(|b| b.name(BUS_NAME))
+        .and_then(|b| {
+            b.serve_at(OBJECT_PATH, OperationsService { queue: queue.clone(), wake: wake.clone() })
+        })
+        .map_err(|e| format!("Failed to set up operations service: {}", e))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to register {} (already running?): {}", BUS_NAME, e))?;
+    log::info!("Operations daemon started");
+
+    loop {
+        let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next_pending();
+        if let Some(id) = next {
+            run_job(&queue, id).await;
+            continue;
+        }
+
+        {
+            let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+            queue.jobs.retain(|job| {
+                job.finished_at.is_none_or(|t| t.elapsed() < FINISHED_JOB_RETENTION)
+            });
+            let idle_since = queue.last_activity.unwrap_or_else(Instant::now);
+            if queue.pending.is_empty() && queue.jobs.is_empty() && idle_since.elapsed() >= IDLE_TIMEOUT {
+                log::info!("Operations daemon idle, exiting");
+                return Ok(());
+            }
+        }
+        let _ = tokio::time::timeout(Duration::from_secs(5), wake.notified()).await;
+    }
+}
+
+/// Client side of the daemon, used by the GUI.
+pub mod client {
+    use super::*;
+    use std::os::unix::process::CommandExt;
+
+    async fn connect() -> Result<zbus::Connection, String> {
+        zbus::Connection::session()
+            .await
+            .map_err(|e| format!("Failed to connect to the session bus: {}", e))
+    }
+
+    async fn list(connection: &zbus::Connection) -> Result<Vec<JobTuple>, String> {
+        connection
+            .call_method(Some(BUS_NAME), OBJECT_PATH, Some(INTERFACE), "Jobs", &())
+            .await
+            .map_err(|e| format!("Failed to list operations: {}", e))?
+            .body()
+            .deserialize()
+            .map_err(|e| format!("Invalid reply from operations daemon: {}", e))
+    }
+
+    /// Start the daemon if it isn't running yet.
+    async fn ensure_running(connection: &zbus::Connection) -> Result<(), String> {
+        if list(connection).await.is_ok() {
+            return Ok(());
+        }
+        let exe = std::env::current_exe().map_err(|e| format!("Failed to locate fileman: {}", e))?;
+        let mut command = std::process::Command::new(exe);
+        command.arg("daemon").stdin(std::process::Stdio::null());
+        // SAFETY: setsid is async-signal-safe and touches no memory of the parent.
+        // In its own session the daemon outlives the window's terminal and
+        // isn't stopped by signals sent to the window's process group.
+        unsafe {
+            command.pre_exec(|| nix::unistd::setsid().map(drop).map_err(std::io::Error::from));
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("Failed to start operations daemon: {}", e))?;
+        // Reaped once it exits after idling, so it doesn't stay a zombie while
+        // the window is open
+        std::thread::spawn(move || child.wait());
+
+        // Wait for the daemon to claim its name
+        for _ in 0..20 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if list(connection).await.is_ok() {
+                return Ok(());
+            }
+        }
+        Err("Operations daemon did not start".to_string())
+    }
+
+    async fn queue_permissions(
+        connection: &zbus::Connection,
+        paths: Vec<Vec<u8>>,
+        change: &PermissionChange,
+    ) -> Result<u32, String> {
+        let mode = |mode: Option<u32>| mode.map(|m| m as i32).unwrap_or(MODE_UNCHANGED);
+        connection
+            .call_method(
+                Some(BUS_NAME),
+                OBJECT_PATH,
+                Some(INTERFACE),
+                "QueuePermissions",
+                &(paths, mode(change.file_mode), mode(change.dir_mode), change.recursive),
+            )
+            .await
+            .map_err(|e| format!("Failed to queue operation: {}", e))?
+            .body()
+            .deserialize()
+            .map_err(|e| format!("Invalid reply from operations daemon: {}", e))
+    }
+
+    /// Queue an operation in the daemon, starting it if needed. Returns the job id.
+    pub async fn queue(operation: &Operation) -> Result<u32, String> {
+        let connection = connect().await?;
+        ensure_running(&connection).await?;
+
+        let to_bytes = |paths: &[PathBuf]| -> Vec<Vec<u8>> { paths.iter().map(|p| path_bytes(p)).collect() };
+        let (kind, sources, destination) = match &operation.kind {
+            OperationKind::Copy { sources, destination } => ("copy", to_bytes(sources), path_bytes(destination)),
+            OperationKind::Move { sources, destination } => ("move", to_bytes(sources), path_bytes(destination)),
+            OperationKind::CreateHardLink { sources, destination } => {
+                ("hardlink", to_bytes(sources), path_bytes(destination))
+            },
+            OperationKind::Delete(paths) => ("delete", to_bytes(paths), Vec::new()),
+            OperationKind::Trash(paths) => ("trash", to_bytes(paths), Vec::new()),
+            OperationKind::Sync { source, destination, mirror } => (
+                if *mirror { "mirror" } else { "sync" },
+                to_bytes(std::slice::from_ref(source)),
+                path_bytes(destination),
+            ),
+            OperationKind::SetPermissions { paths, change } => {
+                return queue_permissions(&connection, to_bytes(paths), change).await;
+            },
+            // The opened device can't be handed over
+            OperationKind::WriteImage { .. } => {
+                return Err("Disk images are written by the window, not the operations daemon".to_string());
+            },
+        };
+        let id = connection
+            .call_method(
+                Some(BUS_NAME),
+                OBJECT_PATH,
+                Some(INTERFACE),
+                "Queue",
+                &(kind, sources, destination, policy_name(operation.policy), operation.follow_symlinks),
+            )
+            .await
+            .map_err(|e| format!("Failed to queue operation: {}", e))?
+            .body()
+            .deserialize()
+            .map_err(|e| format!("Invalid reply from operations daemon: {}", e))?;
+        let speed_limit = operation.speed_limit.get();
+        if speed_limit != throttle::UNLIMITED {
+            set_speed_limit(id, speed_limit).await?;
+        }
+        Ok(id)
+    }
+
+    /// List the daemon's jobs. Returns an empty list if the daemon isn't running.
+    pub async fn jobs() -> Vec<JobInfo> {
+        let Ok(connection) = connect().await else {
+            return Vec::new();
+        };
+        let Ok(jobs) = list(&connection).await else {
+            return Vec::new();
+        };
+        jobs.into_iter()
+            .map(|(id, description, state, fraction, source, destination, message, speed_limit, done_bytes, total_bytes, completed, skipped, failed)| {
+                let state = match state.as_str() {
+                    "queued" => JobState::Queued,
+                    "running" => JobState::Running,
+                    "paused" => JobState::Paused,
+                    "conflict" => JobState::Conflict {
+                        source: bytes_path(source),
+                        destination: bytes_path(destination),
+                        remaining: 0,
+                    },
+                    "finished" => JobState::Finished,
+                    "cancelled" => JobState::Cancelled,
+                    other => match other.strip_prefix("error:") {
+                        Some(remaining) => JobState::Error {
+                            path: bytes_path(source),
+                            message,
+                            remaining: remaining.parse().unwrap_or(0),
+                        },
+                        None => JobState::Failed(other.strip_prefix("failed:").unwrap_or(other).to_string()),
+                    },
+                };
+                JobInfo {
+                    id,
+                    description,
+                    state,
+                    fraction,
+                    done_bytes,
+                    total_bytes,
+                    speed_limit,
+                    completed,
+                    skipped,
+                    failed,
+                }
+            })
+            .collect()
+    }
+
+    /// How to revert a done job, if it can be reverted.
+    pub async fn undo_record(id: u32) -> Option<undo::UndoRecord> {
+        let connection = connect().await.ok()?;
+        let (kind, items): (String, Vec<Vec<Vec<u8>>>) = connection
+            .call_method(Some(BUS_NAME), OBJECT_PATH, Some(INTERFACE), "UndoRecord", &(id,))
+            .await
+            .ok()?
+            .body()
+            .deserialize()
+            .ok()?;
+        let items = items
+            .into_iter()
+            .map(|paths| paths.into_iter().map(bytes_path).collect())
+            .collect();
+        undo::UndoRecord::from_items(&kind, items)
+    }
+
+    /// Stop a queued or running job.
+    pub async fn cancel(id: u32) -> Result<(), String> {
+        job_call("Cancel", id, "cancel operation").await
+    }
+
+    /// Call a method of the daemon that takes a job id and returns nothing.
+    async fn job_call(method: &str, id: u32, what: &str) -> Result<(), String> {
+        let connection = connect().await?;
+        connection
+            .call_method(Some(BUS_NAME), OBJECT_PATH, Some(INTERFACE), method, &(id,))
+            .await
+            .map_err(|e| format!("Failed to {}: {}", what, e))?;
+        Ok(())
+    }
+
+    /// Hold a queued job or suspend a running one.
+    pub async fn pause(id: u32) -> Result<(), String> {
+        job_call("Pause", id, "pause operation").await
+    }
+
+    /// Continue a paused job.
+    pub async fn resume(id: u32) -> Result<(), String> {
+        job_call("Resume", id, "resume operation").await
+    }
+
+    /// Move a waiting job `steps` places through the queue (negative runs it sooner,
+    /// `i32::MIN` runs it next).
+    pub async fn move_job(id: u32, steps: i32) -> Result<(), String> {
+        let connection = connect().await?;
+        connection
+            .call_method(Some(BUS_NAME), OBJECT_PATH, Some(INTERFACE), "MoveJob", &(id, steps))
+            .await
+            .map_err(|e| format!("Failed to reorder operations: {}", e))?;
+        Ok(())
+    }
+
+    /// Queue a failed or cancelled job again. Returns the new job id.
+    pub async fn retry(id: u32) -> Result<u32, String> {
+        let connection = connect().await?;
+        connection
+            .call_method(Some(BUS_NAME), OBJECT_PATH, Some(INTERFACE), "Retry", &(id,))
+            .await
+            .map_err(|e| format!("Failed to retry operation: {}", e))?
+            .body()
+            .deserialize()
+            .map_err(|e| format!("Invalid reply from operations daemon: {}", e))
+    }
+
+    /// Forget finished, failed and cancelled jobs.
+    pub async fn clear_finished() -> Result<(), String> {
+        let connection = connect().await?;
+        connection
+            .call_method(Some(BUS_NAME), OBJECT_PATH, Some(INTERFACE), "ClearFinished", &())
+            .await
+            .map_err(|e| format!("Failed to clear operations: {}", e))?;
+        Ok(())
+    }
+
+    /// Change the speed limit of a queued or running job.
+    pub async fn set_speed_limit(id: u32, bytes_per_second: u64) -> Result<(), String> {
+        let connection = connect().await?;
+        connection
+            .call_method(Some(BUS_NAME), OBJECT_PATH, Some(INTERFACE), "SetSpeedLimit", &(id, bytes_per_second))
+            .await
+            .map_err(|e| format!("Failed to change speed limit: {}", e))?;
+        Ok(())
+    }
+
+    /// Speed limit shared by all jobs; the saved one if the daemon isn't running.
+    pub async fn global_speed_limit() -> u64 {
+        let Ok(connection) = connect().await else {
+            return throttle::load_global_limit();
+        };
+        let reply = connection
+            .call_method(Some(BUS_NAME), OBJECT_PATH, Some(INTERFACE), "GlobalSpeedLimit", &())
+            .await;
+        match reply.map(|reply| reply.body().deserialize::<u64>()) {
+            Ok(Ok(limit)) => limit,
+            _ => throttle::load_global_limit(),
+        }
+    }
+
+    /// Change the speed limit shared by all jobs. It is saved, so a daemon started
+    /// later applies it too.
+    pub async fn set_global_speed_limit(bytes_per_second: u64) -> Result<(), String> {
+        throttle::save_global_limit(bytes_per_second)?;
+        let connection = connect().await?;
+        if list(&connection).await.is_ok() {
+            connection
+                .call_method(
+                    Some(BUS_NAME),
+                    OBJECT_PATH,
+                    Some(INTERFACE),
+                    "SetGlobalSpeedLimit",
+                    &(bytes_per_second,),
+                )
+                .await
+                .map_err(|e| format!("Failed to change speed limit: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Answer a job's pending conflict.
+    pub async fn resolve_conflict(id: u32, resolution: ConflictResolution) -> Result<(), String> {
+        let connection = connect().await?;
+        connection
+            .call_method(
+                Some(BUS_NAME),
+                OBJECT_PATH,
+                Some(INTERFACE),
+                "ResolveConflict",
+                &(id, policy_name(Some(resolution.policy)), resolution.apply_to_all),
+            )
+            .await
+            .map_err(|e| format!("Failed to resolve conflict: {}", e))?;
+        Ok(())
+    }
+
+    /// Answer a job's pending error.
+    pub async fn resolve_error(id: u32, action: ErrorAction) -> Result<(), String> {
+        let connection = connect().await?;
+        connection
+            .call_method(
+                Some(BUS_NAME),
+                OBJECT_PATH,
+                Some(INTERFACE),
+                "ResolveError",
+                &(id, error_action_name(action)),
+            )
+            .await
+            .map_err(|e| format!("Failed to answer error: {}", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trash(path: &str) -> Operation {
+        Operation::new(OperationKind::Trash(vec![PathBuf::from(path)]))
+    }
+
+    #[test]
+    fn paused_jobs_wait_in_the_queue() {
+        let mut queue = Queue::default();
+        let first = queue.push(trash("/a"));
+        let second = queue.push(trash("/b"));
+        let third = queue.push(trash("/c"));
+        assert_eq!((first, second, third), (1, 2, 3));
+        assert_eq!(queue.jobs[1].info.description, "Moving 1 item(s) to the trash");
+
+        queue.job_mut(first).unwrap().pause.store(true, Ordering::Relaxed);
+        assert_eq!(queue.next_pending(), Some(second));
+        assert_eq!(queue.next_pending(), Some(third));
+        assert_eq!(queue.next_pending(), None);
+
+        queue.job_mut(first).unwrap().pause.store(false, Ordering::Relaxed);
+        assert_eq!(queue.next_pending(), Some(first));
+        assert_eq!(queue.next_pending(), None);
+    }
+
+    #[test]
+    fn answering_keeps_a_pause_or_cancel() {
+        let mut queue = Queue::default();
+        let id = queue.push(trash("/a"));
+        let job = queue.job_mut(id).unwrap();
+        assert_eq!(job.answered_state(), JobState::Running);
+        job.pause.store(true, Ordering::Relaxed);
+        assert_eq!(job.answered_state(), JobState::Paused);
+        job.cancel.store(true, Ordering::Relaxed);
+        assert_eq!(job.answered_state(), JobState::Cancelled);
+    }
+
+    #[test]
+    fn waiting_jobs_can_be_reordered() {
+        let mut queue = Queue::default();
+        let ids: Vec<u32> = ["/a", "/b", "/c"].into_iter().map(|path| queue.push(trash(path))).collect();
+        queue.move_pending(ids[2], -1).unwrap();
+        assert_eq!(queue.pending, [ids[0], ids[2], ids[1]]);
+        queue.move_pending(ids[0], 10).unwrap();
+        assert_eq!(queue.pending, [ids[2], ids[1], ids[0]]);
+        queue.move_pending(ids[0], -10).unwrap();
+        assert_eq!(queue.pending, [ids[0], ids[2], ids[1]]);
+
+        assert_eq!(queue.next_pending(), Some(ids[0]));
+        assert!(queue.move_pending(ids[0], 1).is_err());
+    }
+
+    #[test]
+    fn parses_operation_kinds() {
+        let sources = || vec![PathBuf::from("/a"), PathBuf::from("/b")];
+        let destination = || PathBuf::from("/dest");
+        assert!(matches!(
+            parse_kind("copy", sources(), destination()),
+            Ok(OperationKind::Copy { sources, destination }) if sources.len() == 2 && destination == Path::new("/dest")
+        ));
+        assert!(matches!(parse_kind("move", sources(), destination()), Ok(OperationKind::Move { .. })));
+        assert!(matches!(
+            parse_kind("hardlink", sources(), destination()),
+            Ok(OperationKind::CreateHardLink { .. })
+        ));
+        assert!(matches!(parse_kind("delete", sources(), PathBuf::new()), Ok(OperationKind::Delete(paths)) if paths.len() == 2));
+        assert!(matches!(parse_kind("trash", sources(), PathBuf::new()), Ok(OperationKind::Trash(_))));
+        assert!(matches!(
+            parse_kind("mirror", vec![PathBuf::from("/a")], destination()),
+            Ok(OperationKind::Sync { mirror: true, .. })
+        ));
+        assert!(matches!(
+            parse_kind("sync", vec![PathBuf::from("/a")], destination()),
+            Ok(OperationKind::Sync { mirror: false, .. })
+        ));
+        assert!(parse_kind("sync", sources(), destination()).is_err());
+        assert!(parse_kind("sync", Vec::new(), destination()).is_err());
+        assert!(parse_kind("format", sources(), destination()).is_err());
+    }
+
+    #[test]
+    fn names_survive_the_bus() {
+        for policy in [
+            None,
+            Some(OverwritePolicy::Overwrite),
+            Some(OverwritePolicy::Skip),
+            Some(OverwritePolicy::KeepBoth),
+            Some(OverwritePolicy::OverwriteIfNewer),
+            Some(OverwritePolicy::Cancel),
+        ] {
+            assert_eq!(parse_policy(policy_name(policy)), policy);
+        }
+        for action in [ErrorAction::Retry, ErrorAction::Skip, ErrorAction::SkipAll, ErrorAction::Abort] {
+            assert_eq!(parse_error_action(error_action_name(action)), Some(action));
+        }
+        assert_eq!(parse_error_action("ignore"), None);
+
+        let path = bytes_path(b"/home/me/caf\xe9".to_vec());
+        assert_eq!(path_bytes(&path), b"/home/me/caf\xe9");
+    }
+}
@@ -0,0 +1,166 @@
+//! D-Bus services
+//!
+//! `org.freedesktop.FileManager1` lets other applications (browsers, download
+//! managers, ...) ask fileman to show folders, reveal items or open their
+//! properties. The fileman-specific `io.github.Nepsod.Fileman` name is owned by the
+//! primary instance; later launches hand their paths over to it instead of starting
+//! a second process. Requests are forwarded to the window through an
+//! [ExternalRequest] channel.
+
+use nptk::services::thumbnail::npio_adapter::uri_to_path;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc;
+
+const BUS_NAME: &str = "org.freedesktop.FileManager1";
+const OBJECT_PATH: &str = "/org/freedesktop/FileManager1";
+
+const INSTANCE_BUS_NAME: &str = "io.github.Nepsod.Fileman";
+const INSTANCE_OBJECT_PATH: &str = "/io/github/Nepsod/Fileman";
+const INSTANCE_INTERFACE: &str = "io.github.Nepsod.Fileman1";
+
+/// Whether this process owns the instance name, i.e. is the main window
+static PRIMARY: AtomicBool = AtomicBool::new(false);
+
+/// Whether this window is the one later launches hand their paths to.
+pub fn is_primary_instance() -> bool {
+    PRIMARY.load(Ordering::Relaxed)
+}
+
+/// A request from outside the window (D-Bus or another fileman process).
+#[derive(Debug, Clone)]
+pub enum ExternalRequest {
+    /// Open the given folders.
+    ShowFolders(Vec<PathBuf>),
+    /// Open the folders containing the given items and select them.
+    ShowItems(Vec<PathBuf>),
+    /// Reveal the given items and show their properties.
+    ShowItemProperties(Vec<PathBuf>),
+    /// Open folders (each in a new tab) and reveal files, passed on the command
+    /// line of another fileman launch.
+    Open { folders: Vec<PathBuf>, select: Vec<PathBuf> },
+}
+
+struct FileManager1 {
+    tx: mpsc::UnboundedSender<ExternalRequest>,
+}
+
+impl FileManager1 {
+    fn forward(&self, request: ExternalRequest) {
+        log::info!("D-Bus request: {:?}", request);
+        if let Err(e) = self.tx.send(request) {
+            log::warn!("Failed to forward D-Bus request: {}", e);
+        }
+    }
+}
+
+/// Convert URIs (or plain paths) to local paths, skipping non-local ones.
+fn uris_to_paths(uris: &[String]) -> Vec<PathBuf> {
+    uris.iter()
+        .filter_map(|uri| {
+            if uri.starts_with('/') {
+                Some(PathBuf::from(uri))
+            } else {
+                let path = uri_to_path(uri);
+                if path.is_none() {
+                    log::warn!("Ignoring non-local URI: {}", uri);
+                }
+                path
+            }
+        })
+        .collect()
+}
+
+// The startup id is accepted for compatibility; window activation tokens are not
+// supported by the toolkit yet.
+#[zbus::interface(name = "org.freedesktop.FileManager1")]
+impl FileManager1 {
+    async fn show_folders(&self, uris: Vec<String>, _startup_id: String) {
+        self.forward(ExternalRequest::ShowFolders(uris_to_paths(&uris)));
+    }
+
+    async fn show_items(&self, uris: Vec<String>, _startup_id: String) {
+        self.forward(ExternalRequest::ShowItems(uris_to_paths(&uris)));
+    }
+
+    async fn show_item_properties(&self, uris: Vec<String>, _startup_id: String) {
+        self.forward(ExternalRequest::ShowItemProperties(uris_to_paths(&uris)));
+    }
+}
+
+/// Entry point for other fileman processes.
+struct FilemanInstance {
+    tx: mpsc::UnboundedSender<ExternalRequest>,
+}
+
+#[zbus::interface(name = "io.github.Nepsod.Fileman1")]
+impl FilemanInstance {
+    async fn open(&self, folders: Vec<String>, select: Vec<String>) {
+        let request = ExternalRequest::Open {
+            folders: folders.into_iter().map(PathBuf::from).collect(),
+            select: select.into_iter().map(PathBuf::from).collect(),
+        };
+        if let Err(e) = self.tx.send(request) {
+            log::warn!("Failed to forward open request: {}", e);
+        }
+    }
+}
+
+/// Register the D-Bus services on the session bus.
+///
+/// With `single_instance`, this process also claims the fileman instance name so
+/// later launches forward to it. FileManager1 is requested separately, since another
+/// file manager may already own it. The returned connection must be kept alive for
+/// the services to stay registered.
+pub async fn serve(
+    tx: mpsc::UnboundedSender<ExternalRequest>,
+    single_instance: bool,
+) -> Result<zbus::Connection, String> {
+    let mut builder = zbus::connection::Builder::session()
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, FileManager1 { tx: tx.clone() }))
+        .and_then(|builder| builder.serve_at(INSTANCE_OBJECT_PATH, FilemanInstance { tx }))
+        .map_err(|e| format!("Failed to set up D-Bus service: {}", e))?;
+    if single_instance {
+        builder = builder
+            .name(INSTANCE_BUS_NAME)
+            .map_err(|e| format!("Failed to set up D-Bus service: {}", e))?;
+    }
+    let connection = builder
+        .build()
+        .await
+        .map_err(|e| format!("Failed to register on the session bus: {}", e))?;
+    PRIMARY.store(single_instance, Ordering::Relaxed);
+
+    if let Err(e) = connection.request_name(BUS_NAME).await {
+        log::info!("Not serving {} (owned by another application?): {}", BUS_NAME, e);
+    }
+    Ok(connection)
+}
+
+/// Hand folders to open and files to select over to an already running fileman instance.
+///
+/// Returns `false` if no instance is running or it could not be reached.
+pub async fn forward_to_running_instance(folders: &[PathBuf], select: &[PathBuf]) -> bool {
+    let Ok(connection) = zbus::Connection::session().await else {
+        return false;
+    };
+    let to_strings = |paths: &[PathBuf]| -> Vec<String> {
+        paths.iter().map(|p| p.to_string_lossy().to_string()).collect()
+    };
+    match connection
+        .call_method(
+            Some(INSTANCE_BUS_NAME),
+            INSTANCE_OBJECT_PATH,
+            Some(INSTANCE_INTERFACE),
+            "Open",
+            &(to_strings(folders), to_strings(select)),
+        )
+        .await
+    {
+        Ok(_) => true,
+        Err(e) => {
+            log::debug!("No running instance to forward to: {}", e);
+            false
+        },
+    }
+}
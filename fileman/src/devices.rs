@@ -0,0 +1,348 @@
+//! Devices section of the sidebar
+//!
+//! Lists the removable and encrypted volumes known to udisks2 together with
+//! gocryptfs and CryFS vaults found in the home directory and the other file
+//! systems of the mount table, and mounts, unlocks, unmounts and ejects them.
+//! Encrypted volumes and vaults are unlocked with a passphrase and locked again
+//! when they are unmounted or ejected.
+//! Ejecting also powers the drive off, so it can be unplugged; a device that is
+//! still in use isn't unmounted and the processes using it are named instead.
+//!
+//! Disk images can be written to the whole disk of a removable drive (see
+//! [WriteImageDialog]).
+//!
+//! When removable media is mounted by something else while fileman runs, a
+//! notification can offer to open it (see the Preferences).
+
+mod busy;
+mod mounts;
+mod udisks;
+mod unlock_dialog;
+mod vault;
+mod write_image;
+
+pub use unlock_dialog::UnlockDialog;
+pub use write_image::{WriteImageDialog, is_disk_image};
+
+use crate::bus::AppBus;
+use crate::toolbar::NavigationAction;
+use crate::window::FileOperationRequest;
+use crate::{feedback, settings};
+use nptk::widgets::sidebar::SidebarItem;
+use nptk_fileman_widgets::fileman_sidebar::SidebarUpdater;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often the device list is refreshed
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Ids of devices mounted from fileman itself, which are opened already and
+/// aren't offered again by the monitor
+static OWN_MOUNTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn remember_own_mount(device: &Device) {
+    if let Ok(mut own) = OWN_MOUNTS.lock() {
+        own.push(device.id.clone());
+    }
+}
+
+/// Whether `device` was mounted from fileman, forgetting it.
+fn take_own_mount(device: &Device) -> bool {
+    let Ok(mut own) = OWN_MOUNTS.lock() else {
+        return false;
+    };
+    let found = own.iter().position(|id| *id == device.id);
+    found.map(|index| own.remove(index)).is_some()
+}
+
+/// What kind of volume a [Device] is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceKind {
+    /// A block device with a file system (udisks object path)
+    Filesystem { object: String },
+    /// A LUKS volume (udisks object path); `cleartext` is set while unlocked
+    Encrypted { object: String, cleartext: Option<String> },
+    /// A gocryptfs or CryFS vault stored in `cipher_dir`
+    Vault { tool: vault::VaultTool, cipher_dir: PathBuf },
+    /// A file system of the mount table udisks doesn't manage
+    Mount { fs_type: String },
+}
+
+/// A mountable volume shown in the Devices section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    /// Sidebar item id
+    pub id: String,
+    pub label: String,
+    pub kind: DeviceKind,
+    pub mount_point: Option<PathBuf>,
+    /// udisks drive object that can be ejected or powered off, if any
+    pub ejectable_drive: Option<String>,
+}
+
+/// The whole disk of a removable drive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disk {
+    /// udisks block object
+    pub object: String,
+    /// udisks drive object
+    pub drive: String,
+    /// Vendor and model, or the device node
+    pub name: String,
+    /// Device node, such as `/dev/sdb`
+    pub device: PathBuf,
+    pub size: u64,
+}
+
+impl Device {
+    /// Whether mounting needs a passphrase first.
+    pub fn is_locked(&self) -> bool {
+        match &self.kind {
+            DeviceKind::Filesystem { .. } | DeviceKind::Mount { .. } => false,
+            DeviceKind::Encrypted { cleartext, .. } => cleartext.is_none(),
+            DeviceKind::Vault { .. } => self.mount_point.is_none(),
+        }
+    }
+
+    fn sidebar_item(&self) -> SidebarItem {
+        let icon = match &self.kind {
+            DeviceKind::Vault { .. } => "folder",
+            DeviceKind::Mount { fs_type } => mounts::icon(fs_type),
+            _ if self.ejectable_drive.is_some() => "drive-removable-media",
+            _ => "drive-harddisk",
+        };
+        let label = if self.is_locked() && !matches!(self.kind, DeviceKind::Filesystem { .. }) {
+            format!("{} (locked)", self.label)
+        } else {
+            self.label.clone()
+        };
+        let item = SidebarItem::new(self.id.clone(), label).with_icon(icon);
+        match &self.mount_point {
+            Some(path) => item.with_uri(format!("file://{}", path.display())),
+            None => item,
+        }
+    }
+}
+
+/// All devices: udisks volumes first, then vaults, then the other mounts.
+pub async fn list() -> Vec<Device> {
+    let mut devices = udisks::list().await.unwrap_or_else(|e| {
+        log::debug!("{}", e);
+        Vec::new()
+    });
+    devices.extend(tokio::task::spawn_blocking(vault::list).await.unwrap_or_default());
+    let known: Vec<PathBuf> = devices.iter().filter_map(|d| d.mount_point.clone()).collect();
+    devices.extend(tokio::task::spawn_blocking(move || mounts::list(&known)).await.unwrap_or_default());
+    devices
+}
+
+/// Mount an unlocked device and return where it is mounted.
+pub async fn mount(device: &Device) -> Result<PathBuf, String> {
+    if let Some(path) = &device.mount_point {
+        return Ok(path.clone());
+    }
+    let mounted = match &device.kind {
+        DeviceKind::Filesystem { object } => udisks::mount(object).await,
+        DeviceKind::Encrypted { cleartext: Some(cleartext), .. } => udisks::mount(cleartext).await,
+        DeviceKind::Encrypted { cleartext: None, .. } | DeviceKind::Vault { .. } => {
+            Err(format!("{} is locked", device.label))
+        },
+        DeviceKind::Mount { .. } => Err(format!("{} is no longer mounted", device.label)),
+    };
+    mounted.inspect(|_| remember_own_mount(device))
+}
+
+/// Unlock an encrypted volume or vault with `passphrase` and mount it.
+pub async fn unlock(device: &Device, passphrase: &str) -> Result<PathBuf, String> {
+    let mounted = match &device.kind {
+        // Remembered by mount()
+        DeviceKind::Filesystem { .. } | DeviceKind::Mount { .. } => return mount(device).await,
+        DeviceKind::Encrypted { object, .. } => {
+            let cleartext = udisks::unlock(object, passphrase).await?;
+            udisks::mount(&cleartext).await
+        },
+        DeviceKind::Vault { tool, cipher_dir } => {
+            let (tool, cipher_dir, passphrase) = (*tool, cipher_dir.clone(), passphrase.to_string());
+            tokio::task::spawn_blocking(move || vault::unlock(tool, &cipher_dir, &passphrase))
+                .await
+                .map_err(|e| format!("Failed to unlock vault: {}", e))?
+        },
+    };
+    mounted.inspect(|_| remember_own_mount(device))
+}
+
+/// Unmount a device, locking it again if it is encrypted.
+///
+/// If the device is in use, the error names the processes using it.
+pub async fn unmount(device: &Device) -> Result<(), String> {
+    let result = unmount_volume(device).await;
+    match (result, &device.mount_point) {
+        (Err(e), Some(mount_point)) if busy::is_busy_error(&e) => {
+            log::debug!("{}", e);
+            let (label, mount_point) = (device.label.clone(), mount_point.clone());
+            Err(tokio::task::spawn_blocking(move || busy::busy_message(&label, &mount_point))
+                .await
+                .unwrap_or(e))
+        },
+        (result, _) => result,
+    }
+}
+
+async fn unmount_volume(device: &Device) -> Result<(), String> {
+    match &device.kind {
+        DeviceKind::Filesystem { object } => {
+            if device.mount_point.is_some() {
+                udisks::unmount(object).await?;
+            }
+            Ok(())
+        },
+        DeviceKind::Encrypted { object, cleartext } => {
+            if let Some(cleartext) = cleartext {
+                if device.mount_point.is_some() {
+                    udisks::unmount(cleartext).await?;
+                }
+                udisks::lock(object).await?;
+            }
+            Ok(())
+        },
+        DeviceKind::Vault { .. } => match device.mount_point.clone() {
+            Some(mount_point) => tokio::task::spawn_blocking(move || vault::lock(&mount_point))
+                .await
+                .map_err(|e| format!("Failed to lock vault: {}", e))?,
+            None => Ok(()),
+        },
+        DeviceKind::Mount { fs_type } => match device.mount_point.clone() {
+            Some(mount_point) => {
+                let fs_type = fs_type.clone();
+                tokio::task::spawn_blocking(move || mounts::unmount(&mount_point, &fs_type))
+                    .await
+                    .map_err(|e| format!("Failed to unmount: {}", e))?
+            },
+            None => Ok(()),
+        },
+    }
+}
+
+/// Unmount (and lock) a device, then eject its media and power its drive off
+/// so it can be removed safely.
+pub async fn eject(device: &Device) -> Result<(), String> {
+    unmount(device).await?;
+    match &device.ejectable_drive {
+        Some(drive) => udisks::safely_remove(drive).await,
+        None => Ok(()),
+    }
+}
+
+/// Disks that images can be written to.
+pub async fn disks() -> Result<Vec<Disk>, String> {
+    udisks::disks().await
+}
+
+/// Unmount everything on `disk` and open it for writing `image`.
+pub async fn open_for_writing(disk: &Disk, image: &std::path::Path) -> Result<std::fs::File, String> {
+    udisks::open_for_writing(disk, image).await
+}
+
+/// The removable device mounted at or above `path`.
+pub fn containing(devices: &[Device], path: &std::path::Path) -> Option<Device> {
+    deepest_mount(devices.iter().filter(|device| device.ejectable_drive.is_some()), path)
+}
+
+/// The device mounted at or above `path`, removable or not.
+pub fn mounted_at(devices: &[Device], path: &std::path::Path) -> Option<Device> {
+    deepest_mount(devices.iter(), path)
+}
+
+fn deepest_mount<'a>(devices: impl Iterator<Item = &'a Device>, path: &std::path::Path) -> Option<Device> {
+    devices
+        .filter(|device| device.mount_point.as_ref().is_some_and(|m| path.starts_with(m)))
+        .max_by_key(|device| device.mount_point.as_ref().map_or(0, |m| m.components().count()))
+        .cloned()
+}
+
+/// Whether `path` is on a network share, according to the mount table.
+pub fn is_network_share(path: &std::path::Path) -> bool {
+    mounts::entries()
+        .into_iter()
+        .filter(|entry| path.starts_with(&entry.mount_point))
+        .max_by_key(|entry| entry.mount_point.components().count())
+        .is_some_and(|entry| mounts::is_network(&entry.fs_type))
+}
+
+/// Whether a file system is mounted at `path` right now, according to the
+/// mount table rather than the device list, which is refreshed now and then.
+pub fn is_mounted(path: &std::path::Path) -> bool {
+    mounts::entries().iter().any(|entry| entry.mount_point == path)
+}
+
+/// Devices in `current` that weren't mounted in `previous`.
+fn newly_mounted<'a>(previous: &[Device], current: &'a [Device]) -> Vec<&'a Device> {
+    current
+        .iter()
+        .filter(|device| device.mount_point.is_some())
+        .filter(|device| {
+            !previous
+                .iter()
+                .any(|p| p.id == device.id && p.mount_point.is_some())
+        })
+        .collect()
+}
+
+/// Notify about `device` and navigate to its mount point if the user chooses
+/// to open it.
+fn offer_to_open(device: &Device, bus: AppBus) {
+    let Some(mount_point) = device.mount_point.clone() else {
+        return;
+    };
+    let body = format!("{} was mounted at {}", device.label, mount_point.display());
+    tokio::spawn(async move {
+        match feedback::notify_with_action("Removable media mounted", &body, "Open in Files").await {
+            Ok(true) => {
+                bus.navigate(NavigationAction::NavigateTo(mount_point));
+            },
+            Ok(false) => {},
+            Err(e) => log::warn!("{}", e),
+        }
+    });
+}
+
+/// Keep the Devices section of the sidebar up to date.
+///
+/// Devices mounted while fileman runs are posted on `bus`. Removable media
+/// mounted from elsewhere is offered to be opened, if enabled; the ones the
+/// user opens are navigated to over `bus`. Returns the shared
+/// device list, used to look up the device behind a sidebar item.
+pub fn spawn_monitor(updater: SidebarUpdater, bus: AppBus) -> Arc<Mutex<Vec<Device>>> {
+    let devices = Arc::new(Mutex::new(Vec::new()));
+    let shared = devices.clone();
+    tokio::spawn(async move {
+        // Devices mounted before fileman started aren't offered
+        let mut first = true;
+        loop {
+            let current = list().await;
+            let previous = shared.lock().map(|d| d.clone()).unwrap_or_default();
+            for device in newly_mounted(&previous, &current) {
+                if first {
+                    continue;
+                }
+                // Lets a window showing a folder of it before it was unmounted go back there
+                bus.operation(FileOperationRequest::Mounted(device.clone()));
+                let removable = device.ejectable_drive.is_some();
+                if removable && !take_own_mount(device) && settings::get().removable_media_prompt {
+                    offer_to_open(device, bus.clone());
+                }
+            }
+            first = false;
+            let changed = previous != current;
+            if changed {
+                updater.set_items("devices", current.iter().map(Device::sidebar_item).collect());
+                if let Ok(mut devices) = shared.lock() {
+                    *devices = current;
+                }
+            }
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+    devices
+}
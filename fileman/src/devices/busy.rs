@@ -0,0 +1,62 @@
+//! Processes keeping a mount point busy.
+//!
+//! When a volume can't be unmounted because it is in use, the processes with
+//! open files, a working directory or a root below its mount point are looked up
+//! in `/proc` so they can be named in the error. Processes of other users can't
+//! be inspected and are missed.
+
+use std::fs;
+use std::path::Path;
+
+/// Whether an unmount error says the target is in use.
+pub fn is_busy_error(error: &str) -> bool {
+    error.contains("DeviceBusy") || error.contains("target is busy") || error.contains("Device or resource busy")
+}
+
+/// Whether the process `pid` uses something below `mount_point`.
+fn uses(pid: &str, mount_point: &Path) -> bool {
+    let process = Path::new("/proc").join(pid);
+    let below = |link: &Path| fs::read_link(link).is_ok_and(|target| target.starts_with(mount_point));
+    if ["cwd", "root", "exe"].iter().any(|name| below(&process.join(name))) {
+        return true;
+    }
+    fs::read_dir(process.join("fd"))
+        .map(|fds| fds.flatten().any(|fd| below(&fd.path())))
+        .unwrap_or(false)
+}
+
+/// (pid, name) of the processes using `mount_point`, fileman itself excluded.
+pub fn processes_using(mount_point: &Path) -> Vec<(u32, String)> {
+    let own = std::process::id();
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut processes: Vec<(u32, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let pid: u32 = name.parse().ok()?;
+            if pid == own || !uses(&name, mount_point) {
+                return None;
+            }
+            let command = fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+            Some((pid, command.trim().to_string()))
+        })
+        .collect();
+    processes.sort();
+    processes
+}
+
+/// Error for a volume that is in use, naming the processes using it if any
+/// were found.
+pub fn busy_message(label: &str, mount_point: &Path) -> String {
+    let processes = processes_using(mount_point);
+    if processes.is_empty() {
+        return format!("{} is in use; close the files and windows open on it and try again", label);
+    }
+    let names: Vec<String> = processes
+        .iter()
+        .map(|(pid, name)| format!("{} ({})", name, pid))
+        .collect();
+    format!("{} is in use by {}", label, names.join(", "))
+}
@@ -0,0 +1,225 @@
+//! File systems from the mount table
+//!
+//! Mounts udisks doesn't manage (the system disks, network shares, FUSE file
+//! systems, or every mount while udisks isn't running) are read from
+//! `/proc/self/mounts`. Kernel and other pseudo file systems, and mounts below
+//! system folders, are left out.
+
+use super::{Device, DeviceKind};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// File system types that don't store files of the user
+const PSEUDO_TYPES: &[&str] = &[
+    "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs", "devpts", "devtmpfs",
+    "efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs", "overlay", "proc", "pstore", "ramfs",
+    "rpc_pipefs", "securityfs", "selinuxfs", "squashfs", "sysfs", "tmpfs", "tracefs",
+    "fuse.gvfsd-fuse", "fuse.portal", "fuse.lxcfs",
+];
+
+/// Folders whose mounts belong to the system; removable media below `/run/media`
+/// is kept
+const SYSTEM_DIRS: &[&str] = &["/proc", "/sys", "/dev", "/run", "/snap", "/boot", "/var", "/tmp"];
+
+/// File system types of network shares
+const NETWORK_TYPES: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "davfs", "fuse.sshfs", "fuse.rclone", "fuse.davfs2",
+];
+
+/// A line of the mount table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountEntry {
+    /// What is mounted: a device node, a share, or the name of a FUSE tool
+    pub source: OsString,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+}
+
+/// Spaces, tabs, newlines and backslashes are written as octal escapes ("\040").
+/// Other bytes are kept as they are, names needn't be UTF-8.
+fn unescape(field: &[u8]) -> OsString {
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(index) = rest.iter().position(|byte| *byte == b'\\') {
+        bytes.extend_from_slice(&rest[..index]);
+        let escape = rest
+            .get(index + 1..index + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match escape {
+            Some(byte) => {
+                bytes.push(byte);
+                rest = &rest[index + 4..];
+            },
+            None => {
+                bytes.push(b'\\');
+                rest = &rest[index + 1..];
+            },
+        }
+    }
+    bytes.extend_from_slice(rest);
+    OsString::from_vec(bytes)
+}
+
+/// Entries of the mount table `table`, as in `/proc/self/mounts`.
+fn parse(table: &[u8]) -> Vec<MountEntry> {
+    table
+        .split(|byte| *byte == b'\n')
+        .filter_map(|line| {
+            let mut fields = line.split(u8::is_ascii_whitespace).filter(|field| !field.is_empty());
+            Some(MountEntry {
+                source: unescape(fields.next()?),
+                mount_point: PathBuf::from(unescape(fields.next()?)),
+                fs_type: String::from_utf8_lossy(fields.next()?).to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Everything that is mounted, in the order it was mounted.
+pub fn entries() -> Vec<MountEntry> {
+    parse(&fs::read("/proc/self/mounts").unwrap_or_default())
+}
+
+/// Whether `source` is a device node.
+fn is_device(source: &OsStr) -> bool {
+    source.as_bytes().starts_with(b"/dev/")
+}
+
+/// Whether file systems of type `fs_type` are network shares.
+pub fn is_network(fs_type: &str) -> bool {
+    NETWORK_TYPES.contains(&fs_type)
+}
+
+/// Whether `entry` holds files of the user.
+fn is_user_mount(entry: &MountEntry) -> bool {
+    if PSEUDO_TYPES.contains(&entry.fs_type.as_str()) {
+        return false;
+    }
+    let path = &entry.mount_point;
+    path.starts_with("/run/media") || !SYSTEM_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+/// Icon for a file system of type `fs_type`.
+pub fn icon(fs_type: &str) -> &'static str {
+    match fs_type {
+        _ if NETWORK_TYPES.contains(&fs_type) => "folder-remote",
+        "iso9660" | "udf" => "media-optical",
+        "vfat" | "exfat" | "ntfs" | "ntfs3" | "fuseblk" => "drive-removable-media",
+        _ if fs_type.starts_with("fuse.") => "folder",
+        _ => "drive-harddisk",
+    }
+}
+
+/// Label of the device node `source`, from the links in `/dev/disk/by-label`.
+fn disk_label(source: &OsStr) -> Option<String> {
+    let device = fs::canonicalize(source).ok()?;
+    fs::read_dir("/dev/disk/by-label")
+        .ok()?
+        .flatten()
+        .find(|link| fs::canonicalize(link.path()).is_ok_and(|target| target == device))
+        .map(|link| link.file_name().to_string_lossy().replace("\\x20", " "))
+}
+
+/// Name shown for a mount: its disk label and where it is mounted, the
+/// mount point alone if the file system has no label
+fn label(entry: &MountEntry) -> String {
+    let mount_point = entry.mount_point.display().to_string();
+    if entry.mount_point == Path::new("/") {
+        return "File System".to_string();
+    }
+    match is_device(&entry.source).then(|| disk_label(&entry.source)).flatten() {
+        Some(label) => format!("{} ({})", label, mount_point),
+        None => mount_point,
+    }
+}
+
+/// Devices for the user's mounts, except those mounted at one of `known`.
+/// A file system mounted more than once is listed at its first mount point.
+pub fn list(known: &[PathBuf]) -> Vec<Device> {
+    let mut seen_sources = Vec::new();
+    entries()
+        .into_iter()
+        .filter(is_user_mount)
+        .filter(|entry| !known.contains(&entry.mount_point))
+        .filter(|entry| {
+            // Bind mounts of a disk share its source; shares can't be told apart by it
+            let duplicate = is_device(&entry.source) && seen_sources.contains(&entry.source);
+            seen_sources.push(entry.source.clone());
+            !duplicate
+        })
+        .map(|entry| Device {
+            id: format!("mount:{}", entry.mount_point.display()),
+            label: label(&entry),
+            mount_point: Some(entry.mount_point),
+            kind: DeviceKind::Mount { fs_type: entry.fs_type },
+            ejectable_drive: None,
+        })
+        .collect()
+}
+
+/// Unmount the file system at `mount_point`: FUSE file systems with
+/// fusermount, others with umount (which needs them to be user mountable).
+pub fn unmount(mount_point: &Path, fs_type: &str) -> Result<(), String> {
+    let tools: &[&str] = if fs_type.starts_with("fuse") {
+        &["fusermount3", "fusermount"]
+    } else {
+        &["umount"]
+    };
+    let mut last_error = String::new();
+    for tool in tools {
+        let mut command = Command::new(tool);
+        if *tool != "umount" {
+            command.arg("-u");
+        }
+        match command.arg(mount_point).output() {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) => last_error = String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(format!("Failed to unmount {}: {}", mount_point.display(), last_error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(source: &str, mount_point: &str, fs_type: &str) -> MountEntry {
+        MountEntry { source: source.into(), mount_point: mount_point.into(), fs_type: fs_type.to_string() }
+    }
+
+    #[test]
+    fn unescapes_octal_escapes() {
+        assert_eq!(unescape(br"/media/My\040Disk"), "/media/My Disk");
+        assert_eq!(unescape(br"a\011b\134c"), "a\tb\\c");
+        // Not an escape
+        assert_eq!(unescape(br"a\9b\04"), r"a\9b\04");
+    }
+
+    #[test]
+    fn parses_names_that_arent_utf8() {
+        let table = b"/dev/sdb1 /run/media/me/Caf\xe9\\040Photos vfat rw 0 0\nproc /proc proc rw 0 0\n";
+        let entries = parse(table);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].mount_point.as_os_str().as_bytes(),
+            b"/run/media/me/Caf\xe9 Photos"
+        );
+        assert_eq!(entries[0].fs_type, "vfat");
+        assert_eq!(entries[1], entry("proc", "/proc", "proc"));
+    }
+
+    #[test]
+    fn keeps_only_user_mounts() {
+        assert!(is_user_mount(&entry("/dev/sda2", "/home", "ext4")));
+        assert!(is_user_mount(&entry("/dev/sdb1", "/run/media/me/USB", "vfat")));
+        assert!(is_user_mount(&entry("me@host:", "/mnt/share", "fuse.sshfs")));
+        assert!(!is_user_mount(&entry("tmpfs", "/home/me/tmp", "tmpfs")));
+        assert!(!is_user_mount(&entry("/dev/sda1", "/boot/efi", "vfat")));
+        assert!(!is_user_mount(&entry("/dev/loop0", "/run/user/1000/doc", "ext4")));
+    }
+}
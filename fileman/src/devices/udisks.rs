@@ -0,0 +1,316 @@
+//! Block devices through udisks2 on the system bus.
+
+use super::{Device, DeviceKind, Disk};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+const SERVICE: &str = "org.freedesktop.UDisks2";
+const MANAGER_PATH: &str = "/org/freedesktop/UDisks2";
+const BLOCK: &str = "org.freedesktop.UDisks2.Block";
+const FILESYSTEM: &str = "org.freedesktop.UDisks2.Filesystem";
+const ENCRYPTED: &str = "org.freedesktop.UDisks2.Encrypted";
+const DRIVE: &str = "org.freedesktop.UDisks2.Drive";
+const PARTITION: &str = "org.freedesktop.UDisks2.Partition";
+
+/// `O_EXCL`: opening a block device fails while it is mounted or otherwise in use
+const O_EXCL: i32 = 0o200;
+
+/// udisks probes an unlocked volume asynchronously; mounting is retried this often
+const MOUNT_RETRIES: usize = 10;
+const MOUNT_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+type Properties = HashMap<String, OwnedValue>;
+type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, Properties>>;
+type Options<'a> = HashMap<&'a str, Value<'a>>;
+
+async fn connect() -> Result<zbus::Connection, String> {
+    zbus::Connection::system()
+        .await
+        .map_err(|e| format!("Failed to connect to the system bus: {}", e))
+}
+
+fn text(properties: &Properties, key: &str) -> Option<String> {
+    properties
+        .get(key)
+        .and_then(|v| v.downcast_ref::<&str>().ok())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+fn flag(properties: &Properties, key: &str) -> bool {
+    properties.get(key).and_then(|v| v.downcast_ref::<bool>().ok()).unwrap_or(false)
+}
+
+fn number(properties: &Properties, key: &str) -> u64 {
+    properties.get(key).and_then(|v| v.downcast_ref::<u64>().ok()).unwrap_or(0)
+}
+
+/// An object path property, or `None` when it is unset ("/").
+fn object(properties: &Properties, key: &str) -> Option<String> {
+    properties
+        .get(key)
+        .and_then(|v| v.downcast_ref::<ObjectPath>().ok())
+        .map(|p| p.to_string())
+        .filter(|p| p != "/")
+}
+
+/// A NUL-terminated byte string such as `Block.Device`.
+fn byte_string(bytes: Vec<u8>) -> String {
+    String::from_utf8_lossy(bytes.split(|b| *b == 0).next().unwrap_or_default()).to_string()
+}
+
+/// Device node of a block device, such as `/dev/sdb`.
+fn device_node(block: &Properties) -> Option<String> {
+    let value: Value = block.get("Device")?.try_clone().ok()?.into();
+    Vec::<u8>::try_from(value).ok().map(byte_string).filter(|s| !s.is_empty())
+}
+
+fn first_mount_point(properties: &Properties) -> Option<PathBuf> {
+    let value: Value = properties.get("MountPoints")?.try_clone().ok()?.into();
+    Vec::<Vec<u8>>::try_from(value)
+        .ok()?
+        .into_iter()
+        .next()
+        .map(|bytes| PathBuf::from(byte_string(bytes)))
+}
+
+async fn managed_objects(connection: &zbus::Connection) -> Result<ManagedObjects, String> {
+    connection
+        .call_method(Some(SERVICE), MANAGER_PATH, Some("org.freedesktop.DBus.ObjectManager"), "GetManagedObjects", &())
+        .await
+        .map_err(|e| format!("Failed to list devices: {}", e))?
+        .body()
+        .deserialize()
+        .map_err(|e| format!("Invalid reply from udisks: {}", e))
+}
+
+/// Drives with media to eject, and hot-pluggable ones such as USB sticks.
+fn removable_drives(objects: &ManagedObjects) -> Vec<String> {
+    objects
+        .iter()
+        .filter(|(_, interfaces)| {
+            interfaces
+                .get(DRIVE)
+                .is_some_and(|d| flag(d, "Ejectable") || (flag(d, "Removable") && flag(d, "CanPowerOff")))
+        })
+        .map(|(path, _)| path.to_string())
+        .collect()
+}
+
+/// Volumes with a file system or LUKS encryption, skipping system and ignored ones
+/// and the cleartext side of unlocked volumes.
+pub async fn list() -> Result<Vec<Device>, String> {
+    let connection = connect().await?;
+    let objects = managed_objects(&connection).await?;
+    let ejectable_drives = removable_drives(&objects);
+    let mount_point_of = |path: &str| {
+        objects
+            .iter()
+            .find(|(p, _)| p.as_str() == path)
+            .and_then(|(_, interfaces)| interfaces.get(FILESYSTEM))
+            .and_then(first_mount_point)
+    };
+
+    let mut devices = Vec::new();
+    for (path, interfaces) in &objects {
+        let Some(block) = interfaces.get(BLOCK) else {
+            continue;
+        };
+        if flag(block, "HintIgnore") || flag(block, "HintSystem") || object(block, "CryptoBackingDevice").is_some() {
+            continue;
+        }
+        let object_path = path.to_string();
+        let kind = if let Some(encrypted) = interfaces.get(ENCRYPTED) {
+            DeviceKind::Encrypted {
+                object: object_path.clone(),
+                cleartext: object(encrypted, "CleartextDevice"),
+            }
+        } else if interfaces.contains_key(FILESYSTEM) {
+            DeviceKind::Filesystem { object: object_path.clone() }
+        } else {
+            continue;
+        };
+        let mount_point = match &kind {
+            DeviceKind::Encrypted { cleartext: Some(cleartext), .. } => mount_point_of(cleartext),
+            _ => mount_point_of(&object_path),
+        };
+        let label = text(block, "IdLabel")
+            .or_else(|| text(block, "HintName"))
+            .or_else(|| device_node(block))
+            .unwrap_or_else(|| object_path.clone());
+        devices.push(Device {
+            id: format!("device:{}", object_path),
+            label,
+            kind,
+            mount_point,
+            ejectable_drive: object(block, "Drive").filter(|d| ejectable_drives.contains(d)),
+        });
+    }
+    devices.sort_by(|a, b| a.label.cmp(&b.label));
+    Ok(devices)
+}
+
+/// Call a udisks method whose only argument is an (empty) options dictionary.
+async fn call(object: &str, interface: &str, method: &str) -> Result<zbus::Message, String> {
+    let connection = connect().await?;
+    connection
+        .call_method(Some(SERVICE), object, Some(interface), method, &(Options::new(),))
+        .await
+        .map_err(|e| format!("{} failed: {}", method, e))
+}
+
+/// Mount the file system on `object`, retrying while udisks is still probing it.
+pub async fn mount(object: &str) -> Result<PathBuf, String> {
+    let mut last_error = String::new();
+    for _ in 0..MOUNT_RETRIES {
+        match call(object, FILESYSTEM, "Mount").await {
+            Ok(reply) => {
+                let path: String = reply
+                    .body()
+                    .deserialize()
+                    .map_err(|e| format!("Invalid reply from udisks: {}", e))?;
+                return Ok(PathBuf::from(path));
+            },
+            Err(e) => last_error = e,
+        }
+        tokio::time::sleep(MOUNT_RETRY_DELAY).await;
+    }
+    Err(last_error)
+}
+
+pub async fn unmount(object: &str) -> Result<(), String> {
+    call(object, FILESYSTEM, "Unmount").await.map(|_| ())
+}
+
+/// Unlock a LUKS volume and return its cleartext object.
+pub async fn unlock(object: &str, passphrase: &str) -> Result<String, String> {
+    let connection = connect().await?;
+    let reply = connection
+        .call_method(Some(SERVICE), object, Some(ENCRYPTED), "Unlock", &(passphrase, Options::new()))
+        .await
+        .map_err(|e| format!("Failed to unlock: {}", e))?;
+    let cleartext: OwnedObjectPath = reply
+        .body()
+        .deserialize()
+        .map_err(|e| format!("Invalid reply from udisks: {}", e))?;
+    Ok(cleartext.to_string())
+}
+
+pub async fn lock(object: &str) -> Result<(), String> {
+    call(object, ENCRYPTED, "Lock").await.map(|_| ())
+}
+
+/// Eject the media of `drive` if it has any, then power the drive off if it
+/// can be, after which it can be unplugged.
+pub async fn safely_remove(drive: &str) -> Result<(), String> {
+    let connection = connect().await?;
+    let properties: Properties = connection
+        .call_method(Some(SERVICE), drive, Some("org.freedesktop.DBus.Properties"), "GetAll", &(DRIVE,))
+        .await
+        .map_err(|e| format!("Failed to read the drive: {}", e))?
+        .body()
+        .deserialize()
+        .map_err(|e| format!("Invalid reply from udisks: {}", e))?;
+    if flag(&properties, "Ejectable") && flag(&properties, "MediaAvailable") {
+        call(drive, DRIVE, "Eject").await?;
+    }
+    if flag(&properties, "CanPowerOff") {
+        call(drive, DRIVE, "PowerOff").await?;
+    }
+    Ok(())
+}
+
+/// Whole disks of removable drives, to write images to.
+pub async fn disks() -> Result<Vec<Disk>, String> {
+    let connection = connect().await?;
+    let objects = managed_objects(&connection).await?;
+    let drives = removable_drives(&objects);
+    let mut disks: Vec<Disk> = objects
+        .iter()
+        .filter(|(_, interfaces)| !interfaces.contains_key(PARTITION))
+        .filter_map(|(path, interfaces)| {
+            let block = interfaces.get(BLOCK)?;
+            let drive = object(block, "Drive").filter(|d| drives.contains(d))?;
+            let device = device_node(block)?;
+            let model = objects
+                .iter()
+                .find(|(p, _)| p.as_str() == drive)
+                .and_then(|(_, interfaces)| interfaces.get(DRIVE))
+                .map(|d| [text(d, "Vendor"), text(d, "Model")].into_iter().flatten().collect::<Vec<_>>().join(" "))
+                .filter(|model| !model.is_empty());
+            Some(Disk {
+                object: path.to_string(),
+                drive,
+                name: model.unwrap_or_else(|| device.clone()),
+                device: PathBuf::from(device),
+                size: number(block, "Size"),
+            })
+        })
+        .filter(|disk| disk.size > 0)
+        .collect();
+    disks.sort_by(|a, b| a.device.cmp(&b.device));
+    Ok(disks)
+}
+
+/// Unmount and lock everything on the drive of `disk` and open the disk for
+/// writing, asking for authorization if needed. Fails if `image` is stored on
+/// the disk.
+pub async fn open_for_writing(disk: &Disk, image: &Path) -> Result<fs::File, String> {
+    let connection = connect().await?;
+    let objects = managed_objects(&connection).await?;
+    let on_drive: Vec<(&OwnedObjectPath, &HashMap<String, Properties>)> = objects
+        .iter()
+        .filter(|(_, interfaces)| {
+            interfaces
+                .get(BLOCK)
+                .is_some_and(|block| object(block, "Drive").as_deref() == Some(disk.drive.as_str()))
+        })
+        .collect();
+    let mounted_at = |path: &str| {
+        objects
+            .iter()
+            .find(|(p, _)| p.as_str() == path)
+            .and_then(|(_, interfaces)| interfaces.get(FILESYSTEM))
+            .and_then(first_mount_point)
+    };
+
+    // Volumes of the drive, with the cleartext side of unlocked ones
+    let mut volumes = Vec::new();
+    for (path, interfaces) in &on_drive {
+        let cleartext = interfaces.get(ENCRYPTED).and_then(|e| object(e, "CleartextDevice"));
+        if let Some(cleartext) = &cleartext {
+            volumes.push(cleartext.clone());
+        }
+        volumes.push(path.to_string());
+    }
+    let mounted: Vec<(&String, PathBuf)> = volumes
+        .iter()
+        .filter_map(|volume| Some((volume, mounted_at(volume)?)))
+        .collect();
+    if mounted.iter().any(|(_, mount_point)| image.starts_with(mount_point)) {
+        return Err(format!("{} is stored on {}", image.display(), disk.name));
+    }
+    for (volume, _) in mounted {
+        unmount(volume).await?;
+    }
+    for (path, interfaces) in &on_drive {
+        if interfaces.get(ENCRYPTED).and_then(|e| object(e, "CleartextDevice")).is_some() {
+            lock(path.as_str()).await?;
+        }
+    }
+
+    // Buffered, so writes need no aligned buffers; the engine drops the
+    // cached pages before reading the image back to verify it
+    let options: Options = HashMap::from([("flags", Value::I32(O_EXCL))]);
+    let fd: zbus::zvariant::OwnedFd = connection
+        .call_method(Some(SERVICE), disk.object.as_str(), Some(BLOCK), "OpenDevice", &("rw", options))
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", disk.device.display(), e))?
+        .body()
+        .deserialize()
+        .map_err(|e| format!("Invalid reply from udisks: {}", e))?;
+    Ok(fs::File::from(std::os::fd::OwnedFd::from(fd)))
+}
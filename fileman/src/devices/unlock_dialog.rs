@@ -0,0 +1,178 @@
+//! Passphrase prompt for encrypted volumes and vaults.
+
+use super::Device;
+use crate::bus::AppBus;
+use crate::toolbar::NavigationAction;
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Asks for a passphrase, unlocks and mounts the device, and opens it.
+pub struct UnlockDialog {
+    device: Device,
+    bus: AppBus,
+    inner: Container,
+    passphrase: StateSignal<String>,
+    message_text: StateSignal<String>,
+    submitted: Arc<Mutex<bool>>,
+    unlocking: bool,
+    result: Arc<Mutex<Option<Result<PathBuf, String>>>>,
+    signals_hooked: bool,
+}
+
+impl UnlockDialog {
+    pub fn new(
+        device: Device,
+        bus: AppBus,
+    ) -> Self {
+        let passphrase = StateSignal::new(String::new());
+        let message_text = StateSignal::new(format!("Enter the passphrase for {}", device.label));
+        let submitted = Arc::new(Mutex::new(false));
+        let flag = submitted.clone();
+        let inner = Container::new(vec![
+            Box::new(Text::new(message_text.maybe())),
+            Box::new(
+                TextInput::new()
+                    .with_text_signal(passphrase.clone())
+                    .with_placeholder("Passphrase".to_string())
+                    .with_layout_style(LayoutStyle {
+                        size: Vector2::new(Dimension::percent(1.0), Dimension::length(30.0)),
+                        ..Default::default()
+                    }),
+            ),
+            Box::new(
+                Container::new(vec![
+                    Box::new(
+                        Button::new(Text::new("Cancel".to_string()))
+                            .with_on_pressed(MaybeSignal::value(Update::DRAW)),
+                    ),
+                    Box::new(Button::new(Text::new("Unlock".to_string())).with_on_pressed(MaybeSignal::signal(
+                        Box::new(EvalSignal::new(move || {
+                            if let Ok(mut flag) = flag.lock() {
+                                *flag = true;
+                            }
+                            Update::DRAW
+                        })),
+                    ))),
+                ])
+                .with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Row,
+                    gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                    justify_content: Some(JustifyContent::FlexEnd),
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    ..Default::default()
+                }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(10.0)),
+            ..Default::default()
+        });
+
+        Self {
+            device,
+            bus,
+            inner,
+            passphrase,
+            message_text,
+            submitted,
+            unlocking: false,
+            result: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        }
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        let title = format!("Unlock {}", self.device.label);
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), &title, (380, 160), (300, 200));
+    }
+
+    fn start_unlock(&mut self) {
+        // The passphrase is only kept until it has been handed over
+        let passphrase = (*self.passphrase.get()).clone();
+        self.passphrase.set(String::new());
+        if passphrase.is_empty() {
+            return;
+        }
+        self.unlocking = true;
+        self.message_text.set("Unlocking...".to_string());
+        let device = self.device.clone();
+        let slot = self.result.clone();
+        tokio::spawn(async move {
+            let result = super::unlock(&device, &passphrase).await;
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(result);
+            }
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for UnlockDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.passphrase);
+            context.hook_signal(&mut self.message_text);
+            self.signals_hooked = true;
+        }
+
+        let submitted = self.submitted.lock().map(|mut f| std::mem::take(&mut *f)).unwrap_or(false);
+        if submitted && !self.unlocking {
+            self.start_unlock();
+            update.insert(Update::DRAW);
+        }
+
+        let result = self.result.lock().ok().and_then(|mut r| r.take());
+        match result {
+            Some(Ok(mount_point)) => {
+                self.unlocking = false;
+                self.message_text.set(format!("{} is unlocked", self.device.label));
+                self.bus.status(format!("Unlocked {}", self.device.label));
+                self.bus.navigate(NavigationAction::NavigateTo(mount_point));
+                update.insert(Update::LAYOUT | Update::DRAW);
+            },
+            Some(Err(e)) => {
+                self.unlocking = false;
+                log::warn!("{}", e);
+                self.message_text.set(e);
+                update.insert(Update::DRAW);
+            },
+            None => {},
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,134 @@
+//! gocryptfs and CryFS vaults, mounted with their FUSE tools.
+
+use super::{Device, DeviceKind, mounts};
+use nptk_fileman_widgets::xdg;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The tool a vault was created with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultTool {
+    Gocryptfs,
+    Cryfs,
+}
+
+impl VaultTool {
+    /// Detect the tool from the configuration file in `cipher_dir`.
+    fn detect(cipher_dir: &Path) -> Option<Self> {
+        if cipher_dir.join("gocryptfs.conf").is_file() {
+            Some(VaultTool::Gocryptfs)
+        } else if cipher_dir.join("cryfs.config").is_file() {
+            Some(VaultTool::Cryfs)
+        } else {
+            None
+        }
+    }
+
+    fn mount_command(self, cipher_dir: &Path, mount_point: &Path) -> Command {
+        match self {
+            VaultTool::Gocryptfs => {
+                // Reads the password from stdin when it isn't a terminal
+                let mut command = Command::new("gocryptfs");
+                command.arg("-q").arg(cipher_dir).arg(mount_point);
+                command
+            },
+            VaultTool::Cryfs => {
+                let mut command = Command::new("cryfs");
+                command.env("CRYFS_FRONTEND", "noninteractive").arg(cipher_dir).arg(mount_point);
+                command
+            },
+        }
+    }
+}
+
+/// Folders searched for vaults: the home directory and Plasma Vault's store.
+fn search_dirs() -> Vec<PathBuf> {
+    let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+        return Vec::new();
+    };
+    vec![home, xdg::data_home().join("plasma-vault")]
+}
+
+/// Where a vault is mounted while unlocked.
+fn mount_point_for(cipher_dir: &Path) -> PathBuf {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    let name = cipher_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().trim_end_matches(".enc").to_string())
+        .unwrap_or_else(|| "vault".to_string());
+    base.join("fileman/vaults").join(name)
+}
+
+/// Mount points currently in use, from /proc/self/mounts.
+fn mounted() -> Vec<PathBuf> {
+    mounts::entries().into_iter().map(|entry| entry.mount_point).collect()
+}
+
+pub fn list() -> Vec<Device> {
+    let mounted = mounted();
+    let mut devices: Vec<Device> = search_dirs()
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .map(|entry| entry.path())
+        .filter_map(|cipher_dir| {
+            let tool = VaultTool::detect(&cipher_dir)?;
+            let mount_point = mount_point_for(&cipher_dir);
+            let label = mount_point.file_name()?.to_string_lossy().to_string();
+            Some(Device {
+                id: format!("vault:{}", cipher_dir.display()),
+                label,
+                mount_point: mounted.contains(&mount_point).then_some(mount_point),
+                kind: DeviceKind::Vault { tool, cipher_dir },
+                ejectable_drive: None,
+            })
+        })
+        .collect();
+    devices.sort_by(|a, b| a.label.cmp(&b.label));
+    devices
+}
+
+/// Mount a vault, passing the passphrase on stdin, and return its mount point.
+pub fn unlock(tool: VaultTool, cipher_dir: &Path, passphrase: &str) -> Result<PathBuf, String> {
+    let mount_point = mount_point_for(cipher_dir);
+    fs::create_dir_all(&mount_point).map_err(|e| format!("Failed to create {}: {}", mount_point.display(), e))?;
+
+    let mut child = tool
+        .mount_command(cipher_dir, &mount_point)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {:?}: {}", tool, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "{}", passphrase);
+    }
+    let output = child.wait_with_output().map_err(|e| format!("Failed to unlock vault: {}", e))?;
+    if output.status.success() {
+        Ok(mount_point)
+    } else {
+        let _ = fs::remove_dir(&mount_point);
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(format!("Failed to unlock vault: {}", message))
+    }
+}
+
+/// Unmount a vault and remove its mount point.
+pub fn lock(mount_point: &Path) -> Result<(), String> {
+    let unmounted = ["fusermount3", "fusermount"].iter().any(|tool| {
+        Command::new(tool)
+            .arg("-u")
+            .arg(mount_point)
+            .status()
+            .is_ok_and(|status| status.success())
+    });
+    if !unmounted {
+        return Err(format!("Failed to lock {}", mount_point.display()));
+    }
+    let _ = fs::remove_dir(mount_point);
+    Ok(())
+}
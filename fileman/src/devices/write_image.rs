@@ -0,0 +1,394 @@
+//! Writing disk images (ISO and IMG files) to removable drives.
+//!
+//! The image replaces everything on the drive, so writing takes three
+//! confirmations: choosing the drive, agreeing that its data is destroyed, and
+//! a last check naming the image and the device. The drive is unmounted and
+//! opened through udisks, which asks for authorization if needed; the engine
+//! writes the image, reads it back to verify it and reports progress here.
+
+use super::Disk;
+use crate::bus::AppBus;
+use crate::operations::engine::{self, Operation, OperationEvent, OperationKind, OperationOutcome};
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk_fileman_widgets::sizes;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Extensions of files offered to be written to a drive
+const IMAGE_EXTENSIONS: &[&str] = &["iso", "img", "raw"];
+
+/// Whether `path` looks like a disk image that can be written to a drive.
+pub fn is_disk_image(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Which button was pressed, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteRequest {
+    Select(usize),
+    /// Write, then each confirmation
+    Next,
+    Back,
+    Cancel,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stage {
+    Choose,
+    /// Confirmations given after choosing the drive (1 or 2)
+    Confirm(u8),
+    Writing,
+    Finished(String),
+}
+
+/// Picks a removable drive, asks for confirmation three times and writes an
+/// image to it with progress, verification and cancellation.
+pub struct WriteImageDialog {
+    image: PathBuf,
+    image_size: u64,
+    bus: AppBus,
+    disks: Vec<Disk>,
+    loaded: Arc<Mutex<Option<Result<Vec<Disk>, String>>>>,
+    loading_started: bool,
+    selected: Option<usize>,
+    stage: Stage,
+    message: String,
+    progress_text: StateSignal<String>,
+    // Latest progress reported by the engine, shown in `update()`
+    progress: Arc<Mutex<Option<String>>>,
+    cancel: Arc<AtomicBool>,
+    result: Arc<Mutex<Option<Result<OperationOutcome, String>>>>,
+    inner: Container,
+    request: Arc<Mutex<Option<WriteRequest>>>,
+    signals_hooked: bool,
+}
+
+impl WriteImageDialog {
+    pub fn new(image: PathBuf, bus: AppBus) -> Self {
+        let image_size = std::fs::metadata(&image).map(|m| m.len()).unwrap_or(0);
+        let mut dialog = Self {
+            image,
+            image_size,
+            bus,
+            disks: Vec::new(),
+            loaded: Arc::new(Mutex::new(None)),
+            loading_started: false,
+            selected: None,
+            stage: Stage::Choose,
+            message: "Looking for removable drives...".to_string(),
+            progress_text: StateSignal::new(String::new()),
+            progress: Arc::new(Mutex::new(None)),
+            cancel: Arc::new(AtomicBool::new(false)),
+            result: Arc::new(Mutex::new(None)),
+            inner: Container::new(vec![]),
+            request: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        };
+        dialog.rebuild();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        let title = format!(
+            "Write {} to Drive",
+            self.image.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+        );
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), &title, (520, 360), (240, 160));
+    }
+
+    fn button(&self, label: &str, request: WriteRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn close_button(label: &str) -> Button {
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::value(Update::DRAW))
+    }
+
+    fn describe(disk: &Disk) -> String {
+        format!("{} ({}, {})", disk.name, disk.device.display(), sizes::format(disk.size))
+    }
+
+    fn selected_disk(&self) -> Option<&Disk> {
+        self.selected.and_then(|index| self.disks.get(index))
+    }
+
+    fn rebuild(&mut self) {
+        let mut children: Vec<Box<dyn Widget>> = vec![Box::new(Text::new(format!(
+            "Image: {} ({})",
+            self.image.display(),
+            sizes::format(self.image_size)
+        )))];
+        let mut buttons: Vec<Box<dyn Widget>> = Vec::new();
+
+        match (&self.stage, self.selected_disk()) {
+            (Stage::Confirm(1), Some(disk)) => {
+                children.push(Box::new(Text::new(format!(
+                    "All data on {} will be destroyed.",
+                    Self::describe(disk)
+                ))));
+                buttons.push(Box::new(self.button("Back", WriteRequest::Back)));
+                buttons.push(Box::new(self.button(&format!("Erase {}", disk.name), WriteRequest::Next)));
+            },
+            (Stage::Confirm(_), Some(disk)) => {
+                children.push(Box::new(Text::new(format!(
+                    "Last chance: write {} to {}? This can't be undone.",
+                    self.image.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+                    disk.device.display()
+                ))));
+                buttons.push(Box::new(self.button("Back", WriteRequest::Back)));
+                buttons.push(Box::new(self.button("Write Now", WriteRequest::Next)));
+            },
+            (Stage::Writing, _) => {
+                children.push(Box::new(Text::new(self.progress_text.maybe())));
+                buttons.push(Box::new(self.button("Cancel", WriteRequest::Cancel)));
+            },
+            (Stage::Finished(message), _) => {
+                children.push(Box::new(Text::new(message.clone())));
+                buttons.push(Box::new(Self::close_button("Close")));
+            },
+            _ => {
+                children.push(Box::new(Text::new("Write to:".to_string())));
+                for (index, disk) in self.disks.iter().enumerate() {
+                    let mark = if self.selected == Some(index) { "(•)" } else { "( )" };
+                    let too_small = if disk.size < self.image_size { " - too small" } else { "" };
+                    children.push(Box::new(self.button(
+                        &format!("{} {}{}", mark, Self::describe(disk), too_small),
+                        WriteRequest::Select(index),
+                    )));
+                }
+                buttons.push(Box::new(Self::close_button("Cancel")));
+                if self.selected.is_some() {
+                    buttons.push(Box::new(self.button("Write", WriteRequest::Next)));
+                }
+            },
+        }
+        if !self.message.is_empty() {
+            children.push(Box::new(Text::new(self.message.clone()).with_font_size(12.0)));
+        }
+
+        children.push(Box::new(Container::new(buttons).with_layout_style(LayoutStyle {
+            flex_direction: FlexDirection::Row,
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            justify_content: Some(JustifyContent::FlexEnd),
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            ..Default::default()
+        })));
+
+        self.inner = Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(8.0)),
+            ..Default::default()
+        });
+    }
+
+    fn load_disks(&mut self, context: &AppContext) {
+        self.loading_started = true;
+        let slot = self.loaded.clone();
+        let update_manager = context.update();
+        tokio::spawn(async move {
+            let disks = super::disks().await;
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(disks);
+            }
+            update_manager.insert(Update::LAYOUT | Update::DRAW);
+        });
+    }
+
+    fn handle(&mut self, request: WriteRequest, context: &AppContext) {
+        match (request, &self.stage) {
+            (WriteRequest::Select(index), Stage::Choose) => {
+                self.selected = Some(index);
+                self.message.clear();
+            },
+            (WriteRequest::Next, Stage::Choose) => match self.selected_disk().cloned() {
+                Some(disk) if disk.size < self.image_size => {
+                    self.message = format!("The image doesn't fit on {}", disk.name);
+                },
+                Some(_) => self.stage = Stage::Confirm(1),
+                None => {},
+            },
+            (WriteRequest::Next, Stage::Confirm(1)) => self.stage = Stage::Confirm(2),
+            (WriteRequest::Next, Stage::Confirm(_)) => self.start(context),
+            (WriteRequest::Back, Stage::Confirm(_)) => self.stage = Stage::Choose,
+            (WriteRequest::Cancel, Stage::Writing) => {
+                self.cancel.store(true, Ordering::Relaxed);
+                self.progress_text.set("Cancelling...".to_string());
+            },
+            _ => {},
+        }
+    }
+
+    /// Open the drive and write the image on a blocking thread.
+    fn start(&mut self, context: &AppContext) {
+        let Some(disk) = self.selected_disk().cloned() else {
+            return;
+        };
+        self.stage = Stage::Writing;
+        self.message.clear();
+        self.progress_text.set(format!("Opening {}...", disk.device.display()));
+        let image = self.image.clone();
+        let cancel = self.cancel.clone();
+        let progress_slot = self.progress.clone();
+        let result = self.result.clone();
+        let update_manager = context.update();
+        tokio::spawn(async move {
+            let outcome = async {
+                let target = super::open_for_writing(&disk, &image).await?;
+                let mut operation = Operation::new(OperationKind::WriteImage {
+                    image,
+                    device: disk.device.clone(),
+                    target: Arc::new(target),
+                });
+                operation.cancel = cancel;
+                let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+                let forward_update = update_manager.clone();
+                let forward = tokio::spawn(async move {
+                    let mut shown = None;
+                    while let Some(event) = events_rx.recv().await {
+                        let OperationEvent::Progress(progress) = event else {
+                            continue;
+                        };
+                        let percent = (progress.fraction() * 100.0) as u32;
+                        if shown == Some(percent) {
+                            continue;
+                        }
+                        shown = Some(percent);
+                        // The image is counted twice: written, then read back
+                        let phase = if progress.done_bytes * 2 < progress.total_bytes { "Writing" } else { "Verifying" };
+                        if let Ok(mut slot) = progress_slot.lock() {
+                            *slot = Some(format!("{}... {}%", phase, percent));
+                        }
+                        forward_update.insert(Update::DRAW);
+                    }
+                });
+                let outcome = engine::spawn(operation, events_tx).await;
+                let _ = forward.await;
+                outcome
+            }
+            .await;
+            if let Ok(mut slot) = result.lock() {
+                *slot = Some(outcome);
+            }
+            update_manager.insert(Update::LAYOUT | Update::DRAW);
+        });
+    }
+
+    fn finish(&mut self, result: Result<OperationOutcome, String>) {
+        let disk = self.selected_disk().map(|d| d.name.clone()).unwrap_or_default();
+        let message = match result {
+            Ok(outcome) if outcome.cancelled => {
+                format!("Cancelled. {} holds part of the image and has to be formatted before it can be used again", disk)
+            },
+            Ok(_) => format!("The image was written to {} and verified", disk),
+            Err(e) => {
+                log::error!("{}", e);
+                format!("Error: {}", e)
+            },
+        };
+        self.bus.status(message.clone());
+        self.stage = Stage::Finished(message);
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for WriteImageDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.progress_text);
+            self.signals_hooked = true;
+        }
+        if !self.loading_started {
+            self.load_disks(&context);
+        }
+
+        let mut changed = false;
+        let loaded = self.loaded.lock().ok().and_then(|mut l| l.take());
+        match loaded {
+            Some(Ok(disks)) => {
+                self.message = if disks.is_empty() {
+                    "No removable drives found".to_string()
+                } else {
+                    String::new()
+                };
+                self.disks = disks;
+                changed = true;
+            },
+            Some(Err(e)) => {
+                log::warn!("{}", e);
+                self.message = e;
+                changed = true;
+            },
+            None => {},
+        }
+
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        if let Some(request) = request {
+            self.handle(request, &context);
+            changed = true;
+        }
+
+        let progress = self.progress.lock().ok().and_then(|mut p| p.take());
+        if let Some(progress) = progress {
+            if !self.cancel.load(Ordering::Relaxed) {
+                self.progress_text.set(progress);
+            }
+        }
+
+        let result = self.result.lock().ok().and_then(|mut r| r.take());
+        if let Some(result) = result {
+            self.finish(result);
+            changed = true;
+        }
+
+        if changed {
+            self.rebuild();
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,254 @@
+//! Feedback when transfers end
+//!
+//! Depending on the Preferences, a finished or failed daemon job plays an event
+//! sound and asks for the user's attention: on X11 the window is marked as
+//! demanding attention (taskbar urgency); on Wayland, where clients can't do that
+//! on their own, a desktop notification is shown instead.
+//!
+//! Also shows notifications with an action, such as the offer to open removable
+//! media that was just mounted, and summaries of maintenance done in the
+//! background.
+
+use crate::settings;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ClientMessageEvent, ConnectionExt as _, EventMask};
+use x11rb::rust_connection::RustConnection;
+
+/// Freedesktop sound theme files used when `canberra-gtk-play` isn't installed
+const SOUND_DIR: &str = "/usr/share/sounds/freedesktop/stereo";
+/// How long a notification with an action is waited on before giving up
+const ACTION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// `_NET_WM_STATE` client message action adding a state
+const NET_WM_STATE_ADD: u32 = 1;
+/// Source indication of a normal application in EWMH client messages
+const SOURCE_APPLICATION: u32 = 1;
+
+/// React to a daemon job that finished (`error` is `None`) or failed.
+pub fn transfer_ended(description: &str, error: Option<&str>) {
+    let settings = settings::get();
+    if settings.completion_sound {
+        play_sound(if error.is_some() {
+            "dialog-error"
+        } else {
+            "complete"
+        });
+    }
+    if settings.completion_attention {
+        let summary = if error.is_some() {
+            "Transfer failed"
+        } else {
+            "Transfer finished"
+        };
+        let body = match error {
+            Some(error) => format!("{}: {}", description, error),
+            None => description.to_string(),
+        };
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        let demanded = !wayland
+            && demand_attention()
+                .inspect_err(|e| log::debug!("{}", e))
+                .is_ok();
+        if !demanded {
+            let urgency = if error.is_some() { 2 } else { 1 };
+            tokio::spawn(async move {
+                if let Err(e) = notify(summary, &body, urgency).await {
+                    log::warn!("{}", e);
+                }
+            });
+        }
+    }
+}
+
+/// Show a low-urgency notification without waiting for it.
+pub fn inform(summary: &'static str, body: String) {
+    tokio::spawn(async move {
+        if let Err(e) = notify(summary, &body, 0).await {
+            log::warn!("{}", e);
+        }
+    });
+}
+
+/// Play an event sound from the sound theme without waiting for it.
+fn play_sound(id: &'static str) {
+    std::thread::spawn(move || {
+        let played = Command::new("canberra-gtk-play")
+            .args(["-i", id, "-d", "fileman"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if !played {
+            let file = format!("{}/{}.oga", SOUND_DIR, id);
+            if let Err(e) = Command::new("paplay")
+                .arg(&file)
+                .stderr(Stdio::null())
+                .status()
+            {
+                log::debug!("Failed to play {}: {}", file, e);
+            }
+        }
+    });
+}
+
+fn attention_error(e: impl std::fmt::Display) -> String {
+    format!("Failed to request attention: {}", e)
+}
+
+/// Mark this process's top-level windows as demanding attention.
+fn demand_attention() -> Result<(), String> {
+    let (conn, screen_num) = RustConnection::connect(None).map_err(attention_error)?;
+    let root = conn.setup().roots[screen_num].root;
+    let intern = |name: &str| -> Result<u32, String> {
+        Ok(conn
+            .intern_atom(false, name.as_bytes())
+            .map_err(attention_error)?
+            .reply()
+            .map_err(attention_error)?
+            .atom)
+    };
+    let client_list = intern("_NET_CLIENT_LIST")?;
+    let wm_pid = intern("_NET_WM_PID")?;
+    let wm_state = intern("_NET_WM_STATE")?;
+    let demands_attention = intern("_NET_WM_STATE_DEMANDS_ATTENTION")?;
+
+    let windows: Vec<u32> = conn
+        .get_property(false, root, client_list, AtomEnum::WINDOW, 0, u32::MAX)
+        .map_err(attention_error)?
+        .reply()
+        .map_err(attention_error)?
+        .value32()
+        .map(|values| values.collect())
+        .unwrap_or_default();
+    let pid = std::process::id();
+    let mut found = false;
+    for window in windows {
+        let owner = conn
+            .get_property(false, window, wm_pid, AtomEnum::CARDINAL, 0, 1)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|reply| reply.value32().and_then(|mut values| values.next()));
+        if owner != Some(pid) {
+            continue;
+        }
+        let event = ClientMessageEvent::new(
+            32,
+            window,
+            wm_state,
+            [
+                NET_WM_STATE_ADD,
+                demands_attention,
+                0,
+                SOURCE_APPLICATION,
+                0,
+            ],
+        );
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )
+        .map_err(attention_error)?;
+        found = true;
+    }
+    conn.flush().map_err(attention_error)?;
+    if found {
+        Ok(())
+    } else {
+        Err("Failed to request attention: no window of this process found".to_string())
+    }
+}
+
+async fn session() -> Result<zbus::Connection, String> {
+    zbus::Connection::session()
+        .await
+        .map_err(|e| format!("Failed to connect to the session bus: {}", e))
+}
+
+/// Show a desktop notification with the given urgency (0 low, 1 normal, 2 critical).
+async fn notify(summary: &str, body: &str, urgency: u8) -> Result<(), String> {
+    let connection = session().await?;
+    send_notification(&connection, summary, body, urgency, &[]).await.map(|_| ())
+}
+
+/// Send a notification with `actions` (pairs of key and label) and return its id.
+async fn send_notification(
+    connection: &zbus::Connection,
+    summary: &str,
+    body: &str,
+    urgency: u8,
+    actions: &[&str],
+) -> Result<u32, String> {
+    let hints: HashMap<&str, zbus::zvariant::Value> = HashMap::from([("urgency", urgency.into())]);
+    let reply = connection
+        .call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                "fileman",
+                0u32,
+                "system-file-manager",
+                summary,
+                body,
+                actions,
+                hints,
+                -1i32,
+            ),
+        )
+        .await
+        .map_err(|e| format!("Failed to show notification: {}", e))?;
+    reply
+        .body()
+        .deserialize::<u32>()
+        .map_err(|e| format!("Failed to show notification: {}", e))
+}
+
+/// Show a notification with a single `action` and wait until it is clicked
+/// (`true`) or the notification goes away without it (`false`).
+pub async fn notify_with_action(summary: &str, body: &str, action: &str) -> Result<bool, String> {
+    let connection = session().await?;
+    let signal_error = |e: zbus::Error| format!("Failed to watch notifications: {}", e);
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface("org.freedesktop.Notifications")
+        .map_err(signal_error)?
+        .path("/org/freedesktop/Notifications")
+        .map_err(signal_error)?
+        .build();
+    // Subscribed before sending, so a quick click isn't missed
+    let mut signals = zbus::MessageStream::for_match_rule(rule, &connection, None)
+        .await
+        .map_err(signal_error)?;
+    let id = send_notification(&connection, summary, body, 1, &["default", action]).await?;
+
+    let wait = async {
+        while let Some(Ok(message)) = signals.next().await {
+            let header = message.header();
+            match header.member().map(|m| m.as_str()) {
+                Some("ActionInvoked") => {
+                    if let Ok((invoked, _)) = message.body().deserialize::<(u32, String)>() {
+                        if invoked == id {
+                            return true;
+                        }
+                    }
+                },
+                Some("NotificationClosed") => {
+                    if let Ok((closed, _)) = message.body().deserialize::<(u32, u32)>() {
+                        if closed == id {
+                            return false;
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+        false
+    };
+    Ok(tokio::time::timeout(ACTION_TIMEOUT, wait).await.unwrap_or(false))
+}
@@ -0,0 +1,82 @@
+//! Sizes of the folders in the shown folder
+//!
+//! Computed on a blocking worker through the persistent size cache of the widgets
+//! crate, so unchanged trees aren't read again, and shown in the Size column
+//! when enabled. Sizes appear one by one as they are computed; folders still
+//! being measured show a spinner.
+
+use nptk_fileman_widgets::dir_size_cache;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+#[derive(Default)]
+struct Snapshot {
+    dir: PathBuf,
+    sizes: HashMap<PathBuf, u64>,
+}
+
+/// Shared, lazily computed folder sizes for one folder at a time.
+#[derive(Clone, Default)]
+pub struct FolderSizeTracker {
+    snapshot: Arc<RwLock<Snapshot>>,
+    /// Bumped for every refresh so work for a folder left in the meantime stops
+    generation: Arc<AtomicU64>,
+    changed: Arc<AtomicBool>,
+}
+
+impl FolderSizeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn size(&self, path: &Path) -> Option<u64> {
+        self.snapshot.read().ok()?.sizes.get(path).copied()
+    }
+
+    /// Size lookup for the Size column, `None` for folders not measured yet.
+    pub fn provider(&self) -> impl Fn(&Path) -> Option<u64> + Send + Sync + 'static {
+        let tracker = self.clone();
+        move |path| tracker.size(path)
+    }
+
+    /// Compute the sizes of the folders in `dir` in the background.
+    pub fn refresh(&self, dir: PathBuf) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Ok(mut snapshot) = self.snapshot.write() {
+            *snapshot = Snapshot {
+                dir: dir.clone(),
+                sizes: HashMap::new(),
+            };
+        }
+        let tracker = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                return;
+            };
+            let folders = entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                .map(|entry| entry.path());
+            for folder in folders {
+                if tracker.generation.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                let size = dir_size_cache::directory_size(&folder);
+                if let Ok(mut snapshot) = tracker.snapshot.write() {
+                    if snapshot.dir != dir {
+                        return;
+                    }
+                    snapshot.sizes.insert(folder, size);
+                }
+                tracker.changed.store(true, Ordering::SeqCst);
+            }
+        });
+    }
+
+    /// Whether sizes changed since the last call (the view needs a redraw).
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::SeqCst)
+    }
+}
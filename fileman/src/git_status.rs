@@ -0,0 +1,266 @@
+//! Git status of the entries in the shown folder
+//!
+//! When the folder is inside a git work tree, each entry gets a status (for folders,
+//! the most important status of anything below them). Statuses are computed on a
+//! blocking worker with libgit2 and shown as a "Git" column and as icon emblems.
+//!
+//! Entries ignored by git, or matching a pattern in the global ignore list
+//! (`$XDG_CONFIG_HOME/fileman/ignore`, one glob per line), can be drawn dimmed.
+
+use nptk::core::model::ModelData;
+use nptk::core::vg::peniko::Color;
+use nptk_fileman_widgets::file_list::Emblem;
+use nptk_fileman_widgets::file_list::model_adapter::ColumnProvider;
+use nptk_fileman_widgets::xdg;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Status of an entry, ordered by importance (folders show the highest below them).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitStatus {
+    Ignored,
+    Untracked,
+    Staged,
+    Modified,
+    Conflicted,
+}
+
+impl GitStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            GitStatus::Ignored => "Ignored",
+            GitStatus::Untracked => "Untracked",
+            GitStatus::Staged => "Staged",
+            GitStatus::Modified => "Modified",
+            GitStatus::Conflicted => "Conflicted",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            GitStatus::Ignored => Color::from_rgb8(0x9a, 0x9a, 0x9a),
+            GitStatus::Untracked => Color::from_rgb8(0x3d, 0x8b, 0xd9),
+            GitStatus::Staged => Color::from_rgb8(0x3c, 0xa3, 0x4f),
+            GitStatus::Modified => Color::from_rgb8(0xe0, 0x8e, 0x1b),
+            GitStatus::Conflicted => Color::from_rgb8(0xd9, 0x3b, 0x3b),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Snapshot {
+    dir: PathBuf,
+    statuses: HashMap<PathBuf, GitStatus>,
+}
+
+/// Shared, lazily refreshed git statuses for one folder at a time.
+#[derive(Clone, Default)]
+pub struct GitStatusTracker {
+    snapshot: Arc<RwLock<Snapshot>>,
+    /// Bumped for every refresh so results for a folder left in the meantime are dropped
+    generation: Arc<AtomicU64>,
+    changed: Arc<AtomicBool>,
+    dim_ignored: Arc<AtomicBool>,
+    ignore_patterns: Arc<Vec<String>>,
+}
+
+impl GitStatusTracker {
+    pub fn new() -> Self {
+        Self {
+            ignore_patterns: Arc::new(load_ignore_patterns()),
+            ..Default::default()
+        }
+    }
+
+    /// Draw ignored entries dimmed.
+    pub fn set_dim_ignored(&self, dim: bool) {
+        self.dim_ignored.store(dim, Ordering::SeqCst);
+    }
+
+    /// Whether an entry is ignored by git or by the global ignore list.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        if self.status(path) == Some(GitStatus::Ignored) {
+            return true;
+        }
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy()) else {
+            return false;
+        };
+        self.ignore_patterns.iter().any(|pattern| glob_match(pattern, &name))
+    }
+
+    /// Dim provider for the file list (active while dimming is enabled).
+    pub fn dim_provider(&self) -> impl Fn(&Path) -> bool + Send + Sync + 'static {
+        let tracker = self.clone();
+        move |path| tracker.dim_ignored.load(Ordering::SeqCst) && tracker.is_ignored(path)
+    }
+
+    /// Status of an entry of the current folder.
+    pub fn status(&self, path: &Path) -> Option<GitStatus> {
+        self.snapshot.read().ok()?.statuses.get(path).copied()
+    }
+
+    /// The "Git" column for the Table view.
+    pub fn column(&self) -> ColumnProvider {
+        let tracker = self.clone();
+        ColumnProvider::new("Git", move |entry| {
+            ModelData::String(tracker.status(&entry.path).map(GitStatus::label).unwrap_or("").to_string())
+        })
+    }
+
+    /// Emblem provider for the file list icons.
+    pub fn emblem_provider(&self) -> impl Fn(&Path) -> Option<Emblem> + Send + Sync + 'static {
+        let tracker = self.clone();
+        move |path| {
+            // Ignored files are common (build output); don't mark them
+            tracker
+                .status(path)
+                .filter(|status| *status != GitStatus::Ignored)
+                .map(|status| Emblem::new(status.color()))
+        }
+    }
+
+    /// Recompute statuses for `dir` in the background.
+    pub fn refresh(&self, dir: PathBuf) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let tracker = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let statuses = compute(&dir);
+            if tracker.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            if let Ok(mut snapshot) = tracker.snapshot.write() {
+                let unchanged = snapshot.dir == dir && snapshot.statuses == statuses;
+                *snapshot = Snapshot { dir, statuses };
+                if !unchanged {
+                    tracker.changed.store(true, Ordering::SeqCst);
+                }
+            }
+        });
+    }
+
+    /// Whether statuses changed since the last call (the view needs a redraw).
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::SeqCst)
+    }
+}
+
+fn load_ignore_patterns() -> Vec<String> {
+    let config_home = xdg::config_home();
+    std::fs::read_to_string(config_home.join("fileman/ignore"))
+        .map(|content| {
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Match a file name against a glob with `*` and `?`.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p + 1, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(feature = "git")]
+fn classify(status: git2::Status) -> Option<GitStatus> {
+    use git2::Status;
+    if status.is_conflicted() {
+        Some(GitStatus::Conflicted)
+    } else if status.intersects(Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE) {
+        Some(GitStatus::Modified)
+    } else if status.intersects(
+        Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED | Status::INDEX_RENAMED | Status::INDEX_TYPECHANGE,
+    ) {
+        Some(GitStatus::Staged)
+    } else if status.is_wt_new() {
+        Some(GitStatus::Untracked)
+    } else if status.is_ignored() {
+        Some(GitStatus::Ignored)
+    } else {
+        None
+    }
+}
+
+/// Statuses of the direct children of `dir`, empty outside a work tree.
+#[cfg(feature = "git")]
+fn compute(dir: &Path) -> HashMap<PathBuf, GitStatus> {
+    let mut result = HashMap::new();
+    let Ok(repo) = git2::Repository::discover(dir) else {
+        return result;
+    };
+    let (Some(workdir), Ok(canonical_dir)) = (repo.workdir(), dir.canonicalize()) else {
+        return result;
+    };
+    let Ok(workdir) = workdir.canonicalize() else {
+        return result;
+    };
+
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(true)
+        .include_ignored(true)
+        .recurse_untracked_dirs(false)
+        .recurse_ignored_dirs(false);
+    if let Ok(relative) = canonical_dir.strip_prefix(&workdir) {
+        if !relative.as_os_str().is_empty() {
+            options.pathspec(relative);
+        }
+    }
+    let statuses = match repo.statuses(Some(&mut options)) {
+        Ok(statuses) => statuses,
+        Err(e) => {
+            log::warn!("Failed to read git status of {:?}: {}", dir, e);
+            return result;
+        },
+    };
+
+    for entry in statuses.iter() {
+        let (Some(relative), Some(status)) = (entry.path(), classify(entry.status())) else {
+            continue;
+        };
+        // Untracked and ignored folders are reported with a trailing slash
+        let full = workdir.join(relative.trim_end_matches('/'));
+        let Some(first) = full.strip_prefix(&canonical_dir).ok().and_then(|p| p.components().next()) else {
+            continue;
+        };
+        let child = dir.join(first);
+        // An ignored file doesn't make its folder ignored
+        if status == GitStatus::Ignored && canonical_dir.join(first) != full {
+            continue;
+        }
+        result
+            .entry(child)
+            .and_modify(|current: &mut GitStatus| *current = (*current).max(status))
+            .or_insert(status);
+    }
+    result
+}
+
+#[cfg(not(feature = "git"))]
+fn compute(_dir: &Path) -> HashMap<PathBuf, GitStatus> {
+    HashMap::new()
+}
@@ -0,0 +1,321 @@
+//! Headless file operations (`fileman cp/mv/rm/trash/sync/undo`)
+//!
+//! Runs the shared operation engine with a terminal progress bar. Conflicts are
+//! answered interactively unless a policy is given with `--on-conflict`, and so
+//! are items that fail.
+
+use crate::cli::Command;
+use crate::operations::engine::{self, Conflict, ItemError, Operation, OperationEvent, OperationKind, Progress};
+use crate::operations::{sync, throttle, undo};
+use crate::settings;
+use nptk_fileman_widgets::conflict_dialog::{ConflictResolution, OverwritePolicy};
+use nptk_fileman_widgets::error_dialog::ErrorAction;
+use nptk_fileman_widgets::sizes;
+use std::io::{IsTerminal, Write};
+use tokio::sync::mpsc;
+
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// Run a subcommand and return the process exit code.
+pub async fn run(command: Command) -> i32 {
+    let operation = match command {
+        Command::Cp { sources, destination, on_conflict, limit, dereference } => {
+            let operation = Operation::new(OperationKind::Copy { sources, destination })
+                .with_speed_limit(limit)
+                .with_follow_symlinks(dereference);
+            match on_conflict.policy() {
+                Some(policy) => operation.with_policy(policy),
+                None => operation,
+            }
+        },
+        Command::Mv { sources, destination, on_conflict, limit } => {
+            let operation = Operation::new(OperationKind::Move { sources, destination }).with_speed_limit(limit);
+            match on_conflict.policy() {
+                Some(policy) => operation.with_policy(policy),
+                None => operation,
+            }
+        },
+        Command::Rm { paths } => Operation::new(OperationKind::Delete(paths)),
+        Command::Trash { paths } => Operation::new(OperationKind::Trash(paths)),
+        Command::Sync { source, destination, delete, dry_run, limit } => {
+            if dry_run {
+                return print_sync_plan(&source, &destination, delete);
+            }
+            Operation::new(OperationKind::Sync { source, destination, mirror: delete }).with_speed_limit(limit)
+        },
+        Command::Undo => return run_undo(),
+        Command::Daemon => {
+            return match crate::daemon::run().await {
+                Ok(()) => 0,
+                Err(e) => {
+                    eprintln!("fileman: {}", e);
+                    1
+                },
+            };
+        },
+    };
+
+    // The saved limit for all transfers applies to terminal runs as well
+    throttle::set_global_limit(throttle::load_global_limit());
+    sizes::set_units(settings::get().size_units);
+    let verb = operation.verb();
+    let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+    let task = tokio::spawn(engine::spawn(operation, events_tx));
+
+    let mut last_drawn = None;
+    while let Some(event) = events_rx.recv().await {
+        match event {
+            OperationEvent::Progress(progress) => {
+                let percent = (progress.fraction() * 100.0) as usize;
+                if last_drawn != Some((percent, progress.done_items)) {
+                    draw_progress(verb, &progress);
+                    last_drawn = Some((percent, progress.done_items));
+                }
+            },
+            OperationEvent::Conflict(conflict) => {
+                eprintln!();
+                let resolution = ask_conflict(&conflict).await;
+                conflict.respond(resolution);
+                last_drawn = None;
+            },
+            OperationEvent::Error(error) => {
+                eprintln!();
+                let action = ask_error(&error).await;
+                error.respond(action);
+                last_drawn = None;
+            },
+        }
+    }
+    eprintln!();
+
+    let outcome = match task.await {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(e)) => {
+            eprintln!("fileman: {}", e);
+            return 1;
+        },
+        Err(e) => {
+            eprintln!("fileman: operation task failed: {}", e);
+            return 1;
+        },
+    };
+
+    if outcome.skipped > 0 {
+        eprintln!("Skipped {} item(s)", outcome.skipped);
+    }
+    if let Some(record) = &outcome.undo {
+        match undo::save_last(record) {
+            Ok(()) => eprintln!("Undo with: fileman undo"),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+    if outcome.cancelled {
+        eprintln!("Cancelled");
+        return 1;
+    }
+    for error in &outcome.errors {
+        eprintln!("fileman: {}", error);
+    }
+    if outcome.errors.is_empty() { 0 } else { 1 }
+}
+
+fn run_undo() -> i32 {
+    let Some(record) = undo::take_last() else {
+        eprintln!("fileman: nothing to undo");
+        return 1;
+    };
+    let step = record.undo();
+    match step.result {
+        Ok(()) => {
+            eprintln!("Undid {}", record.describe());
+            0
+        },
+        Err(e) => {
+            eprintln!("fileman: {}", e);
+            // What is left can be undone again
+            if let Some(left) = step.left {
+                if let Err(e) = undo::save_last(&left) {
+                    eprintln!("fileman: {}", e);
+                }
+            }
+            1
+        },
+    }
+}
+
+fn draw_progress(verb: &str, progress: &Progress) {
+    let filled = (progress.fraction() * PROGRESS_BAR_WIDTH as f64) as usize;
+    let name = progress
+        .current
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let bytes = if progress.total_bytes > 0 {
+        format!(
+            " ({}/{})",
+            sizes::format(progress.done_bytes),
+            sizes::format(progress.total_bytes)
+        )
+    } else {
+        String::new()
+    };
+    let mut stderr = std::io::stderr();
+    let _ = write!(
+        stderr,
+        "\r\x1b[K[{}{}] {:3}% {}/{}{} {} {}",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled.min(PROGRESS_BAR_WIDTH)),
+        (progress.fraction() * 100.0) as usize,
+        progress.done_items,
+        progress.total_items,
+        bytes,
+        verb,
+        name
+    );
+    let _ = stderr.flush();
+}
+
+/// Ask on the terminal how to resolve a conflict. Without a terminal, skip.
+async fn ask_conflict(conflict: &Conflict) -> ConflictResolution {
+    if !std::io::stdin().is_terminal() {
+        eprintln!("{} exists, skipping (use --on-conflict to choose)", conflict.destination.display());
+        return ConflictResolution { policy: OverwritePolicy::Skip, apply_to_all: false };
+    }
+
+    let prompt = format!(
+        "{} already exists.\n[o]verwrite, [s]kip, [k]eep both, [n]ewer only, [c]ancel{}: ",
+        conflict.destination.display(),
+        if conflict.remaining > 0 {
+            format!(" (uppercase applies to all {} remaining)", conflict.remaining)
+        } else {
+            String::new()
+        }
+    );
+
+    loop {
+        eprint!("{}", prompt);
+        let _ = std::io::stderr().flush();
+        let line = tokio::task::spawn_blocking(|| {
+            let mut line = String::new();
+            match std::io::stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => None, // EOF or unreadable input
+                Ok(_) => Some(line),
+            }
+        })
+        .await;
+        let Ok(Some(line)) = line else {
+            return ConflictResolution { policy: OverwritePolicy::Cancel, apply_to_all: true };
+        };
+
+        if let Some(resolution) = conflict_answer(&line) {
+            return resolution;
+        }
+    }
+}
+
+/// The resolution typed at the conflict prompt; uppercase applies to all
+/// remaining conflicts.
+fn conflict_answer(line: &str) -> Option<ConflictResolution> {
+    let answer = line.trim();
+    let apply_to_all = answer.chars().next().is_some_and(|c| c.is_uppercase());
+    let policy = match answer.to_lowercase().as_str() {
+        "o" => OverwritePolicy::Overwrite,
+        "s" => OverwritePolicy::Skip,
+        "k" => OverwritePolicy::KeepBoth,
+        "n" => OverwritePolicy::OverwriteIfNewer,
+        "c" => OverwritePolicy::Cancel,
+        _ => return None,
+    };
+    Some(ConflictResolution { policy, apply_to_all })
+}
+
+/// Ask on the terminal what to do about a failed item. Without a terminal, skip
+/// it; the failure is reported at the end.
+async fn ask_error(error: &ItemError) -> ErrorAction {
+    if !std::io::stdin().is_terminal() {
+        return ErrorAction::Skip;
+    }
+
+    let prompt = format!(
+        "{}\n[r]etry, [s]kip, {}[a]bort: ",
+        error.message,
+        if error.remaining > 0 { "s[k]ip all, " } else { "" }
+    );
+
+    loop {
+        eprint!("{}", prompt);
+        let _ = std::io::stderr().flush();
+        let line = tokio::task::spawn_blocking(|| {
+            let mut line = String::new();
+            match std::io::stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => None, // EOF or unreadable input
+                Ok(_) => Some(line),
+            }
+        })
+        .await;
+        let Ok(Some(line)) = line else {
+            return ErrorAction::Abort;
+        };
+
+        if let Some(action) = error_answer(&line, error.remaining) {
+            return action;
+        }
+    }
+}
+
+/// The action typed at the error prompt; skipping all is only offered with
+/// `remaining` items left.
+fn error_answer(line: &str, remaining: usize) -> Option<ErrorAction> {
+    match line.trim().to_lowercase().as_str() {
+        "r" => Some(ErrorAction::Retry),
+        "s" => Some(ErrorAction::Skip),
+        "k" if remaining > 0 => Some(ErrorAction::SkipAll),
+        "a" => Some(ErrorAction::Abort),
+        _ => None,
+    }
+}
+
+/// `fileman sync --dry-run`: list the planned actions without running them.
+fn print_sync_plan(source: &std::path::Path, destination: &std::path::Path, mirror: bool) -> i32 {
+    match sync::plan(source, destination, mirror) {
+        Ok(plan) => {
+            for action in &plan.actions {
+                println!("{}", action.describe());
+            }
+            println!("{} to copy, {} to delete", plan.copies(), plan.deletions());
+            0
+        },
+        Err(e) => {
+            eprintln!("fileman: {}", e);
+            1
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_conflict_answers() {
+        let answer = |policy, apply_to_all| Some(ConflictResolution { policy, apply_to_all });
+        assert_eq!(conflict_answer("o\n"), answer(OverwritePolicy::Overwrite, false));
+        assert_eq!(conflict_answer("  S  \n"), answer(OverwritePolicy::Skip, true));
+        assert_eq!(conflict_answer("k"), answer(OverwritePolicy::KeepBoth, false));
+        assert_eq!(conflict_answer("N"), answer(OverwritePolicy::OverwriteIfNewer, true));
+        assert_eq!(conflict_answer("c"), answer(OverwritePolicy::Cancel, false));
+        for line in ["", "\n", "x", "overwrite", "os"] {
+            assert_eq!(conflict_answer(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn reads_error_answers() {
+        assert_eq!(error_answer("r\n", 0), Some(ErrorAction::Retry));
+        assert_eq!(error_answer("S", 0), Some(ErrorAction::Skip));
+        assert_eq!(error_answer("k", 3), Some(ErrorAction::SkipAll));
+        assert_eq!(error_answer("k", 0), None);
+        assert_eq!(error_answer(" a ", 0), Some(ErrorAction::Abort));
+        assert_eq!(error_answer("retry", 1), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! Symbolic links
+//!
+//! Shows where links point, in the optional "Link Target" column, and adds
+//! actions for a selected link to its context menu: follow it, open the folder
+//! of its target and change its target.
+
+mod dialog;
+
+pub use dialog::EditLinkDialog;
+
+use crate::menus;
+use nptk::core::menu::{MenuCommand, MenuItem};
+use nptk::core::model::ModelData;
+use nptk::prelude::*;
+use nptk_fileman_widgets::file_list::model_adapter::ColumnProvider;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Action on a link chosen from its context menu, carried out by the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkRequest {
+    /// Go into the target folder, or open the target file
+    Follow(PathBuf),
+    /// Show the target in its folder
+    OpenTargetFolder(PathBuf),
+    /// Change where the link points
+    Edit(PathBuf),
+}
+
+/// The target of `link` as stored in it, `None` if `link` isn't a link.
+pub fn target(link: &Path) -> Option<PathBuf> {
+    fs::read_link(link).ok()
+}
+
+/// The target of `link` as a path usable from anywhere (relative targets are
+/// relative to the folder of the link).
+pub fn resolved_target(link: &Path) -> Option<PathBuf> {
+    let target = target(link)?;
+    Some(match link.parent() {
+        Some(parent) => parent.join(target),
+        None => target,
+    })
+}
+
+/// Whether `link` points to something that doesn't exist.
+pub fn is_broken(link: &Path) -> bool {
+    fs::metadata(link).is_err()
+}
+
+/// Make `link` point to `target`. The link is replaced atomically, so it never
+/// goes missing if this fails halfway.
+pub fn set_target(link: &Path, target: &Path) -> Result<(), String> {
+    if target.as_os_str().is_empty() {
+        return Err("The link target is empty".to_string());
+    }
+    let parent = link.parent().ok_or_else(|| format!("{}: not a link in a folder", link.display()))?;
+    let name = link.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let temporary = parent.join(format!(".{}.fileman-link", name));
+    std::os::unix::fs::symlink(target, &temporary)
+        .map_err(|e| format!("Failed to create link in {}: {}", parent.display(), e))?;
+    fs::rename(&temporary, link).map_err(|e| {
+        let _ = fs::remove_file(&temporary);
+        format!("Failed to replace {}: {}", link.display(), e)
+    })
+}
+
+/// The "Link Target" column for the Table view.
+pub fn column() -> ColumnProvider {
+    ColumnProvider::new("Link Target", |entry| {
+        ModelData::String(match target(&entry.path) {
+            Some(target) if is_broken(&entry.path) => format!("{} (broken)", target.display()),
+            Some(target) => target.display().to_string(),
+            None => String::new(),
+        })
+    })
+}
+
+/// Builds the link entries of the context menu and keeps the chosen action for
+/// the window, which can navigate and open dialogs.
+#[derive(Clone, Default)]
+pub struct LinkActions {
+    request: Arc<Mutex<Option<LinkRequest>>>,
+}
+
+impl LinkActions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn item(&self, id: u32, label: &str, request: LinkRequest) -> MenuItem {
+        let pending = self.request.clone();
+        MenuItem::new(MenuCommand::Custom(id), label).with_action(move || {
+            if let Ok(mut pending) = pending.lock() {
+                *pending = Some(request.clone());
+            }
+            Update::DRAW
+        })
+    }
+
+    /// Entries for `paths`: none unless a single link is selected.
+    pub fn menu_items(&self, paths: &[PathBuf]) -> Vec<MenuItem> {
+        let [link] = paths else {
+            return Vec::new();
+        };
+        if target(link).is_none() {
+            return Vec::new();
+        }
+        let mut items = Vec::new();
+        if !is_broken(link) {
+            items.push(self.item(
+                menus::FOLLOW_LINK_ID,
+                "Follow Link",
+                LinkRequest::Follow(link.clone()),
+            ));
+            items.push(self.item(
+                menus::OPEN_LINK_TARGET_FOLDER_ID,
+                "Open Target's Folder",
+                LinkRequest::OpenTargetFolder(link.clone()),
+            ));
+        }
+        items.push(self.item(
+            menus::EDIT_LINK_TARGET_ID,
+            "Edit Link Target...",
+            LinkRequest::Edit(link.clone()),
+        ));
+        items
+    }
+
+    /// The action chosen since the last call.
+    pub fn take_request(&self) -> Option<LinkRequest> {
+        self.request.lock().ok().and_then(|mut r| r.take())
+    }
+}
@@ -0,0 +1,159 @@
+//! Popup for changing the target of a link.
+
+use super::{is_broken, set_target, target};
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Text input with the current target of a link, and Apply.
+///
+/// Relative targets are kept relative (to the folder of the link).
+pub struct EditLinkDialog {
+    link: PathBuf,
+    inner: Container,
+    target_text: StateSignal<String>,
+    message_text: StateSignal<String>,
+    apply_requested: Arc<Mutex<bool>>,
+    signals_hooked: bool,
+}
+
+impl EditLinkDialog {
+    pub fn new(link: PathBuf) -> Self {
+        let current = target(&link).map(|t| t.to_string_lossy().to_string()).unwrap_or_default();
+        let message = if is_broken(&link) {
+            "The current target doesn't exist".to_string()
+        } else {
+            String::new()
+        };
+        let mut dialog = Self {
+            link,
+            inner: Container::new(vec![]),
+            target_text: StateSignal::new(current),
+            message_text: StateSignal::new(message),
+            apply_requested: Arc::new(Mutex::new(false)),
+            signals_hooked: false,
+        };
+        dialog.rebuild();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        let name = self
+            .link
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let title = format!("Target of {}", name);
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), &title, (460, 180), (300, 200));
+    }
+
+    fn rebuild(&mut self) {
+        let pending = self.apply_requested.clone();
+        let apply = Button::new(Text::new("Apply".to_string())).with_on_pressed(MaybeSignal::signal(
+            Box::new(EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = true;
+                }
+                Update::DRAW
+            })),
+        ));
+        let close = Button::new(Text::new("Close".to_string())).with_on_pressed(MaybeSignal::value(Update::DRAW));
+
+        self.inner = Container::new(vec![
+            Box::new(
+                TextInput::new()
+                    .with_text_signal(self.target_text.clone())
+                    .with_placeholder("Target path".to_string())
+                    .with_layout_style(LayoutStyle {
+                        size: Vector2::new(Dimension::percent(1.0), Dimension::length(30.0)),
+                        ..Default::default()
+                    }),
+            ),
+            Box::new(Text::new(self.message_text.maybe()).with_font_size(12.0)),
+            Box::new(
+                Container::new(vec![Box::new(close), Box::new(apply)]).with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Row,
+                    gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                    justify_content: Some(JustifyContent::FlexEnd),
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    ..Default::default()
+                }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(10.0)),
+            ..Default::default()
+        });
+    }
+
+    fn apply(&mut self) {
+        let target = PathBuf::from(self.target_text.get().trim());
+        match set_target(&self.link, &target) {
+            Ok(()) if is_broken(&self.link) => {
+                self.message_text.set("Changed; the new target doesn't exist".to_string())
+            },
+            Ok(()) => self.message_text.set("Changed".to_string()),
+            Err(e) => {
+                log::error!("{}", e);
+                self.message_text.set(e);
+            },
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for EditLinkDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.target_text);
+            context.hook_signal(&mut self.message_text);
+            self.signals_hooked = true;
+        }
+
+        let requested = self
+            .apply_requested
+            .lock()
+            .map(|mut r| std::mem::take(&mut *r))
+            .unwrap_or(false);
+        if requested {
+            self.apply();
+            update.insert(Update::DRAW);
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
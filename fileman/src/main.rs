@@ -1,27 +1,97 @@
+mod accessibility;
+mod activation;
 mod app;
+mod bench;
+mod bus;
+mod cli;
+mod clipboard;
+mod compare;
+mod daemon;
+mod dbus;
+mod devices;
+mod feedback;
+mod folder_sizes;
+mod git_status;
+mod headless;
+mod links;
 mod navigation;
+mod palette;
+mod remote;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod search;
+mod send_to;
+mod settings;
+mod startup;
+mod templates;
+mod trash_cleanup;
+mod uri;
+mod view_settings;
 mod window;
+mod window_state;
 mod toolbar;
+mod transfer_queue;
+mod tab_bar;
 mod menus;
 mod operations;
+mod owners;
+mod plugins;
+mod read_only;
 
+use clap::Parser;
+use dbus::ExternalRequest;
 use std::path::PathBuf;
+use tokio::sync::mpsc;
 
 #[tokio::main]
 async fn main() {
     //env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
     // Parse command line arguments
-    let mut args = std::env::args().skip(1);
-    let initial_location = args.next()
-        .map(PathBuf::from)
-        .or_else(|| std::env::current_dir().ok())
-        .or_else(|| {
-            std::env::var("HOME")
-                .ok()
-                .map(PathBuf::from)
-        })
-        .unwrap_or_else(|| PathBuf::from("/"));
+    let mut cli = cli::Cli::parse();
+    if let Some(command) = cli.command.take() {
+        std::process::exit(headless::run(command).await);
+    }
+    if let Some(dir) = cli.bench.take() {
+        std::process::exit(bench::run(&dir, cli.bench_runs).await);
+    }
+    if cli.perf {
+        nptk_fileman_widgets::perf::enable();
+    }
+    let (folders, select) = cli.resolve();
 
-    app::FilemanApp::run(initial_location);
+    // Open the locations in the running instance instead of starting a second process
+    if !cli.new_window && !cli.perf {
+        let (mut forward_folders, forward_select) = (folders.clone(), select.clone());
+        if forward_folders.is_empty() && forward_select.is_empty() {
+            forward_folders.extend(std::env::current_dir().ok());
+        }
+        if dbus::forward_to_running_instance(&forward_folders, &forward_select).await {
+            return;
+        }
+    }
+
+    // One tab per folder; files to select get a tab for their folder, shown first
+    let mut tabs = Vec::new();
+    tabs.extend(select.first().and_then(|p| p.parent()).map(PathBuf::from));
+    tabs.extend(folders);
+    if tabs.is_empty() {
+        tabs.push(startup::initial_location(&settings::get().startup_location));
+    }
+
+    // Serve D-Bus requests from other applications and later launches
+    let (external_tx, external_rx) = mpsc::unbounded_channel();
+    if !select.is_empty() {
+        let _ = external_tx.send(ExternalRequest::ShowItems(select));
+    }
+    let single_instance = !cli.new_window;
+    tokio::spawn(async move {
+        match dbus::serve(external_tx, single_instance).await {
+            // Keep the connection alive for the lifetime of the process
+            Ok(_connection) => std::future::pending::<()>().await,
+            Err(e) => log::warn!("{}", e),
+        }
+    });
+
+    app::FilemanApp::run(tabs, external_rx);
 }
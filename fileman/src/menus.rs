@@ -4,3 +4,58 @@ use nptk::prelude::*;
 pub fn build_menus() {
     // Menu implementation will be added later
 }
+
+// Ids of the entries the window adds to the file list's context menu. Each
+// entry, and each range of numbered entries, has its own constant here so no
+// two features take the same id. The widgets' own entries are listed in
+// nptk_fileman_widgets::menu_ids.
+
+pub use nptk_fileman_widgets::menu_ids::{RANGE_LENGTH, range_id};
+
+pub const CHANGE_PERMISSIONS_ID: u32 = 0x8001;
+pub const PASTE_HARD_LINKS_ID: u32 = 0x8002;
+pub const REFRESH_ID: u32 = 0x8003;
+pub const WRITE_TO_DRIVE_ID: u32 = 0x8004;
+pub const RUN_SCRIPT_ID: u32 = 0x8005;
+pub const COPY_PATH_ID: u32 = 0x8006;
+pub const COPY_NAME_ID: u32 = 0x8007;
+pub const OPEN_ITEM_LOCATION_ID: u32 = 0x8008;
+pub const DUPLICATE_ID: u32 = 0x8009;
+pub const COPY_TO_ID: u32 = 0x800A;
+pub const MOVE_TO_ID: u32 = 0x800B;
+pub const UNDO_ID: u32 = 0x800C;
+pub const REDO_ID: u32 = 0x800D;
+pub const FOLLOW_LINK_ID: u32 = 0x800E;
+pub const OPEN_LINK_TARGET_FOLDER_ID: u32 = 0x800F;
+pub const EDIT_LINK_TARGET_ID: u32 = 0x8010;
+
+pub const SEND_TO_ID: u32 = 0x8100;
+pub const SEND_BY_EMAIL_ID: u32 = 0x8101;
+pub const SEND_TO_BLUETOOTH_ID: u32 = 0x8102;
+pub const SHARE_ON_NETWORK_ID: u32 = 0x8103;
+/// First of the Bluetooth devices, one id each
+pub const BLUETOOTH_DEVICE_IDS: u32 = 0x8110;
+
+pub const NEW_FROM_TEMPLATE_ID: u32 = 0x8200;
+pub const NO_TEMPLATES_ID: u32 = 0x8201;
+pub const MANAGE_TEMPLATES_ID: u32 = 0x8202;
+/// First of the templates, one id each
+pub const TEMPLATE_IDS: u32 = 0x8210;
+
+/// First of the plugin actions, one id each
+pub const PLUGIN_ACTION_IDS: u32 = 0x8300;
+
+// Toolbar's sort menu
+/// First of the sort columns, one id each
+pub const SORT_BY_IDS: u32 = 0x5000;
+pub const DIM_IGNORED_ID: u32 = 0x5010;
+pub const SHOW_HIDDEN_ID: u32 = 0x5011;
+pub const RESET_LAYOUT_ID: u32 = 0x5012;
+pub const FOLDER_THUMBNAILS_ID: u32 = 0x5013;
+
+// Tab context menu
+pub const COMPARE_TABS_ID: u32 = 0x7001;
+pub const CLOSE_TAB_ID: u32 = 0x7002;
+pub const SYNC_TABS_ID: u32 = 0x7003;
+pub const OPEN_TAB_IN_NEW_WINDOW_ID: u32 = 0x7004;
+pub const MOVE_TAB_TO_MAIN_WINDOW_ID: u32 = 0x7005;
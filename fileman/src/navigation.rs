@@ -1,24 +1,101 @@
+//! Tabs and their back/forward history
+//!
+//! Locations are kept as given, without checking them: a tab's history can
+//! hold any path the file list is able to show. Fileman can't open archives
+//! as folders yet; once it can, locations inside them (e.g.
+//! `/home/me/a.zip!/docs`) need no special handling here, as `Path::parent`
+//! already leads from one out to the folder holding the archive.
+
 use nptk::core::signal::{state::StateSignal, Signal};
+use nptk_fileman_widgets::file_list::{FileListSort, FileListViewMode};
 use std::path::PathBuf;
 
-/// Manages navigation state including path history
-pub struct NavigationState {
+/// Maximum number of remembered recent locations
+const MAX_RECENT_LOCATIONS: usize = 20;
+
+/// How a tab shows its folders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TabView {
+    pub view_mode: FileListViewMode,
+    pub sort: FileListSort,
+    pub show_hidden: bool,
+}
+
+/// Back/forward history of a single tab
+#[derive(Debug, Clone)]
+struct TabHistory {
+    /// Identifies the tab while tabs are opened, closed and moved
+    id: u64,
     /// Path history for back/forward navigation
     path_history: Vec<PathBuf>,
     /// Current position in history
     history_position: usize,
+    /// View settings, once the tab has been shown
+    view: Option<TabView>,
+}
+
+impl TabHistory {
+    fn new(id: u64, path: PathBuf) -> Self {
+        Self {
+            id,
+            path_history: vec![path],
+            history_position: 0,
+            view: None,
+        }
+    }
+
+    fn current(&self) -> Option<&PathBuf> {
+        self.path_history.get(self.history_position)
+    }
+}
+
+/// Manages navigation state including tabs and their path history
+///
+/// All navigation methods operate on the active tab; `current_path` always
+/// reflects the active tab's location.
+pub struct NavigationState {
+    /// Open tabs (never empty)
+    tabs: Vec<TabHistory>,
+    /// Index of the active tab
+    active_tab: usize,
     /// Current path (reactive signal)
     current_path: StateSignal<PathBuf>,
+    /// Current location of every tab, in tab order (reactive signal)
+    tab_paths: StateSignal<Vec<PathBuf>>,
+    /// Index of the active tab (reactive signal)
+    active_tab_signal: StateSignal<usize>,
+    /// Previously visited locations, most recent first (reactive signal)
+    recent_locations: StateSignal<Vec<PathBuf>>,
+    /// Id of the next opened tab
+    next_tab_id: u64,
 }
 
 impl NavigationState {
     /// Create a new navigation state with an initial path
     pub fn new(initial_path: PathBuf) -> Self {
-        let current_path = StateSignal::new(initial_path.clone());
+        Self::with_tabs(vec![initial_path])
+    }
+
+    /// Create a navigation state with one tab per path; the first tab is active
+    pub fn with_tabs(paths: Vec<PathBuf>) -> Self {
+        let mut paths = paths;
+        if paths.is_empty() {
+            paths.push(PathBuf::from("/"));
+        }
+        let current_path = StateSignal::new(paths[0].clone());
+        let next_tab_id = paths.len() as u64;
         Self {
-            path_history: vec![initial_path],
-            history_position: 0,
+            tab_paths: StateSignal::new(paths.clone()),
+            tabs: paths
+                .into_iter()
+                .enumerate()
+                .map(|(id, path)| TabHistory::new(id as u64, path))
+                .collect(),
+            active_tab: 0,
             current_path,
+            active_tab_signal: StateSignal::new(0),
+            recent_locations: StateSignal::new(Vec::new()),
+            next_tab_id,
         }
     }
 
@@ -27,32 +104,171 @@ impl NavigationState {
         &self.current_path
     }
 
+    /// Get the tab paths signal (current location of every tab)
+    pub fn tab_paths(&self) -> &StateSignal<Vec<PathBuf>> {
+        &self.tab_paths
+    }
+
+    /// Get the active tab index signal
+    pub fn active_tab_signal(&self) -> &StateSignal<usize> {
+        &self.active_tab_signal
+    }
+
+    /// Get the recent locations signal (previously visited directories, most recent first)
+    pub fn recent_locations(&self) -> &StateSignal<Vec<PathBuf>> {
+        &self.recent_locations
+    }
+
+    /// Number of open tabs
+    pub fn tab_count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    /// Index of the active tab
+    pub fn active_tab(&self) -> usize {
+        self.active_tab
+    }
+
+    /// Id of the active tab, which stays the same while tabs are moved
+    pub fn active_tab_id(&self) -> u64 {
+        self.tab().id
+    }
+
+    /// View settings of the active tab, `None` until it has been shown
+    pub fn tab_view(&self) -> Option<TabView> {
+        self.tab().view
+    }
+
+    /// Remember the view settings of the active tab
+    pub fn set_tab_view(&mut self, view: TabView) {
+        self.tab_mut().view = Some(view);
+    }
+
+    /// Open a new tab at `path` after the active one and make it active. It
+    /// starts with the view settings of the active tab.
+    pub fn open_tab(&mut self, path: PathBuf) {
+        let previous = self.get_current_path();
+        let index = self.active_tab + 1;
+        let mut tab = TabHistory::new(self.next_tab_id, path.clone());
+        tab.view = self.tab().view;
+        self.next_tab_id += 1;
+        self.tabs.insert(index, tab);
+        self.active_tab = index;
+        self.remember_location(previous, &path);
+        self.sync_signals();
+    }
+
+    /// Close the tab at `index`. The last remaining tab cannot be closed.
+    pub fn close_tab(&mut self, index: usize) -> bool {
+        if self.tabs.len() <= 1 || index >= self.tabs.len() {
+            return false;
+        }
+        self.tabs.remove(index);
+        if self.active_tab > index || self.active_tab >= self.tabs.len() {
+            self.active_tab -= 1;
+        }
+        self.sync_signals();
+        true
+    }
+
+    /// Make the tab at `index` active
+    pub fn activate_tab(&mut self, index: usize) {
+        if index < self.tabs.len() && index != self.active_tab {
+            self.active_tab = index;
+            self.sync_signals();
+        }
+    }
+
+    /// Make the next tab active, or the previous one if `forward` is false,
+    /// wrapping around at the ends
+    pub fn cycle_tab(&mut self, forward: bool) {
+        let count = self.tabs.len();
+        let index = if forward {
+            (self.active_tab + 1) % count
+        } else {
+            (self.active_tab + count - 1) % count
+        };
+        self.activate_tab(index);
+    }
+
+    /// Move the tab at `from` to position `to`, keeping the same tab active
+    pub fn move_tab(&mut self, from: usize, to: usize) {
+        if from >= self.tabs.len() || to >= self.tabs.len() || from == to {
+            return;
+        }
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+        self.active_tab = if self.active_tab == from {
+            to
+        } else if from < self.active_tab && to >= self.active_tab {
+            self.active_tab - 1
+        } else if from > self.active_tab && to <= self.active_tab {
+            self.active_tab + 1
+        } else {
+            self.active_tab
+        };
+        self.sync_signals();
+    }
+
+    /// Update the reactive signals after the active tab or its location changed
+    fn sync_signals(&mut self) {
+        let paths: Vec<PathBuf> = self
+            .tabs
+            .iter()
+            .map(|tab| tab.current().cloned().unwrap_or_else(|| PathBuf::from("/")))
+            .collect();
+        let current = paths[self.active_tab].clone();
+        self.tab_paths.set_value(paths);
+        self.active_tab_signal.set_value(self.active_tab);
+        self.current_path.set_value(current);
+    }
+
+    fn tab(&self) -> &TabHistory {
+        &self.tabs[self.active_tab]
+    }
+
+    fn tab_mut(&mut self) -> &mut TabHistory {
+        &mut self.tabs[self.active_tab]
+    }
+
+    /// Remember `previous` as the most recent location when leaving it for `next`
+    fn remember_location(&mut self, previous: PathBuf, next: &PathBuf) {
+        let mut recent = (*self.recent_locations.get()).clone();
+        recent.retain(|p| p != &previous && p != next);
+        recent.insert(0, previous);
+        recent.truncate(MAX_RECENT_LOCATIONS);
+        self.recent_locations.set_value(recent);
+    }
+
     /// Navigate to a new path
     pub fn navigate_to(&mut self, path: PathBuf) {
         // Only add to history if it's different from current
-        let current = if self.history_position < self.path_history.len() {
-            self.path_history[self.history_position].clone()
-        } else {
+        let current = match self.tab().current() {
+            Some(current) => current.clone(),
             // Fallback to getting from signal if history is inconsistent
-            (*self.current_path.get()).clone()
+            None => (*self.current_path.get()).clone(),
         };
         
         if current != path {
+            self.remember_location(current, &path);
+            let tab = self.tab_mut();
             // Remove any history after current position
-            self.path_history.truncate(self.history_position + 1);
+            tab.path_history.truncate(tab.history_position + 1);
             // Add new path to history
-            self.path_history.push(path.clone());
-            self.history_position = self.path_history.len() - 1;
-            self.current_path.set_value(path);
+            tab.path_history.push(path);
+            tab.history_position = tab.path_history.len() - 1;
+            self.sync_signals();
         }
     }
 
     /// Navigate back in history
     pub fn go_back(&mut self) -> Option<PathBuf> {
         if self.can_go_back() {
-            self.history_position -= 1;
-            let path = self.path_history[self.history_position].clone();
-            self.current_path.set_value(path.clone());
+            let previous = self.get_current_path();
+            self.tab_mut().history_position -= 1;
+            let path = self.get_current_path();
+            self.remember_location(previous, &path);
+            self.sync_signals();
             Some(path)
         } else {
             None
@@ -62,9 +278,11 @@ impl NavigationState {
     /// Navigate forward in history
     pub fn go_forward(&mut self) -> Option<PathBuf> {
         if self.can_go_forward() {
-            self.history_position += 1;
-            let path = self.path_history[self.history_position].clone();
-            self.current_path.set_value(path.clone());
+            let previous = self.get_current_path();
+            self.tab_mut().history_position += 1;
+            let path = self.get_current_path();
+            self.remember_location(previous, &path);
+            self.sync_signals();
             Some(path)
         } else {
             None
@@ -73,31 +291,25 @@ impl NavigationState {
 
     /// Check if we can go back
     pub fn can_go_back(&self) -> bool {
-        self.history_position > 0
+        self.tab().history_position > 0
     }
 
     /// Check if we can go forward
     pub fn can_go_forward(&self) -> bool {
-        self.history_position < self.path_history.len() - 1
+        self.tab().history_position < self.tab().path_history.len() - 1
     }
 
     /// Get the current path
     pub fn get_current_path(&self) -> PathBuf {
-        if self.history_position < self.path_history.len() {
-            self.path_history[self.history_position].clone()
-        } else {
+        match self.tab().current() {
+            Some(current) => current.clone(),
             // Fallback to signal if history is inconsistent
-            (*self.current_path.get()).clone()
+            None => (*self.current_path.get()).clone(),
         }
     }
 
     /// Get parent directory
     pub fn parent_path(&self) -> Option<PathBuf> {
-        let current = if self.history_position < self.path_history.len() {
-            self.path_history[self.history_position].clone()
-        } else {
-            (*self.current_path.get()).clone()
-        };
-        current.parent().map(PathBuf::from)
+        self.get_current_path().parent().map(PathBuf::from)
     }
 }
@@ -1,6 +1,15 @@
 use std::path::PathBuf;
 use std::fs;
 
+pub mod activity;
+pub mod compress;
+pub mod engine;
+pub mod permissions;
+pub mod sync;
+pub mod throttle;
+pub mod trash;
+pub mod undo;
+
 /// Create a new directory
 pub fn create_directory(path: PathBuf) -> Result<(), String> {
     fs::create_dir(&path)
@@ -33,10 +42,3 @@ pub fn rename_path(from: PathBuf, to: PathBuf) -> Result<(), String> {
     fs::rename(&from, &to)
         .map_err(|e| format!("Failed to rename: {}", e))
 }
-
-/// Copy a file
-pub fn copy_file(from: PathBuf, to: PathBuf) -> Result<(), String> {
-    fs::copy(&from, &to)
-        .map_err(|e| format!("Failed to copy file: {}", e))?;
-    Ok(())
-}
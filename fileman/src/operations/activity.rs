@@ -0,0 +1,392 @@
+//! Activity log
+//!
+//! The daemon records every job it finishes in `$XDG_STATE_HOME/fileman/activity`,
+//! one tab-separated line per job, keeping the last [MAX_RECORDS]. The log is
+//! shown in Recent Activity and can be exported as JSON or CSV, e.g. for audits.
+
+mod dialog;
+
+pub use dialog::RecentActivityDialog;
+
+use super::engine::OperationKind;
+use chrono::{DateTime, SecondsFormat, Utc};
+use nptk_fileman_widgets::xdg;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime};
+
+/// Records kept in the log; older ones are dropped
+const MAX_RECORDS: usize = 5_000;
+
+/// When this process started, the beginning of "this session"
+static SESSION_START: LazyLock<SystemTime> = LazyLock::new(SystemTime::now);
+
+/// How a job ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Finished,
+    Cancelled,
+    Failed,
+}
+
+impl Outcome {
+    pub fn key(self) -> &'static str {
+        match self {
+            Outcome::Finished => "finished",
+            Outcome::Cancelled => "cancelled",
+            Outcome::Failed => "failed",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "finished" => Some(Outcome::Finished),
+            "cancelled" => Some(Outcome::Cancelled),
+            "failed" => Some(Outcome::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A finished job.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivityRecord {
+    pub started: SystemTime,
+    pub finished: SystemTime,
+    /// E.g. "copy" or "trash", see [operation_name]
+    pub operation: String,
+    pub sources: Vec<PathBuf>,
+    pub destination: Option<PathBuf>,
+    /// Bytes transferred (0 for operations that don't copy)
+    pub bytes: u64,
+    /// Items processed successfully and skipped because of conflicts
+    pub completed: usize,
+    pub skipped: usize,
+    pub outcome: Outcome,
+    /// Why the job failed, if it did
+    pub error: String,
+}
+
+/// Short name of the kind of operation, as recorded in the log.
+pub fn operation_name(kind: &OperationKind) -> &'static str {
+    match kind {
+        OperationKind::Copy { .. } => "copy",
+        OperationKind::Move { .. } => "move",
+        OperationKind::Delete(_) => "delete",
+        OperationKind::Trash(_) => "trash",
+        OperationKind::Sync { mirror: false, .. } => "sync",
+        OperationKind::Sync { mirror: true, .. } => "mirror",
+        OperationKind::SetPermissions { .. } => "permissions",
+        OperationKind::CreateHardLink { .. } => "hard-link",
+        OperationKind::WriteImage { .. } => "write-image",
+    }
+}
+
+/// The items an operation works on and where they go, if anywhere.
+pub fn operation_paths(kind: &OperationKind) -> (Vec<PathBuf>, Option<PathBuf>) {
+    match kind {
+        OperationKind::Copy { sources, destination }
+        | OperationKind::Move { sources, destination }
+        | OperationKind::CreateHardLink { sources, destination } => (sources.clone(), Some(destination.clone())),
+        OperationKind::Delete(paths) | OperationKind::Trash(paths) => (paths.clone(), None),
+        OperationKind::SetPermissions { paths, .. } => (paths.clone(), None),
+        OperationKind::Sync { source, destination, .. } => (vec![source.clone()], Some(destination.clone())),
+        OperationKind::WriteImage { image, device, .. } => (vec![image.clone()], Some(device.clone())),
+    }
+}
+
+/// When this session began; call at startup so it isn't the first use.
+pub fn session_start() -> SystemTime {
+    *SESSION_START
+}
+
+fn log_path() -> PathBuf {
+    xdg::state_home().join("fileman").join("activity")
+}
+
+/// Escape tabs, line breaks and backslashes, which separate fields and
+/// records; a carriage return would be taken for part of the line break.
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(field: &str) -> String {
+    let mut text = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => text.push('\t'),
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            Some(other) => text.push(other),
+            None => {},
+        }
+    }
+    text
+}
+
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl ActivityRecord {
+    /// Serialize as one line: times, operation, outcome, error, counts,
+    /// destination (empty for none), then the sources.
+    fn to_line(&self) -> String {
+        let mut fields = vec![
+            seconds(self.started).to_string(),
+            seconds(self.finished).to_string(),
+            escape(&self.operation),
+            self.outcome.key().to_string(),
+            escape(&self.error),
+            self.bytes.to_string(),
+            self.completed.to_string(),
+            self.skipped.to_string(),
+            self.destination.as_ref().map(|d| escape(&d.to_string_lossy())).unwrap_or_default(),
+        ];
+        fields.extend(self.sources.iter().map(|s| escape(&s.to_string_lossy())));
+        fields.join("\t")
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let time = |field: Option<&str>| -> Option<SystemTime> {
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(field?.parse().ok()?))
+        };
+        let started = time(fields.next())?;
+        let finished = time(fields.next())?;
+        let operation = unescape(fields.next()?);
+        let outcome = Outcome::from_key(fields.next()?)?;
+        let error = unescape(fields.next()?);
+        let bytes = fields.next()?.parse().ok()?;
+        let completed = fields.next()?.parse().ok()?;
+        let skipped = fields.next()?.parse().ok()?;
+        let destination = Some(fields.next()?).filter(|d| !d.is_empty()).map(|d| PathBuf::from(unescape(d)));
+        let sources = fields.map(|s| PathBuf::from(unescape(s))).collect();
+        Some(ActivityRecord {
+            started,
+            finished,
+            operation,
+            sources,
+            destination,
+            bytes,
+            completed,
+            skipped,
+            outcome,
+            error,
+        })
+    }
+
+    /// One line describing the job, e.g. "copy of 3 item(s) to /home/me: finished".
+    pub fn describe(&self) -> String {
+        let mut text = format!("{} of {} item(s)", self.operation, self.sources.len());
+        if let Some(destination) = &self.destination {
+            text.push_str(&format!(" to {}", destination.display()));
+        }
+        text.push_str(&format!(": {}", self.outcome.key()));
+        if !self.error.is_empty() {
+            text.push_str(&format!(" ({})", self.error));
+        }
+        text
+    }
+}
+
+/// Add `record` to the log, dropping the oldest records beyond [MAX_RECORDS].
+pub fn record(record: &ActivityRecord) -> Result<(), String> {
+    let path = log_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create state directory: {}", e))?;
+    }
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    let mut lines: Vec<&str> = content.lines().collect();
+    let line = record.to_line();
+    lines.push(&line);
+    let kept = &lines[lines.len().saturating_sub(MAX_RECORDS)..];
+    fs::write(&path, kept.join("\n") + "\n").map_err(|e| format!("Failed to save the activity log: {}", e))
+}
+
+/// The logged jobs, oldest first; with `since`, only those finished after it.
+pub fn load(since: Option<SystemTime>) -> Vec<ActivityRecord> {
+    fs::read_to_string(log_path())
+        .unwrap_or_default()
+        .lines()
+        .filter_map(ActivityRecord::from_line)
+        .filter(|record| since.is_none_or(|since| record.finished >= since))
+        .collect()
+}
+
+/// A time as RFC 3339, e.g. "2024-05-01T14:02:00Z".
+pub fn timestamp(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// `records` as a JSON array of objects.
+pub fn to_json(records: &[ActivityRecord]) -> String {
+    let objects: Vec<String> = records
+        .iter()
+        .map(|record| {
+            let sources: Vec<String> =
+                record.sources.iter().map(|s| json_string(&s.to_string_lossy())).collect();
+            format!(
+                "  {{\"started\": {}, \"finished\": {}, \"operation\": {}, \"sources\": [{}], \
+                 \"destination\": {}, \"bytes\": {}, \"completed\": {}, \"skipped\": {}, \
+                 \"outcome\": {}, \"error\": {}}}",
+                json_string(&timestamp(record.started)),
+                json_string(&timestamp(record.finished)),
+                json_string(&record.operation),
+                sources.join(", "),
+                record
+                    .destination
+                    .as_ref()
+                    .map(|d| json_string(&d.to_string_lossy()))
+                    .unwrap_or_else(|| "null".to_string()),
+                record.bytes,
+                record.completed,
+                record.skipped,
+                json_string(record.outcome.key()),
+                json_string(&record.error),
+            )
+        })
+        .collect();
+    if objects.is_empty() {
+        return "[]\n".to_string();
+    }
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+/// Quote a CSV field if it needs to be.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// `records` as CSV with a header row, one row per job. Several sources are
+/// separated by newlines within their field.
+pub fn to_csv(records: &[ActivityRecord]) -> String {
+    let mut csv = String::from("started,finished,operation,sources,destination,bytes,completed,skipped,outcome,error\r\n");
+    for record in records {
+        let sources: Vec<String> = record.sources.iter().map(|s| s.to_string_lossy().to_string()).collect();
+        let fields = [
+            timestamp(record.started),
+            timestamp(record.finished),
+            record.operation.clone(),
+            sources.join("\n"),
+            record.destination.as_ref().map(|d| d.to_string_lossy().to_string()).unwrap_or_default(),
+            record.bytes.to_string(),
+            record.completed.to_string(),
+            record.skipped.to_string(),
+            record.outcome.key().to_string(),
+            record.error.clone(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Write `records` to `path`, as CSV if it ends in `.csv` and as JSON otherwise.
+pub fn export(records: &[ActivityRecord], path: &Path) -> Result<(), String> {
+    let csv = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    let content = if csv { to_csv(records) } else { to_json(records) };
+    fs::write(path, content).map_err(|e| format!("Failed to export to {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ActivityRecord {
+        ActivityRecord {
+            started: SystemTime::UNIX_EPOCH + Duration::from_secs(1_714_572_000),
+            finished: SystemTime::UNIX_EPOCH + Duration::from_secs(1_714_572_120),
+            operation: "copy".to_string(),
+            sources: vec![PathBuf::from("/home/me/a\tb"), PathBuf::from("/home/me/line\nbreak\r")],
+            destination: Some(PathBuf::from("/home/me/\"quoted\", with comma\\")),
+            bytes: 4096,
+            completed: 2,
+            skipped: 1,
+            outcome: Outcome::Failed,
+            error: "Disk full\r\nretry later\r".to_string(),
+        }
+    }
+
+    #[test]
+    fn fields_survive_escaping() {
+        for field in ["plain", "a\tb", "a\nb", "ends in cr\r", "\\t is not a tab", "back\\", "\"q\",c"] {
+            let escaped = escape(field);
+            assert!(!escaped.contains(['\t', '\n', '\r']), "{:?}", escaped);
+            assert_eq!(unescape(&escaped), field);
+        }
+    }
+
+    #[test]
+    fn records_survive_the_log() {
+        let record = sample();
+        let line = record.to_line();
+        // As read back by load
+        let read: Vec<ActivityRecord> = format!("{}\n", line).lines().filter_map(ActivityRecord::from_line).collect();
+        assert_eq!(read, vec![record]);
+
+        let none = ActivityRecord { destination: None, sources: Vec::new(), ..sample() };
+        assert_eq!(ActivityRecord::from_line(&none.to_line()), Some(none));
+        assert_eq!(ActivityRecord::from_line("not a record"), None);
+    }
+
+    #[test]
+    fn exports_json() {
+        assert_eq!(to_json(&[]), "[]\n");
+        let json = to_json(&[sample()]);
+        assert!(json.contains("\"started\": \"2024-05-01T14:00:00Z\""), "{}", json);
+        assert!(json.contains("\"sources\": [\"/home/me/a\\tb\", \"/home/me/line\\nbreak\\r\"]"), "{}", json);
+        assert!(json.contains("\"destination\": \"/home/me/\\\"quoted\\\", with comma\\\\\""), "{}", json);
+        assert!(json.contains("\"error\": \"Disk full\\r\\nretry later\\r\"}"), "{}", json);
+        assert_eq!(json_string("bell\u{7}"), "\"bell\\u0007\"");
+    }
+
+    #[test]
+    fn exports_csv() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+        assert_eq!(csv_field("a\tb"), "a\tb");
+
+        assert_eq!(
+            to_csv(&[sample()]),
+            "started,finished,operation,sources,destination,bytes,completed,skipped,outcome,error\r\n\
+             2024-05-01T14:00:00Z,2024-05-01T14:02:00Z,copy,\"/home/me/a\tb\n/home/me/line\nbreak\r\",\
+             \"/home/me/\"\"quoted\"\", with comma\\\",4096,2,1,failed,\"Disk full\r\nretry later\r\"\r\n"
+        );
+    }
+}
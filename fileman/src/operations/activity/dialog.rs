@@ -0,0 +1,223 @@
+//! Recent Activity popup: the latest logged jobs, and export of the log.
+
+use super::{ActivityRecord, export, load, session_start};
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+use nptk_fileman_widgets::xdg;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Jobs listed, newest first; exports include all of them
+const MAX_LISTED: usize = 15;
+
+/// Which button was pressed, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ActivityRequest {
+    ToggleScope,
+    /// Export with this file extension
+    Export(&'static str),
+}
+
+/// The latest jobs of the whole log or of this session, with buttons to
+/// export them as JSON or CSV.
+pub struct RecentActivityDialog {
+    // Only jobs finished since fileman started
+    session_only: bool,
+    records: Vec<ActivityRecord>,
+    inner: Container,
+    path_text: StateSignal<String>,
+    message_text: StateSignal<String>,
+    request: Arc<Mutex<Option<ActivityRequest>>>,
+    signals_hooked: bool,
+}
+
+impl RecentActivityDialog {
+    pub fn new() -> Self {
+        let home = xdg::home();
+        let mut dialog = Self {
+            session_only: false,
+            records: load(None),
+            inner: Container::new(vec![]),
+            path_text: StateSignal::new(home.join("fileman-activity.json").display().to_string()),
+            message_text: StateSignal::new(String::new()),
+            request: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        };
+        dialog.rebuild();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), "Recent Activity", (640, 560), (200, 120));
+    }
+
+    fn button(&self, label: &str, request: ActivityRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn row(children: Vec<Box<dyn Widget>>) -> Container {
+        Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Row,
+            align_items: Some(AlignItems::Center),
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            ..Default::default()
+        })
+    }
+
+    fn rebuild(&mut self) {
+        let scope = if self.session_only { "This Session" } else { "All" };
+        let mut children: Vec<Box<dyn Widget>> = vec![Box::new(Self::row(vec![
+            Box::new(
+                Text::new(format!("{} job(s)", self.records.len())).with_layout_style(LayoutStyle {
+                    flex_grow: 1.0,
+                    ..Default::default()
+                }),
+            ),
+            Box::new(self.button(scope, ActivityRequest::ToggleScope)),
+        ]))];
+        if self.records.is_empty() {
+            children.push(Box::new(Text::new("No finished jobs yet".to_string()).with_font_size(14.0)));
+        }
+        for record in self.records.iter().rev().take(MAX_LISTED) {
+            let finished = DateTime::<Local>::from(record.finished).format("%Y-%m-%d %H:%M");
+            children.push(Box::new(
+                Text::new(format!("{}  {}", finished, record.describe())).with_font_size(12.0),
+            ));
+        }
+        if self.records.len() > MAX_LISTED {
+            children.push(Box::new(
+                Text::new(format!("and {} earlier", self.records.len() - MAX_LISTED)).with_font_size(12.0),
+            ));
+        }
+
+        children.extend([
+            Box::new(Self::row(vec![
+                Box::new(
+                    TextInput::new()
+                        .with_text_signal(self.path_text.clone())
+                        .with_placeholder("Export to".to_string())
+                        .with_layout_style(LayoutStyle {
+                            size: Vector2::new(Dimension::auto(), Dimension::length(30.0)),
+                            flex_grow: 1.0,
+                            ..Default::default()
+                        }),
+                ),
+                Box::new(self.button("Export JSON", ActivityRequest::Export("json"))),
+                Box::new(self.button("Export CSV", ActivityRequest::Export("csv"))),
+            ])) as Box<dyn Widget>,
+            Box::new(Text::new(self.message_text.maybe()).with_font_size(12.0)),
+            Box::new(
+                Container::new(vec![Box::new(
+                    Button::new(Text::new("Close".to_string())).with_on_pressed(MaybeSignal::value(Update::DRAW)),
+                )])
+                .with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: Some(JustifyContent::FlexEnd),
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    ..Default::default()
+                }),
+            ),
+        ]);
+
+        self.inner = Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(8.0)),
+            ..Default::default()
+        });
+    }
+
+    /// Export the jobs shown to the chosen path, with `extension` as its extension.
+    fn export(&mut self, extension: &str) {
+        let text = self.path_text.get().trim().to_string();
+        if text.is_empty() {
+            self.message_text.set("Enter a file to export to".to_string());
+            return;
+        }
+        let path = PathBuf::from(text).with_extension(extension);
+        self.path_text.set(path.display().to_string());
+        match export(&self.records, &path) {
+            Ok(()) => self
+                .message_text
+                .set(format!("Exported {} job(s) to {}", self.records.len(), path.display())),
+            Err(e) => {
+                log::error!("{}", e);
+                self.message_text.set(e);
+            },
+        }
+    }
+}
+
+impl Default for RecentActivityDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for RecentActivityDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.message_text);
+            context.hook_signal(&mut self.path_text);
+            self.signals_hooked = true;
+        }
+
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        if let Some(request) = request {
+            match request {
+                ActivityRequest::ToggleScope => {
+                    self.session_only = !self.session_only;
+                    self.records = load(self.session_only.then(session_start));
+                    self.message_text.set(String::new());
+                },
+                ActivityRequest::Export(extension) => self.export(extension),
+            }
+            self.rebuild();
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
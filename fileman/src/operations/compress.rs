@@ -0,0 +1,55 @@
+//! Compressing items into an archive next to them
+//!
+//! Packing is left to `tar`, which creates gzip-compressed archives everywhere
+//! fileman runs.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Archive to create in `parent` for `paths`: named after the item when there
+/// is one, "Archive" otherwise, and numbered when the name is taken.
+fn archive_path(paths: &[PathBuf], parent: &Path) -> PathBuf {
+    let stem = match paths {
+        [path] => path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Archive".to_string()),
+        _ => "Archive".to_string(),
+    };
+    std::iter::once(format!("{}.tar.gz", stem))
+        .chain((2..).map(|n| format!("{} {}.tar.gz", stem, n)))
+        .map(|name| parent.join(name))
+        .find(|candidate| !candidate.exists())
+        .expect("unbounded range always yields a free name")
+}
+
+/// Pack `paths`, which must be in the same folder, into a `.tar.gz` archive in
+/// that folder. Returns the archive.
+pub fn compress(paths: &[PathBuf]) -> Result<PathBuf, String> {
+    let parent = paths
+        .first()
+        .and_then(|p| p.parent())
+        .map(Path::to_path_buf)
+        .ok_or_else(|| "Nothing to compress".to_string())?;
+    if paths.iter().any(|p| p.parent() != Some(parent.as_path())) {
+        return Err("Items to compress must be in the same folder".to_string());
+    }
+    let archive = archive_path(paths, &parent);
+    let output = Command::new("tar")
+        .arg("-czf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(&parent)
+        .arg("--")
+        .args(paths.iter().filter_map(|p| p.file_name()))
+        .output()
+        .map_err(|e| format!("Failed to run tar: {}", e))?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&archive);
+        return Err(format!(
+            "Failed to compress: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(archive)
+}
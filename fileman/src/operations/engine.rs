@@ -0,0 +1,1435 @@
+//! File operation engine
+//!
+//! Runs copy, move, link, delete, trash, sync, permission and disk image operations on a blocking thread and reports
+//! progress, conflicts and failed items through an event channel. The GUI and the
+//! command line use the same engine; they only differ in how they present progress
+//! and answer conflicts and failures.
+
+use super::permissions::{self, PermissionChange};
+use super::sync::{self, SyncAction};
+use super::throttle::{SpeedLimit, Throttle};
+use super::trash;
+use super::undo::UndoRecord;
+use nix::fcntl::{PosixFadviseAdvice, posix_fadvise};
+use nix::sys::stat::{Mode, SFlag, mknod, utimes};
+use nix::sys::time::TimeVal;
+use nptk_fileman_widgets::conflict_dialog::{ConflictResolution, OverwritePolicy};
+use nptk_fileman_widgets::error_dialog::ErrorAction;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// Size of the buffer used when copying file contents
+const COPY_CHUNK_SIZE: usize = 256 * 1024;
+
+/// How often a paused operation checks whether it may continue
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// `EXDEV`: rename across file systems
+const CROSS_DEVICE_ERROR: i32 = 18;
+
+/// What an operation does.
+#[derive(Debug, Clone)]
+pub enum OperationKind {
+    /// Copy `sources` into the directory `destination`.
+    Copy { sources: Vec<PathBuf>, destination: PathBuf },
+    /// Move `sources` into the directory `destination`.
+    Move { sources: Vec<PathBuf>, destination: PathBuf },
+    /// Delete permanently.
+    Delete(Vec<PathBuf>),
+    /// Move to the trash.
+    Trash(Vec<PathBuf>),
+    /// Make `destination` match `source` (see [sync]); with `mirror`, also delete
+    /// what only exists in `destination`.
+    Sync { source: PathBuf, destination: PathBuf, mirror: bool },
+    /// Change the permissions of `paths` (see [permissions]).
+    SetPermissions { paths: Vec<PathBuf>, change: PermissionChange },
+    /// Create hard links to the files `sources` in the directory `destination`,
+    /// which must be on the same file system.
+    CreateHardLink { sources: Vec<PathBuf>, destination: PathBuf },
+    /// Write the disk image `image` to the start of `target`, the opened block
+    /// device `device`, then read it back to verify it. Only run in the process
+    /// that opened the device, never in the daemon.
+    WriteImage { image: PathBuf, device: PathBuf, target: Arc<fs::File> },
+}
+
+/// An operation to run.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub kind: OperationKind,
+    /// Policy applied to every conflict. When `None`, each conflict is sent as
+    /// [OperationEvent::Conflict] and the engine waits for the answer.
+    pub policy: Option<OverwritePolicy>,
+    /// Set to stop the operation after the item being processed
+    pub cancel: Arc<AtomicBool>,
+    /// While set, the operation waits before the next item or chunk of data
+    pub pause: Arc<AtomicBool>,
+    /// Maximum speed of copied data; can be changed while the operation runs
+    pub speed_limit: SpeedLimit,
+    /// Copy what symbolic links point to instead of the links
+    pub follow_symlinks: bool,
+}
+
+impl Operation {
+    pub fn new(kind: OperationKind) -> Self {
+        Self {
+            kind,
+            policy: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            pause: Arc::new(AtomicBool::new(false)),
+            speed_limit: SpeedLimit::default(),
+            follow_symlinks: false,
+        }
+    }
+
+    pub fn with_policy(mut self, policy: OverwritePolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Limit copied data to `bytes_per_second` (see [super::throttle]).
+    pub fn with_speed_limit(self, bytes_per_second: u64) -> Self {
+        self.speed_limit.set(bytes_per_second);
+        self
+    }
+
+    /// Copy what symbolic links point to instead of the links.
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Present-tense verb for progress messages.
+    pub fn verb(&self) -> &'static str {
+        match self.kind {
+            OperationKind::Copy { .. } => "Copying",
+            OperationKind::Move { .. } => "Moving",
+            OperationKind::Delete(_) => "Deleting",
+            OperationKind::Trash(_) => "Moving to trash",
+            OperationKind::Sync { .. } => "Synchronizing",
+            OperationKind::SetPermissions { .. } => "Changing permissions",
+            OperationKind::CreateHardLink { .. } => "Linking",
+            OperationKind::WriteImage { .. } => "Writing image",
+        }
+    }
+}
+
+/// Progress of a running operation.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub done_bytes: u64,
+    pub total_bytes: u64,
+    pub done_items: usize,
+    pub total_items: usize,
+    /// Item being processed
+    pub current: PathBuf,
+}
+
+impl Progress {
+    /// Completed fraction between 0.0 and 1.0.
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes > 0 {
+            self.done_bytes as f64 / self.total_bytes as f64
+        } else if self.total_items > 0 {
+            self.done_items as f64 / self.total_items as f64
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A destination that already exists. Answer it with [Conflict::respond].
+#[derive(Debug)]
+pub struct Conflict {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    /// Number of further conflicts in the same operation
+    pub remaining: usize,
+    responder: oneshot::Sender<ConflictResolution>,
+}
+
+impl Conflict {
+    pub fn respond(self, resolution: ConflictResolution) {
+        let _ = self.responder.send(resolution);
+    }
+}
+
+/// An item that failed. Answer it with [ItemError::respond]; dropping it
+/// aborts the operation.
+#[derive(Debug)]
+pub struct ItemError {
+    pub path: PathBuf,
+    pub message: String,
+    /// Number of items after this one
+    pub remaining: usize,
+    responder: oneshot::Sender<ErrorAction>,
+}
+
+impl ItemError {
+    pub fn respond(self, action: ErrorAction) {
+        let _ = self.responder.send(action);
+    }
+}
+
+/// Events sent while an operation runs.
+#[derive(Debug)]
+pub enum OperationEvent {
+    Progress(Progress),
+    Conflict(Conflict),
+    Error(ItemError),
+}
+
+/// Result of a completed (or cancelled) operation.
+#[derive(Debug, Clone, Default)]
+pub struct OperationOutcome {
+    /// How to revert the operation, if it can be reverted
+    pub undo: Option<UndoRecord>,
+    /// Items processed successfully
+    pub completed: usize,
+    /// Items skipped because of conflicts
+    pub skipped: usize,
+    /// The user cancelled the operation part way
+    pub cancelled: bool,
+    /// An item failed and the operation stopped there, either because the
+    /// user chose to abort or because nobody was left to answer; its error is
+    /// the last of [OperationOutcome::errors]
+    pub aborted: bool,
+    /// Items that failed without stopping the operation, e.g. skipped after
+    /// an [OperationEvent::Error]
+    pub errors: Vec<String>,
+}
+
+/// Run `operation` on a blocking thread.
+pub async fn spawn(
+    operation: Operation,
+    events: mpsc::UnboundedSender<OperationEvent>,
+) -> Result<OperationOutcome, String> {
+    tokio::task::spawn_blocking(move || run(&operation, &events))
+        .await
+        .map_err(|e| format!("Operation task failed: {}", e))?
+}
+
+/// Total size and item count of `path`, including directory contents.
+pub(crate) fn measure(path: &Path) -> (u64, usize) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return (0, 0);
+    };
+    if metadata.is_dir() {
+        fs::read_dir(path)
+            .map(|entries| {
+                entries.flatten().fold((0, 1), |(bytes, items), entry| {
+                    let (b, i) = measure(&entry.path());
+                    (bytes + b, items + i)
+                })
+            })
+            .unwrap_or((0, 1))
+    } else {
+        (metadata.len(), 1)
+    }
+}
+
+/// Move `from` to `to`, falling back to copy and delete across file systems.
+/// When part of a copied folder fails, the copy is removed again and `from`
+/// is kept.
+/// When `from` was copied but removing it failed, the copy is kept.
+pub fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    let mut tree = TreeCopy::new(false);
+    move_path_with(from, to, &mut tree, &mut |_| {}).map_err(|e| match e {
+        MoveError::Copy(e) => e,
+        MoveError::RemoveSource(e) => e,
+    })?;
+    for warning in &tree.warnings {
+        log::warn!("{}", warning);
+    }
+    match tree.failures.as_slice() {
+        [] => Ok(()),
+        [first, rest @ ..] => {
+            let _ = remove_path(to);
+            Err(io::Error::other(format!("{} (and {} more errors)", first, rest.len())))
+        },
+    }
+}
+
+/// [move_path], copying with `tree` and calling `on_bytes` with every chunk
+/// written when it has to copy. `from` is only removed if all of it was copied.
+fn move_path_with(
+    from: &Path,
+    to: &Path,
+    tree: &mut TreeCopy,
+    on_bytes: &mut dyn FnMut(u64),
+) -> Result<(), MoveError> {
+    match fs::rename(from, to) {
+        Err(e) if e.raw_os_error() == Some(CROSS_DEVICE_ERROR) => {
+            tree.copy(from, to, on_bytes).map_err(MoveError::Copy)?;
+            if tree.failures.is_empty() { remove_path(from).map_err(MoveError::RemoveSource) } else { Ok(()) }
+        },
+        result => result.map_err(MoveError::Copy),
+    }
+}
+
+/// Why [move_path_with] failed.
+enum MoveError {
+    /// Renaming or copying failed. `from` is untouched, so what was copied to
+    /// `to` can be removed.
+    Copy(io::Error),
+    /// All of `from` was copied, but removing it failed, possibly part way.
+    /// `to` may be the only complete copy left and must be kept.
+    RemoveSource(io::Error),
+}
+
+/// Hidden sibling of `target` that a replacement is copied to first, so
+/// `target` is only replaced once the copy is complete.
+fn replacement_path(target: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(target.file_name().unwrap_or_default());
+    name.push(".fileman-tmp");
+    target.with_file_name(name)
+}
+
+/// Put the complete copy `replacement` in the place of `target`. A rename
+/// replaces files at once; folders, or a file and a folder, can't replace
+/// each other that way, so `target` is removed first.
+fn replace_with(replacement: &Path, target: &Path) -> io::Result<()> {
+    if fs::rename(replacement, target).is_ok() {
+        return Ok(());
+    }
+    remove_path(target)?;
+    fs::rename(replacement, target)
+}
+
+fn replace_error(target: &Path, replacement: &Path, e: io::Error) -> String {
+    format!("Failed to replace {}: {}; the copy is kept as {}", target.display(), e, replacement.display())
+}
+
+pub(crate) fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Recursive copy keeping permissions, modification and access times and
+/// symbolic links. Entries inside a folder that fail are collected in
+/// `failures` and the copy goes on with the others.
+pub(crate) struct TreeCopy {
+    /// Copy what symbolic links point to instead of the links
+    follow_symlinks: bool,
+    /// One message per entry that failed
+    pub(crate) failures: Vec<String>,
+    /// One message per entry whose times or permissions couldn't be kept,
+    /// e.g. on FAT or CIFS. Its contents were copied, so it stays.
+    pub(crate) warnings: Vec<String>,
+    /// (device, inode) of the folders being copied, to stop at links to them
+    ancestors: Vec<(u64, u64)>,
+    /// Stops the copy at the next chunk once set
+    cancel: Arc<AtomicBool>,
+}
+
+impl TreeCopy {
+    pub(crate) fn new(follow_symlinks: bool) -> Self {
+        Self {
+            follow_symlinks,
+            failures: Vec::new(),
+            warnings: Vec::new(),
+            ancestors: Vec::new(),
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Give up part way, with an [io::ErrorKind::Interrupted] error, once
+    /// `cancel` is set. The file being written is removed; the caller removes
+    /// the rest of the unfinished copy.
+    pub(crate) fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Copy `from` to `to`, calling `on_bytes` with every chunk written.
+    /// Fails if `to` itself can't be created; what fails inside it is
+    /// collected in [TreeCopy::failures], and times or permissions that
+    /// couldn't be set in [TreeCopy::warnings].
+    pub(crate) fn copy(&mut self, from: &Path, to: &Path, on_bytes: &mut dyn FnMut(u64)) -> io::Result<()> {
+        if self.cancelled() {
+            return Err(cancelled_error());
+        }
+        let metadata = if self.follow_symlinks { fs::metadata(from)? } else { fs::symlink_metadata(from)? };
+        if metadata.file_type().is_symlink() {
+            let target = fs::read_link(from)?;
+            return std::os::unix::fs::symlink(target, to);
+        }
+        if metadata.is_dir() {
+            let id = (metadata.dev(), metadata.ino());
+            if self.ancestors.contains(&id) {
+                return Err(io::Error::other("the link leads to a folder containing it"));
+            }
+            let entries = fs::read_dir(from)?;
+            fs::create_dir(to)?;
+            self.ancestors.push(id);
+            for entry in entries {
+                let source = match entry {
+                    Ok(entry) => entry.path(),
+                    Err(e) => {
+                        self.failures.push(format!("Failed to read {}: {}", from.display(), e));
+                        continue;
+                    },
+                };
+                let target = to.join(source.file_name().unwrap_or_default());
+                if let Err(e) = self.copy(&source, &target, on_bytes) {
+                    if self.cancelled() {
+                        self.ancestors.pop();
+                        return Err(e);
+                    }
+                    self.failures.push(format!("Failed to copy {}: {}", source.display(), e));
+                }
+            }
+            self.ancestors.pop();
+        } else if !metadata.is_file() {
+            make_node(to, &metadata)?;
+        } else if let Err(e) = copy_contents(from, to, &self.cancel, on_bytes) {
+            // Don't leave a partial file behind
+            let _ = fs::remove_file(to);
+            return Err(e);
+        }
+        if let Err(e) = copy_metadata(to, &metadata) {
+            self.warnings
+                .push(format!("Could not keep the times or permissions of {}: {}", to.display(), e));
+        }
+        Ok(())
+    }
+}
+
+fn cancelled_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "cancelled")
+}
+
+fn copy_contents(from: &Path, to: &Path, cancel: &AtomicBool, on_bytes: &mut dyn FnMut(u64)) -> io::Result<()> {
+    let mut reader = fs::File::open(from)?;
+    let mut writer = fs::File::create(to)?;
+    let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(cancelled_error());
+        }
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buffer[..read])?;
+        on_bytes(read as u64);
+    }
+}
+
+/// Make a pipe or device node like the one `metadata` describes at `to`.
+/// Their contents aren't copied: reading a pipe waits for a writer and
+/// reading a device such as `/dev/zero` never ends. Sockets belong to the
+/// program listening on them, so they can't be copied at all.
+fn make_node(to: &Path, metadata: &fs::Metadata) -> io::Result<()> {
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_fifo() {
+        SFlag::S_IFIFO
+    } else if file_type.is_char_device() {
+        SFlag::S_IFCHR
+    } else if file_type.is_block_device() {
+        SFlag::S_IFBLK
+    } else {
+        return Err(io::Error::other("sockets can't be copied"));
+    };
+    mknod(to, kind, Mode::from_bits_truncate(metadata.mode() & 0o777), metadata.rdev()).map_err(io::Error::from)
+}
+
+/// Give the copy `to` the times and permissions of `metadata`. Times go
+/// first, as the permissions may not allow opening it.
+fn copy_metadata(to: &Path, metadata: &fs::Metadata) -> io::Result<()> {
+    if metadata.is_file() || metadata.is_dir() {
+        let times = fs::FileTimes::new()
+            .set_accessed(metadata.accessed()?)
+            .set_modified(metadata.modified()?);
+        fs::File::open(to)?.set_times(times)?;
+    } else {
+        // Opening a pipe would wait for a writer, so it is set by path
+        let time = |seconds, nanoseconds: i64| TimeVal::new(seconds, nanoseconds / 1000);
+        utimes(to, &time(metadata.atime(), metadata.atime_nsec()), &time(metadata.mtime(), metadata.mtime_nsec()))
+            .map_err(io::Error::from)?;
+    }
+    fs::set_permissions(to, metadata.permissions())
+}
+
+/// Tracks progress and conflict answers while an operation runs.
+struct Runner<'a> {
+    events: &'a mpsc::UnboundedSender<OperationEvent>,
+    progress: Progress,
+    policy_for_all: Option<OverwritePolicy>,
+    conflicts_left: usize,
+    /// The user chose to skip all further failures
+    skip_failures: bool,
+    /// Failures of skipped items
+    errors: Vec<String>,
+    cancel: Arc<AtomicBool>,
+    pause: Arc<AtomicBool>,
+    throttle: Throttle,
+}
+
+impl Runner<'_> {
+    /// Whether to stop. Blocks while the operation is paused, so this is also
+    /// where pausing takes effect between items.
+    fn cancelled(&self) -> bool {
+        self.wait_while_paused();
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn wait_while_paused(&self) {
+        while self.pause.load(Ordering::Relaxed) && !self.cancel.load(Ordering::Relaxed) {
+            std::thread::sleep(PAUSE_POLL_INTERVAL);
+        }
+    }
+
+    fn report(&self) {
+        let _ = self.events.send(OperationEvent::Progress(self.progress.clone()));
+    }
+
+    fn add_bytes(&mut self, bytes: u64) {
+        self.progress.done_bytes += bytes;
+        self.report();
+    }
+
+    /// Count `bytes` of copied data, waiting while paused or a speed limit is exceeded.
+    fn add_transferred(&mut self, bytes: u64) {
+        self.wait_while_paused();
+        self.throttle.wait(bytes);
+        self.add_bytes(bytes);
+    }
+
+    /// Decide where `source` goes when `destination` exists. `None` means skip;
+    /// `Err(())` means the user cancelled.
+    fn resolve_conflict(&mut self, source: &Path, destination: &Path) -> Result<Option<PathBuf>, ()> {
+        self.conflicts_left = self.conflicts_left.saturating_sub(1);
+        let policy = match self.policy_for_all {
+            Some(policy) => policy,
+            None => {
+                let (responder, answer) = oneshot::channel();
+                let conflict = Conflict {
+                    source: source.to_path_buf(),
+                    destination: destination.to_path_buf(),
+                    remaining: self.conflicts_left,
+                    responder,
+                };
+                if self.events.send(OperationEvent::Conflict(conflict)).is_err() {
+                    return Err(());
+                }
+                let Ok(resolution) = answer.blocking_recv() else {
+                    return Err(());
+                };
+                if resolution.apply_to_all {
+                    self.policy_for_all = Some(resolution.policy);
+                }
+                resolution.policy
+            },
+        };
+        if policy == OverwritePolicy::Cancel {
+            return Err(());
+        }
+        Ok(policy.resolve_destination(source, destination))
+    }
+
+    /// Run `step` for `path`, asking what to do when it fails: it runs again on
+    /// retry. `Ok(None)` means the item was skipped or the operation was
+    /// cancelled while it ran; either way its error is kept for the outcome.
+    /// `Err` means the operation is aborted: the caller stops with
+    /// [OperationOutcome::aborted], keeping the undo record of what was done.
+    /// `remaining` is the number of items after this one.
+    fn attempt<T>(
+        &mut self,
+        path: &Path,
+        remaining: usize,
+        mut step: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Option<T>, String> {
+        loop {
+            let message = match step(self) {
+                Ok(value) => return Ok(Some(value)),
+                // Stopped by cancelling: there's nothing to ask
+                Err(message) if self.cancel.load(Ordering::Relaxed) => {
+                    self.errors.push(message);
+                    return Ok(None);
+                },
+                Err(message) => message,
+            };
+            let action = if self.skip_failures {
+                ErrorAction::Skip
+            } else {
+                let (responder, answer) = oneshot::channel();
+                let error = ItemError {
+                    path: path.to_path_buf(),
+                    message: message.clone(),
+                    remaining,
+                    responder,
+                };
+                let sent = self.events.send(OperationEvent::Error(error)).is_ok();
+                if sent { answer.blocking_recv().unwrap_or(ErrorAction::Abort) } else { ErrorAction::Abort }
+            };
+            match action {
+                ErrorAction::Retry => continue,
+                ErrorAction::Skip | ErrorAction::SkipAll => {
+                    self.skip_failures |= action == ErrorAction::SkipAll;
+                    self.errors.push(message);
+                    return Ok(None);
+                },
+                ErrorAction::Abort => return Err(message),
+            }
+        }
+    }
+}
+
+/// Run `operation` on the current thread. Must not be called from an async context
+/// (conflict answers are awaited with a blocking receive); use [spawn] there.
+pub fn run(
+    operation: &Operation,
+    events: &mpsc::UnboundedSender<OperationEvent>,
+) -> Result<OperationOutcome, String> {
+    // Synchronization works from a fresh plan; its copies and deletions are measured
+    let sync_plan = match &operation.kind {
+        OperationKind::Sync { source, destination, mirror } => Some(sync::plan(source, destination, *mirror)?),
+        _ => None,
+    };
+    let sync_sources: Vec<PathBuf>;
+    let sources: &[PathBuf] = match &operation.kind {
+        OperationKind::Copy { sources, .. }
+        | OperationKind::Move { sources, .. }
+        | OperationKind::Delete(sources)
+        | OperationKind::Trash(sources)
+        | OperationKind::SetPermissions { paths: sources, .. }
+        | OperationKind::CreateHardLink { sources, .. } => sources,
+        OperationKind::Sync { .. } => {
+            sync_sources = sync_plan
+                .iter()
+                .flat_map(|plan| &plan.actions)
+                .map(|action| match action {
+                    SyncAction::Copy { source, .. } => source.clone(),
+                    SyncAction::Delete(path) => path.clone(),
+                })
+                .collect();
+            &sync_sources
+        },
+        OperationKind::WriteImage { image, .. } => std::slice::from_ref(image),
+    };
+
+    let (total_bytes, total_items) = sources
+        .iter()
+        .map(|s| measure(s))
+        .fold((0, 0), |(bytes, items), (b, i)| (bytes + b, items + i));
+    // Permission changes don't write data, so their progress is counted in entries
+    let (total_bytes, total_items) = match &operation.kind {
+        OperationKind::SetPermissions { paths, change } if !change.recursive => (0, paths.len()),
+        OperationKind::SetPermissions { .. } => (0, total_items),
+        // Neither do links
+        OperationKind::CreateHardLink { sources, .. } => (0, sources.len()),
+        // Written once and read back once
+        OperationKind::WriteImage { .. } => (total_bytes * 2, 1),
+        _ => (total_bytes, total_items),
+    };
+    let mut runner = Runner {
+        events,
+        progress: Progress {
+            done_bytes: 0,
+            total_bytes,
+            done_items: 0,
+            total_items,
+            current: PathBuf::new(),
+        },
+        policy_for_all: operation.policy,
+        conflicts_left: 0,
+        skip_failures: false,
+        errors: Vec::new(),
+        cancel: operation.cancel.clone(),
+        pause: operation.pause.clone(),
+        throttle: Throttle::new(operation.speed_limit.clone()).with_cancel(operation.cancel.clone()),
+    };
+    let mut outcome = OperationOutcome::default();
+
+    match &operation.kind {
+        OperationKind::Copy { destination, .. } | OperationKind::Move { destination, .. } => {
+            let is_move = matches!(operation.kind, OperationKind::Move { .. });
+            if !destination.is_dir() {
+                return Err(format!("{} is not a directory", destination.display()));
+            }
+            let targets: Vec<(PathBuf, PathBuf)> = sources
+                .iter()
+                .map(|source| {
+                    let name = source.file_name().map(PathBuf::from).unwrap_or_default();
+                    (source.clone(), destination.join(name))
+                })
+                .collect();
+            // Checked before anything is done, so nothing is left without an undo record
+            if let Some((source, _)) = targets.iter().find(|(source, target)| target != source && target.starts_with(source)) {
+                return Err(format!("Cannot {} {} into itself", if is_move { "move" } else { "copy" }, source.display()));
+            }
+            runner.conflicts_left = targets.iter().filter(|(_, target)| fs::symlink_metadata(target).is_ok()).count();
+
+            let mut created = Vec::new();
+            let mut moves = Vec::new();
+            let count = targets.len();
+            for (index, (source, target)) in targets.into_iter().enumerate() {
+                let remaining = count - index - 1;
+                if runner.cancelled() {
+                    outcome.cancelled = true;
+                    break;
+                }
+                runner.progress.current = source.clone();
+                runner.report();
+                let (size, items) = measure(&source);
+
+                if is_move && target == source {
+                    // Moving into the directory it is already in
+                    outcome.skipped += 1;
+                    runner.add_bytes(size);
+                    runner.progress.done_items += items;
+                    continue;
+                }
+                let target = if fs::symlink_metadata(&target).is_ok() {
+                    if target == source {
+                        // Copying onto itself: keep both is the only sensible answer
+                        Some(nptk_fileman_widgets::conflict_dialog::keep_both_path(&target))
+                    } else {
+                        match runner.resolve_conflict(&source, &target) {
+                            Ok(target) => target,
+                            Err(()) => {
+                                outcome.cancelled = true;
+                                break;
+                            },
+                        }
+                    }
+                } else {
+                    Some(target)
+                };
+                let Some(target) = target else {
+                    outcome.skipped += 1;
+                    runner.add_bytes(size);
+                    runner.progress.done_items += items;
+                    continue;
+                };
+
+                // What is replaced stays until the copy is complete, which is
+                // written next to it first
+                let replacing = fs::symlink_metadata(&target).is_ok();
+                let copy_to = if replacing { replacement_path(&target) } else { target.clone() };
+                // Set once a move copied everything but couldn't remove the
+                // source; a retry then only removes it again
+                let mut source_copied = false;
+                // Set once the complete copy couldn't take the place of the
+                // target; a retry then only puts it in place again
+                let mut unplaced = false;
+                let verb = if is_move { "move" } else { "copy" };
+                let transferred = match runner.attempt(&source, remaining, |runner| {
+                    if unplaced || source_copied {
+                        if unplaced {
+                            replace_with(&copy_to, &target).map_err(|e| replace_error(&target, &copy_to, e))?;
+                            unplaced = false;
+                        }
+                        if source_copied {
+                            remove_path(&source)
+                                .map_err(|e| format!("Failed to remove {} after moving it: {}", source.display(), e))?;
+                        }
+                        return Ok((0, Vec::new()));
+                    }
+                    if replacing && fs::symlink_metadata(&copy_to).is_ok() {
+                        // Left over from an interrupted replacement
+                        remove_path(&copy_to)
+                            .map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?;
+                    }
+                    let mut copied = 0;
+                    let mut tree = TreeCopy::new(operation.follow_symlinks).with_cancel(runner.cancel.clone());
+                    let result = if is_move {
+                        move_path_with(&source, &copy_to, &mut tree, &mut |bytes| {
+                            copied += bytes;
+                            runner.add_transferred(bytes);
+                        })
+                    } else {
+                        tree.copy(&source, &copy_to, &mut |bytes| {
+                            copied += bytes;
+                            runner.add_transferred(bytes);
+                        })
+                        .map_err(MoveError::Copy)
+                    };
+                    let removed_source = match result {
+                        Ok(()) => Ok(()),
+                        Err(MoveError::Copy(e)) => {
+                            // Counted again when retried
+                            runner.progress.done_bytes -= copied;
+                            // Don't leave a partial copy behind; the source and
+                            // what it would replace are untouched
+                            let _ = remove_path(&copy_to);
+                            return Err(format!("Failed to {} {}: {}", verb, source.display(), e));
+                        },
+                        // Part of the source may be gone, so the copy stays
+                        Err(MoveError::RemoveSource(e)) => {
+                            source_copied = true;
+                            Err(e)
+                        },
+                    };
+                    runner.errors.append(&mut tree.warnings);
+                    if replacing {
+                        if let Err(e) = replace_with(&copy_to, &target) {
+                            runner.progress.done_bytes -= copied;
+                            unplaced = true;
+                            return Err(replace_error(&target, &copy_to, e));
+                        }
+                    }
+                    removed_source.map(|()| (copied, tree.failures)).map_err(|e| {
+                        runner.progress.done_bytes -= copied;
+                        format!("Failed to remove {} after moving it: {}", source.display(), e)
+                    })
+                }) {
+                    Ok(transferred) => transferred,
+                    Err(e) => {
+                        runner.errors.push(e);
+                        outcome.aborted = true;
+                        break;
+                    },
+                };
+                let Some((copied, failures)) = transferred else {
+                    if runner.cancel.load(Ordering::Relaxed) {
+                        outcome.cancelled = true;
+                        break;
+                    }
+                    runner.progress.done_items += items;
+                    runner.add_bytes(size);
+                    continue;
+                };
+                // Account for sizes the copy loop doesn't see (renames, symlinks)
+                runner.progress.done_bytes += size.saturating_sub(copied);
+                if is_move && failures.is_empty() {
+                    moves.push((source, target));
+                } else {
+                    // A move that failed in part kept the original, so only
+                    // the copy has to go to undo it
+                    if is_move {
+                        runner.errors.push(format!("Kept {} as part of it could not be moved", source.display()));
+                    }
+                    created.push(target);
+                }
+                runner.errors.extend(failures);
+                runner.progress.done_items += items;
+                outcome.completed += 1;
+                runner.report();
+            }
+
+            outcome.undo = if is_move {
+                (!moves.is_empty()).then_some(UndoRecord::Moved { moves })
+            } else {
+                (!created.is_empty()).then_some(UndoRecord::Copied { created })
+            };
+        },
+        OperationKind::Delete(paths) => {
+            for (index, path) in paths.iter().enumerate() {
+                if runner.cancelled() {
+                    outcome.cancelled = true;
+                    break;
+                }
+                runner.progress.current = path.clone();
+                let (size, items) = measure(path);
+                let deleted = match runner.attempt(path, paths.len() - index - 1, |_| {
+                    remove_path(path).map_err(|e| format!("Failed to delete {}: {}", path.display(), e))
+                }) {
+                    Ok(deleted) => deleted,
+                    Err(e) => {
+                        runner.errors.push(e);
+                        outcome.aborted = true;
+                        break;
+                    },
+                };
+                runner.progress.done_items += items;
+                runner.add_bytes(size);
+                if deleted.is_some() {
+                    outcome.completed += 1;
+                }
+            }
+        },
+        OperationKind::Trash(paths) => {
+            let mut entries = Vec::new();
+            for (index, path) in paths.iter().enumerate() {
+                if runner.cancelled() {
+                    outcome.cancelled = true;
+                    break;
+                }
+                runner.progress.current = path.clone();
+                let (size, items) = measure(path);
+                let trashed = match runner.attempt(path, paths.len() - index - 1, |_| trash::trash_path(path)) {
+                    Ok(trashed) => trashed,
+                    Err(e) => {
+                        runner.errors.push(e);
+                        outcome.aborted = true;
+                        break;
+                    },
+                };
+                runner.progress.done_items += items;
+                runner.add_bytes(size);
+                if let Some(entry) = trashed {
+                    entries.push(entry);
+                    outcome.completed += 1;
+                }
+            }
+            outcome.undo = (!entries.is_empty()).then_some(UndoRecord::Trashed { entries });
+        },
+        // Replaced and deleted files are gone, so synchronization can't be undone
+        OperationKind::Sync { .. } => {
+            let actions: Vec<&SyncAction> = sync_plan.iter().flat_map(|plan| &plan.actions).collect();
+            for (index, action) in actions.iter().enumerate() {
+                let remaining = actions.len() - index - 1;
+                if runner.cancelled() {
+                    outcome.cancelled = true;
+                    break;
+                }
+                match action {
+                    SyncAction::Copy { source, target, .. } => {
+                        runner.progress.current = source.clone();
+                        runner.report();
+                        let (size, items) = measure(source);
+                        let copied = match runner.attempt(source, remaining, |runner| {
+                            // The older copy stays until the new one is complete
+                            let replacing = fs::symlink_metadata(target).is_ok();
+                            let copy_to = if replacing { replacement_path(target) } else { target.clone() };
+                            if replacing && fs::symlink_metadata(&copy_to).is_ok() {
+                                remove_path(&copy_to)
+                                    .map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?;
+                            }
+                            let mut copied = 0;
+                            let mut tree = TreeCopy::new(operation.follow_symlinks).with_cancel(runner.cancel.clone());
+                            let result = tree.copy(source, &copy_to, &mut |bytes| {
+                                copied += bytes;
+                                runner.add_transferred(bytes);
+                            });
+                            let result = result
+                                .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))
+                                .and_then(|()| {
+                                    if replacing {
+                                        replace_with(&copy_to, target)
+                                            .map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?;
+                                    }
+                                    Ok(())
+                                });
+                            if result.is_ok() {
+                                runner.errors.append(&mut tree.warnings);
+                            }
+                            result.map(|()| (copied, tree.failures)).map_err(|e| {
+                                runner.progress.done_bytes -= copied;
+                                let _ = remove_path(&copy_to);
+                                e
+                            })
+                        }) {
+                            Ok(copied) => copied,
+                            Err(e) => {
+                                runner.errors.push(e);
+                                outcome.aborted = true;
+                                break;
+                            },
+                        };
+                        if copied.is_none() && runner.cancel.load(Ordering::Relaxed) {
+                            outcome.cancelled = true;
+                            break;
+                        }
+                        let copied = copied.map(|(bytes, failures)| {
+                            runner.errors.extend(failures);
+                            bytes
+                        });
+                        runner.progress.done_bytes += size.saturating_sub(copied.unwrap_or(0));
+                        runner.progress.done_items += items;
+                        if copied.is_some() {
+                            outcome.completed += 1;
+                        }
+                    },
+                    SyncAction::Delete(path) => {
+                        runner.progress.current = path.clone();
+                        let (size, items) = measure(path);
+                        let deleted = match runner.attempt(path, remaining, |_| {
+                            remove_path(path).map_err(|e| format!("Failed to delete {}: {}", path.display(), e))
+                        }) {
+                            Ok(deleted) => deleted,
+                            Err(e) => {
+                                runner.errors.push(e);
+                                outcome.aborted = true;
+                                break;
+                            },
+                        };
+                        runner.progress.done_items += items;
+                        runner.add_bytes(size);
+                        if deleted.is_some() {
+                            outcome.completed += 1;
+                        }
+                    },
+                }
+            }
+        },
+        // Previous modes aren't recorded, so permission changes can't be undone
+        OperationKind::SetPermissions { paths, change } => {
+            let mut errors = Vec::new();
+            for path in paths {
+                let finished = permissions::apply(
+                    path,
+                    change,
+                    &mut |entry| {
+                        if runner.cancelled() {
+                            return false;
+                        }
+                        runner.progress.current = entry.to_path_buf();
+                        runner.progress.done_items += 1;
+                        runner.report();
+                        true
+                    },
+                    &mut errors,
+                );
+                if !finished {
+                    outcome.cancelled = true;
+                    break;
+                }
+                outcome.completed += 1;
+            }
+            outcome.errors.extend(errors);
+        },
+        // Undone like a copy, by removing the links
+        OperationKind::CreateHardLink { destination, .. } => {
+            if !destination.is_dir() {
+                return Err(format!("{} is not a directory", destination.display()));
+            }
+            let targets: Vec<(PathBuf, PathBuf)> = sources
+                .iter()
+                .map(|source| {
+                    let name = source.file_name().map(PathBuf::from).unwrap_or_default();
+                    (source.clone(), destination.join(name))
+                })
+                .collect();
+            runner.conflicts_left = targets.iter().filter(|(_, target)| fs::symlink_metadata(target).is_ok()).count();
+
+            let mut created = Vec::new();
+            let count = targets.len();
+            for (index, (source, target)) in targets.into_iter().enumerate() {
+                if runner.cancelled() {
+                    outcome.cancelled = true;
+                    break;
+                }
+                runner.progress.current = source.clone();
+                runner.progress.done_items += 1;
+                runner.report();
+                if let Some(error) = hard_link_error(&source, destination) {
+                    outcome.errors.push(error);
+                    continue;
+                }
+
+                let target = if target == source {
+                    Some(nptk_fileman_widgets::conflict_dialog::keep_both_path(&target))
+                } else if fs::symlink_metadata(&target).is_ok() {
+                    match runner.resolve_conflict(&source, &target) {
+                        Ok(target) => target,
+                        Err(()) => {
+                            outcome.cancelled = true;
+                            break;
+                        },
+                    }
+                } else {
+                    Some(target)
+                };
+                let Some(target) = target else {
+                    outcome.skipped += 1;
+                    continue;
+                };
+                let linked = match runner.attempt(&source, count - index - 1, |_| {
+                    if fs::symlink_metadata(&target).is_ok() {
+                        remove_path(&target)
+                            .map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?;
+                    }
+                    fs::hard_link(&source, &target).map_err(|e| format!("Failed to link {}: {}", source.display(), e))
+                }) {
+                    Ok(linked) => linked,
+                    Err(e) => {
+                        runner.errors.push(e);
+                        outcome.aborted = true;
+                        break;
+                    },
+                };
+                if linked.is_some() {
+                    created.push(target);
+                    outcome.completed += 1;
+                }
+            }
+            outcome.undo = (!created.is_empty()).then_some(UndoRecord::Copied { created });
+        },
+        // Overwritten data is gone, so this can't be undone
+        OperationKind::WriteImage { image, device, target } => {
+            let mut source = fs::File::open(image).map_err(|e| format!("Failed to open {}: {}", image.display(), e))?;
+            let mut target: &fs::File = target;
+            runner.progress.current = device.clone();
+            runner.report();
+            let image_size = source
+                .metadata()
+                .map_err(|e| format!("Failed to read {}: {}", image.display(), e))?
+                .len();
+            let capacity = target
+                .seek(SeekFrom::End(0))
+                .and_then(|capacity| target.seek(SeekFrom::Start(0)).map(|_| capacity))
+                .map_err(|e| format!("Failed to open {}: {}", device.display(), e))?;
+            if image_size > capacity {
+                return Err(format!("{} doesn't fit on {}", image.display(), device.display()));
+            }
+            let mut buffer = vec![0; COPY_CHUNK_SIZE];
+            loop {
+                if runner.cancelled() {
+                    outcome.cancelled = true;
+                    return Ok(outcome);
+                }
+                let read = source
+                    .read(&mut buffer)
+                    .map_err(|e| format!("Failed to read {}: {}", image.display(), e))?;
+                if read == 0 {
+                    break;
+                }
+                target
+                    .write_all(&buffer[..read])
+                    .map_err(|e| format!("Failed to write to {}: {}", device.display(), e))?;
+                runner.add_transferred(read as u64);
+            }
+            target
+                .sync_all()
+                .map_err(|e| format!("Failed to write to {}: {}", device.display(), e))?;
+            // Read back from the drive, not from what is still cached
+            drop_cached(target).map_err(|e| format!("Failed to verify {}: {}", device.display(), e))?;
+
+            source
+                .seek(SeekFrom::Start(0))
+                .and_then(|_| target.seek(SeekFrom::Start(0)))
+                .map_err(|e| format!("Failed to verify {}: {}", device.display(), e))?;
+            let mut written = vec![0; COPY_CHUNK_SIZE];
+            let mut offset = 0;
+            loop {
+                if runner.cancelled() {
+                    outcome.cancelled = true;
+                    return Ok(outcome);
+                }
+                let read = source
+                    .read(&mut buffer)
+                    .map_err(|e| format!("Failed to read {}: {}", image.display(), e))?;
+                if read == 0 {
+                    break;
+                }
+                target
+                    .read_exact(&mut written[..read])
+                    .map_err(|e| format!("Failed to verify {}: {}", device.display(), e))?;
+                if let Some(index) = buffer[..read].iter().zip(&written[..read]).position(|(a, b)| a != b) {
+                    return Err(format!(
+                        "Verification failed: {} differs from the image at byte {}",
+                        device.display(),
+                        offset + index as u64
+                    ));
+                }
+                offset += read as u64;
+                runner.add_bytes(read as u64);
+            }
+            runner.progress.done_items = 1;
+            outcome.completed = 1;
+        },
+    }
+
+    outcome.errors.extend(runner.errors);
+    Ok(outcome)
+}
+
+/// Drop the cached pages of `file`, which must have been synced, so it is
+/// read again from the device.
+fn drop_cached(file: &fs::File) -> io::Result<()> {
+    posix_fadvise(file.as_raw_fd(), 0, 0, PosixFadviseAdvice::POSIX_FADV_DONTNEED).map_err(io::Error::from)
+}
+
+/// Why `source` can't get a hard link in the directory `destination`, if it can't.
+fn hard_link_error(source: &Path, destination: &Path) -> Option<String> {
+    let metadata = match fs::symlink_metadata(source) {
+        Ok(metadata) => metadata,
+        Err(e) => return Some(format!("{}: {}", source.display(), e)),
+    };
+    if metadata.is_dir() {
+        return Some(format!("Cannot link {}: folders can't have hard links", source.display()));
+    }
+    match fs::metadata(destination) {
+        Ok(target) if target.dev() == metadata.dev() => None,
+        Ok(_) => Some(format!(
+            "Cannot link {} into {}: hard links must be on the same file system",
+            source.display(),
+            destination.display()
+        )),
+        Err(e) => Some(format!("{}: {}", destination.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{PermissionsExt, symlink};
+    use std::time::SystemTime;
+
+    #[test]
+    fn copies_folders_with_times_and_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        let file = source.join("sub/file.txt");
+        fs::write(&file, "contents").unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_times(fs::FileTimes::new().set_modified(modified))
+            .unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let target = dir.path().join("target");
+        let mut copied = 0;
+        let mut tree = TreeCopy::new(false);
+        tree.copy(&source, &target, &mut |bytes| copied += bytes).unwrap();
+
+        let copy = target.join("sub/file.txt");
+        let metadata = fs::metadata(&copy).unwrap();
+        assert!(tree.failures.is_empty());
+        assert!(tree.warnings.is_empty());
+        assert_eq!(copied, 8);
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "contents");
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+    }
+
+    #[test]
+    fn keeps_links_unless_following_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("file.txt"), "contents").unwrap();
+        symlink("file.txt", source.join("link")).unwrap();
+
+        let kept = dir.path().join("kept");
+        TreeCopy::new(false).copy(&source, &kept, &mut |_| {}).unwrap();
+        assert_eq!(fs::read_link(kept.join("link")).unwrap(), Path::new("file.txt"));
+
+        let followed = dir.path().join("followed");
+        TreeCopy::new(true).copy(&source, &followed, &mut |_| {}).unwrap();
+        assert!(!fs::symlink_metadata(followed.join("link")).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(followed.join("link")).unwrap(), "contents");
+    }
+
+    #[test]
+    fn stops_at_links_to_a_containing_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("file.txt"), "contents").unwrap();
+        symlink(&source, source.join("loop")).unwrap();
+
+        let target = dir.path().join("target");
+        let mut tree = TreeCopy::new(true);
+        tree.copy(&source, &target, &mut |_| {}).unwrap();
+
+        assert_eq!(tree.failures.len(), 1);
+        assert!(tree.failures[0].contains("loop"));
+        assert!(!target.join("loop").exists());
+        assert_eq!(fs::read_to_string(target.join("file.txt")).unwrap(), "contents");
+    }
+
+    #[test]
+    fn goes_on_after_an_entry_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("a.txt"), "a").unwrap();
+        fs::write(source.join("b.txt"), "b").unwrap();
+        // Following a dangling link fails
+        symlink("missing", source.join("dangling")).unwrap();
+
+        let target = dir.path().join("target");
+        let mut tree = TreeCopy::new(true);
+        tree.copy(&source, &target, &mut |_| {}).unwrap();
+
+        assert_eq!(tree.failures.len(), 1);
+        assert!(tree.failures[0].contains("dangling"));
+        assert_eq!(fs::read_to_string(target.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(target.join("b.txt")).unwrap(), "b");
+    }
+
+    #[test]
+    fn makes_pipes_anew_and_leaves_out_sockets() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("file.txt"), "contents").unwrap();
+        nix::unistd::mkfifo(&source.join("pipe"), Mode::from_bits_truncate(0o640)).unwrap();
+        let _listener = std::os::unix::net::UnixListener::bind(source.join("socket")).unwrap();
+
+        // Would block forever if the pipe were read
+        let target = dir.path().join("target");
+        let mut tree = TreeCopy::new(false);
+        tree.copy(&source, &target, &mut |_| {}).unwrap();
+
+        let pipe = fs::symlink_metadata(target.join("pipe")).unwrap();
+        assert!(pipe.file_type().is_fifo());
+        assert_eq!(pipe.permissions().mode() & 0o777, 0o640);
+        assert_eq!(tree.failures.len(), 1);
+        assert!(tree.failures[0].contains("socket"));
+        assert!(fs::symlink_metadata(target.join("socket")).is_err());
+        assert_eq!(fs::read_to_string(target.join("file.txt")).unwrap(), "contents");
+    }
+
+    #[test]
+    fn cancelling_removes_the_unfinished_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("file.bin");
+        fs::write(&source, vec![0u8; COPY_CHUNK_SIZE * 2]).unwrap();
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut tree = TreeCopy::new(false).with_cancel(cancel.clone());
+        let target = dir.path().join("copy.bin");
+        let result = tree.copy(&source, &target, &mut |_| cancel.store(true, Ordering::Relaxed));
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn overwriting_replaces_a_folder_without_leftovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source/folder");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("new.txt"), "new").unwrap();
+        let destination = dir.path().join("destination");
+        fs::create_dir_all(destination.join("folder")).unwrap();
+        fs::write(destination.join("folder/old.txt"), "old").unwrap();
+
+        let operation = Operation::new(OperationKind::Copy { sources: vec![source], destination: destination.clone() })
+            .with_policy(OverwritePolicy::Overwrite);
+        let (events, _receiver) = mpsc::unbounded_channel();
+        let outcome = run(&operation, &events).unwrap();
+
+        assert_eq!(outcome.completed, 1);
+        assert_eq!(fs::read_to_string(destination.join("folder/new.txt")).unwrap(), "new");
+        assert!(fs::symlink_metadata(destination.join("folder/old.txt")).is_err());
+        assert!(fs::symlink_metadata(destination.join(".folder.fileman-tmp")).is_err());
+    }
+
+    #[test]
+    fn a_dangling_link_is_a_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("file.txt");
+        fs::write(&source, "contents").unwrap();
+        let destination = dir.path().join("destination");
+        fs::create_dir(&destination).unwrap();
+        symlink("missing", destination.join("file.txt")).unwrap();
+
+        let operation = Operation::new(OperationKind::Copy { sources: vec![source], destination: destination.clone() })
+            .with_policy(OverwritePolicy::Skip);
+        let (events, _receiver) = mpsc::unbounded_channel();
+        let outcome = run(&operation, &events).unwrap();
+
+        assert_eq!(outcome.skipped, 1);
+        assert_eq!(fs::read_link(destination.join("file.txt")).unwrap(), Path::new("missing"));
+    }
+
+    #[test]
+    fn refuses_copying_into_itself_before_copying_anything() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        fs::write(&file, "contents").unwrap();
+        let folder = dir.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+
+        let operation =
+            Operation::new(OperationKind::Copy { sources: vec![file, dir.path().to_path_buf()], destination: folder.clone() });
+        let (events, _receiver) = mpsc::unbounded_channel();
+        assert!(run(&operation, &events).unwrap_err().contains("into itself"));
+        assert!(fs::symlink_metadata(folder.join("file.txt")).is_err());
+    }
+
+    #[test]
+    fn an_aborted_move_can_be_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let moved = dir.path().join("moved.txt");
+        fs::write(&moved, "moved").unwrap();
+        let destination = dir.path().join("destination");
+        fs::create_dir(&destination).unwrap();
+
+        // Nobody answers the failure of the missing item, which aborts
+        let operation = Operation::new(OperationKind::Move {
+            sources: vec![moved.clone(), dir.path().join("missing.txt"), dir.path().join("never.txt")],
+            destination: destination.clone(),
+        });
+        let (events, receiver) = mpsc::unbounded_channel();
+        drop(receiver);
+        let outcome = run(&operation, &events).unwrap();
+
+        assert!(outcome.aborted);
+        assert!(!outcome.cancelled);
+        assert_eq!(outcome.completed, 1);
+        assert_eq!(outcome.errors.len(), 1);
+        assert!(outcome.errors[0].contains("missing.txt"));
+        let record = outcome.undo.unwrap();
+        assert_eq!(record, UndoRecord::Moved { moves: vec![(moved.clone(), destination.join("moved.txt"))] });
+        record.undo().result.unwrap();
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "moved");
+    }
+
+    /// Write `image` to the file `device`, collecting the progress reported.
+    fn write_image(image: &Path, device: &Path) -> (Result<OperationOutcome, String>, Vec<Progress>) {
+        let target = fs::File::options().read(true).write(true).open(device).unwrap();
+        let operation = Operation::new(OperationKind::WriteImage {
+            image: image.to_path_buf(),
+            device: device.to_path_buf(),
+            target: Arc::new(target),
+        });
+        let (events, mut receiver) = mpsc::unbounded_channel();
+        let result = run(&operation, &events);
+        let mut progress = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let OperationEvent::Progress(p) = event {
+                progress.push(p);
+            }
+        }
+        (result, progress)
+    }
+
+    #[test]
+    fn writes_and_verifies_an_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("disk.img");
+        let contents: Vec<u8> = (0..COPY_CHUNK_SIZE * 2 + 100).map(|i| i as u8).collect();
+        fs::write(&image, &contents).unwrap();
+        let device = dir.path().join("device");
+        fs::File::create(&device).unwrap().set_len(contents.len() as u64 + 512).unwrap();
+
+        let (result, progress) = write_image(&image, &device);
+
+        assert_eq!(result.unwrap().completed, 1);
+        assert_eq!(&fs::read(&device).unwrap()[..contents.len()], &contents[..]);
+        // Written once and read back once
+        let last = progress.last().unwrap();
+        assert_eq!(last.total_bytes, contents.len() as u64 * 2);
+        assert_eq!(last.done_bytes, last.total_bytes);
+        assert_eq!(last.fraction(), 1.0);
+    }
+
+    #[test]
+    fn refuses_an_image_larger_than_the_device() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("disk.img");
+        fs::write(&image, vec![1u8; 4096]).unwrap();
+        let device = dir.path().join("device");
+        fs::File::create(&device).unwrap().set_len(1024).unwrap();
+
+        let (result, _) = write_image(&image, &device);
+
+        assert!(result.unwrap_err().contains("doesn't fit"));
+        assert_eq!(fs::read(&device).unwrap(), vec![0u8; 1024]);
+    }
+}
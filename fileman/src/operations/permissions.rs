@@ -0,0 +1,163 @@
+//! Batch permission changes
+//!
+//! Applies one mode to files and another to folders, optionally descending into
+//! folders. Failures don't stop the operation; they are collected and reported
+//! together at the end.
+//!
+//! Also provides the optional "Permissions" column, which shows modes the way
+//! `ls -l` does.
+
+mod dialog;
+
+pub use dialog::PermissionsDialog;
+
+use nptk::core::model::ModelData;
+use nptk_fileman_widgets::file_list::model_adapter::ColumnProvider;
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+
+/// Permission bits that can be set (`rwx` for owner, group and others, plus
+/// setuid, setgid and sticky)
+pub const MODE_MASK: u32 = 0o7777;
+
+/// Modes to apply. `None` leaves that kind of entry unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PermissionChange {
+    pub file_mode: Option<u32>,
+    pub dir_mode: Option<u32>,
+    /// Also change everything inside selected folders
+    pub recursive: bool,
+}
+
+impl PermissionChange {
+    /// The mode `path` gets, or `None` if it is left unchanged.
+    fn mode_for(&self, metadata: &fs::Metadata) -> Option<u32> {
+        if metadata.is_dir() { self.dir_mode } else { self.file_mode }
+    }
+}
+
+/// Parse an octal mode such as "755" or "0644". An empty string means unchanged.
+pub fn parse_mode(text: &str) -> Result<Option<u32>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    match u32::from_str_radix(text, 8) {
+        Ok(mode) if mode <= MODE_MASK => Ok(Some(mode)),
+        _ => Err(format!("'{}' is not an octal mode like 644 or 755", text)),
+    }
+}
+
+/// The mode of `metadata` as `ls -l` shows it, e.g. "drwxr-xr-x".
+pub fn mode_string(metadata: &fs::Metadata) -> String {
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_block_device() {
+        'b'
+    } else {
+        '-'
+    };
+    let mode = metadata.permissions().mode();
+    let mut text = String::with_capacity(10);
+    text.push(kind);
+    // (shift, special bit and its letter) for owner, group and others
+    for (shift, special, letter) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = mode >> shift;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => letter,
+            (false, true) => letter.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+/// The "Permissions" column for the Table view, sorted by octal mode.
+pub fn column() -> ColumnProvider {
+    ColumnProvider::new("Permissions", |entry| {
+        ModelData::String(
+            fs::symlink_metadata(&entry.path)
+                .map(|metadata| mode_string(&metadata))
+                .unwrap_or_default(),
+        )
+    })
+    .with_sort_key(|entry| {
+        ModelData::Int(
+            fs::symlink_metadata(&entry.path)
+                .map(|metadata| (metadata.permissions().mode() & MODE_MASK) as i64)
+                .unwrap_or(0),
+        )
+    })
+}
+
+/// Switch the bits in `set` on and those in `clear` off on `path`, keeping its
+/// other bits. Symlinks are left alone.
+pub fn change_bits(path: &Path, set: u32, clear: u32) -> Result<(), String> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+    let mut permissions = metadata.permissions();
+    permissions.set_mode((permissions.mode() & !(clear & MODE_MASK)) | (set & MODE_MASK));
+    fs::set_permissions(path, permissions).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Apply `change` to `path` (and its contents when recursive), calling `visit`
+/// before each entry. `visit` returning `false` stops the walk. Failures are
+/// appended to `errors`.
+pub fn apply(
+    path: &Path,
+    change: &PermissionChange,
+    visit: &mut dyn FnMut(&Path) -> bool,
+    errors: &mut Vec<String>,
+) -> bool {
+    if !visit(path) {
+        return false;
+    }
+    // Symlinks have no permissions of their own; don't follow them either
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => return true,
+        Ok(metadata) => metadata,
+        Err(e) => {
+            errors.push(format!("{}: {}", path.display(), e));
+            return true;
+        },
+    };
+
+    if let Some(mode) = change.mode_for(&metadata) {
+        let mut permissions = metadata.permissions();
+        permissions.set_mode((permissions.mode() & !MODE_MASK) | mode);
+        if let Err(e) = fs::set_permissions(path, permissions) {
+            errors.push(format!("{}: {}", path.display(), e));
+        }
+    }
+
+    if change.recursive && metadata.is_dir() {
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                errors.push(format!("{}: {}", path.display(), e));
+                return true;
+            },
+        };
+        for entry in entries.flatten() {
+            if !apply(&entry.path(), change, visit, errors) {
+                return false;
+            }
+        }
+    }
+    true
+}
@@ -0,0 +1,259 @@
+//! Popup for changing the permissions of many files and folders at once.
+
+use super::{MODE_MASK, PermissionChange, parse_mode};
+use crate::daemon;
+use crate::operations::engine::{Operation, OperationKind};
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Which button was pressed, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PermissionsRequest {
+    ToggleRecursive,
+    Apply,
+    Stop,
+}
+
+/// Octal mode inputs for files and folders, a recursive toggle, and Apply.
+///
+/// The change runs as a job of the operations daemon, so it can be stopped
+/// from here and its errors are summarized in the status bar.
+pub struct PermissionsDialog {
+    paths: Vec<PathBuf>,
+    recursive: bool,
+    inner: Container,
+    file_mode_text: StateSignal<String>,
+    dir_mode_text: StateSignal<String>,
+    message_text: StateSignal<String>,
+    request: Arc<Mutex<Option<PermissionsRequest>>>,
+    // Id of the queued job, once the daemon answered
+    job: Arc<Mutex<Option<Result<u32, String>>>>,
+    job_id: Option<u32>,
+    signals_hooked: bool,
+}
+
+impl PermissionsDialog {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        // Start from the current modes of the first selected file and folder
+        let current_mode = |want_dir: bool| {
+            paths
+                .iter()
+                .filter_map(|p| std::fs::symlink_metadata(p).ok())
+                .find(|m| !m.file_type().is_symlink() && m.is_dir() == want_dir)
+                .map(|m| format!("{:o}", m.permissions().mode() & MODE_MASK))
+                .unwrap_or_default()
+        };
+        let mut dialog = Self {
+            file_mode_text: StateSignal::new(current_mode(false)),
+            dir_mode_text: StateSignal::new(current_mode(true)),
+            paths,
+            recursive: false,
+            inner: Container::new(vec![]),
+            message_text: StateSignal::new(String::new()),
+            request: Arc::new(Mutex::new(None)),
+            job: Arc::new(Mutex::new(None)),
+            job_id: None,
+            signals_hooked: false,
+        };
+        dialog.rebuild();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        let title = format!("Permissions of {} item(s)", self.paths.len());
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), &title, (420, 260), (300, 200));
+    }
+
+    fn button(&self, label: &str, request: PermissionsRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn mode_row(label: &str, text: &StateSignal<String>) -> Container {
+        Container::new(vec![
+            Box::new(Text::new(label.to_string()).with_layout_style(LayoutStyle {
+                size: Vector2::new(Dimension::length(110.0), Dimension::auto()),
+                ..Default::default()
+            })),
+            Box::new(
+                TextInput::new()
+                    .with_text_signal(text.clone())
+                    .with_placeholder("Unchanged".to_string())
+                    .with_layout_style(LayoutStyle {
+                        size: Vector2::new(Dimension::auto(), Dimension::length(30.0)),
+                        flex_grow: 1.0,
+                        ..Default::default()
+                    }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Row,
+            align_items: Some(AlignItems::Center),
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            ..Default::default()
+        })
+    }
+
+    fn rebuild(&mut self) {
+        let mut buttons: Vec<Box<dyn Widget>> = Vec::new();
+        if self.job_id.is_some() {
+            buttons.push(Box::new(self.button("Stop", PermissionsRequest::Stop)));
+        } else {
+            let label = if self.recursive { "Recursive: On" } else { "Recursive: Off" };
+            buttons.push(Box::new(self.button(label, PermissionsRequest::ToggleRecursive)));
+        }
+        buttons.push(Box::new(
+            Button::new(Text::new("Close".to_string())).with_on_pressed(MaybeSignal::value(Update::DRAW)),
+        ));
+        if self.job_id.is_none() {
+            buttons.push(Box::new(self.button("Apply", PermissionsRequest::Apply)));
+        }
+
+        self.inner = Container::new(vec![
+            Box::new(Self::mode_row("Files:", &self.file_mode_text)),
+            Box::new(Self::mode_row("Folders:", &self.dir_mode_text)),
+            Box::new(Text::new(self.message_text.maybe()).with_font_size(12.0)),
+            Box::new(Container::new(buttons).with_layout_style(LayoutStyle {
+                flex_direction: FlexDirection::Row,
+                gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                justify_content: Some(JustifyContent::FlexEnd),
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                ..Default::default()
+            })),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(10.0)),
+            ..Default::default()
+        });
+    }
+
+    /// Validate the inputs and queue the change in the daemon.
+    fn apply(&mut self) {
+        let modes = parse_mode(&self.file_mode_text.get()).and_then(|file_mode| {
+            parse_mode(&self.dir_mode_text.get()).map(|dir_mode| (file_mode, dir_mode))
+        });
+        let (file_mode, dir_mode) = match modes {
+            Ok(modes) => modes,
+            Err(e) => {
+                self.message_text.set(e);
+                return;
+            },
+        };
+        if file_mode.is_none() && dir_mode.is_none() {
+            self.message_text.set("Enter a mode for files or folders".to_string());
+            return;
+        }
+
+        let operation = Operation::new(OperationKind::SetPermissions {
+            paths: self.paths.clone(),
+            change: PermissionChange { file_mode, dir_mode, recursive: self.recursive },
+        });
+        let slot = self.job.clone();
+        tokio::spawn(async move {
+            let result = daemon::client::queue(&operation).await;
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(result);
+            }
+        });
+        self.message_text.set("Starting...".to_string());
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for PermissionsDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.file_mode_text);
+            context.hook_signal(&mut self.dir_mode_text);
+            context.hook_signal(&mut self.message_text);
+            self.signals_hooked = true;
+        }
+
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        match request {
+            Some(PermissionsRequest::ToggleRecursive) => {
+                self.recursive = !self.recursive;
+                self.rebuild();
+                update.insert(Update::LAYOUT | Update::DRAW);
+            },
+            Some(PermissionsRequest::Apply) => {
+                self.apply();
+                update.insert(Update::DRAW);
+            },
+            Some(PermissionsRequest::Stop) => {
+                if let Some(id) = self.job_id {
+                    tokio::spawn(async move {
+                        if let Err(e) = daemon::client::cancel(id).await {
+                            log::error!("{}", e);
+                        }
+                    });
+                    self.message_text.set("Stopped".to_string());
+                }
+                update.insert(Update::DRAW);
+            },
+            None => {},
+        }
+
+        let queued = self.job.lock().ok().and_then(|mut j| j.take());
+        match queued {
+            Some(Ok(id)) => {
+                self.job_id = Some(id);
+                self.message_text.set("Changing permissions; errors are shown in the status bar".to_string());
+                self.rebuild();
+                update.insert(Update::LAYOUT | Update::DRAW);
+            },
+            Some(Err(e)) => {
+                log::error!("{}", e);
+                self.message_text.set(e);
+                update.insert(Update::DRAW);
+            },
+            None => {},
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,228 @@
+//! One-way folder synchronization
+//!
+//! Makes a destination folder match a source folder: entries missing or outdated in
+//! the destination are copied over, and with mirroring, entries that only exist in
+//! the destination are deleted. [plan] computes the actions without touching
+//! anything, for dry-run previews; the engine executes a fresh plan.
+
+mod dialog;
+
+pub use dialog::SyncDialog;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Why an entry is copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyReason {
+    /// Missing in the destination
+    New,
+    /// Size differs or the source is newer
+    Changed,
+    /// Folder on one side, file on the other
+    KindChanged,
+}
+
+/// A single step of a synchronization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncAction {
+    /// Copy `source` to `target`, replacing whatever is there.
+    Copy { source: PathBuf, target: PathBuf, reason: CopyReason },
+    /// Delete an entry that doesn't exist in the source (mirroring only).
+    Delete(PathBuf),
+}
+
+impl SyncAction {
+    pub fn describe(&self) -> String {
+        match self {
+            SyncAction::Copy { target, reason, .. } => {
+                let reason = match reason {
+                    CopyReason::New => "new",
+                    CopyReason::Changed => "changed",
+                    CopyReason::KindChanged => "replaced",
+                };
+                format!("Copy {} ({})", target.display(), reason)
+            },
+            SyncAction::Delete(path) => format!("Delete {}", path.display()),
+        }
+    }
+}
+
+/// Everything a synchronization would do.
+#[derive(Debug, Clone, Default)]
+pub struct SyncPlan {
+    pub actions: Vec<SyncAction>,
+}
+
+impl SyncPlan {
+    pub fn copies(&self) -> usize {
+        self.actions.iter().filter(|a| matches!(a, SyncAction::Copy { .. })).count()
+    }
+
+    pub fn deletions(&self) -> usize {
+        self.actions.iter().filter(|a| matches!(a, SyncAction::Delete(_))).count()
+    }
+}
+
+fn is_newer(source: &fs::Metadata, target: &fs::Metadata) -> bool {
+    let modified = |meta: &fs::Metadata| meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    modified(source) > modified(target)
+}
+
+fn plan_into(source: &Path, destination: &Path, mirror: bool, actions: &mut Vec<SyncAction>) -> Result<(), String> {
+    let read = |dir: &Path| -> Result<Vec<(String, PathBuf)>, String> {
+        let mut entries: Vec<(String, PathBuf)> = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path()))
+            .collect();
+        entries.sort();
+        Ok(entries)
+    };
+    let source_entries = read(source)?;
+
+    for (name, source_path) in &source_entries {
+        let target = destination.join(name);
+        let source_meta = fs::symlink_metadata(source_path)
+            .map_err(|e| format!("Failed to read {}: {}", source_path.display(), e))?;
+        let Ok(target_meta) = fs::symlink_metadata(&target) else {
+            actions.push(SyncAction::Copy { source: source_path.clone(), target, reason: CopyReason::New });
+            continue;
+        };
+
+        match (source_meta.is_dir(), target_meta.is_dir()) {
+            (true, true) => plan_into(source_path, &target, mirror, actions)?,
+            (false, false) => {
+                if source_meta.len() != target_meta.len() || is_newer(&source_meta, &target_meta) {
+                    actions.push(SyncAction::Copy {
+                        source: source_path.clone(),
+                        target,
+                        reason: CopyReason::Changed,
+                    });
+                }
+            },
+            _ => actions.push(SyncAction::Copy {
+                source: source_path.clone(),
+                target,
+                reason: CopyReason::KindChanged,
+            }),
+        }
+    }
+
+    if mirror {
+        for (name, target) in read(destination)? {
+            if source_entries.binary_search_by(|(n, _)| n.cmp(&name)).is_err() {
+                actions.push(SyncAction::Delete(target));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Plan synchronizing `destination` with `source`. With `mirror`, extraneous
+/// entries in the destination are deleted.
+pub fn plan(source: &Path, destination: &Path, mirror: bool) -> Result<SyncPlan, String> {
+    if !source.is_dir() {
+        return Err(format!("{} is not a directory", source.display()));
+    }
+    if !destination.is_dir() {
+        return Err(format!("{} is not a directory", destination.display()));
+    }
+    if destination.starts_with(source) || source.starts_with(destination) {
+        return Err("Cannot synchronize a folder with a folder inside it".to_string());
+    }
+    let mut actions = Vec::new();
+    plan_into(source, destination, mirror, &mut actions)?;
+    Ok(SyncPlan { actions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Source and destination folders, removed when dropped
+    struct Folders(tempfile::TempDir);
+
+    impl Folders {
+        fn new() -> Self {
+            let root = tempfile::tempdir().unwrap();
+            fs::create_dir(root.path().join("src")).unwrap();
+            fs::create_dir(root.path().join("dest")).unwrap();
+            Folders(root)
+        }
+
+        fn source(&self) -> PathBuf {
+            self.0.path().join("src")
+        }
+
+        fn destination(&self) -> PathBuf {
+            self.0.path().join("dest")
+        }
+    }
+
+    fn write(path: &Path, content: &str, modified: SystemTime) {
+        fs::write(path, content).unwrap();
+        fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn plans_what_differs() {
+        let folders = Folders::new();
+        let (src, dest) = (folders.source(), folders.destination());
+        let earlier = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let later = earlier + Duration::from_secs(60);
+
+        write(&src.join("new.txt"), "new", earlier);
+        write(&src.join("same.txt"), "same", earlier);
+        write(&dest.join("same.txt"), "same", earlier);
+        write(&src.join("changed.txt"), "longer now", earlier);
+        write(&dest.join("changed.txt"), "short", later);
+        write(&src.join("touched.txt"), "one", later);
+        write(&dest.join("touched.txt"), "two", earlier);
+        write(&src.join("kind"), "a file", earlier);
+        fs::create_dir(dest.join("kind")).unwrap();
+        fs::create_dir(src.join("sub")).unwrap();
+        fs::create_dir(dest.join("sub")).unwrap();
+        write(&src.join("sub/inner.txt"), "inner", earlier);
+        write(&dest.join("sub/stale.txt"), "stale", earlier);
+        write(&dest.join("extra.txt"), "extra", earlier);
+
+        let copy = |name: &str, reason| SyncAction::Copy { source: src.join(name), target: dest.join(name), reason };
+        let copies = vec![
+            copy("changed.txt", CopyReason::Changed),
+            copy("kind", CopyReason::KindChanged),
+            copy("new.txt", CopyReason::New),
+            copy("sub/inner.txt", CopyReason::New),
+            copy("touched.txt", CopyReason::Changed),
+        ];
+        let planned = plan(&src, &dest, false).unwrap();
+        assert_eq!(planned.actions, copies);
+        assert_eq!((planned.copies(), planned.deletions()), (5, 0));
+
+        // Entries only in the destination go, those in subfolders with their folder
+        let mut mirrored = copies.clone();
+        mirrored.insert(4, SyncAction::Delete(dest.join("sub/stale.txt")));
+        mirrored.push(SyncAction::Delete(dest.join("extra.txt")));
+        let planned = plan(&src, &dest, true).unwrap();
+        assert_eq!(planned.actions, mirrored);
+        assert_eq!((planned.copies(), planned.deletions()), (5, 2));
+        assert_eq!(planned.actions[1].describe(), format!("Copy {} (replaced)", dest.join("kind").display()));
+        assert_eq!(planned.actions[6].describe(), format!("Delete {}", dest.join("extra.txt").display()));
+    }
+
+    #[test]
+    fn refuses_folders_inside_each_other() {
+        let folders = Folders::new();
+        let (src, dest) = (folders.source(), folders.destination());
+        fs::create_dir(src.join("inner")).unwrap();
+        assert!(plan(&src, &src.join("inner"), false).is_err());
+        assert!(plan(&src.join("inner"), &src, false).is_err());
+        assert!(plan(&src, &src, false).is_err());
+        assert!(plan(&src, &dest.join("missing"), false).is_err());
+        write(&src.join("file"), "", SystemTime::now());
+        assert!(plan(&src.join("file"), &dest, false).is_err());
+        assert_eq!(plan(&src, &dest, true).unwrap().copies(), 2);
+    }
+}
@@ -0,0 +1,200 @@
+//! Dry-run preview shown before synchronizing two folders.
+
+use super::{SyncAction, SyncPlan};
+use crate::daemon;
+use crate::operations::engine::{Operation, OperationKind};
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::scroll_container::{ScrollContainer, ScrollDirection};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Which button was pressed, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncRequest {
+    ToggleMirror,
+    Start,
+}
+
+/// Lists what a synchronization would copy and delete, and queues it in the
+/// operations daemon on confirmation.
+///
+/// The plan is computed with mirroring so deletions can be previewed; they are
+/// only listed and executed while "Delete Extraneous" is on.
+pub struct SyncDialog {
+    source: PathBuf,
+    destination: PathBuf,
+    plan: SyncPlan,
+    mirror: bool,
+    started: bool,
+    inner: Container,
+    request: Arc<Mutex<Option<SyncRequest>>>,
+}
+
+impl SyncDialog {
+    pub fn new(source: PathBuf, destination: PathBuf, plan: SyncPlan) -> Self {
+        let mut dialog = Self {
+            source,
+            destination,
+            plan,
+            mirror: false,
+            started: false,
+            inner: Container::new(vec![]),
+            request: Arc::new(Mutex::new(None)),
+        };
+        dialog.rebuild();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        let title = format!("Synchronize {}", self.destination.display());
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), &title, (640, 420), (200, 150));
+    }
+
+    fn button(&self, label: &str, request: SyncRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn visible_actions(&self) -> impl Iterator<Item = &SyncAction> {
+        self.plan
+            .actions
+            .iter()
+            .filter(|action| self.mirror || !matches!(action, SyncAction::Delete(_)))
+    }
+
+    fn rebuild(&mut self) {
+        let mut rows: Vec<Box<dyn Widget>> = self
+            .visible_actions()
+            .map(|action| Box::new(Text::new(action.describe()).with_font_size(12.0)) as Box<dyn Widget>)
+            .collect();
+        let empty = rows.is_empty();
+        if empty {
+            rows.push(Box::new(Text::new("The destination is up to date".to_string())));
+        }
+
+        let deletions = if self.mirror { self.plan.deletions() } else { 0 };
+        let summary = if self.started {
+            "Synchronization queued".to_string()
+        } else {
+            format!("{} to copy, {} to delete", self.plan.copies(), deletions)
+        };
+
+        let mut buttons: Vec<Box<dyn Widget>> = Vec::new();
+        if self.plan.deletions() > 0 && !self.started {
+            let label = if self.mirror { "Keep Extraneous" } else { "Delete Extraneous" };
+            buttons.push(Box::new(self.button(label, SyncRequest::ToggleMirror)));
+        }
+        buttons.push(Box::new(
+            Button::new(Text::new("Close".to_string())).with_on_pressed(MaybeSignal::value(Update::DRAW)),
+        ));
+        if !empty && !self.started {
+            buttons.push(Box::new(self.button("Synchronize", SyncRequest::Start)));
+        }
+
+        let list = Container::new(rows).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(4.0)),
+            ..Default::default()
+        });
+
+        self.inner = Container::new(vec![
+            Box::new(Text::new(format!("From: {}", self.source.display()))),
+            Box::new(Text::new(format!("To: {}", self.destination.display()))),
+            Box::new(
+                ScrollContainer::new()
+                    .with_scroll_direction(ScrollDirection::Vertical)
+                    .with_child(list)
+                    .with_layout_style(LayoutStyle {
+                        size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                        flex_grow: 1.0,
+                        ..Default::default()
+                    }),
+            ),
+            Box::new(Text::new(summary).with_font_size(12.0)),
+            Box::new(Container::new(buttons).with_layout_style(LayoutStyle {
+                flex_direction: FlexDirection::Row,
+                gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                justify_content: Some(JustifyContent::FlexEnd),
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                ..Default::default()
+            })),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(8.0)),
+            ..Default::default()
+        });
+    }
+
+    /// Queue the synchronization in the daemon, which plans it afresh.
+    fn start(&self) {
+        let operation = Operation::new(OperationKind::Sync {
+            source: self.source.clone(),
+            destination: self.destination.clone(),
+            mirror: self.mirror,
+        });
+        tokio::spawn(async move {
+            if let Err(e) = daemon::client::queue(&operation).await {
+                log::error!("{}", e);
+            }
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for SyncDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        match request {
+            Some(SyncRequest::ToggleMirror) => self.mirror = !self.mirror,
+            Some(SyncRequest::Start) if !self.started => {
+                self.start();
+                self.started = true;
+            },
+            _ => {},
+        }
+        if request.is_some() {
+            self.rebuild();
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
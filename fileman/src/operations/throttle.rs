@@ -0,0 +1,266 @@
+//! Transfer speed limits
+//!
+//! Copied data is metered by two token buckets: one per operation and one shared
+//! by every operation in the process (the daemon). Limits are read again for every
+//! chunk, so changing one takes effect while a transfer is running.
+
+mod dialog;
+
+pub use dialog::SpeedLimitDialog;
+
+use nptk_fileman_widgets::xdg;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Limit value meaning "no limit"
+pub const UNLIMITED: u64 = 0;
+
+/// How much unused allowance may be saved up, in seconds at the current rate
+const BURST_SECONDS: f64 = 0.5;
+/// Longest sleep between checks for cancellation while waiting
+const WAIT_SLICE: Duration = Duration::from_millis(100);
+
+/// Limit shared by all operations of the process, in bytes per second
+static GLOBAL_LIMIT: AtomicU64 = AtomicU64::new(UNLIMITED);
+static GLOBAL_BUCKET: Mutex<Bucket> = Mutex::new(Bucket {
+    tokens: 0.0,
+    last: None,
+});
+
+/// A bytes-per-second limit that can be changed while a transfer runs.
+#[derive(Debug, Clone, Default)]
+pub struct SpeedLimit(Arc<AtomicU64>);
+
+impl SpeedLimit {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(bytes_per_second)))
+    }
+
+    /// Bytes per second, or [UNLIMITED].
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, bytes_per_second: u64) {
+        self.0.store(bytes_per_second, Ordering::Relaxed);
+    }
+}
+
+/// Allowance of bytes that refills at the limit's rate. Sending more than the
+/// allowance puts it into debt, which is paid off by waiting.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Option<Instant>,
+}
+
+impl Bucket {
+    /// Take `bytes` at `rate` bytes per second; returns how long to wait before sending more.
+    fn take(&mut self, bytes: u64, rate: u64) -> Duration {
+        if rate == UNLIMITED {
+            self.last = None;
+            return Duration::ZERO;
+        }
+        let now = Instant::now();
+        let rate = rate as f64;
+        self.tokens = match self.last {
+            Some(last) => (self.tokens + now.duration_since(last).as_secs_f64() * rate)
+                .min(rate * BURST_SECONDS),
+            // Start from an empty allowance when the limit is (re)enabled
+            None => 0.0,
+        };
+        self.last = Some(now);
+        self.tokens -= bytes as f64;
+        if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+/// Meters the data written by one operation.
+#[derive(Debug)]
+pub struct Throttle {
+    limit: SpeedLimit,
+    bucket: Bucket,
+    /// Stops waiting once set
+    cancel: Arc<AtomicBool>,
+}
+
+impl Throttle {
+    pub fn new(limit: SpeedLimit) -> Self {
+        Self {
+            limit,
+            bucket: Bucket {
+                tokens: 0.0,
+                last: None,
+            },
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Stop waiting as soon as `cancel` is set.
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Account for `bytes` just written, sleeping as long as either limit
+    /// requires or until cancelled. Time spent paused meanwhile counts
+    /// towards the wait, as nothing is sent.
+    pub fn wait(&mut self, bytes: u64) {
+        let own = self.bucket.take(bytes, self.limit.get());
+        let global = GLOBAL_BUCKET
+            .lock()
+            .map(|mut bucket| bucket.take(bytes, global_limit()))
+            .unwrap_or_default();
+        let until = Instant::now() + own.max(global);
+        while !self.cancel.load(Ordering::Relaxed) {
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            std::thread::sleep(left.min(WAIT_SLICE));
+        }
+    }
+}
+
+/// Limit shared by all operations, in bytes per second.
+pub fn global_limit() -> u64 {
+    GLOBAL_LIMIT.load(Ordering::Relaxed)
+}
+
+pub fn set_global_limit(bytes_per_second: u64) {
+    GLOBAL_LIMIT.store(bytes_per_second, Ordering::Relaxed);
+}
+
+/// Where the global limit is kept between daemon runs.
+fn config_path() -> PathBuf {
+    xdg::config_home().join("fileman/speed-limit")
+}
+
+/// Read the saved global limit ([UNLIMITED] if none was saved).
+pub fn load_global_limit() -> u64 {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|content| content.trim().parse().ok())
+        .unwrap_or(UNLIMITED)
+}
+
+/// Save the global limit for the next daemon run.
+pub fn save_global_limit(bytes_per_second: u64) -> Result<(), String> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to save speed limit: {}", e))?;
+    }
+    std::fs::write(&path, format!("{}\n", bytes_per_second))
+        .map_err(|e| format!("Failed to save speed limit: {}", e))
+}
+
+/// Parse a rate such as `500K`, `2.5M` or `1G` (bytes per second, binary units).
+/// An empty string, `0` or `unlimited` means no limit; other rates are at
+/// least 1 byte per second.
+pub fn parse_rate(text: &str) -> Result<u64, String> {
+    let text = text
+        .trim()
+        .trim_end_matches("/s")
+        .trim_end_matches(['B', 'b']);
+    if text.is_empty() || text.eq_ignore_ascii_case("unlimited") {
+        return Ok(UNLIMITED);
+    }
+    let (number, multiplier) = match text.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&text[..text.len() - 1], 1024.0),
+        Some('M') => (&text[..text.len() - 1], 1024.0 * 1024.0),
+        Some('G') => (&text[..text.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        _ => (text, 1.0),
+    };
+    match number.trim().parse::<f64>() {
+        Ok(value) if value == 0.0 => Ok(UNLIMITED),
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(((value * multiplier).round() as u64).max(1)),
+        _ => Err(format!("Invalid speed '{}' (e.g. 500K, 2M)", text)),
+    }
+}
+
+/// Format a rate the way [parse_rate] reads it back, e.g. `2M` or `1.55M`;
+/// empty when unlimited. Rates that take more than three decimals in a unit
+/// are given in a smaller one.
+pub fn format_rate(bytes_per_second: u64) -> String {
+    let units = [(1024 * 1024 * 1024, "G"), (1024 * 1024, "M"), (1024, "K")];
+    if bytes_per_second == UNLIMITED {
+        return String::new();
+    }
+    units
+        .iter()
+        .filter(|(size, _)| bytes_per_second >= *size)
+        .find_map(|(size, unit)| {
+            let value = bytes_per_second as f64 / *size as f64;
+            (0..=3)
+                .map(|decimals| format!("{:.*}{}", decimals, value, unit))
+                .find(|text| parse_rate(text) == Ok(bytes_per_second))
+        })
+        .unwrap_or_else(|| bytes_per_second.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rates() {
+        assert_eq!(parse_rate(""), Ok(UNLIMITED));
+        assert_eq!(parse_rate("unlimited"), Ok(UNLIMITED));
+        assert_eq!(parse_rate("0"), Ok(UNLIMITED));
+        assert_eq!(parse_rate("0K"), Ok(UNLIMITED));
+        assert_eq!(parse_rate("500"), Ok(500));
+        assert_eq!(parse_rate("500K"), Ok(500 * 1024));
+        assert_eq!(parse_rate(" 2.5m/s "), Ok(5 * 512 * 1024));
+        assert_eq!(parse_rate("1GB"), Ok(1024 * 1024 * 1024));
+        assert_eq!(parse_rate("1.5 K"), Ok(1536));
+        // Too small to round to a byte, but not meant as unlimited
+        assert_eq!(parse_rate("0.4"), Ok(1));
+        assert!(parse_rate("-1K").is_err());
+        assert!(parse_rate("fast").is_err());
+        assert!(parse_rate("inf").is_err());
+    }
+
+    #[test]
+    fn formats_rates_that_parse_back() {
+        assert_eq!(format_rate(UNLIMITED), "");
+        assert_eq!(format_rate(500), "500");
+        assert_eq!(format_rate(2 * 1024 * 1024), "2M");
+        assert_eq!(format_rate(1536), "1.5K");
+        assert_eq!(format_rate(parse_rate("1.55M").unwrap()), "1.55M");
+        for rate in (1..5000).chain((0..2000).map(|i| 1_000_000 + i * 7919)) {
+            assert_eq!(parse_rate(&format_rate(rate)), Ok(rate), "{}", format_rate(rate));
+        }
+    }
+
+    #[test]
+    fn buckets_make_senders_wait_for_their_debt() {
+        let mut bucket = Bucket { tokens: 0.0, last: None };
+        assert_eq!(bucket.take(1000, UNLIMITED), Duration::ZERO);
+        // Starts with an empty allowance
+        let first = bucket.take(1000, 1000);
+        assert!((first.as_secs_f64() - 1.0).abs() < 0.01, "{:?}", first);
+        // The debt is still there right after
+        let second = bucket.take(1000, 1000);
+        assert!(second.as_secs_f64() > 1.9 && second.as_secs_f64() <= 2.0, "{:?}", second);
+        // Removing the limit forgets the debt
+        assert_eq!(bucket.take(1000, UNLIMITED), Duration::ZERO);
+        let again = bucket.take(500, 1000);
+        assert!((again.as_secs_f64() - 0.5).abs() < 0.01, "{:?}", again);
+    }
+
+    #[test]
+    fn cancelling_stops_the_wait() {
+        let cancel = Arc::new(AtomicBool::new(true));
+        let mut throttle = Throttle::new(SpeedLimit::new(1)).with_cancel(cancel);
+        let started = Instant::now();
+        throttle.wait(1024 * 1024);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
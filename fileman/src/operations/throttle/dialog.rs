@@ -0,0 +1,259 @@
+//! Popup for changing transfer speed limits while transfers run.
+
+use super::{format_rate, parse_rate};
+use crate::daemon::{self, JobInfo};
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+use std::sync::{Arc, Mutex};
+
+/// A limit input for the daemon's shared limit and one for each unfinished job.
+///
+/// Limits are entered like `500K` or `2M` (bytes per second); an empty input
+/// means unlimited.
+pub struct SpeedLimitDialog {
+    inner: Container,
+    global_text: StateSignal<String>,
+    /// (job id, limit input)
+    job_texts: Vec<(u32, StateSignal<String>)>,
+    message_text: StateSignal<String>,
+    apply_requested: Arc<Mutex<bool>>,
+    // Current shared limit, read from the daemon in the background
+    global_loaded: Arc<Mutex<Option<u64>>>,
+    applied: Arc<Mutex<Option<Result<(), String>>>>,
+    signals_hooked: bool,
+}
+
+impl SpeedLimitDialog {
+    pub fn new(jobs: &[JobInfo]) -> Self {
+        let global_loaded = Arc::new(Mutex::new(None));
+        let slot = global_loaded.clone();
+        tokio::spawn(async move {
+            let limit = daemon::client::global_speed_limit().await;
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(limit);
+            }
+        });
+
+        let jobs: Vec<&JobInfo> = jobs.iter().filter(|job| job.state.is_active()).collect();
+        let job_texts: Vec<(u32, StateSignal<String>)> = jobs
+            .iter()
+            .map(|job| (job.id, StateSignal::new(format_rate(job.speed_limit))))
+            .collect();
+        let global_text = StateSignal::new(String::new());
+        let message_text = StateSignal::new(String::new());
+        let apply_requested = Arc::new(Mutex::new(false));
+
+        let mut rows: Vec<Box<dyn Widget>> =
+            vec![Box::new(Self::limit_row("All transfers:", &global_text))];
+        if jobs.is_empty() {
+            rows.push(Box::new(
+                Text::new("No transfers running".to_string()).with_font_size(12.0),
+            ));
+        }
+        for (job, (_, text)) in jobs.iter().zip(&job_texts) {
+            rows.push(Box::new(
+                Text::new(job.description.clone()).with_font_size(12.0),
+            ));
+            rows.push(Box::new(Self::limit_row("This transfer:", text)));
+        }
+        rows.push(Box::new(
+            Text::new(message_text.maybe()).with_font_size(12.0),
+        ));
+
+        let apply_flag = apply_requested.clone();
+        let buttons: Vec<Box<dyn Widget>> = vec![
+            Box::new(
+                Button::new(Text::new("Close".to_string()))
+                    .with_on_pressed(MaybeSignal::value(Update::DRAW)),
+            ),
+            Box::new(Button::new(Text::new("Apply".to_string())).with_on_pressed(
+                MaybeSignal::signal(Box::new(EvalSignal::new(move || {
+                    if let Ok(mut flag) = apply_flag.lock() {
+                        *flag = true;
+                    }
+                    Update::DRAW
+                }))),
+            )),
+        ];
+        rows.push(Box::new(Container::new(buttons).with_layout_style(
+            LayoutStyle {
+                flex_direction: FlexDirection::Row,
+                gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                justify_content: Some(JustifyContent::FlexEnd),
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                ..Default::default()
+            },
+        )));
+
+        let inner = Container::new(rows).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(
+                LengthPercentage::length(0.0),
+                LengthPercentage::length(10.0),
+            ),
+            ..Default::default()
+        });
+
+        Self {
+            inner,
+            global_text,
+            job_texts,
+            message_text,
+            apply_requested,
+            global_loaded,
+            applied: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        }
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        // Room for the shared limit plus a description and input per job
+        let height = 180 + 70 * self.job_texts.len() as _;
+        context.popup_manager.create_popup_at(
+            Box::new(self),
+            "Transfer Speed",
+            (420, height),
+            (300, 200),
+        );
+    }
+
+    fn limit_row(label: &str, text: &StateSignal<String>) -> Container {
+        Container::new(vec![
+            Box::new(Text::new(label.to_string()).with_layout_style(LayoutStyle {
+                size: Vector2::new(Dimension::length(110.0), Dimension::auto()),
+                ..Default::default()
+            })),
+            Box::new(
+                TextInput::new()
+                    .with_text_signal(text.clone())
+                    .with_placeholder("Unlimited".to_string())
+                    .with_layout_style(LayoutStyle {
+                        size: Vector2::new(Dimension::auto(), Dimension::length(30.0)),
+                        flex_grow: 1.0,
+                        ..Default::default()
+                    }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Row,
+            align_items: Some(AlignItems::Center),
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            ..Default::default()
+        })
+    }
+
+    /// Validate the inputs and send the limits to the daemon.
+    fn apply(&mut self) {
+        let global = parse_rate(&self.global_text.get());
+        let jobs: Result<Vec<(u32, u64)>, String> = self
+            .job_texts
+            .iter()
+            .map(|(id, text)| parse_rate(&text.get()).map(|limit| (*id, limit)))
+            .collect();
+        let (global, jobs) = match global.and_then(|global| jobs.map(|jobs| (global, jobs))) {
+            Ok(limits) => limits,
+            Err(e) => {
+                self.message_text.set(e);
+                return;
+            },
+        };
+
+        let slot = self.applied.clone();
+        tokio::spawn(async move {
+            let result = daemon::client::set_global_speed_limit(global).await;
+            for (id, limit) in jobs {
+                // The job may have finished since the dialog was opened
+                if let Err(e) = daemon::client::set_speed_limit(id, limit).await {
+                    log::debug!("{}", e);
+                }
+            }
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(result);
+            }
+        });
+        self.message_text.set("Applying...".to_string());
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for SpeedLimitDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(
+        &mut self,
+        layout: &LayoutNode,
+        context: AppContext,
+        info: &mut AppInfo,
+    ) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.global_text);
+            context.hook_signal(&mut self.message_text);
+            for (_, text) in &mut self.job_texts {
+                context.hook_signal(text);
+            }
+            self.signals_hooked = true;
+        }
+
+        if let Some(limit) = self.global_loaded.lock().ok().and_then(|mut l| l.take()) {
+            // Don't overwrite what the user typed in the meantime
+            if self.global_text.get().is_empty() {
+                self.global_text.set(format_rate(limit));
+            }
+            update.insert(Update::DRAW);
+        }
+
+        let apply = self
+            .apply_requested
+            .lock()
+            .map(|mut flag| std::mem::take(&mut *flag))
+            .unwrap_or(false);
+        if apply {
+            self.apply();
+            update.insert(Update::DRAW);
+        }
+
+        match self.applied.lock().ok().and_then(|mut a| a.take()) {
+            Some(Ok(())) => {
+                self.message_text.set("Speed limits changed".to_string());
+                update.insert(Update::DRAW);
+            },
+            Some(Err(e)) => {
+                log::error!("{}", e);
+                self.message_text.set(e);
+                update.insert(Update::DRAW);
+            },
+            None => {},
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,355 @@
+//! Freedesktop.org trash
+//!
+//! Files are moved to `$XDG_DATA_HOME/Trash/files` with a matching `.trashinfo`
+//! file in `Trash/info` recording the original path and deletion date. Items on
+//! other file systems go to a trash at the top of their file system instead
+//! (`.Trash/$uid` if the administrator set one up, otherwise `.Trash-$uid`), so
+//! trashing them doesn't copy them; their `Path=` is relative to that top
+//! directory. Only where no such trash can be made are they copied home.
+//! [purge] permanently deletes old items from the home trash and keeps it below
+//! a size.
+
+use super::engine;
+use crate::uri::encode_path;
+use chrono::{Local, NaiveDateTime};
+use nix::unistd::getuid;
+use nptk::services::thumbnail::npio_adapter::uri_to_path;
+use nptk_fileman_widgets::xdg;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Sticky bit, required on a shared `$topdir/.Trash`
+const STICKY: u32 = 0o1000;
+
+/// A trashed item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashEntry {
+    /// Where the item was before it was trashed
+    pub original: PathBuf,
+    /// Where the item is now, inside `Trash/files`
+    pub trashed: PathBuf,
+}
+
+/// The home trash directory.
+pub fn trash_dir() -> PathBuf {
+    xdg::data_home().join("Trash")
+}
+
+/// The top directory of the file system `path` is on: its highest ancestor on
+/// the same device.
+fn top_dir(path: &Path) -> Option<PathBuf> {
+    let device = fs::symlink_metadata(path).ok()?.dev();
+    let mut top = path;
+    for ancestor in path.ancestors().skip(1) {
+        if fs::metadata(ancestor).ok()?.dev() != device {
+            break;
+        }
+        top = ancestor;
+    }
+    Some(top.to_path_buf())
+}
+
+/// The trash of the user at the top directory `top` of a file system, created
+/// if needed. `None` if there can't be one there.
+fn top_dir_trash(top: &Path) -> Option<PathBuf> {
+    let uid = getuid().as_raw();
+    // A shared trash only counts if it can't be tampered with
+    let shared = top.join(".Trash");
+    if let Ok(metadata) = fs::symlink_metadata(&shared) {
+        if metadata.is_dir() && metadata.permissions().mode() & STICKY != 0 {
+            let own = shared.join(uid.to_string());
+            if fs::DirBuilder::new().recursive(true).mode(0o700).create(&own).is_ok() {
+                return Some(own);
+            }
+        }
+    }
+    let own = top.join(format!(".Trash-{}", uid));
+    match fs::symlink_metadata(&own) {
+        Ok(metadata) => (metadata.is_dir() && metadata.uid() == uid).then_some(own),
+        Err(_) => fs::DirBuilder::new().mode(0o700).create(&own).ok().map(|()| own),
+    }
+}
+
+/// The trash `original` goes to and the `Path=` recorded for it there.
+fn trash_for(original: &Path, home_trash: &Path) -> (PathBuf, String) {
+    let home_device = home_trash
+        .ancestors()
+        .find_map(|dir| fs::metadata(dir).ok())
+        .map(|metadata| metadata.dev());
+    let device = fs::symlink_metadata(original).ok().map(|metadata| metadata.dev());
+    if device.is_some() && device != home_device {
+        let top_trash = top_dir(original).and_then(|top| Some((top_dir_trash(&top)?, top)));
+        if let Some((trash, top)) = top_trash {
+            if let Ok(relative) = original.strip_prefix(&top) {
+                return (trash, encode_path(relative));
+            }
+        }
+    }
+    (home_trash.to_path_buf(), encode_path(original))
+}
+
+/// The trash directory holding `trashed`, an item directly inside its `files`.
+fn containing_trash(trashed: &Path) -> Option<&Path> {
+    let files = trashed.parent()?;
+    if files.file_name()? != "files" {
+        return None;
+    }
+    files.parent()
+}
+
+/// Whether `dir` is the `files` folder of a trash, judged by the names alone
+/// so it can be asked on the UI thread.
+pub fn is_trash_files(dir: &Path) -> bool {
+    let Some(trash) = dir.parent().filter(|_| dir.file_name() == Some(OsStr::new("files"))) else {
+        return false;
+    };
+    let name = trash.file_name().unwrap_or_default().to_string_lossy();
+    name == "Trash"
+        || name.starts_with(".Trash-")
+        || trash.parent().and_then(Path::file_name) == Some(OsStr::new(".Trash"))
+}
+
+/// The `.trashinfo` of `trashed`, an item directly inside a trash's `files`.
+fn info_path(trashed: &Path) -> Option<PathBuf> {
+    let name = trashed.file_name()?.to_string_lossy().to_string();
+    Some(containing_trash(trashed)?.join("info").join(format!("{}.trashinfo", name)))
+}
+
+/// Reserve a name in `Trash/files` by creating its `.trashinfo` with
+/// `O_EXCL`, numbering the name until no other item holds it. Returns the name
+/// and its info file, still open for writing.
+fn reserve_trash_name(
+    files_dir: &Path,
+    info_dir: &Path,
+    name: &str,
+) -> Result<(String, PathBuf, fs::File), String> {
+    for candidate in std::iter::once(name.to_string()).chain((2..).map(|n| format!("{}.{}", name, n))) {
+        if fs::symlink_metadata(files_dir.join(&candidate)).is_ok() {
+            continue;
+        }
+        let info_path = info_dir.join(format!("{}.trashinfo", candidate));
+        match fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(file) => return Ok((candidate, info_path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to write trash info: {}", e)),
+        }
+    }
+    unreachable!("unbounded range always yields a free name")
+}
+
+/// Move `path` to the trash. A symlink is trashed itself, not what it points to.
+pub fn trash_path(path: &Path) -> Result<TrashEntry, String> {
+    trash_path_in(path, &trash_dir())
+}
+
+/// [trash_path] with `home_trash` as the home trash.
+fn trash_path_in(path: &Path, home_trash: &Path) -> Result<TrashEntry, String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Cannot trash {}", path.display()))?;
+    // Only the parent is resolved, so the last component stays a link if it is one
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let original = fs::canonicalize(parent)
+        .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?
+        .join(file_name);
+    fs::symlink_metadata(&original)
+        .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?;
+    let name = file_name.to_string_lossy().to_string();
+
+    let (trash, recorded_path) = trash_for(&original, home_trash);
+    let files_dir = trash.join("files");
+    let info_dir = trash.join("info");
+    fs::create_dir_all(&files_dir).map_err(|e| format!("Failed to create trash directory: {}", e))?;
+    fs::create_dir_all(&info_dir).map_err(|e| format!("Failed to create trash directory: {}", e))?;
+
+    let (trash_name, info_path, mut info_file) = reserve_trash_name(&files_dir, &info_dir, &name)?;
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        recorded_path,
+        Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+    if let Err(e) = info_file.write_all(info.as_bytes()) {
+        let _ = fs::remove_file(&info_path);
+        return Err(format!("Failed to write trash info: {}", e));
+    }
+    drop(info_file);
+
+    // Copied when no trash could be made on the item's own file system
+    let trashed = files_dir.join(&trash_name);
+    if let Err(e) = engine::move_path(&original, &trashed) {
+        let _ = fs::remove_file(&info_path);
+        return Err(format!("Failed to move {} to the trash: {}", original.display(), e));
+    }
+
+    Ok(TrashEntry { original, trashed })
+}
+
+/// Where the item `trashed`, directly inside the `files` of a trash, was
+/// before it was trashed, as recorded in its `.trashinfo`.
+pub fn original_path(trashed: &Path) -> Option<PathBuf> {
+    let info = fs::read_to_string(info_path(trashed)?).ok()?;
+    let encoded = info.lines().find_map(|line| line.strip_prefix("Path="))?.trim();
+    if encoded.starts_with('/') {
+        return uri_to_path(&format!("file://{}", encoded));
+    }
+    // Relative paths are relative to the folder containing the trash, the top
+    // directory for `$topdir/.Trash/$uid`
+    let trash = containing_trash(trashed)?;
+    let mut base = trash.parent()?;
+    if base.file_name().is_some_and(|name| name == ".Trash") {
+        base = base.parent()?;
+    }
+    uri_to_path(&format!("file://{}/{}", encode_path(base), encoded))
+}
+
+/// Move a trashed item back to its original location.
+pub fn restore(entry: &TrashEntry) -> Result<(), String> {
+    if entry.original.exists() {
+        return Err(format!("{} already exists", entry.original.display()));
+    }
+    if let Some(parent) = entry.original.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    engine::move_path(&entry.trashed, &entry.original)
+        .map_err(|e| format!("Failed to restore {}: {}", entry.original.display(), e))?;
+
+    if let Some(info_path) = info_path(&entry.trashed) {
+        let _ = fs::remove_file(info_path);
+    }
+    Ok(())
+}
+
+/// What [purge] deleted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PurgeSummary {
+    pub items: usize,
+    pub bytes: u64,
+}
+
+/// A trashed item as listed by its `.trashinfo`.
+struct TrashedItem {
+    name: String,
+    deleted: Option<NaiveDateTime>,
+    bytes: u64,
+}
+
+fn trashed_items() -> Vec<TrashedItem> {
+    let trash = trash_dir();
+    let Ok(entries) = fs::read_dir(trash.join("info")) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let name = file_name.strip_suffix(".trashinfo")?.to_string();
+            let info = fs::read_to_string(entry.path()).ok()?;
+            let deleted = info
+                .lines()
+                .find_map(|line| line.strip_prefix("DeletionDate="))
+                .and_then(|date| NaiveDateTime::parse_from_str(date.trim(), "%Y-%m-%dT%H:%M:%S").ok());
+            let (bytes, _) = engine::measure(&trash.join("files").join(&name));
+            Some(TrashedItem { name, deleted, bytes })
+        })
+        .collect()
+}
+
+/// Permanently delete `item` and its `.trashinfo`.
+fn delete_trashed(item: &TrashedItem) -> Result<(), String> {
+    let trash = trash_dir();
+    let path = trash.join("files").join(&item.name);
+    match engine::remove_path(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(format!("Failed to delete {}: {}", path.display(), e));
+        },
+        _ => {},
+    }
+    let _ = fs::remove_file(trash.join("info").join(format!("{}.trashinfo", item.name)));
+    Ok(())
+}
+
+/// Permanently delete the items trashed more than `max_age_days` days ago,
+/// then the oldest ones until the trash holds at most `max_bytes`. `None`
+/// leaves out either limit. Items without a deletion date are only deleted
+/// to keep the size, last. Touches every trashed file, so call it off the UI
+/// thread.
+pub fn purge(max_age_days: Option<u32>, max_bytes: Option<u64>) -> PurgeSummary {
+    let mut items = trashed_items();
+    // Oldest first; undated items after all dated ones
+    items.sort_by_key(|item| (item.deleted.is_none(), item.deleted));
+    let cutoff = max_age_days.map(|days| Local::now().naive_local() - chrono::Duration::days(days.into()));
+    let mut total: u64 = items.iter().map(|item| item.bytes).sum();
+    let mut summary = PurgeSummary::default();
+    for item in &items {
+        let expired = cutoff.is_some_and(|cutoff| item.deleted.is_some_and(|deleted| deleted < cutoff));
+        let too_large = max_bytes.is_some_and(|max| total > max);
+        if !expired && !too_large {
+            continue;
+        }
+        match delete_trashed(item) {
+            Ok(()) => {
+                total -= item.bytes;
+                summary.items += 1;
+                summary.bytes += item.bytes;
+            },
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_trash_folders_by_name() {
+        assert!(is_trash_files(Path::new("/home/me/.local/share/Trash/files")));
+        assert!(is_trash_files(Path::new("/media/usb/.Trash-1000/files")));
+        assert!(is_trash_files(Path::new("/media/usb/.Trash/1000/files")));
+        assert!(!is_trash_files(Path::new("/media/usb/.Trash-1000/info")));
+        assert!(!is_trash_files(Path::new("/home/me/files")));
+        assert!(!is_trash_files(Path::new("files")));
+    }
+
+    #[test]
+    fn trashing_a_symlink_keeps_its_target() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        let target = dir.join("target.txt");
+        let link = dir.join("link");
+        fs::write(&target, "kept").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let entry = trash_path_in(&link, &dir.join("data/Trash")).unwrap();
+
+        assert_eq!(entry.original, fs::canonicalize(dir).unwrap().join("link"));
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert!(fs::symlink_metadata(&entry.trashed).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "kept");
+        assert!(entry.trashed.starts_with(dir.join("data/Trash/files")));
+    }
+
+    #[test]
+    fn names_held_by_an_info_file_are_numbered() {
+        let temp = tempfile::tempdir().unwrap();
+        let trash = temp.path().join("Trash");
+        fs::create_dir_all(trash.join("info")).unwrap();
+        fs::write(trash.join("info/notes.txt.trashinfo"), "").unwrap();
+        let file = temp.path().join("notes.txt");
+        fs::write(&file, "notes").unwrap();
+
+        let entry = trash_path_in(&file, &trash).unwrap();
+
+        assert_eq!(entry.trashed, trash.join("files/notes.txt.2"));
+        assert!(fs::read_to_string(trash.join("info/notes.txt.2.trashinfo")).unwrap().contains("Path="));
+        assert_eq!(fs::read_to_string(trash.join("info/notes.txt.trashinfo")).unwrap(), "");
+    }
+}
@@ -0,0 +1,538 @@
+//! Undo records for completed operations
+//!
+//! Records can be saved to disk so the command line can undo the last operation
+//! of a previous invocation. A window keeps its own operations in an
+//! [UndoStack] for Undo and Redo.
+
+use super::trash::{self, TrashEntry};
+use nptk_fileman_widgets::xdg;
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+
+/// What is needed to revert a completed operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UndoRecord {
+    /// Items created by a copy; undone by deleting them.
+    Copied { created: Vec<PathBuf> },
+    /// Items moved from `.0` to `.1`; undone by moving them back.
+    Moved { moves: Vec<(PathBuf, PathBuf)> },
+    /// Items moved to the trash; undone by restoring them.
+    Trashed { entries: Vec<TrashEntry> },
+    /// A new folder; undone by removing it while it is still empty.
+    Created { folder: PathBuf },
+}
+
+/// Operations kept for Undo, oldest ones are dropped beyond this
+const UNDO_LIMIT: usize = 50;
+
+/// What [UndoRecord::undo] or [UndoRecord::redo] carried out of a record.
+#[derive(Debug)]
+pub struct Step {
+    /// Record of the items carried out, to redo them after an undo or to undo
+    /// them after a redo
+    pub done: Option<UndoRecord>,
+    /// Record of the items that failed or weren't reached, to try again
+    pub left: Option<UndoRecord>,
+    /// The first failure
+    pub result: Result<(), String>,
+}
+
+/// Split `items`, handled last to first, into those `fails` and the others,
+/// both in their original order.
+fn split_in_reverse<T: Clone>(items: &[T], mut fails: impl FnMut(&T) -> bool) -> (Vec<T>, Vec<T>) {
+    let (mut failed, mut done): (Vec<T>, Vec<T>) = items.iter().rev().cloned().partition(|item| fails(item));
+    failed.reverse();
+    done.reverse();
+    (failed, done)
+}
+
+impl UndoRecord {
+    /// Short description for status messages, e.g. "copy of 3 item(s)".
+    pub fn describe(&self) -> String {
+        match self {
+            UndoRecord::Copied { created } => format!("copy of {} item(s)", created.len()),
+            UndoRecord::Moved { moves } => match moves.as_slice() {
+                [(from, to)] if from.parent() == to.parent() => format!("renaming of '{}'", name(to)),
+                _ => format!("move of {} item(s)", moves.len()),
+            },
+            UndoRecord::Trashed { entries } => format!("trashing of {} item(s)", entries.len()),
+            UndoRecord::Created { folder } => format!("creation of '{}'", name(folder)),
+        }
+    }
+
+    /// Whether [UndoRecord::redo] can carry out the operation again once it is
+    /// undone. Copies can't be, the sources aren't recorded.
+    pub fn can_redo(&self) -> bool {
+        !matches!(self, UndoRecord::Copied { .. })
+    }
+
+    /// Revert the operation. Continues past failures and reports the first one.
+    pub fn undo(&self) -> Step {
+        let mut first_error = None;
+        let mut fails = |result: Result<(), String>| {
+            result
+                .map_err(|e| {
+                    log::error!("Undo failed: {}", e);
+                    first_error.get_or_insert(e);
+                })
+                .is_err()
+        };
+
+        let (left, done) = match self {
+            UndoRecord::Copied { created } => {
+                let (left, done) = split_in_reverse(created, |path| fails(super::delete_path(path.clone())));
+                (UndoRecord::Copied { created: left }, UndoRecord::Copied { created: done })
+            },
+            UndoRecord::Moved { moves } => {
+                let (left, done) = split_in_reverse(moves, |(from, to)| {
+                    fails(if from.exists() {
+                        Err(format!("{} already exists", from.display()))
+                    } else {
+                        super::engine::move_path(to, from)
+                            .map_err(|e| format!("Failed to move {} back: {}", to.display(), e))
+                    })
+                });
+                (UndoRecord::Moved { moves: left }, UndoRecord::Moved { moves: done })
+            },
+            UndoRecord::Trashed { entries } => {
+                let (left, done) = split_in_reverse(entries, |entry| fails(trash::restore(entry)));
+                (UndoRecord::Trashed { entries: left }, UndoRecord::Trashed { entries: done })
+            },
+            UndoRecord::Created { folder } => {
+                // Whatever was put into the folder since stays
+                let removed = fs::remove_dir(folder).map_err(|e| format!("Failed to remove {}: {}", folder.display(), e));
+                return match removed {
+                    Ok(()) => Step { done: Some(self.clone()), left: None, result: Ok(()) },
+                    Err(e) => Step { done: None, left: Some(self.clone()), result: Err(e) },
+                };
+            },
+        };
+
+        Step {
+            done: done.unless_empty(),
+            left: left.unless_empty(),
+            result: first_error.map_or(Ok(()), Err),
+        }
+    }
+
+    /// Carry out the operation again after it was undone. Its record to undo
+    /// what was carried out with is new, as trashed items get new places in
+    /// the trash. Moves stop at a failure, trashing continues past them.
+    pub fn redo(&self) -> Step {
+        match self {
+            UndoRecord::Copied { .. } => Step { done: None, left: None, result: Err("A copy can't be redone".to_string()) },
+            UndoRecord::Moved { moves } => {
+                let mut done = Vec::new();
+                for (i, (from, to)) in moves.iter().enumerate() {
+                    let moved = if to.exists() {
+                        Err(format!("{} already exists", to.display()))
+                    } else {
+                        super::engine::move_path(from, to)
+                            .map_err(|e| format!("Failed to move {}: {}", from.display(), e))
+                    };
+                    if let Err(e) = moved {
+                        return Step {
+                            done: UndoRecord::Moved { moves: done }.unless_empty(),
+                            left: Some(UndoRecord::Moved { moves: moves[i..].to_vec() }),
+                            result: Err(e),
+                        };
+                    }
+                    done.push((from.clone(), to.clone()));
+                }
+                Step { done: Some(UndoRecord::Moved { moves: done }), left: None, result: Ok(()) }
+            },
+            UndoRecord::Trashed { entries } => {
+                let mut trashed = Vec::new();
+                let mut failed = Vec::new();
+                let mut first_error = None;
+                for entry in entries {
+                    match trash::trash_path(&entry.original) {
+                        Ok(entry) => trashed.push(entry),
+                        Err(e) => {
+                            log::error!("Redo failed: {}", e);
+                            first_error.get_or_insert(e);
+                            failed.push(entry.clone());
+                        },
+                    }
+                }
+                Step {
+                    done: UndoRecord::Trashed { entries: trashed }.unless_empty(),
+                    left: UndoRecord::Trashed { entries: failed }.unless_empty(),
+                    result: first_error.map_or(Ok(()), Err),
+                }
+            },
+            UndoRecord::Created { folder } => match super::create_directory(folder.clone()) {
+                Ok(()) => Step { done: Some(self.clone()), left: None, result: Ok(()) },
+                Err(e) => Step { done: None, left: Some(self.clone()), result: Err(e) },
+            },
+        }
+    }
+
+    /// `None` if the record covers no items, so there is nothing to undo.
+    fn unless_empty(self) -> Option<UndoRecord> {
+        let empty = match &self {
+            UndoRecord::Copied { created } => created.is_empty(),
+            UndoRecord::Moved { moves } => moves.is_empty(),
+            UndoRecord::Trashed { entries } => entries.is_empty(),
+            UndoRecord::Created { .. } => false,
+        };
+        (!empty).then_some(self)
+    }
+
+    /// The kind of operation and the paths of each item, the form a record is
+    /// saved and sent over D-Bus in.
+    pub(crate) fn to_items(&self) -> (&'static str, Vec<Vec<&Path>>) {
+        match self {
+            UndoRecord::Copied { created } => ("copied", created.iter().map(|p| vec![p.as_path()]).collect()),
+            UndoRecord::Moved { moves } => (
+                "moved",
+                moves.iter().map(|(from, to)| vec![from.as_path(), to.as_path()]).collect(),
+            ),
+            UndoRecord::Trashed { entries } => (
+                "trashed",
+                entries
+                    .iter()
+                    .map(|e| vec![e.original.as_path(), e.trashed.as_path()])
+                    .collect(),
+            ),
+            UndoRecord::Created { folder } => ("created", vec![vec![folder.as_path()]]),
+        }
+    }
+
+    /// Inverse of [UndoRecord::to_items]. `None` unless every item has the
+    /// paths its kind needs and all of them are absolute, so a damaged record
+    /// can't point an undo at files the operation never touched.
+    pub(crate) fn from_items(kind: &str, items: Vec<Vec<PathBuf>>) -> Option<Self> {
+        if items.iter().flatten().any(|path| !path.is_absolute()) {
+            return None;
+        }
+        let one = |item: Vec<PathBuf>| <[PathBuf; 1]>::try_from(item).ok().map(|[path]| path);
+        let two = |item: Vec<PathBuf>| <[PathBuf; 2]>::try_from(item).ok().map(|[a, b]| (a, b));
+        match kind {
+            "copied" => Some(UndoRecord::Copied {
+                created: items.into_iter().map(one).collect::<Option<_>>()?,
+            }),
+            "moved" => Some(UndoRecord::Moved {
+                moves: items.into_iter().map(two).collect::<Option<_>>()?,
+            }),
+            "trashed" => Some(UndoRecord::Trashed {
+                entries: items
+                    .into_iter()
+                    .map(|item| two(item).map(|(original, trashed)| TrashEntry { original, trashed }))
+                    .collect::<Option<_>>()?,
+            }),
+            "created" => {
+                let [item] = <[Vec<PathBuf>; 1]>::try_from(items).ok()?;
+                Some(UndoRecord::Created { folder: one(item)? })
+            },
+            _ => None,
+        }
+    }
+
+    /// Serialize as lines: the kind, then the tab-separated paths of each item.
+    /// Paths are kept as raw bytes, with `\\`, newlines and tabs escaped.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let (kind, items) = self.to_items();
+        let mut bytes = kind.as_bytes().to_vec();
+        for item in items {
+            bytes.push(b'\n');
+            for (i, path) in item.into_iter().enumerate() {
+                if i > 0 {
+                    bytes.push(b'\t');
+                }
+                escape(path.as_os_str().as_bytes(), &mut bytes);
+            }
+        }
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut lines = bytes.split(|b| *b == b'\n');
+        let kind = std::str::from_utf8(lines.next()?).ok()?;
+        let items = lines
+            .map(|line| line.split(|b| *b == b'\t').map(unescape).collect::<Option<Vec<_>>>())
+            .collect::<Option<Vec<_>>>()?;
+        Self::from_items(kind, items)
+    }
+}
+
+fn escape(raw: &[u8], out: &mut Vec<u8>) {
+    for &b in raw {
+        match b {
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            b => out.push(b),
+        }
+    }
+}
+
+/// Inverse of [escape]; `None` for an unknown or unfinished escape.
+fn unescape(escaped: &[u8]) -> Option<PathBuf> {
+    let mut raw = Vec::with_capacity(escaped.len());
+    let mut bytes = escaped.iter();
+    while let Some(&b) = bytes.next() {
+        raw.push(match b {
+            b'\\' => match bytes.next()? {
+                b'\\' => b'\\',
+                b'n' => b'\n',
+                b't' => b'\t',
+                _ => return None,
+            },
+            b => b,
+        });
+    }
+    Some(PathBuf::from(OsString::from_vec(raw)))
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Operations of a window that can be undone, and undone ones that can be
+/// redone. Undoing and redoing touch the file system, so records are taken off
+/// the stack, carried out in the background and handed back with [UndoStack::stepped].
+/// When one fails part way, what was carried out goes to the other stack and
+/// what is left goes back where it was taken from, to be tried again.
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: Vec<UndoRecord>,
+    redo: Vec<UndoRecord>,
+}
+
+impl UndoStack {
+    /// Remember a new operation. Whatever was undone can't be redone after it.
+    pub fn push(&mut self, record: UndoRecord) {
+        self.redo.clear();
+        self.push_undo(record);
+    }
+
+    fn push_undo(&mut self, record: UndoRecord) {
+        self.undo.push(record);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    /// The operation Undo would revert.
+    pub fn next_undo(&self) -> Option<&UndoRecord> {
+        self.undo.last()
+    }
+
+    /// The operation Redo would carry out again.
+    pub fn next_redo(&self) -> Option<&UndoRecord> {
+        self.redo.last()
+    }
+
+    /// Take the operation to undo, to hand it to [UndoStack::stepped] once it is.
+    pub fn take_undo(&mut self) -> Option<UndoRecord> {
+        self.undo.pop()
+    }
+
+    /// Take the operation to redo, to hand it to [UndoStack::stepped] once it is.
+    pub fn take_redo(&mut self) -> Option<UndoRecord> {
+        self.redo.pop()
+    }
+
+    /// Hand back what undoing (or with `redo`, redoing) a taken record carried
+    /// out and left.
+    pub fn stepped(&mut self, step: Step, redo: bool) {
+        if redo {
+            self.redo.extend(step.left);
+            self.push_undo_all(step.done);
+        } else {
+            self.push_undo_all(step.left);
+            self.redo.extend(step.done.filter(UndoRecord::can_redo));
+        }
+    }
+
+    fn push_undo_all(&mut self, record: Option<UndoRecord>) {
+        if let Some(record) = record {
+            self.push_undo(record);
+        }
+    }
+}
+
+fn last_record_path() -> PathBuf {
+    xdg::state_home().join("fileman").join("last-operation")
+}
+
+/// Remember `record` as the last undoable operation.
+pub fn save_last(record: &UndoRecord) -> Result<(), String> {
+    let path = last_record_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create state directory: {}", e))?;
+    }
+    fs::write(&path, record.to_bytes()).map_err(|e| format!("Failed to save undo information: {}", e))
+}
+
+/// The last undoable operation, if any, leaving it to be undone.
+pub fn last() -> Option<UndoRecord> {
+    fs::read(last_record_path())
+        .ok()
+        .and_then(|bytes| UndoRecord::from_bytes(&bytes))
+}
+
+/// Take the last undoable operation, if any.
+pub fn take_last() -> Option<UndoRecord> {
+    let record = last();
+    let _ = fs::remove_file(last_record_path());
+    record
+}
+
+/// Entry of the last operation, if it moved `path` (or a folder containing
+/// it) to the trash and the item is still there.
+pub fn trashed_entry(path: &Path) -> Option<TrashEntry> {
+    match last()? {
+        UndoRecord::Trashed { entries } => entries
+            .into_iter()
+            .find(|entry| path.starts_with(&entry.original) && entry.trashed.exists()),
+        _ => None,
+    }
+}
+
+/// Restore one entry of the last operation, which stays undoable for the
+/// other entries.
+pub fn restore_trashed(entry: &TrashEntry) -> Result<(), String> {
+    trash::restore(entry)?;
+    if let Some(UndoRecord::Trashed { mut entries }) = last() {
+        entries.retain(|e| e != entry);
+        if entries.is_empty() {
+            take_last();
+        } else {
+            save_last(&UndoRecord::Trashed { entries })?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_survive_saving() {
+        let records = [
+            UndoRecord::Copied { created: vec![PathBuf::from("/tmp/a"), PathBuf::from("/tmp/b c")] },
+            UndoRecord::Moved {
+                moves: vec![
+                    (PathBuf::from("/home/me/a"), PathBuf::from("/home/me/Work/a")),
+                    (PathBuf::from("/home/me/b c"), PathBuf::from("/home/me/Work/b c")),
+                ],
+            },
+            UndoRecord::Trashed {
+                entries: vec![TrashEntry {
+                    original: PathBuf::from("/home/me/old.txt"),
+                    trashed: PathBuf::from("/home/me/.local/share/Trash/files/old.txt"),
+                }],
+            },
+            UndoRecord::Created { folder: PathBuf::from("/home/me/New Folder") },
+            UndoRecord::Copied { created: Vec::new() },
+        ];
+        for record in records {
+            assert_eq!(UndoRecord::from_bytes(&record.to_bytes()), Some(record));
+        }
+        assert_eq!(UndoRecord::from_bytes(b""), None);
+        assert_eq!(UndoRecord::from_bytes(b"renamed\n/a"), None);
+        assert_eq!(UndoRecord::from_bytes(b"moved\n/a without a pair"), None);
+        assert_eq!(UndoRecord::from_bytes(b"moved\n/a\t/b\t/c"), None);
+        assert_eq!(UndoRecord::from_bytes(b"copied\n/a\n"), None);
+        assert_eq!(UndoRecord::from_bytes(b"copied\nrelative"), None);
+        assert_eq!(UndoRecord::from_bytes(b"copied\n/a\\x"), None);
+        assert_eq!(UndoRecord::from_bytes(b"copied\n/a\\"), None);
+        assert_eq!(UndoRecord::from_bytes(b"created"), None);
+        assert_eq!(UndoRecord::from_bytes(b"created\n/a\n/b"), None);
+    }
+
+    #[test]
+    fn records_keep_names_with_separators_and_invalid_utf8() {
+        let odd = PathBuf::from(OsString::from_vec(b"/home/me/x\n/home/me/Documents\t\\n\xff".to_vec()));
+        let record = UndoRecord::Copied { created: vec![odd.clone()] };
+        let bytes = record.to_bytes();
+        assert_eq!(bytes.iter().filter(|b| **b == b'\n').count(), 1);
+        assert_eq!(UndoRecord::from_bytes(&bytes), Some(record));
+
+        let record = UndoRecord::Moved { moves: vec![(odd.clone(), PathBuf::from("/home/me/b"))] };
+        assert_eq!(UndoRecord::from_bytes(&record.to_bytes()), Some(record));
+    }
+
+    #[test]
+    fn a_failed_redo_keeps_what_it_did() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        for name in ["a", "b", "taken"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let moves = vec![(dir.join("a"), dir.join("a2")), (dir.join("b"), dir.join("taken"))];
+
+        let step = UndoRecord::Moved { moves }.redo();
+        assert!(step.result.is_err());
+        assert_eq!(step.done, Some(UndoRecord::Moved { moves: vec![(dir.join("a"), dir.join("a2"))] }));
+        assert_eq!(step.left, Some(UndoRecord::Moved { moves: vec![(dir.join("b"), dir.join("taken"))] }));
+        assert_eq!(fs::read_to_string(dir.join("a2")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("taken")).unwrap(), "taken");
+
+        let step = UndoRecord::Moved { moves: vec![(dir.join("b"), dir.join("a2"))] }.redo();
+        assert!(step.result.is_err());
+        assert_eq!(step.done, None);
+    }
+
+    #[test]
+    fn a_failed_undo_can_be_tried_again() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        for name in ["a2", "b2", "b"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let record = UndoRecord::Moved {
+            moves: vec![(dir.join("a"), dir.join("a2")), (dir.join("b"), dir.join("b2"))],
+        };
+        let mut stack = UndoStack::default();
+        stack.push(record);
+
+        // "b" is in the way of moving "b2" back
+        let step = stack.take_undo().unwrap().undo();
+        assert!(step.result.is_err());
+        stack.stepped(step, false);
+        assert_eq!(stack.next_redo(), Some(&UndoRecord::Moved { moves: vec![(dir.join("a"), dir.join("a2"))] }));
+        assert_eq!(stack.next_undo(), Some(&UndoRecord::Moved { moves: vec![(dir.join("b"), dir.join("b2"))] }));
+
+        fs::remove_file(dir.join("b")).unwrap();
+        let step = stack.take_undo().unwrap().undo();
+        assert!(step.result.is_ok());
+        stack.stepped(step, false);
+        assert_eq!(stack.next_undo(), None);
+        assert_eq!(stack.redo.len(), 2);
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "b2");
+    }
+
+    #[test]
+    fn the_stack_forgets_redos_after_new_operations() {
+        let created = |name: &str| UndoRecord::Created { folder: PathBuf::from(name) };
+        let mut stack = UndoStack::default();
+        stack.push(created("/a"));
+        stack.push(UndoRecord::Copied { created: vec![PathBuf::from("/b")] });
+
+        // Copies can't be redone
+        let copy = stack.take_undo().unwrap();
+        stack.stepped(Step { done: Some(copy), left: None, result: Ok(()) }, false);
+        assert_eq!(stack.next_redo(), None);
+
+        let folder = stack.take_undo().unwrap();
+        stack.stepped(Step { done: Some(folder), left: None, result: Ok(()) }, false);
+        assert_eq!(stack.next_redo(), Some(&created("/a")));
+        assert_eq!(stack.next_undo(), None);
+
+        stack.push(created("/c"));
+        assert_eq!(stack.next_redo(), None);
+        for i in 0..UNDO_LIMIT {
+            stack.push(created(&format!("/{}", i)));
+        }
+        assert_eq!(stack.undo.len(), UNDO_LIMIT);
+        assert_eq!(stack.undo.first(), Some(&created("/0")));
+    }
+}
@@ -0,0 +1,119 @@
+//! Owner and group of files
+//!
+//! Shown as the optional "Owner" and "Group" columns. Names are looked up once
+//! per id: in `/etc/passwd` and `/etc/group` first, then through `getent` (which
+//! also asks LDAP and other NSS sources) for ids not listed there. Ids without a
+//! name are shown as numbers.
+
+use nptk::core::model::ModelData;
+use nptk_fileman_widgets::file_list::model_adapter::ColumnProvider;
+use std::collections::HashMap;
+use std::os::unix::fs::MetadataExt;
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Database {
+    Users,
+    Groups,
+}
+
+impl Database {
+    /// Name of the database for `getent`
+    fn name(self) -> &'static str {
+        match self {
+            Database::Users => "passwd",
+            Database::Groups => "group",
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            Database::Users => "/etc/passwd",
+            Database::Groups => "/etc/group",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Names {
+    // Databases whose file has been read
+    loaded: Vec<Database>,
+    // `None` for ids without a name
+    names: HashMap<(Database, u32), Option<String>>,
+}
+
+static NAMES: LazyLock<Mutex<Names>> = LazyLock::new(Default::default);
+
+/// (id, name) of the entries of a `name:password:id:...` file.
+fn parse(content: &str) -> impl Iterator<Item = (u32, String)> + '_ {
+    content.lines().filter_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let id = fields.nth(1)?.parse().ok()?;
+        Some((id, name.to_string()))
+    })
+}
+
+/// Ask NSS for the name of `id`.
+fn getent(database: Database, id: u32) -> Option<String> {
+    let output = Command::new("getent")
+        .args([database.name(), &id.to_string()])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse(&String::from_utf8_lossy(&output.stdout)).next().map(|(_, name)| name)
+}
+
+fn lookup(database: Database, id: u32) -> String {
+    let Ok(mut cache) = NAMES.lock() else {
+        return id.to_string();
+    };
+    if !cache.loaded.contains(&database) {
+        cache.loaded.push(database);
+        let content = std::fs::read_to_string(database.path()).unwrap_or_default();
+        for (id, name) in parse(&content) {
+            cache.names.entry((database, id)).or_insert(Some(name));
+        }
+    }
+    cache
+        .names
+        .entry((database, id))
+        .or_insert_with(|| getent(database, id))
+        .clone()
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// Name of the user `uid`, or the number if it has none.
+pub fn user_name(uid: u32) -> String {
+    lookup(Database::Users, uid)
+}
+
+/// Name of the group `gid`, or the number if it has none.
+pub fn group_name(gid: u32) -> String {
+    lookup(Database::Groups, gid)
+}
+
+/// The "Owner" column for the Table view.
+pub fn owner_column() -> ColumnProvider {
+    ColumnProvider::new("Owner", |entry| {
+        ModelData::String(
+            std::fs::symlink_metadata(&entry.path)
+                .map(|metadata| user_name(metadata.uid()))
+                .unwrap_or_default(),
+        )
+    })
+}
+
+/// The "Group" column for the Table view.
+pub fn group_column() -> ColumnProvider {
+    ColumnProvider::new("Group", |entry| {
+        ModelData::String(
+            std::fs::symlink_metadata(&entry.path)
+                .map(|metadata| group_name(metadata.gid()))
+                .unwrap_or_default(),
+        )
+    })
+}
@@ -0,0 +1,457 @@
+//! Command palette (Ctrl+Shift+P)
+//!
+//! Lists what can be done in the window, with the shortcut that does the
+//! same, narrowed down by typing part of a command's title. Commands are sent
+//! over the [AppBus] like those of the toolbar and the shortcuts, so whatever
+//! the window can do on the bus is reachable from the keyboard.
+
+use crate::bus::{AppBus, AppCommand};
+use crate::clipboard::ClipboardAction;
+use crate::toolbar::NavigationAction;
+use crate::window::FileOperationRequest;
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Matching commands listed at a time
+const MAX_SHOWN: usize = 12;
+
+/// What the commands act on: the shown folder and the selection in it
+pub struct PaletteTarget {
+    pub folder: PathBuf,
+    pub selection: Vec<PathBuf>,
+}
+
+/// A command of the palette
+struct PaletteCommand {
+    title: &'static str,
+    /// Keys doing the same, empty if there are none
+    shortcut: &'static str,
+    /// What to send, `None` where the command doesn't apply (e.g. to no selection)
+    command: fn(&PaletteTarget) -> Option<AppCommand>,
+}
+
+fn operation(request: FileOperationRequest) -> Option<AppCommand> {
+    Some(AppCommand::Operation(request))
+}
+
+fn navigation(action: NavigationAction) -> Option<AppCommand> {
+    Some(AppCommand::Navigate(action))
+}
+
+/// Operation on the selection, unless nothing is selected
+fn on_selection(target: &PaletteTarget, request: fn(Vec<PathBuf>) -> FileOperationRequest) -> Option<AppCommand> {
+    (!target.selection.is_empty()).then(|| AppCommand::Operation(request(target.selection.clone())))
+}
+
+fn commands() -> Vec<PaletteCommand> {
+    vec![
+        PaletteCommand {
+            title: "Go Back",
+            shortcut: "",
+            command: |_| navigation(NavigationAction::Back),
+        },
+        PaletteCommand {
+            title: "Go Forward",
+            shortcut: "",
+            command: |_| navigation(NavigationAction::Forward),
+        },
+        PaletteCommand {
+            title: "Go Up",
+            shortcut: "",
+            command: |_| navigation(NavigationAction::Up),
+        },
+        PaletteCommand {
+            title: "Go Home",
+            shortcut: "",
+            command: |_| navigation(NavigationAction::Home),
+        },
+        PaletteCommand {
+            title: "Open in New Tab",
+            shortcut: "Ctrl+T",
+            command: |target| navigation(NavigationAction::OpenInNewTab(target.folder.clone())),
+        },
+        PaletteCommand {
+            title: "Open",
+            shortcut: "Return",
+            command: |target| on_selection(target, FileOperationRequest::Open),
+        },
+        PaletteCommand {
+            title: "Open Item Location",
+            shortcut: "",
+            command: |target| on_selection(target, FileOperationRequest::OpenLocation),
+        },
+        PaletteCommand {
+            title: "New Folder",
+            shortcut: "",
+            command: |target| operation(FileOperationRequest::NewFolder(target.folder.clone())),
+        },
+        PaletteCommand {
+            title: "Rename...",
+            shortcut: "",
+            command: |target| match target.selection.as_slice() {
+                [path] => operation(FileOperationRequest::AskRename(path.clone())),
+                _ => None,
+            },
+        },
+        PaletteCommand {
+            title: "Copy",
+            shortcut: "Ctrl+C",
+            command: |target| {
+                on_selection(target, |paths| FileOperationRequest::SetClipboard { paths, action: ClipboardAction::Copy })
+            },
+        },
+        PaletteCommand {
+            title: "Cut",
+            shortcut: "Ctrl+X",
+            command: |target| {
+                on_selection(target, |paths| FileOperationRequest::SetClipboard { paths, action: ClipboardAction::Cut })
+            },
+        },
+        PaletteCommand {
+            title: "Paste",
+            shortcut: "Ctrl+V",
+            command: |target| {
+                operation(FileOperationRequest::Paste { destination: target.folder.clone(), hard_links: false })
+            },
+        },
+        PaletteCommand {
+            title: "Paste as Hard Links",
+            shortcut: "Ctrl+Shift+V",
+            command: |target| {
+                operation(FileOperationRequest::Paste { destination: target.folder.clone(), hard_links: true })
+            },
+        },
+        PaletteCommand {
+            title: "Copy Path",
+            shortcut: "Ctrl+Shift+C",
+            command: |target| on_selection(target, |paths| FileOperationRequest::CopyPaths { paths, names_only: false }),
+        },
+        PaletteCommand {
+            title: "Copy Name",
+            shortcut: "",
+            command: |target| on_selection(target, |paths| FileOperationRequest::CopyPaths { paths, names_only: true }),
+        },
+        PaletteCommand {
+            title: "Copy To...",
+            shortcut: "",
+            command: |target| {
+                on_selection(target, |sources| FileOperationRequest::ChooseDestination { sources, move_items: false })
+            },
+        },
+        PaletteCommand {
+            title: "Move To...",
+            shortcut: "",
+            command: |target| {
+                on_selection(target, |sources| FileOperationRequest::ChooseDestination { sources, move_items: true })
+            },
+        },
+        PaletteCommand {
+            title: "Move to Trash",
+            shortcut: "Delete",
+            command: |target| on_selection(target, FileOperationRequest::Trash),
+        },
+        PaletteCommand {
+            title: "Delete Permanently",
+            shortcut: "Shift+Delete",
+            command: |target| on_selection(target, FileOperationRequest::Delete),
+        },
+        PaletteCommand {
+            title: "Compress",
+            shortcut: "",
+            command: |target| on_selection(target, FileOperationRequest::Compress),
+        },
+        PaletteCommand {
+            title: "Run Script...",
+            shortcut: "",
+            command: |target| operation(FileOperationRequest::RunScript(target.selection.clone())),
+        },
+        PaletteCommand {
+            title: "Properties",
+            shortcut: "",
+            // Of the shown folder when nothing is selected, like the toolbar button
+            command: |target| {
+                let paths = if target.selection.is_empty() {
+                    vec![target.folder.clone()]
+                } else {
+                    target.selection.clone()
+                };
+                operation(FileOperationRequest::Properties(paths))
+            },
+        },
+        PaletteCommand {
+            title: "Undo",
+            shortcut: "Ctrl+Z",
+            command: |_| operation(FileOperationRequest::Undo),
+        },
+        PaletteCommand {
+            title: "Redo",
+            shortcut: "Ctrl+Shift+Z",
+            command: |_| operation(FileOperationRequest::Redo),
+        },
+        PaletteCommand {
+            title: "Refresh",
+            shortcut: "F5",
+            command: |_| operation(FileOperationRequest::Refresh { deep: false }),
+        },
+        PaletteCommand {
+            title: "Refresh and Drop Cached Data",
+            shortcut: "Ctrl+F5",
+            command: |_| operation(FileOperationRequest::Refresh { deep: true }),
+        },
+        PaletteCommand {
+            title: "Show Hidden Files",
+            shortcut: "Ctrl+H",
+            command: |_| operation(FileOperationRequest::ToggleHiddenFiles),
+        },
+        PaletteCommand {
+            title: "Next View Mode",
+            shortcut: "",
+            command: |_| operation(FileOperationRequest::CycleView),
+        },
+        PaletteCommand {
+            title: "Thumbnails in This Folder",
+            shortcut: "",
+            command: |_| operation(FileOperationRequest::ToggleFolderThumbnails),
+        },
+        PaletteCommand {
+            title: "Reset Layout",
+            shortcut: "",
+            command: |_| operation(FileOperationRequest::ResetLayout),
+        },
+        PaletteCommand {
+            title: "Eject",
+            shortcut: "",
+            command: |_| operation(FileOperationRequest::Eject(None)),
+        },
+        PaletteCommand {
+            title: "Transfers",
+            shortcut: "",
+            command: |_| operation(FileOperationRequest::Transfers),
+        },
+        PaletteCommand {
+            title: "Preferences",
+            shortcut: "",
+            command: |_| operation(FileOperationRequest::Preferences),
+        },
+    ]
+}
+
+/// How well `query` matches `title`, `None` if it doesn't. The characters of
+/// the query have to appear in the title in order, ignoring case; runs of
+/// them and ones starting a word count more.
+fn fuzzy_score(query: &str, title: &str) -> Option<u32> {
+    let title: Vec<char> = title.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + title[position..].iter().position(|c| *c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 2;
+        }
+        if found == 0 || !title[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// The palette popup: a search field and the commands matching it. The rows
+/// are built once and relabelled as the query changes, so typing doesn't
+/// rebuild the search field.
+pub struct CommandPalette {
+    bus: AppBus,
+    target: PaletteTarget,
+    commands: Vec<PaletteCommand>,
+    // Indices into `commands` of the ones listed, best match first
+    shown: Vec<usize>,
+    shown_query: Option<String>,
+    inner: Container,
+    query: StateSignal<String>,
+    // Title and shortcut shown in each row
+    rows: Vec<(StateSignal<String>, StateSignal<String>)>,
+    message_text: StateSignal<String>,
+    // Row pressed, processed in `update()`
+    picked: Arc<Mutex<Option<usize>>>,
+    signals_hooked: bool,
+}
+
+impl CommandPalette {
+    pub fn new(bus: AppBus, target: PaletteTarget) -> Self {
+        let mut palette = Self {
+            bus,
+            target,
+            commands: commands(),
+            shown: Vec::new(),
+            shown_query: None,
+            inner: Container::new(vec![]),
+            query: StateSignal::new(String::new()),
+            rows: (0..MAX_SHOWN)
+                .map(|_| (StateSignal::new(String::new()), StateSignal::new(String::new())))
+                .collect(),
+            message_text: StateSignal::new(String::new()),
+            picked: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        };
+        palette.build();
+        palette.filter();
+        palette
+    }
+
+    /// Show the palette as a popup.
+    pub fn show(self, context: &AppContext) {
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), "Commands", (460, 520), (320, 80));
+    }
+
+    /// List the commands matching the query, unless they already are.
+    /// Returns whether the list changed.
+    fn filter(&mut self) -> bool {
+        let query = (*self.query.get()).clone();
+        if self.shown_query.as_ref() == Some(&query) {
+            return false;
+        }
+        let mut matches: Vec<(u32, usize)> = self
+            .commands
+            .iter()
+            .enumerate()
+            .filter_map(|(i, command)| Some((fuzzy_score(&query, command.title)?, i)))
+            .collect();
+        // Stable, so equally good matches keep their order
+        matches.sort_by(|a, b| b.0.cmp(&a.0));
+        self.shown = matches.into_iter().map(|(_, i)| i).take(MAX_SHOWN).collect();
+        for (row, (title, shortcut)) in self.rows.iter().enumerate() {
+            let command = self.shown.get(row).map(|&i| &self.commands[i]);
+            title.set(command.map(|c| c.title.to_string()).unwrap_or_default());
+            shortcut.set(command.map(|c| c.shortcut.to_string()).unwrap_or_default());
+        }
+        self.message_text.set(if self.shown.is_empty() {
+            "No matching commands".to_string()
+        } else {
+            String::new()
+        });
+        self.shown_query = Some(query);
+        true
+    }
+
+    fn build(&mut self) {
+        let mut children: Vec<Box<dyn Widget>> = vec![Box::new(
+            TextInput::new()
+                .with_text_signal(self.query.clone())
+                .with_placeholder("Type to search commands".to_string())
+                .with_layout_style(LayoutStyle {
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::length(30.0)),
+                    ..Default::default()
+                }),
+        )];
+        for (row, (title, shortcut)) in self.rows.iter().enumerate() {
+            let picked = self.picked.clone();
+            let button = Button::new(Text::new(title.maybe())).with_on_pressed(MaybeSignal::signal(Box::new(
+                EvalSignal::new(move || {
+                    if let Ok(mut picked) = picked.lock() {
+                        *picked = Some(row);
+                    }
+                    Update::DRAW
+                }),
+            )));
+            children.push(Box::new(
+                Container::new(vec![
+                    Box::new(button.with_layout_style(LayoutStyle {
+                        flex_grow: 1.0,
+                        ..Default::default()
+                    })),
+                    Box::new(Text::new(shortcut.maybe()).with_font_size(12.0)),
+                ])
+                .with_layout_style(LayoutStyle {
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    flex_direction: FlexDirection::Row,
+                    align_items: Some(AlignItems::Center),
+                    gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                    ..Default::default()
+                }),
+            ));
+        }
+        children.push(Box::new(Text::new(self.message_text.maybe()).with_font_size(12.0)));
+
+        self.inner = Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(6.0)),
+            ..Default::default()
+        });
+    }
+
+    fn run(&mut self, row: usize) {
+        let Some(command) = self.shown.get(row).map(|&i| &self.commands[i]) else {
+            return;
+        };
+        match (command.command)(&self.target) {
+            Some(app_command) => {
+                self.bus.send(app_command);
+                self.message_text.set(format!("{} done", command.title));
+            },
+            None => self.message_text.set(format!("{} doesn't apply to the selection", command.title)),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for CommandPalette {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.query);
+            context.hook_signal(&mut self.message_text);
+            for (title, shortcut) in &mut self.rows {
+                context.hook_signal(title);
+                context.hook_signal(shortcut);
+            }
+            self.signals_hooked = true;
+        }
+
+        let picked = self.picked.lock().ok().and_then(|mut p| p.take());
+        if let Some(row) = picked {
+            self.run(row);
+            update.insert(Update::DRAW);
+        }
+
+        if self.filter() {
+            update.insert(Update::DRAW);
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
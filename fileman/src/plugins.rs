@@ -0,0 +1,268 @@
+//! Plugins
+//!
+//! A [Plugin] adds context menu actions, Table view columns, previews and
+//! remote backends. Plugins are folders in `$XDG_DATA_HOME/fileman/plugins`
+//! described by a `plugin.conf`, whose entries name commands in the folder.
+//! Those commands are the interface plugins are written against: they can be
+//! written in any language and keep working when fileman is rebuilt. Plugins
+//! are loaded once at startup, except those disabled in Preferences.
+//!
+//! ```text
+//! [plugin]
+//! name = Image Tools
+//! description = Rotate photos and show their size in pixels
+//!
+//! # Context menu entry, run with the selected paths as arguments
+//! [action rotate]
+//! label = Rotate Clockwise
+//! command = rotate --clockwise
+//! extensions = jpg, jpeg, png
+//!
+//! # Table view column, run with one path; prints the value
+//! [column dimensions]
+//! title = Dimensions
+//! command = dimensions
+//! extensions = jpg, jpeg, png
+//!
+//! # Preview of files without one of their own, run with one path; prints text
+//! [preview]
+//! command = exif
+//! extensions = jpg, jpeg
+//!
+//! # Backend of remotes with `type = webdav`, see [external::ExternalBackend]
+//! [backend webdav]
+//! command = webdav
+//! read_only = false
+//! ```
+//!
+//! Commands are split at whitespace; a program found in the plugin folder is
+//! run from there, others from `$PATH`.
+
+mod external;
+
+pub use external::ExternalPlugin;
+
+use crate::bus::AppBus;
+use crate::menus;
+use crate::remote::RemoteBackend;
+use crate::settings;
+use nptk::core::menu::{MenuCommand, MenuItem};
+use nptk::prelude::*;
+use nptk_fileman_widgets::file_list::model_adapter::ColumnProvider;
+use nptk_fileman_widgets::xdg;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+/// Plugins enabled at startup
+static LOADED: LazyLock<Vec<Box<dyn Plugin>>> = LazyLock::new(load);
+
+/// Set when plugin columns computed new values (the view needs a redraw)
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// How long a column value or preview may take before its command is stopped
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a plugin can add to fileman. Everything but the names is optional.
+pub trait Plugin: Send + Sync {
+    /// Name of the plugin's folder, which enables and disables it
+    fn id(&self) -> &str;
+    fn name(&self) -> &str;
+    fn description(&self) -> &str {
+        ""
+    }
+    /// Context menu entries for the selected `paths`.
+    fn actions(&self, _paths: &[PathBuf]) -> Vec<PluginAction> {
+        Vec::new()
+    }
+    /// Columns added to the Table view.
+    fn columns(&self) -> Vec<ColumnProvider> {
+        Vec::new()
+    }
+    /// Text shown in the preview of `path`, if the plugin previews such files.
+    fn preview(&self, _path: &Path) -> Option<Result<String, String>> {
+        None
+    }
+    /// Backend for remotes of type `kind`, configured with the remote's `config`.
+    fn backend(
+        &self,
+        _kind: &str,
+        _config: &HashMap<String, String>,
+    ) -> Option<Result<Box<dyn RemoteBackend>, String>> {
+        None
+    }
+}
+
+/// A context menu entry added by a plugin.
+#[derive(Clone)]
+pub struct PluginAction {
+    pub label: String,
+    pub run: Arc<dyn Fn() -> Result<(), String> + Send + Sync>,
+}
+
+/// Folder the plugins are installed in.
+pub fn plugins_dir() -> PathBuf {
+    xdg::data_home().join("fileman/plugins")
+}
+
+/// The installed plugins, enabled or not, sorted by id. Plugins with a broken
+/// `plugin.conf` are skipped with a warning.
+pub fn installed() -> Vec<ExternalPlugin> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(plugins_dir())
+        .map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect())
+        .unwrap_or_default();
+    dirs.sort();
+    dirs.into_iter()
+        .filter_map(|dir| match ExternalPlugin::load(&dir) {
+            Ok(plugin) => Some(plugin),
+            Err(e) => {
+                log::warn!("{}", e);
+                None
+            },
+        })
+        .collect()
+}
+
+fn load() -> Vec<Box<dyn Plugin>> {
+    let disabled = settings::get().disabled_plugins;
+    installed()
+        .into_iter()
+        .filter(|plugin| !disabled.contains(plugin.id()))
+        .map(|plugin| {
+            log::info!("Loaded plugin {}", plugin.id());
+            Box::new(plugin) as Box<dyn Plugin>
+        })
+        .collect()
+}
+
+/// The plugins enabled at startup.
+pub fn loaded() -> &'static [Box<dyn Plugin>] {
+    &LOADED
+}
+
+/// Context menu entries of all plugins for the selected `paths`. Failures to
+/// start an action are shown in the status bar.
+pub fn menu_items(paths: &[PathBuf], bus: &AppBus) -> Vec<MenuItem> {
+    loaded()
+        .iter()
+        .flat_map(|plugin| plugin.actions(paths))
+        .take(menus::RANGE_LENGTH)
+        .enumerate()
+        .map(|(i, action)| {
+            let bus = bus.clone();
+            MenuItem::new(MenuCommand::Custom(menus::range_id(menus::PLUGIN_ACTION_IDS, i)), action.label.clone()).with_action(move || {
+                if let Err(e) = (action.run)() {
+                    log::error!("{}", e);
+                    bus.status(format!("Error: {}", e));
+                }
+                Update::DRAW
+            })
+        })
+        .collect()
+}
+
+/// Table view columns of all plugins.
+pub fn columns() -> Vec<ColumnProvider> {
+    loaded().iter().flat_map(|plugin| plugin.columns()).collect()
+}
+
+/// Preview text of `path` from the first plugin previewing it.
+pub fn preview(path: &Path) -> Option<Result<String, String>> {
+    loaded().iter().find_map(|plugin| plugin.preview(path))
+}
+
+/// Backend of the first plugin providing remotes of type `kind`.
+pub fn backend(kind: &str, config: &HashMap<String, String>) -> Option<Result<Box<dyn RemoteBackend>, String>> {
+    loaded().iter().find_map(|plugin| plugin.backend(kind, config))
+}
+
+/// Whether plugin columns computed new values since the last call (the view
+/// needs a redraw).
+pub fn take_changed() -> bool {
+    CHANGED.swap(false, Ordering::SeqCst)
+}
+
+fn mark_changed() {
+    CHANGED.store(true, Ordering::SeqCst);
+}
+
+/// Run `command` and return what it printed. With a `timeout`, the command is
+/// stopped once it takes longer. Fails with what it printed to stderr if it
+/// exits unsuccessfully.
+fn output(mut command: Command, timeout: Option<Duration>) -> Result<String, String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {:?}: {}", command.get_program(), e))?;
+    // Read both pipes while waiting, so a chatty command can't fill them and stall
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut text);
+            }
+            text
+        })
+    };
+    let stdout = read(child.stdout.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>));
+    let stderr = read(child.stderr.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>));
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if timeout.is_some_and(|timeout| started.elapsed() > timeout) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{:?} took too long and was stopped", command.get_program()));
+            },
+            Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+            Err(e) => return Err(format!("Failed to wait for {:?}: {}", command.get_program(), e)),
+        }
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        return Err(format!(
+            "{:?} failed ({}): {}",
+            command.get_program(),
+            status,
+            stderr.trim()
+        ));
+    }
+    Ok(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+
+    #[test]
+    fn collects_what_commands_print() {
+        assert_eq!(output(shell("echo out; echo noise >&2"), None), Ok("out\n".to_string()));
+        let error = output(shell("echo broken >&2; exit 3"), None).unwrap_err();
+        assert!(error.contains("broken"), "{}", error);
+        assert!(output(Command::new("/nonexistent/fileman-plugin"), None).is_err());
+    }
+
+    #[test]
+    fn slow_commands_are_stopped() {
+        let started = Instant::now();
+        let error = output(shell("sleep 10"), Some(Duration::from_millis(100))).unwrap_err();
+        assert!(error.contains("took too long"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
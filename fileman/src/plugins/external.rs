@@ -0,0 +1,455 @@
+//! Plugins described by a `plugin.conf`, whose commands do the work.
+
+use super::{COMMAND_TIMEOUT, Plugin, PluginAction, mark_changed, output};
+use crate::remote::{Capabilities, RemoteBackend, RemoteEntry};
+use async_trait::async_trait;
+use nptk::core::model::ModelData;
+use nptk_fileman_widgets::file_list::model_adapter::ColumnProvider;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+/// A command of a plugin, with the file extensions it applies to.
+#[derive(Debug, Clone)]
+struct PluginCommand {
+    args: Vec<String>,
+    // Lowercase; empty for all files
+    extensions: Vec<String>,
+}
+
+impl PluginCommand {
+    fn from_section(section: &str, values: &HashMap<String, String>) -> Result<Self, String> {
+        let args: Vec<String> = values
+            .get("command")
+            .map(|command| command.split_whitespace().map(String::from).collect())
+            .unwrap_or_default();
+        if args.is_empty() {
+            return Err(format!("[{}] has no command", section));
+        }
+        let extensions = values
+            .get("extensions")
+            .map(|extensions| {
+                extensions
+                    .split(',')
+                    .map(|extension| extension.trim().trim_start_matches('.').to_lowercase())
+                    .filter(|extension| !extension.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self { args, extensions })
+    }
+
+    fn applies_to(&self, path: &Path) -> bool {
+        self.extensions.is_empty()
+            || path
+                .extension()
+                .is_some_and(|extension| self.extensions.contains(&extension.to_string_lossy().to_lowercase()))
+    }
+
+    /// The command, run in the plugin folder `dir`.
+    fn command(&self, dir: &Path) -> Command {
+        let program = dir.join(&self.args[0]);
+        let mut command = Command::new(if program.is_file() { program } else { PathBuf::from(&self.args[0]) });
+        command.args(&self.args[1..]).current_dir(dir);
+        command
+    }
+}
+
+/// Values of a plugin column, computed one path at a time on a thread of its
+/// own and shown once they are ready.
+struct ColumnCache {
+    dir: PathBuf,
+    command: PluginCommand,
+    // Value and the modification time it was computed for; no value while pending
+    values: Mutex<HashMap<PathBuf, (Option<SystemTime>, Option<String>)>>,
+    // Paths to compute, for the thread started on first use
+    queue: OnceLock<mpsc::Sender<PathBuf>>,
+}
+
+impl ColumnCache {
+    fn value(self: &Arc<Self>, path: &Path) -> String {
+        if !self.command.applies_to(path) {
+            return String::new();
+        }
+        let modified = fs::symlink_metadata(path).and_then(|m| m.modified()).ok();
+        let Ok(mut values) = self.values.lock() else {
+            return String::new();
+        };
+        if let Some((computed, value)) = values.get(path) {
+            if *computed == modified {
+                return value.clone().unwrap_or_default();
+            }
+        }
+        values.insert(path.to_path_buf(), (modified, None));
+        drop(values);
+        let _ = self.queue().send(path.to_path_buf());
+        String::new()
+    }
+
+    fn queue(self: &Arc<Self>) -> &mpsc::Sender<PathBuf> {
+        self.queue.get_or_init(|| {
+            let (tx, rx) = mpsc::channel::<PathBuf>();
+            let cache = self.clone();
+            std::thread::spawn(move || {
+                for path in rx {
+                    let modified = fs::symlink_metadata(&path).and_then(|m| m.modified()).ok();
+                    let mut command = cache.command.command(&cache.dir);
+                    command.arg(&path);
+                    let value = match output(command, Some(COMMAND_TIMEOUT)) {
+                        Ok(text) => text.lines().next().unwrap_or("").trim().to_string(),
+                        Err(e) => {
+                            log::warn!("{}", e);
+                            String::new()
+                        },
+                    };
+                    if let Ok(mut values) = cache.values.lock() {
+                        values.insert(path, (modified, Some(value)));
+                    }
+                    mark_changed();
+                }
+            });
+            tx
+        })
+    }
+}
+
+/// A plugin installed as a folder with a `plugin.conf`.
+pub struct ExternalPlugin {
+    id: String,
+    name: String,
+    description: String,
+    dir: PathBuf,
+    actions: Vec<(String, PluginCommand)>,
+    columns: Vec<(String, Arc<ColumnCache>)>,
+    preview: Option<PluginCommand>,
+    // Type of remotes, command and whether the backend is read-only
+    backends: Vec<(String, PluginCommand, bool)>,
+}
+
+impl ExternalPlugin {
+    /// Read the plugin in the folder `dir`.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let id = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let manifest = dir.join("plugin.conf");
+        let content = fs::read_to_string(&manifest)
+            .map_err(|e| format!("Plugin '{}': failed to read {}: {}", id, manifest.display(), e))?;
+
+        let mut sections: Vec<(String, HashMap<String, String>)> = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                sections.push((name.trim().to_string(), HashMap::new()));
+            } else if let (Some((key, value)), Some((_, section))) = (line.split_once('='), sections.last_mut()) {
+                section.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+
+        let mut plugin = ExternalPlugin {
+            name: id.clone(),
+            id,
+            description: String::new(),
+            dir: dir.to_path_buf(),
+            actions: Vec::new(),
+            columns: Vec::new(),
+            preview: None,
+            backends: Vec::new(),
+        };
+        let id = plugin.id.clone();
+        let error = |e: String| format!("Plugin '{}': {}", id, e);
+        for (section, mut values) in sections {
+            let (kind, name) = section.split_once(' ').unwrap_or((section.as_str(), ""));
+            let name = name.trim();
+            match kind {
+                "plugin" => {
+                    if let Some(name) = values.remove("name") {
+                        plugin.name = name;
+                    }
+                    plugin.description = values.remove("description").unwrap_or_default();
+                },
+                "action" => {
+                    let command = PluginCommand::from_section(&section, &values).map_err(error)?;
+                    let label = values.remove("label").unwrap_or_else(|| name.to_string());
+                    plugin.actions.push((label, command));
+                },
+                "column" => {
+                    let command = PluginCommand::from_section(&section, &values).map_err(error)?;
+                    let title = values.remove("title").unwrap_or_else(|| name.to_string());
+                    let cache = ColumnCache {
+                        dir: plugin.dir.clone(),
+                        command,
+                        values: Mutex::new(HashMap::new()),
+                        queue: OnceLock::new(),
+                    };
+                    plugin.columns.push((title, Arc::new(cache)));
+                },
+                "preview" => {
+                    plugin.preview = Some(PluginCommand::from_section(&section, &values).map_err(error)?);
+                },
+                "backend" if !name.is_empty() => {
+                    let command = PluginCommand::from_section(&section, &values).map_err(error)?;
+                    let read_only = values.get("read_only").is_some_and(|value| value == "true");
+                    plugin.backends.push((name.to_string(), command, read_only));
+                },
+                _ => log::warn!("Plugin '{}': unknown section [{}]", id, section),
+            }
+        }
+        Ok(plugin)
+    }
+}
+
+impl Plugin for ExternalPlugin {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn actions(&self, paths: &[PathBuf]) -> Vec<PluginAction> {
+        if paths.is_empty() {
+            return Vec::new();
+        }
+        self.actions
+            .iter()
+            .filter(|(_, command)| paths.iter().all(|path| command.applies_to(path)))
+            .map(|(label, command)| {
+                let (label, command, dir, paths) = (label.clone(), command.clone(), self.dir.clone(), paths.to_vec());
+                PluginAction {
+                    label: label.clone(),
+                    run: Arc::new(move || {
+                        command
+                            .command(&dir)
+                            .args(&paths)
+                            .spawn()
+                            .map(|_| ())
+                            .map_err(|e| format!("Failed to run {}: {}", label, e))
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    fn columns(&self) -> Vec<ColumnProvider> {
+        self.columns
+            .iter()
+            .map(|(title, cache)| {
+                let cache = cache.clone();
+                ColumnProvider::new(title.clone(), move |entry| ModelData::String(cache.value(&entry.path)))
+            })
+            .collect()
+    }
+
+    fn preview(&self, path: &Path) -> Option<Result<String, String>> {
+        let command = self.preview.as_ref().filter(|command| command.applies_to(path))?;
+        let mut command = command.command(&self.dir);
+        command.arg(path);
+        Some(output(command, Some(COMMAND_TIMEOUT)))
+    }
+
+    fn backend(
+        &self,
+        kind: &str,
+        config: &HashMap<String, String>,
+    ) -> Option<Result<Box<dyn RemoteBackend>, String>> {
+        let (_, command, read_only) = self.backends.iter().find(|(name, _, _)| name == kind)?;
+        Some(Ok(Box::new(ExternalBackend {
+            dir: self.dir.clone(),
+            command: command.clone(),
+            config: config.clone(),
+            read_only: *read_only,
+        })))
+    }
+}
+
+/// A remote backend run as a plugin command. The remote's settings are passed
+/// as `FILEMAN_REMOTE_<KEY>` environment variables, and the command is run as
+///
+/// ```text
+/// command list PREFIX      prints "SIZE<TAB>MODIFIED<TAB>KEY" lines, MODIFIED in
+///                          seconds since 1970 or "-"
+/// command get KEY DEST     downloads KEY to the file DEST
+/// command put SRC KEY      uploads the file SRC as KEY
+/// command delete KEY
+/// ```
+///
+/// exiting unsuccessfully, with a message on stderr, on failure. Backends
+/// declared with `read_only = true` are never asked to `put` or `delete`.
+pub struct ExternalBackend {
+    dir: PathBuf,
+    command: PluginCommand,
+    config: HashMap<String, String>,
+    read_only: bool,
+}
+
+impl ExternalBackend {
+    async fn run(&self, args: Vec<String>) -> Result<String, String> {
+        let mut command = self.command.command(&self.dir);
+        command.args(args);
+        for (key, value) in &self.config {
+            command.env(format!("FILEMAN_REMOTE_{}", key.to_uppercase()), value);
+        }
+        // Transfers take as long as they take
+        tokio::task::spawn_blocking(move || output(command, None))
+            .await
+            .map_err(|e| format!("Failed to run the backend: {}", e))?
+    }
+}
+
+#[async_trait]
+impl RemoteBackend for ExternalBackend {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { write: !self.read_only }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<RemoteEntry>, String> {
+        let text = self.run(vec!["list".to_string(), prefix.to_string()]).await?;
+        Ok(text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let size = fields.next()?.parse().ok()?;
+                let modified = fields
+                    .next()?
+                    .parse()
+                    .ok()
+                    .map(|seconds| SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+                let key = fields.next()?.to_string();
+                Some(RemoteEntry { key, size, modified })
+            })
+            .collect())
+    }
+
+    async fn get(&self, key: &str, destination: &Path) -> Result<(), String> {
+        self.run(vec!["get".to_string(), key.to_string(), destination.to_string_lossy().to_string()])
+            .await
+            .map(|_| ())
+    }
+
+    async fn put(&self, source: &Path, key: &str) -> Result<(), String> {
+        self.run(vec!["put".to_string(), source.to_string_lossy().to_string(), key.to_string()])
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        self.run(vec!["delete".to_string(), key.to_string()]).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// A plugin folder `name` with `manifest` as its `plugin.conf`, in a
+    /// temporary folder removed when dropped
+    fn plugin_dir(name: &str, manifest: &str) -> (tempfile::TempDir, PathBuf) {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join(name);
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("plugin.conf"), manifest).unwrap();
+        (root, dir)
+    }
+
+    #[test]
+    fn reads_the_manifest() {
+        let (_root, dir) = plugin_dir(
+            "tools",
+            "# Image tools\n\
+             [plugin]\n\
+             name = Image Tools\n\
+             description = Rotate photos\n\
+             \n\
+             [action rotate]\n\
+             label = Rotate Clockwise\n\
+             command = rotate --clockwise\n\
+             extensions = .JPG, png,\n\
+             \n\
+             [action shrink]\n\
+             command = shrink\n\
+             \n\
+             [column dimensions]\n\
+             command = dimensions\n\
+             \n\
+             [preview]\n\
+             command = exif\n\
+             extensions = jpg\n\
+             \n\
+             [backend webdav]\n\
+             command = webdav --verbose\n\
+             read_only = true\n\
+             \n\
+             [toolbar]\n\
+             command = ignored\n",
+        );
+        let plugin = ExternalPlugin::load(&dir).unwrap();
+        assert_eq!(plugin.id(), "tools");
+        assert_eq!(plugin.name(), "Image Tools");
+        assert_eq!(plugin.description(), "Rotate photos");
+
+        let labels = |paths: &[&str]| -> Vec<String> {
+            let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+            plugin.actions(&paths).into_iter().map(|action| action.label).collect()
+        };
+        assert_eq!(labels(&["/a.jpg", "/b.PNG"]), ["Rotate Clockwise", "shrink"]);
+        assert_eq!(labels(&["/a.jpg", "/notes.txt"]), ["shrink"]);
+        assert!(labels(&[]).is_empty());
+
+        let (rotate, shrink) = (&plugin.actions[0].1, &plugin.actions[1].1);
+        assert_eq!(rotate.args, ["rotate", "--clockwise"]);
+        assert_eq!(rotate.extensions, ["jpg", "png"]);
+        assert!(shrink.extensions.is_empty());
+
+        assert_eq!(plugin.columns.len(), 1);
+        assert_eq!(plugin.columns[0].0, "dimensions");
+        assert!(plugin.preview(Path::new("/a.png")).is_none());
+        assert_eq!(plugin.backends.len(), 1);
+        assert_eq!(plugin.backends[0].0, "webdav");
+        assert!(plugin.backends[0].2);
+        assert!(plugin.backend("webdav", &HashMap::new()).is_some());
+        assert!(plugin.backend("sftp", &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn the_folder_names_plugins_without_a_name() {
+        let (_root, dir) = plugin_dir("unnamed", "[preview]\ncommand = cat\n");
+        let plugin = ExternalPlugin::load(&dir).unwrap();
+        assert_eq!(plugin.name(), plugin.id());
+        assert_eq!(plugin.description(), "");
+    }
+
+    #[test]
+    fn sections_need_a_command() {
+        let (root, dir) = plugin_dir("broken", "[plugin]\nname = Broken\n\n[action open]\nlabel = Open\n");
+        let error = ExternalPlugin::load(&dir).err().unwrap();
+        assert!(error.contains("[action open] has no command"), "{}", error);
+
+        let missing = root.path().join("missing");
+        assert!(ExternalPlugin::load(&missing).is_err());
+    }
+
+    #[test]
+    fn commands_in_the_folder_are_run_from_there() {
+        let (_root, dir) = plugin_dir("preview", "[preview]\ncommand = describe --short\n");
+        let script = dir.join("describe");
+        fs::write(&script, "#!/bin/sh\necho \"$1 $2\"\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let plugin = ExternalPlugin::load(&dir).unwrap();
+        assert_eq!(plugin.preview(Path::new("/a.txt")), Some(Ok("--short /a.txt\n".to_string())));
+    }
+}
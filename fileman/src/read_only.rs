@@ -0,0 +1,20 @@
+//! Read-only locations
+//!
+//! Folders on read-only mounts and local copies of remotes that can't be
+//! pushed to are shown read-only: New Folder, Paste, Rename and Delete are
+//! disabled there and the status bar tells why.
+
+use crate::remote::Remote;
+use nptk_fileman_widgets::file_names;
+use std::path::Path;
+
+/// Why nothing in the folder `dir` can be changed, `None` if it can be.
+/// Touches the file system, so call it off the UI thread.
+pub fn reason(dir: &Path, remotes: &[Remote]) -> Option<String> {
+    if let Some(remote) = remotes.iter().find(|remote| dir.starts_with(remote.local_dir())) {
+        if !remote.writable() {
+            return Some(format!("the remote '{}' is read-only", remote.name));
+        }
+    }
+    file_names::read_only_file_system(dir).then(|| "this location is mounted read-only".to_string())
+}
@@ -0,0 +1,244 @@
+//! Remote storage backends
+//!
+//! A [RemoteBackend] stores files under string keys (e.g. an S3 bucket). Remotes
+//! are configured in `$XDG_CONFIG_HOME/fileman/remotes` and listed in the sidebar;
+//! each one is browsed through a local copy in `$XDG_DATA_HOME/fileman/remotes/<name>`
+//! that is synchronized with [pull] and [push].
+//!
+//! ```text
+//! [photos]
+//! type = s3
+//! bucket = my-photos
+//! region = eu-central-1
+//! access_key = AKIA...
+//! secret_key = ...
+//! # endpoint = https://minio.example.com   (optional, for S3-compatible services)
+//! # read_only = true                       (optional, never upload or delete)
+//! ```
+//!
+//! Other types are provided by [plugins](crate::plugins). A remote is read-only
+//! if its config says so or its backend can't write ([Capabilities]).
+
+mod s3;
+
+pub use s3::S3Backend;
+
+use crate::plugins;
+use async_trait::async_trait;
+use nptk::widgets::sidebar::SidebarItem;
+use nptk_fileman_widgets::xdg;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// An object stored in a remote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteEntry {
+    /// Path within the remote, with `/` separators
+    pub key: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// What a backend can do besides listing and downloading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Objects can be uploaded and deleted
+    pub write: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self { write: true }
+    }
+}
+
+/// A storage service files can be listed in, downloaded from and uploaded to.
+#[async_trait]
+pub trait RemoteBackend: Send + Sync {
+    /// What the backend supports; everything by default.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+    /// All objects whose key starts with `prefix`.
+    async fn list(&self, prefix: &str) -> Result<Vec<RemoteEntry>, String>;
+    /// Download `key` to the file `destination`.
+    async fn get(&self, key: &str, destination: &Path) -> Result<(), String>;
+    /// Upload the file `source` as `key`.
+    async fn put(&self, source: &Path, key: &str) -> Result<(), String>;
+    async fn delete(&self, key: &str) -> Result<(), String>;
+}
+
+/// A configured remote.
+pub struct Remote {
+    pub name: String,
+    pub backend: Box<dyn RemoteBackend>,
+    /// Set in the config; the local copy is then never pushed
+    pub read_only: bool,
+}
+
+impl Remote {
+    /// Whether local changes can be pushed to the remote.
+    pub fn writable(&self) -> bool {
+        !self.read_only && self.backend.capabilities().write
+    }
+
+    /// Where the remote's local copy is kept.
+    pub fn local_dir(&self) -> PathBuf {
+        xdg::data_home().join("fileman/remotes").join(&self.name)
+    }
+
+    /// Sidebar item opening the local copy (item id `"remote:<name>"`).
+    pub fn sidebar_item(&self) -> SidebarItem {
+        SidebarItem::new(format!("remote:{}", self.name), self.name.clone())
+            .with_icon("folder-remote")
+            .with_uri(format!("file://{}", self.local_dir().display()))
+    }
+}
+
+/// Read the configured remotes. Sections with an unknown type or missing keys are
+/// skipped with a warning.
+pub fn load() -> Vec<Remote> {
+    let config_home = xdg::config_home();
+    let Ok(content) = fs::read_to_string(config_home.join("fileman/remotes")) else {
+        return Vec::new();
+    };
+
+    let mut sections: Vec<(String, HashMap<String, String>)> = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), HashMap::new()));
+        } else if let (Some((key, value)), Some((_, section))) =
+            (line.split_once('='), sections.last_mut())
+        {
+            section.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    sections
+        .into_iter()
+        .filter_map(|(name, mut values)| {
+            let read_only = values.remove("read_only").is_some_and(|value| value == "true");
+            let backend: Box<dyn RemoteBackend> = match values.get("type").map(String::as_str) {
+                Some("s3") => match S3Backend::from_config(&mut values) {
+                    Ok(backend) => Box::new(backend),
+                    Err(e) => {
+                        log::warn!("Remote '{}': {}", name, e);
+                        return None;
+                    },
+                },
+                other => match other.and_then(|kind| plugins::backend(kind, &values)) {
+                    Some(Ok(backend)) => backend,
+                    Some(Err(e)) => {
+                        log::warn!("Remote '{}': {}", name, e);
+                        return None;
+                    },
+                    None => {
+                        log::warn!("Remote '{}' has unknown type {:?}", name, other);
+                        return None;
+                    },
+                },
+            };
+            Some(Remote { name, backend, read_only })
+        })
+        .collect()
+}
+
+/// Files below `dir`, keyed by their path relative to it with `/` separators.
+fn local_files(dir: &Path) -> HashMap<String, fs::Metadata> {
+    fn walk(dir: &Path, prefix: &str, files: &mut HashMap<String, fs::Metadata>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let key = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => {
+                    walk(&entry.path(), &format!("{}/", key), files)
+                },
+                Ok(metadata) => {
+                    files.insert(key, metadata);
+                },
+                Err(_) => {},
+            }
+        }
+    }
+    let mut files = HashMap::new();
+    walk(dir, "", &mut files);
+    files
+}
+
+/// Download objects that are missing or differ locally. Returns how many were downloaded.
+pub async fn pull(remote: &Remote) -> Result<usize, String> {
+    let local_dir = remote.local_dir();
+    let local = local_files(&local_dir);
+    let mut downloaded = 0;
+    for entry in remote.backend.list("").await? {
+        // "Folder" placeholder objects have no content of their own
+        if entry.key.ends_with('/') {
+            continue;
+        }
+        let up_to_date = local.get(&entry.key).is_some_and(|metadata| {
+            metadata.len() == entry.size
+                && entry
+                    .modified
+                    .is_none_or(|remote| metadata.modified().is_ok_and(|local| local >= remote))
+        });
+        if up_to_date {
+            continue;
+        }
+        let destination = local_dir.join(&entry.key);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        remote.backend.get(&entry.key, &destination).await?;
+        downloaded += 1;
+    }
+    Ok(downloaded)
+}
+
+/// Upload local files that are missing or differ remotely. With `mirror`, objects
+/// without a local file are deleted. Returns how many objects were changed.
+pub async fn push(remote: &Remote, mirror: bool) -> Result<usize, String> {
+    if !remote.writable() {
+        return Err(format!("Remote '{}' is read-only", remote.name));
+    }
+    let local_dir = remote.local_dir();
+    let local = local_files(&local_dir);
+    let remote_entries: HashMap<String, RemoteEntry> = remote
+        .backend
+        .list("")
+        .await?
+        .into_iter()
+        .map(|entry| (entry.key.clone(), entry))
+        .collect();
+
+    let mut changed = 0;
+    for (key, metadata) in &local {
+        let up_to_date = remote_entries.get(key).is_some_and(|entry| {
+            entry.size == metadata.len()
+                && entry
+                    .modified
+                    .is_some_and(|remote| metadata.modified().is_ok_and(|local| local <= remote))
+        });
+        if !up_to_date {
+            remote.backend.put(&local_dir.join(key), key).await?;
+            changed += 1;
+        }
+    }
+    if mirror {
+        for key in remote_entries
+            .keys()
+            .filter(|key| !key.ends_with('/') && !local.contains_key(*key))
+        {
+            remote.backend.delete(key).await?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
@@ -0,0 +1,552 @@
+//! Amazon S3 (and compatible services) with path-style requests signed with
+//! AWS Signature Version 4.
+
+use super::{RemoteBackend, RemoteEntry};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Files larger than this are uploaded in parts
+const MULTIPART_THRESHOLD: u64 = 16 * 1024 * 1024;
+/// Size of each uploaded part (S3 requires at least 5 MiB except for the last)
+const PART_SIZE: usize = 8 * 1024 * 1024;
+/// Request bodies aren't hashed; S3 accepts this over HTTPS
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// A bucket accessed with an access key.
+pub struct S3Backend {
+    client: reqwest::Client,
+    /// E.g. `https://s3.eu-central-1.amazonaws.com`
+    endpoint: String,
+    region: String,
+    bucket: String,
+    access_key: String,
+    secret_key: String,
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Percent-encode everything but unreserved characters (and `/` when `keep_slash`).
+fn uri_encode(text: &str, keep_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            },
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Content of every `<tag>` element in `xml`, as it is (for elements
+/// containing others).
+fn xml_elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    xml.split(&open)
+        .skip(1)
+        .filter_map(|rest| rest.split_once(&close).map(|(value, _)| value))
+        .collect()
+}
+
+/// Text of every `<tag>` element in `xml`, with entities decoded. Only for
+/// elements containing text; decoding twice would turn `&amp;lt;` into `<`.
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    xml_elements(xml, tag).into_iter().map(decode_entities).collect()
+}
+
+/// Replace the predefined and numeric character references of `text`.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let character = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "amp" => '&',
+                name => {
+                    let code = match name.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => name.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                },
+            };
+            Some((c, end))
+        });
+        match character {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Canonical form of a request and the names of its signed headers.
+/// `headers` have lowercase names and are sorted by them.
+fn canonical_request(
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+) -> (String, String) {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, path, query, canonical_headers, signed_headers, payload_hash
+    );
+    (request, signed_headers)
+}
+
+/// Key derived from the secret key for `date` (`YYYYMMDD`), `region` and `service`.
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    [region, service, "aws4_request"].iter().fold(
+        hmac(format!("AWS4{}", secret_key).as_bytes(), date),
+        |key, part| hmac(&key, part),
+    )
+}
+
+/// Credential scope and signature of `canonical_request`, made at
+/// `amz_date` (`YYYYMMDDTHHMMSSZ`).
+fn sign(secret_key: &str, region: &str, amz_date: &str, canonical_request: &str) -> (String, String) {
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex(&hmac(&signing_key(secret_key, date, region, "s3"), &string_to_sign));
+    (scope, signature)
+}
+
+/// Hidden sibling of `destination` a download is written to before it is
+/// complete.
+fn partial_path(destination: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(destination.file_name().unwrap_or_default());
+    name.push(".fileman-tmp");
+    destination.with_file_name(name)
+}
+
+impl S3Backend {
+    /// Build from a remotes config section (`bucket`, `region`, `access_key`,
+    /// `secret_key` and optionally `endpoint`).
+    pub fn from_config(values: &mut HashMap<String, String>) -> Result<Self, String> {
+        let mut take = |key: &str| {
+            values
+                .remove(key)
+                .ok_or_else(|| format!("missing '{}'", key))
+        };
+        let bucket = take("bucket")?;
+        let region = take("region")?;
+        let access_key = take("access_key")?;
+        let secret_key = take("secret_key")?;
+        let endpoint = values
+            .remove("endpoint")
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            region,
+            bucket,
+            access_key,
+            secret_key,
+        })
+    }
+
+    /// Build a signed request for `key` (empty for the bucket itself).
+    fn request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        query: &[(&str, &str)],
+    ) -> reqwest::RequestBuilder {
+        let now: DateTime<Utc> = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let path = format!("/{}/{}", self.bucket, uri_encode(key, true));
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, false), uri_encode(v, false)))
+            .collect();
+        query.sort();
+        let query = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+        let host = self
+            .endpoint
+            .split_once("://")
+            .map(|(_, host)| host)
+            .unwrap_or(&self.endpoint)
+            .to_string();
+
+        let (canonical_request, signed_headers) = canonical_request(
+            method.as_str(),
+            &path,
+            &query,
+            &[
+                ("host", &host),
+                ("x-amz-content-sha256", UNSIGNED_PAYLOAD),
+                ("x-amz-date", &amz_date),
+            ],
+            UNSIGNED_PAYLOAD,
+        );
+        let (scope, signature) = sign(&self.secret_key, &self.region, &amz_date, &canonical_request);
+
+        let url = if query.is_empty() {
+            format!("{}{}", self.endpoint, path)
+        } else {
+            format!("{}{}?{}", self.endpoint, path, query)
+        };
+        self.client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", UNSIGNED_PAYLOAD)
+            .header(
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, signed_headers, signature
+                ),
+            )
+    }
+
+    /// Send a request and fail on non-success statuses with S3's error message.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        what: &str,
+    ) -> Result<reqwest::Response, String> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to {}: {}", what, e))?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        let message = xml_values(&body, "Message")
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| status.to_string());
+        Err(format!("Failed to {}: {}", what, message))
+    }
+
+    async fn put_multipart(&self, source: &Path, key: &str) -> Result<(), String> {
+        let what = format!("upload {}", key);
+        let body = self
+            .send(
+                self.request(reqwest::Method::POST, key, &[("uploads", "")]),
+                &what,
+            )
+            .await?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to {}: {}", what, e))?;
+        let upload_id = xml_values(&body, "UploadId")
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Failed to {}: no upload id", what))?;
+
+        let result = self.upload_parts(source, key, &upload_id, &what).await;
+        if result.is_err() {
+            // Don't leave the uploaded parts behind (they are billed until aborted)
+            let abort = self.request(
+                reqwest::Method::DELETE,
+                key,
+                &[("uploadId", upload_id.as_str())],
+            );
+            let _ = abort.send().await;
+        }
+        result
+    }
+
+    async fn upload_parts(
+        &self,
+        source: &Path,
+        key: &str,
+        upload_id: &str,
+        what: &str,
+    ) -> Result<(), String> {
+        let mut file =
+            std::fs::File::open(source).map_err(|e| format!("Failed to {}: {}", what, e))?;
+        let mut etags = Vec::new();
+        loop {
+            let mut part = Vec::with_capacity(PART_SIZE);
+            (&mut file)
+                .take(PART_SIZE as u64)
+                .read_to_end(&mut part)
+                .map_err(|e| format!("Failed to {}: {}", what, e))?;
+            if part.is_empty() && !etags.is_empty() {
+                break;
+            }
+            let number = (etags.len() + 1).to_string();
+            let last = part.len() < PART_SIZE;
+            let request = self
+                .request(
+                    reqwest::Method::PUT,
+                    key,
+                    &[("partNumber", number.as_str()), ("uploadId", upload_id)],
+                )
+                .body(part);
+            let response = self.send(request, what).await?;
+            let etag = response
+                .headers()
+                .get("ETag")
+                .and_then(|v| v.to_str().ok())
+                .filter(|etag| !etag.is_empty())
+                .ok_or_else(|| format!("Failed to {}: no ETag for part {}", what, number))?
+                .to_string();
+            etags.push(etag);
+            if last {
+                break;
+            }
+        }
+
+        let parts: String = etags
+            .iter()
+            .enumerate()
+            .map(|(i, etag)| {
+                format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                    i + 1,
+                    etag
+                )
+            })
+            .collect();
+        let request = self
+            .request(reqwest::Method::POST, key, &[("uploadId", upload_id)])
+            .body(format!(
+                "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+                parts
+            ));
+        let body = self
+            .send(request, what)
+            .await?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to {}: {}", what, e))?;
+        // Completing can fail after S3 answered 200 OK
+        match xml_elements(&body, "Error").first() {
+            Some(error) => Err(format!(
+                "Failed to {}: {}",
+                what,
+                xml_values(error, "Message")
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(|| "the parts could not be combined".to_string())
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the object `key` to `file`.
+    async fn download(&self, key: &str, file: &Path, what: &str) -> Result<(), String> {
+        let mut response = self
+            .send(self.request(reqwest::Method::GET, key, &[]), what)
+            .await?;
+        let mut file = tokio::fs::File::create(file)
+            .await
+            .map_err(|e| format!("Failed to {}: {}", what, e))?;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Failed to {}: {}", what, e))?
+        {
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| format!("Failed to {}: {}", what, e))?;
+        }
+        file.flush()
+            .await
+            .map_err(|e| format!("Failed to {}: {}", what, e))
+    }
+}
+
+#[async_trait]
+impl RemoteBackend for S3Backend {
+    async fn list(&self, prefix: &str) -> Result<Vec<RemoteEntry>, String> {
+        let what = format!("list bucket {}", self.bucket);
+        let mut entries = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix)];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let body = self
+                .send(self.request(reqwest::Method::GET, "", &query), &what)
+                .await?
+                .text()
+                .await
+                .map_err(|e| format!("Failed to {}: {}", what, e))?;
+
+            for contents in xml_elements(&body, "Contents") {
+                let Some(key) = xml_values(contents, "Key").into_iter().next() else {
+                    continue;
+                };
+                entries.push(RemoteEntry {
+                    key,
+                    size: xml_values(contents, "Size")
+                        .first()
+                        .and_then(|s| s.parse().ok())
+                        .unwrap_or(0),
+                    modified: xml_values(contents, "LastModified")
+                        .first()
+                        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                        .map(|time| time.into()),
+                });
+            }
+
+            let truncated = xml_values(&body, "IsTruncated")
+                .first()
+                .is_some_and(|s| s == "true");
+            token = xml_values(&body, "NextContinuationToken")
+                .into_iter()
+                .next();
+            if !truncated || token.is_none() {
+                return Ok(entries);
+            }
+        }
+    }
+
+    async fn get(&self, key: &str, destination: &Path) -> Result<(), String> {
+        let what = format!("download {}", key);
+        // What is at `destination` stays until the download is complete
+        let partial = partial_path(destination);
+        let mut result = self.download(key, &partial, &what).await;
+        if result.is_ok() {
+            result = tokio::fs::rename(&partial, destination)
+                .await
+                .map_err(|e| format!("Failed to {}: {}", what, e));
+        }
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&partial).await;
+        }
+        result
+    }
+
+    async fn put(&self, source: &Path, key: &str) -> Result<(), String> {
+        let size = std::fs::metadata(source).map(|m| m.len()).unwrap_or(0);
+        if size > MULTIPART_THRESHOLD {
+            return self.put_multipart(source, key).await;
+        }
+        let what = format!("upload {}", key);
+        let data = tokio::fs::read(source)
+            .await
+            .map_err(|e| format!("Failed to {}: {}", what, e))?;
+        self.send(
+            self.request(reqwest::Method::PUT, key, &[]).body(data),
+            &what,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        let what = format!("delete {}", key);
+        self.send(self.request(reqwest::Method::DELETE, key, &[]), &what)
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_uris() {
+        assert_eq!(uri_encode("photos/2024 summer/a+b.jpg", true), "photos/2024%20summer/a%2Bb.jpg");
+        assert_eq!(uri_encode("a/b", false), "a%2Fb");
+        assert_eq!(uri_encode("Az09-_.~", false), "Az09-_.~");
+        assert_eq!(uri_encode("é=&", false), "%C3%A9%3D%26");
+    }
+
+    #[test]
+    fn decodes_values_once() {
+        let xml = "<ListBucketResult><Contents><Key>a&amp;lt;b&gt;&#233;&#x41;&bogus</Key><Size>3</Size></Contents>\
+                   <Contents><Key>second</Key></Contents></ListBucketResult>";
+        let contents = xml_elements(xml, "Contents");
+        assert_eq!(contents.len(), 2);
+        assert_eq!(xml_values(contents[0], "Key"), ["a&lt;b>éA&bogus"]);
+        assert_eq!(xml_values(contents[0], "Size"), ["3"]);
+        assert_eq!(xml_values(contents[1], "Key"), ["second"]);
+        assert!(xml_values(xml, "Missing").is_empty());
+    }
+
+    /// Example from the AWS documentation on deriving the signing key
+    #[test]
+    fn derives_the_signing_key() {
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex(&key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    /// "GET Object" example from the AWS documentation on signing S3 requests
+    #[test]
+    fn signs_requests() {
+        let empty_payload = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let (request, signed_headers) = canonical_request(
+            "GET",
+            "/test.txt",
+            "",
+            &[
+                ("host", "examplebucket.s3.amazonaws.com"),
+                ("range", "bytes=0-9"),
+                ("x-amz-content-sha256", empty_payload),
+                ("x-amz-date", "20130524T000000Z"),
+            ],
+            empty_payload,
+        );
+        assert_eq!(signed_headers, "host;range;x-amz-content-sha256;x-amz-date");
+        assert_eq!(
+            hex(&Sha256::digest(request.as_bytes())),
+            "7344ae5b7ee6c3e7e6b0fe0640412a37625d1fbfff95c48bbb2dc43964946972"
+        );
+        let (scope, signature) =
+            sign("wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY", "us-east-1", "20130524T000000Z", &request);
+        assert_eq!(scope, "20130524/us-east-1/s3/aws4_request");
+        assert_eq!(signature, "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41");
+    }
+}
@@ -0,0 +1,352 @@
+//! Batch scripts
+//!
+//! Scripts written in [rhai](https://rhai.rs) are kept as `.rhai` files in
+//! `$XDG_CONFIG_HOME/fileman/scripts` and run against the selection from the
+//! context menu of the file list. Scripts can't touch files themselves: they
+//! can look at files (`selection()`, `list()`, `name()`, ...) and ask for
+//! copies, moves and renames (`copy()`, `move_to()`, `rename()`), which are
+//! collected into a [ScriptPlan] the user reviews before it is carried out.
+//! Runs are limited in the operations they may take, so a runaway loop ends.
+//!
+//! ```text
+//! // Number the selected photos: "Holiday 01.jpg", "Holiday 02.jpg", ...
+//! let n = 1;
+//! for path in selection() {
+//!     rename(path, `Holiday ${if n < 10 { "0" } else { "" }}${n}.${extension(path)}`);
+//!     n += 1;
+//! }
+//! ```
+
+mod dialog;
+
+pub use dialog::RunScriptDialog;
+
+use crate::daemon;
+use crate::operations;
+use crate::operations::engine::{Operation, OperationKind};
+use nptk_fileman_widgets::file_names;
+use nptk_fileman_widgets::xdg;
+use rhai::{Array, Dynamic, Engine, EvalAltResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Operations a script may take before it is stopped
+const MAX_OPERATIONS: u64 = 5_000_000;
+
+/// Changes a script may ask for before it is stopped
+const MAX_ACTIONS: usize = 10_000;
+
+/// A change asked for by a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptAction {
+    Copy { source: PathBuf, destination: PathBuf },
+    Move { source: PathBuf, destination: PathBuf },
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+impl ScriptAction {
+    /// One line describing the change, for review.
+    pub fn describe(&self) -> String {
+        let name = |path: &Path| {
+            path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string())
+        };
+        match self {
+            ScriptAction::Copy { source, destination } => {
+                format!("Copy {} to {}", name(source), destination.display())
+            },
+            ScriptAction::Move { source, destination } => {
+                format!("Move {} to {}", name(source), destination.display())
+            },
+            ScriptAction::Rename { from, to } => format!("Rename {} to {}", name(from), name(to)),
+        }
+    }
+}
+
+/// What a script run asked for, and what it printed.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptPlan {
+    pub actions: Vec<ScriptAction>,
+    pub output: Vec<String>,
+}
+
+/// Folder the scripts are kept in.
+pub fn scripts_dir() -> PathBuf {
+    xdg::config_home().join("fileman").join("scripts")
+}
+
+/// The saved scripts, sorted by name.
+pub fn list() -> Vec<PathBuf> {
+    let mut scripts: Vec<PathBuf> = fs::read_dir(scripts_dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "rhai"))
+                .collect()
+        })
+        .unwrap_or_default();
+    scripts.sort();
+    scripts
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+fn text(path: &Path) -> Dynamic {
+    Dynamic::from(path.to_string_lossy().to_string())
+}
+
+/// A path given to a script function, which must be absolute
+fn absolute(path: &str) -> ScriptResult<PathBuf> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() {
+        return Err(format!("Not an absolute path: {}", path.display()).into());
+    }
+    Ok(path)
+}
+
+/// Run `script` against `selection`, shown in `folder`, and collect what it asks for.
+pub fn plan(script: &str, selection: &[PathBuf], folder: &Path) -> Result<ScriptPlan, String> {
+    let plan = Arc::new(Mutex::new(ScriptPlan::default()));
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_string_size(1 << 20);
+    engine.set_max_array_size(100_000);
+    engine.disable_symbol("eval");
+
+    let printed = plan.clone();
+    engine.on_print(move |line| {
+        if let Ok(mut plan) = printed.lock() {
+            plan.output.push(line.to_string());
+        }
+    });
+
+    let selected: Array = selection.iter().map(|path| text(path)).collect();
+    engine.register_fn("selection", move || selected.clone());
+    let shown = text(folder);
+    engine.register_fn("folder", move || shown.clone());
+
+    // Looking at files
+    engine.register_fn("list", |dir: &str| -> ScriptResult<Array> {
+        let dir = absolute(dir)?;
+        let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+            .map_err(|e| format!("Failed to list {}: {}", dir.display(), e))?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        paths.sort();
+        Ok(paths.iter().map(|path| text(path)).collect())
+    });
+    engine.register_fn("name", |path: &str| {
+        Path::new(path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+    });
+    engine.register_fn("stem", |path: &str| {
+        Path::new(path).file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+    });
+    engine.register_fn("extension", |path: &str| {
+        Path::new(path).extension().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+    });
+    engine.register_fn("parent", |path: &str| {
+        Path::new(path).parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default()
+    });
+    engine.register_fn("join", |path: &str, name: &str| Path::new(path).join(name).to_string_lossy().to_string());
+    engine.register_fn("exists", |path: &str| Path::new(path).exists());
+    engine.register_fn("is_dir", |path: &str| Path::new(path).is_dir());
+    engine.register_fn("size", |path: &str| fs::metadata(path).map(|m| m.len() as i64).unwrap_or(0));
+
+    // Asking for changes
+    let record = {
+        let plan = plan.clone();
+        move |action: ScriptAction| -> ScriptResult<()> {
+            let mut plan = plan.lock().map_err(|_| "Failed to record the change".to_string())?;
+            if plan.actions.len() >= MAX_ACTIONS {
+                return Err(format!("A script can make at most {} changes", MAX_ACTIONS).into());
+            }
+            plan.actions.push(action);
+            Ok(())
+        }
+    };
+    let copy = record.clone();
+    engine.register_fn("copy", move |source: &str, destination: &str| -> ScriptResult<()> {
+        copy(ScriptAction::Copy { source: absolute(source)?, destination: absolute(destination)? })
+    });
+    let move_to = record.clone();
+    engine.register_fn("move_to", move |source: &str, destination: &str| -> ScriptResult<()> {
+        move_to(ScriptAction::Move { source: absolute(source)?, destination: absolute(destination)? })
+    });
+    engine.register_fn("rename", move |path: &str, name: &str| -> ScriptResult<()> {
+        let from = absolute(path)?;
+        let dir = from.parent().unwrap_or(Path::new("/"));
+        file_names::validate(name, dir).map_err(|e| format!("{}: {}", name, e))?;
+        let to = from.with_file_name(name);
+        record(ScriptAction::Rename { from, to })
+    });
+
+    engine.run(script).map_err(|e| format!("Script failed: {}", e))?;
+    drop(engine);
+    Ok(plan.lock().map(|plan| plan.clone()).unwrap_or_default())
+}
+
+/// How often [apply] checks whether queued jobs it waits for have ended
+const JOB_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// One step of carrying out a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    /// Copies or moves into one folder, queued as one job
+    Queue { moving: bool, sources: Vec<PathBuf>, destination: PathBuf },
+    /// A rename, done at once. With `wait`, it involves what a job queued
+    /// before it copies or moves, so that job has to end first.
+    Rename { from: PathBuf, to: PathBuf, wait: bool },
+}
+
+/// Whether one of `a` and `b` is, or is inside, the other.
+fn related(a: &Path, b: &Path) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+/// The steps carrying out `actions` in their order. Copies or moves in a row
+/// into the same folder become one job.
+fn steps(actions: &[ScriptAction]) -> Vec<Step> {
+    let mut steps: Vec<Step> = Vec::new();
+    // Paths the jobs queued since the last wait read or write
+    let mut queued: Vec<PathBuf> = Vec::new();
+    for action in actions {
+        let (moving, source, destination) = match action {
+            ScriptAction::Copy { source, destination } => (false, source, destination),
+            ScriptAction::Move { source, destination } => (true, source, destination),
+            ScriptAction::Rename { from, to } => {
+                let wait = queued.iter().any(|path| related(from, path) || related(to, path));
+                if wait {
+                    queued.clear();
+                }
+                steps.push(Step::Rename { from: from.clone(), to: to.clone(), wait });
+                continue;
+            },
+        };
+        queued.push(source.clone());
+        queued.push(destination.clone());
+        match steps.last_mut() {
+            Some(Step::Queue { moving: last_moving, sources, destination: last_destination })
+                if *last_moving == moving && last_destination == destination =>
+            {
+                sources.push(source.clone())
+            },
+            _ => steps.push(Step::Queue {
+                moving,
+                sources: vec![source.clone()],
+                destination: destination.clone(),
+            }),
+        }
+    }
+    steps
+}
+
+/// Wait until the daemon jobs `ids` have ended; fails if one of them didn't
+/// finish.
+async fn wait_for(ids: &[u32]) -> Result<(), String> {
+    loop {
+        let jobs = daemon::client::jobs().await;
+        let mine: Vec<&daemon::JobInfo> = jobs.iter().filter(|job| ids.contains(&job.id)).collect();
+        if let Some(job) = mine.iter().find(|job| matches!(job.state, daemon::JobState::Failed(_) | daemon::JobState::Cancelled)) {
+            return Err(format!("{} did not finish; the rest of the script was not carried out", job.description));
+        }
+        if !mine.iter().any(|job| job.state.is_active()) {
+            return Ok(());
+        }
+        tokio::time::sleep(JOB_POLL_INTERVAL).await;
+    }
+}
+
+/// Carry out a reviewed plan in the order the script asked for it: renames
+/// at once, copies and moves queued in the operations daemon. A rename
+/// involving what a queued job copies or moves waits for that job. Returns
+/// how many renames failed.
+pub async fn apply(plan: &ScriptPlan) -> Result<usize, String> {
+    let mut failed = 0;
+    let mut queued = Vec::new();
+    for step in steps(&plan.actions) {
+        match step {
+            Step::Queue { moving, sources, destination } => {
+                let kind = if moving {
+                    OperationKind::Move { sources, destination }
+                } else {
+                    OperationKind::Copy { sources, destination }
+                };
+                queued.push(daemon::client::queue(&Operation::new(kind)).await?);
+            },
+            Step::Rename { from, to, wait } => {
+                if wait {
+                    wait_for(&queued).await?;
+                    queued.clear();
+                }
+                // Renaming never replaces a file; a name may be taken by an earlier rename
+                let result = if fs::symlink_metadata(&to).is_ok() {
+                    Err(format!("{} already exists", to.display()))
+                } else {
+                    operations::rename_path(from, to)
+                };
+                if let Err(e) = result {
+                    log::error!("{}", e);
+                    failed += 1;
+                }
+            },
+        }
+    }
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_follow_the_script() {
+        let script = r#"
+            copy("/home/user/a.txt", "/backup");
+            rename("/home/user/b.txt", "c.txt");
+            move_to("/home/user/c.txt", "/archive");
+            print("done");
+        "#;
+        let plan = plan(script, &[], Path::new("/home/user")).unwrap();
+        assert_eq!(
+            plan.actions,
+            [
+                ScriptAction::Copy { source: "/home/user/a.txt".into(), destination: "/backup".into() },
+                ScriptAction::Rename { from: "/home/user/b.txt".into(), to: "/home/user/c.txt".into() },
+                ScriptAction::Move { source: "/home/user/c.txt".into(), destination: "/archive".into() },
+            ]
+        );
+        assert_eq!(plan.output, ["done"]);
+    }
+
+    #[test]
+    fn steps_keep_the_order_and_wait_for_what_renames_depend_on() {
+        let copy = |source: &str, destination: &str| ScriptAction::Copy {
+            source: source.into(),
+            destination: destination.into(),
+        };
+        let rename = |from: &str, to: &str| ScriptAction::Rename { from: from.into(), to: to.into() };
+        let actions = [
+            copy("/home/a", "/backup"),
+            copy("/home/b", "/backup"),
+            rename("/home/c", "/home/d"),
+            copy("/home/e", "/other"),
+            rename("/backup/a", "/backup/a.old"),
+            ScriptAction::Move { source: "/home/f".into(), destination: "/other".into() },
+        ];
+        assert_eq!(
+            steps(&actions),
+            [
+                Step::Queue { moving: false, sources: vec!["/home/a".into(), "/home/b".into()], destination: "/backup".into() },
+                Step::Rename { from: "/home/c".into(), to: "/home/d".into(), wait: false },
+                Step::Queue { moving: false, sources: vec!["/home/e".into()], destination: "/other".into() },
+                Step::Rename { from: "/backup/a".into(), to: "/backup/a.old".into(), wait: true },
+                Step::Queue { moving: true, sources: vec!["/home/f".into()], destination: "/other".into() },
+            ]
+        );
+    }
+}
@@ -0,0 +1,272 @@
+//! Popup for running a saved script against the selection.
+
+use super::{ScriptPlan, apply, list, plan, scripts_dir};
+use crate::bus::AppBus;
+use crate::toolbar::NavigationAction;
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Planned changes listed for review, the rest summarized
+const MAX_LISTED: usize = 12;
+
+/// Which button was pressed, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScriptRequest {
+    /// Index into the scripts shown
+    Run(usize),
+    Apply,
+    OpenFolder,
+}
+
+/// Saved scripts with Run buttons. A run lists the changes it asks for, which
+/// are only made once they are applied.
+pub struct RunScriptDialog {
+    selection: Vec<PathBuf>,
+    folder: PathBuf,
+    bus: AppBus,
+    scripts: Vec<PathBuf>,
+    // Changes of the last run, until they are applied
+    plan: Option<ScriptPlan>,
+    // Filled in by the blocking thread the script runs on
+    finished: Arc<Mutex<Option<Result<ScriptPlan, String>>>>,
+    running: bool,
+    inner: Container,
+    message_text: StateSignal<String>,
+    request: Arc<Mutex<Option<ScriptRequest>>>,
+    signals_hooked: bool,
+}
+
+impl RunScriptDialog {
+    pub fn new(selection: Vec<PathBuf>, folder: PathBuf, bus: AppBus) -> Self {
+        let mut dialog = Self {
+            selection,
+            folder,
+            bus,
+            scripts: list(),
+            plan: None,
+            finished: Arc::new(Mutex::new(None)),
+            running: false,
+            inner: Container::new(vec![]),
+            message_text: StateSignal::new(String::new()),
+            request: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        };
+        dialog.rebuild();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), "Run Script", (500, 480), (300, 150));
+    }
+
+    fn button(&self, label: &str, request: ScriptRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn row(children: Vec<Box<dyn Widget>>) -> Container {
+        Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Row,
+            align_items: Some(AlignItems::Center),
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            ..Default::default()
+        })
+    }
+
+    fn rebuild(&mut self) {
+        let mut children: Vec<Box<dyn Widget>> = vec![Box::new(Text::new(format!(
+            "{} selected item(s) in {}",
+            self.selection.len(),
+            self.folder.display()
+        )))];
+        if self.scripts.is_empty() {
+            children.push(Box::new(
+                Text::new(format!("No scripts yet; save .rhai files in {}", scripts_dir().display()))
+                    .with_font_size(12.0),
+            ));
+        }
+        for (i, script) in self.scripts.iter().enumerate() {
+            let name = script
+                .file_stem()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            children.push(Box::new(Self::row(vec![
+                Box::new(Text::new(name).with_layout_style(LayoutStyle {
+                    flex_grow: 1.0,
+                    ..Default::default()
+                })),
+                Box::new(self.button("Run", ScriptRequest::Run(i))),
+            ])));
+        }
+
+        if let Some(plan) = &self.plan {
+            for line in &plan.output {
+                children.push(Box::new(Text::new(line.clone()).with_font_size(12.0)));
+            }
+            for action in plan.actions.iter().take(MAX_LISTED) {
+                children.push(Box::new(Text::new(action.describe()).with_font_size(12.0)));
+            }
+            if plan.actions.len() > MAX_LISTED {
+                children.push(Box::new(
+                    Text::new(format!("and {} more", plan.actions.len() - MAX_LISTED)).with_font_size(12.0),
+                ));
+            }
+        }
+        children.push(Box::new(Text::new(self.message_text.maybe()).with_font_size(12.0)));
+
+        let mut buttons: Vec<Box<dyn Widget>> = vec![
+            Box::new(self.button("Open Scripts Folder", ScriptRequest::OpenFolder)),
+            Box::new(
+                Button::new(Text::new("Close".to_string())).with_on_pressed(MaybeSignal::value(Update::DRAW)),
+            ),
+        ];
+        if self.plan.as_ref().is_some_and(|plan| !plan.actions.is_empty()) {
+            buttons.push(Box::new(self.button("Apply", ScriptRequest::Apply)));
+        }
+        children.push(Box::new(Container::new(buttons).with_layout_style(LayoutStyle {
+            flex_direction: FlexDirection::Row,
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            justify_content: Some(JustifyContent::FlexEnd),
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            ..Default::default()
+        })));
+
+        self.inner = Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(8.0)),
+            ..Default::default()
+        });
+    }
+
+    /// Run the script on a blocking thread; its plan is picked up in `update()`.
+    fn run(&mut self, index: usize, context: &AppContext) {
+        let Some(script) = self.scripts.get(index).cloned() else {
+            return;
+        };
+        if self.running {
+            return;
+        }
+        self.running = true;
+        self.plan = None;
+        self.message_text.set(format!("Running {}...", script.display()));
+        let selection = self.selection.clone();
+        let folder = self.folder.clone();
+        let finished = self.finished.clone();
+        let update_manager = context.update();
+        tokio::task::spawn_blocking(move || {
+            let result = std::fs::read_to_string(&script)
+                .map_err(|e| format!("Failed to read {}: {}", script.display(), e))
+                .and_then(|source| plan(&source, &selection, &folder));
+            if let Ok(mut slot) = finished.lock() {
+                *slot = Some(result);
+            }
+            update_manager.insert(Update::LAYOUT | Update::DRAW);
+        });
+    }
+
+    fn apply(&mut self) {
+        let Some(plan) = self.plan.take() else {
+            return;
+        };
+        let bus = self.bus.clone();
+        tokio::spawn(async move {
+            let message = match apply(&plan).await {
+                Ok(0) => format!("Applied {} change(s)", plan.actions.len()),
+                Ok(failed) => format!("Error: {} of {} change(s) failed", failed, plan.actions.len()),
+                Err(e) => {
+                    log::error!("{}", e);
+                    format!("Error: {}", e)
+                },
+            };
+            bus.status(message);
+        });
+        self.message_text.set("Applying; transfers are shown in the transfer queue".to_string());
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for RunScriptDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.message_text);
+            self.signals_hooked = true;
+        }
+
+        let finished = self.finished.lock().ok().and_then(|mut f| f.take());
+        if let Some(result) = finished {
+            self.running = false;
+            match result {
+                Ok(plan) => {
+                    self.message_text.set(format!("{} change(s) to review", plan.actions.len()));
+                    self.plan = Some(plan);
+                },
+                Err(e) => {
+                    log::error!("{}", e);
+                    self.message_text.set(e);
+                },
+            }
+            self.rebuild();
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        if let Some(request) = request {
+            match request {
+                ScriptRequest::Run(index) => self.run(index, &context),
+                ScriptRequest::Apply => self.apply(),
+                ScriptRequest::OpenFolder => {
+                    let dir = scripts_dir();
+                    match std::fs::create_dir_all(&dir) {
+                        Ok(()) => self.bus.navigate(NavigationAction::NavigateTo(dir)),
+                        Err(e) => self.message_text.set(format!("Failed to create {}: {}", dir.display(), e)),
+                    }
+                },
+            }
+            self.rebuild();
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,158 @@
+//! Search
+//!
+//! Typing in the search field of the toolbar searches the current folder and
+//! its subfolders for names containing the query, ignoring case. Folders are
+//! walked breadth-first on a blocking worker and matches are shown as they are
+//! found, nearest first; editing the query stops the walk and starts a new one.
+
+use nptk::services::filesystem::entry::FileEntry;
+use nptk_fileman_widgets::file_list;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Matches shown at most; the walk stops once there are this many
+const MAX_RESULTS: usize = 10_000;
+
+/// How often matches found meanwhile are passed on to the view
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(150);
+
+/// Where the running or last search is.
+#[derive(Debug, Clone, Default)]
+pub struct SearchProgress {
+    pub query: String,
+    pub results: Vec<FileEntry>,
+    /// Folders read so far
+    pub searched: usize,
+    pub finished: bool,
+}
+
+impl SearchProgress {
+    /// Status bar text, e.g. "Searching for 'notes'... 12 result(s) in 40 folder(s)".
+    pub fn describe(&self) -> String {
+        if self.results.len() >= MAX_RESULTS {
+            format!("Showing the first {} results for '{}'", MAX_RESULTS, self.query)
+        } else if self.finished {
+            format!(
+                "{} result(s) for '{}' in {} folder(s)",
+                self.results.len(),
+                self.query,
+                self.searched
+            )
+        } else {
+            format!(
+                "Searching for '{}'... {} result(s) in {} folder(s)",
+                self.query,
+                self.results.len(),
+                self.searched
+            )
+        }
+    }
+}
+
+/// One search at a time, restarted whenever the query changes.
+#[derive(Clone, Default)]
+pub struct Search {
+    progress: Arc<Mutex<SearchProgress>>,
+    /// Bumped for every search so the walk of an edited query stops
+    generation: Arc<AtomicU64>,
+    changed: Arc<AtomicBool>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Search `root` for names containing `query` in the background, stopping
+    /// the search running. Hidden items are only found with `include_hidden`.
+    pub fn start(&self, root: PathBuf, query: String, include_hidden: bool) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Ok(mut progress) = self.progress.lock() {
+            *progress = SearchProgress {
+                query: query.clone(),
+                ..Default::default()
+            };
+        }
+        let search = self.clone();
+        tokio::task::spawn_blocking(move || search.walk(generation, root, query.to_lowercase(), include_hidden));
+    }
+
+    /// Stop the search running, if any.
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The matches found so far and how far the search got.
+    pub fn progress(&self) -> Option<SearchProgress> {
+        self.progress.lock().ok().map(|progress| progress.clone())
+    }
+
+    /// Whether more matches were found or the search finished since the last
+    /// call (the view needs to be updated).
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::SeqCst)
+    }
+
+    fn walk(&self, generation: u64, root: PathBuf, needle: String, include_hidden: bool) {
+        let mut folders = VecDeque::from([root]);
+        let mut found = Vec::new();
+        let mut total = 0;
+        let mut searched = 0;
+        let mut published: Option<Instant> = None;
+        while let Some(folder) = folders.pop_front() {
+            if self.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let Ok(entries) = std::fs::read_dir(&folder) else {
+                continue;
+            };
+            searched += 1;
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                if name.starts_with('.') && !include_hidden {
+                    continue;
+                }
+                // Links to folders aren't followed, so they can't loop
+                if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    folders.push_back(entry.path());
+                }
+                if name.contains(&needle) {
+                    if let Some(entry) = file_list::read_entry(&entry.path()) {
+                        found.push(entry);
+                        total += 1;
+                    }
+                }
+            }
+            if total >= MAX_RESULTS {
+                break;
+            }
+            if published.is_none_or(|published| published.elapsed() >= PUBLISH_INTERVAL) {
+                if !self.publish(generation, &mut found, searched, false) {
+                    return;
+                }
+                published = Some(Instant::now());
+            }
+        }
+        found.truncate(found.len().saturating_sub(total.saturating_sub(MAX_RESULTS)));
+        self.publish(generation, &mut found, searched, true);
+    }
+
+    /// Add `found` to the results unless a newer search started. Returns
+    /// whether the search is still current.
+    fn publish(&self, generation: u64, found: &mut Vec<FileEntry>, searched: usize, finished: bool) -> bool {
+        let Ok(mut progress) = self.progress.lock() else {
+            return false;
+        };
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return false;
+        }
+        progress.results.append(found);
+        progress.searched = searched;
+        progress.finished = finished;
+        self.changed.store(true, Ordering::SeqCst);
+        true
+    }
+}
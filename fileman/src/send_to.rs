@@ -0,0 +1,138 @@
+//! "Send To" targets for the selection
+//!
+//! Files can be attached to a new email (through `xdg-email`), pushed to a paired
+//! Bluetooth device (through BlueZ's OBEX service), or shared on the local network
+//! from a temporary HTTP server whose address is shown as a QR code.
+
+mod bluetooth;
+mod share;
+
+pub use bluetooth::BluetoothDevice;
+pub use share::ShareDialog;
+
+use crate::bus::AppBus;
+use crate::menus;
+use nptk::core::menu::{MenuCommand, MenuItem, MenuTemplate};
+use nptk::prelude::*;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+/// Open the default mail client with `paths` attached.
+pub fn email(paths: &[PathBuf]) -> Result<(), String> {
+    let mut command = Command::new("xdg-email");
+    for path in paths {
+        command.arg("--attach").arg(path);
+    }
+    command
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to run xdg-email: {}", e))
+}
+
+/// Builds the "Send To" submenu and carries out its actions.
+///
+/// Paired Bluetooth devices are looked up in the background, so the submenu
+/// lists the devices found the last time it was opened.
+#[derive(Clone)]
+pub struct SendTo {
+    devices: Arc<Mutex<Vec<BluetoothDevice>>>,
+    // Files to share on the network; the dialog needs the app context
+    share_request: Arc<Mutex<Option<Vec<PathBuf>>>>,
+    bus: AppBus,
+}
+
+impl SendTo {
+    pub fn new(bus: AppBus) -> Self {
+        let send_to = Self {
+            devices: Arc::new(Mutex::new(Vec::new())),
+            share_request: Arc::new(Mutex::new(None)),
+            bus,
+        };
+        send_to.refresh_devices();
+        send_to
+    }
+
+    fn refresh_devices(&self) {
+        let devices = self.devices.clone();
+        tokio::spawn(async move {
+            match bluetooth::paired_devices().await {
+                Ok(found) => {
+                    if let Ok(mut devices) = devices.lock() {
+                        *devices = found;
+                    }
+                },
+                Err(e) => log::debug!("{}", e),
+            }
+        });
+    }
+
+    /// The "Send To" submenu for `paths`.
+    pub fn menu_item(&self, paths: &[PathBuf]) -> MenuItem {
+        self.refresh_devices();
+
+        let email_paths = paths.to_vec();
+        let bus = self.bus.clone();
+        let mut items = vec![MenuItem::new(MenuCommand::Custom(menus::SEND_BY_EMAIL_ID), "Email...").with_action(move || {
+            if let Err(e) = email(&email_paths) {
+                log::error!("{}", e);
+                bus.status(format!("Error: {}", e));
+            }
+            Update::DRAW
+        })];
+
+        let devices = self.devices.lock().map(|d| d.clone()).unwrap_or_default();
+        if !devices.is_empty() {
+            let device_items = devices
+                .into_iter()
+                .take(menus::RANGE_LENGTH)
+                .enumerate()
+                .map(|(i, device)| {
+                    let paths = paths.to_vec();
+                    let bus = self.bus.clone();
+                    let label = device.name.clone();
+                    MenuItem::new(MenuCommand::Custom(menus::range_id(menus::BLUETOOTH_DEVICE_IDS, i)), label).with_action(move || {
+                        let device = device.clone();
+                        let paths = paths.clone();
+                        let bus = bus.clone();
+                        tokio::spawn(async move {
+                            bus.status(format!("Sending {} item(s) to {}", paths.len(), device.name));
+                            let message = match bluetooth::send_files(&device, &paths).await {
+                                Ok(()) => format!("Sent {} item(s) to {}", paths.len(), device.name),
+                                Err(e) => {
+                                    log::error!("{}", e);
+                                    format!("Error: {}", e)
+                                },
+                            };
+                            bus.status(message);
+                        });
+                        Update::DRAW
+                    })
+                })
+                .collect();
+            items.push(
+                MenuItem::new(MenuCommand::Custom(menus::SEND_TO_BLUETOOTH_ID), "Bluetooth Device")
+                    .with_submenu(MenuTemplate::from_items("send_to_bluetooth", device_items)),
+            );
+        }
+
+        let share_request = self.share_request.clone();
+        let share_paths = paths.to_vec();
+        items.push(
+            MenuItem::new(MenuCommand::Custom(menus::SHARE_ON_NETWORK_ID), "Local Network...").with_action(move || {
+                if let Ok(mut request) = share_request.lock() {
+                    *request = Some(share_paths.clone());
+                }
+                Update::DRAW
+            }),
+        );
+
+        MenuItem::new(MenuCommand::Custom(menus::SEND_TO_ID), "Send To")
+            .with_submenu(MenuTemplate::from_items("send_to", items))
+    }
+
+    /// Files picked for "Local Network...", to show a [ShareDialog] for.
+    pub fn take_share_request(&self) -> Option<Vec<PathBuf>> {
+        self.share_request.lock().ok().and_then(|mut r| r.take())
+    }
+}
@@ -0,0 +1,145 @@
+//! Sending files to paired Bluetooth devices with BlueZ's OBEX Object Push.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+/// How often a running transfer is checked
+const TRANSFER_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A paired device that can receive files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BluetoothDevice {
+    pub name: String,
+    pub address: String,
+}
+
+type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+
+/// Paired devices known to BlueZ, sorted by name.
+pub async fn paired_devices() -> Result<Vec<BluetoothDevice>, String> {
+    let connection = zbus::Connection::system()
+        .await
+        .map_err(|e| format!("Failed to connect to the system bus: {}", e))?;
+    let objects: ManagedObjects = connection
+        .call_method(
+            Some("org.bluez"),
+            "/",
+            Some("org.freedesktop.DBus.ObjectManager"),
+            "GetManagedObjects",
+            &(),
+        )
+        .await
+        .map_err(|e| format!("Failed to list Bluetooth devices: {}", e))?
+        .body()
+        .deserialize()
+        .map_err(|e| format!("Invalid reply from BlueZ: {}", e))?;
+
+    let mut devices: Vec<BluetoothDevice> = objects
+        .values()
+        .filter_map(|interfaces| interfaces.get("org.bluez.Device1"))
+        .filter(|properties| {
+            properties
+                .get("Paired")
+                .and_then(|v| v.downcast_ref::<bool>().ok())
+                .unwrap_or(false)
+        })
+        .filter_map(|properties| {
+            let text = |key: &str| {
+                properties
+                    .get(key)
+                    .and_then(|v| v.downcast_ref::<&str>().ok())
+                    .map(str::to_string)
+            };
+            let address = text("Address")?;
+            let name = text("Alias").or_else(|| text("Name")).unwrap_or_else(|| address.clone());
+            Some(BluetoothDevice { name, address })
+        })
+        .collect();
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(devices)
+}
+
+/// Push `paths` to `device` one after another. Folders are skipped.
+pub async fn send_files(device: &BluetoothDevice, paths: &[PathBuf]) -> Result<(), String> {
+    let connection = zbus::Connection::session()
+        .await
+        .map_err(|e| format!("Failed to connect to the session bus: {}", e))?;
+    let args: HashMap<&str, Value> = HashMap::from([("Target", Value::from("opp"))]);
+    let session: OwnedObjectPath = connection
+        .call_method(
+            Some("org.bluez.obex"),
+            "/org/bluez/obex",
+            Some("org.bluez.obex.Client1"),
+            "CreateSession",
+            &(device.address.as_str(), args),
+        )
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", device.name, e))?
+        .body()
+        .deserialize()
+        .map_err(|e| format!("Invalid reply from obexd: {}", e))?;
+
+    let mut result = Ok(());
+    for path in paths.iter().filter(|p| p.is_file()) {
+        result = send_file(&connection, &session, path).await;
+        if result.is_err() {
+            break;
+        }
+    }
+
+    let _ = connection
+        .call_method(
+            Some("org.bluez.obex"),
+            "/org/bluez/obex",
+            Some("org.bluez.obex.Client1"),
+            "RemoveSession",
+            &(&session,),
+        )
+        .await;
+    result
+}
+
+/// Push one file and wait until the transfer ends.
+async fn send_file(connection: &zbus::Connection, session: &OwnedObjectPath, path: &PathBuf) -> Result<(), String> {
+    let (transfer, _): (OwnedObjectPath, HashMap<String, OwnedValue>) = connection
+        .call_method(
+            Some("org.bluez.obex"),
+            session.as_str(),
+            Some("org.bluez.obex.ObjectPush1"),
+            "SendFile",
+            &(path.to_string_lossy().as_ref(),),
+        )
+        .await
+        .map_err(|e| format!("Failed to send {}: {}", path.display(), e))?
+        .body()
+        .deserialize()
+        .map_err(|e| format!("Invalid reply from obexd: {}", e))?;
+
+    loop {
+        tokio::time::sleep(TRANSFER_POLL_INTERVAL).await;
+        let reply = connection
+            .call_method(
+                Some("org.bluez.obex"),
+                transfer.as_str(),
+                Some("org.freedesktop.DBus.Properties"),
+                "Get",
+                &("org.bluez.obex.Transfer1", "Status"),
+            )
+            .await;
+        // obexd drops the transfer object shortly after it completes
+        let Ok(reply) = reply else {
+            return Ok(());
+        };
+        let status: OwnedValue = reply
+            .body()
+            .deserialize()
+            .map_err(|e| format!("Invalid reply from obexd: {}", e))?;
+        match status.downcast_ref::<&str>().unwrap_or_default() {
+            "complete" => return Ok(()),
+            "error" => return Err(format!("The device refused {}", path.display())),
+            _ => {},
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Sharing files on the local network from a temporary HTTP server.
+//!
+//! The server listens on all interfaces under a random URL prefix, serves a page
+//! linking the shared files, and stops when sharing is stopped, the dialog is
+//! closed or after [SHARE_TIMEOUT].
+
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::vg::kurbo::Shape;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use qrcode::QrCode;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Sharing stops on its own after this long
+const SHARE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// How often the server checks whether it should stop
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Size of the QR code in the dialog
+const QR_CODE_SIZE: f32 = 200.0;
+/// Longest request line read; longer requests are refused
+const MAX_REQUEST_LINE: u64 = 8 * 1024;
+/// How long a client may take to send its request line
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Requests served at the same time; further connections are closed
+const MAX_CONNECTIONS: usize = 16;
+/// Random bytes in the URL prefix
+const TOKEN_BYTES: usize = 16;
+
+/// A running share, stopped by [ShareServer::stop] or when dropped.
+struct ShareServer {
+    url: String,
+    stop: Arc<AtomicBool>,
+}
+
+impl ShareServer {
+    /// Serve the files among `paths` (folders aren't shared).
+    fn start(paths: &[PathBuf]) -> Result<Self, String> {
+        let files: Vec<PathBuf> = paths.iter().filter(|p| p.is_file()).cloned().collect();
+        if files.is_empty() {
+            return Err("Only files can be shared on the network".to_string());
+        }
+
+        let listener =
+            TcpListener::bind("0.0.0.0:0").map_err(|e| format!("Failed to start sharing: {}", e))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to start sharing: {}", e))?;
+        let port = listener.local_addr().map_err(|e| format!("Failed to start sharing: {}", e))?.port();
+        let host = local_address().ok_or_else(|| "No local network connection".to_string())?;
+        let mut bytes = [0u8; TOKEN_BYTES];
+        getrandom::fill(&mut bytes).map_err(|e| format!("Failed to start sharing: {}", e))?;
+        let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let url = format!("http://{}:{}/{}/", host, port, token);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        std::thread::spawn(move || {
+            let started = Instant::now();
+            let files = Arc::new(files);
+            let token = Arc::new(token);
+            let connections = Arc::new(AtomicUsize::new(0));
+            while !thread_stop.load(Ordering::Relaxed) && started.elapsed() < SHARE_TIMEOUT {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if connections.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+                            // Dropping the stream closes it
+                            connections.fetch_sub(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        let files = files.clone();
+                        let token = token.clone();
+                        let connections = connections.clone();
+                        std::thread::spawn(move || {
+                            if let Err(e) = serve(stream, &token, &files) {
+                                log::debug!("Share request failed: {}", e);
+                            }
+                            connections.fetch_sub(1, Ordering::Relaxed);
+                        });
+                    },
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_POLL_INTERVAL),
+                    Err(e) => {
+                        log::error!("Sharing stopped: {}", e);
+                        break;
+                    },
+                }
+            }
+        });
+        Ok(Self { url, stop })
+    }
+
+    fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ShareServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Address of the interface used to reach other hosts. Connecting a UDP socket
+/// sends nothing; it only picks the route.
+fn local_address() -> Option<String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip().to_string())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// What follows `/<token>/` in the request `target`, if it has the token. The
+/// token is compared in constant time, so timing doesn't reveal it.
+fn strip_token<'a>(target: &'a str, token: &str) -> Option<&'a str> {
+    let (given, rest) = target.strip_prefix('/')?.split_once('/')?;
+    let matches = given.len() == token.len()
+        && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0;
+    matches.then_some(rest)
+}
+
+/// Answer one request: the index page, file number `n` at `/<token>/<n>`, or 404.
+fn serve(stream: TcpStream, token: &str, files: &[PathBuf]) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?).take(MAX_REQUEST_LINE);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut stream = stream;
+    if !request_line.ends_with('\n') {
+        return stream.write_all(b"HTTP/1.1 414 URI Too Long\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    }
+    let target = request_line.split_whitespace().nth(1).unwrap_or("");
+
+    let Some(rest) = strip_token(target, token) else {
+        return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    };
+
+    if rest.is_empty() {
+        let links: String = files
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                format!("<li><a href=\"{}\">{}</a></li>", i, escape_html(&name))
+            })
+            .collect();
+        let body = format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Shared files</title></head>\
+             <body><ul>{}</ul></body></html>",
+            links
+        );
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+        return Ok(());
+    }
+
+    let Some(path) = rest.parse::<usize>().ok().and_then(|i| files.get(i)) else {
+        return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    };
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len();
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\
+         Content-Disposition: attachment; filename*=UTF-8''{}\r\nConnection: close\r\n\r\n",
+        length,
+        crate::uri::percent_encode(name.as_bytes(), false)
+    )?;
+    io::copy(&mut file, &mut stream)?;
+    Ok(())
+}
+
+/// Draws a QR code as dark squares on a white background.
+struct QrCodeView {
+    modules: Vec<bool>,
+    width: usize,
+}
+
+impl QrCodeView {
+    fn new(text: &str) -> Option<Self> {
+        let code = QrCode::new(text.as_bytes()).ok()?;
+        Some(Self {
+            width: code.width(),
+            modules: code.to_colors().into_iter().map(|c| c == qrcode::Color::Dark).collect(),
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for QrCodeView {
+    fn layout_style(&self, _context: &LayoutContext) -> StyleNode {
+        StyleNode {
+            style: LayoutStyle {
+                size: Vector2::new(Dimension::length(QR_CODE_SIZE), Dimension::length(QR_CODE_SIZE)),
+                flex_shrink: 0.0,
+                ..Default::default()
+            },
+            children: vec![],
+            measure_func: None,
+        }
+    }
+
+    async fn update(&mut self, _layout: &LayoutNode, _context: AppContext, _info: &mut AppInfo) -> Update {
+        Update::empty()
+    }
+
+    fn render(&mut self, graphics: &mut dyn Graphics, layout: &LayoutNode, _info: &mut AppInfo, _context: AppContext) {
+        let x = layout.layout.location.x as f64;
+        let y = layout.layout.location.y as f64;
+        let size = QR_CODE_SIZE as f64;
+        // A quiet zone of two modules on each side
+        let module = size / (self.width + 4) as f64;
+        let fill = |graphics: &mut dyn Graphics, rect: nptk::core::vg::kurbo::Rect, color| {
+            graphics.fill(
+                nptk::core::vg::peniko::Fill::NonZero,
+                nptk::core::vg::kurbo::Affine::IDENTITY,
+                &nptk::core::vg::peniko::Brush::Solid(color),
+                None,
+                &rect.into_path(0.1),
+            );
+        };
+
+        fill(
+            graphics,
+            nptk::core::vg::kurbo::Rect::new(x, y, x + size, y + size),
+            nptk::core::vg::peniko::Color::WHITE,
+        );
+        for (i, _) in self.modules.iter().enumerate().filter(|(_, dark)| **dark) {
+            let mx = x + (i % self.width + 2) as f64 * module;
+            let my = y + (i / self.width + 2) as f64 * module;
+            fill(
+                graphics,
+                nptk::core::vg::kurbo::Rect::new(mx, my, mx + module, my + module),
+                nptk::core::vg::peniko::Color::BLACK,
+            );
+        }
+    }
+}
+
+/// Shows the address and QR code of a running share, with a button to stop it.
+pub struct ShareDialog {
+    server: Option<ShareServer>,
+    inner: Container,
+    stop_requested: Arc<Mutex<bool>>,
+}
+
+impl ShareDialog {
+    /// Start sharing `paths`.
+    pub fn new(paths: &[PathBuf]) -> Result<Self, String> {
+        let mut dialog = Self {
+            server: Some(ShareServer::start(paths)?),
+            inner: Container::new(vec![]),
+            stop_requested: Arc::new(Mutex::new(false)),
+        };
+        dialog.rebuild();
+        Ok(dialog)
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), "Share on Local Network", (380, 360), (300, 150));
+    }
+
+    fn rebuild(&mut self) {
+        let mut children: Vec<Box<dyn Widget>> = Vec::new();
+        match &self.server {
+            Some(server) => {
+                children.push(Box::new(Text::new("Open this address on another device:".to_string())));
+                if let Some(qr) = QrCodeView::new(&server.url) {
+                    children.push(Box::new(qr));
+                }
+                children.push(Box::new(Text::new(server.url.clone()).with_font_size(12.0)));
+                let stop_requested = self.stop_requested.clone();
+                children.push(Box::new(Button::new(Text::new("Stop Sharing".to_string())).with_on_pressed(
+                    MaybeSignal::signal(Box::new(EvalSignal::new(move || {
+                        if let Ok(mut flag) = stop_requested.lock() {
+                            *flag = true;
+                        }
+                        Update::DRAW
+                    }))),
+                )));
+            },
+            None => children.push(Box::new(Text::new("Sharing stopped".to_string()))),
+        }
+
+        self.inner = Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
+            flex_direction: FlexDirection::Column,
+            align_items: Some(AlignItems::Center),
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(10.0)),
+            ..Default::default()
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for ShareDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+        let stop = self.stop_requested.lock().map(|mut f| std::mem::take(&mut *f)).unwrap_or(false);
+        if stop {
+            if let Some(server) = self.server.take() {
+                server.stop();
+            }
+            self.rebuild();
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_exact_token_is_accepted() {
+        let token = "0123456789abcdef0123456789abcdef";
+        assert_eq!(strip_token("/0123456789abcdef0123456789abcdef/", token), Some(""));
+        assert_eq!(strip_token("/0123456789abcdef0123456789abcdef/1", token), Some("1"));
+        assert_eq!(strip_token("/0123456789abcdef0123456789abcdee/1", token), None);
+        assert_eq!(strip_token("/0123456789abcdef/1", token), None);
+        assert_eq!(strip_token("/0123456789abcdef0123456789abcdef", token), None);
+        assert_eq!(strip_token("0123456789abcdef0123456789abcdef/", token), None);
+        assert_eq!(strip_token("/", token), None);
+    }
+}
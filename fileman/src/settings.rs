@@ -0,0 +1,261 @@
+//! User preferences
+//!
+//! Stored as `key = value` lines in `$XDG_CONFIG_HOME/fileman/settings` and edited
+//! in the Preferences dialog. Keys this version doesn't know are kept, so saving
+//! doesn't drop settings written by a newer version.
+
+mod dialog;
+
+pub use dialog::PreferencesDialog;
+
+use crate::activation::{Activation, MimeCategory};
+use crate::startup::StartupLocation;
+use crate::view_settings::Thumbnails;
+use nptk_fileman_widgets::color_scheme::ColorScheme;
+use nptk_fileman_widgets::sizes::SizeUnits;
+use nptk_fileman_widgets::xdg;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// Settings shared by the whole process, loaded on first use
+static CURRENT: RwLock<Option<Settings>> = RwLock::new(None);
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Settings {
+    /// Play a sound when a transfer finishes or fails
+    pub completion_sound: bool,
+    /// Ask for attention (taskbar urgency) when a transfer finishes or fails
+    pub completion_attention: bool,
+    /// Copy what symbolic links point to instead of the links
+    pub copy_follow_symlinks: bool,
+    /// Show the size of folders' contents in the Size column (read at startup)
+    pub folder_size_column: bool,
+    /// Add a "Link Target" column to the Table view (read at startup)
+    pub link_target_column: bool,
+    /// Add "Owner" and "Group" columns to the Table view (read at startup)
+    pub owner_columns: bool,
+    /// Add a "Permissions" column ("drwxr-xr-x") to the Table view (read at startup)
+    pub permissions_column: bool,
+    /// Offer to open removable media mounted while fileman runs
+    pub removable_media_prompt: bool,
+    /// Folder opened when no path is given on the command line
+    pub startup_location: StartupLocation,
+    /// Colors of the file list, sidebar and panels (applied live)
+    pub color_scheme: ColorScheme,
+    /// Show recent dates as "5 minutes ago" or "Yesterday 14:02" (applied live)
+    pub relative_dates: bool,
+    /// Show sizes in KiB/MiB or kB/MB (applied live)
+    pub size_units: SizeUnits,
+    /// Where thumbnails are generated, unless a folder says otherwise (applied
+    /// to folders opened afterwards)
+    pub thumbnails: Thumbnails,
+    /// What double-clicking a file does, by category; missing ones open the file
+    pub activation: BTreeMap<MimeCategory, Activation>,
+    /// Permanently delete items trashed more than this many days ago; 0 keeps them
+    pub trash_max_age_days: u32,
+    /// Permanently delete the oldest trashed items while the trash is larger
+    /// than this many GiB; 0 doesn't limit it
+    pub trash_max_size_gib: u64,
+    /// Ids of installed plugins not to load (read at startup)
+    pub disabled_plugins: BTreeSet<String>,
+    unknown: BTreeMap<String, String>,
+}
+
+fn config_path() -> PathBuf {
+    xdg::config_home().join("fileman/settings")
+}
+
+/// Set `target` from a number, warning about anything else.
+fn read_number<T: std::str::FromStr>(target: &mut T, key: &str, value: &str) {
+    match value.parse() {
+        Ok(number) => *target = number,
+        Err(_) => log::warn!("Invalid value for setting {}: {}", key, value),
+    }
+}
+
+/// Set `target` from a `true`/`false` value, warning about anything else.
+fn read_bool(target: &mut bool, key: &str, value: &str) {
+    match value {
+        "true" => *target = true,
+        "false" => *target = false,
+        _ => log::warn!("Invalid value for setting {}: {}", key, value),
+    }
+}
+
+impl Settings {
+    pub fn activation_for(&self, category: MimeCategory) -> Activation {
+        self.activation.get(&category).copied().unwrap_or_default()
+    }
+
+    /// Read the settings file; missing or invalid values keep their defaults.
+    fn load() -> Self {
+        std::fs::read_to_string(config_path())
+            .map(|content| Self::from_text(&content))
+            .unwrap_or_default()
+    }
+
+    /// Parse `key = value` lines; missing or invalid values keep their defaults.
+    fn from_text(content: &str) -> Self {
+        let mut settings = Settings::default();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "completion_sound" => read_bool(&mut settings.completion_sound, key, value),
+                "completion_attention" => read_bool(&mut settings.completion_attention, key, value),
+                "copy_follow_symlinks" => read_bool(&mut settings.copy_follow_symlinks, key, value),
+                "folder_size_column" => read_bool(&mut settings.folder_size_column, key, value),
+                "link_target_column" => read_bool(&mut settings.link_target_column, key, value),
+                "owner_columns" => read_bool(&mut settings.owner_columns, key, value),
+                "permissions_column" => read_bool(&mut settings.permissions_column, key, value),
+                "relative_dates" => read_bool(&mut settings.relative_dates, key, value),
+                "removable_media_prompt" => read_bool(&mut settings.removable_media_prompt, key, value),
+                "startup_location" => match StartupLocation::from_key(value) {
+                    Some(location) => settings.startup_location = location,
+                    None => log::warn!("Invalid value for setting {}: {}", key, value),
+                },
+                "color_scheme" => match ColorScheme::from_key(value) {
+                    Some(scheme) => settings.color_scheme = scheme,
+                    None => log::warn!("Invalid value for setting {}: {}", key, value),
+                },
+                "trash_max_age_days" => read_number(&mut settings.trash_max_age_days, key, value),
+                "trash_max_size_gib" => read_number(&mut settings.trash_max_size_gib, key, value),
+                "disabled_plugins" => {
+                    settings.disabled_plugins = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty())
+                        .map(String::from)
+                        .collect();
+                },
+                "thumbnails" => match Thumbnails::from_key(value) {
+                    Some(thumbnails) => settings.thumbnails = thumbnails,
+                    None => log::warn!("Invalid value for setting {}: {}", key, value),
+                },
+                "size_units" => match SizeUnits::from_key(value) {
+                    Some(units) => settings.size_units = units,
+                    None => log::warn!("Invalid value for setting {}: {}", key, value),
+                },
+                _ => match key.strip_prefix("activation.").and_then(MimeCategory::from_key) {
+                    Some(category) => match Activation::from_key(value) {
+                        Some(activation) => {
+                            settings.activation.insert(category, activation);
+                        },
+                        None => log::warn!("Invalid value for setting {}: {}", key, value),
+                    },
+                    None => {
+                        settings.unknown.insert(key.to_string(), value.to_string());
+                    },
+                },
+            }
+        }
+        settings
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = self.to_text();
+        let path = config_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to save settings: {}", e))?;
+        }
+        std::fs::write(&path, content).map_err(|e| format!("Failed to save settings: {}", e))
+    }
+
+    /// Serialize as `key = value` lines, including the keys that weren't known.
+    fn to_text(&self) -> String {
+        let mut content = String::new();
+        content.push_str(&format!("completion_sound = {}\n", self.completion_sound));
+        content.push_str(&format!(
+            "completion_attention = {}\n",
+            self.completion_attention
+        ));
+        content.push_str(&format!("copy_follow_symlinks = {}\n", self.copy_follow_symlinks));
+        content.push_str(&format!("folder_size_column = {}\n", self.folder_size_column));
+        content.push_str(&format!("link_target_column = {}\n", self.link_target_column));
+        content.push_str(&format!("owner_columns = {}\n", self.owner_columns));
+        content.push_str(&format!("permissions_column = {}\n", self.permissions_column));
+        content.push_str(&format!("removable_media_prompt = {}\n", self.removable_media_prompt));
+        content.push_str(&format!("startup_location = {}\n", self.startup_location.key()));
+        content.push_str(&format!("color_scheme = {}\n", self.color_scheme.key()));
+        content.push_str(&format!("relative_dates = {}\n", self.relative_dates));
+        content.push_str(&format!("size_units = {}\n", self.size_units.key()));
+        content.push_str(&format!("thumbnails = {}\n", self.thumbnails.key()));
+        content.push_str(&format!("trash_max_age_days = {}\n", self.trash_max_age_days));
+        content.push_str(&format!("trash_max_size_gib = {}\n", self.trash_max_size_gib));
+        content.push_str(&format!(
+            "disabled_plugins = {}\n",
+            self.disabled_plugins.iter().map(String::as_str).collect::<Vec<_>>().join(", ")
+        ));
+        for (category, activation) in &self.activation {
+            content.push_str(&format!("activation.{} = {}\n", category.key(), activation.key()));
+        }
+        for (key, value) in &self.unknown {
+            content.push_str(&format!("{} = {}\n", key, value));
+        }
+        content
+    }
+}
+
+/// The current settings.
+pub fn get() -> Settings {
+    if let Some(settings) = CURRENT.read().ok().and_then(|current| current.clone()) {
+        return settings;
+    }
+    let settings = Settings::load();
+    if let Ok(mut current) = CURRENT.write() {
+        *current = Some(settings.clone());
+    }
+    settings
+}
+
+/// Change the settings with `change` and save them.
+pub fn update(change: impl FnOnce(&mut Settings)) -> Result<(), String> {
+    let mut settings = get();
+    change(&mut settings);
+    if let Ok(mut current) = CURRENT.write() {
+        *current = Some(settings.clone());
+    }
+    settings.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activation_is_kept_per_category() {
+        let settings = Settings::from_text(
+            "activation.image = preview\n\
+             activation.video = ask\n\
+             activation.text = edit\n\
+             activation.fonts = preview\n\
+             # comment\n\
+             future_setting = 1\n",
+        );
+        assert_eq!(settings.activation_for(MimeCategory::Image), Activation::Preview);
+        assert_eq!(settings.activation_for(MimeCategory::Video), Activation::Ask);
+        // Invalid and missing choices open the file
+        assert_eq!(settings.activation_for(MimeCategory::Text), Activation::Open);
+        assert_eq!(settings.activation_for(MimeCategory::Other), Activation::Open);
+        assert_eq!(settings.activation.len(), 2);
+        // Categories this version doesn't know are kept like other keys
+        assert_eq!(settings.unknown.get("activation.fonts").map(String::as_str), Some("preview"));
+        assert_eq!(settings.unknown.get("future_setting").map(String::as_str), Some("1"));
+
+        assert_eq!(Settings::from_text(&settings.to_text()), settings);
+    }
+
+    #[test]
+    fn defaults_survive_saving() {
+        let settings = Settings::default();
+        assert_eq!(Settings::from_text(&settings.to_text()), settings);
+        assert_eq!(Settings::from_text("completion_sound = maybe\ntrash_max_age_days = -1"), settings);
+    }
+}
@@ -0,0 +1,515 @@
+//! Preferences popup. Changes are saved as soon as they are made.
+
+use super::Settings;
+use crate::activation::MimeCategory;
+use crate::bus::AppBus;
+use crate::plugins::{self, Plugin};
+use crate::startup::StartupLocation;
+use crate::templates;
+use crate::trash_cleanup;
+use crate::toolbar::NavigationAction;
+use nptk_fileman_widgets::{color_scheme, dates, sizes};
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Which control was used, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreferencesRequest {
+    ToggleCompletionSound,
+    ToggleCompletionAttention,
+    ToggleCopyFollowSymlinks,
+    CycleActivation(MimeCategory),
+    ToggleFolderSizeColumn,
+    ToggleLinkTargetColumn,
+    ToggleOwnerColumns,
+    TogglePermissionsColumn,
+    ToggleRemovableMediaPrompt,
+    CycleTrashMaxAge,
+    CycleTrashMaxSize,
+    CycleStartupLocation,
+    /// Start in the folder typed next to the button
+    SetStartupFolder,
+    CycleColorScheme,
+    ToggleRelativeDates,
+    CycleSizeUnits,
+    CycleThumbnails,
+    OpenTemplatesFolder,
+    AddTemplate,
+    /// Index into the templates shown
+    RemoveTemplate(usize),
+    /// Index into the plugins shown
+    TogglePlugin(usize),
+    OpenPluginsFolder,
+}
+
+pub struct PreferencesDialog {
+    inner: Container,
+    settings: Settings,
+    bus: AppBus,
+    // Templates as shown, rebuilt when the Templates folder changes
+    templates: Vec<PathBuf>,
+    template_path_text: StateSignal<String>,
+    startup_path_text: StateSignal<String>,
+    // (id, name, description) of the installed plugins
+    plugins: Vec<(String, String, String)>,
+    message_text: StateSignal<String>,
+    request: Arc<Mutex<Option<PreferencesRequest>>>,
+    signals_hooked: bool,
+}
+
+impl PreferencesDialog {
+    pub fn new(bus: AppBus) -> Self {
+        let mut dialog = Self {
+            inner: Container::new(vec![]),
+            settings: super::get(),
+            bus,
+            templates: templates::list(),
+            template_path_text: StateSignal::new(String::new()),
+            startup_path_text: StateSignal::new(String::new()),
+            plugins: plugins::installed()
+                .iter()
+                .map(|plugin| (plugin.id().to_string(), plugin.name().to_string(), plugin.description().to_string()))
+                .collect(),
+            message_text: StateSignal::new(String::new()),
+            request: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        };
+        dialog.rebuild();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        context.popup_manager.create_popup_at(
+            Box::new(self),
+            "Preferences",
+            (460, 900),
+            (300, 150),
+        );
+    }
+
+    fn heading(title: &str) -> Text {
+        Text::new(title.to_string()).with_font_size(16.0)
+    }
+
+    /// A label with an On/Off button.
+    fn toggle_row(&self, label: &str, value: bool, request: PreferencesRequest) -> Container {
+        self.button_row(label, if value { "On" } else { "Off" }, request)
+    }
+
+    /// A label with a button showing the current value.
+    fn button_row(&self, label: &str, value: &str, request: PreferencesRequest) -> Container {
+        Self::row(vec![
+            Box::new(Text::new(label.to_string()).with_layout_style(LayoutStyle {
+                flex_grow: 1.0,
+                ..Default::default()
+            })),
+            Box::new(self.button(value, request)),
+        ])
+    }
+
+    fn button(&self, label: &str, request: PreferencesRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string()))
+            .with_on_pressed(MaybeSignal::signal(Box::new(EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }))))
+    }
+
+    fn row(children: Vec<Box<dyn Widget>>) -> Container {
+        Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Row,
+            align_items: Some(AlignItems::Center),
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            ..Default::default()
+        })
+    }
+
+    fn rebuild(&mut self) {
+        let mut children: Vec<Box<dyn Widget>> = vec![Box::new(Self::heading("Double-Click on Files"))];
+        for category in MimeCategory::ALL {
+            children.push(Box::new(self.button_row(
+                category.label(),
+                self.settings.activation_for(category).label(),
+                PreferencesRequest::CycleActivation(category),
+            )));
+        }
+        children.extend([
+            Box::new(Self::heading("Startup")) as Box<dyn Widget>,
+            Box::new(self.button_row(
+                "Start in",
+                &self.settings.startup_location.label(),
+                PreferencesRequest::CycleStartupLocation,
+            )),
+            Box::new(Self::row(vec![
+                Box::new(
+                    TextInput::new()
+                        .with_text_signal(self.startup_path_text.clone())
+                        .with_placeholder("Path of a folder".to_string())
+                        .with_layout_style(LayoutStyle {
+                            size: Vector2::new(Dimension::auto(), Dimension::length(30.0)),
+                            flex_grow: 1.0,
+                            ..Default::default()
+                        }),
+                ),
+                Box::new(self.button("Always Start Here", PreferencesRequest::SetStartupFolder)),
+            ])),
+            Box::new(Self::heading("Appearance")),
+            Box::new(self.button_row(
+                "Colors",
+                self.settings.color_scheme.label(),
+                PreferencesRequest::CycleColorScheme,
+            )),
+            Box::new(Self::heading("File List")),
+            Box::new(self.toggle_row(
+                "Show recent dates as \"5 minutes ago\"",
+                self.settings.relative_dates,
+                PreferencesRequest::ToggleRelativeDates,
+            )),
+            Box::new(self.button_row(
+                "Sizes",
+                self.settings.size_units.label(),
+                PreferencesRequest::CycleSizeUnits,
+            )),
+            Box::new(self.button_row(
+                "Generate thumbnails",
+                self.settings.thumbnails.label(),
+                PreferencesRequest::CycleThumbnails,
+            )),
+            Box::new(self.toggle_row(
+                "Compute folder sizes for the Size column (after restart)",
+                self.settings.folder_size_column,
+                PreferencesRequest::ToggleFolderSizeColumn,
+            )),
+            Box::new(self.toggle_row(
+                "Show link targets in the Table view (after restart)",
+                self.settings.link_target_column,
+                PreferencesRequest::ToggleLinkTargetColumn,
+            )),
+            Box::new(self.toggle_row(
+                "Show owner and group in the Table view (after restart)",
+                self.settings.owner_columns,
+                PreferencesRequest::ToggleOwnerColumns,
+            )),
+            Box::new(self.toggle_row(
+                "Show permissions in the Table view (after restart)",
+                self.settings.permissions_column,
+                PreferencesRequest::TogglePermissionsColumn,
+            )),
+            Box::new(Self::heading("Transfers")),
+            Box::new(self.toggle_row(
+                "Play a sound when a transfer finishes or fails",
+                self.settings.completion_sound,
+                PreferencesRequest::ToggleCompletionSound,
+            )),
+            Box::new(self.toggle_row(
+                "Request attention when a transfer finishes or fails",
+                self.settings.completion_attention,
+                PreferencesRequest::ToggleCompletionAttention,
+            )),
+            Box::new(self.toggle_row(
+                "Copy what links point to instead of the links",
+                self.settings.copy_follow_symlinks,
+                PreferencesRequest::ToggleCopyFollowSymlinks,
+            )),
+            Box::new(Self::heading("Trash")),
+            Box::new(self.button_row(
+                "Delete trashed items permanently",
+                &trash_cleanup::age_label(self.settings.trash_max_age_days),
+                PreferencesRequest::CycleTrashMaxAge,
+            )),
+            Box::new(self.button_row(
+                "Delete the oldest items when the trash is larger than",
+                &trash_cleanup::size_label(self.settings.trash_max_size_gib),
+                PreferencesRequest::CycleTrashMaxSize,
+            )),
+            Box::new(Self::heading("Devices")),
+            Box::new(self.toggle_row(
+                "Offer to open removable media when it is mounted",
+                self.settings.removable_media_prompt,
+                PreferencesRequest::ToggleRemovableMediaPrompt,
+            )),
+        ]);
+        children.push(Box::new(Self::heading("Templates")));
+        if self.templates.is_empty() {
+            children.push(Box::new(
+                Text::new("No templates yet; files added here are offered in \"New File from Template\"".to_string())
+                    .with_font_size(12.0),
+            ));
+        }
+        for (i, template) in self.templates.iter().enumerate() {
+            children.push(Box::new(self.button_row(
+                &templates::label(template),
+                "Remove",
+                PreferencesRequest::RemoveTemplate(i),
+            )));
+        }
+        children.extend([
+            Box::new(Self::row(vec![
+                Box::new(
+                    TextInput::new()
+                        .with_text_signal(self.template_path_text.clone())
+                        .with_placeholder("Path of a file".to_string())
+                        .with_layout_style(LayoutStyle {
+                            size: Vector2::new(Dimension::auto(), Dimension::length(30.0)),
+                            flex_grow: 1.0,
+                            ..Default::default()
+                        }),
+                ),
+                Box::new(self.button("Add as Template", PreferencesRequest::AddTemplate)),
+            ])) as Box<dyn Widget>,
+            Box::new(self.button_row(
+                &templates::dir().display().to_string(),
+                "Open Folder",
+                PreferencesRequest::OpenTemplatesFolder,
+            )),
+        ]);
+        children.push(Box::new(Self::heading("Plugins (after restart)")));
+        if self.plugins.is_empty() {
+            children.push(Box::new(
+                Text::new("No plugins installed; plugins are folders with a plugin.conf".to_string())
+                    .with_font_size(12.0),
+            ));
+        }
+        for (i, (id, name, description)) in self.plugins.iter().enumerate() {
+            children.push(Box::new(self.toggle_row(
+                name,
+                !self.settings.disabled_plugins.contains(id),
+                PreferencesRequest::TogglePlugin(i),
+            )));
+            if !description.is_empty() {
+                children.push(Box::new(Text::new(description.clone()).with_font_size(12.0)));
+            }
+        }
+        children.extend([
+            Box::new(self.button_row(
+                &plugins::plugins_dir().display().to_string(),
+                "Open Folder",
+                PreferencesRequest::OpenPluginsFolder,
+            )) as Box<dyn Widget>,
+            Box::new(Text::new(self.message_text.maybe()).with_font_size(12.0)),
+            Box::new(
+                Container::new(vec![Box::new(
+                    Button::new(Text::new("Close".to_string()))
+                        .with_on_pressed(MaybeSignal::value(Update::DRAW)),
+                )])
+                .with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: Some(JustifyContent::FlexEnd),
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    ..Default::default()
+                }),
+            ),
+        ]);
+        self.inner = Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(
+                LengthPercentage::length(0.0),
+                LengthPercentage::length(10.0),
+            ),
+            ..Default::default()
+        });
+    }
+
+    /// Apply a change to the settings and save them.
+    fn change(&mut self, change: impl FnOnce(&mut Settings)) {
+        let result = super::update(change);
+        self.settings = super::get();
+        match result {
+            Ok(()) => self.message_text.set(String::new()),
+            Err(e) => {
+                log::error!("{}", e);
+                self.message_text.set(e);
+            },
+        }
+        self.rebuild();
+    }
+
+    /// Show the result of a change of the templates, which are saved as files
+    /// instead of in the settings.
+    fn report(&mut self, result: Result<String, String>) {
+        match result {
+            Ok(message) => self.message_text.set(message),
+            Err(e) => {
+                log::error!("{}", e);
+                self.message_text.set(e);
+            },
+        }
+        self.templates = templates::list();
+        self.rebuild();
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for PreferencesDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(
+        &mut self,
+        layout: &LayoutNode,
+        context: AppContext,
+        info: &mut AppInfo,
+    ) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.message_text);
+            context.hook_signal(&mut self.template_path_text);
+            context.hook_signal(&mut self.startup_path_text);
+            self.signals_hooked = true;
+        }
+
+        // Templates added or removed elsewhere
+        let templates = templates::list();
+        if templates != self.templates {
+            self.templates = templates;
+            self.rebuild();
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        if let Some(request) = request {
+            match request {
+                PreferencesRequest::ToggleCompletionSound => {
+                    self.change(|s| s.completion_sound = !s.completion_sound)
+                },
+                PreferencesRequest::ToggleCompletionAttention => {
+                    self.change(|s| s.completion_attention = !s.completion_attention)
+                },
+                PreferencesRequest::ToggleCopyFollowSymlinks => {
+                    self.change(|s| s.copy_follow_symlinks = !s.copy_follow_symlinks)
+                },
+                PreferencesRequest::CycleActivation(category) => self.change(|s| {
+                    let next = s.activation_for(category).next();
+                    s.activation.insert(category, next);
+                }),
+                PreferencesRequest::ToggleFolderSizeColumn => {
+                    self.change(|s| s.folder_size_column = !s.folder_size_column)
+                },
+                PreferencesRequest::ToggleLinkTargetColumn => {
+                    self.change(|s| s.link_target_column = !s.link_target_column)
+                },
+                PreferencesRequest::ToggleOwnerColumns => {
+                    self.change(|s| s.owner_columns = !s.owner_columns)
+                },
+                PreferencesRequest::TogglePermissionsColumn => {
+                    self.change(|s| s.permissions_column = !s.permissions_column)
+                },
+                PreferencesRequest::ToggleRemovableMediaPrompt => {
+                    self.change(|s| s.removable_media_prompt = !s.removable_media_prompt)
+                },
+                PreferencesRequest::CycleTrashMaxAge => self.change(|s| {
+                    s.trash_max_age_days = trash_cleanup::next_choice(&trash_cleanup::AGE_CHOICES, s.trash_max_age_days)
+                }),
+                PreferencesRequest::CycleTrashMaxSize => self.change(|s| {
+                    s.trash_max_size_gib = trash_cleanup::next_choice(&trash_cleanup::SIZE_CHOICES, s.trash_max_size_gib)
+                }),
+                PreferencesRequest::CycleStartupLocation => {
+                    self.change(|s| s.startup_location = s.startup_location.next())
+                },
+                PreferencesRequest::SetStartupFolder => {
+                    let path = PathBuf::from(self.startup_path_text.get().trim());
+                    if path.is_absolute() && path.is_dir() {
+                        self.startup_path_text.set(String::new());
+                        self.change(|s| s.startup_location = StartupLocation::Fixed(path));
+                    } else {
+                        self.report(Err(format!("{} is not a folder", path.display())));
+                    }
+                },
+                PreferencesRequest::CycleColorScheme => {
+                    self.change(|s| s.color_scheme = s.color_scheme.next());
+                    color_scheme::set_scheme(self.settings.color_scheme);
+                },
+                PreferencesRequest::ToggleRelativeDates => {
+                    self.change(|s| s.relative_dates = !s.relative_dates);
+                    dates::set_relative(self.settings.relative_dates);
+                },
+                PreferencesRequest::CycleSizeUnits => {
+                    self.change(|s| s.size_units = s.size_units.next());
+                    sizes::set_units(self.settings.size_units);
+                },
+                PreferencesRequest::CycleThumbnails => {
+                    self.change(|s| s.thumbnails = s.thumbnails.next())
+                },
+                PreferencesRequest::OpenTemplatesFolder => {
+                    let dir = templates::dir();
+                    let result = std::fs::create_dir_all(&dir)
+                        .map(|_| String::new())
+                        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e));
+                    if result.is_ok() {
+                        self.bus.navigate(NavigationAction::NavigateTo(dir));
+                    }
+                    self.report(result);
+                },
+                PreferencesRequest::AddTemplate => {
+                    let path = PathBuf::from(self.template_path_text.get().trim());
+                    let result = templates::add(&path).map(|template| {
+                        self.template_path_text.set(String::new());
+                        format!("Added {}", templates::label(&template))
+                    });
+                    self.report(result);
+                },
+                PreferencesRequest::RemoveTemplate(i) => {
+                    if let Some(template) = self.templates.get(i).cloned() {
+                        let result = templates::remove(&template)
+                            .map(|()| format!("Moved {} to the trash", templates::label(&template)));
+                        self.report(result);
+                    }
+                },
+                PreferencesRequest::TogglePlugin(i) => {
+                    if let Some((id, _, _)) = self.plugins.get(i).cloned() {
+                        self.change(|s| {
+                            if !s.disabled_plugins.remove(&id) {
+                                s.disabled_plugins.insert(id);
+                            }
+                        });
+                    }
+                },
+                PreferencesRequest::OpenPluginsFolder => {
+                    let dir = plugins::plugins_dir();
+                    let result = std::fs::create_dir_all(&dir)
+                        .map(|_| String::new())
+                        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e));
+                    if result.is_ok() {
+                        self.bus.navigate(NavigationAction::NavigateTo(dir));
+                    }
+                    self.report(result);
+                },
+            }
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
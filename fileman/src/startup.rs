@@ -0,0 +1,108 @@
+//! Where a new window starts
+//!
+//! Without a path on the command line fileman opens the folder chosen in the
+//! Preferences: the working directory it was started in, the home folder, the
+//! folder shown last, or a fixed path. The folder shown last is remembered in
+//! `$XDG_STATE_HOME/fileman/last-location`. A location that no longer exists
+//! is replaced by its nearest existing parent folder.
+
+use nptk_fileman_widgets::xdg;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum StartupLocation {
+    /// The working directory fileman was started in
+    #[default]
+    WorkingDirectory,
+    Home,
+    /// The folder shown last, in any window
+    LastVisited,
+    Fixed(PathBuf),
+}
+
+impl StartupLocation {
+    /// Value used in the settings file; a fixed location is its path
+    pub fn key(&self) -> String {
+        match self {
+            StartupLocation::WorkingDirectory => "working-directory".to_string(),
+            StartupLocation::Home => "home".to_string(),
+            StartupLocation::LastVisited => "last-visited".to_string(),
+            StartupLocation::Fixed(path) => path.display().to_string(),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "working-directory" => Some(StartupLocation::WorkingDirectory),
+            "home" => Some(StartupLocation::Home),
+            "last-visited" => Some(StartupLocation::LastVisited),
+            _ if key.starts_with('/') => Some(StartupLocation::Fixed(PathBuf::from(key))),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            StartupLocation::WorkingDirectory => "Working Directory".to_string(),
+            StartupLocation::Home => "Home".to_string(),
+            StartupLocation::LastVisited => "Last Visited".to_string(),
+            StartupLocation::Fixed(path) => path.display().to_string(),
+        }
+    }
+
+    /// The next choice, for cycling through them with one button. A fixed path
+    /// is chosen with its own button, so cycling skips it.
+    pub fn next(&self) -> Self {
+        match self {
+            StartupLocation::WorkingDirectory => StartupLocation::Home,
+            StartupLocation::Home => StartupLocation::LastVisited,
+            StartupLocation::LastVisited | StartupLocation::Fixed(_) => StartupLocation::WorkingDirectory,
+        }
+    }
+}
+
+fn last_location_path() -> PathBuf {
+    xdg::state_home().join("fileman").join("last-location")
+}
+
+/// Remember `dir` as the folder shown last. Touches the file system, so call
+/// it off the UI thread.
+pub fn remember(dir: &Path) {
+    let path = last_location_path();
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, format!("{}\n", dir.display())));
+    if let Err(e) = result {
+        log::warn!("Failed to remember the last location: {}", e);
+    }
+}
+
+fn last_visited() -> Option<PathBuf> {
+    let content = std::fs::read_to_string(last_location_path()).ok()?;
+    let line = content.lines().next()?.trim();
+    (!line.is_empty()).then(|| PathBuf::from(line))
+}
+
+/// `dir` if it is a folder, otherwise its nearest parent that is.
+fn existing(dir: &Path) -> Option<PathBuf> {
+    let found = dir.ancestors().find(|ancestor| ancestor.is_dir())?;
+    if found != dir {
+        log::info!("{} no longer exists, starting in {}", dir.display(), found.display());
+    }
+    Some(found.to_path_buf())
+}
+
+/// The folder to open when no path was given, as configured in `location`.
+pub fn initial_location(location: &StartupLocation) -> PathBuf {
+    let wanted = match location {
+        StartupLocation::WorkingDirectory => std::env::current_dir().ok(),
+        StartupLocation::Home => Some(xdg::home()),
+        StartupLocation::LastVisited => last_visited(),
+        StartupLocation::Fixed(path) => Some(path.clone()),
+    };
+    wanted
+        .as_deref()
+        .and_then(existing)
+        .unwrap_or_else(home)
+}
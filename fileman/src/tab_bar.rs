@@ -0,0 +1,439 @@
+use nptk::prelude::*;
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::text_render::TextRenderContext;
+use nptk::core::theme::ColorRole;
+use nptk_fileman_widgets::color_scheme;
+use nptk::core::vg::kurbo::{Affine, Rect, Shape, Stroke};
+use nptk::core::vg::peniko::{Brush, Fill};
+use nptk::core::vgi::Graphics;
+use nptk::core::window::{ElementState, MouseButton};
+use crate::compare::{self, CompareDialog, Comparison};
+use crate::menus;
+use crate::navigation::NavigationState;
+use crate::operations::sync::{self, SyncDialog, SyncPlan};
+use nptk::core::menu::{MenuCommand, MenuItem, MenuTemplate};
+use nptk::core::vg::kurbo::Point;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const TAB_BAR_HEIGHT: f32 = 28.0;
+const MAX_TAB_WIDTH: f32 = 200.0;
+const CLOSE_BUTTON_WIDTH: f32 = 20.0;
+/// How far above or below the bar a tab has to be dragged to open in its own window
+const DETACH_DISTANCE: f32 = 40.0;
+
+/// Row of tabs above the file list. Hidden while only one tab is open.
+///
+/// Clicking a tab activates it; clicking its "×" or middle-clicking closes it.
+/// Right-clicking another tab offers comparing its folder with the active one, or
+/// synchronizing it from the active one.
+///
+/// Tabs are reordered by dragging them along the bar; dropping one well above or
+/// below the bar, or outside the window, opens it in a new window. Tabs of other
+/// windows are moved into the main window from their context menu ("Move to
+/// Main Window"): dropping a tab onto another window isn't supported, as each
+/// window is its own process and a drag can't be followed into another one.
+pub struct TabBar {
+    navigation: Arc<Mutex<NavigationState>>,
+    tab_paths: StateSignal<Vec<PathBuf>>,
+    active_tab: StateSignal<usize>,
+    text_render_context: TextRenderContext,
+    signals_hooked: bool,
+    // Folders to compare (active tab, other tab), set from the context menu
+    compare_request: Arc<Mutex<Option<(PathBuf, PathBuf)>>>,
+    // Finished comparison waiting to be shown
+    comparison: Arc<Mutex<Option<Result<Comparison, String>>>>,
+    // Folders to synchronize (source, destination), set from the context menu
+    sync_request: Arc<Mutex<Option<(PathBuf, PathBuf)>>>,
+    // Finished dry run waiting to be previewed
+    sync_plan: Arc<Mutex<Option<(PathBuf, PathBuf, Result<SyncPlan, String>)>>>,
+    // Index of the tab being dragged, while the left button is held on it
+    dragged: Option<usize>,
+}
+
+impl TabBar {
+    pub fn new(navigation: Arc<Mutex<NavigationState>>) -> Self {
+        let (tab_paths, active_tab) = {
+            let nav = navigation.lock().unwrap();
+            (nav.tab_paths().clone(), nav.active_tab_signal().clone())
+        };
+        Self {
+            navigation,
+            tab_paths,
+            active_tab,
+            text_render_context: TextRenderContext::new(),
+            signals_hooked: false,
+            compare_request: Arc::new(Mutex::new(None)),
+            comparison: Arc::new(Mutex::new(None)),
+            sync_request: Arc::new(Mutex::new(None)),
+            sync_plan: Arc::new(Mutex::new(None)),
+            dragged: None,
+        }
+    }
+
+    fn build_context_menu(&self, index: usize) -> MenuTemplate {
+        let paths = self.tab_paths.get().clone();
+        let active = *self.active_tab.get();
+        let mut items = Vec::new();
+
+        if index != active {
+            if let (Some(left), Some(right)) = (paths.get(active).cloned(), paths.get(index).cloned()) {
+                let compare_request = self.compare_request.clone();
+                let (compare_left, compare_right) = (left.clone(), right.clone());
+                items.push(
+                    MenuItem::new(MenuCommand::Custom(menus::COMPARE_TABS_ID), "Compare With Active Tab").with_action(move || {
+                        if let Ok(mut request) = compare_request.lock() {
+                            *request = Some((compare_left.clone(), compare_right.clone()));
+                        }
+                        Update::DRAW
+                    }),
+                );
+                let sync_request = self.sync_request.clone();
+                items.push(
+                    MenuItem::new(MenuCommand::Custom(menus::SYNC_TABS_ID), "Synchronize From Active Tab...").with_action(
+                        move || {
+                            if let Ok(mut request) = sync_request.lock() {
+                                *request = Some((left.clone(), right.clone()));
+                            }
+                            Update::DRAW
+                        },
+                    ),
+                );
+                items.push(MenuItem::separator());
+            }
+        }
+
+        if let Some(path) = paths.get(index).cloned() {
+            let navigation = self.navigation.clone();
+            items.push(
+                MenuItem::new(MenuCommand::Custom(menus::OPEN_TAB_IN_NEW_WINDOW_ID), "Open in New Window").with_action(move || {
+                    Self::detach(&navigation, index, &path);
+                    Update::LAYOUT | Update::DRAW
+                }),
+            );
+        }
+        if !crate::dbus::is_primary_instance() {
+            if let Some(path) = paths.get(index).cloned() {
+                let navigation = self.navigation.clone();
+                items.push(
+                    MenuItem::new(MenuCommand::Custom(menus::MOVE_TAB_TO_MAIN_WINDOW_ID), "Move to Main Window").with_action(move || {
+                        Self::merge(&navigation, index, path.clone());
+                        Update::DRAW
+                    }),
+                );
+            }
+        }
+
+        let navigation = self.navigation.clone();
+        items.push(
+            MenuItem::new(MenuCommand::Custom(menus::CLOSE_TAB_ID), "Close Tab").with_action(move || {
+                if let Ok(mut nav) = navigation.lock() {
+                    nav.close_tab(index);
+                }
+                Update::LAYOUT | Update::DRAW
+            }),
+        );
+        MenuTemplate::from_items("tab_context", items)
+    }
+
+    /// Start pending comparisons and show finished ones.
+    fn process_comparison(&self, context: &AppContext) -> Update {
+        if let Some((left, right)) = self.compare_request.lock().ok().and_then(|mut r| r.take()) {
+            let slot = self.comparison.clone();
+            tokio::task::spawn_blocking(move || {
+                let result = compare::compare_folders(&left, &right);
+                if let Ok(mut slot) = slot.lock() {
+                    *slot = Some(result);
+                }
+            });
+        }
+
+        match self.comparison.lock().ok().and_then(|mut c| c.take()) {
+            Some(Ok(comparison)) => {
+                CompareDialog::new(comparison).show(context);
+                Update::DRAW
+            },
+            Some(Err(e)) => {
+                log::error!("Failed to compare folders: {}", e);
+                Update::empty()
+            },
+            None => Update::empty(),
+        }
+    }
+
+    /// Dry-run pending synchronizations and preview finished ones.
+    fn process_sync(&self, context: &AppContext) -> Update {
+        if let Some((source, destination)) = self.sync_request.lock().ok().and_then(|mut r| r.take()) {
+            let slot = self.sync_plan.clone();
+            tokio::task::spawn_blocking(move || {
+                // Plan with mirroring so the dialog can preview deletions too
+                let plan = sync::plan(&source, &destination, true);
+                if let Ok(mut slot) = slot.lock() {
+                    *slot = Some((source, destination, plan));
+                }
+            });
+        }
+
+        match self.sync_plan.lock().ok().and_then(|mut p| p.take()) {
+            Some((source, destination, Ok(plan))) => {
+                SyncDialog::new(source, destination, plan).show(context);
+                Update::DRAW
+            },
+            Some((_, _, Err(e))) => {
+                log::error!("Failed to plan synchronization: {}", e);
+                Update::empty()
+            },
+            None => Update::empty(),
+        }
+    }
+
+    /// Open the tab at `index` in a new window and close it here.
+    fn detach(navigation: &Arc<Mutex<NavigationState>>, index: usize, path: &PathBuf) {
+        let exe = match std::env::current_exe() {
+            Ok(exe) => exe,
+            Err(e) => {
+                log::error!("Failed to locate fileman: {}", e);
+                return;
+            },
+        };
+        if let Err(e) = std::process::Command::new(exe).arg("--new-window").arg(path).spawn() {
+            log::error!("Failed to open a new window: {}", e);
+            return;
+        }
+        if let Ok(mut nav) = navigation.lock() {
+            nav.close_tab(index);
+        }
+    }
+
+    /// Hand the tab at `index` over to the main window, closing it here once it
+    /// has been opened there.
+    fn merge(navigation: &Arc<Mutex<NavigationState>>, index: usize, path: PathBuf) {
+        let navigation = navigation.clone();
+        tokio::spawn(async move {
+            if !crate::dbus::forward_to_running_instance(std::slice::from_ref(&path), &[]).await {
+                log::error!("No main window to move {} to", path.display());
+                return;
+            }
+            if let Ok(mut nav) = navigation.lock() {
+                // The tab may have moved in the meantime
+                if nav.tab_paths().get().get(index) == Some(&path) {
+                    nav.close_tab(index);
+                }
+            }
+        });
+    }
+
+    /// Follow a tab being dragged: reorder it along the bar, and when it is
+    /// released far from the bar or outside the window, open it in a new window.
+    fn process_drag(&mut self, layout: &LayoutNode, info: &AppInfo) -> Update {
+        let Some(dragged) = self.dragged else {
+            return Update::empty();
+        };
+        let released = info
+            .buttons
+            .iter()
+            .any(|(_, btn, el)| *btn == MouseButton::Left && *el == ElementState::Released);
+        let height = layout.layout.size.height;
+        // Without a cursor position the tab was dragged out of the window
+        let local = info
+            .cursor_pos
+            .map(|cursor| (cursor.x as f32 - layout.layout.location.x, cursor.y as f32 - layout.layout.location.y));
+
+        if released {
+            self.dragged = None;
+            let far = local.is_none_or(|(_, y)| y < -DETACH_DISTANCE || y > height + DETACH_DISTANCE);
+            if far {
+                if let Some(path) = self.tab_paths.get().get(dragged).cloned() {
+                    Self::detach(&self.navigation, dragged, &path);
+                    return Update::LAYOUT | Update::DRAW;
+                }
+            }
+            return Update::empty();
+        }
+
+        let Some((local_x, local_y)) = local else {
+            return Update::empty();
+        };
+
+        if local_y < 0.0 || local_y >= height {
+            return Update::empty();
+        }
+        let tab_count = self.tab_paths.get().len();
+        let tab_width = self.tab_width(layout.layout.size.width);
+        let target = ((local_x.max(0.0) / tab_width) as usize).min(tab_count.saturating_sub(1));
+        if target == dragged {
+            return Update::empty();
+        }
+        if let Ok(mut nav) = self.navigation.lock() {
+            nav.move_tab(dragged, target);
+        }
+        self.dragged = Some(target);
+        Update::DRAW
+    }
+
+    fn tab_width(&self, total_width: f32) -> f32 {
+        let count = self.tab_paths.get().len().max(1) as f32;
+        (total_width / count).min(MAX_TAB_WIDTH)
+    }
+
+    fn tab_label(path: &PathBuf) -> String {
+        path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string())
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for TabBar {
+    fn layout_style(&self, _context: &LayoutContext) -> StyleNode {
+        let height = if self.tab_paths.get().len() > 1 { TAB_BAR_HEIGHT } else { 0.0 };
+        StyleNode {
+            style: LayoutStyle {
+                size: Vector2::new(Dimension::percent(1.0), Dimension::length(height)),
+                flex_shrink: 0.0,
+                ..Default::default()
+            },
+            children: vec![],
+            measure_func: None,
+        }
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.tab_paths);
+            context.hook_signal(&mut self.active_tab);
+            self.signals_hooked = true;
+        }
+
+        let mut update = self.process_comparison(&context);
+        update |= self.process_sync(&context);
+
+        let tab_count = self.tab_paths.get().len();
+        if tab_count <= 1 {
+            self.dragged = None;
+            return update;
+        }
+        update |= self.process_drag(layout, info);
+        let Some(cursor) = info.cursor_pos else {
+            return update;
+        };
+        let local_x = cursor.x as f32 - layout.layout.location.x;
+        let local_y = cursor.y as f32 - layout.layout.location.y;
+        if local_x < 0.0 || local_y < 0.0 || local_y >= layout.layout.size.height {
+            return update;
+        }
+
+        let tab_width = self.tab_width(layout.layout.size.width);
+        let index = (local_x / tab_width) as usize;
+        if index >= tab_count {
+            return update;
+        }
+        let on_close = local_x - index as f32 * tab_width >= tab_width - CLOSE_BUTTON_WIDTH;
+
+        let right_pressed = info
+            .buttons
+            .iter()
+            .any(|(_, btn, el)| *btn == MouseButton::Right && *el == ElementState::Pressed);
+        if right_pressed && !context.menu_manager.is_open() {
+            context
+                .menu_manager
+                .show(self.build_context_menu(index), Point::new(cursor.x, cursor.y));
+            update.insert(Update::DRAW);
+        }
+
+        for (_, btn, el) in &info.buttons {
+            if *el != ElementState::Pressed {
+                continue;
+            }
+            let Ok(mut nav) = self.navigation.lock() else {
+                continue;
+            };
+            match btn {
+                MouseButton::Left if on_close => {
+                    nav.close_tab(index);
+                },
+                MouseButton::Left => {
+                    nav.activate_tab(index);
+                    self.dragged = Some(index);
+                },
+                MouseButton::Middle => {
+                    nav.close_tab(index);
+                },
+                _ => continue,
+            }
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        let paths = self.tab_paths.get().clone();
+        if paths.len() <= 1 {
+            return;
+        }
+        let active = *self.active_tab.get();
+        let palette = context.palette();
+        let text_color = color_scheme::color(palette, ColorRole::BaseText);
+        let selection_color = color_scheme::color(palette, ColorRole::Selection);
+        let border_color = color_scheme::color(palette, ColorRole::ThreedShadow1);
+        let tab_width = self.tab_width(layout.layout.size.width);
+
+        for (i, path) in paths.iter().enumerate() {
+            let x = layout.layout.location.x + i as f32 * tab_width;
+            let y = layout.layout.location.y;
+            let tab_rect = Rect::new(
+                x as f64,
+                y as f64,
+                (x + tab_width) as f64,
+                (y + layout.layout.size.height) as f64,
+            );
+
+            if i == active {
+                graphics.fill(
+                    Fill::NonZero,
+                    Affine::IDENTITY,
+                    &Brush::Solid(selection_color.with_alpha(0.3)),
+                    None,
+                    &tab_rect.to_path(0.1),
+                );
+            }
+            graphics.stroke(
+                &Stroke::new(1.0),
+                Affine::IDENTITY,
+                &Brush::Solid(border_color),
+                None,
+                &tab_rect.to_path(0.1),
+            );
+
+            self.text_render_context.render_text(
+                &mut info.font_context,
+                graphics,
+                &Self::tab_label(path),
+                None,
+                13.0,
+                Brush::Solid(text_color),
+                Affine::translate((x as f64 + 8.0, y as f64 + 6.0)),
+                true,
+                Some((tab_width - CLOSE_BUTTON_WIDTH - 12.0).max(10.0)),
+            );
+            self.text_render_context.render_text(
+                &mut info.font_context,
+                graphics,
+                "×",
+                None,
+                13.0,
+                Brush::Solid(text_color),
+                Affine::translate(((x + tab_width - CLOSE_BUTTON_WIDTH + 4.0) as f64, y as f64 + 6.0)),
+                true,
+                Some(CLOSE_BUTTON_WIDTH),
+            );
+        }
+    }
+}
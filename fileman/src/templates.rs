@@ -0,0 +1,248 @@
+//! Templates of "New File from Template"
+//!
+//! The files in the user's Templates folder (`XDG_TEMPLATES_DIR`, `~/Templates`
+//! if it isn't set) are offered in the context menu of the file list; a new
+//! file is a copy of the chosen template with the same name. The folder is
+//! watched like the file list's, so templates added or removed elsewhere show
+//! up without a restart. Hidden files and folders aren't templates.
+
+use crate::bus::AppBus;
+use crate::menus;
+use crate::operations::trash;
+use crate::window::FileOperationRequest;
+use nptk::core::menu::{MenuCommand, MenuItem, MenuTemplate};
+use nptk::prelude::*;
+use nptk::services::filesystem::model::{FileSystemEvent, FileSystemModel};
+use nptk::services::{get_user_special_dir_path, UserDirectory};
+use nptk_fileman_widgets::{file_names, xdg};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Once, OnceLock, RwLock};
+use tokio::sync::broadcast::error::RecvError;
+
+/// The Templates folder, once it was looked up
+static DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Watches the Templates folder, once it was looked up
+static MODEL: OnceLock<FileSystemModel> = OnceLock::new();
+
+/// Templates in the folder, sorted by name
+static TEMPLATES: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+
+static MONITOR: Once = Once::new();
+
+fn default_dir() -> PathBuf {
+    xdg::home().join("Templates")
+}
+
+/// The Templates folder. It may not exist yet.
+pub fn dir() -> PathBuf {
+    DIR.read().ok().and_then(|dir| dir.clone()).unwrap_or_else(default_dir)
+}
+
+/// The templates, sorted by name.
+pub fn list() -> Vec<PathBuf> {
+    TEMPLATES.read().map(|templates| templates.clone()).unwrap_or_default()
+}
+
+/// Name of a template in the menu: its file name without the extension
+pub fn label(template: &Path) -> String {
+    template
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn read_templates(dir: &Path) -> Vec<PathBuf> {
+    let mut templates: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect()
+        })
+        .unwrap_or_default();
+    templates.sort_by_key(|template| label(template).to_lowercase());
+    templates
+}
+
+/// List the Templates folder again now, after a change made here. The folder
+/// is listed by the model too, so it is watched if it was only just created.
+fn refresh() {
+    let dir = dir();
+    let templates = read_templates(&dir);
+    if let Ok(mut current) = TEMPLATES.write() {
+        *current = templates;
+    }
+    if let Some(model) = MODEL.get() {
+        let _ = model.refresh(&dir);
+    }
+}
+
+/// Whether `event` of the model is about the contents of `dir`.
+fn concerns(event: &FileSystemEvent, dir: &Path) -> bool {
+    match event {
+        FileSystemEvent::DirectoryLoaded { path, .. } => path == dir,
+        FileSystemEvent::EntryAdded { path, .. }
+        | FileSystemEvent::EntryRemoved { path }
+        | FileSystemEvent::EntryModified { path, .. } => path.parent() == Some(dir),
+        _ => false,
+    }
+}
+
+/// Look up the Templates folder and keep the list of templates current. Only
+/// the first call (of the first window) starts watching.
+pub fn spawn_monitor() {
+    MONITOR.call_once(|| {
+        tokio::spawn(async {
+            let dir = get_user_special_dir_path(UserDirectory::Templates)
+                .await
+                .unwrap_or_else(default_dir);
+            if let Ok(mut current) = DIR.write() {
+                *current = Some(dir.clone());
+            }
+            let model = match FileSystemModel::new(dir.clone()) {
+                Ok(model) => MODEL.get_or_init(|| model),
+                Err(e) => {
+                    log::warn!("Failed to watch {}: {}", dir.display(), e);
+                    return;
+                },
+            };
+            let mut events = model.subscribe_events();
+            // Listing the folder starts watching it; its listing is read below
+            let _ = model.refresh(&dir);
+            loop {
+                let listed = dir.clone();
+                let templates = tokio::task::spawn_blocking(move || read_templates(&listed))
+                    .await
+                    .unwrap_or_default();
+                if let Ok(mut current) = TEMPLATES.write() {
+                    if *current != templates {
+                        *current = templates;
+                    }
+                }
+                // Wait for a change, then take the rest of its burst along
+                loop {
+                    match events.recv().await {
+                        Ok(event) if concerns(&event, &dir) => break,
+                        Ok(_) => {},
+                        Err(RecvError::Lagged(_)) => break,
+                        Err(RecvError::Closed) => return,
+                    }
+                }
+                while events.try_recv().is_ok() {}
+            }
+        });
+    });
+}
+
+/// Create a file in `parent` from `template`, named like the template or
+/// "name (2)" if that is taken. Returns the new file.
+pub fn create(template: &Path, parent: &Path) -> Result<PathBuf, String> {
+    let name = template
+        .file_name()
+        .ok_or_else(|| format!("Invalid template: {}", template.display()))?;
+    file_names::validate(&name.to_string_lossy(), parent)?;
+    let mut source = fs::File::open(template).map_err(|e| format!("Failed to open {}: {}", template.display(), e))?;
+    // A name is only taken by creating the file, so another one created
+    // meanwhile is never overwritten
+    for target in file_names::candidates(&parent.join(name)) {
+        let mut file = match fs::OpenOptions::new().write(true).create_new(true).open(&target) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create {}: {}", target.display(), e)),
+        };
+        if let Err(e) = io::copy(&mut source, &mut file) {
+            let _ = fs::remove_file(&target);
+            return Err(format!("Failed to create {}: {}", target.display(), e));
+        }
+        // Like a copy, the file gets the template's permissions
+        if let Ok(metadata) = source.metadata() {
+            let _ = file.set_permissions(metadata.permissions());
+        }
+        return Ok(target);
+    }
+    unreachable!("unbounded range always yields a free name")
+}
+
+/// Copy `file` into the Templates folder, creating the folder if needed.
+pub fn add(file: &Path) -> Result<PathBuf, String> {
+    if !file.is_file() {
+        return Err(format!("{} is not a file", file.display()));
+    }
+    let name = file
+        .file_name()
+        .ok_or_else(|| format!("Invalid file: {}", file.display()))?;
+    let dir = dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let target = dir.join(name);
+    if target.exists() {
+        return Err(format!("There already is a template called {}", name.to_string_lossy()));
+    }
+    fs::copy(file, &target).map_err(|e| format!("Failed to add template: {}", e))?;
+    refresh();
+    Ok(target)
+}
+
+/// Move `template` to the trash.
+pub fn remove(template: &Path) -> Result<(), String> {
+    trash::trash_path(template)?;
+    refresh();
+    Ok(())
+}
+
+/// The "New File from Template" submenu, creating files in `parent`.
+pub fn menu_item(parent: &Path, bus: &AppBus) -> MenuItem {
+    let mut items: Vec<MenuItem> = list()
+        .into_iter()
+        .take(menus::RANGE_LENGTH)
+        .enumerate()
+        .map(|(i, template)| {
+            let bus = bus.clone();
+            let parent = parent.to_path_buf();
+            MenuItem::new(MenuCommand::Custom(menus::range_id(menus::TEMPLATE_IDS, i)), label(&template)).with_action(move || {
+                bus.operation(FileOperationRequest::CreateFromTemplate {
+                    template: template.clone(),
+                    parent: parent.clone(),
+                });
+                Update::DRAW
+            })
+        })
+        .collect();
+    if items.is_empty() {
+        items.push(MenuItem::new(MenuCommand::Custom(menus::NO_TEMPLATES_ID), "No Templates").with_action(|| Update::empty()));
+    }
+    let bus = bus.clone();
+    items.push(MenuItem::separator());
+    items.push(
+        MenuItem::new(MenuCommand::Custom(menus::MANAGE_TEMPLATES_ID), "Manage Templates...").with_action(move || {
+            bus.operation(FileOperationRequest::Preferences);
+            Update::DRAW
+        }),
+    );
+    MenuItem::new(MenuCommand::Custom(menus::NEW_FROM_TEMPLATE_ID), "New File from Template")
+        .with_submenu(MenuTemplate::from_items("new_from_template", items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_numbers_taken_names() {
+        let temp = tempfile::tempdir().unwrap();
+        let template = temp.path().join("Note.txt");
+        fs::write(&template, "note").unwrap();
+        let parent = temp.path().join("docs");
+        fs::create_dir(&parent).unwrap();
+        fs::write(parent.join("Note.txt"), "mine").unwrap();
+
+        assert_eq!(create(&template, &parent).unwrap(), parent.join("Note (2).txt"));
+        assert_eq!(create(&template, &parent).unwrap(), parent.join("Note (3).txt"));
+        assert_eq!(fs::read_to_string(parent.join("Note.txt")).unwrap(), "mine");
+        assert_eq!(fs::read_to_string(parent.join("Note (3).txt")).unwrap(), "note");
+    }
+}
@@ -1,245 +1,470 @@
 use nptk::prelude::*;
 use async_trait::async_trait;
-use nptk::core::signal::eval::EvalSignal;
+use crate::bus::{AppBus, AppCommand, Subscription};
+use crate::menus;
 use crate::navigation::NavigationState;
 use crate::window::FileOperationRequest;
-use nptk_fileman_widgets::file_list::FileListViewMode;
+use nptk_fileman_widgets::file_list::{FileListSort, FileListSortColumn, FileListViewMode, SortOrder};
+use nptk::core::menu::{MenuCommand, MenuItem, MenuTemplate};
+use nptk::core::vg::kurbo::Point;
+use nptk::widgets::text_input::TextInput;
+use nptk_fileman_widgets::accessibility::{AccessibleNode, AccessibleRole, AccessibleSubtree};
+use nptk_fileman_widgets::commands::CommandQueue;
+use nptk_fileman_widgets::focus::{FocusChain, Pane};
+use nptk_fileman_widgets::xdg;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
-use tokio::sync::mpsc;
+
+mod gated;
+
+use gated::GatedButton;
 
 // Toolbar types are re-exported from nptk prelude
 // They're already available via `use nptk::prelude::*;`
 
 /// Navigation actions that can be sent from toolbar buttons
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NavigationAction {
     Back,
     Forward,
     Up,
     Home,
     NavigateTo(PathBuf),
+    OpenInNewTab(PathBuf),
+}
+
+/// What a toolbar button does when pressed
+#[derive(Debug, Clone, PartialEq)]
+enum ToolbarCommand {
+    Navigate(NavigationAction),
+    NewFolder,
+    Rename,
+    Trash,
+    Properties,
+    /// Switch to the next view mode
+    CycleView,
+    /// Show the sort menu below the cursor
+    SortMenu,
+    ToggleInfoPanel,
+    Refresh,
+    Eject,
+    Transfers,
+    Preferences,
 }
 
 /// Wrapper widget for toolbar with navigation and file operation buttons
 pub struct ToolbarWrapper {
     inner: Toolbar,
     navigation: Arc<Mutex<NavigationState>>,
-    operation_tx: mpsc::UnboundedSender<FileOperationRequest>,
-    navigation_tx: mpsc::UnboundedSender<NavigationAction>,
-    navigation_rx: Option<mpsc::UnboundedReceiver<NavigationAction>>,
+    bus: AppBus,
+    // Navigation commands are carried out here
+    bus_commands: Subscription<AppCommand>,
     // Reactive signals
     navigation_path_signal: nptk::core::signal::state::StateSignal<PathBuf>,
     selected_paths_signal: nptk::core::signal::state::StateSignal<Vec<PathBuf>>,
     can_go_back: nptk::core::signal::state::StateSignal<bool>,
     can_go_forward: nptk::core::signal::state::StateSignal<bool>,
-    has_selection: nptk::core::signal::state::StateSignal<bool>,
+    can_go_up: nptk::core::signal::state::StateSignal<bool>,
+    can_delete: nptk::core::signal::state::StateSignal<bool>,
+    // Whether the shown folder can be changed
+    writable: nptk::core::signal::state::StateSignal<bool>,
     signals_hooked: bool,
-    new_folder_requested: Arc<Mutex<bool>>,
-    properties_requested: Arc<Mutex<bool>>,
-    delete_requested: Arc<Mutex<bool>>,
+    commands: CommandQueue<ToolbarCommand>,
     view_mode_signal: nptk::core::signal::state::StateSignal<FileListViewMode>,
+    info_panel_visible: nptk::core::signal::state::StateSignal<bool>,
+    sort_signal: nptk::core::signal::state::StateSignal<FileListSort>,
+    dim_ignored: nptk::core::signal::state::StateSignal<bool>,
+    show_hidden: nptk::core::signal::state::StateSignal<bool>,
+    // Whether thumbnails are generated in the shown folder, if it can be changed
+    thumbnails: Option<nptk::core::signal::state::StateSignal<bool>>,
+    accessibility: AccessibleSubtree,
+    focus: Option<FocusChain>,
 }
 
 impl ToolbarWrapper {
     pub fn new(
         navigation: Arc<Mutex<NavigationState>>,
-        operation_tx: mpsc::UnboundedSender<FileOperationRequest>,
+        bus: AppBus,
         navigation_path_signal: nptk::core::signal::state::StateSignal<PathBuf>,
         selected_paths_signal: nptk::core::signal::state::StateSignal<Vec<PathBuf>>,
         view_mode_signal: nptk::core::signal::state::StateSignal<FileListViewMode>,
-    ) -> (Self, mpsc::UnboundedSender<NavigationAction>) {
-        let (nav_tx, nav_rx) = mpsc::unbounded_channel();
-        
-        // Create buttons using EvalSignal to perform side effects when pressed
-        // EvalSignal evaluates the closure every time get() is called (when button is pressed)
-        let nav_clone1 = navigation.clone();
-        let nav_clone2 = navigation.clone();
-        let nav_clone3 = navigation.clone();
-        let nav_clone4 = navigation.clone();
+        sort_signal: nptk::core::signal::state::StateSignal<FileListSort>,
+        info_panel_visible: nptk::core::signal::state::StateSignal<bool>,
+        dim_ignored: nptk::core::signal::state::StateSignal<bool>,
+        show_hidden: nptk::core::signal::state::StateSignal<bool>,
+        writable: nptk::core::signal::state::StateSignal<bool>,
+    ) -> Self {
+        // Buttons that don't apply right now are disabled
+        let can_go_back = nptk::core::signal::state::StateSignal::new(false);
+        let can_go_forward = nptk::core::signal::state::StateSignal::new(false);
+        let can_go_up = nptk::core::signal::state::StateSignal::new(false);
+        // Deleting also needs a folder that can be changed
+        let can_delete = nptk::core::signal::state::StateSignal::new(false);
         
-        let back_btn = ToolbarButton::with_children(vec![
-            Box::new(Icon::new("arrow-left", 24, None)),
-            Box::new(Text::new("Back".to_string()).with_font_size(14.0))
-        ])
-            .with_on_pressed(nptk::core::signal::MaybeSignal::signal(Box::new(EvalSignal::new(move || {
-                if let Ok(mut nav) = nav_clone1.lock() {
-                    if nav.go_back().is_some() {
-                        return Update::LAYOUT | Update::DRAW;
-                    }
-                }
-                Update::empty()
-            }))))
+        // Buttons post commands, which are carried out once per press in update()
+        let commands = CommandQueue::new();
+        let button = |icon: &'static str, label: &str, command: ToolbarCommand| {
+            ToolbarButton::with_children(vec![
+                Box::new(Icon::new(icon, 24, None)),
+                Box::new(Text::new(label.to_string()).with_font_size(14.0)),
+            ])
+            .with_on_pressed(commands.on_pressed(command))
+        };
+
+        let back_btn = button("arrow-left", "Back", ToolbarCommand::Navigate(NavigationAction::Back))
             .with_tooltip("Go back")
             .with_status_tip("Navigate to the previous directory in history");
 
-        let forward_btn = ToolbarButton::with_children(vec![
-            Box::new(Icon::new("arrow-right", 24, None)),
-            Box::new(Text::new("Forward".to_string()).with_font_size(14.0))
-        ])
-            .with_on_pressed(nptk::core::signal::MaybeSignal::signal(Box::new(EvalSignal::new(move || {
-                if let Ok(mut nav) = nav_clone2.lock() {
-                    if nav.go_forward().is_some() {
-                        return Update::LAYOUT | Update::DRAW;
-                    }
-                }
-                Update::empty()
-            }))))
+        let forward_btn = button("arrow-right", "Forward", ToolbarCommand::Navigate(NavigationAction::Forward))
             .with_tooltip("Go forward")
             .with_status_tip("Navigate to the next directory in history");
 
-        let up_btn = ToolbarButton::with_children(vec![
-            Box::new(Icon::new("arrow-up", 24, None)),
-            Box::new(Text::new("Up".to_string()).with_font_size(14.0))
-        ])
-            .with_on_pressed(nptk::core::signal::MaybeSignal::signal(Box::new(EvalSignal::new(move || {
-                if let Ok(mut nav) = nav_clone3.lock() {
-                    if let Some(parent) = nav.parent_path() {
-                        nav.navigate_to(parent);
-                        return Update::LAYOUT | Update::DRAW;
-                    }
-                }
-                Update::empty()
-            }))))
+        let up_btn = button("arrow-up", "Up", ToolbarCommand::Navigate(NavigationAction::Up))
             .with_status_tip("Navigate to the parent directory");
 
-        let home_btn = ToolbarButton::with_children(vec![
-            Box::new(Icon::new("folder-home", 24, None)),
-            Box::new(Text::new("Home".to_string()).with_font_size(14.0))
-        ])
-            .with_on_pressed(nptk::core::signal::MaybeSignal::signal(Box::new(EvalSignal::new(move || {
-                if let Ok(mut nav) = nav_clone4.lock() {
-                    let home = std::env::var("HOME")
-                        .ok()
-                        .map(PathBuf::from)
-                        .unwrap_or_else(|| PathBuf::from("/home"));
-                    nav.navigate_to(home);
-                    return Update::LAYOUT | Update::DRAW;
-                }
-                Update::empty()
-            }))))
+        let home_btn = button("folder-home", "Home", ToolbarCommand::Navigate(NavigationAction::Home))
             .with_tooltip("Go home")
             .with_status_tip("Navigate to the home directory");
 
-        let new_folder_requested = Arc::new(Mutex::new(false));
-        let new_folder_btn = ToolbarButton::with_children(vec![
-            Box::new(Icon::new("folder-new", 24, None)),
-            Box::new(Text::new("New Folder".to_string()).with_font_size(14.0))
-        ])
-            .with_on_pressed({
-                let new_folder_flag = new_folder_requested.clone();
-                nptk::core::signal::MaybeSignal::signal(Box::new(EvalSignal::new(move || {
-                    if let Ok(mut flag) = new_folder_flag.lock() {
-                        *flag = true;
-                    }
-                    Update::DRAW
-                })))
-            })
+        let new_folder_btn = button("folder-new", "New Folder", ToolbarCommand::NewFolder)
             .with_tooltip("New folder")
             .with_status_tip("Create a new folder in the current directory");
 
-        let properties_requested = Arc::new(Mutex::new(false));
-        let properties_btn = ToolbarButton::with_children(vec![
-            Box::new(Icon::new("document-properties", 24, None)),
-            Box::new(Text::new("Properties".to_string()).with_font_size(14.0))
-        ])
-            .with_on_pressed({
-                let properties_flag = properties_requested.clone();
-                nptk::core::signal::MaybeSignal::signal(Box::new(EvalSignal::new(move || {
-                    if let Ok(mut flag) = properties_flag.lock() {
-                        *flag = true;
-                    }
-                    Update::DRAW
-                })))
-            })
+        let properties_btn = button("document-properties", "Properties", ToolbarCommand::Properties)
             .with_tooltip("Properties")
-            .with_status_tip("Show properties of the selected items");
-
-        // Delete button - read selected paths from signal directly
-        let delete_requested = Arc::new(Mutex::new(false));
-        let delete_requested_clone = delete_requested.clone();
-        let delete_btn = ToolbarButton::with_children(vec![
-            Box::new(Icon::new("delete", 24, None)),
-            Box::new(Text::new("Delete".to_string()).with_font_size(14.0))
-            ])
-            .with_on_pressed({
-                let delete_flag = delete_requested_clone.clone();
-                nptk::core::signal::MaybeSignal::signal(Box::new(EvalSignal::new(move || {
-                    if let Ok(mut f) = delete_flag.lock() {
-                        *f = true;
-                    }
-                    Update::DRAW
-                })))
-            })
-            .with_tooltip("Delete")
-            .with_status_tip("Delete the selected items");
-
-        let view_mode_signal_clone = view_mode_signal.clone();
-        let view_btn = ToolbarButton::with_children(vec![
-            Box::new(Icon::new("view-list-details", 24, None)), // Fallback icon name, hopefully exists or falls back text
-            Box::new(Text::new("View".to_string()).with_font_size(14.0))
-        ])
-         .with_on_pressed(nptk::core::signal::MaybeSignal::signal(Box::new(EvalSignal::new(move || {
-                let current = *view_mode_signal_clone.get();
-                let next = match current {
-                    FileListViewMode::List => FileListViewMode::Icon,
-                    FileListViewMode::Icon => FileListViewMode::Table, // New Table mode
-                    FileListViewMode::Table | FileListViewMode::Compact => FileListViewMode::List,
-                };
-                view_mode_signal_clone.set(next);
-                Update::DRAW
-            }))))
+            .with_status_tip("Show properties of the selected items, or of the current folder");
+
+        let delete_btn = button("user-trash", "Move to Trash", ToolbarCommand::Trash)
+            .with_tooltip("Move to trash")
+            .with_status_tip("Move the selected items to the trash (Shift+Delete deletes them permanently)");
+
+        let rename_btn = button("edit-rename", "Rename", ToolbarCommand::Rename)
+            .with_tooltip("Rename")
+            .with_status_tip("Rename the selected item");
+
+        // Fallback icon name, hopefully exists or falls back text
+        let view_btn = button("view-list-details", "View", ToolbarCommand::CycleView)
             .with_tooltip("Change View")
             .with_status_tip("Switch between List, Icon, and Details views");
 
+        let sort_btn = button("view-sort-ascending", "Sort", ToolbarCommand::SortMenu)
+            .with_tooltip("Sort")
+            .with_status_tip("Change how items are sorted");
+
+        let info_btn = button("dialog-information", "Info", ToolbarCommand::ToggleInfoPanel)
+            .with_tooltip("Information Panel")
+            .with_status_tip("Show or hide details about the current selection");
+
+        let refresh_btn = button("view-refresh", "Refresh", ToolbarCommand::Refresh)
+            .with_tooltip("Refresh (F5)")
+            .with_status_tip("Read the current folder again; Ctrl+F5 also clears its cached data");
+
+        let eject_btn = button("media-eject", "Eject", ToolbarCommand::Eject)
+            .with_tooltip("Eject")
+            .with_status_tip("Unmount and power off the removable device the current folder is on");
+
+        let transfers_btn = button("network-transmit-receive", "Transfers", ToolbarCommand::Transfers)
+            .with_tooltip("Transfers")
+            .with_status_tip("Manage queued and running transfers and their speed limits");
+
+        let preferences_btn = button("preferences-system", "Preferences", ToolbarCommand::Preferences)
+            .with_tooltip("Preferences")
+            .with_status_tip("Change how fileman behaves");
+
         let toolbar = Toolbar::new()
-            .with_child(back_btn)
-            .with_child(forward_btn)
-            .with_child(up_btn)
+            .with_child(GatedButton::new(back_btn, can_go_back.clone()))
+            .with_child(GatedButton::new(forward_btn, can_go_forward.clone()))
+            .with_child(GatedButton::new(up_btn, can_go_up.clone()))
             .with_separator()
             .with_child(home_btn)
+            .with_child(refresh_btn)
+            .with_child(eject_btn)
             .with_separator()
-            .with_child(new_folder_btn)
-            .with_child(delete_btn)
+            .with_child(GatedButton::new(new_folder_btn, writable.clone()))
+            .with_child(GatedButton::new(rename_btn, writable.clone()))
+            .with_child(GatedButton::new(delete_btn, can_delete.clone()))
             .with_separator()
             .with_child(properties_btn)
             .with_separator()
-            .with_child(view_btn);
+            .with_child(view_btn)
+            .with_child(sort_btn)
+            .with_child(info_btn)
+            .with_separator()
+            .with_child(transfers_btn)
+            .with_child(preferences_btn);
 
-        let wrapper = Self {
+        Self {
             inner: toolbar,
             navigation,
-            operation_tx: operation_tx.clone(),
-            navigation_tx: nav_tx.clone(),
-            navigation_rx: Some(nav_rx),
+            bus_commands: bus.subscribe(),
+            bus,
             navigation_path_signal,
             selected_paths_signal,
-            can_go_back: nptk::core::signal::state::StateSignal::new(false),
-            can_go_forward: nptk::core::signal::state::StateSignal::new(false),
-            has_selection: nptk::core::signal::state::StateSignal::new(false),
+            can_go_back,
+            can_go_forward,
+            can_go_up,
+            can_delete,
+            writable,
             signals_hooked: false,
-            new_folder_requested,
-            properties_requested,
-            delete_requested,
+            commands,
             view_mode_signal,
-        };
+            info_panel_visible,
+            sort_signal,
+            dim_ignored,
+            show_hidden,
+            thumbnails: None,
+            accessibility: AccessibleSubtree::new(),
+            focus: None,
+        }
+    }
+
+    /// Join the window's focus chain (shows a focus ring while focused).
+    pub fn with_focus_chain(mut self, focus: FocusChain) -> Self {
+        self.focus = Some(focus);
+        self
+    }
 
-        (wrapper, nav_tx)
+    /// Add a search field at the end, whose text is kept in `query` (searched
+    /// by the file list as it is typed).
+    pub fn with_search(mut self, query: nptk::core::signal::state::StateSignal<String>) -> Self {
+        let field = TextInput::new()
+            .with_text_signal(query)
+            .with_placeholder("Search".to_string())
+            .with_layout_style(LayoutStyle {
+                size: Vector2::new(Dimension::length(220.0), Dimension::length(30.0)),
+                ..Default::default()
+            });
+        self.inner = self.inner.with_separator().with_child(field);
+        self
     }
 
-    /// Get the navigation action sender for external use (e.g., from location bar)
-    pub fn navigation_tx(&self) -> &mpsc::UnboundedSender<NavigationAction> {
-        &self.navigation_tx
+    /// Offer turning thumbnails in the shown folder on and off in the sort menu;
+    /// `thumbnails` tells whether they are generated there now.
+    pub fn with_thumbnails(mut self, thumbnails: nptk::core::signal::state::StateSignal<bool>) -> Self {
+        self.thumbnails = Some(thumbnails);
+        self
+    }
+
+    /// Handle to the accessibility tree of the toolbar: its buttons and whether
+    /// they apply right now.
+    pub fn accessibility(&self) -> AccessibleSubtree {
+        self.accessibility.clone()
+    }
+
+    fn publish_accessibility(&self) {
+        let selected = self.selected_paths_signal.get().len();
+        let read_only = !*self.writable.get();
+        let buttons = [
+            ("Back", !*self.can_go_back.get()),
+            ("Forward", !*self.can_go_forward.get()),
+            ("Up", !*self.can_go_up.get()),
+            ("Home", false),
+            ("New Folder", read_only),
+            ("Rename", selected != 1 || read_only),
+            ("Move to Trash", selected == 0 || read_only),
+            ("Properties", false),
+            ("View", false),
+            ("Sort", false),
+            ("Info", false),
+            ("Transfers", false),
+            ("Preferences", false),
+        ];
+        let children = buttons
+            .into_iter()
+            .map(|(name, disabled)| {
+                AccessibleNode::new(AccessibleRole::Button, name).with_disabled(disabled)
+            })
+            .collect();
+        self.accessibility
+            .set(AccessibleNode::new(AccessibleRole::Toolbar, "Toolbar").with_children(children));
+    }
+
+    fn navigate(&self, action: NavigationAction) -> Update {
+        let Ok(mut nav) = self.navigation.lock() else {
+            return Update::empty();
+        };
+        match action {
+            NavigationAction::Back => {
+                if nav.go_back().is_none() {
+                    return Update::empty();
+                }
+            }
+            NavigationAction::Forward => {
+                if nav.go_forward().is_none() {
+                    return Update::empty();
+                }
+            }
+            NavigationAction::Up => {
+                let Some(parent) = nav.parent_path() else {
+                    return Update::empty();
+                };
+                nav.navigate_to(parent);
+            }
+            NavigationAction::Home => {
+                nav.navigate_to(xdg::home());
+            }
+            NavigationAction::NavigateTo(path) => {
+                nav.navigate_to(path);
+            }
+            NavigationAction::OpenInNewTab(path) => {
+                nav.open_tab(path);
+            }
+        }
+        Update::LAYOUT | Update::DRAW
     }
 
-    /// Get the operation sender for external use
-    pub fn operation_tx(&self) -> &mpsc::UnboundedSender<FileOperationRequest> {
-        &self.operation_tx
+    /// Carry out the command of a pressed button.
+    fn run_command(
+        &mut self,
+        command: ToolbarCommand,
+        context: &nptk::core::app::context::AppContext,
+        info: &nptk::core::app::info::AppInfo,
+    ) -> Update {
+        let selected_paths = (*self.selected_paths_signal.get()).clone();
+        match command {
+            ToolbarCommand::Navigate(action) => return self.navigate(action),
+            ToolbarCommand::NewFolder => {
+                let current = (*self.navigation_path_signal.get()).clone();
+                self.bus.operation(FileOperationRequest::NewFolder(current));
+            }
+            ToolbarCommand::Properties => {
+                // Without a selection, the folder being shown
+                let paths = if selected_paths.is_empty() {
+                    let Ok(nav) = self.navigation.lock() else {
+                        return Update::empty();
+                    };
+                    vec![nav.get_current_path()]
+                } else {
+                    selected_paths
+                };
+                self.bus.operation(FileOperationRequest::Properties(paths));
+            }
+            ToolbarCommand::Trash => {
+                if selected_paths.is_empty() {
+                    return Update::empty();
+                }
+                self.bus.operation(FileOperationRequest::Trash(selected_paths));
+            }
+            ToolbarCommand::Rename => {
+                // Only for a single selected item
+                let [path] = selected_paths.as_slice() else {
+                    return Update::empty();
+                };
+                self.bus.operation(FileOperationRequest::AskRename(path.clone()));
+                return Update::DRAW;
+            }
+            ToolbarCommand::CycleView => {
+                self.bus.operation(FileOperationRequest::CycleView);
+                return Update::DRAW;
+            }
+            ToolbarCommand::SortMenu => {
+                let Some(cursor) = info.cursor_pos else {
+                    return Update::empty();
+                };
+                context.menu_manager.show(self.build_sort_menu(), Point::new(cursor.x, cursor.y));
+                return Update::DRAW;
+            }
+            ToolbarCommand::ToggleInfoPanel => {
+                let visible = *self.info_panel_visible.get();
+                self.info_panel_visible.set(!visible);
+            }
+            ToolbarCommand::Refresh => {
+                self.bus.operation(FileOperationRequest::Refresh { deep: false });
+                return Update::DRAW;
+            }
+            ToolbarCommand::Eject => {
+                self.bus.operation(FileOperationRequest::Eject(None));
+                return Update::DRAW;
+            }
+            ToolbarCommand::Transfers => {
+                self.bus.operation(FileOperationRequest::Transfers);
+                return Update::DRAW;
+            }
+            ToolbarCommand::Preferences => {
+                self.bus.operation(FileOperationRequest::Preferences);
+                return Update::DRAW;
+            }
+        }
+        Update::LAYOUT | Update::DRAW
     }
 
-    pub fn take_navigation_receiver(&mut self) -> Option<mpsc::UnboundedReceiver<NavigationAction>> {
-        self.navigation_rx.take()
+    /// Build the sort menu. Picking the active column again reverses the order.
+    ///
+    /// It also holds the toggles for hidden files and for dimming ignored files,
+    /// and Reset Layout.
+    fn build_sort_menu(&self) -> MenuTemplate {
+        let current = *self.sort_signal.get();
+        let columns = [
+            (FileListSortColumn::Name, "By Name"),
+            (FileListSortColumn::Size, "By Size"),
+            (FileListSortColumn::Type, "By Type"),
+            (FileListSortColumn::Modified, "By Modification Date"),
+        ];
+
+        let mut items = Vec::new();
+        for (i, (column, label)) in columns.into_iter().enumerate() {
+            let sort_signal = self.sort_signal.clone();
+            let label = if column == current.column {
+                let arrow = match current.order {
+                    SortOrder::Ascending => "↑",
+                    SortOrder::Descending => "↓",
+                };
+                format!("{} {}", label, arrow)
+            } else {
+                label.to_string()
+            };
+            items.push(
+                MenuItem::new(MenuCommand::Custom(menus::range_id(menus::SORT_BY_IDS, i)), label).with_action(move || {
+                    let current = *sort_signal.get();
+                    let order = if current.column == column {
+                        current.order.reversed()
+                    } else {
+                        SortOrder::Ascending
+                    };
+                    sort_signal.set(FileListSort { column, order });
+                    Update::LAYOUT | Update::DRAW
+                }),
+            );
+        }
+        items.push(MenuItem::separator());
+        let show_hidden = self.show_hidden.clone();
+        let hidden_label = if *show_hidden.get() { "✓ Show Hidden Files" } else { "Show Hidden Files" };
+        items.push(
+            MenuItem::new(MenuCommand::Custom(menus::SHOW_HIDDEN_ID), hidden_label).with_action(move || {
+                let show = *show_hidden.get();
+                show_hidden.set(!show);
+                Update::LAYOUT | Update::DRAW
+            }),
+        );
+        let dim_ignored = self.dim_ignored.clone();
+        let dim_label = if *dim_ignored.get() { "✓ Dim Ignored Files" } else { "Dim Ignored Files" };
+        items.push(
+            MenuItem::new(MenuCommand::Custom(menus::DIM_IGNORED_ID), dim_label).with_action(move || {
+                let dim = *dim_ignored.get();
+                dim_ignored.set(!dim);
+                Update::DRAW
+            }),
+        );
+        if let Some(thumbnails) = &self.thumbnails {
+            let label = if *thumbnails.get() { "✓ Thumbnails in This Folder" } else { "Thumbnails in This Folder" };
+            let bus = self.bus.clone();
+            items.push(
+                MenuItem::new(MenuCommand::Custom(menus::FOLDER_THUMBNAILS_ID), label).with_action(move || {
+                    bus.operation(FileOperationRequest::ToggleFolderThumbnails);
+                    Update::DRAW
+                }),
+            );
+        }
+        items.push(MenuItem::separator());
+        let bus = self.bus.clone();
+        items.push(
+            MenuItem::new(MenuCommand::Custom(menus::RESET_LAYOUT_ID), "Reset Layout").with_action(move || {
+                bus.operation(FileOperationRequest::ResetLayout);
+                Update::DRAW
+            }),
+        );
+        MenuTemplate::from_items("sort_menu", items)
     }
 
 }
@@ -263,101 +488,46 @@ impl Widget for ToolbarWrapper {
         if !self.signals_hooked {
             context.hook_signal(&mut self.can_go_back);
             context.hook_signal(&mut self.can_go_forward);
-            context.hook_signal(&mut self.has_selection);
+            context.hook_signal(&mut self.can_go_up);
+            context.hook_signal(&mut self.can_delete);
+            context.hook_signal(&mut self.writable);
             context.hook_signal(&mut self.navigation_path_signal);
             context.hook_signal(&mut self.selected_paths_signal);
             context.hook_signal(&mut self.view_mode_signal);
+            context.hook_signal(&mut self.info_panel_visible);
+            context.hook_signal(&mut self.sort_signal);
             self.signals_hooked = true;
         }
 
-        // Process navigation actions from external sources (like location bar)
-        // Note: Button presses are handled via the buttons' on_pressed callbacks
-        // which directly update NavigationState. We process actions here for
-        // programmatic navigation requests.
-        if let Some(ref mut rx) = self.navigation_rx {
-            while let Ok(action) = rx.try_recv() {
-                if let Ok(mut nav) = self.navigation.lock() {
-                    match action {
-                        NavigationAction::Back => {
-                            if nav.go_back().is_some() {
-                                update.insert(Update::LAYOUT | Update::DRAW);
-                            }
-                        }
-                        NavigationAction::Forward => {
-                            if nav.go_forward().is_some() {
-                                update.insert(Update::LAYOUT | Update::DRAW);
-                            }
-                        }
-                        NavigationAction::Up => {
-                            if let Some(parent) = nav.parent_path() {
-                                nav.navigate_to(parent);
-                                update.insert(Update::LAYOUT | Update::DRAW);
-                            }
-                        }
-                        NavigationAction::Home => {
-                            let home = std::env::var("HOME")
-                                .ok()
-                                .map(PathBuf::from)
-                                .unwrap_or_else(|| PathBuf::from("/home"));
-                            nav.navigate_to(home);
-                            update.insert(Update::LAYOUT | Update::DRAW);
-                        }
-                        NavigationAction::NavigateTo(path) => {
-                            nav.navigate_to(path);
-                            update.insert(Update::LAYOUT | Update::DRAW);
-                        }
-                    }
-                }
-            }
-        }
-        
-        // Handle new folder button press
-        if let Ok(mut flag) = self.new_folder_requested.lock() {
-            if *flag {
-                *flag = false;
-                let current = (*self.navigation_path_signal.get()).clone();
-                let name = format!("New Folder {}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs());
-                let _ = self.operation_tx.send(FileOperationRequest::CreateDirectory {
-                    parent: current,
-                    name,
-                });
-                update.insert(Update::LAYOUT | Update::DRAW);
-            }
-        }
-
-        // Handle properties button press - read selected paths from signal
-        if let Ok(mut flag) = self.properties_requested.lock() {
-            if *flag {
-                *flag = false;
-                let selected_paths = (*self.selected_paths_signal.get()).clone();
-                if !selected_paths.is_empty() {
-                    let _ = self.operation_tx.send(FileOperationRequest::Properties(selected_paths));
-                    update.insert(Update::LAYOUT | Update::DRAW);
-                }
+        // Carry out navigation asked for on the bus (location bar, sidebar, ...)
+        while let Some(command) = self.bus_commands.try_recv() {
+            if let AppCommand::Navigate(action) = command {
+                update |= self.navigate(action);
             }
         }
 
-        // Handle delete button - read selected paths from signal directly
-        if let Ok(mut flag) = self.delete_requested.lock() {
-            if *flag {
-                *flag = false;
-                let selected_paths = (*self.selected_paths_signal.get()).clone();
-                if !selected_paths.is_empty() {
-                    let _ = self.operation_tx.send(FileOperationRequest::Delete(selected_paths));
-                    update.insert(Update::LAYOUT | Update::DRAW);
-                }
-            }
+        // Carry out the commands of pressed buttons
+        for command in self.commands.take() {
+            update |= self.run_command(command, &context, info);
         }
 
         // Update button states reactively from navigation
         if let Ok(nav) = self.navigation.lock() {
             self.can_go_back.set(nav.can_go_back());
             self.can_go_forward.set(nav.can_go_forward());
+            self.can_go_up.set(nav.parent_path().is_some());
         }
 
-        // Update has_selection signal reactively from selected_paths_signal
-        let selected_paths = (*self.selected_paths_signal.get()).clone();
-        self.has_selection.set(!selected_paths.is_empty());
+        // Update can_delete signal reactively from selected_paths_signal
+        let has_selection = !self.selected_paths_signal.get().is_empty();
+        self.can_delete.set(has_selection && *self.writable.get());
+        self.publish_accessibility();
+
+        if let Some(focus) = &self.focus {
+            if focus.focus_on_click(Pane::Toolbar, layout, info) {
+                update.insert(Update::DRAW);
+            }
+        }
 
         // Update inner toolbar
         update |= self.inner.update(layout, context, info).await;
@@ -371,7 +541,10 @@ impl Widget for ToolbarWrapper {
         info: &mut nptk::core::app::info::AppInfo,
         context: nptk::core::app::context::AppContext,
     ) {
-        self.inner.render(graphics, layout, info, context)
+        self.inner.render(graphics, layout, info, context.clone());
+        if let Some(focus) = &self.focus {
+            focus.render_ring(Pane::Toolbar, graphics, layout, context.palette());
+        }
     }
 }
 
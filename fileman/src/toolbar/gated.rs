@@ -0,0 +1,72 @@
+//! Toolbar buttons that only apply some of the time
+//!
+//! While its condition isn't met, a button is drawn faded and gets no input, so
+//! it can be neither hovered nor pressed.
+
+use async_trait::async_trait;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::theme::ColorRole;
+use nptk::core::vg::kurbo::{Affine, Rect, Shape};
+use nptk::core::vg::peniko::{Brush, Fill};
+use nptk::prelude::*;
+use nptk_fileman_widgets::color_scheme;
+
+/// How much of a disabled button shows through
+const DISABLED_OPACITY: f32 = 0.4;
+
+/// A toolbar button that is enabled while `enabled` is set.
+pub struct GatedButton {
+    inner: ToolbarButton,
+    enabled: StateSignal<bool>,
+}
+
+impl GatedButton {
+    pub fn new(inner: ToolbarButton, enabled: StateSignal<bool>) -> Self {
+        Self { inner, enabled }
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for GatedButton {
+    fn layout_style(&self, context: &nptk::core::layout::LayoutContext) -> nptk::core::layout::StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(
+        &mut self,
+        layout: &nptk::core::layout::LayoutNode,
+        context: nptk::core::app::context::AppContext,
+        info: &mut nptk::core::app::info::AppInfo,
+    ) -> Update {
+        if !*self.enabled.get() {
+            return Update::empty();
+        }
+        self.inner.update(layout, context, info).await
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn nptk::core::vgi::Graphics,
+        layout: &nptk::core::layout::LayoutNode,
+        info: &mut nptk::core::app::info::AppInfo,
+        context: nptk::core::app::context::AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context.clone());
+        if *self.enabled.get() {
+            return;
+        }
+        let rect = Rect::new(
+            layout.layout.location.x as f64,
+            layout.layout.location.y as f64,
+            (layout.layout.location.x + layout.layout.size.width) as f64,
+            (layout.layout.location.y + layout.layout.size.height) as f64,
+        );
+        graphics.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            &Brush::Solid(color_scheme::color(context.palette(), ColorRole::Window).with_alpha(1.0 - DISABLED_OPACITY)),
+            None,
+            &rect.to_path(0.1),
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Transfer queue manager
+//!
+//! Lists the jobs of the background operation daemon (see [crate::daemon]) with
+//! controls to reorder, prioritize, pause, resume, cancel and retry them. The
+//! listing follows the jobs polled by the window's transfer monitor. Pausing
+//! and cancelling take effect within the file being copied; a cancelled copy
+//! doesn't leave the item it was copying behind half done.
+
+use crate::bus::AppBus;
+use crate::daemon::{self, JobInfo, JobState};
+use crate::operations::activity::RecentActivityDialog;
+use crate::operations::throttle::{self, SpeedLimitDialog};
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk_fileman_widgets::sizes;
+use std::sync::{Arc, Mutex};
+
+/// A button pressed in the dialog, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueueRequest {
+    /// Move a waiting job this many places (negative is sooner)
+    Move(u32, i32),
+    Prioritize(u32),
+    Pause(u32),
+    Resume(u32),
+    Cancel(u32),
+    Retry(u32),
+    ClearFinished,
+    SpeedLimits,
+    RecentActivity,
+}
+
+/// Popup listing queued, running, paused and finished transfers.
+pub struct TransferQueueDialog {
+    inner: Container,
+    jobs: Arc<Mutex<Vec<JobInfo>>>,
+    // Jobs the rows were built for
+    shown: Option<Vec<JobInfo>>,
+    request: Arc<Mutex<Option<QueueRequest>>>,
+    bus: AppBus,
+}
+
+impl TransferQueueDialog {
+    pub fn new(
+        jobs: Arc<Mutex<Vec<JobInfo>>>,
+        bus: AppBus,
+    ) -> Self {
+        let mut dialog = Self {
+            inner: Container::new(vec![]),
+            jobs,
+            shown: None,
+            request: Arc::new(Mutex::new(None)),
+            bus,
+        };
+        dialog.refresh();
+        dialog
+    }
+
+    /// Show the dialog as a popup.
+    pub fn show(self, context: &AppContext) {
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self), "Transfers", (640, 420), (200, 150));
+    }
+
+    fn button(&self, label: &str, request: QueueRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn state_label(job: &JobInfo) -> String {
+        let label = match &job.state {
+            JobState::Queued => "Waiting".to_string(),
+            JobState::Running => job.progress(),
+            JobState::Paused => format!("Paused at {}", job.progress()),
+            JobState::Conflict { .. } => "Waiting for an answer".to_string(),
+            JobState::Error { .. } => "Waiting for an answer about an error".to_string(),
+            JobState::Finished => format!("Finished: {}", job.counts()),
+            JobState::Cancelled => format!("Cancelled: {}", job.counts()),
+            JobState::Failed(e) => format!("Failed: {} ({})", e, job.counts()),
+        };
+        if job.speed_limit != throttle::UNLIMITED && job.state.is_active() {
+            format!(
+                "{} (limited to {}/s)",
+                label,
+                sizes::format(job.speed_limit)
+            )
+        } else {
+            label
+        }
+    }
+
+    fn job_row(&self, job: &JobInfo) -> Container {
+        let id = job.id;
+        let mut buttons: Vec<Box<dyn Widget>> = Vec::new();
+        match job.state {
+            JobState::Queued => {
+                buttons.push(Box::new(self.button("↑", QueueRequest::Move(id, -1))));
+                buttons.push(Box::new(self.button("↓", QueueRequest::Move(id, 1))));
+                buttons.push(Box::new(
+                    self.button("Run Next", QueueRequest::Prioritize(id)),
+                ));
+                buttons.push(Box::new(self.button("Pause", QueueRequest::Pause(id))));
+            },
+            JobState::Running => {
+                buttons.push(Box::new(self.button("Pause", QueueRequest::Pause(id))))
+            },
+            JobState::Paused => {
+                buttons.push(Box::new(self.button("Resume", QueueRequest::Resume(id))))
+            },
+            JobState::Failed(_) | JobState::Cancelled => {
+                buttons.push(Box::new(self.button("Retry", QueueRequest::Retry(id))))
+            },
+            JobState::Conflict { .. } | JobState::Error { .. } | JobState::Finished => {},
+        }
+        if job.state.is_active() {
+            buttons.push(Box::new(self.button("Cancel", QueueRequest::Cancel(id))));
+        }
+
+        Container::new(vec![
+            Box::new(
+                Container::new(vec![
+                    Box::new(Text::new(job.description.clone()).with_font_size(14.0)),
+                    Box::new(Text::new(Self::state_label(job)).with_font_size(12.0)),
+                ])
+                .with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Column,
+                    flex_grow: 1.0,
+                    ..Default::default()
+                }),
+            ),
+            Box::new(Container::new(buttons).with_layout_style(LayoutStyle {
+                flex_direction: FlexDirection::Row,
+                gap: Vector2::new(LengthPercentage::length(4.0), LengthPercentage::length(0.0)),
+                ..Default::default()
+            })),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Row,
+            align_items: Some(AlignItems::Center),
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            ..Default::default()
+        })
+    }
+
+    /// Rebuild the rows if the jobs changed since they were built. Returns whether they did.
+    fn refresh(&mut self) -> bool {
+        let jobs = self
+            .jobs
+            .lock()
+            .map(|jobs| jobs.clone())
+            .unwrap_or_default();
+        if self.shown.as_ref() == Some(&jobs) {
+            return false;
+        }
+
+        let mut rows: Vec<Box<dyn Widget>> = Vec::new();
+        if jobs.is_empty() {
+            rows.push(Box::new(
+                Text::new("No transfers".to_string()).with_font_size(14.0),
+            ));
+        }
+        for job in &jobs {
+            rows.push(Box::new(self.job_row(job)));
+        }
+        rows.push(Box::new(
+            Container::new(vec![
+                Box::new(self.button("Recent Activity...", QueueRequest::RecentActivity)),
+                Box::new(self.button("Speed Limits...", QueueRequest::SpeedLimits)),
+                Box::new(self.button("Clear Finished", QueueRequest::ClearFinished)),
+                Box::new(
+                    Button::new(Text::new("Close".to_string()))
+                        .with_on_pressed(MaybeSignal::value(Update::DRAW)),
+                ),
+            ])
+            .with_layout_style(LayoutStyle {
+                flex_direction: FlexDirection::Row,
+                gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                justify_content: Some(JustifyContent::FlexEnd),
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                ..Default::default()
+            }),
+        ));
+
+        self.inner = Container::new(rows).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(
+                LengthPercentage::length(0.0),
+                LengthPercentage::length(10.0),
+            ),
+            ..Default::default()
+        });
+        self.shown = Some(jobs);
+        true
+    }
+
+    /// Send a job request to the daemon, reporting failures in the status bar.
+    fn send(&self, request: QueueRequest) {
+        let bus = self.bus.clone();
+        tokio::spawn(async move {
+            let result = match request {
+                QueueRequest::Move(id, steps) => daemon::client::move_job(id, steps).await,
+                QueueRequest::Prioritize(id) => daemon::client::move_job(id, i32::MIN).await,
+                QueueRequest::Pause(id) => daemon::client::pause(id).await,
+                QueueRequest::Resume(id) => daemon::client::resume(id).await,
+                QueueRequest::Cancel(id) => daemon::client::cancel(id).await,
+                QueueRequest::Retry(id) => daemon::client::retry(id).await.map(|_| ()),
+                QueueRequest::ClearFinished => daemon::client::clear_finished().await,
+                QueueRequest::SpeedLimits | QueueRequest::RecentActivity => Ok(()),
+            };
+            if let Err(e) = result {
+                log::error!("{}", e);
+                bus.status(format!("Error: {}", e));
+            }
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for TransferQueueDialog {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(
+        &mut self,
+        layout: &LayoutNode,
+        context: AppContext,
+        info: &mut AppInfo,
+    ) -> Update {
+        let mut update = Update::empty();
+
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        match request {
+            Some(QueueRequest::SpeedLimits) => {
+                let jobs = self.shown.clone().unwrap_or_default();
+                SpeedLimitDialog::new(&jobs).show(&context);
+                update.insert(Update::DRAW);
+            },
+            Some(QueueRequest::RecentActivity) => {
+                RecentActivityDialog::new().show(&context);
+                update.insert(Update::DRAW);
+            },
+            Some(request) => self.send(request),
+            None => {},
+        }
+
+        if self.refresh() {
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,79 @@
+//! Automatic trash cleanup
+//!
+//! When enabled in the Preferences, items trashed more than a number of days
+//! ago are deleted permanently, and so are the oldest items while the trash is
+//! larger than a limit. The cleanup runs shortly after start and then every
+//! hour; a notification tells how much was freed.
+
+use crate::feedback;
+use crate::operations::trash;
+use crate::settings;
+use nptk_fileman_widgets::sizes;
+use std::time::Duration;
+
+/// Wait after start, so the cleanup doesn't slow down opening the window
+const FIRST_RUN_DELAY: Duration = Duration::from_secs(30);
+const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Choices offered in the Preferences, 0 turning the limit off
+pub const AGE_CHOICES: [u32; 5] = [0, 7, 30, 90, 365];
+pub const SIZE_CHOICES: [u64; 5] = [0, 1, 5, 10, 50];
+
+/// The choice after `current`, for cycling through them with one button.
+pub fn next_choice<T: Copy + PartialEq>(choices: &[T], current: T) -> T {
+    let next = choices.iter().position(|choice| *choice == current).map_or(0, |i| i + 1);
+    choices[next % choices.len()]
+}
+
+pub fn age_label(days: u32) -> String {
+    match days {
+        0 => "Never".to_string(),
+        1 => "After 1 day".to_string(),
+        days => format!("After {} days", days),
+    }
+}
+
+pub fn size_label(gib: u64) -> String {
+    match gib {
+        0 => "No limit".to_string(),
+        gib => format!("{} GiB", gib),
+    }
+}
+
+/// Clean up the trash as set in the Preferences, now and then.
+pub fn spawn() {
+    tokio::spawn(async {
+        tokio::time::sleep(FIRST_RUN_DELAY).await;
+        loop {
+            run().await;
+            tokio::time::sleep(INTERVAL).await;
+        }
+    });
+}
+
+async fn run() {
+    let settings = settings::get();
+    let max_age_days = Some(settings.trash_max_age_days).filter(|days| *days > 0);
+    let max_bytes = Some(settings.trash_max_size_gib)
+        .filter(|gib| *gib > 0)
+        .map(|gib| gib.saturating_mul(1024 * 1024 * 1024));
+    if max_age_days.is_none() && max_bytes.is_none() {
+        return;
+    }
+    let summary = match tokio::task::spawn_blocking(move || trash::purge(max_age_days, max_bytes)).await {
+        Ok(summary) => summary,
+        Err(e) => {
+            log::warn!("Trash cleanup failed: {}", e);
+            return;
+        },
+    };
+    if summary.items > 0 {
+        let body = format!(
+            "Permanently deleted {} old item(s), freeing {}",
+            summary.items,
+            sizes::format(summary.bytes)
+        );
+        log::info!("Trash cleanup: {}", body);
+        feedback::inform("Trash cleaned up", body);
+    }
+}
@@ -0,0 +1,37 @@
+//! Percent-encoding for `file://` URIs, `.trashinfo` paths and HTTP headers
+
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Percent-encode every byte but unreserved characters (and `/` when
+/// `keep_slash`).
+pub fn percent_encode(bytes: &[u8], keep_slash: bool) -> String {
+    let mut encoded = String::new();
+    for &byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Percent-encode the raw bytes of `path`, keeping `/`, so names that aren't
+/// UTF-8 decode to the same path again.
+pub fn encode_path(path: &Path) -> String {
+    percent_encode(path.as_os_str().as_bytes(), true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn encodes_the_raw_bytes_of_paths() {
+        assert_eq!(encode_path(Path::new("/home/me/a b+é")), "/home/me/a%20b%2B%C3%A9");
+        assert_eq!(encode_path(Path::new(OsStr::from_bytes(b"/tmp/\xff"))), "/tmp/%FF");
+        assert_eq!(percent_encode(b"a/b", false), "a%2Fb");
+    }
+}
@@ -0,0 +1,240 @@
+//! View settings of folders
+//!
+//! Stored as a section per folder in `$XDG_STATE_HOME/fileman/view-settings`,
+//! holding the settings that differ from the defaults:
+//!
+//! ```text
+//! [/home/user/Pictures]
+//! zoom_icon = 2
+//! thumbnails = false
+//! ```
+//!
+//! The file is read on first use. Changes are kept in memory at once and
+//! written on a blocking thread. Keys this version doesn't know are kept.
+//!
+//! Whether thumbnails are generated is also decided here: a folder turning them
+//! on or off does so for its subfolders too, other folders follow [Thumbnails]
+//! from the Preferences.
+
+use crate::devices;
+use nptk_fileman_widgets::file_list::{FileListViewMode, FileListZoom};
+use nptk_fileman_widgets::xdg;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+/// Settings of every folder that has some, loaded on first use
+static CURRENT: RwLock<Option<BTreeMap<PathBuf, ViewSettings>>> = RwLock::new(None);
+
+/// Held while the file is written, so an older state can't overwrite a newer one
+static SAVING: Mutex<()> = Mutex::new(());
+
+/// Zoom keys, by the view mode they are for
+const ZOOM_KEYS: [(&str, FileListViewMode); 3] = [
+    ("zoom_list", FileListViewMode::List),
+    ("zoom_icon", FileListViewMode::Icon),
+    ("zoom_table", FileListViewMode::Table),
+];
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ViewSettings {
+    /// Zoom level of each view mode
+    pub zoom: FileListZoom,
+    /// Whether thumbnails are generated here and below; `None` follows the
+    /// folders above and the Preferences
+    pub thumbnails: Option<bool>,
+    unknown: BTreeMap<String, String>,
+}
+
+/// Where thumbnails are generated, unless a folder turns them on or off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Thumbnails {
+    #[default]
+    Everywhere,
+    /// Not on network shares, where reading whole files is slow
+    LocalOnly,
+    Nowhere,
+}
+
+impl Thumbnails {
+    /// Name used in the settings file
+    pub fn key(self) -> &'static str {
+        match self {
+            Thumbnails::Everywhere => "everywhere",
+            Thumbnails::LocalOnly => "local-only",
+            Thumbnails::Nowhere => "nowhere",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "everywhere" => Some(Thumbnails::Everywhere),
+            "local-only" => Some(Thumbnails::LocalOnly),
+            "nowhere" => Some(Thumbnails::Nowhere),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Thumbnails::Everywhere => "Everywhere",
+            Thumbnails::LocalOnly => "Not on Network Shares",
+            Thumbnails::Nowhere => "Nowhere",
+        }
+    }
+
+    /// The next choice, for cycling through them with one button
+    pub fn next(self) -> Self {
+        match self {
+            Thumbnails::Everywhere => Thumbnails::LocalOnly,
+            Thumbnails::LocalOnly => Thumbnails::Nowhere,
+            Thumbnails::Nowhere => Thumbnails::Everywhere,
+        }
+    }
+}
+
+fn state_path() -> PathBuf {
+    xdg::state_home().join("fileman").join("view-settings")
+}
+
+fn load() -> BTreeMap<PathBuf, ViewSettings> {
+    let Ok(content) = std::fs::read_to_string(state_path()) else {
+        return BTreeMap::new();
+    };
+    let mut folders = BTreeMap::new();
+    let mut current: Option<(PathBuf, ViewSettings)> = None;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(folder) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if let Some((folder, settings)) = current.take() {
+                folders.insert(folder, settings);
+            }
+            current = Some((PathBuf::from(folder), ViewSettings::default()));
+            continue;
+        }
+        let (Some((key, value)), Some((_, settings))) = (line.split_once('='), current.as_mut()) else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match ZOOM_KEYS.iter().find(|(name, _)| *name == key) {
+            Some((_, mode)) => match value.parse() {
+                Ok(level) => settings.zoom.set_level(*mode, level),
+                Err(_) => log::warn!("Ignoring invalid {} = {}", key, value),
+            },
+            None if key == "thumbnails" => match value.parse() {
+                Ok(enabled) => settings.thumbnails = Some(enabled),
+                Err(_) => log::warn!("Ignoring invalid {} = {}", key, value),
+            },
+            None => {
+                settings.unknown.insert(key.to_string(), value.to_string());
+            },
+        }
+    }
+    if let Some((folder, settings)) = current {
+        folders.insert(folder, settings);
+    }
+    folders
+}
+
+fn save() -> Result<(), String> {
+    let _saving = SAVING.lock().map_err(|_| "Failed to save view settings".to_string())?;
+    let mut content = String::new();
+    if let Some(folders) = CURRENT.read().ok().and_then(|current| current.clone()) {
+        for (folder, settings) in &folders {
+            content.push_str(&format!("[{}]\n", folder.display()));
+            for (key, mode) in ZOOM_KEYS {
+                let level = settings.zoom.level(mode);
+                if level != 0 {
+                    content.push_str(&format!("{} = {}\n", key, level));
+                }
+            }
+            if let Some(enabled) = settings.thumbnails {
+                content.push_str(&format!("thumbnails = {}\n", enabled));
+            }
+            for (key, value) in &settings.unknown {
+                content.push_str(&format!("{} = {}\n", key, value));
+            }
+        }
+    }
+    let path = state_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to save view settings: {}", e))?;
+    }
+    std::fs::write(&path, content).map_err(|e| format!("Failed to save view settings: {}", e))
+}
+
+/// The settings of `folder`, the defaults if it has none.
+pub fn get(folder: &Path) -> ViewSettings {
+    if let Ok(current) = CURRENT.read() {
+        if let Some(folders) = current.as_ref() {
+            return folders.get(folder).cloned().unwrap_or_default();
+        }
+    }
+    let folders = load();
+    let settings = folders.get(folder).cloned().unwrap_or_default();
+    if let Ok(mut current) = CURRENT.write() {
+        current.get_or_insert(folders);
+    }
+    settings
+}
+
+/// Whether thumbnails are generated in `folder`: as set for it or the nearest
+/// folder above, otherwise as `default` says. Reads the mount table for
+/// [Thumbnails::LocalOnly].
+pub fn thumbnails(folder: &Path, default: Thumbnails) -> bool {
+    // Loads the file on first use
+    get(folder);
+    let set = CURRENT.read().ok().and_then(|current| {
+        let folders = current.as_ref()?;
+        folder.ancestors().find_map(|dir| folders.get(dir)?.thumbnails)
+    });
+    set.unwrap_or_else(|| match default {
+        Thumbnails::Everywhere => true,
+        Thumbnails::LocalOnly => !devices::is_network_share(folder),
+        Thumbnails::Nowhere => false,
+    })
+}
+
+/// Change the settings of `folder` with `change` and save them in the background.
+pub fn update(folder: &Path, change: impl FnOnce(&mut ViewSettings)) {
+    let mut settings = get(folder);
+    change(&mut settings);
+    let Ok(mut current) = CURRENT.write() else {
+        return;
+    };
+    let folders = current.get_or_insert_with(BTreeMap::new);
+    if settings == ViewSettings::default() {
+        folders.remove(folder);
+    } else {
+        folders.insert(folder.to_path_buf(), settings);
+    }
+    drop(current);
+    save_later();
+}
+
+/// Forget the zoom of every folder (Reset Layout) and save in the background.
+pub fn reset_zoom() {
+    // Loads the file first, so folders not shown yet are reset too
+    get(Path::new("/"));
+    let Ok(mut current) = CURRENT.write() else {
+        return;
+    };
+    if let Some(folders) = current.as_mut() {
+        for settings in folders.values_mut() {
+            settings.zoom = FileListZoom::default();
+        }
+        folders.retain(|_, settings| *settings != ViewSettings::default());
+    }
+    drop(current);
+    save_later();
+}
+
+fn save_later() {
+    tokio::task::spawn_blocking(|| {
+        if let Err(e) = save() {
+            log::warn!("{}", e);
+        }
+    });
+}
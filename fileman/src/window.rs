@@ -1,89 +1,1521 @@
 use nptk::prelude::*;
 use async_trait::async_trait;
-use nptk::core::signal::eval::EvalSignal;
 use nptk::core::shortcut::{Shortcut, ShortcutRegistry};
 use nptk::core::window::KeyCode;
-use nptk_fileman_widgets::file_list::{FileList, FileListOperation};
+use nptk_fileman_widgets::file_list::{FileList, FileListOperation, FileListViewMode, open_with_app};
 use nptk_fileman_widgets::FilemanSidebar;
 use nptk::widgets::breadcrumbs::{Breadcrumbs, BreadcrumbItem};
+use crate::activation;
 use crate::app::AppState;
+use crate::bus::{AppBus, AppCommand, Subscription};
+use crate::clipboard::{self, ClipboardAction, ClipboardFiles};
+use crate::daemon::{self, JobInfo, JobState};
+use crate::devices::{self, Device, UnlockDialog, WriteImageDialog};
+use crate::feedback;
+use crate::dbus::ExternalRequest;
+use crate::folder_sizes::FolderSizeTracker;
+use crate::git_status::GitStatusTracker;
+use crate::links::{self, EditLinkDialog, LinkActions, LinkRequest};
+use crate::menus;
+use crate::navigation::TabView;
 use crate::operations;
+use crate::operations::permissions::PermissionsDialog;
+use crate::operations::undo::{UndoRecord, UndoStack};
+use crate::owners;
+use crate::palette::{CommandPalette, PaletteTarget};
+use crate::plugins;
+use crate::read_only;
+use crate::search::Search;
+use crate::transfer_queue::TransferQueueDialog;
+use crate::remote::{self, Remote};
+use crate::settings::{self, PreferencesDialog};
+use crate::startup;
+use crate::templates;
+use crate::trash_cleanup;
+use crate::view_settings;
+use crate::window_state::{self, WindowState, WindowStateTracker};
+use crate::send_to::{SendTo, ShareDialog};
+use nptk::core::menu::{MenuCommand, MenuItem};
+use nptk_fileman_widgets::conflict_dialog::{ConflictDialog, ConflictDialogHandle};
+use nptk_fileman_widgets::commands::CommandQueue;
+use nptk_fileman_widgets::error_dialog::{ErrorDialog, ErrorDialogHandle};
+use nptk_fileman_widgets::file_chooser::{FileChooser, FileChooserHandle, FileChooserMode, FileChooserResponse};
+use nptk_fileman_widgets::file_names;
+use nptk_fileman_widgets::rename_dialog::{RenameDialog, RenameDialogHandle};
+use nptk_fileman_widgets::{dir_size_cache, directory_service, xdg};
+use nptk_fileman_widgets::focus::{FocusChain, PaneKey};
+use nptk_fileman_widgets::status_bar::Toast;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use tokio::sync::mpsc;
 
+/// How long to wait for the daemon to record a trashing before telling whether
+/// a vanished folder can be restored (jobs are recorded once they are done)
+const TRASH_RECORD_GRACE: Duration = Duration::from_millis(500);
+
 /// File operation requests that can be sent from UI to be processed
 #[derive(Debug, Clone)]
 pub enum FileOperationRequest {
+    /// Open items: a single folder is entered, files are opened as configured
+    /// for their type
+    Open(Vec<PathBuf>),
+    /// Open items with the application `app_id`
+    OpenWith { paths: Vec<PathBuf>, app_id: String },
+    /// Put items on the clipboard to be copied or moved
+    SetClipboard { paths: Vec<PathBuf>, action: ClipboardAction },
+    /// Open the folders items shown outside of them are in (search results,
+    /// trashed items) and select the items there
+    OpenLocation(Vec<PathBuf>),
+    /// Open the folder `paths` are in, which [FileOperationRequest::OpenLocation]
+    /// found to exist, and select them there
+    Reveal(Vec<PathBuf>),
+    /// Put the paths of items on the clipboard as text, one per line; with
+    /// `names_only` just their names
+    CopyPaths { paths: Vec<PathBuf>, names_only: bool },
+    /// Copy or move the files on the clipboard into `destination` (or next to
+    /// it if it is a file); with `hard_links`, link them there instead
+    Paste { destination: PathBuf, hard_links: bool },
+    /// Copy items, folders with their contents, into the folder `destination`;
+    /// copies into their own folder are named "name (2)"
+    Copy { sources: Vec<PathBuf>, destination: PathBuf },
+    /// Move items into the folder `destination`
+    Move { sources: Vec<PathBuf>, destination: PathBuf },
+    /// Ask for a folder to copy (or with `move_items`, move) items into
+    ChooseDestination { sources: Vec<PathBuf>, move_items: bool },
+    /// Move items to the trash without asking, as it can be undone; items
+    /// already in a trash are deleted permanently, asking first
+    Trash(Vec<PathBuf>),
+    /// Delete permanently, asking for confirmation first
     Delete(Vec<PathBuf>),
+    /// Queued in the background operation daemon without asking again
+    DeleteConfirmed(Vec<PathBuf>),
+    /// Create a folder named "New Folder", numbered if that is taken
+    NewFolder(PathBuf),
     CreateDirectory { parent: PathBuf, name: String },
+    /// Create a file in `parent` as a copy of `template`
+    CreateFromTemplate { template: PathBuf, parent: PathBuf },
+    /// Ask for a new name for an item, then rename it
+    AskRename(PathBuf),
     Rename { from: PathBuf, to: PathBuf },
     Properties(Vec<PathBuf>),
-    // Future: Copy, Move, etc.
+    /// Ask for the passphrase of an encrypted volume or vault
+    Unlock(Device),
+    /// `Device` was mounted since the device monitor last looked
+    Mounted(Device),
+    /// Show the transfer queue
+    Transfers,
+    /// Show the Preferences dialog
+    Preferences,
+    /// Bring panels and zoom levels back to their defaults
+    ResetLayout,
+    /// Turn generating thumbnails in the shown folder and its subfolders on or off
+    ToggleFolderThumbnails,
+    /// Show or hide hidden files in the current tab
+    ToggleHiddenFiles,
+    /// Switch to the next view mode
+    CycleView,
+    /// Show the command palette
+    CommandPalette,
+    /// Re-read the shown folder; a deep refresh also drops what is cached about it
+    Refresh { deep: bool },
+    /// Re-read the shown folder and select `paths` once they are listed, such
+    /// as an item renamed or created in the background
+    Select(Vec<PathBuf>),
+    /// Unmount and power off a removable device; `None` for the one the
+    /// current folder is on
+    Eject(Option<Device>),
+    /// Pack items into an archive next to them
+    Compress(Vec<PathBuf>),
+    /// Switch the permission bits in `set` on and those in `clear` off, keeping the others
+    SetPermissions { paths: Vec<PathBuf>, set: u32, clear: u32 },
+    /// Choose a saved script to run against items
+    RunScript(Vec<PathBuf>),
+    /// Revert the last rename, move, new folder or trashing of the window
+    Undo,
+    /// Carry out the last undone operation again
+    Redo,
+}
+
+impl From<FileListOperation> for FileOperationRequest {
+    fn from(operation: FileListOperation) -> Self {
+        match operation {
+            FileListOperation::Open(paths) => FileOperationRequest::Open(paths),
+            FileListOperation::OpenWith { paths, app_id } => FileOperationRequest::OpenWith { paths, app_id },
+            FileListOperation::Copy(paths) => FileOperationRequest::SetClipboard {
+                paths,
+                action: ClipboardAction::Copy,
+            },
+            FileListOperation::Move(paths) => FileOperationRequest::SetClipboard {
+                paths,
+                action: ClipboardAction::Cut,
+            },
+            FileListOperation::Trash(paths) => FileOperationRequest::Trash(paths),
+            FileListOperation::Delete(paths) => FileOperationRequest::DeleteConfirmed(paths),
+            FileListOperation::Rename { from, to } => FileOperationRequest::Rename { from, to },
+            FileListOperation::Compress(paths) => FileOperationRequest::Compress(paths),
+            FileListOperation::Properties(paths) => FileOperationRequest::Properties(paths),
+            FileListOperation::SetPermissions { paths, set, clear } => {
+                FileOperationRequest::SetPermissions { paths, set, clear }
+            },
+        }
+    }
+}
+
+/// An item to open or show, once it is known whether it is a folder
+enum OpenTarget {
+    Folder(PathBuf),
+    File(PathBuf),
+    /// A folder to open in a new tab
+    Tab(PathBuf),
+    /// An item to show in its folder
+    Item(PathBuf),
+    /// Items of one folder to show, in a new tab unless the active one shows
+    /// the folder, and with `properties` in the properties dialog
+    Items { items: Vec<PathBuf>, properties: bool },
+}
+
+/// A folder whose device was unmounted while it was shown. The tab returns to
+/// it once the device is mounted again, unless another place was opened.
+struct LostMount {
+    device: Device,
+    path: PathBuf,
+    /// Folder shown instead meanwhile
+    shown: PathBuf,
 }
 
 /// Wrapper widget that manages FileList and connects it to navigation state
 struct FileListWrapper {
     file_list: FileList,
     navigation: Arc<Mutex<crate::navigation::NavigationState>>,
-    navigation_rx: Option<mpsc::UnboundedReceiver<PathBuf>>,
     // Reactive signals - cloned from NavigationState and FileList
     navigation_path_signal: StateSignal<PathBuf>,
     file_list_path_signal: StateSignal<PathBuf>,
+    // Whether the list shows a saved listing of a slow location, and the last value reported
+    listing_stale_signal: StateSignal<bool>,
+    listing_stale_reported: bool,
     signals_hooked: bool,
     // File operation processing - receives from FileList widget (already confirmed)
     file_list_operation_rx: Option<mpsc::UnboundedReceiver<FileListOperation>>,
-    // File operation processing - receives from toolbar/other UI (needs confirmation)
-    operation_rx: Option<mpsc::UnboundedReceiver<FileOperationRequest>>,
-    // Status message sender (for displaying operation results)
-    status_tx: Option<mpsc::UnboundedSender<String>>,
-    // Pending delete operations waiting for confirmation (from toolbar)
-    pending_delete_confirmation: Arc<Mutex<Option<Vec<PathBuf>>>>,
+    // File operations are carried out here, whoever asked for them on the bus
+    bus: AppBus,
+    bus_commands: Subscription<AppCommand>,
+    // Deletions confirmed in the confirmation dialog
+    delete_confirmations: CommandQueue<Vec<PathBuf>>,
+    // Requests from other applications (D-Bus FileManager1)
+    external_rx: Option<mpsc::UnboundedReceiver<ExternalRequest>>,
+    // Jobs of the background operation daemon, refreshed by the transfer monitor
+    transfer_jobs: Arc<Mutex<Vec<JobInfo>>>,
+    // Conflict dialogs shown for daemon jobs, by job id and destination (None once answered)
+    transfer_conflicts: HashMap<(u32, PathBuf), Option<ConflictDialogHandle>>,
+    // Error dialogs shown for failed items of daemon jobs, by job id and item (None once answered)
+    transfer_errors: HashMap<(u32, PathBuf), Option<ErrorDialogHandle>>,
+    // Number of finished daemon jobs seen, to refresh the listing when one completes
+    finished_transfers: usize,
+    // Git statuses of the shown folder and the folder they were requested for
+    git_status: GitStatusTracker,
+    git_status_path: Option<PathBuf>,
+    // Folder sizes for the Size column, when they are enabled
+    folder_sizes: Option<FolderSizeTracker>,
+    dim_ignored: StateSignal<bool>,
+    // Selection to change permissions of, set from the context menu
+    permissions_request: Arc<Mutex<Option<Vec<PathBuf>>>>,
+    // File double-clicked in the list, handled as configured for its type
+    activation_request: Arc<Mutex<Option<PathBuf>>>,
+    // Folder to link the clipboard files into, chosen from the context menu
+    paste_links_request: Arc<Mutex<Option<PathBuf>>>,
+    // Set by Refresh in the context menu
+    refresh_requested: Arc<Mutex<bool>>,
+    // Disk image to write to a drive, chosen from the context menu
+    write_image_request: Arc<Mutex<Option<PathBuf>>>,
+    // Tab whose view settings the list shows
+    shown_tab: Option<u64>,
+    // Folder whose saved zoom the list shows
+    zoomed_folder: Option<PathBuf>,
+    // Folder the thumbnail setting was applied for, and whether they are shown there
+    thumbnails_folder: Option<PathBuf>,
+    thumbnails: StateSignal<bool>,
+    // Devices of the sidebar, to find the one the current folder is on
+    devices: Arc<Mutex<Vec<Device>>>,
+    // Device the shown folder is on while it is mounted
+    shown_mount: Option<Device>,
+    lost_mount: Option<LostMount>,
+    send_to: SendTo,
+    // Link actions chosen from the context menu
+    link_actions: LinkActions,
+    // Items to open, sorted into folders and files in the background
+    open_tx: mpsc::UnboundedSender<OpenTarget>,
+    open_rx: mpsc::UnboundedReceiver<OpenTarget>,
+    // Text of the search field, the query searched for (empty for none), the
+    // folder searched and whether its results were shown yet
+    search_query: StateSignal<String>,
+    search: Search,
+    searched_query: String,
+    search_root: PathBuf,
+    search_shown: bool,
+    // Remotes, to tell whether their local copies may be changed
+    remotes: Arc<Vec<Remote>>,
+    // Why the shown folder can't be changed (None if it can), whether it can,
+    // and the answer of the background check for a folder
+    read_only: Option<String>,
+    writable: StateSignal<bool>,
+    read_only_check: Arc<Mutex<Option<(PathBuf, Option<String>)>>>,
+    // Folder chooser of Copy To/Move To, with the items and whether to move them
+    destination_chooser: Option<(FileChooserHandle, Vec<PathBuf>, bool)>,
+    rename_dialog: Option<RenameDialogHandle>,
+    // Operations to undo and redo, and daemon jobs queued here whose records
+    // are added to it once they are done
+    undo: Arc<Mutex<UndoStack>>,
+    undoable_jobs: Arc<Mutex<Vec<u32>>>,
 }
 
 impl FileListWrapper {
     fn new(
         initial_path: PathBuf,
         navigation: Arc<Mutex<crate::navigation::NavigationState>>,
-        navigation_rx: mpsc::UnboundedReceiver<PathBuf>,
-        operation_rx: mpsc::UnboundedReceiver<FileOperationRequest>,
-        status_tx: mpsc::UnboundedSender<String>,
+        bus: AppBus,
         navigation_path_signal: StateSignal<PathBuf>,
+        undo: Arc<Mutex<UndoStack>>,
     ) -> Self {
         // Create channel for FileList operations
         let (file_list_op_tx, file_list_op_rx) = mpsc::unbounded_channel::<FileListOperation>();
         
         // Create FileList (selection_change_tx is optional for backward compatibility)
         let file_list = FileList::new_with_operations(initial_path.clone(), Some(file_list_op_tx), None);
+
+        // Git status badges (computed in the background for the shown folder)
+        let git_status = GitStatusTracker::new();
+        let file_list = file_list
+            .with_emblem_provider(git_status.emblem_provider())
+            .with_dim_provider(git_status.dim_provider());
+        #[cfg(feature = "git")]
+        let file_list = file_list.with_column(git_status.column());
+        let folder_sizes = settings::get().folder_size_column.then(FolderSizeTracker::new);
+        let file_list = match &folder_sizes {
+            Some(folder_sizes) => file_list.with_directory_sizes(folder_sizes.provider()),
+            None => file_list,
+        };
+        let file_list = if settings::get().link_target_column {
+            file_list.with_column(links::column())
+        } else {
+            file_list
+        };
+        let file_list = if settings::get().owner_columns {
+            file_list
+                .with_column(owners::owner_column())
+                .with_column(owners::group_column())
+        } else {
+            file_list
+        };
+        let file_list = if settings::get().permissions_column {
+            file_list.with_column(operations::permissions::column())
+        } else {
+            file_list
+        };
+        let file_list = plugins::columns()
+            .into_iter()
+            .fold(file_list, |file_list, column| file_list.with_column(column));
+
+        let permissions_request = Arc::new(Mutex::new(None));
+        let menu_permissions_request = permissions_request.clone();
+        let send_to = SendTo::new(bus.clone());
+        let menu_send_to = send_to.clone();
+        let link_actions = LinkActions::new();
+        let menu_link_actions = link_actions.clone();
+        let paste_links_request = Arc::new(Mutex::new(None));
+        let menu_paste_links_request = paste_links_request.clone();
+        let refresh_requested = Arc::new(Mutex::new(false));
+        let menu_refresh_requested = refresh_requested.clone();
+        let write_image_request = Arc::new(Mutex::new(None));
+        let menu_write_image_request = write_image_request.clone();
+        let menu_bus = bus.clone();
+        let menu_path_signal = file_list.current_path_signal().clone();
+        let menu_undo = undo.clone();
+        let file_list = file_list.with_on_context_menu(move |paths| {
+            let request = menu_permissions_request.clone();
+            let permission_paths = paths.to_vec();
+            let paste_links = menu_paste_links_request.clone();
+            let refresh = menu_refresh_requested.clone();
+            let script_bus = menu_bus.clone();
+            let script_paths = paths.to_vec();
+            let copy_bus = menu_bus.clone();
+            let copy_paths = paths.to_vec();
+            // Into the selected folder, otherwise next to the selection
+            let links_destination = match paths {
+                [path] => Some(path.clone()),
+                _ => paths.first().and_then(|p| p.parent()).map(PathBuf::from),
+            };
+            let mut items = menu_link_actions.menu_items(paths);
+            // The background menu stands in for an Edit menu
+            if paths.is_empty() {
+                if let Ok(stack) = menu_undo.lock() {
+                    let steps = [
+                        (menus::UNDO_ID, "Undo", stack.next_undo(), FileOperationRequest::Undo),
+                        (menus::REDO_ID, "Redo", stack.next_redo(), FileOperationRequest::Redo),
+                    ];
+                    for (id, verb, record, operation) in steps {
+                        let Some(record) = record else {
+                            continue;
+                        };
+                        let bus = copy_bus.clone();
+                        let label = format!("{} {}", verb, record.describe());
+                        items.push(MenuItem::new(MenuCommand::Custom(id), label).with_action(move || {
+                            bus.operation(operation.clone());
+                            Update::DRAW
+                        }));
+                    }
+                }
+            }
+            if let Some(destination) = &links_destination {
+                items.push(templates::menu_item(destination, &menu_bus));
+            }
+            // Items shown outside their folder, by a search or in the trash
+            let current = menu_path_signal.get();
+            let shown_elsewhere = !paths.is_empty()
+                && (operations::trash::is_trash_files(&current)
+                    || paths.iter().any(|path| path.parent() != Some(current.as_path())));
+            if shown_elsewhere {
+                let (bus, paths) = (copy_bus.clone(), copy_paths.clone());
+                items.push(MenuItem::new(MenuCommand::Custom(menus::OPEN_ITEM_LOCATION_ID), "Open Item Location").with_action(move || {
+                    bus.operation(FileOperationRequest::OpenLocation(paths.clone()));
+                    Update::DRAW
+                }));
+            }
+            if !paths.is_empty() {
+                let mut transfers = Vec::new();
+                // Duplicates go next to the items, so they must share a folder
+                let folder = paths[0].parent();
+                if let Some(folder) = folder.filter(|_| paths.iter().all(|p| p.parent() == folder)) {
+                    let sources = paths.to_vec();
+                    let destination = folder.to_path_buf();
+                    transfers.push((menus::DUPLICATE_ID, "Duplicate", FileOperationRequest::Copy { sources, destination }));
+                }
+                for (id, label, move_items) in [(menus::COPY_TO_ID, "Copy To...", false), (menus::MOVE_TO_ID, "Move To...", true)] {
+                    let sources = paths.to_vec();
+                    transfers.push((id, label, FileOperationRequest::ChooseDestination { sources, move_items }));
+                }
+                for (id, label, request) in transfers {
+                    let bus = copy_bus.clone();
+                    items.push(MenuItem::new(MenuCommand::Custom(id), label).with_action(move || {
+                        bus.operation(request.clone());
+                        Update::DRAW
+                    }));
+                }
+            }
+            if !paths.is_empty() {
+                for (id, label, names_only) in [(menus::COPY_PATH_ID, "Copy Path", false), (menus::COPY_NAME_ID, "Copy Name", true)] {
+                    let (bus, paths) = (copy_bus.clone(), copy_paths.clone());
+                    items.push(MenuItem::new(MenuCommand::Custom(id), label).with_action(move || {
+                        bus.operation(FileOperationRequest::CopyPaths { paths: paths.clone(), names_only });
+                        Update::DRAW
+                    }));
+                }
+            }
+            items.extend([
+                menu_send_to.menu_item(paths),
+                MenuItem::new(MenuCommand::Custom(menus::PASTE_HARD_LINKS_ID), "Paste as Hard Links").with_action(move || {
+                    if let Ok(mut request) = paste_links.lock() {
+                        *request = links_destination.clone();
+                    }
+                    Update::DRAW
+                }),
+                MenuItem::new(MenuCommand::Custom(menus::CHANGE_PERMISSIONS_ID), "Change Permissions...").with_action(move || {
+                    if let Ok(mut request) = request.lock() {
+                        *request = Some(permission_paths.clone());
+                    }
+                    Update::DRAW
+                }),
+                MenuItem::new(MenuCommand::Custom(menus::RUN_SCRIPT_ID), "Run Script...").with_action(move || {
+                    script_bus.operation(FileOperationRequest::RunScript(script_paths.clone()));
+                    Update::DRAW
+                }),
+                MenuItem::new(MenuCommand::Custom(menus::REFRESH_ID), "Refresh").with_action(move || {
+                    if let Ok(mut requested) = refresh.lock() {
+                        *requested = true;
+                    }
+                    Update::DRAW
+                }),
+            ]);
+            let plugin_items = plugins::menu_items(paths, &menu_bus);
+            if !plugin_items.is_empty() {
+                items.push(MenuItem::separator());
+                items.extend(plugin_items);
+            }
+            if let [image] = paths {
+                if devices::is_disk_image(image) {
+                    let request = menu_write_image_request.clone();
+                    let image = image.clone();
+                    items.push(MenuItem::new(MenuCommand::Custom(menus::WRITE_TO_DRIVE_ID), "Write to Drive...").with_action(move || {
+                        if let Ok(mut request) = request.lock() {
+                            *request = Some(image.clone());
+                        }
+                        Update::DRAW
+                    }));
+                }
+            }
+            items
+        });
         
+        let activation_request = Arc::new(Mutex::new(None));
+        let activated = activation_request.clone();
+        let file_list = file_list.with_on_activate(move |path| {
+            if let Ok(mut request) = activated.lock() {
+                *request = Some(path);
+            }
+            Update::DRAW
+        });
+
         // Clone signals from FileList for reactive subscription
         let file_list_path_signal = file_list.current_path_signal().clone();
+        let listing_stale_signal = file_list.listing_stale_signal().clone();
+        let (open_tx, open_rx) = mpsc::unbounded_channel();
         
         Self {
             file_list,
             navigation,
-            navigation_rx: Some(navigation_rx),
             navigation_path_signal,
             file_list_path_signal,
+            listing_stale_signal,
+            listing_stale_reported: false,
             signals_hooked: false,
             file_list_operation_rx: Some(file_list_op_rx),
-            operation_rx: Some(operation_rx),
-            status_tx: Some(status_tx),
-            pending_delete_confirmation: Arc::new(Mutex::new(None)),
+            bus_commands: bus.subscribe(),
+            bus,
+            delete_confirmations: CommandQueue::new(),
+            external_rx: None,
+            transfer_jobs: Arc::new(Mutex::new(Vec::new())),
+            transfer_conflicts: HashMap::new(),
+            transfer_errors: HashMap::new(),
+            finished_transfers: 0,
+            git_status,
+            git_status_path: None,
+            folder_sizes,
+            dim_ignored: StateSignal::new(false),
+            permissions_request,
+            activation_request,
+            paste_links_request,
+            refresh_requested,
+            write_image_request,
+            shown_tab: None,
+            zoomed_folder: None,
+            thumbnails_folder: None,
+            thumbnails: StateSignal::new(true),
+            devices: Arc::new(Mutex::new(Vec::new())),
+            shown_mount: None,
+            lost_mount: None,
+            send_to,
+            link_actions,
+            open_tx,
+            open_rx,
+            search_query: StateSignal::new(String::new()),
+            search: Search::new(),
+            searched_query: String::new(),
+            search_root: PathBuf::new(),
+            search_shown: false,
+            remotes: Arc::new(Vec::new()),
+            read_only: None,
+            writable: StateSignal::new(true),
+            read_only_check: Arc::new(Mutex::new(None)),
+            destination_chooser: None,
+            rename_dialog: None,
+            undo,
+            undoable_jobs: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
+    /// Draw ignored files dimmed while `dim_ignored` is set
+    fn with_dim_ignored(mut self, dim_ignored: StateSignal<bool>) -> Self {
+        self.dim_ignored = dim_ignored;
+        self
+    }
+
+    /// Search the current folder for what is typed into `search_query`
+    fn with_search_query(mut self, search_query: StateSignal<String>) -> Self {
+        self.search_query = search_query;
+        self
+    }
+
+    /// Tell whether the shown folder can be changed in `writable`; local copies
+    /// of read-only `remotes` can't
+    fn with_write_guard(mut self, writable: StateSignal<bool>, remotes: Arc<Vec<Remote>>) -> Self {
+        self.writable = writable;
+        self.remotes = remotes;
+        self
+    }
+
+    /// Look up devices to eject in `devices`
+    fn with_device_list(mut self, devices: Arc<Mutex<Vec<Device>>>) -> Self {
+        self.devices = devices;
+        self
+    }
+
+    /// Join the window's focus chain
+    fn with_focus_chain(mut self, focus: FocusChain) -> Self {
+        self.file_list.set_focus_chain(focus);
+        self
+    }
+
+    /// Follow the jobs of the background operation daemon
+    fn with_transfer_jobs(mut self, jobs: Arc<Mutex<Vec<JobInfo>>>) -> Self {
+        self.transfer_jobs = jobs;
+        self
+    }
+
+    /// Tell that the shown folder `vanished` and the list moved up to a folder
+    /// that exists. If fileman moved it to the trash, restoring it is offered.
+    fn report_vanished_folder(&self, vanished: PathBuf) {
+        let bus = self.bus.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(TRASH_RECORD_GRACE).await;
+            let lookup = vanished.clone();
+            let entry = tokio::task::spawn_blocking(move || operations::undo::trashed_entry(&lookup))
+                .await
+                .ok()
+                .flatten();
+            let mut toast = Toast::new("Folder was deleted, moved to parent");
+            if let Some(entry) = entry {
+                let restore_bus = bus.clone();
+                toast = toast.with_action("Restore from Trash", move || {
+                    let entry = entry.clone();
+                    let vanished = vanished.clone();
+                    let bus = restore_bus.clone();
+                    tokio::task::spawn_blocking(move || match operations::undo::restore_trashed(&entry) {
+                        Ok(()) => {
+                            directory_service::forget(&entry.original);
+                            bus.navigate(crate::toolbar::NavigationAction::NavigateTo(vanished));
+                        },
+                        Err(e) => bus.status(format!("Error: {}", e)),
+                    });
+                });
+            }
+            bus.toast(toast);
+        });
+    }
+
+    /// Remember the device `path` is on while it is mounted, so it is known
+    /// which one went away when the folder vanishes.
+    fn track_shown_mount(&mut self, path: &std::path::Path) {
+        let mounted = self.devices.lock().ok().and_then(|devices| devices::mounted_at(&devices, path));
+        let still_on_it = |device: &Device| device.mount_point.as_ref().is_some_and(|m| path.starts_with(m));
+        match mounted {
+            Some(device) => self.shown_mount = Some(device),
+            None if self.shown_mount.as_ref().is_some_and(still_on_it) => {},
+            None => self.shown_mount = None,
+        }
+    }
+
+    /// Tell that the device the shown folder `path` was on was unmounted and
+    /// the list moved up to `shown`, offering to mount it again or to go home.
+    fn report_lost_mount(&mut self, device: Device, path: PathBuf, shown: PathBuf) {
+        log::info!("{} was unmounted while {} was shown", device.label, path.display());
+        let mut toast = Toast::new(format!("{} was unmounted", device.label));
+        // Mounts of the mount table can't be mounted from fileman
+        if !matches!(device.kind, devices::DeviceKind::Mount { .. }) {
+            let unmounted = Device { mount_point: None, ..device.clone() };
+            let bus = self.bus.clone();
+            toast = toast.with_action("Remount", move || {
+                let (device, bus) = (unmounted.clone(), bus.clone());
+                if device.is_locked() {
+                    bus.operation(FileOperationRequest::Unlock(device));
+                    return;
+                }
+                tokio::spawn(async move {
+                    if let Err(e) = devices::mount(&device).await {
+                        log::error!("{}", e);
+                        bus.status(format!("Error: {}", e));
+                    }
+                });
+            });
+        }
+        let bus = self.bus.clone();
+        toast = toast.with_action("Go Home", move || {
+            let home = xdg::home();
+            bus.navigate(crate::toolbar::NavigationAction::NavigateTo(home));
+        });
+        self.bus.toast(toast);
+        self.lost_mount = Some(LostMount { device, path, shown });
+    }
+
+    /// Go back to the folder of [LostMount] now that its device is mounted
+    /// again. Having opened another place meanwhile gives up on it, except its
+    /// (new) mount point, which mounting from the sidebar opens.
+    fn return_to_remounted(&mut self, device: Device) {
+        let Some(lost) = self.lost_mount.take_if(|lost| lost.device.id == device.id) else {
+            return;
+        };
+        let (Some(old_mount), Some(mount_point)) = (lost.device.mount_point, device.mount_point) else {
+            return;
+        };
+        let current = self.file_list.get_current_path();
+        if current != lost.shown && !current.starts_with(&old_mount) {
+            return;
+        }
+        let path = lost
+            .path
+            .strip_prefix(&old_mount)
+            .map(|inside| mount_point.join(inside))
+            .unwrap_or(mount_point);
+        let bus = self.bus.clone();
+        tokio::task::spawn_blocking(move || {
+            let Some(path) = path.ancestors().find(|p| p.is_dir()).map(PathBuf::from) else {
+                return;
+            };
+            bus.status(format!("{} is mounted again", device.label));
+            if path != current {
+                bus.navigate(crate::toolbar::NavigationAction::NavigateTo(path));
+            }
+        });
+    }
+
+    /// Ask about conflicts and failed items of daemon jobs and refresh the listing
+    /// when jobs finish
+    fn process_transfer_jobs(&mut self, context: &AppContext) -> Update {
+        let mut update = Update::empty();
+        let jobs = match self.transfer_jobs.lock() {
+            Ok(jobs) => jobs.clone(),
+            Err(_) => return update,
+        };
+
+        for job in &jobs {
+            if let JobState::Conflict { source, destination, remaining } = &job.state {
+                let key = (job.id, destination.clone());
+                if !self.transfer_conflicts.contains_key(&key) {
+                    let handle = ConflictDialog::new(source.clone(), destination.clone())
+                        .with_remaining_conflicts(*remaining)
+                        .show(context);
+                    self.transfer_conflicts.insert(key, Some(handle));
+                    update.insert(Update::DRAW);
+                }
+            }
+            if let JobState::Error { path, message, remaining } = &job.state {
+                let key = (job.id, path.clone());
+                if !self.transfer_errors.contains_key(&key) {
+                    let handle = ErrorDialog::new(path.clone(), message.clone())
+                        .with_remaining_items(*remaining)
+                        .show(context);
+                    self.transfer_errors.insert(key, Some(handle));
+                    update.insert(Update::DRAW);
+                }
+            } else {
+                // Answered and running again, so a retried item that fails again is asked about again
+                self.transfer_errors.retain(|(id, _), slot| *id != job.id || slot.is_some());
+            }
+        }
+
+        for ((id, _), slot) in self.transfer_conflicts.iter_mut() {
+            let Some(resolution) = slot.as_ref().and_then(|handle| handle.try_take()) else {
+                continue;
+            };
+            // Keep the entry so the dialog isn't shown again before the next poll
+            *slot = None;
+            let id = *id;
+            let bus = self.bus.clone();
+            tokio::spawn(async move {
+                if let Err(e) = daemon::client::resolve_conflict(id, resolution).await {
+                    log::error!("{}", e);
+                    bus.status(format!("Error: {}", e));
+                }
+            });
+        }
+        self.transfer_conflicts
+            .retain(|(id, _), _| jobs.iter().any(|job| job.id == *id));
+
+        for ((id, _), slot) in self.transfer_errors.iter_mut() {
+            let Some(action) = slot.as_ref().and_then(|handle| handle.try_take()) else {
+                continue;
+            };
+            // Keep the entry so the dialog isn't shown again before the next poll
+            *slot = None;
+            let id = *id;
+            let bus = self.bus.clone();
+            tokio::spawn(async move {
+                if let Err(e) = daemon::client::resolve_error(id, action).await {
+                    log::error!("{}", e);
+                    bus.status(format!("Error: {}", e));
+                }
+            });
+        }
+        self.transfer_errors
+            .retain(|(id, _), _| jobs.iter().any(|job| job.id == *id));
+
+        // Jobs queued here that are done can be undone, also when they
+        // failed or were cancelled part way
+        let done: Vec<u32> = match self.undoable_jobs.lock() {
+            Ok(mut undoable) => {
+                let (done, waiting) = undoable
+                    .iter()
+                    .copied()
+                    .partition(|id| jobs.iter().any(|job| job.id == *id && !job.state.is_active()));
+                *undoable = waiting;
+                done
+            }
+            Err(_) => Vec::new(),
+        };
+        for id in done {
+            let stack = self.undo.clone();
+            tokio::spawn(async move {
+                if let Some(record) = daemon::client::undo_record(id).await {
+                    if let Ok(mut stack) = stack.lock() {
+                        stack.push(record);
+                    }
+                }
+            });
+        }
+
+        let finished = jobs
+            .iter()
+            .filter(|job| !job.state.is_active())
+            .count();
+        if finished > self.finished_transfers {
+            self.file_list.refresh(Vec::new());
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+        self.finished_transfers = finished;
+        update
+    }
+
+    /// Carry out `request`, whether it came from the context menu, the toolbar
+    /// or a shortcut
+    fn dispatch(&mut self, request: FileOperationRequest, context: &AppContext) -> Update {
+        let mut update = Update::empty();
+        if let Some(refusal) = self.refusal(&request) {
+            self.bus.status(format!("Error: {}", refusal));
+            return Update::DRAW;
+        }
+        match request {
+            FileOperationRequest::Open(paths) => {
+                self.open(paths);
+            }
+            FileOperationRequest::OpenWith { paths, app_id } => {
+                // Looking up the application reads its desktop file
+                let bus = self.bus.clone();
+                tokio::task::spawn_blocking(move || {
+                    if let Err(e) = open_with_app(&app_id, &paths) {
+                        log::warn!("{}", e);
+                        bus.status(format!("Error: {}", e));
+                    }
+                });
+            }
+            FileOperationRequest::SetClipboard { paths, action } => {
+                if !paths.is_empty() {
+                    // Taking the clipboard waits on the display server
+                    let bus = self.bus.clone();
+                    tokio::task::spawn_blocking(move || {
+                        bus.status(match clipboard::set_files(&paths, action) {
+                            Ok(()) if action == ClipboardAction::Cut => format!("Cut {} item(s)", paths.len()),
+                            Ok(()) => format!("Copied {} item(s)", paths.len()),
+                            Err(e) => format!("Error: {}", e),
+                        });
+                    });
+                }
+            }
+            FileOperationRequest::OpenLocation(paths) => {
+                // Trashed items are looked for where they were trashed from,
+                // which reads a file per item
+                let bus = self.bus.clone();
+                tokio::task::spawn_blocking(move || {
+                    let paths: Vec<PathBuf> = paths
+                        .into_iter()
+                        .map(|path| operations::trash::original_path(&path).unwrap_or(path))
+                        .collect();
+                    let Some(parent) = paths.first().and_then(|p| p.parent()) else {
+                        return;
+                    };
+                    if parent.is_dir() {
+                        bus.operation(FileOperationRequest::Reveal(paths));
+                    } else {
+                        bus.status(format!("Error: {} no longer exists", parent.display()));
+                    }
+                });
+            }
+            FileOperationRequest::Reveal(paths) => {
+                self.reveal(paths);
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+            FileOperationRequest::CopyPaths { paths, names_only } => {
+                if !paths.is_empty() {
+                    let text = paths
+                        .iter()
+                        .map(|path| match path.file_name() {
+                            Some(name) if names_only => name.to_string_lossy().to_string(),
+                            _ => path.to_string_lossy().to_string(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let what = if names_only { "name" } else { "path" };
+                    let bus = self.bus.clone();
+                    tokio::task::spawn_blocking(move || {
+                        bus.status(match clipboard::set_text(&text) {
+                            Ok(()) => format!("Copied {} {}(s)", paths.len(), what),
+                            Err(e) => format!("Error: {}", e),
+                        });
+                    });
+                }
+            }
+            FileOperationRequest::Paste { destination, hard_links } => {
+                paste(destination, hard_links, self.bus.clone(), self.undoable_jobs.clone());
+            }
+            FileOperationRequest::Copy { sources, destination } => {
+                if !sources.is_empty() {
+                    self.queue_transfer(operations::engine::OperationKind::Copy { sources, destination });
+                }
+            }
+            FileOperationRequest::Move { sources, destination } => {
+                if !sources.is_empty() {
+                    self.queue_transfer(operations::engine::OperationKind::Move { sources, destination });
+                }
+            }
+            FileOperationRequest::ChooseDestination { sources, move_items } => {
+                let chooser = FileChooser::new(FileChooserMode::Open, self.file_list.get_current_path())
+                    .with_directories(true)
+                    .with_accept_label(if move_items { "Move Here" } else { "Copy Here" });
+                let handle = chooser.handle();
+                let title = if move_items { "Move To" } else { "Copy To" };
+                context.popup_manager.create_popup_at(Box::new(chooser), title, (640, 480), (200, 120));
+                self.destination_chooser = Some((handle, sources, move_items));
+                update.insert(Update::DRAW);
+            }
+            FileOperationRequest::Trash(paths) => {
+                // Items in a trash can only be deleted for good, after asking;
+                // the rest of the selection is trashed. Looking for them reads
+                // a file per item, too slow for large selections on the UI
+                // thread.
+                let bus = self.bus.clone();
+                let undoable_jobs = self.undoable_jobs.clone();
+                tokio::spawn(async move {
+                    let (trashed, paths): (Vec<PathBuf>, Vec<PathBuf>) = tokio::task::spawn_blocking(move || {
+                        paths.into_iter().partition(|path| operations::trash::original_path(path).is_some())
+                    })
+                    .await
+                    .unwrap_or_default();
+                    if !trashed.is_empty() {
+                        bus.operation(FileOperationRequest::Delete(trashed));
+                    }
+                    if !paths.is_empty() {
+                        queue_job(operations::engine::OperationKind::Trash(paths), &bus, &undoable_jobs).await;
+                    }
+                });
+            }
+            FileOperationRequest::Delete(paths) => {
+                self.show_delete_confirmation_dialog(&paths, context.clone());
+                update.insert(Update::DRAW);
+            }
+            FileOperationRequest::DeleteConfirmed(paths) => {
+                // Failed items are asked about, and the list is refreshed once it is done
+                self.queue_transfer(operations::engine::OperationKind::Delete(paths));
+            }
+            FileOperationRequest::NewFolder(parent) => {
+                // Finding a free name looks at the folder's items
+                let bus = self.bus.clone();
+                tokio::task::spawn_blocking(move || {
+                    let name = file_names::free_or_numbered(&parent.join("New Folder"))
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "New Folder".to_string());
+                    bus.operation(FileOperationRequest::CreateDirectory { parent, name });
+                });
+            }
+            FileOperationRequest::CreateDirectory { parent, name } => {
+                let undo = self.undo.clone();
+                let bus = self.bus.clone();
+                tokio::task::spawn_blocking(move || {
+                    let new_dir = parent.join(&name);
+                    let created = file_names::validate(&name, &parent)
+                        .and_then(|_| operations::create_directory(new_dir.clone()));
+                    match created {
+                        Ok(_) => {
+                            log::info!("Created directory: {:?}", new_dir);
+                            if let Ok(mut stack) = undo.lock() {
+                                stack.push(UndoRecord::Created { folder: new_dir.clone() });
+                            }
+                            bus.status(format!("Created directory '{}'", name));
+                            bus.operation(FileOperationRequest::Select(vec![new_dir]));
+                        }
+                        Err(e) => {
+                            log::error!("Failed to create directory {:?}: {}", new_dir, e);
+                            bus.status(format!("Error: {}", e));
+                        }
+                    }
+                });
+            }
+            FileOperationRequest::CreateFromTemplate { template, parent } => {
+                let bus = self.bus.clone();
+                tokio::task::spawn_blocking(move || match templates::create(&template, &parent) {
+                    Ok(file) => {
+                        let name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                        bus.status(format!("Created '{}'", name));
+                        bus.operation(FileOperationRequest::Select(vec![file]));
+                    }
+                    Err(e) => {
+                        log::error!("{}", e);
+                        bus.status(format!("Error: {}", e));
+                    }
+                });
+            }
+            FileOperationRequest::AskRename(path) => {
+                self.rename_dialog = Some(RenameDialog::new(path).show(context));
+                update.insert(Update::DRAW);
+            }
+            FileOperationRequest::Rename { from, to } => {
+                let undo = self.undo.clone();
+                let bus = self.bus.clone();
+                tokio::task::spawn_blocking(move || {
+                    match operations::rename_path(from.clone(), to.clone()) {
+                        Ok(_) => {
+                            log::info!("Renamed: {:?} -> {:?}", from, to);
+                            if let Ok(mut stack) = undo.lock() {
+                                stack.push(UndoRecord::Moved { moves: vec![(from, to.clone())] });
+                            }
+                            bus.status("Renamed successfully");
+                            bus.operation(FileOperationRequest::Select(vec![to]));
+                        }
+                        Err(e) => {
+                            log::error!("Failed to rename {:?} to {:?}: {}", from, to, e);
+                            bus.status(format!("Error: {}", e));
+                        }
+                    }
+                });
+            }
+            FileOperationRequest::Properties(paths) => {
+                self.file_list.show_properties(paths);
+                update.insert(Update::DRAW);
+            }
+            #[cfg(feature = "scripting")]
+            FileOperationRequest::RunScript(paths) => {
+                let folder = self.file_list.get_current_path();
+                crate::scripting::RunScriptDialog::new(paths, folder, self.bus.clone()).show(context);
+                update.insert(Update::DRAW);
+            }
+            #[cfg(not(feature = "scripting"))]
+            FileOperationRequest::RunScript(_) => {
+                self.bus.status("Error: fileman was built without scripting");
+            }
+            FileOperationRequest::Compress(paths) => {
+                let bus = self.bus.clone();
+                tokio::task::spawn_blocking(move || {
+                    let message = match operations::compress::compress(&paths) {
+                        Ok(archive) => format!(
+                            "Compressed {} item(s) into {}",
+                            paths.len(),
+                            archive.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+                        ),
+                        Err(e) => {
+                            log::error!("{}", e);
+                            format!("Error: {}", e)
+                        },
+                    };
+                    bus.status(message);
+                });
+            }
+            FileOperationRequest::SetPermissions { paths, set, clear } => {
+                // Many items on a slow file system would stall the window; the
+                // list is refreshed once all of them are changed
+                let bus = self.bus.clone();
+                tokio::task::spawn_blocking(move || {
+                    let errors: Vec<String> = paths
+                        .iter()
+                        .filter_map(|path| operations::permissions::change_bits(path, set, clear).err())
+                        .collect();
+                    for error in &errors {
+                        log::error!("Failed to change permissions: {}", error);
+                    }
+                    bus.status(match errors.first() {
+                        None => format!("Changed permissions of {} item(s)", paths.len()),
+                        Some(first) if errors.len() == 1 => format!("Error: {}", first),
+                        Some(first) => format!("Error: {} (and {} more errors)", first, errors.len() - 1),
+                    });
+                    bus.operation(FileOperationRequest::Refresh { deep: false });
+                });
+            }
+            FileOperationRequest::Unlock(device) => {
+                UnlockDialog::new(device, self.bus.clone()).show(context);
+                update.insert(Update::DRAW);
+            }
+            FileOperationRequest::Mounted(device) => self.return_to_remounted(device),
+            FileOperationRequest::Preferences => {
+                PreferencesDialog::new(self.bus.clone()).show(context);
+                update.insert(Update::DRAW);
+            }
+            FileOperationRequest::Refresh { deep } => {
+                self.refresh(deep);
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+            FileOperationRequest::Select(paths) => {
+                self.file_list.refresh(paths);
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+            FileOperationRequest::Eject(device) => {
+                self.eject(device);
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+            FileOperationRequest::ResetLayout => {
+                view_settings::reset_zoom();
+                self.file_list.set_zoom(Default::default());
+                window_state::request_reset();
+                self.bus.status("Layout reset to the defaults");
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+            FileOperationRequest::ToggleFolderThumbnails => {
+                let folder = self.file_list.get_current_path();
+                let enabled = !*self.thumbnails.get();
+                view_settings::update(&folder, |view| view.thumbnails = Some(enabled));
+                // Applied by sync_folder_thumbnails
+                self.thumbnails_folder = None;
+                self.bus.status(if enabled {
+                    "Thumbnails are generated in this folder"
+                } else {
+                    "Thumbnails are no longer generated in this folder"
+                });
+                update.insert(Update::DRAW);
+            }
+            FileOperationRequest::Transfers => {
+                TransferQueueDialog::new(self.transfer_jobs.clone(), self.bus.clone()).show(context);
+                update.insert(Update::DRAW);
+            }
+            FileOperationRequest::ToggleHiddenFiles => {
+                let show_hidden = self.file_list.show_hidden_signal();
+                let show = *show_hidden.get();
+                show_hidden.set(!show);
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+            FileOperationRequest::CycleView => {
+                let view_mode = self.file_list.view_mode_signal();
+                let next = match *view_mode.get() {
+                    FileListViewMode::List => FileListViewMode::Icon,
+                    FileListViewMode::Icon => FileListViewMode::Table,
+                    FileListViewMode::Table | FileListViewMode::Compact => FileListViewMode::List,
+                };
+                view_mode.set(next);
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+            FileOperationRequest::CommandPalette => {
+                let target = PaletteTarget {
+                    folder: self.file_list.get_current_path(),
+                    selection: (*self.file_list.selected_paths_signal().get()).clone(),
+                };
+                CommandPalette::new(self.bus.clone(), target).show(context);
+                update.insert(Update::DRAW);
+            }
+            FileOperationRequest::Undo => {
+                self.step_back_or_forward(false);
+                update.insert(Update::DRAW);
+            }
+            FileOperationRequest::Redo => {
+                self.step_back_or_forward(true);
+                update.insert(Update::DRAW);
+            }
+        }
+        update
+    }
+
+    /// Undo the last operation, or with `redo` carry out the last undone one
+    /// again, in the background
+    fn step_back_or_forward(&self, redo: bool) {
+        let record = self.undo.lock().ok().and_then(|mut stack| {
+            if redo { stack.take_redo() } else { stack.take_undo() }
+        });
+        let Some(record) = record else {
+            self.bus.status(if redo { "Nothing to redo" } else { "Nothing to undo" });
+            return;
+        };
+        let stack = self.undo.clone();
+        let bus = self.bus.clone();
+        tokio::task::spawn_blocking(move || {
+            let description = record.describe();
+            let step = if redo { record.redo() } else { record.undo() };
+            let result = step.result.clone();
+            // What failed is handed back to be tried again
+            if let Ok(mut stack) = stack.lock() {
+                stack.stepped(step, redo);
+            }
+            bus.status(match result {
+                Ok(()) if redo => format!("Redid {}", description),
+                Ok(()) => format!("Undid {}", description),
+                Err(e) => {
+                    log::error!("{}", e);
+                    format!("Error: {}", e)
+                },
+            });
+            bus.operation(FileOperationRequest::Refresh { deep: false });
+        });
+    }
+
+    /// Open `paths` without checking what they are on the UI thread: a single
+    /// folder is entered, files are handled as configured for their type and
+    /// folders among several items are left alone.
+    fn open(&self, paths: Vec<PathBuf>) {
+        let open_tx = self.open_tx.clone();
+        tokio::spawn(async move {
+            let single = paths.len() == 1;
+            for path in paths {
+                let is_dir = tokio::fs::metadata(&path).await.is_ok_and(|m| m.is_dir());
+                let target = match (is_dir, single) {
+                    (true, true) => OpenTarget::Folder(path),
+                    (true, false) => continue,
+                    (false, _) => OpenTarget::File(path),
+                };
+                let _ = open_tx.send(target);
+            }
+        });
+    }
+
+    fn queue_transfer(&self, kind: crate::operations::engine::OperationKind) {
+        let bus = self.bus.clone();
+        let undoable_jobs = self.undoable_jobs.clone();
+        tokio::spawn(async move {
+            queue_job(kind, &bus, &undoable_jobs).await;
+        });
+    }
+
+    /// Handle requests from other applications
+    fn with_external_requests(mut self, rx: Option<mpsc::UnboundedReceiver<ExternalRequest>>) -> Self {
+        self.external_rx = rx;
+        self
+    }
+
+    /// Carry out a request from another application
+    ///
+    /// Folders open in new tabs. Items are revealed in the active tab if it already
+    /// shows their folder, otherwise in a new tab. Which paths are folders is
+    /// looked up in the background; the tabs are opened once it is known.
+    fn handle_external_request(&self, request: ExternalRequest) {
+        let (folders, reveal, show_properties) = match request {
+            ExternalRequest::ShowFolders(folders) => (folders, Vec::new(), false),
+            ExternalRequest::ShowItems(paths) => (Vec::new(), paths, false),
+            ExternalRequest::ShowItemProperties(paths) => (Vec::new(), paths, true),
+            ExternalRequest::Open { folders, select } => (folders, select, false),
+        };
+        let open_tx = self.open_tx.clone();
+        tokio::spawn(async move {
+            for folder in folders {
+                if tokio::fs::metadata(&folder).await.is_ok_and(|m| m.is_dir()) {
+                    let _ = open_tx.send(OpenTarget::Tab(folder));
+                }
+            }
+            if !reveal.is_empty() {
+                let _ = open_tx.send(OpenTarget::Items { items: reveal, properties: show_properties });
+            }
+        });
+    }
+
+    /// Show `items` of one folder, in a new tab unless the active one shows it.
+    fn reveal_items(&mut self, items: Vec<PathBuf>, properties: bool) {
+        let Some(parent) = items.first().and_then(|p| p.parent()).map(PathBuf::from) else {
+            return;
+        };
+        if let Ok(mut nav) = self.navigation.lock() {
+            if nav.get_current_path() != parent {
+                nav.open_tab(parent);
+            }
+        }
+        self.file_list.reveal(&items);
+        if properties {
+            self.file_list.show_properties(items);
+        }
+    }
+
+    /// Re-read the shown folder, keeping the view as it is. A deep refresh also
+    /// drops what is cached about the folder: icons and thumbnails of its entries
+    /// and the sizes of the folders below it.
+    fn refresh(&mut self, deep: bool) {
+        let path = self.file_list.get_current_path();
+        self.file_list.refresh(Vec::new());
+        if !deep {
+            // Git statuses and folder sizes are recomputed for the "new" folder
+            self.git_status_path = None;
+            return;
+        }
+        self.file_list.invalidate_caches();
+        self.git_status.refresh(path.clone());
+        self.git_status_path = Some(path.clone());
+        let folder_sizes = self.folder_sizes.clone();
+        let bus = self.bus.clone();
+        directory_service::forget(&path);
+        tokio::task::spawn_blocking(move || {
+            dir_size_cache::forget(&path);
+            if let Some(folder_sizes) = folder_sizes {
+                folder_sizes.refresh(path.clone());
+            }
+            bus.status(format!("Cleared cached data of {}", path.display()));
+        });
+    }
+
+    /// Find out in the background whether `dir` can be changed.
+    fn check_read_only(&self, dir: PathBuf) {
+        let remotes = self.remotes.clone();
+        let slot = self.read_only_check.clone();
+        tokio::task::spawn_blocking(move || {
+            let reason = read_only::reason(&dir, &remotes);
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some((dir, reason));
+            }
+        });
+    }
+
+    /// Take the answer of [Self::check_read_only] if it is for the shown folder,
+    /// telling in the status bar when the folder is read-only.
+    fn apply_read_only_check(&mut self) -> Update {
+        let checked = self.read_only_check.lock().ok().and_then(|mut slot| slot.take());
+        let Some((dir, reason)) = checked.filter(|(dir, _)| *dir == self.file_list.get_current_path()) else {
+            return Update::empty();
+        };
+        if let Some(reason) = &reason {
+            if self.read_only.as_ref() != Some(reason) {
+                log::info!("{} is read-only: {}", dir.display(), reason);
+                self.bus.status(format!("Read-only: {}", reason));
+            }
+        }
+        self.writable.set(reason.is_none());
+        self.read_only = reason;
+        Update::DRAW
+    }
+
+    /// Why `request` is refused: it would change the shown folder, which is
+    /// read-only.
+    fn refusal(&self, request: &FileOperationRequest) -> Option<String> {
+        let reason = self.read_only.as_ref()?;
+        let (action, paths): (&str, Vec<&PathBuf>) = match request {
+            FileOperationRequest::Paste { destination, .. } => ("paste", vec![destination]),
+            FileOperationRequest::CreateDirectory { parent, .. }
+            | FileOperationRequest::CreateFromTemplate { parent, .. } => ("create items", vec![parent]),
+            FileOperationRequest::Rename { from, .. } => ("rename", vec![from]),
+            FileOperationRequest::Copy { destination, .. } => ("copy items", vec![destination]),
+            FileOperationRequest::Move { sources, destination } => {
+                ("move items", sources.iter().chain([destination]).collect())
+            },
+            FileOperationRequest::Trash(paths)
+            | FileOperationRequest::Delete(paths)
+            | FileOperationRequest::DeleteConfirmed(paths) => {
+                ("delete", paths.iter().collect())
+            },
+            _ => return None,
+        };
+        let current = self.file_list.get_current_path();
+        paths
+            .iter()
+            .any(|path| path.starts_with(&current))
+            .then(|| format!("Can't {} here: {}", action, reason))
+    }
+
+    /// Navigate to the folder the items `paths` are in and select them there.
+    fn reveal(&mut self, paths: Vec<PathBuf>) {
+        let Some(parent) = paths.first().and_then(|p| p.parent()).map(PathBuf::from) else {
+            return;
+        };
+        self.end_search();
+        if let Ok(mut nav) = self.navigation.lock() {
+            if nav.get_current_path() != parent {
+                nav.navigate_to(parent);
+            }
+        }
+        self.file_list.reveal(&paths);
+    }
+
+    /// Stop the search and empty the search field; the list keeps what it shows.
+    fn end_search(&mut self) {
+        self.search.cancel();
+        self.searched_query.clear();
+        self.search_shown = false;
+        self.search_query.set(String::new());
+    }
+
+    /// Start a search when the query is edited, stopping the one running, and
+    /// stream its matches into the list with their number in the status bar.
+    /// An empty query, or leaving the searched folder, ends the search.
+    fn sync_search(&mut self) -> Update {
+        let query = self.search_query.get().trim().to_string();
+        let current = self.file_list.get_current_path();
+        if !self.searched_query.is_empty() && current != self.search_root {
+            self.end_search();
+            return Update::DRAW;
+        }
+        // The listing was refreshed, e.g. after an operation: search it again
+        let refreshed = self.search_shown && !self.file_list.showing_results();
+        if query != self.searched_query || refreshed {
+            self.searched_query = query.clone();
+            self.search_shown = false;
+            if query.is_empty() {
+                self.search.cancel();
+                self.file_list.refresh(Vec::new());
+                return Update::LAYOUT | Update::DRAW;
+            }
+            self.search_root = current.clone();
+            let include_hidden = *self.file_list.show_hidden_signal().get();
+            self.search.start(current, query, include_hidden);
+        }
+        if !self.search.take_changed() {
+            return Update::empty();
+        }
+        let Some(progress) = self.search.progress() else {
+            return Update::empty();
+        };
+        self.bus.status(progress.describe());
+        self.file_list.show_results(progress.results);
+        self.search_shown = true;
+        Update::LAYOUT | Update::DRAW
+    }
+
+    /// Eject `device`, or the removable device the current folder is on,
+    /// leaving it first so the listing doesn't keep it busy.
+    fn eject(&mut self, device: Option<Device>) {
+        let current = self.file_list.get_current_path();
+        let device = device.or_else(|| devices::containing(&self.devices.lock().ok()?, &current));
+        let Some(device) = device else {
+            self.bus.status("The current folder isn't on a removable device");
+            return;
+        };
+        if device.mount_point.as_ref().is_some_and(|m| current.starts_with(m)) {
+            let home = xdg::home();
+            if let Ok(mut nav) = self.navigation.lock() {
+                nav.navigate_to(home);
+            }
+        }
+        let bus = self.bus.clone();
+        tokio::spawn(async move {
+            let message = match devices::eject(&device).await {
+                Ok(()) => format!("{} can be removed", device.label),
+                Err(e) => {
+                    log::error!("{}", e);
+                    format!("Error: {}", e)
+                },
+            };
+            bus.status(message);
+        });
+    }
+
+    /// Carry out an action chosen from the context menu of a link. The target
+    /// is read in the background and opened once it is known what it is.
+    fn handle_link_request(&self, request: LinkRequest, context: &AppContext) {
+        let (link, follow) = match request {
+            LinkRequest::Follow(link) => (link, true),
+            LinkRequest::OpenTargetFolder(link) => (link, false),
+            LinkRequest::Edit(link) => {
+                EditLinkDialog::new(link).show(context);
+                return;
+            },
+        };
+        let open_tx = self.open_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let Some(target) = links::resolved_target(&link) else {
+                return;
+            };
+            let target = if !follow {
+                OpenTarget::Item(target)
+            } else if target.is_dir() {
+                OpenTarget::Folder(target)
+            } else {
+                OpenTarget::File(target)
+            };
+            let _ = open_tx.send(target);
+        });
+    }
+
     /// Get the selected paths signal (for reactive subscription by other widgets)
     pub fn selected_paths_signal(&self) -> &StateSignal<Vec<PathBuf>> {
         self.file_list.selected_paths_signal()
     }
     
+    /// Get the accessibility tree published by the file list
+    pub fn accessibility(&self) -> nptk_fileman_widgets::accessibility::AccessibleSubtree {
+        self.file_list.accessibility()
+    }
+
+    /// Get the sort signal
+    pub fn sort_signal(&self) -> &StateSignal<nptk_fileman_widgets::file_list::FileListSort> {
+        self.file_list.sort_signal()
+    }
+
     /// Get the view mode signal
     pub fn view_mode_signal(&self) -> &StateSignal<nptk_fileman_widgets::file_list::FileListViewMode> {
         self.file_list.view_mode_signal()
     }
 
+    pub fn show_hidden_signal(&self) -> &StateSignal<bool> {
+        self.file_list.show_hidden_signal()
+    }
+
+    /// Number of hidden items the shown folder has that aren't listed
+    pub fn hidden_count_signal(&self) -> &StateSignal<usize> {
+        self.file_list.hidden_count_signal()
+    }
+
+    /// Whether thumbnails are generated in the shown folder
+    pub fn thumbnails_signal(&self) -> &StateSignal<bool> {
+        &self.thumbnails
+    }
+
+    /// Get the zoom signal
+    pub fn zoom_signal(&self) -> &StateSignal<nptk_fileman_widgets::file_list::FileListZoom> {
+        self.file_list.zoom_signal()
+    }
+
+    /// Keep the zoom of each folder: apply the saved zoom when another folder
+    /// is shown, and save the zoom of the shown one when it is changed.
+    fn sync_folder_zoom(&mut self) -> Update {
+        let folder = self.file_list.get_current_path();
+        let zoom = self.file_list.zoom();
+        let saved = view_settings::get(&folder).zoom;
+        if self.zoomed_folder.as_ref() != Some(&folder) {
+            self.zoomed_folder = Some(folder);
+            if saved != zoom {
+                self.file_list.set_zoom(saved);
+                return Update::LAYOUT | Update::DRAW;
+            }
+        } else if saved != zoom {
+            view_settings::update(&folder, |view| view.zoom = zoom);
+        }
+        Update::empty()
+    }
+
+    /// Generate thumbnails in the shown folder only if it (or a folder above
+    /// it) or the Preferences allow it, checked once per folder.
+    fn sync_folder_thumbnails(&mut self) {
+        let folder = self.file_list.get_current_path();
+        if self.thumbnails_folder.as_ref() == Some(&folder) {
+            return;
+        }
+        let enabled = view_settings::thumbnails(&folder, settings::get().thumbnails);
+        self.file_list.set_thumbnails(enabled);
+        self.thumbnails.set(enabled);
+        self.thumbnails_folder = Some(folder);
+    }
+
+    /// Keep the view mode, sort and hidden files of each tab: remember the
+    /// settings of the shown tab, and apply those of another tab when it becomes
+    /// active.
+    fn sync_tab_view(&mut self) -> Update {
+        let current = TabView {
+            view_mode: *self.file_list.view_mode_signal().get(),
+            sort: self.file_list.sort(),
+            show_hidden: *self.file_list.show_hidden_signal().get(),
+        };
+        let Ok(mut nav) = self.navigation.lock() else {
+            return Update::empty();
+        };
+        let tab = nav.active_tab_id();
+        let view = nav.tab_view();
+        if self.shown_tab == Some(tab) || view.is_none() {
+            self.shown_tab = Some(tab);
+            if view != Some(current) {
+                nav.set_tab_view(current);
+            }
+            return Update::empty();
+        }
+        drop(nav);
+        self.shown_tab = Some(tab);
+        match view {
+            Some(view) if view != current => {
+                self.file_list.set_view_mode(view.view_mode);
+                self.file_list.set_sort(view.sort.column, view.sort.order);
+                self.file_list.set_show_hidden(view.show_hidden);
+                Update::LAYOUT | Update::DRAW
+            },
+            _ => Update::empty(),
+        }
+    }
+
     /// Show properties popup for the given paths
     pub fn show_properties_for_paths(&mut self, paths: &[PathBuf], context: nptk::core::app::context::AppContext) {
         // Properties functionality is handled internally by FileListContent
@@ -109,9 +1541,6 @@ impl FileListWrapper {
             format!("Are you sure you want to delete {} selected item(s)?", paths.len())
         };
 
-        let pending_delete = self.pending_delete_confirmation.clone();
-        let paths_to_delete = paths.to_vec();
-
         // Message text widget
         let message_text = Text::new(message);
         
@@ -121,17 +1550,7 @@ impl FileListWrapper {
         
         // Delete button - confirms deletion
         let delete_btn = Button::new(Text::new("Delete".to_string()))
-            .with_on_pressed({
-                let pending_delete_btn = pending_delete.clone();
-                let paths_btn = paths_to_delete.clone();
-                MaybeSignal::signal(Box::new(EvalSignal::new(move || {
-                    // Set pending delete confirmation - will be processed in update()
-                    if let Ok(mut pending) = pending_delete_btn.lock() {
-                        *pending = Some(paths_btn.clone());
-                    }
-                    Update::DRAW
-                })))
-            });
+            .with_on_pressed(self.delete_confirmations.on_pressed(paths.to_vec()));
 
         // Build dialog content
         let dialog_content = Container::new(vec![
@@ -185,18 +1604,13 @@ impl Widget for FileListWrapper {
         if !self.signals_hooked {
             context.hook_signal(&mut self.navigation_path_signal);
             context.hook_signal(&mut self.file_list_path_signal);
+            context.hook_signal(&mut self.dim_ignored);
+            context.hook_signal(&mut self.listing_stale_signal);
+            context.hook_signal(&mut self.search_query);
             self.signals_hooked = true;
         }
 
-        // Handle sidebar navigation events (sync to NavigationState, which will reactively update FileList)
-        if let Some(ref mut rx) = self.navigation_rx {
-            while let Ok(path) = rx.try_recv() {
-                if let Ok(mut nav) = self.navigation.lock() {
-                    nav.navigate_to(path.clone());
-                    update.insert(Update::LAYOUT | Update::DRAW);
-                }
-            }
-        }
+        update |= self.sync_tab_view();
 
         // Reactively sync NavigationState path changes to FileList
         let nav_path = (*self.navigation_path_signal.get()).clone();
@@ -206,30 +1620,82 @@ impl Widget for FileListWrapper {
             update.insert(Update::LAYOUT | Update::DRAW);
         }
 
+        update |= self.sync_folder_zoom();
+        self.sync_folder_thumbnails();
+        update |= self.sync_search();
+
         // Update the wrapped FileList to let it handle internal updates
         let file_list_update = self.file_list.update(layout, context.clone(), info).await;
         update |= file_list_update;
 
         // Path refresh/recovery logic: If current directory no longer exists, navigate to parent
         // This handles the case where a directory is deleted externally
+        // (checked in the background, so a slow mount doesn't stall the frame)
         let current_path = (*self.file_list_path_signal.get()).clone();
-        if !current_path.exists() {
-            // Navigate to parent directory, continuing up until we find a valid directory
-            let mut recovery_path = current_path.clone();
-            while !recovery_path.exists() && recovery_path != PathBuf::from("/") {
-                if let Some(parent) = recovery_path.parent() {
-                    recovery_path = parent.to_path_buf();
-                } else {
-                    break;
-                }
+        let update_manager = context.update();
+        let existing = directory_service::existing_ancestor(&current_path, move || {
+            update_manager.insert(Update::DRAW);
+        });
+        if let Some(recovery_path) = existing.filter(|p| *p != current_path) {
+            // Gone with its device rather than deleted?
+            let lost_device = self.shown_mount.take().filter(|device| {
+                device
+                    .mount_point
+                    .as_ref()
+                    .is_some_and(|m| current_path.starts_with(m) && !devices::is_mounted(m))
+            });
+            if let Ok(mut nav) = self.navigation.lock() {
+                // The vanished folder stays in the history behind its parent
+                nav.navigate_to(recovery_path.clone());
+                self.file_list.set_path(recovery_path.clone());
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+            match lost_device {
+                Some(device) => self.report_lost_mount(device, current_path, recovery_path),
+                None => self.report_vanished_folder(current_path),
             }
-            // If we found a valid parent, navigate there
-            if recovery_path.exists() && recovery_path != current_path {
-                if let Ok(mut nav) = self.navigation.lock() {
-                    nav.navigate_to(recovery_path.clone());
-                    self.file_list.set_path(recovery_path);
+        } else {
+            self.track_shown_mount(&current_path);
+        }
+
+        // Open items once it is known what they are (e.g. folders restored
+        // from the trash, or opened from the context menu)
+        while let Ok(target) = self.open_rx.try_recv() {
+            match target {
+                OpenTarget::Folder(folder) => {
+                    if let Ok(mut nav) = self.navigation.lock() {
+                        nav.navigate_to(folder.clone());
+                        self.file_list.set_path(folder);
+                        update.insert(Update::LAYOUT | Update::DRAW);
+                    }
+                },
+                OpenTarget::File(path) => {
+                    if let Err(e) = activation::activate(path, &context) {
+                        self.bus.status(format!("Error: {}", e));
+                    }
+                    update.insert(Update::DRAW);
+                },
+                OpenTarget::Tab(folder) => {
+                    if let Ok(mut nav) = self.navigation.lock() {
+                        nav.open_tab(folder.clone());
+                    }
+                    self.file_list.set_path(folder);
                     update.insert(Update::LAYOUT | Update::DRAW);
-                }
+                },
+                OpenTarget::Item(path) => {
+                    let Some(parent) = path.parent().map(PathBuf::from) else {
+                        continue;
+                    };
+                    if let Ok(mut nav) = self.navigation.lock() {
+                        nav.navigate_to(parent);
+                    }
+                    self.file_list.reveal(&[path]);
+                    update.insert(Update::LAYOUT | Update::DRAW);
+                },
+                OpenTarget::Items { items, properties } => {
+                    self.reveal_items(items, properties);
+                    update.insert(Update::LAYOUT | Update::DRAW);
+                },
             }
         }
 
@@ -242,162 +1708,136 @@ impl Widget for FileListWrapper {
             }
         }
 
-        // Process file operations from FileList widget (context menu, etc.)
-        if let Some(ref mut rx) = self.file_list_operation_rx {
-            while let Ok(op) = rx.try_recv() {
-                match op {
-                    FileListOperation::Delete(paths) => {
-                        // Convert to FileOperationRequest and process
-                        let paths_clone = paths.clone();
-                        // Process delete operation
-                        let mut all_success = true;
-                        let mut error_msg = String::new();
-                        
-                        for path in &paths {
-                            match operations::delete_path(path.clone()) {
-                                Ok(_) => {
-                                    log::info!("Deleted: {:?}", path);
-                                }
-                                Err(e) => {
-                                    log::error!("Failed to delete {:?}: {}", path, e);
-                                    all_success = false;
-                                    error_msg = e;
-                                    break;
-                                }
-                            }
-                        }
-                        
-                        // Update status message
-                        if let Some(ref tx) = self.status_tx {
-                            if all_success {
-                                let _ = tx.send(format!("Deleted {} item(s)", paths_clone.len()));
-                            } else {
-                                let _ = tx.send(format!("Error: {}", error_msg));
-                            }
-                        }
-                        
-                        // Refresh file list
-                        let current_path = self.file_list.get_current_path();
-                        self.file_list.set_path(current_path.clone());
-                        update.insert(Update::LAYOUT | Update::DRAW);
-                    }
-                }
+        // Process requests from other applications
+        let mut external_requests = Vec::new();
+        if let Some(ref mut rx) = self.external_rx {
+            while let Ok(request) = rx.try_recv() {
+                external_requests.push(request);
             }
         }
+        for request in external_requests {
+            self.handle_external_request(request);
+        }
 
-        // Process file operations from toolbar/other UI
-        // Note: Delete operations need confirmation, so show dialog first
-        // Collect operations first to avoid borrow conflicts
-        let mut pending_deletes = Vec::new();
-        if let Some(ref mut rx) = self.operation_rx {
-            while let Ok(op) = rx.try_recv() {
-                match op {
-                    FileOperationRequest::Delete(paths) => {
-                        // Collect delete requests to show confirmation dialog
-                        log::warn!("RECEIVED DELETE REQUEST for {} path(s)", paths.len());
-                        pending_deletes.push(paths);
-                    }
-                    FileOperationRequest::CreateDirectory { parent, name } => {
-                        let new_dir = parent.join(&name);
-                        match operations::create_directory(new_dir.clone()) {
-                            Ok(_) => {
-                                log::info!("Created directory: {:?}", new_dir);
-                                if let Some(ref tx) = self.status_tx {
-                                    let _ = tx.send(format!("Created directory '{}'", name));
-                                }
-                                // Refresh file list
-                                let current_path = self.file_list.get_current_path();
-                                self.file_list.set_path(current_path.clone());
-                                update.insert(Update::LAYOUT | Update::DRAW);
-                            }
-                            Err(e) => {
-                                log::error!("Failed to create directory {:?}: {}", new_dir, e);
-                                if let Some(ref tx) = self.status_tx {
-                                    let _ = tx.send(format!("Error: {}", e));
-                                }
-                            }
-                        }
-                    }
-                    FileOperationRequest::Rename { from, to } => {
-                        match operations::rename_path(from.clone(), to.clone()) {
-                            Ok(_) => {
-                                log::info!("Renamed: {:?} -> {:?}", from, to);
-                                if let Some(ref tx) = self.status_tx {
-                                    let _ = tx.send("Renamed successfully".to_string());
-                                }
-                                // Refresh file list
-                                let current_path = self.file_list.get_current_path();
-                                self.file_list.set_path(current_path.clone());
-                                update.insert(Update::LAYOUT | Update::DRAW);
-                            }
-                            Err(e) => {
-                                log::error!("Failed to rename {:?} to {:?}: {}", from, to, e);
-                                if let Some(ref tx) = self.status_tx {
-                                    let _ = tx.send(format!("Error: {}", e));
-                                }
-                            }
-                        }
-                    }
-                    FileOperationRequest::Properties(paths) => {
-                        // Show properties using the same mechanism as context menu
-                        // We need to trigger the properties action through the FileList's operation channel
-                        // For now, log the request - the actual implementation would need to be done
-                        // through the FileList's internal operation system
-                        log::info!("Properties requested for paths: {:?}", paths);
-                        if let Some(ref tx) = self.status_tx {
-                            let _ = tx.send("Properties functionality available via right-click".to_string());
-                        }
-                        update.insert(Update::DRAW);
-                    }
+        // Follow transfers running in the background operation daemon
+        update.insert(self.process_transfer_jobs(&context));
+
+        let permissions_paths = self.permissions_request.lock().ok().and_then(|mut r| r.take());
+        if let Some(paths) = permissions_paths {
+            PermissionsDialog::new(paths).show(&context);
+            update.insert(Update::DRAW);
+        }
+        let write_image = self.write_image_request.lock().ok().and_then(|mut r| r.take());
+        if let Some(image) = write_image {
+            WriteImageDialog::new(image, self.bus.clone()).show(&context);
+            update.insert(Update::DRAW);
+        }
+        // Folders are navigated into by the list itself, which already shows them
+        let activated = self.activation_request.lock().ok().and_then(|mut r| r.take());
+        if let Some(path) = activated.filter(|p| *p != self.file_list.get_current_path()) {
+            if let Err(e) = activation::activate(path, &context) {
+                self.bus.status(format!("Error: {}", e));
+            }
+            update.insert(Update::DRAW);
+        }
+        let chosen = self.destination_chooser.as_ref().and_then(|(handle, _, _)| handle.try_take());
+        if let Some(response) = chosen {
+            if let (FileChooserResponse::Accepted(folders), Some((_, sources, move_items))) =
+                (response, self.destination_chooser.take())
+            {
+                if let Some(destination) = folders.into_iter().next() {
+                    let request = if move_items {
+                        FileOperationRequest::Move { sources, destination }
+                    } else {
+                        FileOperationRequest::Copy { sources, destination }
+                    };
+                    update |= self.dispatch(request, &context);
                 }
             }
+            self.destination_chooser = None;
         }
-        
-        // Show confirmation dialogs for pending delete operations (after releasing borrow)
-        if !pending_deletes.is_empty() {
-            log::warn!("SHOWING {} DELETE CONFIRMATION DIALOG(S)", pending_deletes.len());
+        if let Some((from, to)) = self.rename_dialog.as_ref().and_then(|h| h.try_take()) {
+            self.rename_dialog = None;
+            update |= self.dispatch(FileOperationRequest::Rename { from, to }, &context);
+        }
+        let paste_links_destination = self.paste_links_request.lock().ok().and_then(|mut r| r.take());
+        if let Some(destination) = paste_links_destination {
+            update |= self.dispatch(FileOperationRequest::Paste { destination, hard_links: true }, &context);
+        }
+        let refresh_requested = self.refresh_requested.lock().map(|mut r| std::mem::take(&mut *r)).unwrap_or(false);
+        if refresh_requested {
+            self.refresh(false);
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+        if let Some(request) = self.link_actions.take_request() {
+            self.handle_link_request(request, &context);
+            update.insert(Update::DRAW);
         }
-        for paths in pending_deletes {
-            self.show_delete_confirmation_dialog(&paths, context.clone());
+        if let Some(paths) = self.send_to.take_share_request() {
+            match ShareDialog::new(&paths) {
+                Ok(dialog) => dialog.show(&context),
+                Err(e) => {
+                    self.bus.status(format!("Error: {}", e));
+                },
+            }
             update.insert(Update::DRAW);
         }
+
+        // Tell when a saved listing is shown for a slow location, and when it was replaced
+        let listing_stale = *self.listing_stale_signal.get();
+        if listing_stale != self.listing_stale_reported {
+            self.listing_stale_reported = listing_stale;
+            self.bus.status(if listing_stale {
+                "Showing saved listing, refreshing..."
+            } else {
+                "Listing refreshed"
+            });
+        }
+
+        // Recompute git statuses and folder sizes when the folder changes
+        let shown_path = self.file_list.get_current_path();
+        if self.git_status_path.as_ref() != Some(&shown_path) {
+            self.git_status.refresh(shown_path.clone());
+            if let Some(folder_sizes) = &self.folder_sizes {
+                folder_sizes.refresh(shown_path.clone());
+            }
+            self.check_read_only(shown_path.clone());
+            let last_location = shown_path.clone();
+            tokio::task::spawn_blocking(move || startup::remember(&last_location));
+            self.git_status_path = Some(shown_path);
+        }
+        update |= self.apply_read_only_check();
+        if self.git_status.take_changed() {
+            update.insert(Update::DRAW);
+        }
+        if self.folder_sizes.as_ref().is_some_and(|f| f.take_changed()) {
+            update.insert(Update::DRAW);
+        }
+        if plugins::take_changed() {
+            update.insert(Update::DRAW);
+        }
+        self.git_status.set_dim_ignored(*self.dim_ignored.get());
+
+        // Context menu, toolbar and shortcut requests all go through dispatch
+        // (collected first to avoid borrow conflicts)
+        let mut requests: Vec<FileOperationRequest> = Vec::new();
+        if let Some(ref mut rx) = self.file_list_operation_rx {
+            while let Ok(op) = rx.try_recv() {
+                requests.push(op.into());
+            }
+        }
+        while let Some(command) = self.bus_commands.try_recv() {
+            if let AppCommand::Operation(request) = command {
+                requests.push(request);
+            }
+        }
+        for request in requests {
+            update |= self.dispatch(request, &context);
+        }
         
         // Process confirmed delete operations from toolbar (user clicked "Delete" in confirmation dialog)
-        if let Ok(mut pending_delete) = self.pending_delete_confirmation.lock() {
-            if let Some(paths) = pending_delete.take() {
-                // User confirmed - proceed with deletion
-                let paths_clone = paths.clone();
-                let mut all_success = true;
-                let mut error_msg = String::new();
-                
-                for path in &paths {
-                    match operations::delete_path(path.clone()) {
-                        Ok(_) => {
-                            log::info!("Deleted: {:?}", path);
-                        }
-                        Err(e) => {
-                            log::error!("Failed to delete {:?}: {}", path, e);
-                            all_success = false;
-                            error_msg = e;
-                            break;
-                        }
-                    }
-                }
-                
-                // Update status message
-                if let Some(ref tx) = self.status_tx {
-                    if all_success {
-                        let _ = tx.send(format!("Deleted {} item(s)", paths_clone.len()));
-                    } else {
-                        let _ = tx.send(format!("Error: {}", error_msg));
-                    }
-                }
-                
-                // Refresh file list
-                let current_path = self.file_list.get_current_path();
-                self.file_list.set_path(current_path.clone());
-                update.insert(Update::LAYOUT | Update::DRAW);
-            }
+        for paths in self.delete_confirmations.take() {
+            update |= self.dispatch(FileOperationRequest::DeleteConfirmed(paths), &context);
         }
         
         update
@@ -467,12 +1907,18 @@ pub fn build_window(context: AppContext, state: AppState) -> impl Widget {
     let initial_path = navigation.get_current_path();
     // Clone navigation path signal for reactive subscription
     let navigation_path_signal = navigation.current_path().clone();
+    let recent_locations_signal = navigation.recent_locations().clone();
     let nav_clone = state.navigation.clone();
     drop(navigation);
+    let external_rx = state.external_requests.lock().ok().and_then(|mut rx| rx.take());
+    let preferences = settings::get();
+    nptk_fileman_widgets::color_scheme::set_scheme(preferences.color_scheme);
+    nptk_fileman_widgets::dates::set_relative(preferences.relative_dates);
+    nptk_fileman_widgets::sizes::set_units(preferences.size_units);
+    let window_state = WindowState::load().unwrap_or_default();
 
-    // Create channels for operations and status (async operations still use channels)
-    let (operation_tx, operation_rx) = mpsc::unbounded_channel::<FileOperationRequest>();
-    let (status_tx, status_rx) = mpsc::unbounded_channel::<String>();
+    // Everything below talks over the bus of the app state
+    let bus = state.bus.clone();
     
     // Register keyboard shortcuts
     // TODO: Implement focus text input functionality for "Go to Location" shortcuts
@@ -480,30 +1926,190 @@ pub fn build_window(context: AppContext, state: AppState) -> impl Widget {
         Shortcut::ctrl(KeyCode::KeyL),
         || Update::DRAW, // Placeholder - will implement focus text input later
     );
-    context.shortcut_registry.register(
-        Shortcut::new(KeyCode::F6, nptk::core::window::ModifiersState::empty()),
-        || Update::DRAW, // Placeholder - will implement focus text input later
-    );
+
+    // Focus traversal between the panes; Up, Down and Return go to the focused one
+    let focus = FocusChain::new();
+    let no_modifiers = nptk::core::window::ModifiersState::empty();
+    let shift = nptk::core::window::ModifiersState::SHIFT;
+    for (key, modifiers, forward) in [
+        (KeyCode::Tab, no_modifiers, true),
+        (KeyCode::F6, no_modifiers, true),
+        (KeyCode::Tab, shift, false),
+        (KeyCode::F6, shift, false),
+    ] {
+        let focus = focus.clone();
+        context.shortcut_registry.register(Shortcut::new(key, modifiers), move || {
+            if forward {
+                focus.next();
+            } else {
+                focus.previous();
+            }
+            Update::DRAW
+        });
+    }
+    for (key, pane_key) in [
+        (KeyCode::ArrowUp, PaneKey::Up),
+        (KeyCode::ArrowDown, PaneKey::Down),
+        (KeyCode::Enter, PaneKey::Activate),
+    ] {
+        let focus = focus.clone();
+        context.shortcut_registry.register(Shortcut::new(key, no_modifiers), move || {
+            focus.send_key(pane_key);
+            Update::DRAW
+        });
+    }
+
+    // Refresh (F5) and deep refresh, which also drops cached data (Ctrl+F5)
+    for (modifiers, deep) in [(no_modifiers, false), (nptk::core::window::ModifiersState::CONTROL, true)] {
+        let refresh_bus = bus.clone();
+        context.shortcut_registry.register(Shortcut::new(KeyCode::F5, modifiers), move || {
+            refresh_bus.operation(FileOperationRequest::Refresh { deep });
+            Update::DRAW
+        });
+    }
+
+    // Tab shortcuts
+    let nav_for_new_tab = state.navigation.clone();
+    context.shortcut_registry.register(Shortcut::ctrl(KeyCode::KeyT), move || {
+        if let Ok(mut nav) = nav_for_new_tab.lock() {
+            let path = nav.get_current_path();
+            nav.open_tab(path);
+        }
+        Update::LAYOUT | Update::DRAW
+    });
+    let nav_for_close_tab = state.navigation.clone();
+    context.shortcut_registry.register(Shortcut::ctrl(KeyCode::KeyW), move || {
+        if let Ok(mut nav) = nav_for_close_tab.lock() {
+            let index = nav.active_tab();
+            nav.close_tab(index);
+        }
+        Update::LAYOUT | Update::DRAW
+    });
+    // Next and previous tab (Ctrl+Tab, Ctrl+PageDown / Ctrl+Shift+Tab, Ctrl+PageUp)
+    let control = nptk::core::window::ModifiersState::CONTROL;
+    for (key, modifiers, forward) in [
+        (KeyCode::Tab, control, true),
+        (KeyCode::PageDown, control, true),
+        (KeyCode::Tab, control | shift, false),
+        (KeyCode::PageUp, control, false),
+    ] {
+        let nav_for_cycle = state.navigation.clone();
+        context.shortcut_registry.register(Shortcut::new(key, modifiers), move || {
+            if let Ok(mut nav) = nav_for_cycle.lock() {
+                nav.cycle_tab(forward);
+            }
+            Update::LAYOUT | Update::DRAW
+        });
+    }
+    // Jump to tab N (Alt+1..9)
+    for (index, key) in [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let nav_for_jump = state.navigation.clone();
+        context.shortcut_registry.register(
+            Shortcut::new(key, nptk::core::window::ModifiersState::ALT),
+            move || {
+                if let Ok(mut nav) = nav_for_jump.lock() {
+                    nav.activate_tab(index);
+                }
+                Update::LAYOUT | Update::DRAW
+            },
+        );
+    }
+
+    // Sidebar visibility (F9)
+    let sidebar_visible = StateSignal::new(window_state.sidebar_visible);
+    let toggle_sidebar = sidebar_visible.clone();
+    context.shortcut_registry.register(Shortcut::new(KeyCode::F9, no_modifiers), move || {
+        let visible = *toggle_sidebar.get();
+        toggle_sidebar.set(!visible);
+        Update::LAYOUT | Update::DRAW
+    });
 
     // Create FilemanSidebar
     let mut sidebar = FilemanSidebar::new()
         .with_places(true)
         .with_bookmarks(true)
-        .with_width(200.0);
-    
-    // Take the navigation receiver for FileListWrapper
-    let sidebar_nav_rx = sidebar.take_navigation_receiver()
+        .with_devices(true)
+        .with_recent_locations(recent_locations_signal, 8)
+        .with_width(window_state.sidebar_width)
+        .with_visible_signal(sidebar_visible.clone())
+        .with_focus_chain(focus.clone());
+    if !remotes.is_empty() {
+        for remote in remotes.iter() {
+            if let Err(e) = std::fs::create_dir_all(remote.local_dir()) {
+                log::warn!("Failed to create local copy of remote '{}': {}", remote.name, e);
+            }
+        }
+        let items = remotes.iter().map(Remote::sidebar_item).collect();
+        let menu_bus = bus.clone();
+        let menu_remotes = remotes.clone();
+        sidebar = sidebar
+            .with_custom_section_id("remote", "Remote", items)
+            .with_on_item_context_menu(move |target| {
+                if target.section != "remote" {
+                    return Vec::new();
+                }
+                menu_remotes
+                    .iter()
+                    .position(|r| format!("remote:{}", r.name) == target.item.id)
+                    .map(|index| remote_menu_items(&menu_remotes, index, &menu_bus))
+                    .unwrap_or_default()
+            });
+    }
+    sidebar.spawn_bookmarks_reload();
+    let sidebar_devices = devices::spawn_monitor(sidebar.updater(), bus.clone());
+    templates::spawn_monitor();
+    trash_cleanup::spawn();
+    // Recent Activity can show the jobs of this session
+    operations::activity::session_start();
+
+    // Places picked in the sidebar are navigated to like any other location
+    let mut sidebar_nav_rx = sidebar.take_navigation_receiver()
         .expect("FilemanSidebar should provide navigation receiver");
+    let sidebar_nav_bus = bus.clone();
+    tokio::spawn(async move {
+        while let Some(path) = sidebar_nav_rx.recv().await {
+            sidebar_nav_bus.navigate(crate::toolbar::NavigationAction::NavigateTo(path));
+        }
+    });
+    let mut sidebar_action_rx = sidebar.take_action_receiver()
+        .expect("FilemanSidebar should provide action receiver");
+
+    // Whether ignored files are drawn dimmed (toggled from the sort menu)
+    let dim_ignored = StateSignal::new(false);
+    // Text of the search field in the toolbar
+    let search_query = StateSignal::new(String::new());
+    // Whether the shown folder can be changed (see read_only)
+    let writable = StateSignal::new(true);
+    let remotes = Arc::new(remote::load());
 
     // Create FileList wrapper that syncs with navigation state
     let mut file_list_wrapper = FileListWrapper::new(
         initial_path.clone(),
         nav_clone.clone(),
-        sidebar_nav_rx,
-        operation_rx,
-        status_tx.clone(),
+        bus.clone(),
         navigation_path_signal.clone(),
-    );
+        state.undo.clone(),
+    )
+    .with_external_requests(external_rx)
+    .with_transfer_jobs(spawn_transfer_monitor(bus.clone()))
+    .with_dim_ignored(dim_ignored.clone())
+    .with_search_query(search_query.clone())
+    .with_write_guard(writable.clone(), remotes.clone())
+    .with_device_list(sidebar_devices.clone())
+    .with_focus_chain(focus.clone());
     
     // Set file list to grow and fill remaining space
     file_list_wrapper.set_layout_style(LayoutStyle {
@@ -513,38 +2119,276 @@ pub fn build_window(context: AppContext, state: AppState) -> impl Widget {
         ..Default::default()
     });
 
+    // Show or hide hidden files in the current tab (also in the sort menu)
+    let show_hidden = file_list_wrapper.show_hidden_signal().clone();
+    let hidden_bus = bus.clone();
+    context.shortcut_registry.register(Shortcut::ctrl(KeyCode::KeyH), move || {
+        hidden_bus.operation(FileOperationRequest::ToggleHiddenFiles);
+        Update::LAYOUT | Update::DRAW
+    });
+
+    // Reset the zoom of the current view (Ctrl+0; Ctrl+scroll zooms)
+    let zoom = file_list_wrapper.zoom_signal().clone();
+    let zoom_view_mode = file_list_wrapper.view_mode_signal().clone();
+    context.shortcut_registry.register(Shortcut::ctrl(KeyCode::Digit0), move || {
+        let mut levels = *zoom.get();
+        levels.set_level(*zoom_view_mode.get(), 0);
+        zoom.set(levels);
+        Update::LAYOUT | Update::DRAW
+    });
+
     // Clone selected paths signal from FileList for ToolbarWrapper and StatusBarWrapper
     let selected_paths_signal = file_list_wrapper.selected_paths_signal().clone();
 
+    // Clipboard shortcuts (files are shared with other applications), handled
+    // like the context menu items
+    for (key, action) in [
+        (KeyCode::KeyC, ClipboardAction::Copy),
+        (KeyCode::KeyX, ClipboardAction::Cut),
+    ] {
+        let selected = selected_paths_signal.clone();
+        let clipboard_bus = bus.clone();
+        context.shortcut_registry.register(Shortcut::ctrl(key), move || {
+            let paths = (*selected.get()).clone();
+            if paths.is_empty() {
+                return Update::empty();
+            }
+            clipboard_bus.operation(FileOperationRequest::SetClipboard { paths, action });
+            Update::DRAW
+        });
+    }
+    let selected = selected_paths_signal.clone();
+    let copy_paths_bus = bus.clone();
+    context.shortcut_registry.register(
+        Shortcut::new(
+            KeyCode::KeyC,
+            nptk::core::window::ModifiersState::CONTROL | nptk::core::window::ModifiersState::SHIFT,
+        ),
+        move || {
+            let paths = (*selected.get()).clone();
+            if paths.is_empty() {
+                return Update::empty();
+            }
+            copy_paths_bus.operation(FileOperationRequest::CopyPaths { paths, names_only: false });
+            Update::DRAW
+        },
+    );
+    // Delete moves the selection to the trash, Shift+Delete deletes it permanently
+    for (modifiers, permanently) in [(no_modifiers, false), (shift, true)] {
+        let selected = selected_paths_signal.clone();
+        let delete_bus = bus.clone();
+        context.shortcut_registry.register(Shortcut::new(KeyCode::Delete, modifiers), move || {
+            let paths = (*selected.get()).clone();
+            if paths.is_empty() {
+                return Update::empty();
+            }
+            delete_bus.operation(if permanently {
+                FileOperationRequest::Delete(paths)
+            } else {
+                FileOperationRequest::Trash(paths)
+            });
+            Update::DRAW
+        });
+    }
+    for (shortcut, request) in [
+        (
+            Shortcut::new(
+                KeyCode::KeyP,
+                nptk::core::window::ModifiersState::CONTROL | nptk::core::window::ModifiersState::SHIFT,
+            ),
+            FileOperationRequest::CommandPalette,
+        ),
+        (Shortcut::ctrl(KeyCode::KeyZ), FileOperationRequest::Undo),
+        (
+            Shortcut::new(
+                KeyCode::KeyZ,
+                nptk::core::window::ModifiersState::CONTROL | nptk::core::window::ModifiersState::SHIFT,
+            ),
+            FileOperationRequest::Redo,
+        ),
+    ] {
+        let undo_bus = bus.clone();
+        context.shortcut_registry.register(shortcut, move || {
+            undo_bus.operation(request.clone());
+            Update::DRAW
+        });
+    }
+    for (shortcut, hard_links) in [
+        (Shortcut::ctrl(KeyCode::KeyV), false),
+        (
+            Shortcut::new(
+                KeyCode::KeyV,
+                nptk::core::window::ModifiersState::CONTROL | nptk::core::window::ModifiersState::SHIFT,
+            ),
+            true,
+        ),
+    ] {
+        let paste_nav = nav_clone.clone();
+        let paste_bus = bus.clone();
+        context.shortcut_registry.register(shortcut, move || {
+            if let Ok(nav) = paste_nav.lock() {
+                let destination = nav.get_current_path();
+                paste_bus.operation(FileOperationRequest::Paste { destination, hard_links });
+            }
+            Update::DRAW
+        });
+    }
+
+    // Information panel visibility (toggled from the toolbar)
+    let info_panel_visible = StateSignal::new(window_state.info_panel_visible);
+
     // Create ToolbarWrapper
-    let (mut toolbar_wrapper, toolbar_nav_tx) = crate::toolbar::ToolbarWrapper::new(
+    let mut toolbar_wrapper = crate::toolbar::ToolbarWrapper::new(
         nav_clone.clone(),
-        operation_tx.clone(),
+        bus.clone(),
         navigation_path_signal.clone(),
         selected_paths_signal.clone(),
         file_list_wrapper.view_mode_signal().clone(),
+        file_list_wrapper.sort_signal().clone(),
+        info_panel_visible.clone(),
+        dim_ignored.clone(),
+        file_list_wrapper.show_hidden_signal().clone(),
+        writable,
     );
+    toolbar_wrapper = toolbar_wrapper
+        .with_focus_chain(focus.clone())
+        .with_search(search_query)
+        .with_thumbnails(file_list_wrapper.thumbnails_signal().clone());
+
+    // Handle actions picked from sidebar item context menus
+    let sidebar_action_bus = bus.clone();
+    tokio::spawn(async move {
+        use nptk_fileman_widgets::fileman_sidebar::SidebarItemAction;
+        while let Some((action, target)) = sidebar_action_rx.recv().await {
+            // Mounting and unmounting devices; other actions use the mount point below
+            let device_action = matches!(
+                action,
+                SidebarItemAction::Mount | SidebarItemAction::Unmount | SidebarItemAction::Eject
+            );
+            let device = (device_action && target.section == "devices")
+                .then(|| sidebar_devices.lock().ok()?.iter().find(|d| d.id == target.item.id).cloned())
+                .flatten();
+            if let Some(device) = device {
+                let result = match action {
+                    SidebarItemAction::Mount if device.is_locked() => {
+                        sidebar_action_bus.operation(FileOperationRequest::Unlock(device));
+                        continue;
+                    }
+                    SidebarItemAction::Eject => {
+                        sidebar_action_bus.operation(FileOperationRequest::Eject(Some(device)));
+                        continue;
+                    }
+                    SidebarItemAction::Mount => devices::mount(&device).await.map(|path| {
+                        sidebar_action_bus.navigate(crate::toolbar::NavigationAction::NavigateTo(path));
+                        format!("Mounted {}", device.label)
+                    }),
+                    _ => devices::unmount(&device).await.map(|()| format!("Unmounted {}", device.label)),
+                };
+                let message = result.unwrap_or_else(|e| {
+                    log::error!("{}", e);
+                    format!("Error: {}", e)
+                });
+                sidebar_action_bus.status(message);
+                continue;
+            }
+            let path = target
+                .item
+                .uri
+                .as_deref()
+                .and_then(nptk::services::thumbnail::npio_adapter::uri_to_path);
+            match (action, path) {
+                (SidebarItemAction::OpenInNewTab, Some(path)) => {
+                    sidebar_action_bus.navigate(crate::toolbar::NavigationAction::OpenInNewTab(path));
+                }
+                (SidebarItemAction::Properties, Some(path)) => {
+                    sidebar_action_bus.operation(FileOperationRequest::Properties(vec![path]));
+                }
+                (SidebarItemAction::Mount | SidebarItemAction::Unmount | SidebarItemAction::Eject, _) => {
+                    sidebar_action_bus.status("Only devices can be mounted");
+                }
+                (action, None) => {
+                    log::warn!("Sidebar action {:?} for item without a local path", action);
+                }
+            }
+        }
+    });
 
     // Create FileLocationBar
     use nptk_fileman_widgets::location_bar::FileLocationBar;
+    use nptk_fileman_widgets::path_completer::PathCompleter;
     
-    let nav_tx_clone = toolbar_nav_tx.clone();
+    let location_bus = bus.clone();
     let location_bar = FileLocationBar::new(navigation_path_signal.clone())
+        .with_path_completer(PathCompleter::new())
+        .with_show_hidden_signal(show_hidden.clone())
+        .with_focus_chain(focus.clone())
         .with_on_navigate(move |path| {
-             let _ = nav_tx_clone.send(crate::toolbar::NavigationAction::NavigateTo(path));
+             location_bus.navigate(crate::toolbar::NavigationAction::NavigateTo(path));
              Update::DRAW
         });
 
+    // Create FileInfoPanel
+    use nptk_fileman_widgets::info_panel::{FileInfoPanel, InfoPanelAction};
+
+    // The status bar and the info panel show the same selection total
+    let selection_sizer = nptk_fileman_widgets::selection_size::SelectionSizer::new();
+
+    let info_bus = bus.clone();
+    let info_panel = FileInfoPanel::new(
+        navigation_path_signal.clone(),
+        selected_paths_signal.clone(),
+    )
+    .with_visible_signal(info_panel_visible.clone())
+    .with_selection_sizer(selection_sizer.clone())
+    .with_on_action(move |action, paths| {
+        match action {
+            InfoPanelAction::Open => {
+                let bus = info_bus.clone();
+                tokio::spawn(async move {
+                    for path in paths {
+                        if tokio::fs::metadata(&path).await.is_ok_and(|m| m.is_dir()) {
+                            bus.navigate(crate::toolbar::NavigationAction::NavigateTo(path));
+                            break;
+                        } else if let Err(e) = std::process::Command::new("xdg-open").arg(&path).spawn() {
+                            log::warn!("Failed to open {:?}: {}", path, e);
+                        }
+                    }
+                });
+            }
+            InfoPanelAction::Properties => {
+                info_bus.operation(FileOperationRequest::Properties(paths));
+            }
+        }
+        Update::DRAW
+    });
+
     // Create FileStatusBar
     use nptk_fileman_widgets::status_bar::FileStatusBar;
-    
+
+    let (status_rx, toast_rx) = bus.status_receivers();
+    let show_hidden_bus = bus.clone();
     let statusbar = FileStatusBar::new(
         navigation_path_signal.clone(),
         selected_paths_signal.clone(),
-    ).with_message_receiver(status_rx);
+    ).with_message_receiver(status_rx)
+    .with_toast_receiver(toast_rx)
+    .with_hidden_count(file_list_wrapper.hidden_count_signal().clone(), move || {
+        show_hidden_bus.operation(FileOperationRequest::ToggleHiddenFiles);
+    })
+    .with_selection_sizer(selection_sizer)
+    .with_focus_chain(focus);
+
+    // Expose the panes to screen readers in reading order
+    crate::accessibility::spawn(vec![
+        toolbar_wrapper.accessibility(),
+        sidebar.accessibility(),
+        file_list_wrapper.accessibility(),
+        statusbar.accessibility(),
+    ]);
 
-    // Build main layout
-    Container::new(vec![
+    // Build main layout, remembered with the window geometry
+    let sidebar_width = sidebar.width();
+    let main_layout = Container::new(vec![
         // Toolbar area
         Box::new(Container::new(vec![
             Box::new(toolbar_wrapper),
@@ -555,10 +2399,13 @@ pub fn build_window(context: AppContext, state: AppState) -> impl Widget {
             gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(4.0)),
             ..Default::default()
         })),
+        // Tabs (hidden while only one is open)
+        Box::new(crate::tab_bar::TabBar::new(nav_clone.clone())),
         // Content area (sidebar + file list)
         Box::new(Container::new(vec![
             Box::new(sidebar),
             Box::new(file_list_wrapper),
+            Box::new(info_panel),
         ]).with_layout_style(LayoutStyle {
             size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
             flex_direction: FlexDirection::Row,
@@ -571,5 +2418,157 @@ pub fn build_window(context: AppContext, state: AppState) -> impl Widget {
         size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
         flex_direction: FlexDirection::Column,
         ..Default::default()
-    })
+    });
+    WindowStateTracker::new(main_layout, window_state, sidebar_visible, info_panel_visible, sidebar_width)
+}
+
+/// Copy or move the files on the clipboard (or, with `hard_links`, link them)
+/// into `target` if it is a folder, otherwise next to it, as a job of the
+/// operations daemon.
+fn paste(target: PathBuf, hard_links: bool, bus: AppBus, undoable_jobs: Arc<Mutex<Vec<u32>>>) {
+    tokio::spawn(async move {
+        let destination = match tokio::fs::metadata(&target).await {
+            Ok(metadata) if metadata.is_dir() => target,
+            _ => target.parent().map(PathBuf::from).unwrap_or(target),
+        };
+        // Reading the clipboard waits on the owning application
+        let files = tokio::task::spawn_blocking(clipboard::get_files)
+            .await
+            .unwrap_or_else(|e| Err(format!("Failed to read the clipboard: {}", e)));
+        let kind = match files {
+            Ok(Some(ClipboardFiles { paths: sources, .. })) if hard_links => {
+                operations::engine::OperationKind::CreateHardLink { sources, destination }
+            },
+            Ok(Some(ClipboardFiles { action: ClipboardAction::Copy, paths: sources })) => {
+                operations::engine::OperationKind::Copy { sources, destination }
+            },
+            Ok(Some(ClipboardFiles { action: ClipboardAction::Cut, paths: sources })) => {
+                operations::engine::OperationKind::Move { sources, destination }
+            },
+            Ok(None) => {
+                bus.status("Nothing to paste");
+                return;
+            },
+            Err(e) => {
+                bus.status(format!("Error: {}", e));
+                return;
+            },
+        };
+        queue_job(kind, &bus, &undoable_jobs).await;
+    });
+}
+
+/// Queue `kind` as a single job of the operations daemon, however many items
+/// it has; the transfer monitor sums up what it did once it ends. Copies
+/// follow symbolic links if the preferences say so.
+async fn queue_job(kind: operations::engine::OperationKind, bus: &AppBus, undoable_jobs: &Mutex<Vec<u32>>) {
+    let follow_symlinks = matches!(kind, operations::engine::OperationKind::Copy { .. })
+        && settings::get().copy_follow_symlinks;
+    let operation = operations::engine::Operation::new(kind).with_follow_symlinks(follow_symlinks);
+    match daemon::client::queue(&operation).await {
+        Ok(id) => track_undoable(undoable_jobs, id),
+        Err(e) => {
+            log::error!("{}", e);
+            bus.status(format!("Error: {}", e));
+        },
+    }
+}
+
+/// Add what the daemon job `id` did to the window's undo stack once it is done
+fn track_undoable(undoable_jobs: &Mutex<Vec<u32>>, id: u32) {
+    if let Ok(mut jobs) = undoable_jobs.lock() {
+        jobs.push(id);
+    }
+}
+
+/// Sidebar context menu items synchronizing the remote at `index` with its local copy.
+fn remote_menu_items(
+    remotes: &Arc<Vec<Remote>>,
+    index: usize,
+    bus: &AppBus,
+) -> Vec<MenuItem> {
+    let item = |id: u32, label: &str, direction: Option<bool>| {
+        let remotes = remotes.clone();
+        let bus = bus.clone();
+        MenuItem::new(MenuCommand::Custom(id), label).with_action(move || {
+            let remotes = remotes.clone();
+            let bus = bus.clone();
+            tokio::spawn(async move {
+                let remote = &remotes[index];
+                bus.status(format!("Synchronizing {}...", remote.name));
+                let result = match direction {
+                    None => remote::pull(remote)
+                        .await
+                        .map(|n| format!("Downloaded {} file(s) from {}", n, remote.name)),
+                    Some(mirror) => remote::push(remote, mirror)
+                        .await
+                        .map(|n| format!("Updated {} object(s) in {}", n, remote.name)),
+                };
+                let message = result.unwrap_or_else(|e| {
+                    log::error!("{}", e);
+                    format!("Error: {}", e)
+                });
+                bus.status(message);
+            });
+            Update::DRAW
+        })
+    };
+    vec![
+        item(0x6101, "Download From Remote", None),
+        item(0x6102, "Upload to Remote", Some(false)),
+        item(0x6103, "Mirror to Remote", Some(true)),
+    ]
+}
+
+/// Poll the background operation daemon and report running jobs, and the
+/// number of items ended jobs did and failed on, in the status bar.
+///
+/// Jobs outlive the window that queued them, so a new window picks them up here.
+fn spawn_transfer_monitor(bus: AppBus) -> Arc<Mutex<Vec<JobInfo>>> {
+    let jobs = Arc::new(Mutex::new(Vec::new()));
+    let shared = jobs.clone();
+    tokio::spawn(async move {
+        let mut last_message = String::new();
+        // Jobs that were still active at the previous poll
+        let mut active: Vec<u32> = Vec::new();
+        loop {
+            let current = daemon::client::jobs().await;
+            // Ended jobs are summed up once, instead of reporting each item
+            let mut summary = None;
+            for job in current.iter().filter(|job| active.contains(&job.id) && !job.state.is_active()) {
+                let description = format!("{} ({})", job.description, job.counts());
+                match &job.state {
+                    JobState::Finished => feedback::transfer_ended(&description, None),
+                    JobState::Failed(e) => feedback::transfer_ended(&description, Some(e)),
+                    _ => {},
+                }
+                summary = Some(match &job.state {
+                    JobState::Cancelled => format!("{}: cancelled, {}", job.description, job.counts()),
+                    _ => format!("{}: {}", job.description, job.counts()),
+                });
+            }
+            active = current.iter().filter(|job| job.state.is_active()).map(|job| job.id).collect();
+            let message = current
+                .iter()
+                .find_map(|job| match &job.state {
+                    JobState::Running => Some(format!("{}: {}", job.description, job.progress())),
+                    JobState::Conflict { .. } => Some(format!("{} (waiting for an answer)", job.description)),
+                    JobState::Error { message, .. } => Some(format!("Error: {}", message)),
+                    JobState::Failed(e) => Some(format!("Error: {}", e)),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            if let Some(summary) = summary {
+                bus.status(summary);
+            } else if !message.is_empty() && message != last_message {
+                bus.status(message.clone());
+            }
+            last_message = message;
+            if let Ok(mut jobs) = shared.lock() {
+                *jobs = current;
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+    });
+    jobs
 }
@@ -0,0 +1,316 @@
+//! Window geometry and layout, kept across sessions
+//!
+//! Stored as `key = value` lines in `$XDG_STATE_HOME/fileman/window`: the size,
+//! position and maximized state of the window and which panels are shown. The
+//! window is opened with the saved state, and changes are saved shortly after
+//! they were made. Reset Layout ([request_reset]) brings the panels back to
+//! their defaults, keeping the size and position of the window.
+//!
+//! The size is known from the toolkit. Where the window is and whether it is
+//! maximized is asked from the window manager on X11; on Wayland clients aren't
+//! told, so the position is left to the compositor there.
+
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::config::MayConfig;
+use nptk::core::vg::kurbo::Point;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk_fileman_widgets::xdg;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _};
+use x11rb::rust_connection::RustConnection;
+
+/// How long the state has to stay unchanged before it is saved, so resizing
+/// doesn't write the file on every frame
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// Smallest size restored, in case the saved one is unusable
+const MIN_SIZE: (f64, f64) = (400.0, 300.0);
+
+/// Set by [request_reset], carried out by the tracker on its next update
+static RESET_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowState {
+    /// Size of the window when it isn't maximized
+    pub width: f64,
+    pub height: f64,
+    /// Position of the window frame when it isn't maximized, if known
+    pub position: Option<(i32, i32)>,
+    pub maximized: bool,
+    pub sidebar_width: f32,
+    pub sidebar_visible: bool,
+    pub info_panel_visible: bool,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            width: 1000.0,
+            height: 700.0,
+            position: None,
+            maximized: false,
+            sidebar_width: 200.0,
+            sidebar_visible: true,
+            info_panel_visible: false,
+        }
+    }
+}
+
+fn state_path() -> PathBuf {
+    xdg::state_home().join("fileman").join("window")
+}
+
+impl WindowState {
+    /// The saved state, `None` if there is none; invalid values keep their defaults.
+    pub fn load() -> Option<Self> {
+        let content = std::fs::read_to_string(state_path()).ok()?;
+        let mut state = WindowState::default();
+        let (mut x, mut y) = (None, None);
+        for line in content.lines().map(str::trim) {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "width" => state.width = value.parse().unwrap_or(state.width).max(MIN_SIZE.0),
+                "height" => state.height = value.parse().unwrap_or(state.height).max(MIN_SIZE.1),
+                "x" => x = value.parse().ok(),
+                "y" => y = value.parse().ok(),
+                "maximized" => state.maximized = value == "true",
+                "sidebar_width" => state.sidebar_width = value.parse().unwrap_or(state.sidebar_width),
+                "sidebar_visible" => state.sidebar_visible = value != "false",
+                "info_panel_visible" => state.info_panel_visible = value == "true",
+                other => log::debug!("Ignoring unknown window state {}", other),
+            }
+        }
+        state.position = x.zip(y);
+        Some(state)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = state_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create state directory: {}", e))?;
+        }
+        let mut content = format!(
+            "width = {}\nheight = {}\nmaximized = {}\nsidebar_width = {}\nsidebar_visible = {}\ninfo_panel_visible = {}\n",
+            self.width, self.height, self.maximized, self.sidebar_width, self.sidebar_visible, self.info_panel_visible,
+        );
+        if let Some((x, y)) = self.position {
+            content.push_str(&format!("x = {}\ny = {}\n", x, y));
+        }
+        std::fs::write(&path, content).map_err(|e| format!("Failed to save the window state: {}", e))
+    }
+
+    /// Open the window as it was saved.
+    pub fn apply(&self, config: &mut MayConfig) {
+        config.window.size = Vector2::new(self.width, self.height);
+        config.window.maximized = self.maximized;
+        if let Some((x, y)) = self.position {
+            config.window.position = Some(Point::new(x as f64, y as f64));
+        }
+    }
+}
+
+/// Bring the panels back to their default visibility and width. The sidebar
+/// width is applied at the next start.
+pub fn request_reset() {
+    RESET_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+fn x11_error(e: impl std::fmt::Display) -> String {
+    format!("Failed to read the window placement: {}", e)
+}
+
+/// Position of the frame of this process's window and whether it is maximized,
+/// as the X11 window manager sees it.
+fn x11_placement() -> Result<((i32, i32), bool), String> {
+    let (conn, screen_num) = RustConnection::connect(None).map_err(x11_error)?;
+    let root = conn.setup().roots[screen_num].root;
+    let intern = |name: &str| -> Result<u32, String> {
+        Ok(conn
+            .intern_atom(false, name.as_bytes())
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?
+            .atom)
+    };
+    let cardinals = |window: u32, property: u32, kind: AtomEnum, length: u32| -> Vec<u32> {
+        conn.get_property(false, window, property, kind, 0, length)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|reply| reply.value32().map(|values| values.collect()))
+            .unwrap_or_default()
+    };
+    let client_list = intern("_NET_CLIENT_LIST")?;
+    let wm_pid = intern("_NET_WM_PID")?;
+    let wm_state = intern("_NET_WM_STATE")?;
+    let maximized_vert = intern("_NET_WM_STATE_MAXIMIZED_VERT")?;
+    let maximized_horz = intern("_NET_WM_STATE_MAXIMIZED_HORZ")?;
+    let frame_extents = intern("_NET_FRAME_EXTENTS")?;
+
+    let pid = std::process::id();
+    let window = cardinals(root, client_list, AtomEnum::WINDOW, u32::MAX)
+        .into_iter()
+        .find(|&window| cardinals(window, wm_pid, AtomEnum::CARDINAL, 1).first() == Some(&pid))
+        .ok_or_else(|| x11_error("no window of this process found"))?;
+
+    let states = cardinals(window, wm_state, AtomEnum::ATOM, 32);
+    let maximized = states.contains(&maximized_vert) && states.contains(&maximized_horz);
+    let origin = conn
+        .translate_coordinates(window, root, 0, 0)
+        .map_err(x11_error)?
+        .reply()
+        .map_err(x11_error)?;
+    // Left, right, top and bottom border added by the window manager
+    let extents = cardinals(window, frame_extents, AtomEnum::CARDINAL, 4);
+    let (left, top) = match extents.as_slice() {
+        [left, _, top, _] => (*left as i32, *top as i32),
+        _ => (0, 0),
+    };
+    Ok(((origin.dst_x as i32 - left, origin.dst_y as i32 - top), maximized))
+}
+
+/// Wraps the window contents and saves the window state when it changes.
+pub struct WindowStateTracker {
+    inner: Container,
+    sidebar_visible: StateSignal<bool>,
+    info_panel_visible: StateSignal<bool>,
+    sidebar_width: f32,
+    // State the window was opened with or last saved
+    saved: WindowState,
+    // Latest state waiting for the save, shared with the task saving it
+    pending: Arc<Mutex<Option<WindowState>>>,
+    signals_hooked: bool,
+}
+
+impl WindowStateTracker {
+    pub fn new(
+        inner: Container,
+        saved: WindowState,
+        sidebar_visible: StateSignal<bool>,
+        info_panel_visible: StateSignal<bool>,
+        sidebar_width: f32,
+    ) -> Self {
+        Self {
+            inner,
+            sidebar_visible,
+            info_panel_visible,
+            sidebar_width,
+            saved,
+            pending: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        }
+    }
+
+    /// Save `state` once it stopped changing. The placement on screen is looked
+    /// up then, as the window manager has caught up with a move or resize.
+    fn save_later(&self, state: WindowState) {
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        let saving = pending.is_some();
+        *pending = Some(state);
+        if saving {
+            return;
+        }
+        let pending = self.pending.clone();
+        tokio::spawn(async move {
+            let mut last = None;
+            loop {
+                tokio::time::sleep(SAVE_DELAY).await;
+                let current = pending.lock().ok().and_then(|p| p.clone());
+                if current == last {
+                    break;
+                }
+                last = current;
+            }
+            let Some(mut state) = pending.lock().ok().and_then(|mut p| p.take()) else {
+                return;
+            };
+            tokio::task::spawn_blocking(move || {
+                if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+                    match x11_placement() {
+                        Ok((position, maximized)) => {
+                            state.maximized = maximized;
+                            if !maximized {
+                                state.position = Some(position);
+                            }
+                        },
+                        Err(e) => log::debug!("{}", e),
+                    }
+                }
+                // A maximized window keeps the size and position to restore it to
+                if let Some(previous) = WindowState::load().filter(|_| state.maximized) {
+                    state.width = previous.width;
+                    state.height = previous.height;
+                    state.position = previous.position;
+                }
+                if let Err(e) = state.save() {
+                    log::warn!("{}", e);
+                }
+            });
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for WindowStateTracker {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        StyleNode {
+            style: LayoutStyle {
+                size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
+                ..Default::default()
+            },
+            children: vec![self.inner.layout_style(context)],
+            measure_func: None,
+        }
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.sidebar_visible);
+            context.hook_signal(&mut self.info_panel_visible);
+            self.signals_hooked = true;
+        }
+
+        let mut update = Update::empty();
+        if RESET_REQUESTED.swap(false, Ordering::SeqCst) {
+            let defaults = WindowState::default();
+            self.sidebar_visible.set(defaults.sidebar_visible);
+            self.info_panel_visible.set(defaults.info_panel_visible);
+            self.sidebar_width = defaults.sidebar_width;
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        let current = WindowState {
+            width: info.size.x,
+            height: info.size.y,
+            sidebar_width: self.sidebar_width,
+            sidebar_visible: *self.sidebar_visible.get(),
+            info_panel_visible: *self.info_panel_visible.get(),
+            ..self.saved.clone()
+        };
+        if current != self.saved && current.width > 0.0 && current.height > 0.0 {
+            self.save_later(current.clone());
+            self.saved = current;
+        }
+
+        if let Some(child) = layout.children.first() {
+            update |= self.inner.update(child, context, info).await;
+        }
+        update
+    }
+
+    fn render(&mut self, graphics: &mut dyn Graphics, layout: &LayoutNode, info: &mut AppInfo, context: AppContext) {
+        if let Some(child) = layout.children.first() {
+            self.inner.render(graphics, child, info, context);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Accessibility metadata
+//!
+//! Widgets describe what they show as a small tree of [AccessibleNode]s with roles,
+//! names and selection state, and publish it through an [AccessibleSubtree] handle
+//! taken before the widget is added to the layout. The application collects the
+//! handles and exposes the combined tree to assistive technologies.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// What an accessible node is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessibleRole {
+    List,
+    ListItem,
+    Toolbar,
+    Button,
+    /// Landmark around the places, bookmarks and devices
+    Navigation,
+    /// Group of items under a heading
+    Group,
+    StatusBar,
+}
+
+/// One node of the accessibility tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibleNode {
+    pub role: AccessibleRole,
+    pub name: String,
+    pub description: Option<String>,
+    /// `Some` for items that can be selected
+    pub selected: Option<bool>,
+    pub disabled: bool,
+    /// Changes of the name are announced by screen readers
+    pub live: bool,
+    pub children: Vec<AccessibleNode>,
+}
+
+impl AccessibleNode {
+    pub fn new(role: AccessibleRole, name: impl Into<String>) -> Self {
+        Self {
+            role,
+            name: name.into(),
+            description: None,
+            selected: None,
+            disabled: false,
+            live: false,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_selected(mut self, selected: bool) -> Self {
+        self.selected = Some(selected);
+        self
+    }
+
+    pub fn with_disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+
+    /// Announce changes of the name (status messages).
+    pub fn live(mut self) -> Self {
+        self.live = true;
+        self
+    }
+
+    pub fn with_children(mut self, children: Vec<AccessibleNode>) -> Self {
+        self.children = children;
+        self
+    }
+}
+
+/// Shared slot a widget publishes its accessibility tree into.
+#[derive(Clone, Default)]
+pub struct AccessibleSubtree {
+    node: Arc<Mutex<Option<AccessibleNode>>>,
+    changed: Arc<AtomicBool>,
+}
+
+impl AccessibleSubtree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish `node`, marking the subtree changed if it differs from the last one.
+    pub fn set(&self, node: AccessibleNode) {
+        let Ok(mut current) = self.node.lock() else {
+            return;
+        };
+        if current.as_ref() != Some(&node) {
+            *current = Some(node);
+            self.changed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// The last published tree, if any.
+    pub fn get(&self) -> Option<AccessibleNode> {
+        self.node.lock().ok()?.clone()
+    }
+
+    /// Whether the tree changed since the last call.
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::SeqCst)
+    }
+}
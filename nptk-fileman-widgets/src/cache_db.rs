@@ -0,0 +1,32 @@
+//! SQLite databases in `$XDG_CACHE_HOME/fileman`, shared by the persistent caches.
+
+use crate::xdg;
+use rusqlite::Connection;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Cache entries not used for this long are removed when their cache is opened
+pub(crate) const UNUSED_ENTRY_LIFETIME: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+fn cache_dir() -> PathBuf {
+    xdg::cache_home().join("fileman")
+}
+
+pub(crate) fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Open (creating if needed) the database `file_name` and apply `schema` to it.
+pub(crate) fn open(file_name: &str, schema: &str) -> Result<Connection, String> {
+    let dir = cache_dir();
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    let connection = Connection::open(dir.join(file_name))
+        .map_err(|e| format!("Failed to open cache {}: {}", file_name, e))?;
+    connection
+        .execute_batch(schema)
+        .map_err(|e| format!("Failed to create cache {}: {}", file_name, e))?;
+    Ok(connection)
+}
@@ -0,0 +1,188 @@
+//! Color schemes
+//!
+//! The widgets draw with the toolkit [Palette] through [color], which swaps in a
+//! built-in light, dark or high-contrast scheme when one is selected with
+//! [set_scheme]. A change shows on the next frame. Text drawn over selections and
+//! emblem outlines are picked by contrast so they stay legible in every scheme.
+
+use nptk::core::theme::{ColorRole, Palette};
+use nptk::core::vg::peniko::Color;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Contrast ratio text needs against its background (WCAG AA)
+const TEXT_CONTRAST: f32 = 4.5;
+/// Contrast ratio graphical marks need against their surroundings (WCAG AA)
+const GRAPHIC_CONTRAST: f32 = 3.0;
+
+static SCHEME: AtomicU8 = AtomicU8::new(ColorScheme::System as u8);
+
+/// Colors the widgets draw with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    /// The toolkit's palette
+    #[default]
+    System,
+    Light,
+    Dark,
+    /// White on black with a yellow selection
+    HighContrast,
+}
+
+impl ColorScheme {
+    pub const ALL: [ColorScheme; 4] = [
+        ColorScheme::System,
+        ColorScheme::Light,
+        ColorScheme::Dark,
+        ColorScheme::HighContrast,
+    ];
+
+    /// Name in the settings file
+    pub fn key(self) -> &'static str {
+        match self {
+            ColorScheme::System => "system",
+            ColorScheme::Light => "light",
+            ColorScheme::Dark => "dark",
+            ColorScheme::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scheme| scheme.key() == key)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorScheme::System => "System",
+            ColorScheme::Light => "Light",
+            ColorScheme::Dark => "Dark",
+            ColorScheme::HighContrast => "High Contrast",
+        }
+    }
+
+    /// The scheme after this one (for cycling through them with a button).
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|s| *s == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Color of `role` in this scheme, if the scheme defines it.
+    fn color(self, role: ColorRole) -> Option<Color> {
+        let rgb = match (self, role) {
+            (ColorScheme::System, _) => return None,
+
+            (ColorScheme::Light, ColorRole::Window) => (0xf6, 0xf5, 0xf4),
+            (ColorScheme::Light, ColorRole::BaseText) => (0x1e, 0x1e, 0x1e),
+            (ColorScheme::Light, ColorRole::Selection) => (0x1c, 0x71, 0xd8),
+            (ColorScheme::Light, ColorRole::HoverHighlight) => (0xe2, 0xe1, 0xdf),
+            (ColorScheme::Light, ColorRole::ThreedShadow1) => (0xc0, 0xbf, 0xbc),
+            (ColorScheme::Light, ColorRole::DisabledTextFront) => (0x6e, 0x6d, 0x71),
+
+            (ColorScheme::Dark, ColorRole::Window) => (0x24, 0x24, 0x24),
+            (ColorScheme::Dark, ColorRole::BaseText) => (0xf0, 0xf0, 0xf0),
+            (ColorScheme::Dark, ColorRole::Selection) => (0x35, 0x84, 0xe4),
+            (ColorScheme::Dark, ColorRole::HoverHighlight) => (0x3a, 0x3a, 0x3a),
+            (ColorScheme::Dark, ColorRole::ThreedShadow1) => (0x48, 0x48, 0x48),
+            (ColorScheme::Dark, ColorRole::DisabledTextFront) => (0xa0, 0x9f, 0x9c),
+
+            (ColorScheme::HighContrast, ColorRole::Window) => (0x00, 0x00, 0x00),
+            (ColorScheme::HighContrast, ColorRole::BaseText) => (0xff, 0xff, 0xff),
+            (ColorScheme::HighContrast, ColorRole::Selection) => (0xff, 0xff, 0x00),
+            (ColorScheme::HighContrast, ColorRole::HoverHighlight) => (0x3a, 0x3a, 0x3a),
+            (ColorScheme::HighContrast, ColorRole::ThreedShadow1) => (0xff, 0xff, 0xff),
+            (ColorScheme::HighContrast, ColorRole::DisabledTextFront) => (0xc8, 0xc8, 0xc8),
+
+            _ => return None,
+        };
+        Some(Color::from_rgb8(rgb.0, rgb.1, rgb.2))
+    }
+}
+
+/// Switch the widgets to `scheme`.
+pub fn set_scheme(scheme: ColorScheme) {
+    SCHEME.store(scheme as u8, Ordering::Relaxed);
+}
+
+/// The scheme the widgets draw with.
+pub fn scheme() -> ColorScheme {
+    let value = SCHEME.load(Ordering::Relaxed);
+    ColorScheme::ALL
+        .into_iter()
+        .find(|scheme| *scheme as u8 == value)
+        .unwrap_or_default()
+}
+
+/// Color of `role` in the current scheme, falling back to the toolkit palette.
+pub fn color(palette: &Palette, role: ColorRole) -> Color {
+    scheme().color(role).unwrap_or_else(|| palette.color(role))
+}
+
+/// Fill of selected items, `alpha` being how strongly the selection tints them.
+///
+/// Selections are opaque in the high-contrast scheme.
+pub fn selection(palette: &Palette, alpha: f32) -> Color {
+    let alpha = if scheme() == ColorScheme::HighContrast {
+        1.0
+    } else {
+        alpha
+    };
+    color(palette, ColorRole::Selection).with_alpha(alpha)
+}
+
+/// Color for text drawn over `background` (which may be translucent over the
+/// window): the regular text color if it is legible there, otherwise black or white.
+pub fn text_on(palette: &Palette, background: Color) -> Color {
+    let background = over(background, color(palette, ColorRole::Window));
+    let text = color(palette, ColorRole::BaseText);
+    if contrast(text, background) >= TEXT_CONTRAST {
+        return text;
+    }
+    legible_on(background)
+}
+
+/// Outline of an emblem of color `emblem`, separating it from the icon under it.
+pub fn emblem_outline(palette: &Palette, emblem: Color) -> Color {
+    let window = color(palette, ColorRole::Window);
+    if contrast(emblem, window) >= GRAPHIC_CONTRAST {
+        return window;
+    }
+    legible_on(emblem)
+}
+
+/// Black or white, whichever stands out more against `background`.
+fn legible_on(background: Color) -> Color {
+    if contrast(Color::BLACK, background) >= contrast(Color::WHITE, background) {
+        Color::BLACK
+    } else {
+        Color::WHITE
+    }
+}
+
+/// `top` composited over the opaque `bottom`.
+fn over(top: Color, bottom: Color) -> Color {
+    let [r, g, b, a] = top.components;
+    let [br, bg, bb, _] = bottom.components;
+    Color::new([
+        r * a + br * (1.0 - a),
+        g * a + bg * (1.0 - a),
+        b * a + bb * (1.0 - a),
+        1.0,
+    ])
+}
+
+/// WCAG relative luminance.
+fn luminance(color: Color) -> f32 {
+    let [r, g, b, _] = color.components.map(|c| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// WCAG contrast ratio, from 1 (none) to 21 (black on white).
+fn contrast(a: Color, b: Color) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
@@ -0,0 +1,73 @@
+//! Commands posted by buttons
+//!
+//! A button evaluates its `on_pressed` signal whenever it reads it, which can be
+//! more than once for a single press. Side effects in that signal can therefore
+//! fire spuriously. Instead, buttons post a command to a [CommandQueue] and the
+//! widget owning the queue takes the posted commands in its update and acts on
+//! each once.
+
+use nptk::core::app::update::Update;
+use nptk::core::signal::MaybeSignal;
+use nptk::core::signal::eval::EvalSignal;
+use std::sync::{Arc, Mutex};
+
+/// Commands posted by buttons, waiting for the widget that owns them. Clones
+/// share the queue.
+pub struct CommandQueue<C> {
+    pending: Arc<Mutex<Vec<C>>>,
+}
+
+impl<C> Clone for CommandQueue<C> {
+    fn clone(&self) -> Self {
+        Self {
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<C> Default for CommandQueue<C> {
+    fn default() -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl<C: Clone + PartialEq + 'static> CommandQueue<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Post `command` unless it is already waiting, so evaluating a press more
+    /// than once still posts it once.
+    pub fn post(&self, command: C) {
+        if let Ok(mut pending) = self.pending.lock() {
+            if !pending.contains(&command) {
+                pending.push(command);
+            }
+        }
+    }
+
+    /// `on_pressed` signal for a button that posts `command`.
+    pub fn on_pressed(&self, command: C) -> MaybeSignal<Update> {
+        let queue = self.clone();
+        MaybeSignal::signal(Box::new(EvalSignal::new(move || {
+            queue.post(command.clone());
+            Update::DRAW
+        })))
+    }
+
+    /// Take the posted commands, oldest first.
+    pub fn take(&self) -> Vec<C> {
+        self.pending
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default()
+    }
+
+    /// Take the oldest posted command, leaving the others.
+    pub fn take_first(&self) -> Option<C> {
+        let mut pending = self.pending.lock().ok()?;
+        (!pending.is_empty()).then(|| pending.remove(0))
+    }
+}
@@ -0,0 +1,218 @@
+//! File conflict dialog
+//!
+//! Shown when a copy or move would overwrite an existing file. Renders a preview of
+//! the source and the destination (icon, size, modification date) and lets the user
+//! pick an [OverwritePolicy], optionally for all remaining conflicts.
+
+use chrono::{DateTime, Local};
+use nptk::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::commands::CommandQueue;
+use crate::file_names;
+use crate::sizes;
+
+/// How to resolve a conflict between a source and an existing destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverwritePolicy {
+    /// Replace the destination with the source.
+    Overwrite,
+    /// Leave the destination untouched and skip the source.
+    Skip,
+    /// Keep both by writing the source under a non-colliding name.
+    KeepBoth,
+    /// Replace the destination only if the source is newer.
+    OverwriteIfNewer,
+    /// Abort the whole operation.
+    Cancel,
+}
+
+impl OverwritePolicy {
+    /// Decide where (if anywhere) `source` should be written for a conflicting
+    /// `destination`. Returns `None` when the source should not be written.
+    pub fn resolve_destination(self, source: &Path, destination: &Path) -> Option<PathBuf> {
+        match self {
+            OverwritePolicy::Overwrite => Some(destination.to_path_buf()),
+            OverwritePolicy::Skip | OverwritePolicy::Cancel => None,
+            OverwritePolicy::KeepBoth => Some(keep_both_path(destination)),
+            OverwritePolicy::OverwriteIfNewer => {
+                let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+                match (modified(source), modified(destination)) {
+                    (Some(src), Some(dst)) if src > dst => Some(destination.to_path_buf()),
+                    _ => None,
+                }
+            },
+        }
+    }
+}
+
+/// The user's answer to a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConflictResolution {
+    pub policy: OverwritePolicy,
+    /// Apply the same policy to all remaining conflicts of the operation.
+    pub apply_to_all: bool,
+}
+
+/// Generate a non-colliding sibling of `path` ("name (2).ext", "name (3).ext", ...).
+pub fn keep_both_path(path: &Path) -> PathBuf {
+    file_names::free_path(path)
+}
+
+/// Handle to a shown conflict dialog, polled by the operation executor.
+#[derive(Clone)]
+pub struct ConflictDialogHandle {
+    answers: CommandQueue<ConflictResolution>,
+}
+
+impl ConflictDialogHandle {
+    /// Take the resolution if the user has answered.
+    pub fn try_take(&self) -> Option<ConflictResolution> {
+        self.answers.take_first()
+    }
+}
+
+/// Dialog asking how to resolve a conflict between `source` and `destination`.
+pub struct ConflictDialog {
+    source: PathBuf,
+    destination: PathBuf,
+    remaining_conflicts: usize,
+    answers: CommandQueue<ConflictResolution>,
+}
+
+impl ConflictDialog {
+    /// Create a dialog for a single conflict.
+    pub fn new(source: PathBuf, destination: PathBuf) -> Self {
+        Self {
+            source,
+            destination,
+            remaining_conflicts: 0,
+            answers: CommandQueue::new(),
+        }
+    }
+
+    /// Number of further conflicts in the same operation. When non-zero, the
+    /// "... All" buttons are offered.
+    pub fn with_remaining_conflicts(mut self, remaining: usize) -> Self {
+        self.remaining_conflicts = remaining;
+        self
+    }
+
+    /// Show the dialog as a popup and return a handle to poll for the answer.
+    pub fn show(self, context: &AppContext) -> ConflictDialogHandle {
+        let handle = ConflictDialogHandle {
+            answers: self.answers.clone(),
+        };
+        let title = format!(
+            "\"{}\" already exists",
+            self.destination
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        );
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self.build()), &title, (480, 260), (300, 200));
+        handle
+    }
+
+    fn preview(label: &str, path: &Path) -> Container {
+        let (icon_name, details) = match fs::metadata(path) {
+            Ok(meta) => {
+                let icon_name = if meta.is_dir() { "folder" } else { "text-x-generic" };
+                let size = if meta.is_dir() {
+                    "Folder".to_string()
+                } else {
+                    sizes::format(meta.len())
+                };
+                let modified = meta
+                    .modified()
+                    .map(|t| {
+                        let dt: DateTime<Local> = t.into();
+                        dt.format("%Y-%m-%d %H:%M").to_string()
+                    })
+                    .unwrap_or_else(|_| "Unknown date".to_string());
+                (icon_name, format!("{}, modified {}", size, modified))
+            },
+            Err(_) => ("dialog-question", "Unavailable".to_string()),
+        };
+
+        Container::new(vec![
+            Box::new(Icon::new(icon_name, 48, None)),
+            Box::new(
+                Container::new(vec![
+                    Box::new(Text::new(label.to_string()).with_font_size(13.0)),
+                    Box::new(Text::new(path.display().to_string()).with_font_size(12.0)),
+                    Box::new(Text::new(details).with_font_size(12.0)),
+                ])
+                .with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Column,
+                    flex_grow: 1.0,
+                    ..Default::default()
+                }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Row,
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            ..Default::default()
+        })
+    }
+
+    fn button(&self, label: &str, policy: OverwritePolicy, apply_to_all: bool) -> Button {
+        Button::new(Text::new(label.to_string()))
+            .with_on_pressed(self.answers.on_pressed(ConflictResolution { policy, apply_to_all }))
+    }
+
+    fn build(&self) -> Container {
+        let mut buttons: Vec<Box<dyn Widget>> = vec![
+            Box::new(self.button("Cancel", OverwritePolicy::Cancel, true)),
+            Box::new(self.button("Skip", OverwritePolicy::Skip, false)),
+        ];
+        if self.remaining_conflicts > 0 {
+            buttons.push(Box::new(self.button("Skip All", OverwritePolicy::Skip, true)));
+        }
+        buttons.push(Box::new(self.button("Keep Both", OverwritePolicy::KeepBoth, false)));
+        buttons.push(Box::new(self.button("Replace", OverwritePolicy::Overwrite, false)));
+        if self.remaining_conflicts > 0 {
+            buttons.push(Box::new(self.button("Replace All", OverwritePolicy::Overwrite, true)));
+            buttons.push(Box::new(self.button(
+                "Replace Older",
+                OverwritePolicy::OverwriteIfNewer,
+                true,
+            )));
+        }
+
+        let mut children: Vec<Box<dyn Widget>> = vec![
+            Box::new(Self::preview("Replace:", &self.destination)),
+            Box::new(Self::preview("With:", &self.source)),
+        ];
+        if self.remaining_conflicts > 0 {
+            children.push(Box::new(Text::new(format!(
+                "{} more conflict(s) in this operation",
+                self.remaining_conflicts
+            ))));
+        }
+        children.push(Box::new(Container::new(buttons).with_layout_style(LayoutStyle {
+            flex_direction: FlexDirection::Row,
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            justify_content: Some(JustifyContent::FlexEnd),
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            ..Default::default()
+        })));
+
+        Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(12.0)),
+            ..Default::default()
+        })
+    }
+}
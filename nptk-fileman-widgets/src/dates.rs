@@ -0,0 +1,83 @@
+//! Formatting of file dates
+//!
+//! Dates are formatted for the user's locale (`LC_ALL`, `LC_TIME` or `LANG`). With
+//! relative dates on ([set_relative]), recent ones read "5 minutes ago" or
+//! "Yesterday 14:02"; views showing them redraw every [REFRESH_INTERVAL] so the
+//! labels stay current.
+
+use chrono::{DateTime, Local, Locale};
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// How often relative dates are brought up to date
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+static RELATIVE: AtomicBool = AtomicBool::new(false);
+static LOCALE: LazyLock<Locale> = LazyLock::new(user_locale);
+
+/// Show recent dates relative to now.
+pub fn set_relative(relative: bool) {
+    RELATIVE.store(relative, Ordering::Relaxed);
+}
+
+/// Whether recent dates are shown relative to now.
+pub fn is_relative() -> bool {
+    RELATIVE.load(Ordering::Relaxed)
+}
+
+/// The locale of dates from the environment, POSIX if unset or unknown.
+fn user_locale() -> Locale {
+    let name = ["LC_ALL", "LC_TIME", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    // "de_DE.UTF-8@euro" -> "de_DE"
+    let name = name.split(['.', '@']).next().unwrap_or_default();
+    Locale::try_from(name).unwrap_or(Locale::POSIX)
+}
+
+/// Format `time` as set: relative if it is recent and relative dates are on,
+/// otherwise as the locale's date and time.
+pub fn format(time: SystemTime) -> String {
+    let time: DateTime<Local> = time.into();
+    if is_relative() {
+        if let Some(relative) = format_relative(time, Local::now()) {
+            return relative;
+        }
+    }
+    time.format_localized("%x %H:%M", *LOCALE).to_string()
+}
+
+/// `time` relative to `now`, if it is within the last week.
+fn format_relative(time: DateTime<Local>, now: DateTime<Local>) -> Option<String> {
+    let age = now.signed_duration_since(time);
+    let minutes = age.num_minutes();
+    let hours = age.num_hours();
+    let days = (now.date_naive() - time.date_naive()).num_days();
+    let clock = time.format("%H:%M");
+    let relative = if age.num_seconds() < -60 {
+        return None;
+    } else if minutes < 1 {
+        // Slightly in the future (clock skew on network filesystems) counts as now
+        "Just now".to_string()
+    } else if minutes == 1 {
+        "1 minute ago".to_string()
+    } else if minutes < 60 {
+        format!("{} minutes ago", minutes)
+    } else if hours == 1 {
+        "1 hour ago".to_string()
+    } else if hours < 6 {
+        format!("{} hours ago", hours)
+    } else if days == 0 {
+        format!("Today {}", clock)
+    } else if days == 1 {
+        format!("Yesterday {}", clock)
+    } else if days < 7 {
+        time.format_localized("%A %H:%M", *LOCALE).to_string()
+    } else {
+        return None;
+    };
+    Some(relative)
+}
@@ -0,0 +1,384 @@
+//! Desktop entries (`.desktop` launchers)
+//!
+//! Trusted launchers are listed with the name and icon they declare instead of
+//! their file name and the generic icon of the type, in the user's language
+//! like the descriptions of [crate::mime_types]. A launcher can claim any name
+//! and icon, so untrusted ones keep their file name. Launchers are read when
+//! the listing loads (see [LauncherLabels]) and again only after they were
+//! modified.
+
+use crate::mime_types::LANGUAGES;
+use crate::xdg;
+use nptk::services::filesystem::entry::FileEntry;
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// What a launcher starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DesktopEntryKind {
+    /// A program, started with the `Exec` line
+    Application { exec: String, terminal: bool, working_dir: Option<PathBuf> },
+    /// A web address or file, opened with its default application
+    Link { url: String },
+    /// A folder; nothing is started
+    Directory,
+}
+
+/// The keys of the `[Desktop Entry]` group the file manager uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopEntry {
+    pub name: String,
+    /// Icon name from the theme, or the path of an image
+    pub icon: Option<String>,
+    pub kind: DesktopEntryKind,
+}
+
+/// Whether `path` is named like a launcher.
+pub fn is_launcher(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "desktop")
+}
+
+/// The launcher at `path`, if it is a valid one.
+pub fn load(path: &Path) -> Option<DesktopEntry> {
+    let content = fs::read_to_string(path).ok()?;
+    parse(&content)
+}
+
+/// `applications` folders of the XDG data directories
+fn application_dirs() -> Vec<PathBuf> {
+    xdg::data_dirs().into_iter().map(|dir| dir.join("applications")).collect()
+}
+
+/// Whether the launcher at `path` may be started without asking: it is in
+/// an application folder of the system or the user, or marked executable.
+pub fn is_trusted(path: &Path) -> bool {
+    let executable = fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o100 != 0);
+    executable || application_dirs().iter().any(|dir| path.starts_with(dir))
+}
+
+/// Name and icon a launcher declares, with the modification time it was read at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Label {
+    modified: SystemTime,
+    trusted: bool,
+    name: String,
+    /// Icon name from the theme; images given by path aren't used
+    icon: Option<String>,
+}
+
+/// Names and icons of the launchers of a listing, read once when it loads
+/// instead of every time an entry is drawn.
+#[derive(Debug, Clone, Default)]
+pub struct LauncherLabels(HashMap<PathBuf, Label>);
+
+impl LauncherLabels {
+    /// Read the launchers among `entries`, taking those not modified since
+    /// from `previous`. Whether they are trusted is checked again.
+    pub fn read(entries: &[FileEntry], previous: &LauncherLabels) -> Self {
+        let labels = entries
+            .iter()
+            .filter(|entry| entry.is_file() && is_launcher(&entry.path))
+            .filter_map(|entry| {
+                let modified = entry.metadata.modified;
+                let trusted = is_trusted(&entry.path);
+                if let Some(label) = previous.0.get(&entry.path).filter(|label| label.modified == modified) {
+                    return Some((entry.path.clone(), Label { trusted, ..label.clone() }));
+                }
+                let launcher = load(&entry.path)?;
+                let label = Label {
+                    modified,
+                    trusted,
+                    name: launcher.name,
+                    icon: launcher.icon.filter(|icon| !icon.starts_with('/')),
+                };
+                Some((entry.path.clone(), label))
+            })
+            .collect();
+        Self(labels)
+    }
+
+    /// Take the launchers among the `changed` entries from `read`, a reading
+    /// of just those, keeping the others.
+    pub fn update(&mut self, changed: &[PathBuf], read: LauncherLabels) {
+        for path in changed {
+            self.0.remove(path);
+        }
+        self.0.extend(read.0);
+    }
+
+    fn trusted(&self, path: &Path) -> Option<&Label> {
+        self.0.get(path).filter(|label| label.trusted)
+    }
+
+    /// Name shown for an entry: the name a trusted launcher declares,
+    /// otherwise the file name.
+    pub fn display_name<'a>(&'a self, entry: &'a FileEntry) -> &'a str {
+        self.trusted(&entry.path).map_or(&entry.name, |label| &label.name)
+    }
+
+    /// Icon from the theme a trusted launcher declares.
+    pub fn icon_name(&self, path: &Path) -> Option<&str> {
+        self.trusted(path)?.icon.as_deref()
+    }
+}
+
+/// Read the `[Desktop Entry]` group; `None` without a name or without what
+/// its type needs (`Exec` or `URL`).
+pub fn parse(content: &str) -> Option<DesktopEntry> {
+    let mut values: HashMap<&str, &str> = HashMap::new();
+    let mut in_group = false;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            in_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if let (true, Some((key, value))) = (in_group, line.split_once('=')) {
+            values.entry(key.trim()).or_insert(value.trim());
+        }
+    }
+
+    let localized = |key: &str| {
+        LANGUAGES
+            .iter()
+            .find_map(|language| values.get(format!("{}[{}]", key, language).as_str()))
+            .or_else(|| values.get(key))
+            .map(|value| unescape(value))
+    };
+    let kind = match values.get("Type").copied().unwrap_or("Application") {
+        "Application" => DesktopEntryKind::Application {
+            exec: values.get("Exec").map(|exec| unescape(exec))?,
+            terminal: values.get("Terminal").is_some_and(|value| *value == "true"),
+            working_dir: values.get("Path").filter(|path| !path.is_empty()).map(PathBuf::from),
+        },
+        "Link" => DesktopEntryKind::Link { url: values.get("URL").map(|url| unescape(url))? },
+        "Directory" => DesktopEntryKind::Directory,
+        _ => return None,
+    };
+    Some(DesktopEntry {
+        name: localized("Name").filter(|name| !name.is_empty())?,
+        icon: localized("Icon").filter(|icon| !icon.is_empty()),
+        kind,
+    })
+}
+
+/// Escapes of string values ("\s", "\n", "\t", "\r", "\\")
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => text.push(' '),
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some('r') => text.push('\r'),
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+impl DesktopEntry {
+    /// Program and arguments of the `Exec` line of the launcher at `path`.
+    ///
+    /// Arguments are split at spaces outside double quotes. Nothing is passed
+    /// to open, so the file codes (`%f`, `%U`, ...) are dropped; `%i`, `%c` and
+    /// `%k` become the icon, the name and the launcher itself.
+    pub fn command(&self, path: &Path) -> Result<Vec<String>, String> {
+        let DesktopEntryKind::Application { exec, .. } = &self.kind else {
+            return Err(format!("{} doesn't start a program", self.name));
+        };
+        let mut args = Vec::new();
+        for word in split_exec(exec)? {
+            match word.as_str() {
+                "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => {},
+                "%i" => {
+                    if let Some(icon) = &self.icon {
+                        args.extend(["--icon".to_string(), icon.clone()]);
+                    }
+                },
+                _ => args.push(self.expand_codes(&word, path)),
+            }
+        }
+        if args.is_empty() {
+            return Err(format!("{} has an empty Exec line", self.name));
+        }
+        Ok(args)
+    }
+
+    /// Expand `%c`, `%k` and `%%` in one pass over `word`, so the name and the
+    /// path are never scanned for codes themselves.
+    fn expand_codes(&self, word: &str, path: &Path) -> String {
+        let mut expanded = String::new();
+        let mut chars = word.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('c') => expanded.push_str(&self.name),
+                Some('k') => expanded.push_str(&path.to_string_lossy()),
+                Some('%') => expanded.push('%'),
+                Some(code) => expanded.extend(['%', code]),
+                None => expanded.push('%'),
+            }
+        }
+        expanded
+    }
+}
+
+/// Split an `Exec` line into words; inside double quotes, `\` escapes `"`,
+/// `` ` ``, `$` and `\`.
+fn split_exec(exec: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut in_word = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            },
+            '\\' if quoted => word.extend(chars.next()),
+            ' ' | '\t' if !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            },
+            _ => {
+                word.push(c);
+                in_word = true;
+            },
+        }
+    }
+    if quoted {
+        return Err(format!("Unterminated quote in Exec line: {}", exec));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nptk::services::filesystem::entry::{FileMetadata, FileType};
+
+    fn entry(path: &Path) -> FileEntry {
+        let metadata = fs::metadata(path).unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let file_metadata = FileMetadata {
+            size: metadata.len(),
+            modified: metadata.modified().unwrap(),
+            created: metadata.created().ok(),
+            permissions: metadata.permissions().mode(),
+            mime_type: None,
+            is_hidden: false,
+        };
+        FileEntry::new(path.to_path_buf(), name, FileType::File, file_metadata, path.parent().map(Path::to_path_buf))
+    }
+
+    #[test]
+    fn parses_an_application() {
+        let launcher = parse(
+            "# A comment\n\
+             [Desktop Entry]\n\
+             Type=Application\n\
+             Name = Text\\sEditor\n\
+             Name[xx_TEST]=Not this one\n\
+             Icon=accessories-text-editor\n\
+             Exec=editor %F\n\
+             Terminal=true\n\
+             Path=/tmp\n\
+             Name=Second name\n\
+             [Desktop Action New]\n\
+             Exec=ignored\n",
+        )
+        .unwrap();
+        assert_eq!(launcher.name, "Text Editor");
+        assert_eq!(launcher.icon.as_deref(), Some("accessories-text-editor"));
+        assert_eq!(
+            launcher.kind,
+            DesktopEntryKind::Application {
+                exec: "editor %F".to_string(),
+                terminal: true,
+                working_dir: Some(PathBuf::from("/tmp")),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_incomplete_launchers() {
+        assert_eq!(parse("[Desktop Entry]\nExec=editor\n"), None);
+        assert_eq!(parse("[Desktop Entry]\nName=\nExec=editor\n"), None);
+        assert_eq!(parse("[Desktop Entry]\nName=Editor\n"), None);
+        assert_eq!(parse("[Desktop Entry]\nType=Link\nName=Site\n"), None);
+        assert_eq!(parse("[Desktop Entry]\nType=Service\nName=Daemon\nExec=daemon\n"), None);
+        assert_eq!(parse("[Other]\nName=Editor\nExec=editor\n"), None);
+        assert_eq!(
+            parse("[Desktop Entry]\nType=Link\nName=Site\nURL=https://example.org\n").map(|l| l.kind),
+            Some(DesktopEntryKind::Link { url: "https://example.org".to_string() })
+        );
+    }
+
+    #[test]
+    fn unescapes_values() {
+        assert_eq!(unescape(r"a\sb\tc\nd\re\\f\"), "a b\tc\nd\re\\f\\");
+    }
+
+    #[test]
+    fn builds_the_command() {
+        let launcher =
+            parse("[Desktop Entry]\nName=Viewer\nIcon=viewer\nExec=\"/opt/my viewer\" --title %c %U 100%% %i\n")
+                .unwrap();
+        assert_eq!(
+            launcher.command(Path::new("/tmp/viewer.desktop")).unwrap(),
+            ["/opt/my viewer", "--title", "Viewer", "100%", "--icon", "viewer"]
+        );
+        assert_eq!(split_exec(r#"sh -c "echo \"\$HOME\"""#).unwrap(), ["sh", "-c", "echo \"$HOME\""]);
+        assert!(split_exec("editor \"unterminated").is_err());
+        assert!(parse("[Desktop Entry]\nName=Empty\nExec=%f\n").unwrap().command(Path::new("x")).is_err());
+    }
+
+    #[test]
+    fn expands_codes_in_one_pass() {
+        let launcher = parse("[Desktop Entry]\nName=50%k off\nExec=tool %%c --name=%c %k%%\n").unwrap();
+        assert_eq!(
+            launcher.command(Path::new("/tmp/sale.desktop")).unwrap(),
+            ["tool", "%c", "--name=50%k off", "/tmp/sale.desktop%"]
+        );
+    }
+
+    #[test]
+    fn untrusted_launchers_keep_their_file_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("invoice.pdf.desktop");
+        fs::write(&path, "[Desktop Entry]\nName=invoice.pdf\nIcon=application-pdf\nExec=sh -c evil\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let listed = entry(&path);
+        let labels = LauncherLabels::read(std::slice::from_ref(&listed), &LauncherLabels::default());
+        assert_eq!(labels.display_name(&listed), "invoice.pdf.desktop");
+        assert_eq!(labels.icon_name(&path), None);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o744)).unwrap();
+        let labels = LauncherLabels::read(std::slice::from_ref(&listed), &labels);
+        assert_eq!(labels.display_name(&listed), "invoice.pdf");
+        assert_eq!(labels.icon_name(&path), Some("application-pdf"));
+    }
+}
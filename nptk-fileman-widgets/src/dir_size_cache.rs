@@ -0,0 +1,205 @@
+//! Persistent cache of directory sizes
+//!
+//! For every directory walked, the total size of the files directly inside it and
+//! the list of its subdirectories are stored with the directory's modification
+//! time in `$XDG_CACHE_HOME/fileman/dir-sizes.sqlite`. A directory whose mtime is
+//! unchanged is not read again; only its subdirectories are checked, so an
+//! unchanged tree costs one `stat` per directory instead of one per file.
+//!
+//! Adding, removing or renaming entries updates a directory's mtime, but writing
+//! to an existing file does not, so a file that grew in place is only counted
+//! anew once its directory changes, or once the cache is told to [forget] it.
+
+use crate::cache_db::{self, UNUSED_ENTRY_LIFETIME, unix_seconds};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The open cache, `None` until first use or when it can't be opened
+static CACHE: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// Cached contents of one directory (subdirectories are stored NUL-separated)
+struct CachedDirectory {
+    files_size: u64,
+    subdirectories: Vec<PathBuf>,
+}
+
+/// Modification time in nanoseconds, precise enough to tell quick changes apart
+fn mtime_nanos(metadata: &fs::Metadata) -> Option<i64> {
+    let modified = metadata.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos() as i64)
+}
+
+fn open() -> Result<Connection, String> {
+    let connection = cache_db::open(
+        "dir-sizes.sqlite",
+        "CREATE TABLE IF NOT EXISTS directories (
+            path TEXT PRIMARY KEY,
+            mtime INTEGER NOT NULL,
+            files_size INTEGER NOT NULL,
+            subdirectories TEXT NOT NULL,
+            last_used INTEGER NOT NULL
+        );",
+    )?;
+    let expired = unix_seconds(SystemTime::now() - UNUSED_ENTRY_LIFETIME);
+    if let Err(e) = connection.execute(
+        "DELETE FROM directories WHERE last_used < ?1",
+        params![expired],
+    ) {
+        log::warn!("Failed to prune size cache: {}", e);
+    }
+    Ok(connection)
+}
+
+fn lookup(connection: &Connection, path: &str, mtime: i64) -> Option<CachedDirectory> {
+    let row = connection
+        .query_row(
+            "SELECT files_size, subdirectories FROM directories WHERE path = ?1 AND mtime = ?2",
+            params![path, mtime],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to read size cache: {}", e);
+            None
+        })?;
+    let _ = connection.execute(
+        "UPDATE directories SET last_used = ?2 WHERE path = ?1",
+        params![path, unix_seconds(SystemTime::now())],
+    );
+    Some(CachedDirectory {
+        files_size: row.0 as u64,
+        subdirectories: row
+            .1
+            .split('\0')
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+    })
+}
+
+fn store(connection: &Connection, path: &str, mtime: i64, directory: &CachedDirectory) {
+    let subdirectories = directory
+        .subdirectories
+        .iter()
+        .map(|p| p.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\0");
+    if let Err(e) = connection.execute(
+        "INSERT OR REPLACE INTO directories (path, mtime, files_size, subdirectories, last_used)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            path,
+            mtime,
+            directory.files_size as i64,
+            subdirectories,
+            unix_seconds(SystemTime::now())
+        ],
+    ) {
+        log::warn!("Failed to write size cache: {}", e);
+    }
+}
+
+/// Read the files and subdirectories directly inside `path`.
+fn scan(path: &Path) -> CachedDirectory {
+    let mut directory = CachedDirectory {
+        files_size: 0,
+        subdirectories: Vec::new(),
+    };
+    let Ok(entries) = fs::read_dir(path) else {
+        return directory;
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        // Symlinks are counted by their own size and not followed, so loops can't occur
+        if file_type.is_dir() {
+            directory.subdirectories.push(entry.path());
+        } else if let Ok(metadata) = entry.metadata() {
+            directory.files_size = directory.files_size.saturating_add(metadata.len());
+        }
+    }
+    directory
+}
+
+fn walk(connection: Option<&Connection>, path: &Path) -> u64 {
+    let Ok(metadata) = fs::metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    let key = path.to_string_lossy();
+    let mtime = mtime_nanos(&metadata);
+    let cached = match (connection, mtime) {
+        (Some(connection), Some(mtime)) => lookup(connection, &key, mtime),
+        _ => None,
+    };
+    let directory = cached.unwrap_or_else(|| {
+        let directory = scan(path);
+        if let (Some(connection), Some(mtime)) = (connection, mtime) {
+            store(connection, &key, mtime, &directory);
+        }
+        directory
+    });
+
+    directory
+        .subdirectories
+        .iter()
+        .fold(directory.files_size, |total, subdirectory| {
+            total.saturating_add(walk(connection, subdirectory))
+        })
+}
+
+/// Drop what is cached about `path` and the directories below it, so the next
+/// size computed for them reads them again.
+pub fn forget(path: &Path) {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.is_none() {
+        match open() {
+            Ok(connection) => *cache = Some(connection),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+    let Some(connection) = cache.as_ref() else {
+        return;
+    };
+    let key = path.to_string_lossy();
+    let below = format!("{}/", key.trim_end_matches('/'));
+    if let Err(e) = connection.execute(
+        "DELETE FROM directories WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2",
+        params![key, below],
+    ) {
+        log::warn!("Failed to clear size cache: {}", e);
+    }
+}
+
+/// Total size of the files below `path` (or the size of `path` if it is a file).
+///
+/// Blocks while unchanged parts are checked and changed parts are read, so call it
+/// off the UI thread for large trees.
+pub fn directory_size(path: &Path) -> u64 {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.is_none() {
+        match open() {
+            Ok(connection) => *cache = Some(connection),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+    let connection = cache.as_ref();
+    // One transaction for the whole walk keeps writes to the cache cheap
+    if let Some(connection) = connection {
+        let _ = connection.execute_batch("BEGIN");
+    }
+    let size = walk(connection, path);
+    if let Some(connection) = connection {
+        if let Err(e) = connection.execute_batch("COMMIT") {
+            log::warn!("Failed to write size cache: {}", e);
+        }
+    }
+    size
+}
@@ -0,0 +1,178 @@
+//! Filesystem lookups for UI callbacks
+//!
+//! Widgets must not touch the filesystem while handling input: on a slow or hung
+//! mount a single `stat` freezes the window. The lookups here answer at once with
+//! what is known and check the filesystem on a blocking thread, calling
+//! `on_change` (usually to schedule a redraw) when the answer changed. Answers
+//! are rechecked once they are older than [MAX_AGE].
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long an answer is used before it is checked again
+pub const MAX_AGE: Duration = Duration::from_secs(2);
+
+/// Answers beyond this many are dropped once they are stale
+const MAX_ENTRIES: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Query {
+    /// Nearest folder at or above a path that exists
+    ExistingAncestor(PathBuf),
+    /// Subfolders of a folder
+    Folders(PathBuf),
+    /// Everything in a folder
+    Entries(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Answer {
+    Path(PathBuf),
+    Folders(Arc<Vec<PathBuf>>),
+    Entries(Arc<Vec<(PathBuf, bool)>>),
+}
+
+struct Entry {
+    answer: Option<Answer>,
+    checked: Option<Instant>,
+    pending: bool,
+}
+
+static CACHE: LazyLock<Mutex<HashMap<Query, Entry>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn is_stale(entry: &Entry) -> bool {
+    entry.checked.is_none_or(|checked| checked.elapsed() > MAX_AGE)
+}
+
+fn answer(query: &Query) -> Answer {
+    match query {
+        Query::ExistingAncestor(path) => {
+            let existing = path
+                .ancestors()
+                .find(|p| p.exists())
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from("/"));
+            Answer::Path(existing)
+        },
+        Query::Folders(path) => {
+            let folders = list(path).into_iter().filter(|(_, is_dir)| *is_dir).map(|(path, _)| path).collect();
+            Answer::Folders(Arc::new(folders))
+        },
+        Query::Entries(path) => Answer::Entries(Arc::new(list(path))),
+    }
+}
+
+/// The entries of `path` and whether each is a folder or a link to one.
+fn list(path: &Path) -> Vec<(PathBuf, bool)> {
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| {
+                    let is_dir = entry
+                        .file_type()
+                        .is_ok_and(|t| t.is_dir() || (t.is_symlink() && entry.path().is_dir()));
+                    (entry.path(), is_dir)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Store a fresh answer; returns whether it differs from the previous one
+fn store(query: Query, fresh: Answer) -> bool {
+    let Ok(mut cache) = CACHE.lock() else {
+        return false;
+    };
+    let entry = cache.entry(query).or_insert(Entry { answer: None, checked: None, pending: false });
+    entry.pending = false;
+    entry.checked = Some(Instant::now());
+    let changed = entry.answer.as_ref() != Some(&fresh);
+    entry.answer = Some(fresh);
+    changed
+}
+
+/// Known answer to `query`, checking it in the background if it is stale
+fn lookup(query: Query, on_change: impl FnOnce() + Send + 'static) -> Option<Answer> {
+    let mut cache = CACHE.lock().ok()?;
+    if cache.len() > MAX_ENTRIES {
+        cache.retain(|_, entry| entry.pending || !is_stale(entry));
+    }
+    let entry = cache
+        .entry(query.clone())
+        .or_insert(Entry { answer: None, checked: None, pending: false });
+    let known = entry.answer.clone();
+    if !entry.pending && is_stale(entry) {
+        entry.pending = true;
+        tokio::task::spawn_blocking(move || {
+            let fresh = answer(&query);
+            if store(query, fresh) {
+                on_change();
+            }
+        });
+    }
+    known
+}
+
+/// Nearest folder at or above `path` that exists, `None` until first checked.
+pub fn existing_ancestor(path: &Path, on_change: impl FnOnce() + Send + 'static) -> Option<PathBuf> {
+    match lookup(Query::ExistingAncestor(path.to_path_buf()), on_change)? {
+        Answer::Path(existing) => Some(existing),
+        _ => None,
+    }
+}
+
+/// Subfolders of `path` (hidden ones included, in no particular order),
+/// `None` until first listed.
+pub fn folders(path: &Path, on_change: impl FnOnce() + Send + 'static) -> Option<Arc<Vec<PathBuf>>> {
+    match lookup(Query::Folders(path.to_path_buf()), on_change)? {
+        Answer::Folders(folders) => Some(folders),
+        _ => None,
+    }
+}
+
+/// Answer to `query`, waiting for it to be checked unless the known answer is
+/// recent. `None` if the check failed to run.
+async fn fresh_answer(query: Query) -> Option<Answer> {
+    if let Ok(cache) = CACHE.lock() {
+        if let Some(Entry { answer: Some(known), .. }) = cache.get(&query).filter(|entry| !is_stale(entry)) {
+            return Some(known.clone());
+        }
+    }
+    let checked = {
+        let query = query.clone();
+        tokio::task::spawn_blocking(move || answer(&query)).await.ok()?
+    };
+    store(query, checked.clone());
+    Some(checked)
+}
+
+/// Subfolders of `path`, waiting for them to be listed unless the known
+/// listing is recent.
+pub async fn list_folders(path: PathBuf) -> Arc<Vec<PathBuf>> {
+    match fresh_answer(Query::Folders(path)).await {
+        Some(Answer::Folders(folders)) => folders,
+        _ => Arc::new(Vec::new()),
+    }
+}
+
+/// Everything in `path` and whether each is a folder (hidden entries
+/// included, in no particular order), waiting for it to be listed unless the
+/// known listing is recent.
+pub async fn list_entries(path: PathBuf) -> Arc<Vec<(PathBuf, bool)>> {
+    match fresh_answer(Query::Entries(path)).await {
+        Some(Answer::Entries(entries)) => entries,
+        _ => Arc::new(Vec::new()),
+    }
+}
+
+/// Drop all answers about `path` and the paths below it.
+pub fn forget(path: &Path) {
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.retain(|query, _| match query {
+            Query::ExistingAncestor(p) | Query::Folders(p) | Query::Entries(p) => !p.starts_with(path),
+        });
+    }
+}
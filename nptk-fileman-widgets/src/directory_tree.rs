@@ -0,0 +1,370 @@
+//! Directory tree widget
+//!
+//! A reusable, lazily expanded tree of directories. Children of a node are only read
+//! from disk the first time the node is expanded. The selected directory is exposed
+//! as a signal so file managers and file choosers can follow it.
+
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::text_render::TextRenderContext;
+use nptk::core::theme::ColorRole;
+use crate::color_scheme;
+use nptk::core::vg::kurbo::{Affine, Rect, Shape};
+use nptk::core::vg::peniko::{Brush, Fill};
+use nptk::core::vgi::Graphics;
+use nptk::core::window::{ElementState, MouseButton};
+use nptk::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single node of the directory tree.
+#[derive(Debug, Clone)]
+struct TreeNode {
+    path: PathBuf,
+    name: String,
+    depth: usize,
+    expanded: bool,
+    /// `None` until the node is expanded for the first time.
+    children: Option<Vec<TreeNode>>,
+}
+
+impl TreeNode {
+    fn new(path: PathBuf, depth: usize) -> Self {
+        let name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        Self {
+            path,
+            name,
+            depth,
+            expanded: false,
+            children: None,
+        }
+    }
+
+    /// Read the sub-directories of this node from disk.
+    fn load_children(&mut self, show_hidden: bool) {
+        let mut children: Vec<TreeNode> = match fs::read_dir(&self.path) {
+            Ok(entries) => entries
+                .flatten()
+                .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+                .filter(|e| show_hidden || !e.file_name().to_string_lossy().starts_with('.'))
+                .map(|e| TreeNode::new(e.path(), self.depth + 1))
+                .collect(),
+            Err(e) => {
+                log::warn!("Failed to read directory {:?}: {}", self.path, e);
+                Vec::new()
+            },
+        };
+        children.sort_by_key(|n| n.name.to_lowercase());
+        self.children = Some(children);
+    }
+
+    /// Find a node by path (mutable).
+    fn find_mut(&mut self, path: &Path) -> Option<&mut TreeNode> {
+        if self.path == path {
+            return Some(self);
+        }
+        if !path.starts_with(&self.path) {
+            return None;
+        }
+        self.children
+            .as_mut()?
+            .iter_mut()
+            .find_map(|child| child.find_mut(path))
+    }
+
+    /// Append the visible rows (self and expanded descendants) to `rows`.
+    fn collect_visible<'a>(&'a self, rows: &mut Vec<&'a TreeNode>) {
+        rows.push(self);
+        if self.expanded {
+            if let Some(children) = &self.children {
+                for child in children {
+                    child.collect_visible(rows);
+                }
+            }
+        }
+    }
+}
+
+/// A lazily expanded tree of directories.
+pub struct DirectoryTree {
+    root: TreeNode,
+    selected_path: StateSignal<Option<PathBuf>>,
+    show_hidden: StateSignal<bool>,
+    last_show_hidden: bool,
+    on_select: Option<Box<dyn Fn(PathBuf) -> Update + Send + Sync>>,
+    layout_style: MaybeSignal<LayoutStyle>,
+    text_render_context: TextRenderContext,
+    row_height: f32,
+    indent: f32,
+    signals_hooked: bool,
+}
+
+impl DirectoryTree {
+    /// Create a new tree rooted at `root`. The root node starts expanded.
+    pub fn new(root: PathBuf) -> Self {
+        let mut root = TreeNode::new(root, 0);
+        root.load_children(false);
+        root.expanded = true;
+
+        Self {
+            root,
+            selected_path: StateSignal::new(None),
+            show_hidden: StateSignal::new(false),
+            last_show_hidden: false,
+            on_select: None,
+            layout_style: LayoutStyle {
+                size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
+                ..Default::default()
+            }
+            .into(),
+            text_render_context: TextRenderContext::new(),
+            row_height: 24.0,
+            indent: 16.0,
+            signals_hooked: false,
+        }
+    }
+
+    /// Drive hidden directory filtering from an external signal.
+    pub fn with_show_hidden_signal(mut self, show_hidden: StateSignal<bool>) -> Self {
+        self.show_hidden = show_hidden;
+        self
+    }
+
+    /// Set the callback invoked when a directory is selected.
+    pub fn with_on_select<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(PathBuf) -> Update + Send + Sync + 'static,
+    {
+        self.on_select = Some(Box::new(callback));
+        self
+    }
+
+    /// Get the selected path signal (for reactive subscription).
+    pub fn selected_path_signal(&self) -> &StateSignal<Option<PathBuf>> {
+        &self.selected_path
+    }
+
+    /// Get the hidden directory filter signal.
+    pub fn show_hidden_signal(&self) -> &StateSignal<bool> {
+        &self.show_hidden
+    }
+
+    /// Expand all ancestors of `path` and select it.
+    pub fn reveal(&mut self, path: &Path) {
+        if !path.starts_with(&self.root.path) {
+            return;
+        }
+        let show_hidden = *self.show_hidden.get();
+        let mut current = self.root.path.clone();
+        let relative = path.strip_prefix(&self.root.path).unwrap_or(path).to_path_buf();
+        for component in relative.components() {
+            if let Some(node) = self.root.find_mut(&current) {
+                if node.children.is_none() {
+                    node.load_children(show_hidden);
+                }
+                node.expanded = true;
+            }
+            current.push(component);
+        }
+        self.selected_path.set(Some(path.to_path_buf()));
+    }
+
+    /// Toggle the expansion state of the node at `path`.
+    pub fn toggle_expanded(&mut self, path: &Path) {
+        let show_hidden = *self.show_hidden.get();
+        if let Some(node) = self.root.find_mut(path) {
+            if node.children.is_none() {
+                node.load_children(show_hidden);
+            }
+            node.expanded = !node.expanded;
+        }
+    }
+
+    /// Re-read all loaded children with the current filter, keeping expanded nodes open.
+    fn reload(&mut self) {
+        fn reset(node: &mut TreeNode, show_hidden: bool) {
+            if node.expanded || node.children.is_some() {
+                let expanded: Vec<PathBuf> = node
+                    .children
+                    .as_ref()
+                    .map(|c| c.iter().filter(|n| n.expanded).map(|n| n.path.clone()).collect())
+                    .unwrap_or_default();
+                node.load_children(show_hidden);
+                if let Some(children) = node.children.as_mut() {
+                    for child in children.iter_mut() {
+                        if expanded.contains(&child.path) {
+                            child.expanded = true;
+                            reset(child, show_hidden);
+                        }
+                    }
+                }
+            }
+        }
+        let show_hidden = *self.show_hidden.get();
+        reset(&mut self.root, show_hidden);
+    }
+
+    fn visible_rows(&self) -> Vec<(PathBuf, usize)> {
+        let mut rows = Vec::new();
+        self.root.collect_visible(&mut rows);
+        rows.into_iter().map(|n| (n.path.clone(), n.depth)).collect()
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for DirectoryTree {
+    fn layout_style(&self, _context: &LayoutContext) -> StyleNode {
+        let mut style = self.layout_style.get().clone();
+        let rows = self.visible_rows().len() as f32;
+        style.min_size = Vector2::new(Dimension::auto(), Dimension::length(rows * self.row_height));
+        StyleNode {
+            style,
+            children: vec![],
+            measure_func: None,
+        }
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.selected_path);
+            context.hook_signal(&mut self.show_hidden);
+            self.signals_hooked = true;
+        }
+
+        let show_hidden = *self.show_hidden.get();
+        if show_hidden != self.last_show_hidden {
+            self.last_show_hidden = show_hidden;
+            self.reload();
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        let Some(cursor) = info.cursor_pos else {
+            return update;
+        };
+        let local_x = cursor.x as f32 - layout.layout.location.x;
+        let local_y = cursor.y as f32 - layout.layout.location.y;
+        let in_bounds = local_x >= 0.0
+            && local_x < layout.layout.size.width
+            && local_y >= 0.0
+            && local_y < layout.layout.size.height;
+        if !in_bounds {
+            return update;
+        }
+
+        let clicked = info
+            .buttons
+            .iter()
+            .any(|(_, btn, el)| *btn == MouseButton::Left && *el == ElementState::Pressed);
+        if !clicked {
+            return update;
+        }
+
+        let rows = self.visible_rows();
+        let index = (local_y / self.row_height) as usize;
+        if let Some((path, depth)) = rows.get(index).cloned() {
+            let expander_x = depth as f32 * self.indent;
+            if local_x >= expander_x && local_x < expander_x + self.indent {
+                self.toggle_expanded(&path);
+            } else {
+                self.selected_path.set(Some(path.clone()));
+                if let Some(callback) = &self.on_select {
+                    update |= callback(path);
+                }
+            }
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        let palette = context.palette();
+        let text_color = color_scheme::color(palette, ColorRole::BaseText);
+        let selection_color = color_scheme::color(palette, ColorRole::Selection);
+        let selected = (*self.selected_path.get()).clone();
+
+        let mut rows = Vec::new();
+        self.root.collect_visible(&mut rows);
+        let rows: Vec<(String, usize, bool, bool, bool)> = rows
+            .into_iter()
+            .map(|n| {
+                let has_children = n.children.as_ref().map(|c| !c.is_empty()).unwrap_or(true);
+                (
+                    n.name.clone(),
+                    n.depth,
+                    n.expanded,
+                    has_children,
+                    selected.as_ref() == Some(&n.path),
+                )
+            })
+            .collect();
+
+        for (i, (name, depth, expanded, has_children, is_selected)) in rows.into_iter().enumerate() {
+            let y = layout.layout.location.y + i as f32 * self.row_height;
+            let row_rect = Rect::new(
+                layout.layout.location.x as f64,
+                y as f64,
+                (layout.layout.location.x + layout.layout.size.width) as f64,
+                (y + self.row_height) as f64,
+            );
+
+            if is_selected {
+                graphics.fill(
+                    Fill::NonZero,
+                    Affine::IDENTITY,
+                    &Brush::Solid(selection_color.with_alpha(0.3)),
+                    None,
+                    &row_rect.to_path(0.1),
+                );
+            }
+
+            let x = layout.layout.location.x + depth as f32 * self.indent;
+            if has_children {
+                let expander = if expanded { "▾" } else { "▸" };
+                self.text_render_context.render_text(
+                    &mut info.font_context,
+                    graphics,
+                    expander,
+                    None,
+                    14.0,
+                    Brush::Solid(text_color),
+                    Affine::translate((x as f64 + 2.0, row_rect.y0 + 4.0)),
+                    true,
+                    Some(self.indent),
+                );
+            }
+
+            let text_x = x + self.indent;
+            self.text_render_context.render_text(
+                &mut info.font_context,
+                graphics,
+                &name,
+                None,
+                14.0,
+                Brush::Solid(text_color),
+                Affine::translate((text_x as f64, row_rect.y0 + 4.0)),
+                true,
+                Some((layout.layout.size.width - (text_x - layout.layout.location.x)).max(20.0)),
+            );
+        }
+    }
+}
+
+impl WidgetLayoutExt for DirectoryTree {
+    fn set_layout_style(&mut self, layout_style: impl Into<MaybeSignal<LayoutStyle>>) {
+        self.layout_style = layout_style.into();
+    }
+}
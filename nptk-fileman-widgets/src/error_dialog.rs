@@ -0,0 +1,146 @@
+//! Operation error dialog
+//!
+//! Shown when an item of a file operation fails (e.g. a file that can't be
+//! deleted). Names the item and the error and lets the user retry it, skip it,
+//! skip all further failures, or abort the operation.
+
+use crate::commands::CommandQueue;
+use nptk::prelude::*;
+use std::path::PathBuf;
+
+/// What to do about an item that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorAction {
+    /// Try the item again.
+    Retry,
+    /// Leave the item and continue with the next one.
+    Skip,
+    /// Leave this and every further failing item without asking.
+    SkipAll,
+    /// Stop the whole operation.
+    Abort,
+}
+
+/// Handle to a shown error dialog, polled by the operation executor.
+#[derive(Clone)]
+pub struct ErrorDialogHandle {
+    answers: CommandQueue<ErrorAction>,
+}
+
+impl ErrorDialogHandle {
+    /// Take the answer if the user has given one.
+    pub fn try_take(&self) -> Option<ErrorAction> {
+        self.answers.take_first()
+    }
+}
+
+/// Dialog asking what to do about a failed item.
+pub struct ErrorDialog {
+    path: PathBuf,
+    message: String,
+    remaining_items: usize,
+    answers: CommandQueue<ErrorAction>,
+}
+
+impl ErrorDialog {
+    /// Create a dialog for `path`, which failed with `message`.
+    pub fn new(path: PathBuf, message: String) -> Self {
+        Self {
+            path,
+            message,
+            remaining_items: 0,
+            answers: CommandQueue::new(),
+        }
+    }
+
+    /// Number of items the operation still has to process. When non-zero,
+    /// "Skip All" is offered.
+    pub fn with_remaining_items(mut self, remaining: usize) -> Self {
+        self.remaining_items = remaining;
+        self
+    }
+
+    /// Show the dialog as a popup and return a handle to poll for the answer.
+    pub fn show(self, context: &AppContext) -> ErrorDialogHandle {
+        let handle = ErrorDialogHandle {
+            answers: self.answers.clone(),
+        };
+        let title = format!(
+            "Error with \"{}\"",
+            self.path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.path.display().to_string())
+        );
+        context
+            .popup_manager
+            .create_popup_at(Box::new(self.build()), &title, (480, 200), (300, 200));
+        handle
+    }
+
+    fn button(&self, label: &str, action: ErrorAction) -> Button {
+        Button::new(Text::new(label.to_string())).with_on_pressed(self.answers.on_pressed(action))
+    }
+
+    fn build(&self) -> Container {
+        let mut buttons: Vec<Box<dyn Widget>> = vec![
+            Box::new(self.button("Abort", ErrorAction::Abort)),
+            Box::new(self.button("Skip", ErrorAction::Skip)),
+        ];
+        if self.remaining_items > 0 {
+            buttons.push(Box::new(self.button("Skip All", ErrorAction::SkipAll)));
+        }
+        buttons.push(Box::new(self.button("Retry", ErrorAction::Retry)));
+
+        let mut children: Vec<Box<dyn Widget>> = vec![
+            Box::new(
+                Container::new(vec![
+                    Box::new(Icon::new("dialog-error", 48, None)),
+                    Box::new(
+                        Container::new(vec![
+                            Box::new(Text::new(self.path.display().to_string()).with_font_size(13.0)),
+                            Box::new(Text::new(self.message.clone()).with_font_size(12.0)),
+                        ])
+                        .with_layout_style(LayoutStyle {
+                            flex_direction: FlexDirection::Column,
+                            flex_grow: 1.0,
+                            ..Default::default()
+                        }),
+                    ),
+                ])
+                .with_layout_style(LayoutStyle {
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    flex_direction: FlexDirection::Row,
+                    gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                    ..Default::default()
+                }),
+            ),
+        ];
+        if self.remaining_items > 0 {
+            children.push(Box::new(Text::new(format!(
+                "{} more item(s) in this operation",
+                self.remaining_items
+            ))));
+        }
+        children.push(Box::new(Container::new(buttons).with_layout_style(LayoutStyle {
+            flex_direction: FlexDirection::Row,
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            justify_content: Some(JustifyContent::FlexEnd),
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            ..Default::default()
+        })));
+
+        Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(12.0)),
+            ..Default::default()
+        })
+    }
+}
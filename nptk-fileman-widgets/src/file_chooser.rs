@@ -0,0 +1,283 @@
+//! File chooser for picking files to open or a location to save to.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+
+use crate::file_list::FileList;
+
+/// What the chooser is picking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChooserMode {
+    /// Pick existing files (or folders, see [FileChooser::with_directories])
+    Open,
+    /// Pick a folder and enter a file name to save to
+    Save,
+}
+
+/// The user's answer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileChooserResponse {
+    Accepted(Vec<PathBuf>),
+    Cancelled,
+}
+
+/// Handle to poll a shown [FileChooser] for the user's answer.
+#[derive(Clone)]
+pub struct FileChooserHandle {
+    result: Arc<Mutex<Option<FileChooserResponse>>>,
+}
+
+impl FileChooserHandle {
+    /// Take the response if the user has answered.
+    pub fn try_take(&self) -> Option<FileChooserResponse> {
+        self.result.lock().ok().and_then(|mut r| r.take())
+    }
+}
+
+/// A file list with a name field (when saving) and Cancel/accept buttons.
+pub struct FileChooser {
+    mode: FileChooserMode,
+    start_dir: PathBuf,
+    multiple: bool,
+    directories: bool,
+    accept_label: String,
+
+    inner: Container,
+    current_path: StateSignal<PathBuf>,
+    selected_paths: StateSignal<Vec<PathBuf>>,
+    name_text: StateSignal<String>,
+    accepted: Arc<Mutex<bool>>,
+    cancelled: Arc<Mutex<bool>>,
+    activated: Arc<Mutex<Option<PathBuf>>>,
+    result: Arc<Mutex<Option<FileChooserResponse>>>,
+    signals_hooked: bool,
+}
+
+impl FileChooser {
+    /// Create a chooser showing `start_dir`.
+    pub fn new(mode: FileChooserMode, start_dir: PathBuf) -> Self {
+        let accept_label = match mode {
+            FileChooserMode::Open => "Open",
+            FileChooserMode::Save => "Save",
+        };
+        let file_list = FileList::new(start_dir.clone());
+        let current_path = file_list.current_path_signal().clone();
+        let selected_paths = file_list.selected_paths_signal().clone();
+        Self {
+            mode,
+            start_dir,
+            multiple: false,
+            directories: false,
+            accept_label: accept_label.to_string(),
+            inner: Container::new(vec![]),
+            current_path,
+            selected_paths,
+            name_text: StateSignal::new(String::new()),
+            accepted: Arc::new(Mutex::new(false)),
+            cancelled: Arc::new(Mutex::new(false)),
+            activated: Arc::new(Mutex::new(None)),
+            result: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        }
+        .rebuild()
+    }
+
+    /// Allow picking several files.
+    pub fn with_multiple(mut self, multiple: bool) -> Self {
+        self.multiple = multiple;
+        self
+    }
+
+    /// Pick folders instead of files.
+    pub fn with_directories(mut self, directories: bool) -> Self {
+        self.directories = directories;
+        self
+    }
+
+    /// Pre-fill the file name (save mode).
+    pub fn with_current_name(self, name: impl Into<String>) -> Self {
+        self.name_text.set(name.into());
+        self
+    }
+
+    /// Label of the accept button (defaults to "Open" or "Save").
+    pub fn with_accept_label(mut self, label: impl Into<String>) -> Self {
+        self.accept_label = label.into();
+        self.rebuild()
+    }
+
+    /// Handle to poll for the user's answer.
+    pub fn handle(&self) -> FileChooserHandle {
+        FileChooserHandle {
+            result: self.result.clone(),
+        }
+    }
+
+    fn rebuild(mut self) -> Self {
+        let activated = self.activated.clone();
+        let file_list = FileList::new(self.start_dir.clone()).with_on_activate(move |path| {
+            if let Ok(mut activated) = activated.lock() {
+                *activated = Some(path);
+            }
+            Update::DRAW
+        });
+        self.current_path = file_list.current_path_signal().clone();
+        self.selected_paths = file_list.selected_paths_signal().clone();
+
+        let mut children: Vec<Box<dyn Widget>> = vec![Box::new(file_list.with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_grow: 1.0,
+            ..Default::default()
+        }))];
+
+        if self.mode == FileChooserMode::Save {
+            children.push(Box::new(
+                TextInput::new()
+                    .with_text_signal(self.name_text.clone())
+                    .with_placeholder("File name".to_string())
+                    .with_layout_style(LayoutStyle {
+                        size: Vector2::new(Dimension::percent(1.0), Dimension::length(30.0)),
+                        ..Default::default()
+                    }),
+            ));
+        }
+
+        let cancelled = self.cancelled.clone();
+        let accepted = self.accepted.clone();
+        children.push(Box::new(
+            Container::new(vec![
+                Box::new(Button::new(Text::new("Cancel".to_string())).with_on_pressed(
+                    MaybeSignal::signal(Box::new(EvalSignal::new(move || {
+                        if let Ok(mut flag) = cancelled.lock() {
+                            *flag = true;
+                        }
+                        Update::DRAW
+                    }))),
+                )),
+                Box::new(Button::new(Text::new(self.accept_label.clone())).with_on_pressed(
+                    MaybeSignal::signal(Box::new(EvalSignal::new(move || {
+                        if let Ok(mut flag) = accepted.lock() {
+                            *flag = true;
+                        }
+                        Update::DRAW
+                    }))),
+                )),
+            ])
+            .with_layout_style(LayoutStyle {
+                flex_direction: FlexDirection::Row,
+                gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                justify_content: Some(JustifyContent::FlexEnd),
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                ..Default::default()
+            }),
+        ));
+
+        self.inner = Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(8.0),
+                right: LengthPercentage::length(8.0),
+                top: LengthPercentage::length(8.0),
+                bottom: LengthPercentage::length(8.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(8.0)),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// The paths the accept button would return, if any.
+    fn chosen_paths(&self) -> Option<Vec<PathBuf>> {
+        let current = (*self.current_path.get()).clone();
+        match self.mode {
+            FileChooserMode::Save => {
+                let name = self.name_text.get().trim().to_string();
+                (!name.is_empty()).then(|| vec![current.join(name)])
+            },
+            FileChooserMode::Open => {
+                let mut paths: Vec<PathBuf> = self
+                    .selected_paths
+                    .get()
+                    .iter()
+                    .filter(|p| p.is_dir() == self.directories)
+                    .cloned()
+                    .collect();
+                if paths.is_empty() && self.directories {
+                    // Nothing selected: choose the folder being shown
+                    paths.push(current);
+                }
+                if !self.multiple {
+                    paths.truncate(1);
+                }
+                (!paths.is_empty()).then_some(paths)
+            },
+        }
+    }
+
+    fn respond(&self, response: FileChooserResponse) {
+        if let Ok(mut result) = self.result.lock() {
+            *result = Some(response);
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for FileChooser {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.name_text);
+            self.signals_hooked = true;
+        }
+
+        let take = |flag: &Arc<Mutex<bool>>| flag.lock().map(|mut f| std::mem::take(&mut *f)).unwrap_or(false);
+        if take(&self.cancelled) {
+            self.respond(FileChooserResponse::Cancelled);
+        }
+        if take(&self.accepted) {
+            if let Some(paths) = self.chosen_paths() {
+                self.respond(FileChooserResponse::Accepted(paths));
+            }
+        }
+
+        // Double-clicking a file picks it (directories are navigated into by the list)
+        let activated = self.activated.lock().ok().and_then(|mut a| a.take());
+        if let Some(path) = activated.filter(|p| !p.is_dir()) {
+            match self.mode {
+                FileChooserMode::Open if !self.directories => {
+                    self.respond(FileChooserResponse::Accepted(vec![path]));
+                },
+                FileChooserMode::Save => {
+                    if let Some(name) = path.file_name() {
+                        self.name_text.set(name.to_string_lossy().to_string());
+                    }
+                },
+                FileChooserMode::Open => {},
+            }
+        }
+
+        self.inner.update(layout, context, info).await
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
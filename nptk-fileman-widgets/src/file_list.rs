@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
@@ -13,29 +14,110 @@ use nptk::core::vg::kurbo::{Affine, Point, Rect, Shape, Stroke, Vec2};
 use nptk::core::vg::peniko::{Brush, Color, Fill};
 use nptk::core::vgi::Graphics;
 use nptk::core::widget::{BoxedWidget, Widget, WidgetLayoutExt};
-use nptk::core::window::{ElementState, MouseButton};
+use nptk::core::window::{ElementState, MouseButton, MouseScrollDelta};
 use nptk::prelude::LayoutContext;
 use nptk::services::filesystem::entry::{FileEntry, FileType};
 use nptk::services::filesystem::model::{FileSystemEvent, FileSystemModel};
 use npio::service::icon::IconRegistry;
 use npio::{ThumbnailService, ThumbnailEvent, ThumbnailImage, get_file_for_uri, register_backend};
 use npio::backend::local::LocalBackend;
-use nptk::services::thumbnail::npio_adapter::{uri_to_path, thumbnail_size_to_u32};
+use nptk::services::thumbnail::npio_adapter::{
+    file_entry_to_uri, thumbnail_size_to_u32, u32_to_thumbnail_size, uri_to_path,
+};
 use nptk::core::theme::{ColorRole, Palette};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use tokio::{sync::broadcast, time::{Duration, Instant}};
 
 mod actions;
+mod advanced_tab;
+mod callbacks;
+mod columns;
+mod emblems;
+mod load_queue;
+mod lru_cache;
+mod permissions_tab;
+mod prefetch;
 mod properties;
+mod sorting;
+mod usage_tab;
 mod view_compact;
 mod view_icon;
 mod view_list;
+mod zoom;
+
+
+pub use actions::{open_with_app, open_with_default_app};
+pub use callbacks::{
+    ActivateCallback, ContextMenuCallback, DimCallback, EmblemCallback, SelectionChangedCallback,
+};
+pub use columns::TableColumns;
+pub use emblems::Emblem;
+pub use sorting::{FileListSort, FileListSortColumn, SortOrder, natural_cmp};
+pub use zoom::FileListZoom;
+use callbacks::FileListCallbacks;
+use load_queue::{LoadKind, LoadQueue};
+use lru_cache::LruCache;
+use prefetch::Prefetcher;
+
+/// Icons by (path, size); `None` when the file has no icon
+type IconCache = Arc<Mutex<LruCache<(PathBuf, u32), Option<npio::service::icon::CachedIcon>>>>;
+/// Thumbnails by (path, size)
+type ThumbnailCache = Arc<Mutex<LruCache<(PathBuf, u32), ThumbnailImage>>>;
+
+/// Memory budget of the icon cache
+const ICON_CACHE_BYTES: usize = 32 * 1024 * 1024;
+/// Memory budget of the thumbnail cache
+const THUMBNAIL_CACHE_BYTES: usize = 128 * 1024 * 1024;
+/// How long changes reported by the watcher are collected before they are shown
+const CHANGE_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Entries of `dir` read again after the watcher reported them changed, `None`
+/// for those that no longer exist, and the launchers among them.
+struct ReadChanges {
+    dir: PathBuf,
+    entries: Vec<(PathBuf, Option<FileEntry>)>,
+    launchers: LauncherLabels,
+}
+
+fn icon_bytes(icon: &Option<npio::service::icon::CachedIcon>) -> usize {
+    match icon {
+        Some(npio::service::icon::CachedIcon::Image { data, .. }) => data.len(),
+        Some(npio::service::icon::CachedIcon::Svg(svg_source)) => svg_source.len(),
+        Some(npio::service::icon::CachedIcon::Path(_)) | None => 256,
+    }
+}
+
+fn thumbnail_bytes(thumbnail: &ThumbnailImage) -> usize {
+    thumbnail.data.len()
+}
 
+/// The entry of `path` as the list shows it, `None` if it doesn't exist.
+pub fn read_entry(path: &std::path::Path) -> Option<FileEntry> {
+    prefetch::read_entry(path, path.parent()?)
+}
 
 /// Simple operation request type for use within FileList widget
 /// This is converted to the full FileOperationRequest in FileListWrapper
+#[derive(Debug, Clone)]
 pub enum FileListOperation {
+    /// A single folder is entered, files are opened
+    Open(Vec<PathBuf>),
+    /// Open with the application `app_id`
+    OpenWith { paths: Vec<PathBuf>, app_id: String },
+    /// Put on the clipboard to be copied
+    Copy(Vec<PathBuf>),
+    /// Put on the clipboard to be moved (cut)
+    Move(Vec<PathBuf>),
+    /// Move to the trash; not asked about, as it can be undone
+    Trash(Vec<PathBuf>),
+    /// Delete permanently, already confirmed by the user
     Delete(Vec<PathBuf>),
+    Rename { from: PathBuf, to: PathBuf },
+    /// Pack into an archive next to the items
+    Compress(Vec<PathBuf>),
+    Properties(Vec<PathBuf>),
+    /// Switch the permission bits in `set` on and those in `clear` off, keeping the others
+    SetPermissions { paths: Vec<PathBuf>, set: u32, clear: u32 },
 }
 
 use nptk::widgets::scroll_container::{ScrollContainer, ScrollDirection};
@@ -46,7 +128,17 @@ use std::path::PathBuf;
 use nptk::widgets::container::Container;
 use nptk::widgets::button::Button;
 use nptk::widgets::text::Text;
-use humansize::{format_size, BINARY};
+use crate::accessibility::{AccessibleNode, AccessibleRole, AccessibleSubtree};
+use crate::color_scheme;
+use crate::dates;
+use crate::desktop_entries::LauncherLabels;
+use crate::focus::{FocusChain, Pane, PaneKey};
+use crate::listing_cache;
+use crate::mime_types;
+use crate::perf;
+use crate::rename_dialog::{RenameDialog, RenameDialogHandle};
+use crate::sizes;
+use crate::special_files;
 use std::fs;
 
 /// View mode for the file list.
@@ -67,9 +159,22 @@ pub struct FileList {
     // State
     current_path: StateSignal<PathBuf>,
     entries: StateSignal<Vec<FileEntry>>,
+    // Names and icons of the launchers among the entries
+    launchers: StateSignal<LauncherLabels>,
     selected_paths: StateSignal<Vec<PathBuf>>,
     view_mode: StateSignal<FileListViewMode>,
     icon_size: StateSignal<u32>,
+    // Zoom level of each view mode, changed with Ctrl+scroll
+    zoom: StateSignal<FileListZoom>,
+    last_zoom: FileListZoom,
+    sort: StateSignal<FileListSort>,
+    last_sort: FileListSort,
+    // Whether entries whose name starts with a dot are listed
+    show_hidden: StateSignal<bool>,
+    last_show_hidden: bool,
+    // Hidden entries of the shown folder left out of the listing, and their number
+    hidden: HashSet<PathBuf>,
+    hidden_count: StateSignal<usize>,
 
     // Model
     fs_model: Arc<FileSystemModel>,
@@ -81,6 +186,9 @@ pub struct FileList {
     // Child widgets
     scroll_container: BoxedWidget,
 
+    // Text of the performance overlay (see crate::perf)
+    perf_overlay_text: TextRenderContext,
+
     // Track if signals are hooked
     signals_hooked: bool,
 
@@ -95,6 +203,58 @@ pub struct FileList {
     
     // Selection signal for ItemView (Table mode)
     item_view_selection: Option<StateSignal<Vec<usize>>>,
+
+    // Set by the ItemView when its column header is right-clicked
+    header_menu_requested: Arc<AtomicBool>,
+
+    // Callbacks registered via the builder methods (shared with FileListContent)
+    callbacks: Arc<Mutex<FileListCallbacks>>,
+    last_notified_selection: Vec<PathBuf>,
+
+    // Extra columns appended to the Table view model
+    custom_columns: Vec<model_adapter::ColumnProvider>,
+    // Columns the Table view shows and their widths, chosen in the header menu
+    table_columns: StateSignal<TableColumns>,
+    last_table_columns: TableColumns,
+    // Folder sizes shown in the Size column, if enabled
+    directory_sizes: Option<model_adapter::DirectorySizes>,
+
+    // Paths to show the properties popup for (processed by FileListContent)
+    properties_request: Arc<Mutex<Option<Vec<PathBuf>>>>,
+
+    // Saved listings for slow locations: folder whose listing is shown, whether
+    // it is on a slow filesystem, and whether the shown listing is the saved one
+    listing_path: Option<PathBuf>,
+    listing_slow: bool,
+    listing_stale: StateSignal<bool>,
+
+    // Listings of hovered or selected folders, shown at once when one is opened
+    prefetcher: Prefetcher,
+
+    // Entries and selection for assistive technologies, republished when dirty
+    accessibility: AccessibleSubtree,
+    accessibility_dirty: bool,
+
+    // Keyboard focus between the window's panes
+    focus: Option<FocusChain>,
+
+    // When the Table view was last redrawn to bring relative dates up to date
+    dates_refreshed: Instant,
+
+    // Paths to select once the listing requested by refresh() is in
+    refresh_selection: Option<Vec<PathBuf>>,
+
+    // Entries the watcher reported as changed, and when the first of them was
+    pending_changes: HashSet<PathBuf>,
+    changes_since: Option<Instant>,
+    // Changed entries read again on a blocking thread, shown in update()
+    changes_read: Arc<Mutex<Vec<ReadChanges>>>,
+
+    // Entries from anywhere (see show_results) are shown instead of the listing
+    showing_results: bool,
+
+    // Whether thumbnails are generated for the shown entries (shared with the content)
+    thumbnails: Arc<AtomicBool>,
 }
 
 impl FileList {
@@ -139,13 +299,16 @@ impl FileList {
         let event_rx = Arc::new(Mutex::new(fs_model.subscribe_events()));
 
         // Initial load
+        perf::listing_started(&initial_path);
         let _ = fs_model.refresh(&initial_path);
 
         let current_path = StateSignal::new(initial_path.clone());
         let entries = StateSignal::new(Vec::new());
+        let launchers = StateSignal::new(LauncherLabels::default());
         let selected_paths = StateSignal::new(Vec::new());
         let view_mode = StateSignal::new(FileListViewMode::List);
-        let icon_size = StateSignal::new(48);
+        let icon_size = StateSignal::new(FileListZoom::default().icon_size());
+        let zoom = StateSignal::new(FileListZoom::default());
 
         // Create icon registry
         let icon_registry =
@@ -169,13 +332,20 @@ impl FileList {
         // Wrap selection_change_tx in Arc for sharing with FileListContent
         let selection_change_tx_arc = selection_change_tx.map(|tx| Arc::new(tx));
 
+        let callbacks = Arc::new(Mutex::new(FileListCallbacks::default()));
+        let properties_request = Arc::new(Mutex::new(None));
+        let prefetcher = Prefetcher::default();
+        let thumbnails = Arc::new(AtomicBool::new(true));
+
         // Create content widget
         let content = FileListContent::new(
             entries.clone(),
+            launchers.clone(),
             selected_paths.clone(),
             current_path.clone(),
             view_mode.clone(),
             icon_size.clone(),
+            zoom.clone(),
             fs_model.clone(),
             icon_registry.clone(),
             thumbnail_service.clone(),
@@ -185,6 +355,10 @@ impl FileList {
             cache_invalidate_rx,
             operation_tx,
             selection_change_tx_arc.clone(),
+            callbacks.clone(),
+            properties_request.clone(),
+            prefetcher.clone(),
+            thumbnails.clone(),
         );
         
         // Store cache invalidation sender for use in FileList::update()
@@ -199,9 +373,18 @@ impl FileList {
         Self {
             current_path,
             entries,
+            launchers,
             selected_paths,
             view_mode,
             icon_size,
+            zoom,
+            last_zoom: FileListZoom::default(),
+            sort: StateSignal::new(FileListSort::default()),
+            last_sort: FileListSort::default(),
+            show_hidden: StateSignal::new(false),
+            last_show_hidden: false,
+            hidden: HashSet::new(),
+            hidden_count: StateSignal::new(0),
             fs_model,
             _event_rx: event_rx,
             layout_style: LayoutStyle {
@@ -210,29 +393,61 @@ impl FileList {
             }
             .into(),
             scroll_container: Box::new(scroll_container),
+            perf_overlay_text: TextRenderContext::new(),
             signals_hooked: false,
             selection_change_tx: selection_change_tx_arc,
             cache_invalidate_tx: cache_invalidate_tx_arc,
             item_view: None,
             item_view_selection: None,
+            header_menu_requested: Arc::new(AtomicBool::new(false)),
+            callbacks,
+            last_notified_selection: Vec::new(),
+            custom_columns: Vec::new(),
+            table_columns: StateSignal::new(TableColumns::default()),
+            last_table_columns: TableColumns::default(),
+            directory_sizes: None,
+            properties_request,
+            listing_path: None,
+            listing_slow: false,
+            listing_stale: StateSignal::new(false),
+            prefetcher,
+            accessibility: AccessibleSubtree::new(),
+            accessibility_dirty: true,
+            focus: None,
+            dates_refreshed: Instant::now(),
+            refresh_selection: None,
+            pending_changes: HashSet::new(),
+            changes_since: None,
+            changes_read: Arc::new(Mutex::new(Vec::new())),
+            showing_results: false,
+            thumbnails,
         }
     }
 
+    /// Model of the Table view with all columns, hidden ones included
+    fn table_model(&self) -> model_adapter::FileSystemItemModel {
+        model_adapter::FileSystemItemModel::new(self.entries.clone(), self.launchers.clone())
+            .with_columns(self.custom_columns.clone())
+            .with_directory_sizes(self.directory_sizes.clone())
+    }
+
     /// Initialize ItemView if needed
     fn ensure_item_view(&mut self) {
         if self.item_view.is_none() {
-            use crate::file_list::model_adapter::FileSystemItemModel;
             use nptk::widgets::item_view::{ItemView, ViewMode};
             
-            let model = Arc::new(FileSystemItemModel::new(self.entries.clone()));
+            let columns = (*self.table_columns.get()).clone();
+            let model = Arc::new(self.table_model().with_hidden_columns(columns.hidden));
              
              // Setup ItemView with selection sync
             let selected_paths = self.selected_paths.clone();
             let entries = self.entries.clone();
             let selection_change_tx = self.selection_change_tx.clone();
+            let header_menu_requested = self.header_menu_requested.clone();
             
             let mut view = ItemView::new(model)
                 .with_view_mode(ViewMode::Table)
+                .with_row_height(self.zoom.get().row_height(FileListViewMode::Table))
                 .with_on_selection_change(move |indices| {
                     // Update FileList selection from ItemView selection
                     let current_entries = entries.get();
@@ -249,6 +464,11 @@ impl FileList {
                         let _ = tx.send(new_paths);
                     }
                     
+                    Update::DRAW
+                })
+                .with_on_header_context_menu(move |_column| {
+                    // Shown by handle_header_menu, which has the AppContext
+                    header_menu_requested.store(true, Ordering::Relaxed);
                     Update::DRAW
                 });
                 
@@ -263,6 +483,9 @@ impl FileList {
             self.item_view_selection = Some(selection_signal.clone());
             
             view = view.with_selected_rows(MaybeSignal::signal(Box::new(selection_signal)));
+            if !columns.widths.is_empty() {
+                view = view.with_column_widths(columns.widths);
+            }
             
             self.item_view = Some(Box::new(view));
         }
@@ -270,11 +493,274 @@ impl FileList {
 
     /// Set the current path.
     pub fn set_path(&mut self, path: PathBuf) {
+        self.showing_results = false;
         self.current_path.set(path.clone());
         // Trigger reload in model
+        perf::listing_started(&path);
+        let _ = self.fs_model.refresh(&path);
+    }
+
+    /// Re-read the current folder in place, e.g. after an operation changed it.
+    ///
+    /// Unlike calling [Self::set_path] with the same folder, the view keeps its
+    /// scroll position and the entries that still exist stay selected. `select`
+    /// is added to the selection once the new listing is in, such as a folder
+    /// that was just created or the new name of a renamed item.
+    pub fn refresh(&mut self, select: Vec<PathBuf>) {
+        let path = (*self.current_path.get()).clone();
+        self.showing_results = false;
+        self.refresh_selection = Some(select);
+        perf::listing_started(&path);
         let _ = self.fs_model.refresh(&path);
     }
 
+    /// Show `entries`, such as search results, instead of the listing of the
+    /// current folder until [Self::set_path] or [Self::refresh] is called.
+    /// Entries may be anywhere; listings and changes of the folder are ignored
+    /// meanwhile. Calling it again replaces the entries, keeping the selection
+    /// of those still shown.
+    pub fn show_results(&mut self, entries: Vec<FileEntry>) {
+        self.showing_results = true;
+        self.refresh_selection = None;
+        self.pending_changes.clear();
+        self.changes_since = None;
+        self.hidden.clear();
+        self.replace_entries(entries, Vec::new());
+    }
+
+    /// Whether entries given to [Self::show_results] are shown.
+    pub fn showing_results(&self) -> bool {
+        self.showing_results
+    }
+
+    /// Drop the cached icons, thumbnails and layouts of the shown entries, so they
+    /// are loaded again.
+    pub fn invalidate_caches(&self) {
+        for entry in self.entries.get().iter() {
+            if let Err(e) = self.cache_invalidate_tx.send(entry.path.clone()) {
+                log::warn!("Failed to send cache invalidation request: {}", e);
+            }
+        }
+    }
+
+    /// Apply listings and changes reported by the filesystem model.
+    fn process_fs_events(&mut self, context: &AppContext) -> Update {
+        let mut update = Update::empty();
+        // A clone, so the lock doesn't borrow self while events are applied
+        let event_rx = self._event_rx.clone();
+        let Ok(mut rx) = event_rx.try_lock() else {
+            return update;
+        };
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                FileSystemEvent::DirectoryLoaded { path, entries } => {
+                    if path == *self.current_path.get() && !self.showing_results {
+                        perf::listing_finished(&path, entries.len());
+                        if self.listing_slow {
+                            let saved_entries = entries.clone();
+                            tokio::task::spawn_blocking(move || listing_cache::save(&path, &saved_entries));
+                        }
+                        self.listing_stale.set(false);
+                        let select = self.refresh_selection.take().unwrap_or_default();
+                        self.hidden.clear();
+                        self.replace_entries(entries, select);
+                        update.insert(Update::LAYOUT | Update::DRAW);
+                    }
+                },
+                FileSystemEvent::EntryAdded { path, .. } | FileSystemEvent::EntryRemoved { path } | FileSystemEvent::EntryModified { path, .. } => {
+                    if let Some(parent) = path.parent() {
+                        if parent == *self.current_path.get() && !self.showing_results {
+                            // Applied together once the burst has settled (see apply_changes)
+                            if self.pending_changes.is_empty() {
+                                self.changes_since = Some(Instant::now());
+                                let update_manager = context.update();
+                                tokio::spawn(async move {
+                                    tokio::time::sleep(CHANGE_DEBOUNCE).await;
+                                    update_manager.insert(Update::DRAW);
+                                });
+                            }
+                            self.pending_changes.insert(path.clone());
+                            // Invalidate caches for the affected path
+                            if let Err(e) = self.cache_invalidate_tx.send(path.clone()) {
+                                log::warn!("Failed to send cache invalidation request: {}", e);
+                            }
+                        }
+                    }
+                },
+                _ => {
+                    // For other events, we might want to refresh if they affect current path
+                    // But for now, let's just rely on DirectoryLoaded
+                },
+            }
+        }
+        self.read_changes(context);
+        update | self.apply_changes()
+    }
+
+    /// Read the entries changed over the last [CHANGE_DEBOUNCE] again on a
+    /// blocking thread, instead of the whole folder; [Self::apply_changes]
+    /// shows them once read.
+    fn read_changes(&mut self, context: &AppContext) {
+        if self.changes_since.is_none_or(|since| since.elapsed() < CHANGE_DEBOUNCE) {
+            return;
+        }
+        self.changes_since = None;
+        let dir = (*self.current_path.get()).clone();
+        let paths = std::mem::take(&mut self.pending_changes);
+        let changes_read = self.changes_read.clone();
+        let update_manager = context.update();
+        tokio::task::spawn_blocking(move || {
+            let entries: Vec<(PathBuf, Option<FileEntry>)> = paths
+                .into_iter()
+                .map(|path| {
+                    let entry = prefetch::read_entry(&path, &dir);
+                    (path, entry)
+                })
+                .collect();
+            let read: Vec<FileEntry> = entries.iter().filter_map(|(_, entry)| entry.clone()).collect();
+            let launchers = LauncherLabels::read(&read, &LauncherLabels::default());
+            if let Ok(mut changes_read) = changes_read.lock() {
+                changes_read.push(ReadChanges { dir, entries, launchers });
+            }
+            update_manager.insert(Update::DRAW);
+        });
+    }
+
+    /// Apply the entries read by [Self::read_changes] to the shown listing.
+    fn apply_changes(&mut self) -> Update {
+        let Some(changes) = self.changes_read.lock().ok().map(|mut read| std::mem::take(&mut *read)) else {
+            return Update::empty();
+        };
+        let mut update = Update::empty();
+        for changes in changes {
+            // Changes in a folder that was left since don't apply
+            if changes.dir == *self.current_path.get() && !self.showing_results {
+                self.apply_read_changes(changes);
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+        }
+        update
+    }
+
+    /// Take out the entries that are gone, replace changed ones where they
+    /// keep their place in the order and insert the others where they belong,
+    /// keeping the selection of the entries still shown.
+    fn apply_read_changes(&mut self, changes: ReadChanges) {
+        let sort = *self.sort.get();
+        let show_hidden = *self.show_hidden.get();
+        let mut entries = (*self.entries.get()).clone();
+        let index: HashMap<PathBuf, usize> =
+            entries.iter().enumerate().map(|(i, entry)| (entry.path.clone(), i)).collect();
+        let mut removed = vec![false; entries.len()];
+        let mut added = Vec::new();
+        let mut gone = HashSet::new();
+        let mut changed = Vec::with_capacity(changes.entries.len());
+        for (path, entry) in changes.entries {
+            self.hidden.remove(&path);
+            let entry = entry.filter(|entry| {
+                let hidden = !show_hidden && entry.name.starts_with('.');
+                if hidden {
+                    self.hidden.insert(path.clone());
+                }
+                !hidden
+            });
+            if entry.is_none() {
+                gone.insert(path.clone());
+            }
+            match (index.get(&path).copied(), entry) {
+                (Some(i), Some(entry)) if sorting::fits_at(&entries, i, &entry, sort) => entries[i] = entry,
+                (Some(i), entry) => {
+                    removed[i] = true;
+                    added.extend(entry);
+                },
+                (None, entry) => added.extend(entry),
+            }
+            changed.push(path);
+        }
+        let mut removed = removed.into_iter();
+        entries.retain(|_| !removed.next().unwrap_or(false));
+        sorting::sort_entries(&mut added, sort);
+        let entries = sorting::merge_sorted(entries, added, sort);
+
+        self.sync_hidden_count();
+        let mut launchers = (*self.launchers.get()).clone();
+        launchers.update(&changed, changes.launchers);
+        self.launchers.set(launchers);
+        let selection: Vec<PathBuf> = self
+            .selected_paths
+            .get()
+            .iter()
+            .filter(|path| !gone.contains(*path))
+            .cloned()
+            .collect();
+        self.entries.set(entries);
+        if selection != *self.selected_paths.get() {
+            self.select_paths(selection);
+        }
+        self.accessibility_dirty = true;
+    }
+
+    /// Show `entries` (sorted here), keeping the selection of the entries that
+    /// are still there and adding `select` to it.
+    fn replace_entries(&mut self, mut entries: Vec<FileEntry>, select: Vec<PathBuf>) {
+        self.filter_hidden(&mut entries);
+        sorting::sort_entries(&mut entries, *self.sort.get());
+        let mut selection: Vec<PathBuf> = self
+            .selected_paths
+            .get()
+            .iter()
+            .filter(|p| entries.iter().any(|e| e.path == **p))
+            .cloned()
+            .collect();
+        for path in select {
+            if !selection.contains(&path) && entries.iter().any(|e| e.path == path) {
+                selection.push(path);
+            }
+        }
+        let launchers = LauncherLabels::read(&entries, &self.launchers.get());
+        self.launchers.set(launchers);
+        self.entries.set(entries);
+        // Selection indices of the Table view are re-synced from the paths in update()
+        if selection != *self.selected_paths.get() {
+            self.select_paths(selection);
+        }
+        self.accessibility_dirty = true;
+    }
+
+    /// Signal that is true while a saved listing of a slow location (network
+    /// filesystem or removable drive) is shown until the fresh one has loaded.
+    pub fn listing_stale_signal(&self) -> &StateSignal<bool> {
+        &self.listing_stale
+    }
+
+    /// Show a prefetched listing of a newly opened folder, or the saved listing of
+    /// a slow location, until the filesystem model has loaded it.
+    fn apply_early_listing(&mut self) -> Update {
+        let path = (*self.current_path.get()).clone();
+        if self.listing_path.as_ref() == Some(&path) {
+            return Update::empty();
+        }
+        self.listing_slow = listing_cache::is_slow_location(&path);
+        self.listing_path = Some(path.clone());
+        let (early, stale) = match self.prefetcher.take(&path) {
+            Some(entries) => (Some(entries), false),
+            None if self.listing_slow => (listing_cache::load(&path), true),
+            None => (None, false),
+        };
+        self.listing_stale.set(early.is_some() && stale);
+        match early {
+            Some(mut entries) => {
+                self.hidden.clear();
+                self.filter_hidden(&mut entries);
+                sorting::sort_entries(&mut entries, *self.sort.get());
+                self.entries.set(entries);
+                self.accessibility_dirty = true;
+                Update::LAYOUT | Update::DRAW
+            },
+            None => Update::empty(),
+        }
+    }
+
     /// Get the current path.
     pub fn get_current_path(&self) -> PathBuf {
         (*self.current_path.get()).clone()
@@ -320,6 +806,43 @@ impl FileList {
         }
     }
 
+    /// Select the given paths.
+    pub fn select_paths(&mut self, paths: Vec<PathBuf>) {
+        self.selected_paths.set(paths.clone());
+        // Notify about selection change
+        if let Some(ref tx) = self.selection_change_tx {
+            let _ = tx.send(paths);
+        }
+    }
+
+    /// Show the given paths: navigate to the directory containing the first one and
+    /// select those that are in it. Results given to [Self::show_results] are
+    /// replaced by the listing of that directory.
+    pub fn reveal(&mut self, paths: &[PathBuf]) {
+        let Some(parent) = paths.first().and_then(|p| p.parent()).map(PathBuf::from) else {
+            return;
+        };
+        if parent != *self.current_path.get() || self.showing_results {
+            self.set_path(parent.clone());
+        }
+        let selection = paths
+            .iter()
+            .filter(|p| p.parent() == Some(parent.as_path()))
+            .cloned()
+            .collect();
+        self.select_paths(selection);
+    }
+
+    /// Show the properties popup for the given paths.
+    pub fn show_properties(&self, paths: Vec<PathBuf>) {
+        if paths.is_empty() {
+            return;
+        }
+        if let Ok(mut request) = self.properties_request.lock() {
+            *request = Some(paths);
+        }
+    }
+
     /// Set the view mode.
     pub fn set_view_mode(&mut self, mode: FileListViewMode) {
         self.view_mode.set(mode);
@@ -349,6 +872,390 @@ impl FileList {
     pub fn icon_size_signal(&self) -> &StateSignal<u32> {
         &self.icon_size
     }
+
+    /// Set the zoom level of each view mode.
+    pub fn set_zoom(&mut self, zoom: FileListZoom) {
+        self.zoom.set(zoom);
+    }
+
+    /// Get the current zoom levels.
+    pub fn zoom(&self) -> FileListZoom {
+        *self.zoom.get()
+    }
+
+    /// Get the zoom signal. Setting it from outside resizes the views on the
+    /// next update; subscribing to it notifies about zoom changes.
+    pub fn zoom_signal(&self) -> &StateSignal<FileListZoom> {
+        &self.zoom
+    }
+
+    /// Columns the Table view shows and their widths. Setting it from outside
+    /// builds the view again on the next update.
+    pub fn table_columns_signal(&self) -> &StateSignal<TableColumns> {
+        &self.table_columns
+    }
+
+    /// Build the Table view again once other columns were chosen in the header menu.
+    fn apply_table_columns(&mut self) {
+        let columns = (*self.table_columns.get()).clone();
+        if columns != self.last_table_columns {
+            self.last_table_columns = columns;
+            self.item_view = None;
+            self.item_view_selection = None;
+        }
+    }
+
+    /// Offer the column header menu once the Table view reported a
+    /// right-click on its header.
+    fn handle_header_menu(&self, context: &AppContext, info: &AppInfo) -> Update {
+        if !self.header_menu_requested.swap(false, Ordering::Relaxed) {
+            return Update::empty();
+        }
+        let Some(cursor) = info.cursor_pos else {
+            return Update::empty();
+        };
+        let menu = columns::header_menu(&self.table_model(), &self.table_columns, &self.sort);
+        context.menu_manager.show(menu, Point::new(cursor.x, cursor.y));
+        Update::DRAW
+    }
+
+    /// Resize the views to the current zoom levels: the Icon view through the
+    /// icon size, the Table view by building it again with the new row height.
+    fn apply_zoom(&mut self) -> Update {
+        let zoom = *self.zoom.get();
+        if zoom == self.last_zoom {
+            return Update::empty();
+        }
+        if zoom.icon_size() != self.last_zoom.icon_size() {
+            self.icon_size.set(zoom.icon_size());
+        }
+        let table = FileListViewMode::Table;
+        if zoom.row_height(table) != self.last_zoom.row_height(table) {
+            self.item_view = None;
+            self.item_view_selection = None;
+        }
+        self.last_zoom = zoom;
+        Update::LAYOUT | Update::DRAW
+    }
+
+    /// Zoom the current view with Ctrl+scroll over the list. The scroll is
+    /// used up, so the list doesn't scroll as well.
+    fn handle_zoom_scroll(&mut self, layout: &LayoutNode, info: &mut AppInfo) {
+        if !info.modifiers.control_key() {
+            return;
+        }
+        let Some(cursor) = info.cursor_pos else {
+            return;
+        };
+        let bounds = &layout.layout;
+        let (x, y) = (cursor.x as f32, cursor.y as f32);
+        if x < bounds.location.x
+            || y < bounds.location.y
+            || x >= bounds.location.x + bounds.size.width
+            || y >= bounds.location.y + bounds.size.height
+        {
+            return;
+        }
+        let delta = match info.mouse_scroll_delta.take() {
+            Some(MouseScrollDelta::LineDelta(_, y)) => y as f64,
+            Some(MouseScrollDelta::PixelDelta(position)) => position.y,
+            None => return,
+        };
+        if delta == 0.0 {
+            return;
+        }
+        let mode = *self.view_mode.get();
+        let mut zoom = *self.zoom.get();
+        if zoom.zoom(mode, if delta > 0.0 { 1 } else { -1 }) {
+            self.zoom.set(zoom);
+        }
+    }
+
+    /// Set the callback invoked when an entry is activated (double-clicked).
+    ///
+    /// Directories are still navigated into; the callback is notified as well.
+    pub fn with_on_activate<F>(self, callback: F) -> Self
+    where
+        F: Fn(PathBuf) -> Update + Send + Sync + 'static,
+    {
+        self.apply_with(|this| {
+            if let Ok(mut callbacks) = this.callbacks.lock() {
+                callbacks.on_activate = Some(Box::new(callback));
+            }
+        })
+    }
+
+    /// Set the callback invoked with the new selection whenever it changes.
+    pub fn with_on_selection_changed<F>(self, callback: F) -> Self
+    where
+        F: Fn(Vec<PathBuf>) -> Update + Send + Sync + 'static,
+    {
+        self.apply_with(|this| {
+            if let Ok(mut callbacks) = this.callbacks.lock() {
+                callbacks.on_selection_changed = Some(Box::new(callback));
+            }
+        })
+    }
+
+    /// Set the callback invoked when the context menu opens for the given paths.
+    /// The returned items are appended to the built-in menu.
+    pub fn with_on_context_menu<F>(self, callback: F) -> Self
+    where
+        F: Fn(&[PathBuf]) -> Vec<MenuItem> + Send + Sync + 'static,
+    {
+        self.apply_with(|this| {
+            if let Ok(mut callbacks) = this.callbacks.lock() {
+                callbacks.on_context_menu = Some(Box::new(callback));
+            }
+        })
+    }
+
+    /// Set the function providing emblems drawn over entry icons.
+    ///
+    /// It is called while rendering, so it should only look up precomputed state.
+    pub fn with_emblem_provider<F>(self, provider: F) -> Self
+    where
+        F: Fn(&std::path::Path) -> Option<Emblem> + Send + Sync + 'static,
+    {
+        self.apply_with(|this| {
+            if let Ok(mut callbacks) = this.callbacks.lock() {
+                callbacks.emblem_provider = Some(Box::new(provider));
+            }
+        })
+    }
+
+    /// Set the function deciding which entries are drawn dimmed (e.g. ignored files).
+    ///
+    /// It is called while rendering, so it should only look up precomputed state.
+    pub fn with_dim_provider<F>(self, provider: F) -> Self
+    where
+        F: Fn(&std::path::Path) -> bool + Send + Sync + 'static,
+    {
+        self.apply_with(|this| {
+            if let Ok(mut callbacks) = this.callbacks.lock() {
+                callbacks.dim_provider = Some(Box::new(provider));
+            }
+        })
+    }
+
+    /// Register an extra column shown in the Table view after the built-in columns.
+    pub fn add_column(&mut self, column: model_adapter::ColumnProvider) {
+        self.custom_columns.push(column);
+        // Rebuild the ItemView with the new model on next use
+        self.item_view = None;
+        self.item_view_selection = None;
+    }
+
+    /// Register an extra column (builder pattern).
+    pub fn with_column(self, column: model_adapter::ColumnProvider) -> Self {
+        self.apply_with(|this| this.add_column(column))
+    }
+
+    /// Show folder sizes from `provider` in the Size column and tooltips instead
+    /// of "Directory"; a spinner is shown while it returns `None`.
+    ///
+    /// It is called while rendering, so it should only look up precomputed state.
+    pub fn with_directory_sizes<F>(self, provider: F) -> Self
+    where
+        F: Fn(&std::path::Path) -> Option<u64> + Send + Sync + 'static,
+    {
+        self.apply_with(|this| {
+            this.directory_sizes = Some(Arc::new(provider));
+            // Rebuild the ItemView with the new model on next use
+            this.item_view = None;
+            this.item_view_selection = None;
+        })
+    }
+
+    /// Sort the entries by `column` in the given `order`.
+    pub fn set_sort(&mut self, column: FileListSortColumn, order: SortOrder) {
+        self.sort.set(FileListSort { column, order });
+        self.apply_sort();
+    }
+
+    /// Set the sort (builder pattern).
+    pub fn with_sort(self, column: FileListSortColumn, order: SortOrder) -> Self {
+        self.apply_with(|this| this.set_sort(column, order))
+    }
+
+    /// Get the current sort settings.
+    pub fn sort(&self) -> FileListSort {
+        *self.sort.get()
+    }
+
+    /// Get the sort signal. Setting it from outside re-sorts the list on the next
+    /// update; subscribing to it notifies about sort changes.
+    pub fn sort_signal(&self) -> &StateSignal<FileListSort> {
+        &self.sort
+    }
+
+    /// Show or hide entries whose name starts with a dot.
+    pub fn set_show_hidden(&mut self, show_hidden: bool) {
+        self.show_hidden.set(show_hidden);
+    }
+
+    /// Show or hide hidden entries (builder pattern).
+    pub fn with_show_hidden(self, show_hidden: bool) -> Self {
+        self.apply_with(|this| {
+            this.show_hidden.set(show_hidden);
+            this.last_show_hidden = show_hidden;
+        })
+    }
+
+    /// Get the hidden entries signal. Setting it from outside reloads the list
+    /// on the next update.
+    pub fn show_hidden_signal(&self) -> &StateSignal<bool> {
+        &self.show_hidden
+    }
+
+    /// Generate thumbnails of the shown files, or only show icons (e.g. in
+    /// large folders on slow shares). Thumbnails made before are still shown.
+    pub fn set_thumbnails(&mut self, enabled: bool) {
+        self.thumbnails.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Signal with the number of hidden entries of the shown folder that are
+    /// left out of the listing; 0 while hidden entries are shown.
+    pub fn hidden_count_signal(&self) -> &StateSignal<usize> {
+        &self.hidden_count
+    }
+
+    /// Drop hidden entries from `entries` unless they are shown, counting them.
+    fn filter_hidden(&mut self, entries: &mut Vec<FileEntry>) {
+        if *self.show_hidden.get() {
+            self.hidden.clear();
+        } else {
+            let hidden = &mut self.hidden;
+            entries.retain(|entry| {
+                let is_hidden = entry.name.starts_with('.');
+                if is_hidden {
+                    hidden.insert(entry.path.clone());
+                }
+                !is_hidden
+            });
+        }
+        self.sync_hidden_count();
+    }
+
+    /// Publish the number of hidden entries if it changed.
+    fn sync_hidden_count(&mut self) {
+        if *self.hidden_count.get() != self.hidden.len() {
+            self.hidden_count.set(self.hidden.len());
+        }
+    }
+
+    /// Re-sort the current entries with the current sort settings.
+    fn apply_sort(&mut self) {
+        let sort = *self.sort.get();
+        let mut entries = (*self.entries.get()).clone();
+        sorting::sort_entries(&mut entries, sort);
+        self.entries.set(entries);
+        self.last_sort = sort;
+        self.accessibility_dirty = true;
+    }
+
+    /// Join the window's focus chain: the list shows a focus ring while focused and
+    /// handles Up, Down and Return.
+    pub fn set_focus_chain(&mut self, focus: FocusChain) {
+        self.focus = Some(focus);
+    }
+
+    /// Join the window's focus chain (builder pattern).
+    pub fn with_focus_chain(self, focus: FocusChain) -> Self {
+        self.apply_with(|this| this.set_focus_chain(focus))
+    }
+
+    /// Take focus when clicked and handle the keys sent to the list while focused.
+    fn handle_focus(&mut self, layout: &LayoutNode, info: &AppInfo) -> Update {
+        let Some(focus) = self.focus.clone() else {
+            return Update::empty();
+        };
+        let mut update = Update::empty();
+        if focus.focus_on_click(Pane::FileList, layout, info) {
+            update.insert(Update::DRAW);
+        }
+        for key in focus.take_keys(Pane::FileList) {
+            update |= match key {
+                PaneKey::Up => self.move_cursor(-1),
+                PaneKey::Down => self.move_cursor(1),
+                PaneKey::Activate => self.activate_cursor(),
+            };
+        }
+        update
+    }
+
+    /// Select the entry `delta` rows away from the last selected one.
+    fn move_cursor(&mut self, delta: isize) -> Update {
+        let path = {
+            let entries = self.entries.get();
+            if entries.is_empty() {
+                return Update::empty();
+            }
+            let current = self
+                .selected_paths
+                .get()
+                .last()
+                .and_then(|path| entries.iter().position(|e| e.path == *path));
+            let index = match current {
+                Some(index) => index.saturating_add_signed(delta).min(entries.len() - 1),
+                None if delta < 0 => entries.len() - 1,
+                None => 0,
+            };
+            entries[index].path.clone()
+        };
+        self.select_paths(vec![path]);
+        Update::DRAW
+    }
+
+    /// Open the last selected entry, as double-clicking it does.
+    fn activate_cursor(&mut self) -> Update {
+        let Some(path) = self.selected_paths.get().last().cloned() else {
+            return Update::empty();
+        };
+        let is_dir = self.entries.get().iter().any(|e| e.path == path && e.is_dir());
+        let mut update = Update::DRAW;
+        if is_dir {
+            self.set_path(path.clone());
+            self.clear_selection();
+            update.insert(Update::LAYOUT);
+        }
+        if let Ok(callbacks) = self.callbacks.lock() {
+            if let Some(on_activate) = &callbacks.on_activate {
+                update |= on_activate(path);
+            }
+        }
+        update
+    }
+
+    /// Handle to the accessibility tree of the list: its entries with their
+    /// selection state. Take it before adding the list to the layout.
+    pub fn accessibility(&self) -> AccessibleSubtree {
+        self.accessibility.clone()
+    }
+
+    fn publish_accessibility(&self) {
+        let selected: HashSet<PathBuf> = self.selected_paths.get().iter().cloned().collect();
+        let launchers = self.launchers.get();
+        let items = self
+            .entries
+            .get()
+            .iter()
+            .map(|entry| {
+                AccessibleNode::new(AccessibleRole::ListItem, launchers.display_name(entry).to_string())
+                    .with_description(mime_types::describe(entry))
+                    .with_selected(selected.contains(&entry.path))
+            })
+            .collect();
+        let current_path = self.current_path.get();
+        let folder = current_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| current_path.to_string_lossy().to_string());
+        self.accessibility.set(
+            AccessibleNode::new(AccessibleRole::List, format!("Files in {}", folder))
+                .with_children(items),
+        );
+    }
 }
 
 #[async_trait(?Send)]
@@ -369,24 +1276,82 @@ impl Widget for FileList {
             context.hook_signal(&mut self.selected_paths);
             context.hook_signal(&mut self.view_mode);
             context.hook_signal(&mut self.icon_size);
-            context.hook_signal(&mut self.icon_size);
+            context.hook_signal(&mut self.zoom);
+            context.hook_signal(&mut self.sort);
+            context.hook_signal(&mut self.show_hidden);
+            context.hook_signal(&mut self.listing_stale);
+            context.hook_signal(&mut self.table_columns);
             self.signals_hooked = true;
         }
+
+        let listing_update = self.apply_early_listing();
+        // Before the Table view returns early, so it sees new listings too
+        let fs_update = self.process_fs_events(&context);
+
+        self.handle_zoom_scroll(layout, info);
+        let zoom_update = self.apply_zoom();
+        self.apply_table_columns();
+
+        // Re-sort if the sort settings were changed through the signal
+        if *self.sort.get() != self.last_sort {
+            self.apply_sort();
+        }
+        // Hidden entries that are shown again have to be read again
+        let show_hidden = *self.show_hidden.get();
+        if show_hidden != self.last_show_hidden {
+            self.last_show_hidden = show_hidden;
+            self.refresh(Vec::new());
+        }
+
+        // Notify the selection callback (covers every view mode and selection source)
+        let selection_update = {
+            let selection = (*self.selected_paths.get()).clone();
+            if selection != self.last_notified_selection {
+                self.last_notified_selection = selection.clone();
+                self.accessibility_dirty = true;
+                if let [selected] = selection.as_slice() {
+                    let is_dir = self.entries.get().iter().any(|e| e.path == *selected && e.is_dir());
+                    if is_dir {
+                        self.prefetcher.request(selected);
+                    }
+                }
+                self.callbacks
+                    .lock()
+                    .ok()
+                    .and_then(|callbacks| callbacks.on_selection_changed.as_ref().map(|cb| cb(selection)))
+                    .unwrap_or_else(Update::empty)
+            } else {
+                Update::empty()
+            }
+        };
+
+        if self.accessibility_dirty {
+            self.accessibility_dirty = false;
+            self.publish_accessibility();
+        }
+
+        let focus_update = self.handle_focus(layout, info);
         
         // Ensure ItemView exists if mode is Table
         if *self.view_mode.get() == FileListViewMode::Table {
+            let mut dates_update = Update::empty();
+            if dates::is_relative() && self.dates_refreshed.elapsed() >= dates::REFRESH_INTERVAL {
+                self.dates_refreshed = Instant::now();
+                dates_update = Update::DRAW;
+            }
             self.ensure_item_view();
             if let Some(ref mut view) = self.item_view {
                  // Sync FileList selection (paths) -> ItemView selection (indices)
-                 let current_selected_paths = self.selected_paths.get();
+                 // One pass over the entries, so selecting thousands of them
+                 // doesn't search the listing for each one on every update
+                 let current_selected_paths: HashSet<PathBuf> = self.selected_paths.get().iter().cloned().collect();
                  let entries = self.entries.get();
-                 let mut indices = Vec::new();
-                 
-                 for path in current_selected_paths.iter() {
-                     if let Some(idx) = entries.iter().position(|e| e.path == *path) {
-                         indices.push(idx);
-                     }
-                 }
+                 let indices: Vec<usize> = entries
+                     .iter()
+                     .enumerate()
+                     .filter(|(_, e)| current_selected_paths.contains(&e.path))
+                     .map(|(idx, _)| idx)
+                     .collect();
                  
                  // Access view internal signal if possible, or we need to expose it on ItemView trait?
                  // ItemView is concrete struct here? No, it's ItemView struct.
@@ -410,44 +1375,19 @@ impl Widget for FileList {
                      signal.set(indices);
                  }
                  
-                 return view.update(layout, context, info).await;
+                 let view_update = view.update(layout, context.clone(), info).await;
+                 return view_update
+                     | self.handle_header_menu(&context, info)
+                     | selection_update
+                     | listing_update
+                     | focus_update
+                     | fs_update
+                     | zoom_update
+                     | dates_update;
             }
         }
 
-        let mut update = Update::empty();
-
-        // Poll filesystem events
-        if let Ok(mut rx) = self._event_rx.try_lock() {
-            while let Ok(event) = rx.try_recv() {
-                match event {
-                    FileSystemEvent::DirectoryLoaded { path, entries } => {
-                        if path == *self.current_path.get() {
-                            self.entries.set(entries);
-                            
-                            // Re-sync selection indices if using ItemView
-                            // This ensures that if the file list changes (e.g. reload), selection indices are valid
-                            // Logic is handled below in the view update block, so just trigger Update
-                            update.insert(Update::LAYOUT | Update::DRAW);
-                        }
-                    },
-                    FileSystemEvent::EntryAdded { path, .. } | FileSystemEvent::EntryRemoved { path } | FileSystemEvent::EntryModified { path, .. } => {
-                        if let Some(parent) = path.parent() {
-                            if parent == *self.current_path.get() {
-                                let _ = self.fs_model.refresh(parent);
-                                // Invalidate caches for the affected path
-                                if let Err(e) = self.cache_invalidate_tx.send(path.clone()) {
-                                    log::warn!("Failed to send cache invalidation request: {}", e);
-                                }
-                            }
-                        }
-                    },
-                    _ => {
-                        // For other events, we might want to refresh if they affect current path
-                        // But for now, let's just rely on DirectoryLoaded
-                    },
-                }
-            }
-        }
+        let mut update = selection_update | listing_update | focus_update | fs_update | zoom_update;
 
         // Update child (ScrollContainer)
         if !layout.children.is_empty() {
@@ -466,17 +1406,70 @@ impl Widget for FileList {
         info: &mut AppInfo,
         context: AppContext,
     ) {
-        if *self.view_mode.get() == FileListViewMode::Table {
-            if let Some(ref mut view) = self.item_view {
-                view.render(graphics, layout, info, context);
-                return;
-            }
+        let frame_start = perf::is_enabled().then(Instant::now);
+
+        let table_mode = *self.view_mode.get() == FileListViewMode::Table;
+        match self.item_view.as_mut() {
+            Some(view) if table_mode => view.render(graphics, layout, info, context.clone()),
+            // Render ScrollContainer
+            _ if !layout.children.is_empty() => {
+                self.scroll_container
+                    .render(graphics, &layout.children[0], info, context.clone());
+            },
+            _ => {},
         }
-        
-        // Render ScrollContainer
-        if !layout.children.is_empty() {
-            self.scroll_container
-                .render(graphics, &layout.children[0], info, context);
+
+        if let Some(focus) = &self.focus {
+            focus.render_ring(Pane::FileList, graphics, layout, context.palette());
+        }
+
+        if let Some(frame_start) = frame_start {
+            perf::record_frame(frame_start.elapsed());
+            self.render_perf_overlay(graphics, context.palette(), layout, info);
+        }
+    }
+}
+
+impl FileList {
+    /// Draw the performance counters over the top right corner.
+    fn render_perf_overlay(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        palette: &Palette,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+    ) {
+        const LINE_HEIGHT: f64 = 16.0;
+        const WIDTH: f64 = 300.0;
+
+        let lines = perf::snapshot().lines();
+        let x1 = (layout.layout.location.x + layout.layout.size.width) as f64 - 8.0;
+        let y0 = layout.layout.location.y as f64 + 8.0;
+        let rect = Rect::new(
+            x1 - WIDTH,
+            y0,
+            x1,
+            y0 + lines.len() as f64 * LINE_HEIGHT + 8.0,
+        );
+        graphics.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            &Brush::Solid(color_scheme::color(palette, ColorRole::Window).with_alpha(0.85)),
+            None,
+            &rect.to_path(0.1),
+        );
+        for (i, line) in lines.iter().enumerate() {
+            self.perf_overlay_text.render_text(
+                &mut info.font_context,
+                graphics,
+                line,
+                None,
+                12.0,
+                Brush::Solid(color_scheme::color(palette, ColorRole::BaseText)),
+                Affine::translate((rect.x0 + 6.0, rect.y0 + 4.0 + i as f64 * LINE_HEIGHT)),
+                true,
+                Some(WIDTH as f32 - 12.0),
+            );
         }
     }
 }
@@ -490,15 +1483,16 @@ impl WidgetLayoutExt for FileList {
 /// Inner widget that renders the actual list content.
 struct FileListContent {
     entries: StateSignal<Vec<FileEntry>>,
+    launchers: StateSignal<LauncherLabels>,
     selected_paths: StateSignal<Vec<PathBuf>>,
     current_path: StateSignal<PathBuf>,
     view_mode: StateSignal<FileListViewMode>,
     icon_size: StateSignal<u32>,
+    zoom: StateSignal<FileListZoom>,
     fs_model: Arc<FileSystemModel>,
     icon_registry: Arc<IconRegistry>,
     thumbnail_service: Arc<ThumbnailService>,
 
-    item_height: f32,
     text_render_context: TextRenderContext,
     thumbnail_size: u32,
 
@@ -507,16 +1501,14 @@ struct FileListContent {
     last_click_index: Option<usize>,
     anchor_index: Option<usize>, // For Shift+Click range selection
 
-    // Icon cache per entry (to avoid repeated lookups)
-    icon_cache: Arc<
-        Mutex<std::collections::HashMap<(PathBuf, u32), Option<npio::service::icon::CachedIcon>>>,
-    >,
+    // Icon cache per entry (to avoid repeated lookups), least recently used dropped first
+    icon_cache: IconCache,
 
     // Track pending thumbnail requests to avoid duplicate requests
     pending_thumbnails: Arc<Mutex<HashSet<PathBuf>>>,
 
-    // Thumbnail cache: (path, size) -> ThumbnailImage
-    thumbnail_cache: Arc<Mutex<std::collections::HashMap<(PathBuf, u32), ThumbnailImage>>>,
+    // Thumbnail cache: (path, size) -> ThumbnailImage, least recently used dropped first
+    thumbnail_cache: ThumbnailCache,
 
     // Thumbnail event receiver
     thumbnail_event_rx: Arc<Mutex<tokio::sync::broadcast::Receiver<ThumbnailEvent>>>,
@@ -565,11 +1557,26 @@ struct FileListContent {
     hovered_item_index: Option<usize>, // Index of file item currently hovered
     tooltip_shown: bool, // Track if tooltip popup is currently shown
 
-    // Semaphore to limit concurrent async tasks (icon/thumbnail loading)
-    async_task_semaphore: Arc<tokio::sync::Semaphore>,
+    // Icon and thumbnail loads, visible rows first with bounded concurrency
+    load_queue: LoadQueue,
     
     // Track previous path to detect directory changes
     previous_path: Option<PathBuf>,
+
+    // Rename dialog currently shown (polled for the result in update())
+    rename_dialog: Option<RenameDialogHandle>,
+
+    // Callbacks registered on the owning FileList
+    callbacks: Arc<Mutex<FileListCallbacks>>,
+
+    // Properties requests from FileList::show_properties
+    properties_request: Arc<Mutex<Option<Vec<PathBuf>>>>,
+
+    // Lists hovered folders in the background (shared with FileList)
+    prefetcher: Prefetcher,
+
+    // Whether thumbnails are generated (shared with FileList)
+    thumbnails: Arc<AtomicBool>,
 }
 
 #[derive(Clone)]
@@ -578,24 +1585,40 @@ struct PendingAction {
     app_id: Option<String>,
     properties: bool,
     delete: bool, // If true, this is a delete action
+    rename: bool, // If true, show the rename dialog for the single path
+    operation: Option<FileListOperation>, // Handed to the embedder as is
+}
+
+impl PendingAction {
+    /// The operation the embedder handles for this action, if it isn't one the
+    /// list asks about first (delete, rename).
+    fn forwarded(&self) -> Option<FileListOperation> {
+        if self.delete || self.rename {
+            return None;
+        }
+        let paths = self.paths.clone();
+        Some(match (&self.operation, &self.app_id) {
+            (Some(operation), _) => operation.clone(),
+            (None, Some(app_id)) => FileListOperation::OpenWith { paths, app_id: app_id.clone() },
+            (None, None) if self.properties => FileListOperation::Properties(paths),
+            (None, None) => FileListOperation::Open(paths),
+        })
+    }
 }
 
 impl FileListContent {
     // Cache size limits to prevent unbounded memory growth
-    const MAX_ICON_CACHE_SIZE: usize = 1000;
-    const MAX_THUMBNAIL_CACHE_SIZE: usize = 500;
     const MAX_LAYOUT_CACHE_SIZE: usize = 2000;
     const MAX_SVG_SCENE_CACHE_SIZE: usize = 500;
 
-    // Maximum number of concurrent async tasks (icon/thumbnail loading)
-    const MAX_CONCURRENT_ASYNC_TASKS: usize = 50;
-
     fn new(
         entries: StateSignal<Vec<FileEntry>>,
+        launchers: StateSignal<LauncherLabels>,
         selected_paths: StateSignal<Vec<PathBuf>>,
         current_path: StateSignal<PathBuf>,
         view_mode: StateSignal<FileListViewMode>,
         icon_size: StateSignal<u32>,
+        zoom: StateSignal<FileListZoom>,
         fs_model: Arc<FileSystemModel>,
         icon_registry: Arc<IconRegistry>,
         thumbnail_service: Arc<ThumbnailService>,
@@ -605,25 +1628,33 @@ impl FileListContent {
         cache_invalidate_rx: tokio::sync::mpsc::UnboundedReceiver<PathBuf>,
         operation_tx: Option<tokio::sync::mpsc::UnboundedSender<FileListOperation>>,
         selection_change_tx: Option<Arc<tokio::sync::mpsc::UnboundedSender<Vec<PathBuf>>>>,
+        callbacks: Arc<Mutex<FileListCallbacks>>,
+        properties_request: Arc<Mutex<Option<Vec<PathBuf>>>>,
+        prefetcher: Prefetcher,
+        thumbnails: Arc<AtomicBool>,
     ) -> Self {
         Self {
             entries,
+            launchers,
             selected_paths,
             current_path,
             view_mode,
             icon_size,
+            zoom,
             fs_model,
             icon_registry,
             thumbnail_service,
-            item_height: 30.0,
             text_render_context: TextRenderContext::new(),
             thumbnail_size: 128,
             last_click_time: None,
             last_click_index: None,
             anchor_index: None,
-            icon_cache: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            icon_cache: Arc::new(Mutex::new(LruCache::new(ICON_CACHE_BYTES, icon_bytes))),
             pending_thumbnails: Arc::new(Mutex::new(HashSet::new())),
-            thumbnail_cache: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            thumbnail_cache: Arc::new(Mutex::new(LruCache::new(
+                THUMBNAIL_CACHE_BYTES,
+                thumbnail_bytes,
+            ))),
             thumbnail_event_rx: Arc::new(Mutex::new(thumbnail_event_rx)),
             update_manager: Arc::new(Mutex::new(None)),
             cache_update_tx,
@@ -646,8 +1677,13 @@ impl FileListContent {
             selection_change_tx,
             hovered_item_index: None,
             tooltip_shown: false,
-            async_task_semaphore: Arc::new(tokio::sync::Semaphore::new(Self::MAX_CONCURRENT_ASYNC_TASKS)),
+            load_queue: LoadQueue::default(),
             previous_path: None,
+            rename_dialog: None,
+            callbacks,
+            properties_request,
+            prefetcher,
+            thumbnails,
         }
         .with_thumbnail_size(128)
     }
@@ -657,48 +1693,15 @@ impl FileListContent {
         self
     }
 
-    /// Evict entries from icon cache if it exceeds the limit
-    /// 
-    /// NOTE: This is NOT a true LRU (Least Recently Used) eviction strategy.
-    /// HashMap iteration order is not guaranteed, so this removes arbitrary entries.
-    /// For proper LRU behavior, consider using LinkedHashMap or a custom LRU cache structure.
-    /// This simple eviction prevents unbounded memory growth but may evict frequently-used entries.
-    fn evict_icon_cache_if_needed(&self) {
-        let mut cache = self.icon_cache.lock().expect("Failed to lock icon_cache for eviction");
-        if cache.len() > Self::MAX_ICON_CACHE_SIZE {
-            // Simple eviction: remove oldest entries (first N entries)
-            // NOTE: HashMap iteration order is not guaranteed, so this is not true LRU
-            let to_remove = cache.len() - Self::MAX_ICON_CACHE_SIZE;
-            let keys: Vec<_> = cache.keys().take(to_remove).cloned().collect();
-            for key in keys {
-                cache.remove(&key);
-            }
-            log::debug!("Evicted {} entries from icon cache", to_remove);
-        }
-    }
-
-    /// Evict entries from thumbnail cache if it exceeds the limit
-    /// 
-    /// NOTE: This is NOT a true LRU (Least Recently Used) eviction strategy.
-    /// See evict_icon_cache_if_needed() for details.
-    fn evict_thumbnail_cache_if_needed(&self) {
-        let mut cache = self.thumbnail_cache.lock().expect("Failed to lock thumbnail_cache for eviction");
-        if cache.len() > Self::MAX_THUMBNAIL_CACHE_SIZE {
-            // Simple eviction: remove oldest entries (first N entries)
-            // NOTE: HashMap iteration order is not guaranteed, so this is not true LRU
-            let to_remove = cache.len() - Self::MAX_THUMBNAIL_CACHE_SIZE;
-            let keys: Vec<_> = cache.keys().take(to_remove).cloned().collect();
-            for key in keys {
-                cache.remove(&key);
-            }
-            log::debug!("Evicted {} entries from thumbnail cache", to_remove);
-        }
+    /// Height of the rows of the List view at the current zoom
+    fn item_height(&self) -> f32 {
+        self.zoom.get().row_height(FileListViewMode::List)
     }
 
     /// Evict entries from layout cache if it exceeds the limit
     /// 
     /// NOTE: This is NOT a true LRU (Least Recently Used) eviction strategy.
-    /// See evict_icon_cache_if_needed() for details.
+    /// HashMap iteration order is not guaranteed, so this removes arbitrary entries.
     /// 
     /// Additionally, layout_cache is cleared when viewport width changes significantly,
     /// as layout calculations depend on available width. Icon and thumbnail caches are
@@ -719,7 +1722,7 @@ impl FileListContent {
     /// Evict entries from SVG scene cache if it exceeds the limit
     /// 
     /// NOTE: This is NOT a true LRU (Least Recently Used) eviction strategy.
-    /// See evict_icon_cache_if_needed() for details.
+    /// See evict_layout_cache_if_needed() for details.
     fn evict_svg_scene_cache_if_needed(&mut self) {
         if self.svg_scene_cache.len() > Self::MAX_SVG_SCENE_CACHE_SIZE {
             // Simple eviction: remove oldest entries (first N entries)
@@ -733,6 +1736,85 @@ impl FileListContent {
         }
     }
 
+    /// Queue generating the thumbnail of `entry` unless one is already on the way.
+    ///
+    /// The thumbnail service reports completion with a `ThumbnailReady` event, which
+    /// loads the image into the thumbnail cache.
+    pub(super) fn request_thumbnail(&self, entry: &FileEntry, size: u32) {
+        if !self.thumbnails.load(Ordering::Relaxed) {
+            return;
+        }
+        let pending = self.pending_thumbnails.lock().expect("Failed to lock pending_thumbnails");
+        if pending.contains(&entry.path) {
+            return;
+        }
+        drop(pending);
+
+        let pending_clone = self.pending_thumbnails.clone();
+        let service_clone = self.thumbnail_service.clone();
+        let entry_clone = entry.clone();
+        self.load_queue.request(LoadKind::Thumbnail, &entry.path, size, move || async move {
+            // Marked only once running, so a request dropped from the queue can be made again
+            if !pending_clone.lock().expect("Failed to lock pending_thumbnails in async task").insert(entry_clone.path.clone()) {
+                return;
+            }
+            if let Ok(file) = get_file_for_uri(&file_entry_to_uri(&entry_clone)) {
+                let _ = service_clone
+                    .get_or_generate_thumbnail(&*file, u32_to_thumbnail_size(size), None)
+                    .await;
+            }
+        });
+    }
+
+    /// The cached icon of `entry`, queueing it to be loaded if it isn't cached yet.
+    pub(super) fn cached_icon(
+        &self,
+        entry: &FileEntry,
+        size: u32,
+    ) -> Option<npio::service::icon::CachedIcon> {
+        let cache_key = (entry.path.clone(), size);
+        {
+            let mut cache = self.icon_cache.lock().expect("Failed to lock icon_cache");
+            if let Some(icon) = cache.get(&cache_key) {
+                return icon.clone();
+            }
+        }
+
+        let cache_clone = self.icon_cache.clone();
+        let registry_clone = self.icon_registry.clone();
+        let entry_clone = entry.clone();
+        let launcher_icon = self.launchers.get().icon_name(&entry.path).map(str::to_string);
+        let cache_update_tx_clone = self.cache_update_tx.clone();
+        self.load_queue.request(LoadKind::Icon, &entry.path, size, move || async move {
+            // Special files are never opened to guess their type
+            if let Some(kind) = special_files::entry_kind(&entry_clone) {
+                let icon = registry_clone.get_icon(kind.icon_name(), size);
+                cache_clone.lock().expect("Failed to lock icon_cache in async task").insert(cache_key, icon);
+                let _ = cache_update_tx_clone.try_send(());
+                return;
+            }
+            // Launchers show the icon of what they start
+            if let Some(icon) = launcher_icon
+                .and_then(|name| registry_clone.get_icon(&name, size))
+            {
+                cache_clone.lock().expect("Failed to lock icon_cache in async task").insert(cache_key, Some(icon));
+                let _ = cache_update_tx_clone.try_send(());
+                return;
+            }
+            let uri = file_entry_to_uri(&entry_clone);
+            if let Ok(file) = get_file_for_uri(&uri) {
+                let icon = registry_clone.get_file_icon(&*file, size).await;
+                let mut cache = cache_clone.lock().expect("Failed to lock icon_cache in async task");
+                cache.insert(cache_key, icon);
+                // Notify that cache was updated to trigger redraw
+                if cache_update_tx_clone.try_send(()).is_err() {
+                    log::debug!("Cache update channel full, skipping notification");
+                }
+            }
+        });
+        None
+    }
+
     /// Invalidate all caches for a given path (used when files are deleted or moved)
     /// 
     /// This method is called automatically when FileSystemEvent::EntryRemoved is received,
@@ -800,11 +1882,15 @@ impl FileListContent {
     fn format_file_size_for_tooltip(&self, path: &PathBuf) -> String {
         if let Ok(metadata) = fs::metadata(path) {
             if metadata.is_dir() {
-                // For directories, just show "Directory" (not recursive size - too expensive for tooltips)
-                "Directory".to_string()
+                // Recursive sizes are only shown once computed in the background
+                match self.directory_sizes.as_ref().map(|provider| provider(path)) {
+                    Some(Some(size)) => sizes::format(size),
+                    Some(None) => "Computing size...".to_string(),
+                    None => "Directory".to_string(),
+                }
             } else {
                 // For files, show human-readable size
-                format_size(metadata.len(), BINARY)
+                sizes::format(metadata.len())
             }
         } else {
             "Unknown size".to_string()
@@ -820,10 +1906,10 @@ impl FileListContent {
 
         if view_mode == FileListViewMode::List {
             // List view: simple row calculation
-            if self.item_height <= 0.0 {
+            if self.item_height() <= 0.0 {
                 return None;
             }
-            let idx = (local_y / self.item_height) as usize;
+            let idx = (local_y / self.item_height()) as usize;
             if idx < entries_len {
                 Some(idx)
             } else {
@@ -935,12 +2021,12 @@ impl FileListContent {
         } else {
             // List view
             for (i, entry) in entries.iter().enumerate() {
-                let y = i as f32 * self.item_height;
+                let y = i as f32 * self.item_height();
                 let row_rect = Rect::new(
                     0.0,
                     y as f64,
                     layout_width as f64,
-                    (y + self.item_height) as f64,
+                    (y + self.item_height()) as f64,
                 );
 
                 if check_intersection(row_rect) {
@@ -1059,7 +2145,7 @@ impl Widget for FileListContent {
             // Approx: rows * (cell + spacing) - spacing + padding
             (rows * (cell_height + spacing) - spacing + self.icon_view_padding * 2.0).max(100.0)
         } else {
-            (count as f32 * self.item_height).max(100.0)
+            (count as f32 * self.item_height()).max(100.0)
         };
 
         // Note: FileListContent doesn't create child widgets currently - it renders manually.
@@ -1150,10 +2236,13 @@ impl Widget for FileListContent {
             // Hover state changed
             if let Some(index) = current_hovered_index {
                 if index < entries_len {
-                    let entry_path = {
+                    let (entry_path, entry_is_dir) = {
                         let entries = self.entries.get();
-                        entries[index].path.clone()
+                        (entries[index].path.clone(), entries[index].is_dir())
                     };
+                    if entry_is_dir {
+                        self.prefetcher.request(&entry_path);
+                    }
                     let tooltip_text = self.format_file_size_for_tooltip(&entry_path);
                     // Show tooltip using TooltipManager
                     if let Some(cursor) = info.cursor_pos {
@@ -1185,8 +2274,6 @@ impl Widget for FileListContent {
         static UPDATE_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
         let counter = UPDATE_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         if counter % 60 == 0 {
-            self.evict_icon_cache_if_needed();
-            self.evict_thumbnail_cache_if_needed();
             self.evict_layout_cache_if_needed();
             self.evict_svg_scene_cache_if_needed();
         }
@@ -1211,16 +2298,14 @@ impl Widget for FileListContent {
                             if let Ok(file) = get_file_for_uri(&uri) {
                                 let update_mgr_clone = self.update_manager.clone();
                                 let cache_update_tx_clone = self.cache_update_tx.clone();
-                                let semaphore_clone = self.async_task_semaphore.clone();
-                                tokio::spawn(async move {
-                                    // Acquire semaphore permit to limit concurrent tasks
-                                    let _permit = semaphore_clone.acquire().await.ok();
+                                self.load_queue.request(LoadKind::ThumbnailImage, &entry_path, size_u32, move || async move {
                                     if let Ok(thumbnail_image) = service_clone
                                         .get_thumbnail_image(&*file, size, None)
                                         .await
                                     {
                                         let mut cache = cache_clone.lock().expect("Failed to lock thumbnail_cache in async task");
                                         cache.insert((path_clone, size_u32), thumbnail_image);
+                                        perf::record_thumbnail();
                                         
                                         // Trigger redraw when thumbnail is cached
                                         if let Ok(mut update_mgr) = update_mgr_clone.lock() {
@@ -1235,7 +2320,6 @@ impl Widget for FileListContent {
                                             log::debug!("Cache update channel full, skipping notification");
                                         }
                                     }
-                                    // Permit is automatically released when dropped
                                 });
                             }
                             
@@ -1401,10 +2485,10 @@ impl Widget for FileListContent {
                 } else {
                     // List view
                     // Guard against negative coordinates and division by zero
-                    if local_y < 0.0 || self.item_height <= 0.0 {
+                    if local_y < 0.0 || self.item_height() <= 0.0 {
                         None
                     } else {
-                        let idx = (local_y / self.item_height) as usize;
+                        let idx = (local_y / self.item_height()) as usize;
                         let entries = self.entries.get();
                         if idx < entries.len() {
                             Some(idx)
@@ -1489,6 +2573,8 @@ impl Widget for FileListContent {
                                                     app_id: None,
                                                     properties: false,
                                                     delete: false,
+                                                    rename: false,
+                                                    operation: None,
                                                 });
                                             }
                                             Update::DRAW
@@ -1509,11 +2595,38 @@ impl Widget for FileListContent {
                                 );
                             }
 
+                            // Add Copy, Cut, Compress and Move to Trash items (handled by the embedder)
+                            if self.operation_tx.is_some() {
+                                let forwarded_items = [
+                                    (MenuCommand::Custom(0x2008), "Copy", FileListOperation::Copy(paths_for_action.clone())),
+                                    (MenuCommand::Custom(0x2009), "Cut", FileListOperation::Move(paths_for_action.clone())),
+                                    (MenuCommand::Custom(0x200A), "Compress", FileListOperation::Compress(paths_for_action.clone())),
+                                    (MenuCommand::Custom(0x200B), "Move to Trash", FileListOperation::Trash(paths_for_action.clone())),
+                                ];
+                                for (command, label, operation) in forwarded_items {
+                                    let pending = self.pending_action.clone();
+                                    let paths = paths_for_action.clone();
+                                    core_items.push(MenuItem::new(command, label).with_action(move || {
+                                        if let Ok(mut pending_lock) = pending.lock() {
+                                            *pending_lock = Some(PendingAction {
+                                                paths: paths.clone(),
+                                                app_id: None,
+                                                properties: false,
+                                                delete: false,
+                                                rename: false,
+                                                operation: Some(operation.clone()),
+                                            });
+                                        }
+                                        Update::DRAW
+                                    }));
+                                }
+                            }
+
                             // Add Delete item
                             let pending_delete = self.pending_action.clone();
                             let delete_paths = paths_for_action.clone();
                             core_items.push(
-                                MenuItem::new(MenuCommand::FileDelete, "Delete")
+                                MenuItem::new(MenuCommand::FileDelete, "Delete Permanently")
                                     .with_action(move || {
                                         log::warn!("====== DELETE MENU ITEM CLICKED - setting pending_action for {} paths ======", delete_paths.len());
                                         if let Ok(mut pending_lock) = pending_delete.lock() {
@@ -1522,6 +2635,8 @@ impl Widget for FileListContent {
                                                 app_id: None,
                                                 properties: false,
                                                 delete: true,
+                                                rename: false,
+                                                operation: None,
                                             });
                                             log::warn!("====== pending_action.delete set to true ======");
                                         }
@@ -1529,6 +2644,28 @@ impl Widget for FileListContent {
                                     }),
                             );
 
+                            // Add Rename item (single selection only)
+                            if paths_for_action.len() == 1 {
+                                let pending_rename = self.pending_action.clone();
+                                let rename_paths = paths_for_action.clone();
+                                core_items.push(
+                                    MenuItem::new(MenuCommand::Custom(0x2007), "Rename")
+                                        .with_action(move || {
+                                            if let Ok(mut pending_lock) = pending_rename.lock() {
+                                                *pending_lock = Some(PendingAction {
+                                                    paths: rename_paths.clone(),
+                                                    app_id: None,
+                                                    properties: false,
+                                                    delete: false,
+                                                    rename: true,
+                                                    operation: None,
+                                                });
+                                            }
+                                            Update::DRAW
+                                        }),
+                                );
+                            }
+
                             // Add Properties item
                             let pending_props = self.pending_action.clone();
                             let props_paths = paths_for_action.clone();
@@ -1542,6 +2679,8 @@ impl Widget for FileListContent {
                                                 app_id: None,
                                                 properties: true,
                                                 delete: false,
+                                                rename: false,
+                                                operation: None,
                                             });
                                             println!("DEBUG: Properties action set in pending_action");
                                         }
@@ -1551,6 +2690,20 @@ impl Widget for FileListContent {
 
                             // Build groups with separators
                             let mut all_items = core_items;
+
+                            // Append items provided by the embedder
+                            let extra_items = self
+                                .callbacks
+                                .lock()
+                                .ok()
+                                .and_then(|callbacks| {
+                                    callbacks.on_context_menu.as_ref().map(|cb| cb(&paths_for_action))
+                                })
+                                .unwrap_or_default();
+                            if !extra_items.is_empty() {
+                                all_items.push(MenuItem::separator());
+                                all_items.extend(extra_items);
+                            }
                             all_items.push(MenuItem::separator());
                             all_items.push(
                                 MenuItem::new(MenuCommand::Custom(0x2003), "Share (placeholder)")
@@ -1632,6 +2785,11 @@ impl Widget for FileListContent {
                                                 update.insert(Update::LAYOUT);
                                             }
                                         }
+                                        if let Ok(callbacks) = self.callbacks.lock() {
+                                            if let Some(on_activate) = &callbacks.on_activate {
+                                                update |= on_activate(target_path.clone());
+                                            }
+                                        }
                                     }
                                 }
                             }
@@ -1704,6 +2862,13 @@ impl Widget for FileListContent {
             }
         }
 
+        // Process properties requests from FileList::show_properties
+        let properties_paths = self.properties_request.lock().ok().and_then(|mut r| r.take());
+        if let Some(paths) = properties_paths {
+            self.show_properties_popup(&paths, context.clone());
+            update.insert(Update::DRAW);
+        }
+
         // Check menu state to detect when menu closes
         let menu_is_open = context.menu_manager.is_open();
         
@@ -1732,6 +2897,18 @@ impl Widget for FileListContent {
                         log::warn!("====== SHOWING DELETE CONFIRMATION DIALOG for {} paths ======", action.paths.len());
                         self.show_delete_confirmation_dialog(&action.paths, context);
                         update.insert(Update::DRAW);
+                    } else if action.rename {
+                        if let Some(path) = action.paths.first() {
+                            self.rename_dialog = Some(RenameDialog::new(path.clone()).show(&context));
+                            update.insert(Update::DRAW);
+                        }
+                    } else if let (Some(op_tx), Some(operation)) = (&self.operation_tx, action.forwarded()) {
+                        // The embedder opens, copies and shows properties the same way
+                        // as from its toolbar and shortcuts
+                        if let Err(e) = op_tx.send(operation) {
+                            log::error!("Failed to send operation: {}", e);
+                        }
+                        update.insert(Update::DRAW);
                     } else if let Some(app_id) = action.app_id {
                         for path in action.paths.iter() {
                             if let Err(err) = self.mime_registry.launch(&app_id, path) {
@@ -1793,6 +2970,19 @@ impl Widget for FileListContent {
             self.menu_was_open = menu_is_open;
         }
 
+        // Process a confirmed rename from the rename dialog
+        if let Some((from, to)) = self.rename_dialog.as_ref().and_then(|h| h.try_take()) {
+            self.rename_dialog = None;
+            if let Some(ref op_tx) = self.operation_tx {
+                if let Err(e) = op_tx.send(FileListOperation::Rename { from, to }) {
+                    log::error!("Failed to send rename operation: {}", e);
+                }
+            } else {
+                log::warn!("Rename confirmed but no operation channel available");
+            }
+            update.insert(Update::DRAW);
+        }
+
         // Process confirmed delete operations (user clicked "Delete" in confirmation dialog)
         if let Ok(mut pending_delete) = self.pending_delete_confirmation.lock() {
             if let Some(paths) = pending_delete.take() {
@@ -1832,6 +3022,9 @@ impl Widget for FileListContent {
 
         let view_mode = *self.view_mode.get();
 
+        // Loads requested by the rows drawn now go before those of earlier frames
+        self.load_queue.next_frame();
+
         if view_mode == FileListViewMode::Icon {
             self.render_icon_view(graphics, palette, layout, info);
         } else if view_mode == FileListViewMode::Compact {
@@ -1850,7 +3043,7 @@ impl Widget for FileListContent {
 
                 let rect = Rect::new(min_x, min_y, max_x, max_y);
 
-                let selection_color = palette.color(ColorRole::Selection);
+                let selection_color = color_scheme::color(palette, ColorRole::Selection);
 
                 // Draw selection fill
                 graphics.fill(
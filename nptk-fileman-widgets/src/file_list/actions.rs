@@ -7,6 +7,22 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// Open `path` with the default application for its type, like "Open" in the context menu.
+pub fn open_with_default_app(path: PathBuf) {
+    FileListContent::launch_path(MimeRegistry::load_default(), path);
+}
+
+/// Open `paths` with the application `app_id`, like "Open With" in the context menu.
+pub fn open_with_app(app_id: &str, paths: &[PathBuf]) -> Result<(), String> {
+    let registry = MimeRegistry::load_default();
+    for path in paths {
+        registry
+            .launch(app_id, path)
+            .map_err(|e| format!("Failed to open {} with {}: {}", path.display(), app_id, e))?;
+    }
+    Ok(())
+}
+
 impl FileListContent {
     pub(super) fn launch_path(registry: MimeRegistry, path: PathBuf) {
         let mime = smol::block_on(MimeDetector::detect_mime_type(&path)).or_else(|| Self::xdg_mime_filetype(&path));
@@ -122,6 +138,8 @@ impl FileListContent {
                                 app_id: Some(app_id_cloned.clone()),
                                 properties: false,
                                 delete: false,
+                                rename: false,
+                                operation: None,
                             });
                         }
                         Update::DRAW
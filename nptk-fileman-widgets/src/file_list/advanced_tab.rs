@@ -0,0 +1,81 @@
+//! Advanced tab of Properties
+//!
+//! Low-level details of a single item as the filesystem stores them: inode,
+//! hard links, allocated blocks and device. Useful for debugging and forensics.
+//! Links are described themselves, not their targets.
+
+use crate::sizes;
+use nptk::prelude::*;
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+
+/// "major:minor" of a Linux device number.
+fn device_name(dev: u64) -> String {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    format!("{}:{}", major, minor)
+}
+
+/// Label and value of each detail of `metadata`.
+fn rows(metadata: &fs::Metadata) -> Vec<(&'static str, String)> {
+    let mut rows = vec![
+        ("Inode", metadata.ino().to_string()),
+        ("Hard links", metadata.nlink().to_string()),
+        (
+            "Blocks",
+            format!(
+                "{} of 512 bytes ({} on disk)",
+                metadata.blocks(),
+                sizes::format(metadata.blocks().saturating_mul(512))
+            ),
+        ),
+        ("I/O block size", sizes::format(metadata.blksize())),
+        ("Device", format!("{} ({})", device_name(metadata.dev()), metadata.dev())),
+    ];
+    let file_type = metadata.file_type();
+    if file_type.is_char_device() || file_type.is_block_device() {
+        rows.push(("Device node", device_name(metadata.rdev())));
+    }
+    rows
+}
+
+/// The tab for `path`, with a message instead of details if it can't be read.
+pub(super) fn advanced_tab(path: &Path) -> Container {
+    let children: Vec<Box<dyn Widget>> = match fs::symlink_metadata(path) {
+        Ok(metadata) => rows(&metadata)
+            .into_iter()
+            .map(|(label, value)| -> Box<dyn Widget> {
+                Box::new(
+                    Container::new(vec![
+                        Box::new(Text::new(format!("{}:", label)).with_layout_style(LayoutStyle {
+                            size: Vector2::new(Dimension::length(110.0), Dimension::auto()),
+                            ..Default::default()
+                        })),
+                        Box::new(Text::new(value)),
+                    ])
+                    .with_layout_style(LayoutStyle {
+                        size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                        flex_direction: FlexDirection::Row,
+                        gap: Vector2::new(LengthPercentage::length(6.0), LengthPercentage::length(0.0)),
+                        ..Default::default()
+                    }),
+                )
+            })
+            .collect(),
+        Err(e) => vec![Box::new(Text::new(format!("Failed to read {}: {}", path.display(), e)))],
+    };
+
+    Container::new(children).with_layout_style(LayoutStyle {
+        size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+        flex_direction: FlexDirection::Column,
+        padding: Rect {
+            left: LengthPercentage::length(12.0),
+            right: LengthPercentage::length(12.0),
+            top: LengthPercentage::length(12.0),
+            bottom: LengthPercentage::length(12.0),
+        },
+        gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(6.0)),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,32 @@
+use nptk::core::app::update::Update;
+use nptk::core::menu::MenuItem;
+use super::emblems::Emblem;
+use std::path::{Path, PathBuf};
+
+/// Called when an entry is activated (double-clicked).
+pub type ActivateCallback = Box<dyn Fn(PathBuf) -> Update + Send + Sync>;
+
+/// Called with the new selection whenever it changes.
+pub type SelectionChangedCallback = Box<dyn Fn(Vec<PathBuf>) -> Update + Send + Sync>;
+
+/// Called when the context menu opens; returned items are appended to the menu.
+pub type ContextMenuCallback = Box<dyn Fn(&[PathBuf]) -> Vec<MenuItem> + Send + Sync>;
+
+/// Returns the emblem to draw over an entry's icon, if any. Called while rendering.
+pub type EmblemCallback = Box<dyn Fn(&Path) -> Option<Emblem> + Send + Sync>;
+
+/// Returns whether an entry should be drawn dimmed. Called while rendering.
+pub type DimCallback = Box<dyn Fn(&Path) -> bool + Send + Sync>;
+
+/// Callbacks registered through the FileList builder methods.
+///
+/// Shared between [super::FileList] and its inner content widget, which is where
+/// activation and context menu requests originate.
+#[derive(Default)]
+pub(super) struct FileListCallbacks {
+    pub(super) on_activate: Option<ActivateCallback>,
+    pub(super) on_selection_changed: Option<SelectionChangedCallback>,
+    pub(super) on_context_menu: Option<ContextMenuCallback>,
+    pub(super) emblem_provider: Option<EmblemCallback>,
+    pub(super) dim_provider: Option<DimCallback>,
+}
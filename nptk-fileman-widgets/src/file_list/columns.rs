@@ -0,0 +1,157 @@
+//! Columns of the Table view
+//!
+//! Right-clicking the column header offers which columns to show, how to sort
+//! and "Auto-size Columns". Hidden columns are left out of the model given to
+//! the view; auto-sizing fits each shown column to its longest value.
+
+use super::model_adapter::FileSystemItemModel;
+use super::sorting::{FileListSort, SortOrder};
+use nptk::core::app::update::Update;
+use nptk::core::menu::{MenuCommand, MenuItem, MenuTemplate};
+use nptk::core::model::{ItemModel, ItemRole, ModelData, Orientation};
+use nptk::core::signal::Signal;
+use nptk::core::signal::state::StateSignal;
+
+/// Average width of a character of the Table view's text
+const CHAR_WIDTH: f32 = 7.5;
+/// Room for the cell padding and, in the Name column, the icon
+const CELL_PADDING: f32 = 16.0;
+const ICON_WIDTH: f32 = 24.0;
+const MIN_WIDTH: f32 = 60.0;
+const MAX_WIDTH: f32 = 600.0;
+/// Rows measured when auto-sizing, so huge folders don't stall the window
+const AUTO_SIZE_ROWS: usize = 2000;
+
+/// Menu ids of the header menu: a range of [MENU_COLUMNS] ids per kind of
+/// entry, offset by the column
+const TOGGLE_COLUMN_ID: u32 = 0x2100;
+const SORT_COLUMN_ID: u32 = 0x2200;
+const AUTO_SIZE_ID: u32 = 0x2300;
+/// Columns the header menu has entries for; further ones are left out
+const MENU_COLUMNS: usize = 0x100;
+
+/// Id of the menu entry for column `col` in the range starting at `first`.
+fn column_id(first: u32, col: usize) -> u32 {
+    first + col as u32
+}
+
+/// Which columns the Table view shows and how wide.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableColumns {
+    /// Hidden columns, by their index among all columns (Name is always shown)
+    pub hidden: Vec<usize>,
+    /// Widths of the shown columns from "Auto-size Columns", in order; empty
+    /// leaves them to the view
+    pub widths: Vec<f32>,
+}
+
+fn text(data: ModelData) -> String {
+    match data {
+        ModelData::String(text) => text,
+        _ => String::new(),
+    }
+}
+
+/// Widths fitting the header and the longest value of each column of `model`.
+fn auto_widths(model: &FileSystemItemModel) -> Vec<f32> {
+    let rows = model.row_count().min(AUTO_SIZE_ROWS);
+    (0..model.column_count())
+        .map(|col| {
+            let header = text(model.header_data(col, Orientation::Horizontal, ItemRole::Display));
+            let longest = (0..rows)
+                .map(|row| text(model.data(row, col, ItemRole::Display)).chars().count())
+                .chain([header.chars().count()])
+                .max()
+                .unwrap_or(0);
+            let icon = if col == 0 { ICON_WIDTH } else { 0.0 };
+            (longest as f32 * CHAR_WIDTH + CELL_PADDING + icon).clamp(MIN_WIDTH, MAX_WIDTH)
+        })
+        .collect()
+}
+
+/// Context menu of the column header: a toggle per column but Name, the
+/// sort columns and "Auto-size Columns". `model` has all columns; the
+/// choices go to `columns` and `sort`.
+pub(super) fn header_menu(
+    model: &FileSystemItemModel,
+    columns: &StateSignal<TableColumns>,
+    sort: &StateSignal<FileListSort>,
+) -> MenuTemplate {
+    let current = (*columns.get()).clone();
+    let headers = model.headers();
+    let mut items = Vec::new();
+
+    if headers.len() > MENU_COLUMNS {
+        log::warn!("Only the first {} of {} columns are in the header menu", MENU_COLUMNS, headers.len());
+    }
+
+    for (col, header) in headers.iter().enumerate().take(MENU_COLUMNS).skip(1) {
+        let shown = !current.hidden.contains(&col);
+        let label = if shown { format!("✓ {}", header) } else { header.clone() };
+        let columns = columns.clone();
+        items.push(MenuItem::new(MenuCommand::Custom(column_id(TOGGLE_COLUMN_ID, col)), label).with_action(move || {
+            let mut changed = (*columns.get()).clone();
+            if shown {
+                changed.hidden.push(col);
+            } else {
+                changed.hidden.retain(|c| *c != col);
+            }
+            // Widths were fitted to the columns shown before
+            changed.widths.clear();
+            columns.set(changed);
+            Update::LAYOUT | Update::DRAW
+        }));
+    }
+
+    items.push(MenuItem::separator());
+    let current_sort = *sort.get();
+    for (col, header) in headers.iter().enumerate().take(MENU_COLUMNS) {
+        let Some(column) = model.sort_column(col) else {
+            continue;
+        };
+        let label = if column == current_sort.column {
+            let arrow = match current_sort.order {
+                SortOrder::Ascending => "↑",
+                SortOrder::Descending => "↓",
+            };
+            format!("Sort by {} {}", header, arrow)
+        } else {
+            format!("Sort by {}", header)
+        };
+        let sort = sort.clone();
+        items.push(MenuItem::new(MenuCommand::Custom(column_id(SORT_COLUMN_ID, col)), label).with_action(move || {
+            let current = *sort.get();
+            let order = if current.column == column {
+                current.order.reversed()
+            } else {
+                SortOrder::Ascending
+            };
+            sort.set(FileListSort { column, order });
+            Update::LAYOUT | Update::DRAW
+        }));
+    }
+
+    items.push(MenuItem::separator());
+    let shown = model.clone().with_hidden_columns(current.hidden);
+    let columns = columns.clone();
+    items.push(MenuItem::new(MenuCommand::Custom(AUTO_SIZE_ID), "Auto-size Columns").with_action(move || {
+        let mut changed = (*columns.get()).clone();
+        changed.widths = auto_widths(&shown);
+        columns.set(changed);
+        Update::LAYOUT | Update::DRAW
+    }));
+
+    MenuTemplate::from_items("column_header_menu", items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_ids_stay_apart() {
+        let last = MENU_COLUMNS - 1;
+        assert!(column_id(TOGGLE_COLUMN_ID, last) < SORT_COLUMN_ID);
+        assert!(column_id(SORT_COLUMN_ID, last) < AUTO_SIZE_ID);
+    }
+}
@@ -0,0 +1,77 @@
+use super::FileListContent;
+use nptk::core::theme::{ColorRole, Palette};
+use crate::color_scheme;
+use nptk::core::vg::kurbo::{Affine, Circle, Point, Rect, Shape, Stroke};
+use nptk::core::vg::peniko::{Brush, Color, Fill};
+use nptk::core::vgi::Graphics;
+use std::path::Path;
+
+/// A small marker drawn over the bottom-right corner of an entry's icon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Emblem {
+    pub color: Color,
+}
+
+impl Emblem {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl FileListContent {
+    /// Whether the embedder wants `path` drawn dimmed.
+    pub(super) fn is_dimmed(&self, path: &Path) -> bool {
+        self.callbacks
+            .lock()
+            .ok()
+            .and_then(|callbacks| callbacks.dim_provider.as_ref().map(|cb| cb(path)))
+            .unwrap_or(false)
+    }
+
+    /// Fade an already drawn icon into the background.
+    pub(super) fn render_dimmed_icon(&self, graphics: &mut dyn Graphics, palette: &Palette, icon_rect: Rect) {
+        graphics.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            &Brush::Solid(color_scheme::color(palette, ColorRole::Window).with_alpha(0.55)),
+            None,
+            &icon_rect.to_path(0.1),
+        );
+    }
+
+    /// Draw the emblem the embedder provides for `path`, if any.
+    pub(super) fn render_emblem(
+        &self,
+        graphics: &mut dyn Graphics,
+        palette: &Palette,
+        icon_rect: Rect,
+        path: &Path,
+    ) {
+        let emblem = self
+            .callbacks
+            .lock()
+            .ok()
+            .and_then(|callbacks| callbacks.emblem_provider.as_ref().and_then(|cb| cb(path)));
+        let Some(emblem) = emblem else {
+            return;
+        };
+
+        let radius = (icon_rect.width() * 0.2).clamp(3.0, 8.0);
+        let circle = Circle::new(Point::new(icon_rect.x1 - radius, icon_rect.y1 - radius), radius);
+        graphics.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            &Brush::Solid(emblem.color),
+            None,
+            &circle.to_path(0.1),
+        );
+        // The outline keeps emblems too close to the background color visible
+        graphics.stroke(
+            &Stroke::new(1.0),
+            Affine::IDENTITY,
+            &Brush::Solid(color_scheme::emblem_outline(palette, emblem.color)),
+            None,
+            &circle.to_path(0.1),
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Prioritized loading of icons and thumbnails.
+//!
+//! Views request loads while rendering their rows. Requests are keyed, so a row
+//! requesting its icon every frame queues it only once, and a re-request moves it
+//! to the front. A fixed number of workers run the loads, most recently rendered
+//! rows first. Requests not renewed for a few frames belong to rows that scrolled
+//! out of view and are dropped without running.
+
+use crate::perf;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Number of loads running at once
+const LOAD_WORKERS: usize = 8;
+/// Requests not renewed for this many frames are dropped
+const STALE_FRAMES: u64 = 2;
+
+/// What is loaded for a file at a size
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(super) enum LoadKind {
+    Icon,
+    /// Generating a thumbnail (the service reports completion with an event)
+    Thumbnail,
+    /// Reading a generated thumbnail into the cache
+    ThumbnailImage,
+}
+
+type LoadKey = (LoadKind, PathBuf, u32);
+type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+struct Queued {
+    /// Frame the load was last requested in
+    frame: u64,
+    /// Request order within a frame (rows drawn first load first)
+    order: u64,
+    job: Job,
+}
+
+#[derive(Default)]
+struct State {
+    queued: HashMap<LoadKey, Queued>,
+    running: HashSet<LoadKey>,
+    frame: u64,
+    order: u64,
+}
+
+impl State {
+    /// Take the most urgent load, dropping stale ones on the way.
+    fn next(&mut self) -> Option<(LoadKey, Job)> {
+        let frame = self.frame;
+        self.queued
+            .retain(|_, queued| queued.frame + STALE_FRAMES >= frame);
+        let key = self
+            .queued
+            .iter()
+            .max_by_key(|(_, queued)| (queued.frame, std::cmp::Reverse(queued.order)))
+            .map(|(key, _)| key.clone())?;
+        let queued = self.queued.remove(&key)?;
+        self.running.insert(key.clone());
+        self.report_depth();
+        Some((key, queued.job))
+    }
+
+    fn report_depth(&self) {
+        perf::set_load_queue_depth(self.queued.len(), self.running.len());
+    }
+}
+
+/// Shared queue of icon and thumbnail loads with bounded concurrency.
+#[derive(Clone, Default)]
+pub(super) struct LoadQueue {
+    state: Arc<Mutex<State>>,
+    wake: Arc<Notify>,
+    workers_started: Arc<AtomicBool>,
+}
+
+impl LoadQueue {
+    /// Start a new frame; loads requested from now on take precedence.
+    pub(super) fn next_frame(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.frame += 1;
+            state.order = 0;
+        }
+    }
+
+    /// Queue a load unless it is already queued or running.
+    ///
+    /// `job` is only called when the load is newly queued.
+    pub(super) fn request<F, Fut>(&self, kind: LoadKind, path: &Path, size: u32, job: F)
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let key = (kind, path.to_path_buf(), size);
+        {
+            let Ok(mut state) = self.state.lock() else {
+                return;
+            };
+            if state.running.contains(&key) {
+                return;
+            }
+            let frame = state.frame;
+            state.order += 1;
+            let order = state.order;
+            if let Some(queued) = state.queued.get_mut(&key) {
+                queued.frame = frame;
+                queued.order = order;
+                return;
+            }
+            state.queued.insert(
+                key,
+                Queued {
+                    frame,
+                    order,
+                    job: Box::pin(job()),
+                },
+            );
+            state.report_depth();
+        }
+        self.start_workers();
+        self.wake.notify_one();
+    }
+
+    /// Spawn the workers on first use (needs the runtime the views render on).
+    fn start_workers(&self) {
+        if self.workers_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        for _ in 0..LOAD_WORKERS {
+            let queue = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    let next = queue.state.lock().ok().and_then(|mut state| state.next());
+                    match next {
+                        Some((key, job)) => {
+                            job.await;
+                            if let Ok(mut state) = queue.state.lock() {
+                                state.running.remove(&key);
+                                state.report_depth();
+                            }
+                        },
+                        None => queue.wake.notified().await,
+                    }
+                }
+            });
+        }
+    }
+}
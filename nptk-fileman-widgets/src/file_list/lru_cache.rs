@@ -0,0 +1,97 @@
+//! Least-recently-used cache with a memory budget.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+struct Slot<V> {
+    value: V,
+    bytes: usize,
+    last_used: u64,
+}
+
+/// Keeps values until their estimated size exceeds `max_bytes`, then drops the
+/// least recently used ones.
+pub(super) struct LruCache<K, V> {
+    slots: HashMap<K, Slot<V>>,
+    /// Keys by the tick they were last used at (oldest first)
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    bytes: usize,
+    max_bytes: usize,
+    weigh: fn(&V) -> usize,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    /// Create a cache holding up to `max_bytes` as estimated by `weigh`.
+    pub(super) fn new(max_bytes: usize, weigh: fn(&V) -> usize) -> Self {
+        Self {
+            slots: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            bytes: 0,
+            max_bytes,
+            weigh,
+        }
+    }
+
+    fn touch(&mut self, key: &K) {
+        self.tick += 1;
+        if let Some(slot) = self.slots.get_mut(key) {
+            self.order.remove(&slot.last_used);
+            slot.last_used = self.tick;
+            self.order.insert(self.tick, key.clone());
+        }
+    }
+
+    /// Get a value, marking it as recently used.
+    pub(super) fn get(&mut self, key: &K) -> Option<&V> {
+        self.touch(key);
+        self.slots.get(key).map(|slot| &slot.value)
+    }
+
+    /// Insert a value, evicting the least recently used ones if over budget.
+    pub(super) fn insert(&mut self, key: K, value: V) {
+        self.remove(&key);
+        self.tick += 1;
+        let bytes = (self.weigh)(&value);
+        self.bytes += bytes;
+        self.order.insert(self.tick, key.clone());
+        self.slots.insert(
+            key,
+            Slot {
+                value,
+                bytes,
+                last_used: self.tick,
+            },
+        );
+
+        while self.bytes > self.max_bytes && self.slots.len() > 1 {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(slot) = self.slots.remove(&oldest) {
+                self.bytes -= slot.bytes;
+            }
+        }
+    }
+
+    pub(super) fn remove(&mut self, key: &K) -> Option<V> {
+        let slot = self.slots.remove(key)?;
+        self.order.remove(&slot.last_used);
+        self.bytes -= slot.bytes;
+        Some(slot.value)
+    }
+
+    /// Keep only the entries for which `keep` returns true.
+    pub(super) fn retain(&mut self, mut keep: impl FnMut(&K, &V) -> bool) {
+        let removed: Vec<K> = self
+            .slots
+            .iter()
+            .filter(|(key, slot)| !keep(key, &slot.value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in removed {
+            self.remove(&key);
+        }
+    }
+}
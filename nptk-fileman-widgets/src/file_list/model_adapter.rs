@@ -2,17 +2,142 @@ use nptk::core::model::{ItemModel, ItemRole, ModelData, Orientation};
 use nptk::core::signal::state::StateSignal;
 use nptk::core::signal::Signal;
 use nptk::services::filesystem::entry::FileEntry;
-use humansize::{format_size, BINARY};
+use super::sorting::FileListSortColumn;
+use crate::dates;
+use crate::desktop_entries::LauncherLabels;
+use crate::mime_types;
+use crate::sizes;
+use crate::special_files;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Instant, UNIX_EPOCH};
+
+/// Headers of the built-in columns, in order, and what sorting by each sorts by
+const BUILTIN: [(&str, FileListSortColumn); 4] = [
+    ("Name", FileListSortColumn::Name),
+    ("Size", FileListSortColumn::Size),
+    ("Type", FileListSortColumn::Type),
+    ("Date Modified", FileListSortColumn::Modified),
+];
+
+/// Number of built-in columns
+const BUILTIN_COLUMNS: usize = BUILTIN.len();
+
+/// An extra column registered by the embedder.
+///
+/// `value` produces the displayed data for an entry; `sort_key` (if set) produces the
+/// data used for sorting, otherwise the displayed data is used.
+#[derive(Clone)]
+pub struct ColumnProvider {
+    header: String,
+    value: Arc<dyn Fn(&FileEntry) -> ModelData + Send + Sync>,
+    sort_key: Option<Arc<dyn Fn(&FileEntry) -> ModelData + Send + Sync>>,
+}
+
+impl ColumnProvider {
+    /// Create a column with the given header label and value function.
+    pub fn new<F>(header: impl Into<String>, value: F) -> Self
+    where
+        F: Fn(&FileEntry) -> ModelData + Send + Sync + 'static,
+    {
+        Self {
+            header: header.into(),
+            value: Arc::new(value),
+            sort_key: None,
+        }
+    }
+
+    /// Set a dedicated sort key for this column.
+    pub fn with_sort_key<F>(mut self, sort_key: F) -> Self
+    where
+        F: Fn(&FileEntry) -> ModelData + Send + Sync + 'static,
+    {
+        self.sort_key = Some(Arc::new(sort_key));
+        self
+    }
+
+    /// Get the header label.
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+}
+
+/// Recursive size of a folder, `None` while it is still being computed.
+pub type DirectorySizes = Arc<dyn Fn(&Path) -> Option<u64> + Send + Sync>;
+
+/// Frames of the spinner shown for folder sizes being computed
+const SPINNER: [&str; 8] = ["⠋", "⠙", "⠸", "⠴", "⠦", "⠇", "⠏", "⠹"];
+
+fn spinner() -> &'static str {
+    static START: std::sync::LazyLock<Instant> = std::sync::LazyLock::new(Instant::now);
+    SPINNER[(START.elapsed().as_millis() / 100) as usize % SPINNER.len()]
+}
 
 /// Adapter to expose a StateSignal<Vec<FileEntry>> as an ItemModel
 #[derive(Clone)]
 pub struct FileSystemItemModel {
     entries: StateSignal<Vec<FileEntry>>,
+    launchers: StateSignal<LauncherLabels>,
+    custom_columns: Arc<Vec<ColumnProvider>>,
+    directory_sizes: Option<DirectorySizes>,
+    // Columns left out, by their index among all columns
+    hidden_columns: Arc<Vec<usize>>,
 }
 
 impl FileSystemItemModel {
-    pub fn new(entries: StateSignal<Vec<FileEntry>>) -> Self {
-        Self { entries }
+    pub fn new(entries: StateSignal<Vec<FileEntry>>, launchers: StateSignal<LauncherLabels>) -> Self {
+        Self {
+            entries,
+            launchers,
+            custom_columns: Arc::new(Vec::new()),
+            directory_sizes: None,
+            hidden_columns: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Show folder sizes from `sizes` in the Size column instead of "Directory".
+    pub fn with_directory_sizes(mut self, sizes: Option<DirectorySizes>) -> Self {
+        self.directory_sizes = sizes;
+        self
+    }
+
+    /// Append custom columns after the built-in ones.
+    pub fn with_columns(mut self, columns: Vec<ColumnProvider>) -> Self {
+        self.custom_columns = Arc::new(columns);
+        self
+    }
+
+    /// Leave out the columns `hidden`, by their index among all columns. The
+    /// Name column is always shown.
+    pub fn with_hidden_columns(mut self, hidden: Vec<usize>) -> Self {
+        self.hidden_columns = Arc::new(hidden.into_iter().filter(|col| *col != 0).collect());
+        self
+    }
+
+    /// Header labels of all columns, hidden ones included.
+    pub fn headers(&self) -> Vec<String> {
+        BUILTIN
+            .iter()
+            .map(|(header, _)| header.to_string())
+            .chain(self.custom_columns.iter().map(|c| c.header.clone()))
+            .collect()
+    }
+
+    /// How the list is sorted by the column `col`, by its index among all
+    /// columns. Custom columns only sort within the Table view.
+    pub fn sort_column(&self, col: usize) -> Option<FileListSortColumn> {
+        BUILTIN.get(col).map(|(_, column)| *column)
+    }
+
+    /// Index among all columns of the shown column `col`.
+    fn source_column(&self, col: usize) -> Option<usize> {
+        (0..BUILTIN_COLUMNS + self.custom_columns.len())
+            .filter(|c| !self.hidden_columns.contains(c))
+            .nth(col)
+    }
+
+    fn custom_column(&self, col: usize) -> Option<&ColumnProvider> {
+        col.checked_sub(BUILTIN_COLUMNS).and_then(|i| self.custom_columns.get(i))
     }
 }
 
@@ -22,28 +147,51 @@ impl ItemModel for FileSystemItemModel {
     }
 
     fn column_count(&self) -> usize {
-        4 // Name, Size, Type, Date (Modified)
+        let all = BUILTIN_COLUMNS + self.custom_columns.len(); // Name, Size, Type, Date (Modified), custom...
+        all - self.hidden_columns.iter().filter(|col| **col < all).count()
     }
 
     fn data(&self, row: usize, col: usize, role: ItemRole) -> ModelData {
+        let Some(col) = self.source_column(col) else {
+            return ModelData::None;
+        };
         let entries = self.entries.get();
         if row >= entries.len() {
             return ModelData::None;
         }
         let entry = &entries[row];
 
+        if let Some(column) = self.custom_column(col) {
+            return match role {
+                ItemRole::Display => (column.value)(entry),
+                ItemRole::Sort => match &column.sort_key {
+                    Some(sort_key) => sort_key(entry),
+                    None => (column.value)(entry),
+                },
+                _ => ModelData::None,
+            };
+        }
+
         match role {
             ItemRole::Display => match col {
-                0 => ModelData::String(entry.name.clone()),
+                0 => ModelData::String(self.launchers.get().display_name(entry).to_string()),
                 1 => {
                      if entry.is_dir() {
-                        ModelData::String("Directory".to_string())
+                        ModelData::String(match &self.directory_sizes {
+                            Some(directory_sizes) => directory_sizes(&entry.path)
+                                .map(sizes::format)
+                                .unwrap_or_else(|| spinner().to_string()),
+                            None => "Directory".to_string(),
+                        })
+                     } else if special_files::entry_kind(entry).is_some() {
+                        // Pipes, sockets and devices have no size of their own
+                        ModelData::String(String::new())
                      } else {
-                        ModelData::String(format_size(entry.metadata.size, BINARY))
+                        ModelData::String(sizes::format(entry.metadata.size))
                      }
                 },
-                2 => ModelData::String(format!("{:?}", entry.file_type)), // Simplify for now
-                3 => ModelData::String("Unknown".to_string()), // Date not in FileEntry yet?
+                2 => ModelData::String(mime_types::describe(entry)),
+                3 => ModelData::String(dates::format(entry.metadata.modified)),
                 _ => ModelData::None,
             },
             ItemRole::Icon => {
@@ -60,7 +208,21 @@ impl ItemModel for FileSystemItemModel {
                 // For sorting
                 match col {
                     0 => ModelData::String(entry.name.clone()),
-                    1 => ModelData::Int(entry.metadata.size as i64),
+                    1 => match &self.directory_sizes {
+                        Some(directory_sizes) if entry.is_dir() => {
+                            ModelData::Int(directory_sizes(&entry.path).unwrap_or(0) as i64)
+                        },
+                        _ => ModelData::Int(entry.metadata.size as i64),
+                    },
+                    2 => ModelData::String(mime_types::describe(entry).to_lowercase()),
+                    3 => ModelData::Int(
+                        entry
+                            .metadata
+                            .modified
+                            .duration_since(UNIX_EPOCH)
+                            .map(|d| d.as_secs() as i64)
+                            .unwrap_or(0),
+                    ),
                     _ => ModelData::None,
                 }
             }
@@ -70,13 +232,10 @@ impl ItemModel for FileSystemItemModel {
 
     fn header_data(&self, section: usize, orientation: Orientation, role: ItemRole) -> ModelData {
         if orientation == Orientation::Horizontal && role == ItemRole::Display {
-            match section {
-                0 => ModelData::String("Name".to_string()),
-                1 => ModelData::String("Size".to_string()),
-                2 => ModelData::String("Type".to_string()),
-                3 => ModelData::String("Date Modified".to_string()),
-                _ => ModelData::None,
-            }
+            self.source_column(section)
+                .and_then(|col| self.headers().into_iter().nth(col))
+                .map(ModelData::String)
+                .unwrap_or(ModelData::None)
         } else {
             ModelData::None
         }
@@ -0,0 +1,253 @@
+//! Permissions tab of Properties
+//!
+//! Shows the read, write and execute bits of the selected items for owner, group
+//! and others. A bit that only some of the items have is shown as mixed and left
+//! alone on Apply unless it is switched on or off.
+
+use super::FileListOperation;
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Classes of users with the shift of their bits in the mode
+const CLASSES: [(&str, u32); 3] = [("Owner", 6), ("Group", 3), ("Others", 0)];
+/// Permissions of a class with their bit
+const PERMISSIONS: [(&str, u32); 3] = [("Read", 0o4), ("Write", 0o2), ("Execute", 0o1)];
+
+/// A permission bit across the selected items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BitState {
+    On,
+    Off,
+    /// Set on some of the items only
+    Mixed,
+}
+
+/// Which button was pressed, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PermissionsRequest {
+    /// Cycle the bit with this mode value
+    Toggle(u32),
+    Apply,
+}
+
+pub(super) struct PermissionsTab {
+    paths: Vec<PathBuf>,
+    // (bit, state read from the items, state as edited)
+    bits: Vec<(u32, BitState, BitState)>,
+    inner: Container,
+    message_text: StateSignal<String>,
+    request: Arc<Mutex<Option<PermissionsRequest>>>,
+    operation_tx: Option<UnboundedSender<FileListOperation>>,
+    signals_hooked: bool,
+}
+
+impl PermissionsTab {
+    pub(super) fn new(
+        paths: Vec<PathBuf>,
+        operation_tx: Option<UnboundedSender<FileListOperation>>,
+    ) -> Self {
+        // Symlinks have no permissions of their own
+        let modes: Vec<u32> = paths
+            .iter()
+            .filter_map(|p| std::fs::symlink_metadata(p).ok())
+            .filter(|m| !m.file_type().is_symlink())
+            .map(|m| m.permissions().mode())
+            .collect();
+        let bits = CLASSES
+            .iter()
+            .flat_map(|(_, shift)| PERMISSIONS.iter().map(move |(_, bit)| bit << shift))
+            .map(|bit| {
+                let set = modes.iter().filter(|mode| *mode & bit != 0).count();
+                let state = if set == 0 {
+                    BitState::Off
+                } else if set == modes.len() {
+                    BitState::On
+                } else {
+                    BitState::Mixed
+                };
+                (bit, state, state)
+            })
+            .collect();
+        let message = if modes.is_empty() {
+            "Links have no permissions of their own".to_string()
+        } else {
+            String::new()
+        };
+
+        let mut tab = Self {
+            paths,
+            bits,
+            inner: Container::new(vec![]),
+            message_text: StateSignal::new(message),
+            request: Arc::new(Mutex::new(None)),
+            operation_tx,
+            signals_hooked: false,
+        };
+        tab.rebuild();
+        tab
+    }
+
+    fn button(&self, label: &str, request: PermissionsRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn state(&self, bit: u32) -> BitState {
+        self.bits
+            .iter()
+            .find(|(b, _, _)| *b == bit)
+            .map(|(_, _, state)| *state)
+            .unwrap_or(BitState::Off)
+    }
+
+    /// On -> Off -> (Mixed, if it was) -> On
+    fn toggle(&mut self, bit: u32) {
+        if let Some((_, initial, state)) = self.bits.iter_mut().find(|(b, _, _)| *b == bit) {
+            *state = match (*state, *initial) {
+                (BitState::On, _) => BitState::Off,
+                (BitState::Off, BitState::Mixed) => BitState::Mixed,
+                (BitState::Off, _) | (BitState::Mixed, _) => BitState::On,
+            };
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let mut children: Vec<Box<dyn Widget>> = Vec::new();
+        for (class, shift) in CLASSES {
+            let mut row: Vec<Box<dyn Widget>> =
+                vec![Box::new(Text::new(format!("{}:", class)).with_layout_style(LayoutStyle {
+                    size: Vector2::new(Dimension::length(70.0), Dimension::auto()),
+                    ..Default::default()
+                }))];
+            for (permission, bit) in PERMISSIONS {
+                let bit = bit << shift;
+                let mark = match self.state(bit) {
+                    BitState::On => "[x]",
+                    BitState::Off => "[ ]",
+                    BitState::Mixed => "[-]",
+                };
+                let label = format!("{} {}", mark, permission);
+                row.push(Box::new(self.button(&label, PermissionsRequest::Toggle(bit))));
+            }
+            children.push(Box::new(Container::new(row).with_layout_style(LayoutStyle {
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                flex_direction: FlexDirection::Row,
+                align_items: Some(AlignItems::Center),
+                gap: Vector2::new(LengthPercentage::length(6.0), LengthPercentage::length(0.0)),
+                ..Default::default()
+            })));
+        }
+        children.push(Box::new(Text::new(self.message_text.maybe()).with_font_size(12.0)));
+        children.push(Box::new(
+            Container::new(vec![Box::new(self.button("Apply", PermissionsRequest::Apply))])
+                .with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Row,
+                    justify_content: Some(JustifyContent::FlexEnd),
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    ..Default::default()
+                }),
+        ));
+
+        self.inner = Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(12.0),
+                right: LengthPercentage::length(12.0),
+                top: LengthPercentage::length(12.0),
+                bottom: LengthPercentage::length(12.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(8.0)),
+            ..Default::default()
+        });
+    }
+
+    /// Send the bits switched on or off to the window; mixed bits are kept per item.
+    fn apply(&mut self) {
+        let (mut set, mut clear) = (0, 0);
+        for (bit, _, state) in &self.bits {
+            match state {
+                BitState::On => set |= bit,
+                BitState::Off => clear |= bit,
+                BitState::Mixed => {},
+            }
+        }
+        let Some(tx) = &self.operation_tx else {
+            return;
+        };
+        let operation = FileListOperation::SetPermissions {
+            paths: self.paths.clone(),
+            set,
+            clear,
+        };
+        if let Err(e) = tx.send(operation) {
+            log::error!("Failed to send permission change: {}", e);
+            return;
+        }
+        for (_, initial, state) in &mut self.bits {
+            if *state != BitState::Mixed {
+                *initial = *state;
+            }
+        }
+        self.message_text.set("Permissions changed".to_string());
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for PermissionsTab {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.message_text);
+            self.signals_hooked = true;
+        }
+
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        match request {
+            Some(PermissionsRequest::Toggle(bit)) => {
+                self.toggle(bit);
+                self.message_text.set(String::new());
+                self.rebuild();
+                update.insert(Update::LAYOUT | Update::DRAW);
+            },
+            Some(PermissionsRequest::Apply) => {
+                self.apply();
+                update.insert(Update::DRAW);
+            },
+            None => {},
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,130 @@
+//! Background listing of folders the user is likely to open next.
+//!
+//! Hovering or selecting a folder lists it on a blocking worker. When the folder
+//! is opened shortly after, the prefetched listing is shown at once while the
+//! filesystem model loads the authoritative one.
+
+use nptk::services::filesystem::entry::{FileEntry, FileMetadata, FileType};
+use std::collections::{HashSet, VecDeque};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Number of prefetched listings kept (least recently used are dropped)
+const PREFETCH_CAPACITY: usize = 8;
+/// Prefetched listings older than this are not shown
+const PREFETCH_MAX_AGE: Duration = Duration::from_secs(30);
+
+struct Prefetched {
+    path: PathBuf,
+    entries: Vec<FileEntry>,
+    listed_at: Instant,
+}
+
+#[derive(Clone, Default)]
+pub(super) struct Prefetcher {
+    /// Most recently used last
+    listings: Arc<Mutex<VecDeque<Prefetched>>>,
+    pending: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+/// Entry for `path` in `dir`, from the type of the path itself (links aren't
+/// followed) and the metadata of what it points to.
+fn make_entry(path: PathBuf, dir: &Path, file_type: std::fs::FileType, metadata: std::fs::Metadata) -> Option<FileEntry> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let file_type = if file_type.is_symlink() {
+        FileType::Symlink
+    } else if file_type.is_dir() {
+        FileType::Directory
+    } else if file_type.is_file() {
+        FileType::File
+    } else {
+        FileType::Other
+    };
+    let file_metadata = FileMetadata {
+        size: metadata.len(),
+        modified: metadata.modified().ok()?,
+        created: metadata.created().ok(),
+        permissions: metadata.permissions().mode(),
+        mime_type: None,
+        is_hidden: name.starts_with('.'),
+    };
+    Some(FileEntry::new(
+        path,
+        name,
+        file_type,
+        file_metadata,
+        Some(dir.to_path_buf()),
+    ))
+}
+
+fn list(dir: &Path) -> Vec<FileEntry> {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let file_type = entry.file_type().ok()?;
+            let metadata = std::fs::metadata(entry.path())
+                .or_else(|_| entry.metadata())
+                .ok()?;
+            make_entry(entry.path(), dir, file_type, metadata)
+        })
+        .collect()
+}
+
+/// Entry for the single `path` in `dir`, `None` if it no longer exists.
+pub(super) fn read_entry(path: &Path, dir: &Path) -> Option<FileEntry> {
+    let link_metadata = std::fs::symlink_metadata(path).ok()?;
+    let metadata = std::fs::metadata(path).unwrap_or_else(|_| link_metadata.clone());
+    make_entry(path.to_path_buf(), dir, link_metadata.file_type(), metadata)
+}
+
+impl Prefetcher {
+    /// List `dir` in the background unless a recent listing is already kept.
+    pub(super) fn request(&self, dir: &Path) {
+        let fresh = self.listings.lock().is_ok_and(|listings| {
+            listings
+                .iter()
+                .any(|p| p.path == dir && p.listed_at.elapsed() < PREFETCH_MAX_AGE)
+        });
+        if fresh
+            || !self
+                .pending
+                .lock()
+                .is_ok_and(|mut pending| pending.insert(dir.to_path_buf()))
+        {
+            return;
+        }
+
+        let prefetcher = self.clone();
+        let dir = dir.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let entries = list(&dir);
+            if let Ok(mut listings) = prefetcher.listings.lock() {
+                listings.retain(|p| p.path != dir);
+                if listings.len() >= PREFETCH_CAPACITY {
+                    listings.pop_front();
+                }
+                listings.push_back(Prefetched {
+                    path: dir.clone(),
+                    entries,
+                    listed_at: Instant::now(),
+                });
+            }
+            if let Ok(mut pending) = prefetcher.pending.lock() {
+                pending.remove(&dir);
+            }
+        });
+    }
+
+    /// Take the prefetched listing of `dir`, if a recent one is kept.
+    pub(super) fn take(&self, dir: &Path) -> Option<Vec<FileEntry>> {
+        let mut listings = self.listings.lock().ok()?;
+        let index = listings.iter().position(|p| p.path == dir)?;
+        let prefetched = listings.remove(index)?;
+        (prefetched.listed_at.elapsed() < PREFETCH_MAX_AGE).then_some(prefetched.entries)
+    }
+}
@@ -1,10 +1,12 @@
 use async_trait::async_trait;
-use super::FileListContent;
+use super::advanced_tab::advanced_tab;
+use super::permissions_tab::PermissionsTab;
+use super::usage_tab::usage_tab;
+use super::{FileListContent, FileListOperation, IconCache};
 use nptk::prelude::{LayoutContext, LengthPercentage};
 use nptk::widgets::file_icon::renderer::{render_image_icon, render_svg_icon_with_arc_cache};
 use nptk::widgets::tabs_container::{TabItem, TabsContainer};
 use chrono::{DateTime, Local};
-use humansize::{format_size, BINARY};
 use nalgebra::Vector2;
 use nptk::core::app::context::AppContext;
 use nptk::core::app::info::AppInfo;
@@ -22,24 +24,34 @@ use npio::service::icon::IconRegistry;
 use nptk::services::thumbnail::npio_adapter::{file_entry_to_uri, u32_to_thumbnail_size};
 use npio::{ThumbnailService, get_file_for_uri};
 use nptk::core::theme::{ColorRole, Palette};
+use crate::color_scheme;
+use crate::mime_types;
+use crate::sizes;
+use crate::special_files::{self, SpecialKind};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Rows filled in once they are computed in the background, by label
+type LateRows = Arc<Mutex<Vec<(String, String)>>>;
 
 impl FileListContent {
     pub(super) fn build_properties_widget(
         data: PropertiesData,
         icon_registry: Arc<IconRegistry>,
         thumbnail_service: Arc<ThumbnailService>,
-        icon_cache: Arc<
-            Mutex<
-                std::collections::HashMap<(PathBuf, u32), Option<npio::service::icon::CachedIcon>>,
-            >,
-        >,
+        icon_cache: IconCache,
         svg_scene_cache: Arc<
             Mutex<std::collections::HashMap<String, (nptk::core::vg::Scene, f64, f64)>>,
         >,
+        operation_tx: Option<UnboundedSender<FileListOperation>>,
     ) -> BoxedWidget {
+        let permissions = PermissionsTab::new(data.paths.clone(), operation_tx);
+        let (advanced, usage) = match data.paths.as_slice() {
+            [path] => (Some(advanced_tab(path)), usage_tab(path)),
+            _ => (None, None),
+        };
         let content = PropertiesContent::new(
             data,
             icon_registry,
@@ -48,12 +60,20 @@ impl FileListContent {
             svg_scene_cache,
         );
         let tab = TabItem::new("general", "General", content);
-        let tabs = TabsContainer::new()
+        let permissions_tab = TabItem::new("permissions", "Permissions", permissions);
+        let mut tabs = TabsContainer::new()
             .with_layout_style(LayoutStyle {
                 size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
                 ..Default::default()
             })
-            .with_tab(tab);
+            .with_tab(tab)
+            .with_tab(permissions_tab);
+        if let Some(usage) = usage {
+            tabs = tabs.with_tab(TabItem::new("usage", "Usage", usage));
+        }
+        if let Some(advanced) = advanced {
+            tabs = tabs.with_tab(TabItem::new("advanced", "Advanced", advanced));
+        }
         Box::new(tabs)
     }
 
@@ -63,6 +83,7 @@ impl FileListContent {
         }
 
         let mut rows: Vec<(String, String)> = Vec::new();
+        let late_rows: LateRows = Arc::new(Mutex::new(Vec::new()));
 
         let (title, icon_label) = if paths.len() == 1 {
             let path = &paths[0];
@@ -76,14 +97,21 @@ impl FileListContent {
                 .map(|s| s.to_uppercase())
                 .unwrap_or_else(|| "FILE".to_string());
 
-            let mime_type = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(MimeDetector::detect_mime_type(path))
-            })
-                .or_else(|| Self::xdg_mime_filetype(path))
-                .unwrap_or_else(|| "unknown".to_string());
+            let special = SpecialKind::of_path(path);
+            // Special files aren't read to guess their type; reading a pipe blocks
+            let mime_type = match special {
+                Some(kind) => kind.mime_type().to_string(),
+                None => tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(MimeDetector::detect_mime_type(path))
+                })
+                    .or_else(|| Self::xdg_mime_filetype(path))
+                    .unwrap_or_else(|| "unknown".to_string()),
+            };
 
             let kind_display = if let Some(description) = self.lookup_mime_description(&mime_type) {
                 format!("{} ({})", description, mime_type)
+            } else if let Some(kind) = special {
+                format!("{} ({})", kind.label(), mime_type)
             } else {
                 mime_type.clone()
             };
@@ -96,10 +124,18 @@ impl FileListContent {
                 } else {
                     meta.len()
                 };
-                rows.push((
-                    "Size".to_string(),
-                    format_size(size, BINARY) + " (" + size.to_string().as_str() + " bytes)",
-                ));
+                if special.is_none() {
+                    rows.push((
+                        "Size".to_string(),
+                        sizes::format(size) + " (" + size.to_string().as_str() + " bytes)",
+                    ));
+                }
+                if special_files::is_sparse(&meta) {
+                    rows.push((
+                        "On disk".to_string(),
+                        format!("{} (sparse)", sizes::format(special_files::on_disk_size(&meta))),
+                    ));
+                }
                 if let Ok(modified) = meta.modified() {
                     rows.push(("Modified".to_string(), Self::format_system_time(modified)));
                 }
@@ -108,6 +144,18 @@ impl FileListContent {
                 }
             }
 
+            if let Ok(target) = fs::read_link(path) {
+                let broken = fs::metadata(path).is_err();
+                rows.push((
+                    "Link target".to_string(),
+                    if broken {
+                        format!("{} (broken)", target.display())
+                    } else {
+                        target.display().to_string()
+                    },
+                ));
+            }
+
             rows.push((
                 "Location".to_string(),
                 path.parent()
@@ -118,19 +166,15 @@ impl FileListContent {
             (name.to_string(), icon_label)
         } else {
             let count = paths.len();
-            let mut total_size: u64 = 0;
-            for p in paths {
-                if let Ok(meta) = fs::metadata(p) {
-                    let size = if meta.is_dir() {
-                        Self::calculate_directory_size(p)
-                    } else {
-                        meta.len()
-                    };
-                    total_size = total_size.saturating_add(size);
-                }
-            }
+            let folders = paths.iter().filter(|p| p.is_dir()).count();
             rows.push(("Items".to_string(), count.to_string()));
-            rows.push(("Total size".to_string(), format_size(total_size, BINARY)));
+            rows.push((
+                "Contents".to_string(),
+                format!("{} file(s), {} folder(s)", count - folders, folders),
+            ));
+            rows.push(("Type".to_string(), "...".to_string()));
+            rows.push(("Total size".to_string(), "Calculating...".to_string()));
+            Self::summarize_selection(paths.to_vec(), late_rows.clone());
             (format!("{} items", count), "MULTI".to_string())
         };
 
@@ -140,6 +184,7 @@ impl FileListContent {
             title,
             icon_label,
             rows,
+            late_rows,
             paths: paths.to_vec(),
         };
         let svg_scene_cache = Arc::new(Mutex::new(std::collections::HashMap::new()));
@@ -149,6 +194,7 @@ impl FileListContent {
             self.thumbnail_service.clone(),
             self.icon_cache.clone(),
             svg_scene_cache,
+            self.operation_tx.clone(),
         );
         let pos = self
             .last_cursor
@@ -156,206 +202,84 @@ impl FileListContent {
             .unwrap_or((100, 100));
         context
             .popup_manager
-            .create_popup_at(props_widget, "Properties", (360, 260), pos);
+            .create_popup_at(props_widget, "Properties", (400, 300), pos);
     }
 
-    fn format_system_time(time: std::time::SystemTime) -> String {
-        let dt: DateTime<Local> = time.into();
-        dt.format("%Y-%m-%d %H:%M:%S").to_string()
-    }
-
-    fn lookup_mime_description(&self, mime_type: &str) -> Option<String> {
-        for variant in Self::mime_description_variants(mime_type) {
-            if let Some(desc) = self.mime_registry.description(&variant) {
-                return Some(desc);
-            }
-        }
-        Self::get_mime_description(mime_type)
-    }
-
-    fn get_mime_description(mime_type: &str) -> Option<String> {
-        for variant in Self::mime_description_variants(mime_type) {
-            if let Some(desc) = Self::get_mime_description_single(&variant) {
-                return Some(desc);
+    /// Find the common type and the total size of several items in the background
+    /// (folder sizes can take long) and fill in their rows.
+    fn summarize_selection(paths: Vec<PathBuf>, late_rows: LateRows) {
+        let handle = tokio::runtime::Handle::current();
+        tokio::task::spawn_blocking(move || {
+            let mime_types: Vec<String> = paths
+                .iter()
+                .map(|path| {
+                    let mime = if path.is_dir() {
+                        Some("inode/directory".to_string())
+                    } else if let Some(kind) = SpecialKind::of_path(path) {
+                        Some(kind.mime_type().to_string())
+                    } else {
+                        handle.block_on(MimeDetector::detect_mime_type(path))
+                    };
+                    mime.unwrap_or_else(|| "unknown".to_string())
+                })
+                .collect();
+            let kind = Self::common_kind(&mime_types);
+            if let Ok(mut rows) = late_rows.lock() {
+                rows.push(("Type".to_string(), kind));
             }
-        }
-        None
-    }
 
-    fn get_mime_description_single(mime_type: &str) -> Option<String> {
-        if let Some((major, minor)) = mime_type.split_once('/') {
-            let path = Path::new("/usr/share/mime")
-                .join(major)
-                .join(format!("{minor}.xml"));
-            if let Ok(content) = fs::read_to_string(&path) {
-                if content.contains(&format!(r#"type="{}""#, mime_type)) {
-                    if let Some(comment) = Self::extract_comment(&content) {
-                        return Some(comment);
+            let total_size = paths
+                .iter()
+                .filter_map(|p| fs::metadata(p).ok().map(|meta| (p, meta)))
+                .map(|(p, meta)| {
+                    if meta.is_dir() {
+                        Self::calculate_directory_size(p)
+                    } else {
+                        meta.len()
                     }
-                }
+                })
+                .fold(0u64, u64::saturating_add);
+            if let Ok(mut rows) = late_rows.lock() {
+                rows.push(("Total size".to_string(), sizes::format(total_size)));
             }
-        }
+        });
+    }
 
-        let packages_dir = Path::new("/usr/share/mime/packages");
-        let entries = match fs::read_dir(packages_dir) {
-            Ok(entries) => entries,
-            Err(_) => return None,
+    /// "All <type>" if the items share a MIME type, "Various <kind> types" if
+    /// they share its major part, otherwise "Mixed types".
+    fn common_kind(mime_types: &[String]) -> String {
+        let Some(first) = mime_types.first() else {
+            return String::new();
         };
-
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) != Some("xml") {
-                continue;
-            }
-            let content = match fs::read_to_string(&path) {
-                Ok(c) => c,
-                Err(_) => continue,
-            };
-
-            let mut search_start = 0;
-            while let Some(idx) = content[search_start..].find("<mime-type") {
-                let mime_start = search_start + idx;
-                let tag_end = match content[mime_start..].find('>') {
-                    Some(i) => mime_start + i + 1,
-                    None => break,
-                };
-                let tag_text = &content[mime_start..tag_end];
-
-                let type_attr = r#"type=""#;
-                let type_idx = match tag_text.find(type_attr) {
-                    Some(i) => i + type_attr.len(),
-                    None => {
-                        search_start = tag_end;
-                        continue;
-                    },
-                };
-                let rest = &tag_text[type_idx..];
-                let end_quote = match rest.find('"') {
-                    Some(i) => i,
-                    None => {
-                        search_start = tag_end;
-                        continue;
-                    },
-                };
-                let ty = &rest[..end_quote];
-                if ty != mime_type {
-                    search_start = tag_end;
-                    continue;
-                }
-
-                let end_tag = "</mime-type>";
-                let block_end = match content[tag_end..].find(end_tag) {
-                    Some(i) => tag_end + i + end_tag.len(),
-                    None => {
-                        search_start = tag_end;
-                        continue;
-                    },
-                };
-                let mime_block = &content[mime_start..block_end];
-                if let Some(comment) = Self::extract_comment(mime_block) {
-                    return Some(comment);
-                }
-                search_start = block_end;
-            }
+        if mime_types.iter().all(|m| m == first) {
+            let description = mime_types::description(first).unwrap_or_else(|| first.clone());
+            return format!("All {}", description);
+        }
+        let major = |mime: &str| mime.split('/').next().unwrap_or_default().to_string();
+        let first_major = major(first);
+        if mime_types.iter().all(|m| major(m) == first_major) {
+            format!("Various {} types", first_major)
+        } else {
+            "Mixed types".to_string()
         }
-
-        None
     }
 
-    fn extract_comment(mime_block: &str) -> Option<String> {
-        let mut best: Option<String> = None;
-        let mut fallback: Option<String> = None;
-        let mut search_start = 0;
-        while let Some(idx) = mime_block[search_start..].find("<comment") {
-            let comment_start = search_start + idx;
-            let tag_end = match mime_block[comment_start..].find('>') {
-                Some(i) => comment_start + i + 1,
-                None => break,
-            };
-            let end_tag = match mime_block[tag_end..].find("</comment>") {
-                Some(i) => tag_end + i,
-                None => break,
-            };
-            let tag_text = &mime_block[comment_start..tag_end];
-            let body = mime_block[tag_end..end_tag].trim();
-            if body.is_empty() {
-                search_start = end_tag + "</comment>".len();
-                continue;
-            }
-            let is_en = tag_text.contains(r#"xml:lang="en""#);
-            if is_en {
-                best = Some(body.to_string());
-                break;
-            } else if fallback.is_none() {
-                fallback = Some(body.to_string());
-            }
-            search_start = end_tag + "</comment>".len();
-        }
-        best.or(fallback)
+    fn format_system_time(time: std::time::SystemTime) -> String {
+        let dt: DateTime<Local> = time.into();
+        dt.format("%Y-%m-%d %H:%M:%S").to_string()
     }
 
-    fn mime_description_variants(mime_type: &str) -> Vec<String> {
-        let mut variants = Vec::new();
-        variants.push(mime_type.to_string());
-        if let Some((major, rest)) = mime_type.split_once('/') {
-            if let Some(stripped) = rest.strip_prefix("x-") {
-                variants.push(format!("{}/{}", major, stripped));
-            }
-        }
-        match mime_type {
-            "application/toml" => variants.push("text/x-toml".to_string()),
-            "text/x-rust" => variants.push("text/rust".to_string()),
-            "application/x-shellscript" => {
-                variants.push("text/x-shellscript".to_string());
-                variants.push("text/x-sh".to_string());
-            },
-            "application/zstd" => variants.push("application/x-zstd".to_string()),
-            "application/x-rar" => variants.push("application/vnd.rar".to_string()),
-            "application/x-iso9660-image" => {
-                variants.push("application/x-iso9660-image".to_string())
-            },
-            "text/x-log" => variants.push("text/plain".to_string()),
-            _ => {},
-        }
-        variants
+    /// Description from the default applications' registry, otherwise from
+    /// the MIME database
+    fn lookup_mime_description(&self, mime_type: &str) -> Option<String> {
+        mime_types::variants(mime_type)
+            .iter()
+            .find_map(|variant| self.mime_registry.description(variant))
+            .or_else(|| mime_types::description(mime_type))
     }
 
     fn calculate_directory_size(path: &Path) -> u64 {
-        let metadata = match fs::metadata(path) {
-            Ok(m) => m,
-            Err(_) => return 0,
-        };
-
-        if !metadata.is_dir() {
-            return metadata.len();
-        }
-
-        let mut total_size = 0u64;
-        let entries = match fs::read_dir(path) {
-            Ok(entries) => entries,
-            Err(_) => return metadata.len(),
-        };
-
-        for entry in entries {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => continue,
-            };
-
-            let entry_path = entry.path();
-            let entry_metadata = match entry.metadata() {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
-
-            if entry_metadata.is_dir() {
-                total_size = total_size.saturating_add(Self::calculate_directory_size(&entry_path));
-            } else {
-                total_size = total_size.saturating_add(entry_metadata.len());
-            }
-        }
-
-        total_size
+        crate::dir_size_cache::directory_size(path)
     }
 }
 
@@ -363,6 +287,7 @@ pub(super) struct PropertiesData {
     title: String,
     icon_label: String,
     rows: Vec<(String, String)>,
+    late_rows: LateRows,
     paths: Vec<PathBuf>,
 }
 
@@ -371,11 +296,7 @@ struct PropertiesContent {
     text_ctx: TextRenderContext,
     icon_registry: Arc<IconRegistry>,
     thumbnail_service: Arc<ThumbnailService>,
-    _icon_cache: Arc<
-        Mutex<
-            std::collections::HashMap<(PathBuf, u32), Option<npio::service::icon::CachedIcon>>,
-        >,
-    >,
+    _icon_cache: IconCache,
     svg_scene_cache: Arc<Mutex<std::collections::HashMap<String, (nptk::core::vg::Scene, f64, f64)>>>,
     thumbnail_size: u32,
 }
@@ -385,11 +306,7 @@ impl PropertiesContent {
         data: PropertiesData,
         icon_registry: Arc<IconRegistry>,
         thumbnail_service: Arc<ThumbnailService>,
-        icon_cache: Arc<
-            Mutex<
-                std::collections::HashMap<(PathBuf, u32), Option<npio::service::icon::CachedIcon>>,
-            >,
-        >,
+        icon_cache: IconCache,
         svg_scene_cache: Arc<
             Mutex<std::collections::HashMap<String, (nptk::core::vg::Scene, f64, f64)>>,
         >,
@@ -420,7 +337,19 @@ impl Widget for PropertiesContent {
     }
 
     async fn update(&mut self, _: &LayoutNode, _: AppContext, _: &mut AppInfo) -> Update {
-        Update::empty()
+        let late_rows: Vec<(String, String)> = match self.data.late_rows.lock() {
+            Ok(mut rows) => rows.drain(..).collect(),
+            Err(_) => return Update::empty(),
+        };
+        if late_rows.is_empty() {
+            return Update::empty();
+        }
+        for (label, value) in late_rows {
+            if let Some(row) = self.data.rows.iter_mut().find(|(l, _)| *l == label) {
+                row.1 = value;
+            }
+        }
+        Update::DRAW
     }
 
     fn render(
@@ -431,7 +360,7 @@ impl Widget for PropertiesContent {
         context: AppContext,
     ) {
         let palette = context.palette();
-        let bg = palette.color(ColorRole::Window);
+        let bg = color_scheme::color(palette, ColorRole::Window);
         let rect = Rect::new(
             layout.layout.location.x as f64,
             layout.layout.location.y as f64,
@@ -446,8 +375,8 @@ impl Widget for PropertiesContent {
             &rect.to_path(4.0),
         );
 
-        let text_color = palette.color(ColorRole::BaseText);
-        let label_color = palette.color(ColorRole::DisabledTextFront);
+        let text_color = color_scheme::color(palette, ColorRole::BaseText);
+        let label_color = color_scheme::color(palette, ColorRole::DisabledTextFront);
 
         let padding = 12.0;
         let icon_size = 48.0;
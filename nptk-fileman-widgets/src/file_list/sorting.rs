@@ -0,0 +1,186 @@
+use crate::mime_types;
+use nptk::services::filesystem::entry::FileEntry;
+use std::cmp::Ordering;
+
+/// Column the file list is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileListSortColumn {
+    /// File name (case-insensitive)
+    Name,
+    /// File size in bytes
+    Size,
+    /// Type as described in the Type column
+    Type,
+    /// Last modification time
+    Modified,
+}
+
+/// Direction of the sort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    /// The opposite direction.
+    pub fn reversed(self) -> Self {
+        match self {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        }
+    }
+}
+
+/// Current sort settings of a file list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileListSort {
+    pub column: FileListSortColumn,
+    pub order: SortOrder,
+}
+
+impl Default for FileListSort {
+    fn default() -> Self {
+        Self {
+            column: FileListSortColumn::Name,
+            order: SortOrder::Ascending,
+        }
+    }
+}
+
+/// Compare names case-insensitively, with runs of digits compared by their
+/// value ("file2" before "file10").
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().flat_map(char::to_lowercase).peekable();
+    let mut b = b.chars().flat_map(char::to_lowercase).peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let mut x_digits = String::new();
+                while let Some(c) = a.next_if(char::is_ascii_digit) {
+                    x_digits.push(c);
+                }
+                let mut y_digits = String::new();
+                while let Some(c) = b.next_if(char::is_ascii_digit) {
+                    y_digits.push(c);
+                }
+                let x_value = x_digits.trim_start_matches('0');
+                let y_value = y_digits.trim_start_matches('0');
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x_digits.len().cmp(&y_digits.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            },
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            },
+        }
+    }
+}
+
+fn type_key(entry: &FileEntry) -> String {
+    mime_types::describe(entry).to_lowercase()
+}
+
+fn compare(a: &FileEntry, b: &FileEntry, column: FileListSortColumn) -> Ordering {
+    let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+    match column {
+        FileListSortColumn::Name => by_name(),
+        FileListSortColumn::Size => a.metadata.size.cmp(&b.metadata.size).then_with(by_name),
+        FileListSortColumn::Type => type_key(a).cmp(&type_key(b)).then_with(by_name),
+        FileListSortColumn::Modified => a.metadata.modified.cmp(&b.metadata.modified).then_with(by_name),
+    }
+}
+
+/// Order of `a` and `b` under `sort`. Directories are always listed before files.
+fn ordering(a: &FileEntry, b: &FileEntry, sort: FileListSort) -> Ordering {
+    b.is_dir().cmp(&a.is_dir()).then_with(|| {
+        let ordering = compare(a, b, sort.column);
+        match sort.order {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        }
+    })
+}
+
+/// Sort entries in place. Directories are always listed before files.
+pub(super) fn sort_entries(entries: &mut [FileEntry], sort: FileListSort) {
+    entries.sort_by(|a, b| ordering(a, b, sort));
+}
+
+/// Whether `entry` can replace the one at `index` of the sorted `entries`
+/// without moving.
+pub(super) fn fits_at(entries: &[FileEntry], index: usize, entry: &FileEntry, sort: FileListSort) -> bool {
+    index
+        .checked_sub(1)
+        .and_then(|before| entries.get(before))
+        .is_none_or(|before| ordering(before, entry, sort).is_le())
+        && entries.get(index + 1).is_none_or(|after| ordering(entry, after, sort).is_le())
+}
+
+/// Merge `added` into `entries`, both sorted, keeping the result sorted.
+pub(super) fn merge_sorted(entries: Vec<FileEntry>, added: Vec<FileEntry>, sort: FileListSort) -> Vec<FileEntry> {
+    let mut merged = Vec::with_capacity(entries.len() + added.len());
+    let mut added = added.into_iter().peekable();
+    for entry in entries {
+        while let Some(new) = added.next_if(|new| ordering(new, &entry, sort).is_lt()) {
+            merged.push(new);
+        }
+        merged.push(entry);
+    }
+    merged.extend(added);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nptk::services::filesystem::entry::{FileMetadata, FileType};
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+
+    fn entry(name: &str, size: u64) -> FileEntry {
+        let metadata = FileMetadata {
+            size,
+            modified: SystemTime::UNIX_EPOCH,
+            created: None,
+            permissions: 0o644,
+            mime_type: None,
+            is_hidden: false,
+        };
+        FileEntry::new(PathBuf::from("/dir").join(name), name.to_string(), FileType::File, metadata, None)
+    }
+
+    fn names(entries: &[FileEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn merges_added_entries_into_place() {
+        let sort = FileListSort::default();
+        let entries = vec![entry("b", 0), entry("d", 0)];
+        let merged = merge_sorted(entries, vec![entry("a", 0), entry("c", 0), entry("e", 0)], sort);
+        assert_eq!(names(&merged), ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn changed_entries_fit_while_their_place_stays() {
+        let sort = FileListSort { column: FileListSortColumn::Size, order: SortOrder::Ascending };
+        let entries = vec![entry("a", 1), entry("b", 5), entry("c", 9)];
+        assert!(fits_at(&entries, 1, &entry("b", 7), sort));
+        assert!(!fits_at(&entries, 1, &entry("b", 10), sort));
+        assert!(fits_at(&entries, 0, &entry("a", 0), sort));
+        assert!(fits_at(&entries, 2, &entry("c", 100), sort));
+    }
+}
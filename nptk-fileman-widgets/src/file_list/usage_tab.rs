@@ -0,0 +1,166 @@
+//! Usage tab of Properties
+//!
+//! For a mount point, how much of its file system is used and free, as a bar
+//! and in numbers. Free space is what the user can still write; space the file
+//! system reserves for root counts as used.
+
+use crate::color_scheme;
+use crate::sizes;
+use async_trait::async_trait;
+use nix::sys::statvfs::statvfs;
+use nptk::core::app::info::AppInfo;
+use nptk::core::theme::ColorRole;
+use nptk::core::vg::kurbo::{Affine, RoundedRect, Shape};
+use nptk::core::vg::peniko::{Brush, Fill};
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// Height of the bar
+const BAR_HEIGHT: f32 = 22.0;
+
+/// Size and free space of a file system, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Usage {
+    total: u64,
+    available: u64,
+}
+
+impl Usage {
+    fn of(path: &Path) -> Result<Self, String> {
+        let stat = statvfs(path).map_err(|e| format!("Failed to read the file system of {}: {}", path.display(), e))?;
+        let fragment = stat.fragment_size() as u64;
+        Ok(Usage {
+            total: (stat.blocks() as u64).saturating_mul(fragment),
+            available: (stat.blocks_available() as u64).saturating_mul(fragment),
+        })
+    }
+
+    fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    /// Used part of the file system, 0 to 1
+    fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.used() as f64 / self.total as f64
+    }
+}
+
+/// Whether a file system is mounted at `path`: the root, or a folder on
+/// another device than its parent.
+fn is_mount_point(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    if !metadata.is_dir() {
+        return false;
+    }
+    match path.parent() {
+        Some(parent) => fs::metadata(parent).is_ok_and(|parent| parent.dev() != metadata.dev()),
+        None => true,
+    }
+}
+
+/// Bar filled as far as the file system is used.
+struct UsageBar {
+    fraction: f64,
+}
+
+#[async_trait(?Send)]
+impl Widget for UsageBar {
+    fn layout_style(&self, _context: &LayoutContext) -> StyleNode {
+        StyleNode {
+            style: LayoutStyle {
+                size: Vector2::new(Dimension::percent(1.0), Dimension::length(BAR_HEIGHT)),
+                ..Default::default()
+            },
+            children: vec![],
+            measure_func: None,
+        }
+    }
+
+    async fn update(&mut self, _: &LayoutNode, _: AppContext, _: &mut AppInfo) -> Update {
+        Update::empty()
+    }
+
+    fn render(&mut self, graphics: &mut dyn Graphics, layout: &LayoutNode, _: &mut AppInfo, context: AppContext) {
+        let palette = context.palette();
+        let (x, y) = (layout.layout.location.x as f64, layout.layout.location.y as f64);
+        let (width, height) = (layout.layout.size.width as f64, layout.layout.size.height as f64);
+
+        let track = RoundedRect::new(x, y, x + width, y + height, 4.0);
+        graphics.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            &Brush::Solid(color_scheme::color(palette, ColorRole::ThreedShadow1)),
+            None,
+            &track.into_path(0.1),
+        );
+        if self.fraction > 0.0 {
+            let used = RoundedRect::new(x, y, x + width * self.fraction.min(1.0), y + height, 4.0);
+            graphics.fill(
+                Fill::NonZero,
+                Affine::IDENTITY,
+                &Brush::Solid(color_scheme::color(palette, ColorRole::Selection)),
+                None,
+                &used.into_path(0.1),
+            );
+        }
+    }
+}
+
+fn row(label: &str, value: String) -> Box<dyn Widget> {
+    Box::new(
+        Container::new(vec![
+            Box::new(Text::new(format!("{}:", label)).with_layout_style(LayoutStyle {
+                size: Vector2::new(Dimension::length(110.0), Dimension::auto()),
+                ..Default::default()
+            })),
+            Box::new(Text::new(value)),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Row,
+            gap: Vector2::new(LengthPercentage::length(6.0), LengthPercentage::length(0.0)),
+            ..Default::default()
+        }),
+    )
+}
+
+/// The tab for `path` if a file system is mounted there, with a message
+/// instead of the chart if its usage can't be read.
+pub(super) fn usage_tab(path: &Path) -> Option<Container> {
+    if !is_mount_point(path) {
+        return None;
+    }
+    let children: Vec<Box<dyn Widget>> = match Usage::of(path) {
+        Ok(usage) => vec![
+            Box::new(UsageBar { fraction: usage.fraction() }),
+            row(
+                "Used",
+                format!("{} ({:.0}%)", sizes::format(usage.used()), usage.fraction() * 100.0),
+            ),
+            row("Free", sizes::format(usage.available)),
+            row("Capacity", sizes::format(usage.total)),
+        ],
+        Err(e) => vec![Box::new(Text::new(e))],
+    };
+
+    Some(Container::new(children).with_layout_style(LayoutStyle {
+        size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+        flex_direction: FlexDirection::Column,
+        padding: Rect {
+            left: LengthPercentage::length(12.0),
+            right: LengthPercentage::length(12.0),
+            top: LengthPercentage::length(12.0),
+            bottom: LengthPercentage::length(12.0),
+        },
+        gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(8.0)),
+        ..Default::default()
+    }))
+}
@@ -9,12 +9,14 @@ use nptk::core::vg::peniko::{Brush, Color, Fill};
 use nptk::core::vgi::Graphics;
 use nptk::core::widget::Widget;
 use nptk::services::filesystem::entry::FileEntry;
-use nptk::services::thumbnail::npio_adapter::{file_entry_to_uri, u32_to_thumbnail_size};
 use nptk::core::theme::{ColorRole, Palette};
-use npio::get_file_for_uri;
+use crate::color_scheme;
 use std::collections::HashSet;
 use std::path::PathBuf;
 
+/// Opacity of the selection behind selected labels
+const LABEL_SELECTION_ALPHA: f32 = 0.7;
+
 impl FileListContent {
     pub(super) fn calculate_compact_view_layout(&self, width: f32) -> (usize, f32, f32, f32) {
         let cell_width = 250.0; // Fixed width for compact tiles
@@ -66,7 +68,7 @@ impl FileListContent {
         // Measure text to determine label width
         let (text_width, line_count) = self.text_render_context.measure_text_layout(
             font_cx,
-            &entry.name,
+            self.launchers.get().display_name(entry),
             None,
             font_size,
             Some(max_text_width as f32),
@@ -175,20 +177,18 @@ impl FileListContent {
             // 1. Draw Label Background (Selection/Hover)
             if is_selected || is_hovered {
                 let color = if is_selected {
-                    palette.color(ColorRole::Selection)
+                    color_scheme::selection(palette, LABEL_SELECTION_ALPHA)
                 } else {
-                    palette.color(ColorRole::HoverHighlight)
+                    color_scheme::color(palette, ColorRole::HoverHighlight).with_alpha(0.5)
                 };
 
-                let alpha = if is_selected { 0.7 } else { 0.5 };
-
                 let label_bg_rect =
                     RoundedRect::from_rect(label_rect, RoundedRectRadii::new(3.0, 3.0, 3.0, 3.0));
 
                 graphics.fill(
                     Fill::NonZero,
                     Affine::IDENTITY,
-                    &Brush::Solid(color.with_alpha(alpha)),
+                    &Brush::Solid(color),
                     None,
                     &label_bg_rect.to_path(0.1),
                 );
@@ -202,7 +202,7 @@ impl FileListContent {
             let mut use_thumbnail = false;
             let thumbnail_cache_key = (entry.path.clone(), thumb_size);
             if let Some(thumbnail_image) = {
-                let cache = self.thumbnail_cache.lock().expect("Failed to lock thumbnail_cache in view_compact");
+                let mut cache = self.thumbnail_cache.lock().expect("Failed to lock thumbnail_cache in view_compact");
                 cache.get(&thumbnail_cache_key).cloned()
             } {
                 use nptk::core::vg::peniko::{
@@ -227,63 +227,13 @@ impl FileListContent {
                 use_thumbnail = true;
             }
 
-            if !use_thumbnail {
+            if !use_thumbnail && entry.is_file() {
                 // Request thumbnail generation asynchronously (non-blocking)
-                if entry.is_file() {
-                    let mut pending = self.pending_thumbnails.lock().expect("Failed to lock pending_thumbnails in view_compact");
-                    // Use insert() which returns true if the value was newly inserted (atomic check-and-insert)
-                    if pending.insert(entry.path.clone()) {
-                        let file_clone = get_file_for_uri(&file_entry_to_uri(&entry)).ok();
-                        let service_clone = self.thumbnail_service.clone();
-                        let size = u32_to_thumbnail_size(thumb_size);
-                        
-                        // Spawn async task to generate thumbnail (non-blocking)
-                        let semaphore_clone = self.async_task_semaphore.clone();
-                        tokio::spawn(async move {
-                            // Acquire semaphore permit to limit concurrent tasks
-                            let _permit = semaphore_clone.acquire().await.ok();
-                            if let Some(f) = file_clone {
-                                let _ = service_clone
-                                    .get_or_generate_thumbnail(&*f, size, None)
-                                    .await;
-                            }
-                            // Permit is automatically released when dropped
-                        });
-                    }
-                }
+                self.request_thumbnail(&entry, thumb_size);
             }
 
-            // Get icon for this entry (only use cached, don't block on loading)
-            let cache_key = (entry.path.clone(), thumb_size);
-            let cached_icon = {
-                let cache = self.icon_cache.lock().expect("Failed to lock icon_cache in view_compact");
-                cache.get(&cache_key).and_then(|opt| opt.clone())
-            };
-            
-            // If icon not cached, request it asynchronously (non-blocking)
-            if cached_icon.is_none() {
-                let cache_clone = self.icon_cache.clone();
-                let registry_clone = self.icon_registry.clone();
-                let entry_clone = entry.clone();
-                let cache_key_clone = cache_key.clone();
-                let cache_update_tx_clone = self.cache_update_tx.clone();
-                let semaphore_clone = self.async_task_semaphore.clone();
-                tokio::spawn(async move {
-                    // Acquire semaphore permit to limit concurrent tasks
-                    let _permit = semaphore_clone.acquire().await.ok();
-                    let uri = file_entry_to_uri(&entry_clone);
-                    if let Ok(file) = get_file_for_uri(&uri) {
-                        let icon = registry_clone.get_file_icon(&*file, thumb_size).await;
-                        let mut cache = cache_clone.lock().expect("Failed to lock icon_cache in async task (view_compact)");
-                        cache.insert(cache_key_clone, icon);
-                        // Notify that cache was updated to trigger redraw
-                        if cache_update_tx_clone.try_send(()).is_err() {
-                            log::debug!("Cache update channel full, skipping notification");
-                        }
-                    }
-                    // Permit is automatically released when dropped
-                });
-            }
+            // Get icon for this entry (only use cached, loading is queued otherwise)
+            let cached_icon = self.cached_icon(&entry, thumb_size);
 
             if let Some(icon) = cached_icon {
                 render_cached_icon(
@@ -303,12 +253,18 @@ impl FileListContent {
                 );
             }
 
+            let dimmed = self.is_dimmed(&entry.path);
+            if dimmed {
+                self.render_dimmed_icon(graphics, palette, icon_rect);
+            }
+            self.render_emblem(graphics, palette, icon_rect, &entry.path);
+
             // 3. Draw Icon Overlay (Selection/Hover)
             if is_selected || is_hovered {
                 let color = if is_selected {
-                    palette.color(ColorRole::Selection)
+                    color_scheme::color(palette, ColorRole::Selection)
                 } else {
-                    palette.color(ColorRole::HoverHighlight)
+                    color_scheme::color(palette, ColorRole::HoverHighlight)
                 };
 
                 let alpha = if is_selected { 0.5 } else { 0.3 };
@@ -326,7 +282,12 @@ impl FileListContent {
             }
 
             // 4. Draw Label Text
-            let text_color = palette.color(ColorRole::BaseText);
+            let text_color = if is_selected {
+                color_scheme::text_on(palette, color_scheme::selection(palette, LABEL_SELECTION_ALPHA))
+            } else {
+                color_scheme::color(palette, ColorRole::BaseText)
+            };
+            let text_color = if dimmed { text_color.with_alpha(0.5) } else { text_color };
 
             // Use label_rect to position text (reverse padding)
             let text_x = label_rect.x0 + 4.0; // label_padding_x
@@ -341,7 +302,7 @@ impl FileListContent {
             self.text_render_context.render_text_with_max_lines(
                 &mut info.font_context,
                 graphics,
-                &entry.name,
+                self.launchers.get().display_name(entry),
                 None,
                 font_size,
                 Brush::Solid(text_color),
@@ -9,12 +9,14 @@ use nptk::core::vg::peniko::{Brush, Color, Fill};
 use nptk::core::vgi::Graphics;
 use nptk::core::widget::Widget;
 use nptk::services::filesystem::entry::FileEntry;
-use nptk::services::thumbnail::npio_adapter::{file_entry_to_uri, u32_to_thumbnail_size};
 use nptk::core::theme::{ColorRole, Palette};
-use npio::get_file_for_uri;
+use crate::color_scheme;
 use std::collections::HashSet;
 use std::path::PathBuf;
 
+/// Opacity of the selection behind selected labels
+const LABEL_SELECTION_ALPHA: f32 = 0.9;
+
 impl FileListContent {
     pub(super) fn calculate_icon_view_layout(
         &self,
@@ -67,7 +69,7 @@ impl FileListContent {
             (layout.layout.location.y + layout.layout.size.height) as f64,
         );
 
-        let bg_color = palette.color(ColorRole::Window);
+        let bg_color = color_scheme::color(palette, ColorRole::Window);
 
         graphics.fill(
             Fill::NonZero,
@@ -207,7 +209,7 @@ impl FileListContent {
         };
 
         if is_hovered && !is_selected {
-            let hover_color = palette.color(ColorRole::HoverHighlight);
+            let hover_color = color_scheme::color(palette, ColorRole::HoverHighlight);
 
             // Draw label hover rectangle
             let label_hover_rect = RoundedRect::new(
@@ -228,7 +230,7 @@ impl FileListContent {
         }
 
         if is_selected {
-            let color = palette.color(ColorRole::Selection);
+            let color = color_scheme::selection(palette, LABEL_SELECTION_ALPHA);
 
             // Draw label selection rectangle
             let label_selection_rect = RoundedRect::new(
@@ -242,7 +244,7 @@ impl FileListContent {
             graphics.fill(
                 Fill::NonZero,
                 Affine::IDENTITY,
-                &Brush::Solid(color.with_alpha(0.9)),
+                &Brush::Solid(color),
                 None,
                 &label_selection_rect.to_path(0.1),
             );
@@ -253,7 +255,7 @@ impl FileListContent {
         let mut use_thumbnail = false;
             let thumbnail_cache_key = (entry.path.clone(), icon_size);
             if let Some(thumbnail_image) = {
-                let cache = self.thumbnail_cache.lock().expect("Failed to lock thumbnail_cache in view_icon");
+                let mut cache = self.thumbnail_cache.lock().expect("Failed to lock thumbnail_cache in view_icon");
             cache.get(&thumbnail_cache_key).cloned()
         } {
             use nptk::core::vg::peniko::{
@@ -279,63 +281,13 @@ impl FileListContent {
         }
 
         // If no thumbnail, use icon
-        if !use_thumbnail {
+        if !use_thumbnail && entry.is_file() {
             // Request thumbnail generation asynchronously (non-blocking)
-            if entry.is_file() {
-                let mut pending = self.pending_thumbnails.lock().expect("Failed to lock pending_thumbnails in view_icon");
-                // Use insert() which returns true if the value was newly inserted (atomic check-and-insert)
-                if pending.insert(entry.path.clone()) {
-                    let file_clone = get_file_for_uri(&file_entry_to_uri(entry)).ok();
-                    let service_clone = self.thumbnail_service.clone();
-                    let size = u32_to_thumbnail_size(icon_size);
-                    
-                    // Spawn async task to generate thumbnail (non-blocking)
-                    let semaphore_clone = self.async_task_semaphore.clone();
-                    tokio::spawn(async move {
-                        // Acquire semaphore permit to limit concurrent tasks
-                        let _permit = semaphore_clone.acquire().await.ok();
-                        if let Some(f) = file_clone {
-                            let _ = service_clone
-                                .get_or_generate_thumbnail(&*f, size, None)
-                                .await;
-                        }
-                        // Permit is automatically released when dropped
-                    });
-                }
-            }
+            self.request_thumbnail(entry, icon_size);
         }
 
-        // Get icon for this entry (only use cached, don't block on loading)
-        let cache_key = (entry.path.clone(), icon_size);
-        let cached_icon = {
-            let cache = self.icon_cache.lock().expect("Failed to lock icon_cache in view_icon");
-            cache.get(&cache_key).and_then(|opt| opt.clone())
-        };
-        
-        // If icon not cached, request it asynchronously (non-blocking)
-        if cached_icon.is_none() {
-            let cache_clone = self.icon_cache.clone();
-            let registry_clone = self.icon_registry.clone();
-            let entry_clone = entry.clone();
-            let cache_key_clone = cache_key.clone();
-            let cache_update_tx_clone = self.cache_update_tx.clone();
-            let semaphore_clone = self.async_task_semaphore.clone();
-            tokio::spawn(async move {
-                // Acquire semaphore permit to limit concurrent tasks
-                let _permit = semaphore_clone.acquire().await.ok();
-                    let uri = file_entry_to_uri(&entry_clone);
-                    if let Ok(file) = get_file_for_uri(&uri) {
-                        let icon = registry_clone.get_file_icon(&*file, icon_size).await;
-                        let mut cache = cache_clone.lock().expect("Failed to lock icon_cache in async task (view_icon)");
-                    cache.insert(cache_key_clone, icon);
-                    // Notify that cache was updated to trigger redraw
-                    if cache_update_tx_clone.try_send(()).is_err() {
-                        log::debug!("Cache update channel full, skipping notification");
-                    }
-                }
-                // Permit is automatically released when dropped
-            });
-        }
+        // Get icon for this entry (only use cached, loading is queued otherwise)
+        let cached_icon = self.cached_icon(entry, icon_size);
 
         if let Some(icon) = cached_icon {
             render_cached_icon(
@@ -355,9 +307,15 @@ impl FileListContent {
             );
         }
 
+        let dimmed = self.is_dimmed(&entry.path);
+        if dimmed {
+            self.render_dimmed_icon(graphics, palette, icon_rect);
+        }
+        self.render_emblem(graphics, palette, icon_rect, &entry.path);
+
         // 3. Draw Icon Overlays (Hover/Selection) - on top of icon (tint)
         if is_hovered && !is_selected {
-            let hover_color = palette.color(ColorRole::HoverHighlight);
+            let hover_color = color_scheme::color(palette, ColorRole::HoverHighlight);
 
             // Draw icon hover rectangle (overlay)
             let icon_hover_rect = RoundedRect::new(
@@ -378,7 +336,7 @@ impl FileListContent {
         }
 
         if is_selected {
-            let color = palette.color(ColorRole::Selection);
+            let color = color_scheme::color(palette, ColorRole::Selection);
 
             // Draw icon selection rectangle (overlay)
             let icon_selection_rect = RoundedRect::new(
@@ -399,7 +357,12 @@ impl FileListContent {
         }
 
         // Draw filename in label rectangle
-        let text_color = palette.color(ColorRole::BaseText);
+        let text_color = if is_selected {
+            color_scheme::text_on(palette, color_scheme::selection(palette, LABEL_SELECTION_ALPHA))
+        } else {
+            color_scheme::color(palette, ColorRole::BaseText)
+        };
+        let text_color = if dimmed { text_color.with_alpha(0.5) } else { text_color };
 
         // Text position: Start at the left edge of the max_text_width area.
         // We use max_text_width as the wrap width, and ask Parley to center align.
@@ -544,7 +507,8 @@ impl FileListContent {
         let line_height = font_size * 1.2;
         let max_text_width = (cell_width - self.icon_view_padding * 2.0).max(10.0);
 
-        let name = &entry.name;
+        let launchers = self.launchers.get();
+        let name = launchers.display_name(entry);
         let (text_with_breaks, has_natural_breaks) = {
             let is_continuous = name.chars().all(|c| c.is_alphanumeric());
             let mut result = String::with_capacity(name.len() + name.len() / 8);
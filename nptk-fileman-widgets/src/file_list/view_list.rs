@@ -7,12 +7,14 @@ use nptk::core::vg::kurbo::{Affine, Rect, Shape};
 use nptk::core::vg::peniko::{Brush, Color, Fill};
 use nptk::core::vgi::Graphics;
 use nptk::core::widget::Widget;
-use nptk::services::thumbnail::npio_adapter::{file_entry_to_uri, u32_to_thumbnail_size};
 use nptk::core::theme::{ColorRole, Palette};
-use npio::get_file_for_uri;
+use crate::color_scheme;
 use std::collections::HashSet;
 use std::path::PathBuf;
 
+/// Opacity of the selection behind selected rows
+const SELECTION_ALPHA: f32 = 0.3;
+
 impl FileListContent {
     pub(super) fn render_list_view(
         &mut self,
@@ -34,7 +36,7 @@ impl FileListContent {
             (layout.layout.location.y + layout.layout.size.height) as f64,
         );
 
-        let bg_color = palette.color(ColorRole::Window);
+        let bg_color = color_scheme::color(palette, ColorRole::Window);
 
         graphics.fill(
             Fill::NonZero,
@@ -53,18 +55,19 @@ impl FileListContent {
         let viewport_start_y = (-layout.layout.location.y).max(0.0);
         let viewport_end_y = info.size.y as f32 - layout.layout.location.y;
 
-        let start_index = (viewport_start_y / self.item_height).floor().max(0.0) as usize;
-        let end_index = ((viewport_end_y / self.item_height).ceil() as usize + 1).min(entry_count);
+        let item_height = self.item_height();
+        let start_index = (viewport_start_y / item_height).floor().max(0.0) as usize;
+        let end_index = ((viewport_end_y / item_height).ceil() as usize + 1).min(entry_count);
 
         // Only render visible items
         for i in start_index..end_index {
             let entry = &entries[i];
-            let y = layout.layout.location.y + i as f32 * self.item_height;
+            let y = layout.layout.location.y + i as f32 * item_height;
             let row_rect = Rect::new(
                 layout.layout.location.x as f64,
                 y as f64,
                 (layout.layout.location.x + layout.layout.size.width) as f64,
-                (y + self.item_height) as f64,
+                (y + item_height) as f64,
             );
 
             // Check for hover state
@@ -81,7 +84,7 @@ impl FileListContent {
 
             // Draw hover background (if not selected)
             if is_hovered && !selected_set.contains(&entry.path) {
-                let hover_color = palette.color(ColorRole::HoverHighlight);
+                let hover_color = color_scheme::color(palette, ColorRole::HoverHighlight);
 
                 graphics.fill(
                     Fill::NonZero,
@@ -94,82 +97,35 @@ impl FileListContent {
 
             // Draw selection background
             if selected_set.contains(&entry.path) {
-                let color = palette.color(ColorRole::Selection);
+                let color = color_scheme::selection(palette, SELECTION_ALPHA);
 
                 graphics.fill(
                     Fill::NonZero,
                     Affine::IDENTITY,
-                    &Brush::Solid(color.with_alpha(0.3)),
+                    &Brush::Solid(color),
                     None,
                     &row_rect.to_path(0.1),
                 );
             }
 
             // Try to get thumbnail first, fall back to icon (view_list uses icons, not thumbnails)
-            let icon_size = 20.0;
+            // The icon fills the row, which grows with the zoom
+            let icon_size = (item_height - 10.0).max(1.0);
             let icon_rect = Rect::new(
                 row_rect.x0 + 5.0,
                 row_rect.y0 + 5.0,
-                row_rect.x0 + 25.0,
+                row_rect.x0 + 5.0 + icon_size as f64,
                 row_rect.y1 - 5.0,
             );
 
             // Request thumbnail generation asynchronously (non-blocking)
             // Thumbnails will be rendered when ready via event system
             if entry.is_file() {
-                let mut pending = self.pending_thumbnails.lock().expect("Failed to lock pending_thumbnails in view_list");
-                // Use insert() which returns true if the value was newly inserted (atomic check-and-insert)
-                if pending.insert(entry.path.clone()) {
-                    let file_clone = get_file_for_uri(&file_entry_to_uri(entry)).ok();
-                    let service_clone = self.thumbnail_service.clone();
-                    let size = u32_to_thumbnail_size(self.thumbnail_size);
-                    
-                    // Spawn async task to generate thumbnail (non-blocking)
-                    let semaphore_clone = self.async_task_semaphore.clone();
-                    tokio::spawn(async move {
-                        // Acquire semaphore permit to limit concurrent tasks
-                        let _permit = semaphore_clone.acquire().await.ok();
-                        if let Some(f) = file_clone {
-                            let _ = service_clone
-                                .get_or_generate_thumbnail(&*f, size, None)
-                                .await;
-                        }
-                        // Permit is automatically released when dropped
-                    });
-                }
+                self.request_thumbnail(entry, self.thumbnail_size);
             }
 
-            // Get icon for this entry (only use cached, don't block on loading)
-            let cache_key = (entry.path.clone(), icon_size as u32);
-            let cached_icon = {
-                let cache = self.icon_cache.lock().expect("Failed to lock icon_cache in view_list");
-                cache.get(&cache_key).and_then(|opt| opt.clone())
-            };
-            
-            // If icon not cached, request it asynchronously (non-blocking)
-            if cached_icon.is_none() {
-                let cache_clone = self.icon_cache.clone();
-                let registry_clone = self.icon_registry.clone();
-                let entry_clone = entry.clone();
-                let cache_key_clone = cache_key.clone();
-                let cache_update_tx_clone = self.cache_update_tx.clone();
-                let semaphore_clone = self.async_task_semaphore.clone();
-                tokio::spawn(async move {
-                    // Acquire semaphore permit to limit concurrent tasks
-                    let _permit = semaphore_clone.acquire().await.ok();
-                    let uri = file_entry_to_uri(&entry_clone);
-                    if let Ok(file) = get_file_for_uri(&uri) {
-                        let icon = registry_clone.get_file_icon(&*file, icon_size as u32).await;
-                        let mut cache = cache_clone.lock().expect("Failed to lock icon_cache in async task (view_list)");
-                        cache.insert(cache_key_clone, icon);
-                        // Notify that cache was updated to trigger redraw
-                        if cache_update_tx_clone.try_send(()).is_err() {
-                            log::debug!("Cache update channel full, skipping notification");
-                        }
-                    }
-                    // Permit is automatically released when dropped
-                });
-            }
+            // Get icon for this entry (only use cached, loading is queued otherwise)
+            let cached_icon = self.cached_icon(entry, icon_size as u32);
 
             if let Some(icon) = cached_icon {
                 // Validate that the file still exists before using cached icon
@@ -200,21 +156,33 @@ impl FileListContent {
                 );
             }
 
+            let dimmed = self.is_dimmed(&entry.path);
+            if dimmed {
+                self.render_dimmed_icon(graphics, palette, icon_rect);
+            }
+            self.render_emblem(graphics, palette, icon_rect, &entry.path);
+
             // Draw text
-            let text_color = palette.color(ColorRole::BaseText);
+            let text_color = if selected_set.contains(&entry.path) {
+                color_scheme::text_on(palette, color_scheme::selection(palette, SELECTION_ALPHA))
+            } else {
+                color_scheme::color(palette, ColorRole::BaseText)
+            };
+            let text_color = if dimmed { text_color.with_alpha(0.5) } else { text_color };
 
-            let transform = Affine::translate((row_rect.x0 + 35.0, row_rect.y0 + 5.0));
+            let text_x = icon_rect.x1 + 10.0;
+            let transform = Affine::translate((text_x, row_rect.y0 + (item_height as f64 - 20.0) / 2.0));
 
             self.text_render_context.render_text(
                 &mut info.font_context,
                 graphics,
-                &entry.name,
+                self.launchers.get().display_name(entry),
                 None,
                 16.0,
                 Brush::Solid(text_color),
                 transform,
                 true,
-                Some(row_rect.width() as f32 - 40.0),
+                Some((row_rect.x1 - text_x) as f32 - 5.0),
             );
         }
 
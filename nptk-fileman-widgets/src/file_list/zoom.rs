@@ -0,0 +1,83 @@
+//! Zoom of the file list views
+//!
+//! Every view mode has its own zoom level, counted in steps from the default
+//! size of the mode: the row height of the List and Table views and the icon
+//! size of the Icon view. The Compact view has fixed tiles and isn't zoomed.
+
+use super::FileListViewMode;
+
+/// Row heights of the List and Table views
+const ROW_HEIGHTS: [f32; 6] = [22.0, 26.0, 30.0, 36.0, 44.0, 52.0];
+const DEFAULT_ROW_HEIGHT: usize = 2;
+
+/// Icon sizes of the Icon view
+const ICON_SIZES: [u32; 7] = [32, 48, 64, 96, 128, 192, 256];
+const DEFAULT_ICON_SIZE: usize = 1;
+
+/// Zoom level of each view mode, 0 being the default size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileListZoom {
+    list: i32,
+    icon: i32,
+    table: i32,
+}
+
+impl FileListZoom {
+    /// The sizes `mode` can be zoomed through and the index of its default
+    fn steps(mode: FileListViewMode) -> (usize, usize) {
+        match mode {
+            FileListViewMode::List | FileListViewMode::Table => (ROW_HEIGHTS.len(), DEFAULT_ROW_HEIGHT),
+            FileListViewMode::Icon => (ICON_SIZES.len(), DEFAULT_ICON_SIZE),
+            FileListViewMode::Compact => (1, 0),
+        }
+    }
+
+    /// Index of the size `mode` is zoomed to
+    fn index(&self, mode: FileListViewMode) -> usize {
+        let (count, default) = Self::steps(mode);
+        (default as i32 + self.level(mode)).clamp(0, count as i32 - 1) as usize
+    }
+
+    /// Zoom level of `mode`.
+    pub fn level(&self, mode: FileListViewMode) -> i32 {
+        match mode {
+            FileListViewMode::List => self.list,
+            FileListViewMode::Icon => self.icon,
+            FileListViewMode::Table => self.table,
+            FileListViewMode::Compact => 0,
+        }
+    }
+
+    /// Set the zoom level of `mode`, limited to the sizes it has.
+    pub fn set_level(&mut self, mode: FileListViewMode, level: i32) {
+        let (count, default) = Self::steps(mode);
+        let level = level.clamp(-(default as i32), (count - 1 - default) as i32);
+        match mode {
+            FileListViewMode::List => self.list = level,
+            FileListViewMode::Icon => self.icon = level,
+            FileListViewMode::Table => self.table = level,
+            FileListViewMode::Compact => {},
+        }
+    }
+
+    /// Zoom `mode` in (positive `steps`) or out; false if it is already at the
+    /// largest or smallest size.
+    pub fn zoom(&mut self, mode: FileListViewMode, steps: i32) -> bool {
+        let level = self.level(mode);
+        self.set_level(mode, level + steps);
+        self.level(mode) != level
+    }
+
+    /// Row height of the List view, or of the Table view for `Table`.
+    pub fn row_height(&self, mode: FileListViewMode) -> f32 {
+        match mode {
+            FileListViewMode::Table => ROW_HEIGHTS[self.index(FileListViewMode::Table)],
+            _ => ROW_HEIGHTS[self.index(FileListViewMode::List)],
+        }
+    }
+
+    /// Icon size of the Icon view.
+    pub fn icon_size(&self) -> u32 {
+        ICON_SIZES[self.index(FileListViewMode::Icon)]
+    }
+}
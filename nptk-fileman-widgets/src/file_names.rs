@@ -0,0 +1,188 @@
+//! File name validation
+//!
+//! Shared by everything that names files: New Folder, New File, Rename and
+//! batch renames. [validate] rejects names no file system takes (or the one
+//! the name is for doesn't) and warns about legal names that are easily
+//! mistaken; [free_path] and [alternatives] number a name until it is free.
+//! [read_only_file_system] tells where no names can be given at all.
+
+use nix::sys::statvfs::{FsFlags, statvfs};
+use std::path::{Path, PathBuf};
+
+/// Longest name assumed where the file system doesn't tell, in bytes
+const DEFAULT_NAME_MAX: usize = 255;
+
+/// Something legal but probably unintended about a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameWarning {
+    /// Names starting with a dot are hidden
+    Hidden,
+    /// Spaces at the start or end are easily overlooked
+    SurroundingSpaces,
+}
+
+impl NameWarning {
+    pub fn message(self) -> &'static str {
+        match self {
+            NameWarning::Hidden => "Names starting with \".\" are hidden",
+            NameWarning::SurroundingSpaces => "The name starts or ends with spaces",
+        }
+    }
+}
+
+/// Longest name, in bytes, the file system of the folder `dir` accepts.
+pub fn max_name_length(dir: &Path) -> usize {
+    statvfs(dir)
+        .ok()
+        .map(|stat| stat.name_max() as usize)
+        .filter(|max| *max > 0)
+        .unwrap_or(DEFAULT_NAME_MAX)
+}
+
+/// Whether the folder `dir` is on a file system mounted read-only.
+pub fn read_only_file_system(dir: &Path) -> bool {
+    statvfs(dir).is_ok_and(|stat| stat.flags().contains(FsFlags::ST_RDONLY))
+}
+
+/// Check `name` for an item in the folder `dir`. Returns the warnings of a
+/// usable name, or a user-facing message why it can't be used.
+pub fn validate(name: &str, dir: &Path) -> Result<Vec<NameWarning>, String> {
+    if name.trim().is_empty() {
+        return Err("The name cannot be empty".to_string());
+    }
+    if name == "." || name == ".." {
+        return Err(format!("\"{}\" is not a valid name", name));
+    }
+    if name.contains('/') {
+        return Err("The name cannot contain \"/\"".to_string());
+    }
+    if name.contains('\0') {
+        return Err("The name cannot contain NUL characters".to_string());
+    }
+    let max = max_name_length(dir);
+    if name.len() > max {
+        return Err(format!(
+            "The name is too long ({} bytes, this location allows {})",
+            name.len(),
+            max
+        ));
+    }
+
+    let mut warnings = Vec::new();
+    if name.starts_with('.') {
+        warnings.push(NameWarning::Hidden);
+    }
+    if name.trim() != name {
+        warnings.push(NameWarning::SurroundingSpaces);
+    }
+    Ok(warnings)
+}
+
+/// Numbered siblings of `path`: "name (2).ext", "name (3).ext", ...
+fn numbered(path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path.extension().map(|s| s.to_string_lossy().to_string());
+    (2..).map(move |n| {
+        let name = match &extension {
+            Some(ext) => format!("{} ({}).{}", stem, n, ext),
+            None => format!("{} ({})", stem, n),
+        };
+        parent.join(name)
+    })
+}
+
+/// Whether something, even a dangling link, is at `path`.
+fn taken(path: &Path) -> bool {
+    path.symlink_metadata().is_ok()
+}
+
+/// The first numbered sibling of `path` that doesn't exist yet.
+pub fn free_path(path: &Path) -> PathBuf {
+    numbered(path)
+        .find(|candidate| !taken(candidate))
+        .expect("unbounded range always yields a free name")
+}
+
+/// `path` if it is free, otherwise its first free numbered sibling.
+pub fn free_or_numbered(path: &Path) -> PathBuf {
+    if taken(path) { free_path(path) } else { path.to_path_buf() }
+}
+
+/// `path` followed by its numbered siblings, to try one after the other where
+/// a name has to be claimed by creating the item.
+pub fn candidates(path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    std::iter::once(path.to_path_buf()).chain(numbered(path))
+}
+
+/// Up to `count` free names to offer instead of the taken `path`.
+pub fn alternatives(path: &Path, count: usize) -> Vec<String> {
+    numbered(path)
+        .filter(|candidate| !taken(candidate))
+        .take(count)
+        .filter_map(|candidate| candidate.file_name().map(|n| n.to_string_lossy().to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn rejects_unusable_names() {
+        let dir = std::env::temp_dir();
+        for name in ["", "   ", ".", "..", "a/b", "/", "nul\0byte"] {
+            assert!(validate(name, &dir).is_err(), "{:?}", name);
+        }
+        let max = max_name_length(&dir);
+        assert!(validate(&"x".repeat(max), &dir).is_ok());
+        assert!(validate(&"x".repeat(max + 1), &dir).is_err());
+        // Bytes count, not characters
+        assert!(validate(&"é".repeat(max / 2 + 1), &dir).is_err());
+    }
+
+    #[test]
+    fn warns_about_names_easily_mistaken() {
+        let dir = std::env::temp_dir();
+        assert_eq!(validate("notes.txt", &dir), Ok(Vec::new()));
+        assert_eq!(validate("...", &dir), Ok(vec![NameWarning::Hidden]));
+        assert_eq!(validate(".config", &dir), Ok(vec![NameWarning::Hidden]));
+        assert_eq!(validate(" notes ", &dir), Ok(vec![NameWarning::SurroundingSpaces]));
+        assert_eq!(
+            validate(". hidden ", &dir),
+            Ok(vec![NameWarning::Hidden, NameWarning::SurroundingSpaces])
+        );
+    }
+
+    #[test]
+    fn numbers_names_until_free() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("name.ext");
+        assert_eq!(free_or_numbered(&file), file);
+
+        fs::write(&file, "").unwrap();
+        assert_eq!(free_or_numbered(&file), dir.path().join("name (2).ext"));
+        fs::write(dir.path().join("name (2).ext"), "").unwrap();
+        assert_eq!(free_path(&file), dir.path().join("name (3).ext"));
+        assert_eq!(alternatives(&file, 2), vec!["name (3).ext", "name (4).ext"]);
+
+        // Only the last extension is kept apart
+        assert_eq!(free_path(&dir.path().join("archive.tar.gz")), dir.path().join("archive.tar (2).gz"));
+        assert_eq!(free_path(&dir.path().join(".bashrc")), dir.path().join(".bashrc (2)"));
+        assert_eq!(free_path(&dir.path().join("folder")), dir.path().join("folder (2)"));
+    }
+
+    #[test]
+    fn dangling_links_take_their_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(dir.path().join("missing"), &link).unwrap();
+        assert_eq!(free_or_numbered(&link), dir.path().join("link (2)"));
+        std::os::unix::fs::symlink(dir.path().join("missing"), dir.path().join("link (2)")).unwrap();
+        assert_eq!(alternatives(&link, 1), vec!["link (3)"]);
+    }
+}
@@ -15,16 +15,48 @@ use nptk::services::thumbnail::npio_adapter::uri_to_path;
 use nptk::core::app::info::AppInfo;
 use nptk::core::vgi::Graphics;
 use nptk::core::theme::{ColorRole, Palette};
+use nptk::core::menu::{MenuCommand, MenuItem, MenuTemplate};
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vg::kurbo::{Affine, Point, RoundedRect, Shape, Stroke};
+use nptk::core::vg::peniko::Brush;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 
+mod bookmarks;
+mod rename_popup;
+
+use crate::accessibility::{AccessibleNode, AccessibleRole, AccessibleSubtree};
+use crate::color_scheme;
+use crate::focus::{FocusChain, Pane, PaneKey};
+use rename_popup::BookmarkRenamePopup;
+
+/// Height of a section header row in the inner Sidebar.
+const SECTION_HEADER_HEIGHT: f32 = 28.0;
+/// Height of an item row in the inner Sidebar.
+const ITEM_HEIGHT: f32 = 32.0;
+
+/// An entry of the Places section.
+#[derive(Debug, Clone)]
+pub enum PlacesEntry {
+    /// The home directory (item id `"home"`).
+    Home,
+    /// An XDG user directory (item id is the lowercase variant name, e.g. `"documents"`).
+    UserDirectory(UserDirectory),
+    /// An embedder-provided item.
+    Item(SidebarItem),
+}
+
 /// Configuration for FilemanSidebar
 #[derive(Debug, Clone)]
 pub struct FilemanSidebarConfig {
     show_places: bool,
     show_bookmarks: bool,
     show_devices: bool,
-    user_directories: Vec<UserDirectory>,
+    places: Vec<PlacesEntry>,
+    /// Icon names overriding the defaults, keyed by item id.
+    icon_overrides: HashMap<String, String>,
     custom_sections: Vec<SidebarSection>,
     width: f32,
     use_symbolic_icons: bool,
@@ -36,14 +68,16 @@ impl Default for FilemanSidebarConfig {
             show_places: true,
             show_bookmarks: false,
             show_devices: false,
-            user_directories: vec![
-                UserDirectory::Desktop,
-                UserDirectory::Documents,
-                UserDirectory::Download,
-                UserDirectory::Music,
-                UserDirectory::Pictures,
-                UserDirectory::Videos,
+            places: vec![
+                PlacesEntry::Home,
+                PlacesEntry::UserDirectory(UserDirectory::Desktop),
+                PlacesEntry::UserDirectory(UserDirectory::Documents),
+                PlacesEntry::UserDirectory(UserDirectory::Download),
+                PlacesEntry::UserDirectory(UserDirectory::Music),
+                PlacesEntry::UserDirectory(UserDirectory::Pictures),
+                PlacesEntry::UserDirectory(UserDirectory::Videos),
             ],
+            icon_overrides: HashMap::new(),
             custom_sections: Vec::new(),
             width: 200.0,
             use_symbolic_icons: false,
@@ -51,52 +85,142 @@ impl Default for FilemanSidebarConfig {
     }
 }
 
+/// A change to the sidebar sections applied at runtime.
+///
+/// Sections are identified by id: `"places"`, `"bookmarks"`, `"recent"`, `"devices"`, or the
+/// id passed to [FilemanSidebar::with_custom_section_id].
+#[derive(Debug, Clone)]
+pub enum SidebarPatch {
+    /// Replace the items of an existing section.
+    SetItems { section: String, items: Vec<SidebarItem> },
+    /// Insert or replace a whole section. New sections are appended at the end.
+    SetSection { id: String, title: String, items: Vec<SidebarItem> },
+    /// Remove a section.
+    RemoveSection(String),
+}
+
+/// Cloneable handle used to patch sidebar sections from other widgets or async tasks.
+#[derive(Clone)]
+pub struct SidebarUpdater {
+    tx: mpsc::UnboundedSender<SidebarPatch>,
+}
+
+impl SidebarUpdater {
+    /// Queue a patch; it is applied on the sidebar's next update.
+    pub fn send(&self, patch: SidebarPatch) {
+        let _ = self.tx.send(patch);
+    }
+
+    /// Replace the items of a section.
+    pub fn set_items(&self, section: impl Into<String>, items: Vec<SidebarItem>) {
+        self.send(SidebarPatch::SetItems { section: section.into(), items });
+    }
+}
+
+/// Actions from sidebar item context menus that the embedder has to carry out.
+///
+/// Renaming and removing bookmarks is handled by the sidebar itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidebarItemAction {
+    /// Open the item's location in a new tab.
+    OpenInNewTab,
+    /// Mount the device.
+    Mount,
+    /// Unmount the device.
+    Unmount,
+    /// Eject the device.
+    Eject,
+    /// Show the properties of the device.
+    Properties,
+}
+
+/// The item a context menu was requested for.
+#[derive(Debug, Clone)]
+pub struct SidebarItemContext {
+    /// Id of the section containing the item (see [SidebarPatch]).
+    pub section: String,
+    pub item: SidebarItem,
+}
+
+/// Called when an item's context menu opens; returned items are appended to the menu.
+pub type SidebarContextMenuCallback =
+    Box<dyn Fn(&SidebarItemContext) -> Vec<MenuItem> + Send + Sync>;
+
+/// A built section together with its id and title.
+#[derive(Debug, Clone)]
+struct SectionEntry {
+    id: String,
+    title: String,
+    section: SidebarSection,
+}
+
 /// A reusable file manager sidebar widget.
 ///
 /// Provides Places (user directories), Bookmarks, Devices, and custom sections.
 /// Uses a channel for navigation events to ensure Send+Sync compatibility.
+/// Sections can be patched at runtime through [SidebarUpdater] without re-reading
+/// the configuration.
 pub struct FilemanSidebar {
     inner: Sidebar,
     config: FilemanSidebarConfig,
+    sections: Vec<SectionEntry>,
     navigation_tx: mpsc::UnboundedSender<PathBuf>,
     navigation_rx: Option<mpsc::UnboundedReceiver<PathBuf>>,
+    patch_tx: mpsc::UnboundedSender<SidebarPatch>,
+    patch_rx: mpsc::UnboundedReceiver<SidebarPatch>,
+    action_tx: mpsc::UnboundedSender<(SidebarItemAction, SidebarItemContext)>,
+    action_rx: Option<mpsc::UnboundedReceiver<(SidebarItemAction, SidebarItemContext)>>,
+    on_context_menu: Option<SidebarContextMenuCallback>,
+    pending_bookmark_rename: Arc<Mutex<Option<SidebarItemContext>>>,
+    pending_bookmark_remove: Arc<Mutex<Option<SidebarItemContext>>>,
+    bookmark_rename: Option<(String, Arc<Mutex<Option<String>>>)>,
+    recent_locations: Option<StateSignal<Vec<PathBuf>>>,
+    recent_max_items: usize,
+    shown_recent: Vec<PathBuf>,
+    signals_hooked: bool,
     bookmarks_service: Option<BookmarksService>,
     layout_style: MaybeSignal<LayoutStyle>,
+    visible: StateSignal<bool>,
+    /// Id of the item last picked, for the accessibility tree
+    selected_item: Arc<Mutex<Option<String>>>,
+    /// The item the inner sidebar last requested a context menu for
+    context_menu_item: Arc<Mutex<Option<SidebarItem>>>,
+    accessibility: AccessibleSubtree,
+    focus: Option<FocusChain>,
 }
 
 impl FilemanSidebar {
     /// Create a new FilemanSidebar with default configuration.
     pub fn new() -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
+        let (patch_tx, patch_rx) = mpsc::unbounded_channel();
+        let (action_tx, action_rx) = mpsc::unbounded_channel();
         let config = FilemanSidebarConfig::default();
         
         // Build sections based on config (synchronous - user dirs will be loaded later)
         let sections = Self::build_sections(&config, tx.clone());
-        
-        // Set up navigation callback
-        let nav_tx_clone = tx.clone();
-        let mut sidebar = Sidebar::new()
-            .with_on_item_selected(move |item| {
-                if let Some(ref uri) = item.uri {
-                    // Extract path from file:// URI
-                    if let Some(path) = uri_to_path(uri) {
-                        let _ = nav_tx_clone.send(path);
-                        return Update::EVAL | Update::LAYOUT | Update::DRAW;
-                    }
-                }
-                Update::empty()
-            });
-        
-        // Add sections to sidebar
-        for section in sections {
-            sidebar = sidebar.with_section(section);
-        }
+        let selected_item = Arc::new(Mutex::new(None));
+        let context_menu_item = Arc::new(Mutex::new(None));
+        let inner = Self::build_inner(&sections, tx.clone(), selected_item.clone(), context_menu_item.clone());
 
         Self {
-            inner: sidebar,
+            inner,
             config,
+            sections,
             navigation_tx: tx,
             navigation_rx: Some(rx),
+            patch_tx,
+            patch_rx,
+            action_tx,
+            action_rx: Some(action_rx),
+            on_context_menu: None,
+            pending_bookmark_rename: Arc::new(Mutex::new(None)),
+            pending_bookmark_remove: Arc::new(Mutex::new(None)),
+            bookmark_rename: None,
+            recent_locations: None,
+            recent_max_items: 0,
+            shown_recent: Vec::new(),
+            signals_hooked: false,
             bookmarks_service: None,
             layout_style: LayoutStyle {
                 size: Vector2::new(Dimension::length(200.0), Dimension::percent(1.0)),
@@ -104,9 +228,50 @@ impl FilemanSidebar {
                 ..Default::default()
             }
             .into(),
+            visible: StateSignal::new(true),
+            selected_item,
+            context_menu_item,
+            accessibility: AccessibleSubtree::new(),
+            focus: None,
         }
     }
 
+    /// Create the inner Sidebar from built sections, wiring the navigation and
+    /// context menu callbacks.
+    fn build_inner(
+        sections: &[SectionEntry],
+        nav_tx: mpsc::UnboundedSender<PathBuf>,
+        selected_item: Arc<Mutex<Option<String>>>,
+        context_menu_item: Arc<Mutex<Option<SidebarItem>>>,
+    ) -> Sidebar {
+        let mut sidebar = Sidebar::new()
+            .with_on_item_selected(move |item| {
+                if let Ok(mut selected) = selected_item.lock() {
+                    *selected = Some(item.id.clone());
+                }
+                if let Some(ref uri) = item.uri {
+                    // Extract path from file:// URI
+                    if let Some(path) = uri_to_path(uri) {
+                        let _ = nav_tx.send(path);
+                        return Update::EVAL | Update::LAYOUT | Update::DRAW;
+                    }
+                }
+                Update::empty()
+            })
+            .with_on_item_context_menu(move |item| {
+                // Shown by FilemanSidebar::update, which knows the item's section
+                if let Ok(mut pending) = context_menu_item.lock() {
+                    *pending = Some(item.clone());
+                }
+                Update::DRAW
+            });
+
+        for entry in sections {
+            sidebar = sidebar.with_section(entry.section.clone());
+        }
+        sidebar
+    }
+
     fn apply_with(mut self, f: impl FnOnce(&mut Self)) -> Self {
         f(&mut self);
         self
@@ -137,8 +302,45 @@ impl FilemanSidebar {
     }
 
     /// Set which user directories to show in Places section.
+    ///
+    /// Other Places entries keep their position; the directories are placed after Home.
     pub fn with_user_directories(mut self, dirs: Vec<UserDirectory>) -> Self {
-        self.config.user_directories = dirs;
+        self.config
+            .places
+            .retain(|entry| !matches!(entry, PlacesEntry::UserDirectory(_)));
+        let insert_at = self
+            .config
+            .places
+            .iter()
+            .position(|entry| matches!(entry, PlacesEntry::Home))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        self.config.places.splice(
+            insert_at..insert_at,
+            dirs.into_iter().map(PlacesEntry::UserDirectory),
+        );
+        self.rebuild_sidebar();
+        self
+    }
+
+    /// Set the Places entries and their order.
+    pub fn with_places_order(mut self, entries: Vec<PlacesEntry>) -> Self {
+        self.config.places = entries;
+        self.rebuild_sidebar();
+        self
+    }
+
+    /// Insert an item into the Places section at `position` (clamped to the end).
+    pub fn with_places_item_at(mut self, position: usize, item: SidebarItem) -> Self {
+        let position = position.min(self.config.places.len());
+        self.config.places.insert(position, PlacesEntry::Item(item));
+        self.rebuild_sidebar();
+        self
+    }
+
+    /// Override the icon of a Places item by id (see [PlacesEntry] for the ids).
+    pub fn with_item_icon(mut self, item_id: impl Into<String>, icon: impl Into<String>) -> Self {
+        self.config.icon_overrides.insert(item_id.into(), icon.into());
         self.rebuild_sidebar();
         self
     }
@@ -150,6 +352,40 @@ impl FilemanSidebar {
         self
     }
 
+    /// Add a custom section with an id, so it can be patched at runtime.
+    pub fn with_custom_section_id(
+        mut self,
+        id: impl Into<String>,
+        title: impl Into<String>,
+        items: Vec<SidebarItem>,
+    ) -> Self {
+        self.apply_patch(SidebarPatch::SetSection {
+            id: id.into(),
+            title: title.into(),
+            items,
+        });
+        self
+    }
+
+    /// Show a "Recent" section listing up to `max_items` locations from `locations`
+    /// (most recent first). The section updates whenever the signal changes.
+    pub fn with_recent_locations(mut self, locations: StateSignal<Vec<PathBuf>>, max_items: usize) -> Self {
+        self.recent_locations = Some(locations);
+        self.recent_max_items = max_items;
+        self
+    }
+
+    /// Drive the sidebar visibility from an external signal.
+    pub fn with_visible_signal(mut self, visible: StateSignal<bool>) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    /// Width of the sidebar when visible.
+    pub fn width(&self) -> f32 {
+        self.config.width
+    }
+
     /// Set the width of the sidebar.
     pub fn with_width(mut self, width: f32) -> Self {
         self.apply_with(|s| {
@@ -176,10 +412,31 @@ impl FilemanSidebar {
         self.navigation_rx.take()
     }
 
+    /// Add items to the context menu of sidebar items.
+    ///
+    /// Bookmarks get Open in New Tab, Rename and Remove; devices get Mount, Unmount,
+    /// Eject and Properties. Items returned by the callback are appended after those.
+    pub fn with_on_item_context_menu<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&SidebarItemContext) -> Vec<MenuItem> + Send + Sync + 'static,
+    {
+        self.on_context_menu = Some(Box::new(callback));
+        self
+    }
+
+    /// Get the receiver end of the item action channel.
+    ///
+    /// This consumes the receiver. Poll it to handle actions picked from item
+    /// context menus.
+    pub fn take_action_receiver(
+        &mut self,
+    ) -> Option<mpsc::UnboundedReceiver<(SidebarItemAction, SidebarItemContext)>> {
+        self.action_rx.take()
+    }
+
     /// Reload bookmarks from disk asynchronously.
     ///
     /// This will update the Bookmarks section if it's enabled.
-    /// Note: This requires rebuilding the sidebar sections.
     pub async fn reload_bookmarks(&mut self) -> Result<(), String> {
         if !self.config.show_bookmarks {
             return Ok(());
@@ -192,136 +449,487 @@ impl FilemanSidebar {
             .await
             .map_err(|e| format!("Failed to load bookmarks: {}", e))?;
 
-        // TODO: Rebuild sidebar sections to include updated bookmarks
-        // This requires a way to update the inner Sidebar's sections
+        let items = bookmarks::items(service, self.config.use_symbolic_icons);
+        self.apply_patch(SidebarPatch::SetSection {
+            id: "bookmarks".to_string(),
+            title: "Bookmarks".to_string(),
+            items,
+        });
         Ok(())
     }
 
-    /// Rebuild the sidebar with current configuration.
-    /// This is called when configuration changes via builder methods.
-    fn rebuild_sidebar(&mut self) {
-        // Note: Sidebar doesn't support modifying sections after creation easily
-        // For now, we rebuild the entire sidebar. This is called when builder methods change config.
-        let sections = Self::build_sections(&self.config, self.navigation_tx.clone());
-        
-        // Clone the sender for the callback
-        let nav_tx_for_callback = self.navigation_tx.clone();
-        
-        // Recreate sidebar with new sections and callback
-        let mut new_sidebar = Sidebar::new()
-            .with_on_item_selected(move |item| {
-                if let Some(ref uri) = item.uri {
-                    if let Some(path) = uri_to_path(uri) {
-                        let _ = nav_tx_for_callback.send(path);
-                        return Update::EVAL | Update::LAYOUT | Update::DRAW;
-                    }
+    /// Load bookmarks in a background task and patch the Bookmarks section when done.
+    ///
+    /// Unlike [Self::reload_bookmarks] this does not borrow the sidebar, so it can be
+    /// called right after construction.
+    pub fn spawn_bookmarks_reload(&self) {
+        if !self.config.show_bookmarks {
+            return;
+        }
+        let updater = self.updater();
+        let use_symbolic_icons = self.config.use_symbolic_icons;
+        tokio::spawn(async move {
+            let items = bookmarks::load_items(use_symbolic_icons).await;
+            updater.send(SidebarPatch::SetSection {
+                id: "bookmarks".to_string(),
+                title: "Bookmarks".to_string(),
+                items,
+            });
+        });
+    }
+
+    /// Get a handle for patching sections at runtime (e.g. from async tasks).
+    pub fn updater(&self) -> SidebarUpdater {
+        SidebarUpdater { tx: self.patch_tx.clone() }
+    }
+
+    /// Apply a patch to the sections and to the matching section of the inner
+    /// sidebar. The other sections, selection and collapse state are kept.
+    pub fn apply_patch(&mut self, patch: SidebarPatch) {
+        match patch {
+            SidebarPatch::SetItems { section, items } => {
+                let Some(index) = self.sections.iter().position(|e| e.id == section) else {
+                    log::debug!("Ignoring items for unknown sidebar section '{}'", section);
+                    return;
+                };
+                let entry = &mut self.sections[index];
+                entry.section = SidebarSection::new(entry.title.clone()).with_items(items.clone());
+                self.inner.set_section_items(index, items);
+            },
+            SidebarPatch::SetSection { id, title, items } => {
+                let section = SidebarSection::new(title.clone()).with_items(items);
+                if let Some(index) = self.sections.iter().position(|e| e.id == id) {
+                    let entry = &mut self.sections[index];
+                    entry.title = title;
+                    entry.section = section.clone();
+                    self.inner.set_section(index, section);
+                } else {
+                    self.inner.add_section(section.clone());
+                    self.sections.push(SectionEntry { id, title, section });
                 }
-                Update::empty()
+            },
+            SidebarPatch::RemoveSection(id) => {
+                if let Some(index) = self.sections.iter().position(|e| e.id == id) {
+                    self.sections.remove(index);
+                    self.inner.remove_section(index);
+                }
+            },
+        }
+    }
+
+    /// Patch the Recent section if the recent locations changed.
+    fn sync_recent_section(&mut self) -> bool {
+        let Some(signal) = &self.recent_locations else {
+            return false;
+        };
+        let recent: Vec<PathBuf> = signal.get().iter().take(self.recent_max_items).cloned().collect();
+        if recent == self.shown_recent {
+            return false;
+        }
+
+        let icon = if self.config.use_symbolic_icons { "folder-symbolic" } else { "folder" };
+        let items: Vec<SidebarItem> = recent
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let label = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string());
+                SidebarItem::new(format!("recent-{}", i), label)
+                    .with_icon(icon)
+                    .with_uri(format!("file://{}", path.display()))
+            })
+            .collect();
+
+        if items.is_empty() {
+            self.apply_patch(SidebarPatch::RemoveSection("recent".to_string()));
+        } else {
+            self.apply_patch(SidebarPatch::SetSection {
+                id: "recent".to_string(),
+                title: "Recent".to_string(),
+                items,
             });
-        
-        for section in sections {
-            new_sidebar = new_sidebar.with_section(section);
         }
-        
-        self.inner = new_sidebar;
+        self.shown_recent = recent;
+        true
+    }
+
+    /// Join the window's focus chain: while focused, Up and Down move between the
+    /// items and Return opens the current one.
+    pub fn with_focus_chain(mut self, focus: FocusChain) -> Self {
+        self.focus = Some(focus);
+        self
+    }
+
+    /// Take focus when clicked and handle the keys sent to the sidebar while focused.
+    fn handle_focus(&mut self, layout: &LayoutNode, info: &AppInfo) -> Update {
+        let Some(focus) = self.focus.clone() else {
+            return Update::empty();
+        };
+        let mut update = Update::empty();
+        if focus.focus_on_click(Pane::Sidebar, layout, info) {
+            update.insert(Update::DRAW);
+        }
+        for key in focus.take_keys(Pane::Sidebar) {
+            update |= match key {
+                PaneKey::Up => self.move_cursor(-1),
+                PaneKey::Down => self.move_cursor(1),
+                PaneKey::Activate => self.activate_cursor(),
+            };
+        }
+        update
+    }
+
+    /// Make the item `delta` rows away from the current one current.
+    fn move_cursor(&mut self, delta: isize) -> Update {
+        let items: Vec<&SidebarItem> = self
+            .sections
+            .iter()
+            .flat_map(|e| e.section.items.iter())
+            .collect();
+        if items.is_empty() {
+            return Update::empty();
+        }
+        let Ok(mut selected) = self.selected_item.lock() else {
+            return Update::empty();
+        };
+        let current = selected
+            .as_ref()
+            .and_then(|id| items.iter().position(|item| item.id == *id));
+        let index = match current {
+            Some(index) => index.saturating_add_signed(delta).min(items.len() - 1),
+            None if delta < 0 => items.len() - 1,
+            None => 0,
+        };
+        *selected = Some(items[index].id.clone());
+        Update::DRAW
+    }
+
+    /// Open the current item, as clicking it does.
+    fn activate_cursor(&mut self) -> Update {
+        let selected = self.selected_item.lock().ok().and_then(|s| s.clone());
+        let path = self
+            .sections
+            .iter()
+            .flat_map(|e| e.section.items.iter())
+            .find(|item| selected.as_deref() == Some(item.id.as_str()))
+            .and_then(|item| item.uri.as_deref())
+            .and_then(uri_to_path);
+        match path {
+            Some(path) => {
+                let _ = self.navigation_tx.send(path);
+                Update::EVAL | Update::LAYOUT | Update::DRAW
+            },
+            None => Update::empty(),
+        }
+    }
+
+    /// Outline the current item and the sidebar while it has focus.
+    fn render_focus(&self, graphics: &mut dyn Graphics, layout: &LayoutNode, palette: &Palette) {
+        let Some(focus) = &self.focus else {
+            return;
+        };
+        focus.render_ring(Pane::Sidebar, graphics, layout, palette);
+        if !focus.is_focused(Pane::Sidebar) {
+            return;
+        }
+        let (Some(inner_layout), Some(selected)) = (
+            layout.children.first(),
+            self.selected_item.lock().ok().and_then(|s| s.clone()),
+        ) else {
+            return;
+        };
+        let mut top = 0.0;
+        for entry in &self.sections {
+            top += SECTION_HEADER_HEIGHT;
+            for item in &entry.section.items {
+                if item.id == selected {
+                    let x = inner_layout.layout.location.x as f64;
+                    let y = (inner_layout.layout.location.y + top) as f64;
+                    let row = RoundedRect::new(
+                        x + 4.0,
+                        y + 1.0,
+                        x + inner_layout.layout.size.width as f64 - 4.0,
+                        y + ITEM_HEIGHT as f64 - 1.0,
+                        3.0,
+                    );
+                    graphics.stroke(
+                        &Stroke::new(1.0),
+                        Affine::IDENTITY,
+                        &Brush::Solid(color_scheme::color(palette, ColorRole::Selection)),
+                        None,
+                        &row.into_path(0.1),
+                    );
+                    return;
+                }
+                top += ITEM_HEIGHT;
+            }
+        }
+    }
+
+    /// Handle to the accessibility tree of the sidebar: its sections and items.
+    /// Take it before adding the sidebar to the layout.
+    pub fn accessibility(&self) -> AccessibleSubtree {
+        self.accessibility.clone()
+    }
+
+    fn publish_accessibility(&self) {
+        let selected = self.selected_item.lock().ok().and_then(|s| s.clone());
+        let sections = self
+            .sections
+            .iter()
+            .map(|entry| {
+                let items = entry
+                    .section
+                    .items
+                    .iter()
+                    .map(|item| {
+                        AccessibleNode::new(AccessibleRole::ListItem, item.label.clone())
+                            .with_selected(selected.as_deref() == Some(item.id.as_str()))
+                    })
+                    .collect();
+                AccessibleNode::new(AccessibleRole::Group, entry.title.clone()).with_children(items)
+            })
+            .collect();
+        self.accessibility.set(
+            AccessibleNode::new(AccessibleRole::Navigation, "Places").with_children(sections),
+        );
+    }
+
+    /// The item `clicked`, with the id of its section. Ids are only unique
+    /// within a section, so its location and label have to match as well.
+    fn item_context(&self, clicked: &SidebarItem) -> Option<SidebarItemContext> {
+        self.sections.iter().find_map(|entry| {
+            let item = entry.section.items.iter().find(|item| {
+                item.id == clicked.id && item.uri == clicked.uri && item.label == clicked.label
+            })?;
+            Some(SidebarItemContext {
+                section: entry.id.clone(),
+                item: item.clone(),
+            })
+        })
+    }
+
+    fn action_item(&self, id: u32, label: &str, action: SidebarItemAction, target: &SidebarItemContext) -> MenuItem {
+        let tx = self.action_tx.clone();
+        let target = target.clone();
+        MenuItem::new(MenuCommand::Custom(id), label).with_action(move || {
+            let _ = tx.send((action, target.clone()));
+            Update::DRAW
+        })
+    }
+
+    fn pending_item(
+        id: u32,
+        label: &str,
+        pending: &Arc<Mutex<Option<SidebarItemContext>>>,
+        target: &SidebarItemContext,
+    ) -> MenuItem {
+        let pending = pending.clone();
+        let target = target.clone();
+        MenuItem::new(MenuCommand::Custom(id), label).with_action(move || {
+            if let Ok(mut pending) = pending.lock() {
+                *pending = Some(target.clone());
+            }
+            Update::DRAW
+        })
+    }
+
+    /// Build the context menu for an item, or `None` if there is nothing to show.
+    fn build_context_menu(&self, target: &SidebarItemContext) -> Option<MenuTemplate> {
+        let mut items = match target.section.as_str() {
+            "bookmarks" => vec![
+                self.action_item(0x6001, "Open in New Tab", SidebarItemAction::OpenInNewTab, target),
+                MenuItem::separator(),
+                Self::pending_item(0x6002, "Rename", &self.pending_bookmark_rename, target),
+                Self::pending_item(0x6003, "Remove", &self.pending_bookmark_remove, target),
+            ],
+            "devices" => vec![
+                self.action_item(0x6004, "Mount", SidebarItemAction::Mount, target),
+                self.action_item(0x6005, "Unmount", SidebarItemAction::Unmount, target),
+                self.action_item(0x6006, "Eject", SidebarItemAction::Eject, target),
+                MenuItem::separator(),
+                self.action_item(0x6007, "Properties", SidebarItemAction::Properties, target),
+            ],
+            _ if target.item.uri.is_some() => vec![
+                self.action_item(0x6001, "Open in New Tab", SidebarItemAction::OpenInNewTab, target),
+            ],
+            _ => Vec::new(),
+        };
+
+        if let Some(callback) = &self.on_context_menu {
+            let extra = callback(target);
+            if !extra.is_empty() {
+                if !items.is_empty() {
+                    items.push(MenuItem::separator());
+                }
+                items.extend(extra);
+            }
+        }
+
+        if items.is_empty() {
+            None
+        } else {
+            Some(MenuTemplate::from_items("sidebar_item_context", items))
+        }
+    }
+
+    /// Start renaming or removing bookmarks picked from the context menu, and apply
+    /// a confirmed rename.
+    fn process_bookmark_requests(&mut self, context: &AppContext) -> bool {
+        let mut changed = false;
+
+        let remove = self.pending_bookmark_remove.lock().ok().and_then(|mut p| p.take());
+        if let Some(target) = remove.and_then(|t| t.item.uri) {
+            let updater = self.updater();
+            let use_symbolic_icons = self.config.use_symbolic_icons;
+            tokio::spawn(async move {
+                if let Err(e) = bookmarks::remove(&target).await {
+                    log::warn!("{}", e);
+                }
+                updater.send(SidebarPatch::SetItems {
+                    section: "bookmarks".to_string(),
+                    items: bookmarks::load_items(use_symbolic_icons).await,
+                });
+            });
+            changed = true;
+        }
+
+        let rename = self.pending_bookmark_rename.lock().ok().and_then(|mut p| p.take());
+        if let Some(target) = rename {
+            if let Some(uri) = target.item.uri.clone() {
+                let result = Arc::new(Mutex::new(None));
+                let popup = BookmarkRenamePopup::new(target.item.label.clone(), result.clone());
+                context
+                    .popup_manager
+                    .create_popup_at(Box::new(popup), "Rename Bookmark", (360, 130), (300, 200));
+                self.bookmark_rename = Some((uri, result));
+                changed = true;
+            }
+        }
+
+        let confirmed = self
+            .bookmark_rename
+            .as_ref()
+            .and_then(|(uri, result)| result.lock().ok().and_then(|mut r| r.take()).map(|l| (uri.clone(), l)));
+        if let Some((uri, label)) = confirmed {
+            self.bookmark_rename = None;
+            let updater = self.updater();
+            let use_symbolic_icons = self.config.use_symbolic_icons;
+            tokio::spawn(async move {
+                if let Err(e) = bookmarks::rename(&uri, &label).await {
+                    log::warn!("{}", e);
+                }
+                updater.send(SidebarPatch::SetItems {
+                    section: "bookmarks".to_string(),
+                    items: bookmarks::load_items(use_symbolic_icons).await,
+                });
+            });
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// Rebuild the sidebar with current configuration.
+    /// This is called when configuration changes via builder methods.
+    fn rebuild_sidebar(&mut self) {
+        // Keep sections added at runtime that are not derived from the config
+        let runtime_sections: Vec<SectionEntry> = self
+            .sections
+            .iter()
+            .filter(|e| !Self::is_config_section(&e.id))
+            .cloned()
+            .collect();
+
+        self.sections = Self::build_sections(&self.config, self.navigation_tx.clone());
+        self.sections.extend(runtime_sections);
+        self.inner = Self::build_inner(
+            &self.sections,
+            self.navigation_tx.clone(),
+            self.selected_item.clone(),
+            self.context_menu_item.clone(),
+        );
+    }
+
+    /// Whether a section id is produced by [Self::build_sections].
+    fn is_config_section(id: &str) -> bool {
+        matches!(id, "places" | "bookmarks" | "devices") || id.starts_with("custom-")
     }
 
     /// Build sections based on configuration.
     fn build_sections(
         config: &FilemanSidebarConfig,
         _nav_tx: mpsc::UnboundedSender<PathBuf>,
-    ) -> Vec<SidebarSection> {
+    ) -> Vec<SectionEntry> {
         let mut sections = Vec::new();
+        let mut push = |id: String, title: &str, section: SidebarSection| {
+            sections.push(SectionEntry { id, title: title.to_string(), section });
+        };
 
         // Places section
         if config.show_places {
             if let Some(places_section) = Self::build_places_section(config) {
-                sections.push(places_section);
+                push("places".to_string(), "Places", places_section);
             }
         }
 
         // Bookmarks section
         if config.show_bookmarks {
             if let Some(bookmarks_section) = Self::build_bookmarks_section(config) {
-                sections.push(bookmarks_section);
+                push("bookmarks".to_string(), "Bookmarks", bookmarks_section);
             }
         }
 
         // Custom sections
-        sections.extend(config.custom_sections.clone());
+        for (i, section) in config.custom_sections.iter().enumerate() {
+            push(format!("custom-{}", i), "", section.clone());
+        }
 
-        // Devices section (placeholder for now)
+        // Devices section, filled by the embedder through the SidebarUpdater
         if config.show_devices {
-            sections.push(SidebarSection::new("Devices"));
+            push("devices".to_string(), "Devices", SidebarSection::new("Devices"));
         }
 
         sections
     }
 
-    /// Build the Places section with user directories.
+    /// Build the Places section from the configured entries.
     /// Note: User directories are loaded synchronously using blocking approach.
     /// This works because we're in a tokio runtime context from #[tokio::main].
     fn build_places_section(config: &FilemanSidebarConfig) -> Option<SidebarSection> {
+        let icon_for = |id: &str, default: String| {
+            config.icon_overrides.get(id).cloned().unwrap_or(default)
+        };
         let mut items = Vec::new();
 
-        // Home directory - use env var directly to avoid requiring npio backend
-        let home_path = std::env::var("HOME")
-            .ok()
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("/"));
-
-        let home_icon = get_home_icon_name(config.use_symbolic_icons);
-        log::debug!("Home icon name: '{}'", home_icon);
-        items.push(
-            SidebarItem::new("home", "Home")
-                .with_icon(home_icon)
-                .with_uri(format!("file://{}", home_path.display())),
-        );
+        for entry in &config.places {
+            match entry {
+                PlacesEntry::Home => {
+                    // Home directory - from the environment, to avoid requiring npio backend
+                    let home_path = crate::xdg::home();
 
-        // User directories - load synchronously using tokio runtime handle
-        // This works because we're in a tokio runtime context from #[tokio::main].
-        // We use block_in_place + block_on to safely convert async call to sync during widget construction.
-        // Use get_user_special_dir_path instead of get_user_special_file to avoid requiring npio backend
-        for dir_type in &config.user_directories {
-            // Use block_in_place to move to a blocking thread, then block_on the async call
-            // This prevents blocking the async runtime if we're already on an async thread
-            let path_result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::try_current()
-                    .map(|handle| {
-                        handle.block_on(async {
-                            get_user_special_dir_path(*dir_type).await
-                        })
-                    })
-                    .unwrap_or_else(|_| {
-                        // If no runtime available (shouldn't happen in normal execution),
-                        // return None so we skip this directory
-                        log::warn!("No tokio runtime available for loading user directory {:?}", dir_type);
-                        None
-                    })
-            });
-            
-            if let Some(path) = path_result {
-                let uri = format!("file://{}", path.display());
-                let label = match dir_type {
-                    UserDirectory::Desktop => "Desktop",
-                    UserDirectory::Documents => "Documents",
-                    UserDirectory::Download => "Downloads",
-                    UserDirectory::Music => "Music",
-                    UserDirectory::Pictures => "Pictures",
-                    UserDirectory::Videos => "Videos",
-                    UserDirectory::PublicShare => "Public",
-                    UserDirectory::Templates => "Templates",
-                };
-                let icon = get_directory_icon_name(*dir_type, config.use_symbolic_icons);
-                log::debug!("Adding sidebar item: {} with icon '{}' and path {:?}", label, icon, path);
-
-                items.push(
-                    SidebarItem::new(format!("{:?}", dir_type).to_lowercase(), label)
-                        .with_icon(icon)
-                        .with_uri(uri),
-                );
-            } else {
-                log::warn!("User directory {:?} not found or could not be loaded", dir_type);
+                    let home_icon = icon_for("home", get_home_icon_name(config.use_symbolic_icons).to_string());
+                    log::debug!("Home icon name: '{}'", home_icon);
+                    items.push(
+                        SidebarItem::new("home", "Home")
+                            .with_icon(home_icon)
+                            .with_uri(format!("file://{}", home_path.display())),
+                    );
+                },
+                PlacesEntry::UserDirectory(dir_type) => {
+                    if let Some(item) = Self::build_user_directory_item(config, *dir_type, &icon_for) {
+                        items.push(item);
+                    }
+                },
+                PlacesEntry::Item(item) => {
+                    let item = match config.icon_overrides.get(&item.id) {
+                        Some(icon) => item.clone().with_icon(icon.clone()),
+                        None => item.clone(),
+                    };
+                    items.push(item);
+                },
             }
         }
 
@@ -332,6 +940,54 @@ impl FilemanSidebar {
         }
     }
 
+    /// Build the item for a user directory, or `None` if it doesn't exist.
+    fn build_user_directory_item(
+        config: &FilemanSidebarConfig,
+        dir_type: UserDirectory,
+        icon_for: &impl Fn(&str, String) -> String,
+    ) -> Option<SidebarItem> {
+        // User directories - load synchronously using tokio runtime handle
+        // We use block_in_place + block_on to safely convert async call to sync during widget construction.
+        // Use get_user_special_dir_path instead of get_user_special_file to avoid requiring npio backend
+        // This prevents blocking the async runtime if we're already on an async thread
+        let path_result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::try_current()
+                .map(|handle| {
+                    handle.block_on(async {
+                        get_user_special_dir_path(dir_type).await
+                    })
+                })
+                .unwrap_or_else(|_| {
+                    // If no runtime available (shouldn't happen in normal execution),
+                    // return None so we skip this directory
+                    log::warn!("No tokio runtime available for loading user directory {:?}", dir_type);
+                    None
+                })
+        });
+
+        let Some(path) = path_result else {
+            log::warn!("User directory {:?} not found or could not be loaded", dir_type);
+            return None;
+        };
+
+        let uri = format!("file://{}", path.display());
+        let label = match dir_type {
+            UserDirectory::Desktop => "Desktop",
+            UserDirectory::Documents => "Documents",
+            UserDirectory::Download => "Downloads",
+            UserDirectory::Music => "Music",
+            UserDirectory::Pictures => "Pictures",
+            UserDirectory::Videos => "Videos",
+            UserDirectory::PublicShare => "Public",
+            UserDirectory::Templates => "Templates",
+        };
+        let id = format!("{:?}", dir_type).to_lowercase();
+        let icon = icon_for(&id, get_directory_icon_name(dir_type, config.use_symbolic_icons).to_string());
+        log::debug!("Adding sidebar item: {} with icon '{}' and path {:?}", label, icon, path);
+
+        Some(SidebarItem::new(id, label).with_icon(icon).with_uri(uri))
+    }
+
     /// Build the Bookmarks section.
     /// Returns None if bookmarks cannot be loaded or are empty.
     /// Note: Bookmark loading may be deferred to avoid blocking during widget construction.
@@ -341,12 +997,8 @@ impl FilemanSidebar {
         // widget tree construction which may be in a tokio runtime context. Using
         // smol::block_on() or tokio::block_on() here can cause deadlocks.
         //
-        // Solution: Bookmarks should be loaded asynchronously after widget creation.
-        // For now, return None - the bookmarks section will be empty initially.
-        // TODO: Implement proper async bookmark loading that:
-        //   1. Creates sidebar with empty bookmarks section initially
-        //   2. Spawns async task to load bookmarks
-        //   3. Updates sidebar sections when bookmarks are loaded
+        // Bookmarks are loaded asynchronously after widget creation through
+        // reload_bookmarks(), which patches the section in place.
         log::debug!("Bookmarks section loading deferred to avoid blocking during construction");
         None
     }
@@ -361,8 +1013,12 @@ impl Default for FilemanSidebar {
 #[async_trait(?Send)]
 impl Widget for FilemanSidebar {
     fn layout_style(&self, _context: &LayoutContext) -> StyleNode {
+        let mut style = self.layout_style.get().clone();
+        if !*self.visible.get() {
+            style.size.x = Dimension::length(0.0);
+        }
         StyleNode {
-            style: self.layout_style.get().clone(),
+            style,
             children: vec![self.inner.layout_style(_context)],
             measure_func: None,
         }
@@ -374,15 +1030,60 @@ impl Widget for FilemanSidebar {
         context: AppContext,
         info: &mut AppInfo,
     ) -> Update {
-        // Handle navigation events from channel
-        // Note: The receiver should be taken and polled externally, but we can check here too
-        // For now, just delegate to inner sidebar
-        
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            if let Some(signal) = self.recent_locations.as_mut() {
+                context.hook_signal(signal);
+            }
+            context.hook_signal(&mut self.visible);
+            self.signals_hooked = true;
+        }
+
+        if self.sync_recent_section() {
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        // Apply section patches queued through SidebarUpdater
+        let mut patches = Vec::new();
+        while let Ok(patch) = self.patch_rx.try_recv() {
+            patches.push(patch);
+        }
+        if !patches.is_empty() {
+            for patch in patches {
+                self.apply_patch(patch);
+            }
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        if self.process_bookmark_requests(&context) {
+            update.insert(Update::DRAW);
+        }
+
+        // Hidden: sections are kept current, but there is nothing to interact with
+        if !*self.visible.get() {
+            return update;
+        }
+
+        update |= self.handle_focus(layout, info);
+
+        // Navigation events are polled externally through take_navigation_receiver()
         if !layout.children.is_empty() {
-            self.inner.update(&layout.children[0], context, info).await
-        } else {
-            Update::empty()
+            update |= self.inner.update(&layout.children[0], context.clone(), info).await;
         }
+
+        // Open the context menu of the item the inner sidebar was right-clicked on
+        let requested = self.context_menu_item.lock().ok().and_then(|mut p| p.take());
+        if let (Some(item), Some(cursor)) = (requested, info.cursor_pos) {
+            if !context.menu_manager.is_open() {
+                if let Some(menu) = self.item_context(&item).and_then(|t| self.build_context_menu(&t)) {
+                    context.menu_manager.show(menu, Point::new(cursor.x, cursor.y));
+                    update.insert(Update::DRAW);
+                }
+            }
+        }
+        self.publish_accessibility();
+        update
     }
 
     fn render(
@@ -392,9 +1093,13 @@ impl Widget for FilemanSidebar {
         info: &mut AppInfo,
         context: AppContext,
     ) {
+        if !*self.visible.get() {
+            return;
+        }
         if !layout.children.is_empty() {
-            self.inner.render(graphics, &layout.children[0], info, context);
+            self.inner.render(graphics, &layout.children[0], info, context.clone());
         }
+        self.render_focus(graphics, layout, context.palette());
     }
 }
 
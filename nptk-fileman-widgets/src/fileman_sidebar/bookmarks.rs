@@ -0,0 +1,139 @@
+//! GTK bookmarks file access (`~/.config/gtk-3.0/bookmarks`).
+//!
+//! Each line is `file:///path` optionally followed by a space and a label.
+//! Bookmarks are read through [BookmarksService]; renaming and removing edit
+//! the file directly.
+
+use crate::xdg;
+use nptk::services::bookmarks::BookmarksService;
+use nptk::services::thumbnail::npio_adapter::uri_to_path;
+use nptk::widgets::sidebar::SidebarItem;
+use std::path::PathBuf;
+
+fn bookmarks_file() -> PathBuf {
+    xdg::config_home().join("gtk-3.0").join("bookmarks")
+}
+
+/// Split a bookmarks line into URI and optional label.
+fn parse_line(line: &str) -> (&str, Option<&str>) {
+    match line.split_once(' ') {
+        Some((uri, label)) => (uri, Some(label)),
+        None => (line, None),
+    }
+}
+
+/// `label` without control characters such as line breaks and tabs.
+fn clean_label(label: &str) -> String {
+    label.chars().filter(|c| !c.is_control()).collect()
+}
+
+async fn read_lines() -> Vec<String> {
+    tokio::fs::read_to_string(bookmarks_file())
+        .await
+        .map(|content| {
+            content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn write_lines(lines: &[String]) -> Result<(), String> {
+    let path = bookmarks_file();
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create bookmarks directory: {}", e))?;
+    }
+    let mut content = lines.join("\n");
+    content.push('\n');
+    // Written next to the file first, so other programs never read half of it
+    let partial = path.with_file_name(".bookmarks.fileman-tmp");
+    if let Err(e) = tokio::fs::write(&partial, content).await {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(format!("Failed to write bookmarks: {}", e));
+    }
+    tokio::fs::rename(&partial, &path)
+        .await
+        .map_err(|e| format!("Failed to write bookmarks: {}", e))
+}
+
+/// The bookmarks loaded by `service` as sidebar items.
+pub(super) fn items(service: &BookmarksService, use_symbolic_icons: bool) -> Vec<SidebarItem> {
+    let icon = if use_symbolic_icons { "folder-symbolic" } else { "folder" };
+    service
+        .bookmarks()
+        .iter()
+        .enumerate()
+        .map(|(i, bookmark)| {
+            let label = bookmark.label.clone().unwrap_or_else(|| {
+                uri_to_path(&bookmark.uri)
+                    .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+                    .unwrap_or_else(|| bookmark.uri.clone())
+            });
+            SidebarItem::new(format!("bookmark-{}", i), label)
+                .with_icon(icon)
+                .with_uri(bookmark.uri.clone())
+        })
+        .collect()
+}
+
+/// Load the bookmarks as sidebar items.
+pub(super) async fn load_items(use_symbolic_icons: bool) -> Vec<SidebarItem> {
+    let mut service = BookmarksService::new();
+    if let Err(e) = service.load().await {
+        log::warn!("Failed to load bookmarks: {}", e);
+    }
+    items(&service, use_symbolic_icons)
+}
+
+/// Remove every bookmark pointing at `uri`.
+pub(super) async fn remove(uri: &str) -> Result<(), String> {
+    let lines: Vec<String> = read_lines()
+        .await
+        .into_iter()
+        .filter(|line| parse_line(line).0 != uri)
+        .collect();
+    write_lines(&lines).await
+}
+
+/// Set the label of the bookmark pointing at `uri`. An empty label removes it;
+/// control characters are dropped, as a line break would start another bookmark.
+pub(super) async fn rename(uri: &str, label: &str) -> Result<(), String> {
+    let label = clean_label(label);
+    let label = label.trim();
+    let lines: Vec<String> = read_lines()
+        .await
+        .into_iter()
+        .map(|line| {
+            if parse_line(&line).0 != uri {
+                line
+            } else if label.is_empty() {
+                uri.to_string()
+            } else {
+                format!("{} {}", uri, label)
+            }
+        })
+        .collect();
+    write_lines(&lines).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_lines() {
+        assert_eq!(parse_line("file:///home/me/Music"), ("file:///home/me/Music", None));
+        assert_eq!(parse_line("file:///srv/a%20b My share"), ("file:///srv/a%20b", Some("My share")));
+    }
+
+    #[test]
+    fn labels_stay_on_their_line() {
+        assert_eq!(clean_label("Work\nfile:///etc Evil"), "Workfile:///etc Evil");
+        assert_eq!(clean_label("Tab\there\r"), "Tabhere");
+        assert_eq!(clean_label("Fotos – 2024"), "Fotos – 2024");
+    }
+}
@@ -0,0 +1,119 @@
+//! Small popup asking for a new bookmark label.
+
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+use std::sync::{Arc, Mutex};
+
+pub(super) struct BookmarkRenamePopup {
+    inner: Container,
+    label_text: StateSignal<String>,
+    confirmed: Arc<Mutex<bool>>,
+    result: Arc<Mutex<Option<String>>>,
+    signals_hooked: bool,
+}
+
+impl BookmarkRenamePopup {
+    /// Create the popup. The entered label is written to `result` on confirmation.
+    pub(super) fn new(current_label: String, result: Arc<Mutex<Option<String>>>) -> Self {
+        let label_text = StateSignal::new(current_label);
+        let confirmed = Arc::new(Mutex::new(false));
+
+        let confirmed_flag = confirmed.clone();
+        let inner = Container::new(vec![
+            Box::new(
+                TextInput::new()
+                    .with_text_signal(label_text.clone())
+                    .with_placeholder("Bookmark name".to_string())
+                    .with_layout_style(LayoutStyle {
+                        size: Vector2::new(Dimension::percent(1.0), Dimension::length(30.0)),
+                        ..Default::default()
+                    }),
+            ),
+            Box::new(
+                Container::new(vec![
+                    Box::new(
+                        Button::new(Text::new("Cancel".to_string()))
+                            .with_on_pressed(MaybeSignal::value(Update::DRAW)),
+                    ),
+                    Box::new(Button::new(Text::new("Rename".to_string())).with_on_pressed(
+                        MaybeSignal::signal(Box::new(EvalSignal::new(move || {
+                            if let Ok(mut flag) = confirmed_flag.lock() {
+                                *flag = true;
+                            }
+                            Update::DRAW
+                        }))),
+                    )),
+                ])
+                .with_layout_style(LayoutStyle {
+                    flex_direction: FlexDirection::Row,
+                    gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                    justify_content: Some(JustifyContent::FlexEnd),
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    ..Default::default()
+                }),
+            ),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(10.0)),
+            ..Default::default()
+        });
+
+        Self {
+            inner,
+            label_text,
+            confirmed,
+            result,
+            signals_hooked: false,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for BookmarkRenamePopup {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.label_text);
+            self.signals_hooked = true;
+        }
+
+        let confirmed = self
+            .confirmed
+            .lock()
+            .map(|mut flag| std::mem::take(&mut *flag))
+            .unwrap_or(false);
+        if confirmed {
+            if let Ok(mut result) = self.result.lock() {
+                *result = Some((*self.label_text.get()).clone());
+            }
+        }
+
+        self.inner.update(layout, context, info).await
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,182 @@
+//! Keyboard focus between the panes of the window
+//!
+//! The panes form a chain (sidebar, toolbar, location bar, file list, status bar)
+//! walked with Tab/F6 and Shift+Tab/Shift+F6. Each pane takes a [FocusChain] handle,
+//! draws a focus ring while it has focus and takes focus when clicked. Keys meant
+//! for the focused pane (moving and activating its items) are queued on the chain
+//! and taken by that pane in its next update.
+
+use crate::color_scheme;
+use nptk::core::app::info::AppInfo;
+use nptk::core::layout::LayoutNode;
+use nptk::core::theme::{ColorRole, Palette};
+use nptk::core::vg::kurbo::{Affine, RoundedRect, Shape, Stroke};
+use nptk::core::vg::peniko::Brush;
+use nptk::core::vgi::Graphics;
+use nptk::core::window::{ElementState, MouseButton};
+use std::sync::{Arc, Mutex};
+
+/// Width of the focus ring
+const RING_WIDTH: f64 = 2.0;
+
+/// A pane of the window that can have keyboard focus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Sidebar,
+    Toolbar,
+    LocationBar,
+    FileList,
+    StatusBar,
+}
+
+impl Pane {
+    /// Panes in traversal order
+    pub const CHAIN: [Pane; 5] = [
+        Pane::Sidebar,
+        Pane::Toolbar,
+        Pane::LocationBar,
+        Pane::FileList,
+        Pane::StatusBar,
+    ];
+}
+
+/// Key handled by the focused pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneKey {
+    Up,
+    Down,
+    /// Open the current item (Return)
+    Activate,
+}
+
+struct State {
+    focused: Pane,
+    keys: Vec<PaneKey>,
+}
+
+/// Shared handle to the focused pane.
+#[derive(Clone)]
+pub struct FocusChain {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for FocusChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FocusChain {
+    /// Create a chain with the file list focused.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                focused: Pane::FileList,
+                keys: Vec::new(),
+            })),
+        }
+    }
+
+    /// The pane that has focus.
+    pub fn focused(&self) -> Pane {
+        self.state
+            .lock()
+            .map(|s| s.focused)
+            .unwrap_or(Pane::FileList)
+    }
+
+    pub fn is_focused(&self, pane: Pane) -> bool {
+        self.focused() == pane
+    }
+
+    /// Give focus to `pane`. Keys queued for the previous pane are dropped.
+    pub fn focus(&self, pane: Pane) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.focused != pane {
+                state.focused = pane;
+                state.keys.clear();
+            }
+        }
+    }
+
+    /// Move focus to the next pane, wrapping around.
+    pub fn next(&self) {
+        self.step(1);
+    }
+
+    /// Move focus to the previous pane, wrapping around.
+    pub fn previous(&self) {
+        self.step(Pane::CHAIN.len() - 1);
+    }
+
+    fn step(&self, by: usize) {
+        let focused = self.focused();
+        let index = Pane::CHAIN.iter().position(|p| *p == focused).unwrap_or(0);
+        self.focus(Pane::CHAIN[(index + by) % Pane::CHAIN.len()]);
+    }
+
+    /// Queue a key for the focused pane.
+    pub fn send_key(&self, key: PaneKey) {
+        if let Ok(mut state) = self.state.lock() {
+            state.keys.push(key);
+        }
+    }
+
+    /// Take the keys queued for `pane` if it has focus.
+    pub fn take_keys(&self, pane: Pane) -> Vec<PaneKey> {
+        match self.state.lock() {
+            Ok(mut state) if state.focused == pane => std::mem::take(&mut state.keys),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Focus `pane` when it is clicked. Returns whether focus moved to it.
+    pub fn focus_on_click(&self, pane: Pane, layout: &LayoutNode, info: &AppInfo) -> bool {
+        let Some(cursor) = info.cursor_pos else {
+            return false;
+        };
+        let local_x = cursor.x as f32 - layout.layout.location.x;
+        let local_y = cursor.y as f32 - layout.layout.location.y;
+        let in_bounds = local_x >= 0.0
+            && local_x < layout.layout.size.width
+            && local_y >= 0.0
+            && local_y < layout.layout.size.height;
+        let pressed = info
+            .buttons
+            .iter()
+            .any(|(_, btn, el)| *btn == MouseButton::Left && *el == ElementState::Pressed);
+        if !in_bounds || !pressed || self.is_focused(pane) {
+            return false;
+        }
+        self.focus(pane);
+        true
+    }
+
+    /// Draw the focus ring around `pane` if it has focus.
+    pub fn render_ring(
+        &self,
+        pane: Pane,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        palette: &Palette,
+    ) {
+        if !self.is_focused(pane) {
+            return;
+        }
+        let inset = RING_WIDTH / 2.0;
+        let ring = RoundedRect::new(
+            layout.layout.location.x as f64 + inset,
+            layout.layout.location.y as f64 + inset,
+            (layout.layout.location.x + layout.layout.size.width) as f64 - inset,
+            (layout.layout.location.y + layout.layout.size.height) as f64 - inset,
+            3.0,
+        );
+        graphics.stroke(
+            &Stroke::new(RING_WIDTH),
+            Affine::IDENTITY,
+            &Brush::Solid(color_scheme::color(palette, ColorRole::Selection)),
+            None,
+            &ring.into_path(0.1),
+        );
+    }
+}
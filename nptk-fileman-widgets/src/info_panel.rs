@@ -0,0 +1,447 @@
+//! Information side panel widget
+//!
+//! Shows details about the current selection (icon, name, type, size, dates, tags)
+//! together with a row of quick actions. Falls back to the current directory when
+//! nothing is selected. The details are read on a blocking thread, as folders can
+//! be large or on slow mounts.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use npio::service::filesystem::mime_detector::MimeDetector;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vg::kurbo::Shape;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::color_scheme;
+use crate::selection_size::{SelectionSizer, SelectionTotal};
+use crate::sizes;
+
+/// Quick actions offered at the bottom of the info panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfoPanelAction {
+    /// Open the shown item(s) with the default handler.
+    Open,
+    /// Show the full Properties dialog.
+    Properties,
+}
+
+type TagsProvider = Arc<dyn Fn(&Path) -> Vec<String> + Send + Sync>;
+
+/// The paths last asked for and their summary, once it is in.
+type SummarySlot = Arc<Mutex<(Vec<PathBuf>, Option<InfoSummary>)>>;
+
+/// Summary of the items displayed by the panel.
+#[derive(Debug, Clone, Default)]
+struct InfoSummary {
+    icon_name: String,
+    title: String,
+    rows: Vec<(String, String)>,
+    /// Whether a Size row shows the total of the selection with folder contents
+    total_size: bool,
+}
+
+/// A toggleable panel displaying details of the current selection.
+///
+/// The panel subscribes to a selected paths signal and rebuilds its content whenever
+/// the selection changes. Visibility is driven by a `StateSignal<bool>` so a toolbar
+/// button or shortcut can toggle it without holding a reference to the widget.
+pub struct FileInfoPanel {
+    inner: Container,
+    current_path: StateSignal<PathBuf>,
+    selected_paths: StateSignal<Vec<PathBuf>>,
+    visible: StateSignal<bool>,
+    width: f32,
+    /// Paths the panel shows, or is summarizing in the background
+    requested_paths: Option<Vec<PathBuf>>,
+    summary: SummarySlot,
+    tags_provider: Option<TagsProvider>,
+    on_action: Option<Box<dyn Fn(InfoPanelAction, Vec<PathBuf>) -> Update + Send + Sync>>,
+    pending_action: Arc<Mutex<Option<InfoPanelAction>>>,
+    sizer: SelectionSizer,
+    shown_total: Option<SelectionTotal>,
+    total_text: StateSignal<String>,
+    signals_hooked: bool,
+}
+
+impl FileInfoPanel {
+    /// Create a new info panel following the given path and selection signals.
+    pub fn new(
+        current_path: StateSignal<PathBuf>,
+        selected_paths: StateSignal<Vec<PathBuf>>,
+    ) -> Self {
+        Self {
+            inner: Container::new(vec![]),
+            current_path,
+            selected_paths,
+            visible: StateSignal::new(true),
+            width: 240.0,
+            requested_paths: None,
+            summary: Arc::new(Mutex::new((Vec::new(), None))),
+            tags_provider: None,
+            on_action: None,
+            pending_action: Arc::new(Mutex::new(None)),
+            sizer: SelectionSizer::new(),
+            shown_total: None,
+            total_text: StateSignal::new(String::new()),
+            signals_hooked: false,
+        }
+    }
+
+    /// Drive the panel visibility from an external signal.
+    pub fn with_visible_signal(mut self, visible: StateSignal<bool>) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    /// Set the width of the panel when visible.
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Provide tags for a path (e.g. from extended attributes or a tag database).
+    pub fn with_tags_provider<F>(mut self, provider: F) -> Self
+    where
+        F: Fn(&Path) -> Vec<String> + Send + Sync + 'static,
+    {
+        self.tags_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Set the callback invoked when one of the quick action buttons is pressed.
+    pub fn with_on_action<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(InfoPanelAction, Vec<PathBuf>) -> Update + Send + Sync + 'static,
+    {
+        self.on_action = Some(Box::new(callback));
+        self
+    }
+
+    /// Share the selection size computation, e.g. with the status bar.
+    pub fn with_selection_sizer(mut self, sizer: SelectionSizer) -> Self {
+        self.sizer = sizer;
+        self
+    }
+
+    /// Get the visibility signal.
+    pub fn visible_signal(&self) -> &StateSignal<bool> {
+        &self.visible
+    }
+
+    /// Paths the panel is describing: the selection, or the current directory.
+    fn target_paths(&self) -> Vec<PathBuf> {
+        let selected = (*self.selected_paths.get()).clone();
+        if selected.is_empty() {
+            vec![(*self.current_path.get()).clone()]
+        } else {
+            selected
+        }
+    }
+
+    /// Summarize `paths` on a blocking thread, waking the panel when done.
+    /// Summaries of paths asked for before are dropped.
+    fn request_summary(&self, paths: Vec<PathBuf>, on_done: impl FnOnce() + Send + 'static) {
+        if let Ok(mut slot) = self.summary.lock() {
+            *slot = (paths.clone(), None);
+        }
+        let selected = !self.selected_paths.get().is_empty();
+        let tags_provider = self.tags_provider.clone();
+        let slot = self.summary.clone();
+        tokio::task::spawn_blocking(move || {
+            let summary = Self::summarize(&paths, selected, tags_provider.as_ref());
+            if let Ok(mut slot) = slot.lock() {
+                if slot.0 == paths {
+                    slot.1 = Some(summary);
+                    on_done();
+                }
+            }
+        });
+    }
+
+    /// What is shown until the summary of `paths` is in.
+    fn placeholder(paths: &[PathBuf]) -> InfoSummary {
+        match paths {
+            [path] => InfoSummary {
+                icon_name: "text-x-generic".to_string(),
+                title: path
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string()),
+                ..Default::default()
+            },
+            _ => InfoSummary {
+                icon_name: "document-multiple".to_string(),
+                title: format!("{} items selected", paths.len()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Read the details of `paths`; blocks on the file system. `selected` is
+    /// whether they are the selection rather than the current directory.
+    fn summarize(paths: &[PathBuf], selected: bool, tags_provider: Option<&TagsProvider>) -> InfoSummary {
+        if paths.len() == 1 {
+            let path = &paths[0];
+            let name = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| path.display().to_string());
+            let mut rows = Vec::new();
+
+            let metadata = fs::metadata(path).ok();
+            let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false);
+            let mime = if is_dir {
+                Some("inode/directory".to_string())
+            } else {
+                smol::block_on(MimeDetector::detect_mime_type(path))
+            };
+            let icon_name = match mime.as_deref() {
+                Some("inode/directory") => "folder".to_string(),
+                Some(mime) => mime.replace('/', "-"),
+                None => "text-x-generic".to_string(),
+            };
+
+            rows.push(("Type".to_string(), mime.unwrap_or_else(|| "unknown".to_string())));
+            if let Some(meta) = &metadata {
+                if is_dir {
+                    let count = fs::read_dir(path).map(|it| it.count()).unwrap_or(0);
+                    rows.push(("Contents".to_string(), format!("{} item(s)", count)));
+                } else {
+                    rows.push(("Size".to_string(), sizes::format(meta.len())));
+                }
+                if let Ok(modified) = meta.modified() {
+                    rows.push(("Modified".to_string(), Self::format_time(modified)));
+                }
+                if let Ok(created) = meta.created() {
+                    rows.push(("Created".to_string(), Self::format_time(created)));
+                }
+            }
+            if let Some(provider) = tags_provider {
+                let tags = provider(path);
+                if !tags.is_empty() {
+                    rows.push(("Tags".to_string(), tags.join(", ")));
+                }
+            }
+            rows.push((
+                "Location".to_string(),
+                path.parent().map(|p| p.display().to_string()).unwrap_or_default(),
+            ));
+
+            InfoSummary {
+                icon_name,
+                title: name,
+                rows,
+                total_size: is_dir && selected,
+            }
+        } else {
+            let mut files = 0usize;
+            let mut dirs = 0usize;
+            for path in paths {
+                match fs::metadata(path) {
+                    Ok(meta) if meta.is_dir() => dirs += 1,
+                    Ok(_) => files += 1,
+                    Err(_) => {},
+                }
+            }
+            let rows = vec![
+                ("Folders".to_string(), dirs.to_string()),
+                ("Files".to_string(), files.to_string()),
+            ];
+            InfoSummary {
+                icon_name: "document-multiple".to_string(),
+                title: format!("{} items selected", paths.len()),
+                rows,
+                total_size: true,
+            }
+        }
+    }
+
+    fn format_time(time: std::time::SystemTime) -> String {
+        let dt: DateTime<Local> = time.into();
+        dt.format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    fn action_button(&self, label: &str, action: InfoPanelAction) -> Button {
+        let pending = self.pending_action.clone();
+        Button::new(Text::new(label.to_string()).with_font_size(13.0)).with_on_pressed(
+            MaybeSignal::signal(Box::new(EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(action);
+                }
+                Update::DRAW
+            }))),
+        )
+    }
+
+    /// Rebuild the inner container showing `summary`.
+    fn rebuild(&mut self, summary: InfoSummary) {
+        let mut children: Vec<Box<dyn Widget>> = vec![
+            Box::new(Icon::new(summary.icon_name, 64, None)),
+            Box::new(Text::new(summary.title).with_font_size(16.0)),
+        ];
+        let mut rows: Vec<(String, Text)> = summary
+            .rows
+            .into_iter()
+            .map(|(label, value)| (label, Text::new(value)))
+            .collect();
+        if summary.total_size {
+            // Filled in while the selection is added up
+            rows.push(("Size".to_string(), Text::new(self.total_text.maybe())));
+        }
+        for (label, value) in rows {
+            children.push(Box::new(
+                Container::new(vec![
+                    Box::new(Text::new(format!("{}:", label)).with_font_size(12.0)),
+                    Box::new(value.with_font_size(12.0)),
+                ])
+                .with_layout_style(LayoutStyle {
+                    size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                    flex_direction: FlexDirection::Column,
+                    ..Default::default()
+                }),
+            ));
+        }
+
+        children.push(Box::new(
+            Container::new(vec![
+                Box::new(self.action_button("Open", InfoPanelAction::Open)),
+                Box::new(self.action_button("Properties", InfoPanelAction::Properties)),
+            ])
+            .with_layout_style(LayoutStyle {
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                flex_direction: FlexDirection::Row,
+                gap: Vector2::new(LengthPercentage::length(4.0), LengthPercentage::length(0.0)),
+                ..Default::default()
+            }),
+        ));
+
+        self.inner = Container::new(children).with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::percent(1.0)),
+            flex_direction: FlexDirection::Column,
+            padding: nptk::core::layout::Rect {
+                left: LengthPercentage::length(8.0),
+                right: LengthPercentage::length(8.0),
+                top: LengthPercentage::length(8.0),
+                bottom: LengthPercentage::length(8.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(6.0)),
+            ..Default::default()
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for FileInfoPanel {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        let width = if *self.visible.get() { self.width } else { 0.0 };
+        StyleNode {
+            style: LayoutStyle {
+                size: Vector2::new(Dimension::length(width), Dimension::percent(1.0)),
+                flex_shrink: 0.0,
+                ..Default::default()
+            },
+            children: vec![self.inner.layout_style(context)],
+            measure_func: None,
+        }
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.current_path);
+            context.hook_signal(&mut self.selected_paths);
+            context.hook_signal(&mut self.visible);
+            context.hook_signal(&mut self.total_text);
+            self.signals_hooked = true;
+        }
+
+        if !*self.visible.get() {
+            return update;
+        }
+
+        let paths = self.target_paths();
+        if self.requested_paths.as_ref() != Some(&paths) {
+            self.requested_paths = Some(paths.clone());
+            let update_manager = context.update();
+            self.request_summary(paths.clone(), move || update_manager.insert(Update::DRAW));
+            self.rebuild(Self::placeholder(&paths));
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+        let summary = self.summary.lock().ok().and_then(|mut slot| slot.1.take());
+        if let Some(summary) = summary {
+            self.rebuild(summary);
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        let selected = (*self.selected_paths.get()).clone();
+        if !selected.is_empty() {
+            let update_manager = context.update();
+            let total = self.sizer.total(&selected, move || update_manager.insert(Update::DRAW));
+            if self.shown_total != Some(total) {
+                self.shown_total = Some(total);
+                self.total_text.set(format!("{} ({} files)", total.format(), total.files));
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+        }
+
+        let action = self.pending_action.lock().ok().and_then(|mut p| p.take());
+        if let (Some(action), Some(callback)) = (action, &self.on_action) {
+            update |= callback(action, paths);
+        }
+
+        if !layout.children.is_empty() {
+            update |= self.inner.update(&layout.children[0], context, info).await;
+        }
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        if !*self.visible.get() {
+            return;
+        }
+
+        let palette = context.palette();
+        let bg = color_scheme::color(palette, nptk::core::theme::ColorRole::Window);
+        let border = color_scheme::color(palette, nptk::core::theme::ColorRole::ThreedShadow1);
+        let rect = nptk::core::vg::kurbo::Rect::new(
+            layout.layout.location.x as f64,
+            layout.layout.location.y as f64,
+            (layout.layout.location.x + layout.layout.size.width) as f64,
+            (layout.layout.location.y + layout.layout.size.height) as f64,
+        );
+        graphics.fill(
+            nptk::core::vg::peniko::Fill::NonZero,
+            nptk::core::vg::kurbo::Affine::IDENTITY,
+            &nptk::core::vg::peniko::Brush::Solid(bg),
+            None,
+            &rect.into_path(0.1),
+        );
+
+        // Left border separating the panel from the file list
+        let border_line = nptk::core::vg::kurbo::Line::new((rect.x0, rect.y0), (rect.x0, rect.y1));
+        graphics.stroke(
+            &nptk::core::vg::kurbo::Stroke::new(1.0),
+            nptk::core::vg::kurbo::Affine::IDENTITY,
+            &nptk::core::vg::peniko::Brush::Solid(border),
+            None,
+            &border_line.into_path(0.1),
+        );
+
+        if !layout.children.is_empty() {
+            self.inner.render(graphics, &layout.children[0], info, context);
+        }
+    }
+}
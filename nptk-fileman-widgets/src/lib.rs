@@ -10,7 +10,79 @@ pub mod file_list;
 /// Contains the [fileman_sidebar::FilemanSidebar] widget.
 pub mod fileman_sidebar;
 
+/// Contains the [conflict_dialog::ConflictDialog] widget.
+pub mod conflict_dialog;
+
+/// Contains the [error_dialog::ErrorDialog] widget.
+pub mod error_dialog;
+
+/// Contains the [directory_tree::DirectoryTree] widget.
+pub mod directory_tree;
+
+/// Contains the [info_panel::FileInfoPanel] widget.
+pub mod info_panel;
+
+/// Contains the [file_chooser::FileChooser] widget.
+pub mod file_chooser;
+
+/// Accessibility metadata published by the widgets.
+pub mod accessibility;
+
+/// Light, dark and high-contrast colors of the widgets.
+pub mod color_scheme;
+
+/// Keyboard focus between the panes of the window.
+pub mod focus;
+
+/// Locale-aware and relative formatting of file dates.
+pub mod dates;
+
+/// File sizes in binary or SI units.
+pub mod sizes;
+
+/// Pipes, sockets, devices and sparse files.
+pub mod special_files;
+
+/// Localized descriptions of file types from the MIME database.
+pub mod mime_types;
+
+/// Names, icons and commands of `.desktop` launchers.
+pub mod desktop_entries;
+
+/// Persistent cache of directory sizes.
+pub mod dir_size_cache;
+
+/// Saved listings of folders on slow filesystems.
+pub mod listing_cache;
+
+/// Opt-in performance counters.
+pub mod perf;
+
+/// Filesystem lookups answered in the background.
+pub mod directory_service;
+
+/// Total size of the selection, walked in the background.
+pub mod selection_size;
+
+/// Validation of file names and free alternatives to taken ones.
+pub mod file_names;
+
+/// Commands posted by buttons, taken once per press.
+pub mod commands;
+
+/// Base directories for settings, state, data and caches.
+pub mod xdg;
+
+/// Ids of the widgets' context menu entries.
+pub mod menu_ids;
+
+mod cache_db;
+
 // Re-export for convenience
-pub use fileman_sidebar::FilemanSidebar;
+pub use fileman_sidebar::{
+    FilemanSidebar, PlacesEntry, SidebarItemAction, SidebarItemContext, SidebarPatch, SidebarUpdater,
+};
 pub mod location_bar;
+pub mod path_completer;
+pub mod rename_dialog;
 pub mod status_bar;
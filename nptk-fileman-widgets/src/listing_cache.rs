@@ -0,0 +1,242 @@
+//! Saved listings of folders on slow filesystems
+//!
+//! Listings of folders on network filesystems and removable drives are stored in
+//! `$XDG_CACHE_HOME/fileman/listings.sqlite` whenever they are loaded. When such a
+//! folder is opened again, the file list shows the saved listing at once, marked
+//! stale, and replaces it when the fresh listing arrives.
+
+use crate::cache_db::{self, UNUSED_ENTRY_LIFETIME, unix_seconds};
+use nptk::services::filesystem::entry::{FileEntry, FileMetadata, FileType};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Filesystem types whose listings are slow enough to be worth saving
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "davfs",
+    "fuse.davfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+    "fuse.s3fs",
+    "fuse.gvfsd-fuse",
+];
+
+/// The open cache, `None` until first use or when it can't be opened
+static CACHE: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// Mount point, filesystem type and device number of the mount containing `path`
+fn mount_of(path: &Path) -> Option<(PathBuf, String, String)> {
+    let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            // "<id> <parent> <major:minor> <root> <mount point> <options> [optional...] - <type> ..."
+            let mut fields = line.split(' ');
+            let device = fields.nth(2)?;
+            let mount_point = unescape_mount_path(fields.nth(1)?);
+            let mut after_separator = line.split(" - ").nth(1)?.split(' ');
+            let fs_type = after_separator.next()?;
+            path.starts_with(&mount_point)
+                .then(|| (mount_point, fs_type.to_string(), device.to_string()))
+        })
+        .max_by_key(|(mount_point, _, _)| mount_point.as_os_str().len())
+}
+
+/// Mount paths escape spaces and a few other characters as octal (`\040`).
+fn unescape_mount_path(escaped: &str) -> PathBuf {
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let raw = escaped.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        let octal = raw
+            .get(i + 1..i + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match (raw[i], octal) {
+            (b'\\', Some(byte)) => {
+                bytes.push(byte);
+                i += 4;
+            },
+            (byte, _) => {
+                bytes.push(byte);
+                i += 1;
+            },
+        }
+    }
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+/// Whether the block device `major:minor` (or the disk it is a partition of) is removable.
+fn is_removable_device(device: &str) -> bool {
+    let sys = PathBuf::from("/sys/dev/block").join(device);
+    [sys.join("removable"), sys.join("../removable")]
+        .iter()
+        .any(|flag| std::fs::read_to_string(flag).is_ok_and(|value| value.trim() == "1"))
+}
+
+/// Whether `path` is on a network filesystem or a removable drive.
+pub fn is_slow_location(path: &Path) -> bool {
+    let Some((_, fs_type, device)) = mount_of(path) else {
+        return false;
+    };
+    NETWORK_FILESYSTEMS.contains(&fs_type.as_str()) || is_removable_device(&device)
+}
+
+fn open() -> Result<Connection, String> {
+    let connection = cache_db::open(
+        "listings.sqlite",
+        "CREATE TABLE IF NOT EXISTS listings (
+            dir TEXT PRIMARY KEY,
+            saved INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS entries (
+            dir TEXT NOT NULL,
+            name TEXT NOT NULL,
+            kind INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            modified INTEGER NOT NULL,
+            created INTEGER,
+            mime_type TEXT
+        );
+        CREATE INDEX IF NOT EXISTS entries_dir ON entries (dir);",
+    )?;
+    let expired = unix_seconds(SystemTime::now() - UNUSED_ENTRY_LIFETIME);
+    let pruned = connection.execute_batch(&format!(
+        "DELETE FROM entries WHERE dir IN (SELECT dir FROM listings WHERE saved < {expired});
+         DELETE FROM listings WHERE saved < {expired};"
+    ));
+    if let Err(e) = pruned {
+        log::warn!("Failed to prune listing cache: {}", e);
+    }
+    Ok(connection)
+}
+
+/// Run `f` with the open cache, opening it on first use.
+fn with_cache<T>(f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Option<T> {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.is_none() {
+        match open() {
+            Ok(connection) => *cache = Some(connection),
+            Err(e) => {
+                log::warn!("{}", e);
+                return None;
+            },
+        }
+    }
+    f(cache.as_ref()?)
+        .map_err(|e| log::warn!("Listing cache failed: {}", e))
+        .ok()
+}
+
+fn kind_to_int(file_type: &FileType) -> i64 {
+    match file_type {
+        FileType::File => 0,
+        FileType::Directory => 1,
+        FileType::Symlink => 2,
+        FileType::Other => 3,
+    }
+}
+
+fn int_to_kind(kind: i64) -> FileType {
+    match kind {
+        0 => FileType::File,
+        1 => FileType::Directory,
+        2 => FileType::Symlink,
+        _ => FileType::Other,
+    }
+}
+
+fn to_nanos(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+fn from_nanos(nanos: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(nanos.max(0) as u64)
+}
+
+/// The saved listing of `dir`, if there is one.
+pub fn load(dir: &Path) -> Option<Vec<FileEntry>> {
+    let key = dir.to_string_lossy().to_string();
+    with_cache(|connection| {
+        let saved: Option<i64> = connection
+            .query_row(
+                "SELECT saved FROM listings WHERE dir = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if saved.is_none() {
+            return Ok(None);
+        }
+        let mut statement = connection.prepare(
+            "SELECT name, kind, size, modified, created, mime_type FROM entries WHERE dir = ?1",
+        )?;
+        let entries = statement
+            .query_map(params![key], |row| {
+                let name: String = row.get(0)?;
+                let metadata = FileMetadata {
+                    size: row.get::<_, i64>(2)? as u64,
+                    modified: from_nanos(row.get(3)?),
+                    created: row.get::<_, Option<i64>>(4)?.map(from_nanos),
+                    permissions: 0,
+                    mime_type: row.get(5)?,
+                    is_hidden: name.starts_with('.'),
+                };
+                Ok(FileEntry::new(
+                    dir.join(&name),
+                    name,
+                    int_to_kind(row.get(1)?),
+                    metadata,
+                    Some(dir.to_path_buf()),
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Some(entries))
+    })
+    .flatten()
+}
+
+/// Save the listing of `dir`, replacing the previous one.
+pub fn save(dir: &Path, entries: &[FileEntry]) {
+    let key = dir.to_string_lossy().to_string();
+    with_cache(|connection| {
+        let transaction = connection.unchecked_transaction()?;
+        transaction.execute("DELETE FROM entries WHERE dir = ?1", params![key])?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO entries (dir, name, kind, size, modified, created, mime_type)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for entry in entries {
+                insert.execute(params![
+                    key,
+                    entry.name,
+                    kind_to_int(&entry.file_type),
+                    entry.metadata.size as i64,
+                    to_nanos(entry.metadata.modified),
+                    entry.metadata.created.map(to_nanos),
+                    entry.metadata.mime_type,
+                ])?;
+            }
+        }
+        transaction.execute(
+            "INSERT OR REPLACE INTO listings (dir, saved) VALUES (?1, ?2)",
+            params![key, unix_seconds(SystemTime::now())],
+        )?;
+        transaction.commit()
+    });
+}
@@ -1,12 +1,18 @@
 use nptk::prelude::*;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use async_trait::async_trait;
 use nptk::core::signal::state::StateSignal;
 use nptk::core::signal::MaybeSignal;
 use nptk::widgets::breadcrumbs::{Breadcrumbs, BreadcrumbItem};
 use nptk::widgets::text_input::TextInput;
+use nptk::core::vg::kurbo::Point;
+use crate::focus::{FocusChain, Pane};
+use crate::directory_service;
+use crate::path_completer::PathCompleter;
+
+mod neighbors;
 
 /// Helper function to convert PathBuf to breadcrumb items
 fn path_to_breadcrumb_items(path: &PathBuf) -> Vec<BreadcrumbItem> {
@@ -29,15 +35,13 @@ fn path_to_breadcrumb_items(path: &PathBuf) -> Vec<BreadcrumbItem> {
         }
     }
     
-    // Last item is not clickable (current location)
-    if let Some(last) = items.last_mut() {
-        last.clickable = false;
-    }
-    
     items
 }
 
 /// A reusable location bar widget combining breadcrumbs and text input.
+///
+/// Clicking a breadcrumb goes to that folder; clicking the last one (the current
+/// folder) opens a dropdown of the folders next to it.
 pub struct FileLocationBar {
     inner: Container,
     current_path: StateSignal<PathBuf>,
@@ -47,6 +51,19 @@ pub struct FileLocationBar {
     on_navigate: Option<Box<dyn Fn(PathBuf) -> Update + Send + Sync>>,
     signals_hooked: bool,
     internal_rx: Option<mpsc::UnboundedReceiver<PathBuf>>,
+    completer: Option<PathCompleter>,
+    last_text: String,
+    // Typed text waiting for its folder to be listed before completing it
+    completion_pending: bool,
+    focus: Option<FocusChain>,
+    show_hidden: Option<StateSignal<bool>>,
+    // Folders next to a path, listed in the background
+    listed_neighbors: Arc<Mutex<Option<(PathBuf, Vec<PathBuf>)>>>,
+    // Folders of the open dropdown, to scroll it
+    neighbors: Option<(PathBuf, Vec<PathBuf>)>,
+    neighbor_scroll: Arc<Mutex<Option<usize>>>,
+    neighbor_tx: mpsc::UnboundedSender<PathBuf>,
+    neighbor_rx: mpsc::UnboundedReceiver<PathBuf>,
 }
 
 impl FileLocationBar {
@@ -98,15 +115,27 @@ impl FileLocationBar {
             ..Default::default()
         });
         
+        let (neighbor_tx, neighbor_rx) = mpsc::unbounded_channel();
+
         Self {
             inner: container,
             current_path,
             breadcrumb_items,
             text_value,
+            last_text: path_val.to_string_lossy().to_string(),
             last_synced_path: path_val,
             on_navigate: None,
             signals_hooked: false,
             internal_rx: Some(rx),
+            completer: None,
+            completion_pending: false,
+            focus: None,
+            show_hidden: None,
+            listed_neighbors: Arc::new(Mutex::new(None)),
+            neighbors: None,
+            neighbor_scroll: Arc::new(Mutex::new(None)),
+            neighbor_tx,
+            neighbor_rx,
         }
     }
     
@@ -117,6 +146,66 @@ impl FileLocationBar {
         self.on_navigate = Some(Box::new(callback));
         self
     }
+
+    /// Attach a path completer. Folder suggestions are shown in a dropdown when
+    /// the typed path ends with a separator, once the folder is listed.
+    pub fn with_path_completer(mut self, completer: PathCompleter) -> Self {
+        self.completer = Some(completer);
+        self
+    }
+
+    /// Join the window's focus chain (shows a focus ring while focused).
+    pub fn with_focus_chain(mut self, focus: FocusChain) -> Self {
+        self.focus = Some(focus);
+        self
+    }
+
+    /// Whether hidden folders are offered in the dropdown of the last
+    /// breadcrumb. They are left out without this signal.
+    pub fn with_show_hidden_signal(mut self, show_hidden: StateSignal<bool>) -> Self {
+        self.show_hidden = Some(show_hidden);
+        self
+    }
+
+    /// List the folders next to `path` in the background; the dropdown opens
+    /// once they are in.
+    fn request_neighbors(&self, path: PathBuf, context: &nptk::core::app::context::AppContext) {
+        let show_hidden = self.show_hidden.as_ref().is_some_and(|s| *s.get());
+        let listed = self.listed_neighbors.clone();
+        let update_manager = context.update();
+        tokio::spawn(async move {
+            let folders = neighbors::list(path.clone(), show_hidden).await;
+            if let Ok(mut slot) = listed.lock() {
+                *slot = Some((path, folders));
+            }
+            update_manager.insert(Update::DRAW);
+        });
+    }
+
+    /// Show the dropdown of the listed folders from `start` below the breadcrumbs.
+    fn show_neighbors(
+        &self,
+        start: usize,
+        layout: &nptk::core::layout::LayoutNode,
+        context: &nptk::core::app::context::AppContext,
+    ) {
+        let Some((path, folders)) = &self.neighbors else {
+            return;
+        };
+        let template = neighbors::menu_template(
+            folders,
+            path,
+            start,
+            self.neighbor_tx.clone(),
+            self.neighbor_scroll.clone(),
+        );
+        let anchor = layout.children.first().unwrap_or(layout);
+        let pos = Point::new(
+            anchor.layout.location.x as f64,
+            (anchor.layout.location.y + anchor.layout.size.height) as f64,
+        );
+        context.menu_manager.show(template, pos);
+    }
 }
 
 #[async_trait(?Send)]
@@ -150,7 +239,9 @@ impl Widget for FileLocationBar {
             self.breadcrumb_items.set(new_items);
             
             // Update text
-            self.text_value.set(path.to_string_lossy().to_string());
+            let text = path.to_string_lossy().to_string();
+            self.last_text = text.clone();
+            self.text_value.set(text);
             
             update.insert(Update::LAYOUT | Update::DRAW);
         }
@@ -158,12 +249,71 @@ impl Widget for FileLocationBar {
         // Handle internal navigation events
         if let Some(ref mut rx) = self.internal_rx {
             while let Ok(path) = rx.try_recv() {
-                if let Some(callback) = &self.on_navigate {
+                if path == self.last_synced_path {
+                    self.request_neighbors(path, &context);
+                } else if let Some(callback) = &self.on_navigate {
                     update |= callback(path);
                 }
             }
         }
+
+        // Folders picked from the dropdown of the last breadcrumb
+        while let Ok(path) = self.neighbor_rx.try_recv() {
+            if let Some(callback) = &self.on_navigate {
+                update |= callback(path);
+            }
+        }
+
+        // Open the dropdown once the folders are listed, unless the location
+        // changed meanwhile, and page through it
+        let listed = self.listed_neighbors.lock().ok().and_then(|mut slot| slot.take());
+        if let Some((path, folders)) = listed {
+            if path == self.last_synced_path && !folders.is_empty() {
+                let start = neighbors::page_around(&folders, &path);
+                self.neighbors = Some((path, folders));
+                self.show_neighbors(start, layout, &context);
+                update.insert(Update::DRAW);
+            }
+        }
+        let scroll = self.neighbor_scroll.lock().ok().and_then(|mut slot| slot.take());
+        if let Some(start) = scroll {
+            self.show_neighbors(start, layout, &context);
+            update.insert(Update::DRAW);
+        }
         
+        // Offer completions when the user typed into the text field
+        let text = (*self.text_value.get()).clone();
+        if text != self.last_text {
+            self.last_text = text.clone();
+            self.completion_pending = self.completer.is_some() && text.ends_with('/');
+        }
+        if self.completion_pending {
+            if let Some(completer) = &self.completer {
+                let update_manager = context.update();
+                let folders = directory_service::folders(&completer.directory(&text), move || {
+                    update_manager.insert(Update::DRAW);
+                });
+                if let Some(folders) = folders {
+                    self.completion_pending = false;
+                    if let Some(template) = completer.folder_menu_template(&text, &folders, self.text_value.clone()) {
+                        let anchor = layout.children.get(1).unwrap_or(layout);
+                        let pos = Point::new(
+                            anchor.layout.location.x as f64,
+                            (anchor.layout.location.y + anchor.layout.size.height) as f64,
+                        );
+                        context.menu_manager.show(template, pos);
+                        update.insert(Update::DRAW);
+                    }
+                }
+            }
+        }
+
+        if let Some(focus) = &self.focus {
+            if focus.focus_on_click(Pane::LocationBar, layout, info) {
+                update.insert(Update::DRAW);
+            }
+        }
+
         update |= self.inner.update(layout, context, info).await;
         update
     }
@@ -175,7 +325,10 @@ impl Widget for FileLocationBar {
         info: &mut nptk::core::app::info::AppInfo,
         context: nptk::core::app::context::AppContext,
     ) {
-        self.inner.render(graphics, layout, info, context)
+        self.inner.render(graphics, layout, info, context.clone());
+        if let Some(focus) = &self.focus {
+            focus.render_ring(Pane::LocationBar, graphics, layout, context.palette());
+        }
     }
 }
 
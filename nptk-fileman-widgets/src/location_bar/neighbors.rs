@@ -0,0 +1,88 @@
+//! Sibling folders of the current location, offered in a dropdown when the last
+//! breadcrumb is clicked.
+//!
+//! Folders are listed by the [crate::directory_service] so slow mounts don't
+//! stall the frame, and the dropdown shows a page of them around the current folder with items
+//! to scroll up and down.
+
+use nptk::core::app::update::Update;
+use nptk::core::menu::{MenuCommand, MenuItem, MenuTemplate};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+use crate::directory_service;
+use crate::file_list::natural_cmp;
+use crate::menu_ids;
+
+/// Most folders shown in the dropdown at once.
+pub const PAGE_SIZE: usize = 25;
+
+/// Folders next to `path` (the folders of its parent, `path` included), in
+/// natural order. Hidden folders are skipped unless `show_hidden`.
+pub async fn list(path: PathBuf, show_hidden: bool) -> Vec<PathBuf> {
+    let Some(parent) = path.parent().map(Path::to_path_buf) else {
+        return Vec::new();
+    };
+    let mut folders: Vec<(String, PathBuf)> = directory_service::list_folders(parent)
+        .await
+        .iter()
+        .filter_map(|folder| Some((folder.file_name()?.to_string_lossy().to_string(), folder.clone())))
+        .filter(|(name, folder)| show_hidden || !name.starts_with('.') || *folder == path)
+        .collect();
+    folders.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+    folders.into_iter().map(|(_, folder)| folder).collect()
+}
+
+/// First folder of the page that has `current` in its middle.
+pub fn page_around(folders: &[PathBuf], current: &Path) -> usize {
+    let index = folders.iter().position(|f| f == current).unwrap_or(0);
+    index
+        .saturating_sub(PAGE_SIZE / 2)
+        .min(folders.len().saturating_sub(PAGE_SIZE))
+}
+
+/// Dropdown with the folders of the page starting at `start`. Picking a folder
+/// sends it to `navigate`; the scroll items send the start of the previous or
+/// next page to `scroll`.
+pub fn menu_template(
+    folders: &[PathBuf],
+    current: &Path,
+    start: usize,
+    navigate: mpsc::UnboundedSender<PathBuf>,
+    scroll: Arc<Mutex<Option<usize>>>,
+) -> MenuTemplate {
+    let end = (start + PAGE_SIZE).min(folders.len());
+    let mut items = Vec::new();
+    let scroll_item = |id: u32, label: String, to: usize| {
+        let scroll = scroll.clone();
+        MenuItem::new(MenuCommand::Custom(id), label).with_action(move || {
+            if let Ok(mut slot) = scroll.lock() {
+                *slot = Some(to);
+            }
+            Update::DRAW
+        })
+    };
+    if start > 0 {
+        items.push(scroll_item(menu_ids::NEIGHBORS_SCROLL_UP_ID, format!("▲ {} more", start), start.saturating_sub(PAGE_SIZE)));
+    }
+    for (i, folder) in folders[start..end].iter().enumerate() {
+        let name = folder
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| folder.display().to_string());
+        let label = if folder == current { format!("✓ {}", name) } else { name };
+        let navigate = navigate.clone();
+        let folder = folder.clone();
+        items.push(
+            MenuItem::new(MenuCommand::Custom(menu_ids::range_id(menu_ids::NEIGHBOR_IDS, i)), label).with_action(move || {
+                let _ = navigate.send(folder.clone());
+                Update::DRAW
+            }),
+        );
+    }
+    if end < folders.len() {
+        items.push(scroll_item(menu_ids::NEIGHBORS_SCROLL_DOWN_ID, format!("▼ {} more", folders.len() - end), end));
+    }
+    MenuTemplate::from_items("breadcrumb_neighbors", items)
+}
@@ -0,0 +1,16 @@
+//! Ids of the widgets' own context menu entries. The application adds its own
+//! entries around them; its list of ids re-exports [range_id] so ranges are
+//! numbered the same way on both sides. The file list's entries use 0x20xx.
+
+pub const NEIGHBORS_SCROLL_UP_ID: u32 = 0x40FE;
+pub const NEIGHBORS_SCROLL_DOWN_ID: u32 = 0x40FF;
+/// First of the breadcrumb's neighbor folders, one id each
+pub const NEIGHBOR_IDS: u32 = 0x4100;
+
+/// Entries a range of ids has room for; further ones are left out
+pub const RANGE_LENGTH: usize = 0xF0;
+
+/// Id of entry `index` of the range starting at `first`.
+pub fn range_id(first: u32, index: usize) -> u32 {
+    first + index as u32
+}
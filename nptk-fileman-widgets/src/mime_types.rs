@@ -0,0 +1,244 @@
+//! Descriptions of file types
+//!
+//! File types are shown as the shared MIME database describes them ("PNG
+//! image", "Plain text document") in the user's language (`LC_ALL`,
+//! `LC_MESSAGES` or `LANG`), falling back to English. Entries the listing
+//! detected no type for get one guessed from their name with the database's
+//! glob patterns. Each type is looked up once and remembered.
+
+use crate::special_files;
+use crate::xdg;
+use nptk::services::filesystem::entry::{FileEntry, FileType};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+
+/// Languages to take descriptions in, most specific first ("de_DE", "de")
+pub(crate) static LANGUAGES: LazyLock<Vec<String>> = LazyLock::new(user_languages);
+
+/// Descriptions looked up so far, by MIME type
+static DESCRIPTIONS: LazyLock<Mutex<HashMap<String, Option<String>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static GLOBS: LazyLock<Globs> = LazyLock::new(Globs::load);
+
+fn user_languages() -> Vec<String> {
+    let name = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    // "de_DE.UTF-8@euro" -> "de_DE"
+    let name = name.split(['.', '@']).next().unwrap_or_default();
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        return Vec::new();
+    }
+    let mut languages = vec![name.to_string()];
+    if let Some((language, _)) = name.split_once('_') {
+        languages.push(language.to_string());
+    }
+    languages
+}
+
+/// `mime` directories of the XDG data directories, most important first
+fn mime_dirs() -> Vec<PathBuf> {
+    xdg::data_dirs().into_iter().map(|dir| dir.join("mime")).collect()
+}
+
+/// Description of `mime_type` in the user's language, if the database has one.
+pub fn description(mime_type: &str) -> Option<String> {
+    if let Some(known) = DESCRIPTIONS.lock().ok().and_then(|d| d.get(mime_type).cloned()) {
+        return known;
+    }
+    let found = variants(mime_type).iter().find_map(|variant| lookup(variant));
+    if let Ok(mut descriptions) = DESCRIPTIONS.lock() {
+        descriptions.insert(mime_type.to_string(), found.clone());
+    }
+    found
+}
+
+/// What an entry is, as shown in the Type column.
+pub fn describe(entry: &FileEntry) -> String {
+    if entry.is_dir() {
+        return capitalize(description("inode/directory").unwrap_or_else(|| "Folder".to_string()));
+    }
+    if let Some(kind) = special_files::entry_kind(entry) {
+        return kind.label().to_string();
+    }
+    let mime_type = entry.metadata.mime_type.clone().or_else(|| guess_from_name(&entry.name));
+    if let Some(described) = mime_type.as_deref().and_then(description) {
+        return capitalize(described);
+    }
+    if entry.file_type == FileType::Symlink {
+        return "Link".to_string();
+    }
+    match entry.path.extension() {
+        Some(extension) => format!("{} file", extension.to_string_lossy().to_uppercase()),
+        None => mime_type.unwrap_or_else(|| "File".to_string()),
+    }
+}
+
+/// MIME type of a file called `name`, from the database's glob patterns.
+pub fn guess_from_name(name: &str) -> Option<String> {
+    GLOBS.lookup(name)
+}
+
+/// Database descriptions are often lower case ("folder")
+fn capitalize(text: String) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) if first.is_lowercase() => first.to_uppercase().chain(chars).collect(),
+        _ => text,
+    }
+}
+
+/// Names `mime_type` is also known by in the database
+pub(crate) fn variants(mime_type: &str) -> Vec<String> {
+    let mut variants = vec![mime_type.to_string()];
+    if let Some((major, rest)) = mime_type.split_once('/') {
+        if let Some(stripped) = rest.strip_prefix("x-") {
+            variants.push(format!("{}/{}", major, stripped));
+        }
+    }
+    match mime_type {
+        "application/toml" => variants.push("text/x-toml".to_string()),
+        "text/x-rust" => variants.push("text/rust".to_string()),
+        "application/x-shellscript" => {
+            variants.push("text/x-shellscript".to_string());
+            variants.push("text/x-sh".to_string());
+        },
+        "application/zstd" => variants.push("application/x-zstd".to_string()),
+        "application/x-rar" => variants.push("application/vnd.rar".to_string()),
+        "text/x-log" => variants.push("text/plain".to_string()),
+        _ => {},
+    }
+    variants
+}
+
+/// Description of exactly `mime_type`: from its own file that
+/// update-mime-database writes, otherwise from the package it is defined in.
+fn lookup(mime_type: &str) -> Option<String> {
+    let (major, minor) = mime_type.split_once('/')?;
+    let dirs = mime_dirs();
+    for dir in &dirs {
+        let Ok(content) = fs::read_to_string(dir.join(major).join(format!("{minor}.xml"))) else {
+            continue;
+        };
+        if content.contains(&format!(r#"type="{}""#, mime_type)) {
+            if let Some(comment) = comment(&content) {
+                return Some(comment);
+            }
+        }
+    }
+    dirs.iter()
+        .filter_map(|dir| fs::read_dir(dir.join("packages")).ok())
+        .flat_map(|entries| entries.flatten())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("xml"))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .find_map(|content| {
+            let block = mime_type_block(&content, mime_type)?;
+            comment(block)
+        })
+}
+
+/// The `<mime-type type="...">` element of `mime_type` in a package
+fn mime_type_block<'a>(content: &'a str, mime_type: &str) -> Option<&'a str> {
+    let mut search_start = 0;
+    while let Some(idx) = content[search_start..].find("<mime-type") {
+        let start = search_start + idx;
+        let tag_end = start + content[start..].find('>')? + 1;
+        let end = tag_end + content[tag_end..].find("</mime-type>")? + "</mime-type>".len();
+        if content[start..tag_end].contains(&format!(r#"type="{}""#, mime_type)) {
+            return Some(&content[start..end]);
+        }
+        search_start = end;
+    }
+    None
+}
+
+/// The `<comment>` of a type in the user's language, otherwise the untranslated
+/// (English) one
+fn comment(block: &str) -> Option<String> {
+    let mut comments: Vec<(Option<&str>, &str)> = Vec::new();
+    let mut search_start = 0;
+    while let Some(idx) = block[search_start..].find("<comment") {
+        let start = search_start + idx;
+        let tag_end = start + block[start..].find('>')? + 1;
+        let end = tag_end + block[tag_end..].find("</comment>")?;
+        let language = block[start..tag_end]
+            .split_once(r#"xml:lang=""#)
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .map(|(language, _)| language);
+        let body = block[tag_end..end].trim();
+        if !body.is_empty() {
+            comments.push((language, body));
+        }
+        search_start = end + "</comment>".len();
+    }
+    LANGUAGES
+        .iter()
+        .find_map(|wanted| comments.iter().find(|(language, _)| *language == Some(wanted.as_str())))
+        .or_else(|| comments.iter().find(|(language, _)| language.is_none()))
+        .or_else(|| comments.iter().find(|(language, _)| *language == Some("en")))
+        .or_else(|| comments.first())
+        .map(|(_, body)| unescape(body))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Glob patterns of the database: whole file names and extensions
+#[derive(Default)]
+struct Globs {
+    names: HashMap<String, String>,
+    extensions: HashMap<String, String>,
+}
+
+impl Globs {
+    /// Read the `globs2` files ("weight:type:pattern" lines, heaviest first).
+    /// Patterns other than names and `*.ext` are rare and left out.
+    fn load() -> Self {
+        let mut globs = Globs::default();
+        for dir in mime_dirs() {
+            let Ok(content) = fs::read_to_string(dir.join("globs2")) else {
+                continue;
+            };
+            for line in content.lines().filter(|line| !line.starts_with('#')) {
+                let mut fields = line.split(':');
+                let (Some(_weight), Some(mime_type), Some(pattern)) = (fields.next(), fields.next(), fields.next())
+                else {
+                    continue;
+                };
+                let pattern = pattern.to_lowercase();
+                let target = match pattern.strip_prefix("*.") {
+                    Some(extension) if !extension.contains(['*', '?', '[']) => {
+                        globs.extensions.entry(extension.to_string())
+                    },
+                    _ if !pattern.contains(['*', '?', '[']) => globs.names.entry(pattern),
+                    _ => continue,
+                };
+                target.or_insert_with(|| mime_type.to_string());
+            }
+        }
+        globs
+    }
+
+    /// Type of `name`, the longest matching extension winning ("tar.gz" over "gz")
+    fn lookup(&self, name: &str) -> Option<String> {
+        let name = name.to_lowercase();
+        if let Some(mime_type) = self.names.get(&name) {
+            return Some(mime_type.clone());
+        }
+        name.match_indices('.')
+            .filter_map(|(index, _)| self.extensions.get(&name[index + 1..]))
+            .next()
+            .cloned()
+    }
+}
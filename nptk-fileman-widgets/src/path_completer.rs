@@ -0,0 +1,216 @@
+//! Path autocompletion component
+//!
+//! [PathCompleter] is not a widget on its own: it computes completions for a partially
+//! typed path and can build a dropdown menu from them. Any `TextInput`-based location
+//! field can attach one and show the suggestions; [crate::location_bar::FileLocationBar]
+//! does so via `with_path_completer`. Folders are listed on a blocking thread
+//! through [crate::directory_service], which keeps recent listings per folder.
+
+use nptk::core::app::update::Update;
+use nptk::core::menu::{MenuCommand, MenuItem, MenuTemplate};
+use nptk::core::signal::state::StateSignal;
+use nptk::core::signal::Signal;
+use crate::directory_service;
+use crate::xdg;
+use std::path::{Path, PathBuf};
+
+/// Computes completions for partially typed paths.
+#[derive(Debug, Clone)]
+pub struct PathCompleter {
+    show_hidden: bool,
+    directories_only: bool,
+    max_results: usize,
+    base_dir: Option<PathBuf>,
+}
+
+impl Default for PathCompleter {
+    fn default() -> Self {
+        Self {
+            show_hidden: false,
+            directories_only: true,
+            max_results: 20,
+            base_dir: None,
+        }
+    }
+}
+
+impl PathCompleter {
+    /// Create a completer that suggests non-hidden directories.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Include entries starting with a dot. Always enabled when the typed
+    /// name itself starts with a dot.
+    pub fn with_show_hidden(mut self, show_hidden: bool) -> Self {
+        self.show_hidden = show_hidden;
+        self
+    }
+
+    /// Only suggest directories (the default) or also suggest files.
+    pub fn with_directories_only(mut self, directories_only: bool) -> Self {
+        self.directories_only = directories_only;
+        self
+    }
+
+    /// Limit the number of suggestions returned.
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results;
+        self
+    }
+
+    /// Directory used to resolve relative input.
+    pub fn with_base_dir(mut self, base_dir: PathBuf) -> Self {
+        self.base_dir = Some(base_dir);
+        self
+    }
+
+    /// Expand a leading `~` and resolve relative input against the base directory.
+    fn resolve(&self, input: &str) -> PathBuf {
+        let path = if input == "~" {
+            xdg::home()
+        } else if let Some(rest) = input.strip_prefix("~/") {
+            xdg::home().join(rest)
+        } else {
+            PathBuf::from(input)
+        };
+        match &self.base_dir {
+            Some(base) if path.is_relative() => base.join(path),
+            _ => path,
+        }
+    }
+
+    /// Split the input into the directory to list and the partial name being typed.
+    fn split_input(&self, input: &str) -> (PathBuf, String) {
+        let resolved = self.resolve(input);
+        if input.ends_with('/') || input.is_empty() {
+            return (resolved, String::new());
+        }
+        let partial = resolved
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let parent = resolved.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("/"));
+        (parent, partial)
+    }
+
+    /// Directory whose entries complete the typed input.
+    pub fn directory(&self, input: &str) -> PathBuf {
+        self.split_input(input).0
+    }
+
+    /// The candidates matching the partial name of the input, sorted
+    /// case-insensitively.
+    fn select(&self, input: &str, candidates: impl Iterator<Item = PathBuf>) -> Vec<PathBuf> {
+        let (_, partial) = self.split_input(input);
+        let show_hidden = self.show_hidden || partial.starts_with('.');
+        let partial_lower = partial.to_lowercase();
+
+        let mut matches: Vec<PathBuf> = candidates
+            .filter(|path| {
+                let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                (show_hidden || !name.starts_with('.')) && name.to_lowercase().starts_with(&partial_lower)
+            })
+            .collect();
+        matches.sort_by_key(|p| p.file_name().map(|n| n.to_string_lossy().to_lowercase()));
+        matches.truncate(self.max_results);
+        matches
+    }
+
+    /// Return the full paths matching the typed input, sorted case-insensitively,
+    /// and whether each is a folder.
+    async fn complete_entries(&self, input: &str) -> Vec<(PathBuf, bool)> {
+        let entries = directory_service::list_entries(self.directory(input)).await;
+        let candidates = entries
+            .iter()
+            .filter(|(_, is_dir)| !self.directories_only || *is_dir)
+            .map(|(path, _)| path.clone());
+        self.select(input, candidates)
+            .into_iter()
+            .map(|path| {
+                let is_dir = entries.iter().any(|(entry, is_dir)| *is_dir && *entry == path);
+                (path, is_dir)
+            })
+            .collect()
+    }
+
+    /// Return the full paths matching the typed input, sorted case-insensitively.
+    pub async fn complete(&self, input: &str) -> Vec<PathBuf> {
+        self.complete_entries(input).await.into_iter().map(|(path, _)| path).collect()
+    }
+
+    /// Longest common prefix of all completions, useful for Tab-style completion.
+    pub async fn common_prefix(&self, input: &str) -> Option<String> {
+        let matches = self.complete_entries(input).await;
+        let first = matches.first()?.0.to_string_lossy().to_string();
+        let prefix = matches.iter().skip(1).fold(first, |acc, (p, _)| {
+            let other = p.to_string_lossy();
+            acc.chars()
+                .zip(other.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        });
+        if let [(_, true)] = matches.as_slice() {
+            Some(format!("{}/", prefix))
+        } else {
+            Some(prefix)
+        }
+    }
+
+    /// Build a dropdown menu for the completions of `input`.
+    ///
+    /// Picking an item writes the completion (with a trailing slash for
+    /// directories) into `text`. Returns `None` when there is nothing to suggest.
+    pub async fn menu_template(&self, input: &str, text: StateSignal<String>) -> Option<MenuTemplate> {
+        let completions = self.complete_entries(input).await;
+        Self::menu_from(completions, text)
+    }
+
+    /// Build the dropdown for the completions of `input` among `folders`, the
+    /// subfolders of its [Self::directory], without touching the filesystem.
+    pub fn folder_menu_template(
+        &self,
+        input: &str,
+        folders: &[PathBuf],
+        text: StateSignal<String>,
+    ) -> Option<MenuTemplate> {
+        let completions = self
+            .select(input, folders.iter().cloned())
+            .into_iter()
+            .map(|path| (path, true))
+            .collect();
+        Self::menu_from(completions, text)
+    }
+
+    fn menu_from(completions: Vec<(PathBuf, bool)>, text: StateSignal<String>) -> Option<MenuTemplate> {
+        if completions.is_empty() {
+            return None;
+        }
+
+        let mut command_id = 0x4000u32; // Start from 0x4000 for completion commands
+        let items = completions
+            .into_iter()
+            .map(|(path, is_dir)| {
+                let label = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string());
+                let value = if is_dir {
+                    format!("{}/", path.display())
+                } else {
+                    path.display().to_string()
+                };
+                let text = text.clone();
+                let cmd = MenuCommand::Custom(command_id);
+                command_id += 1;
+                MenuItem::new(cmd, label).with_action(move || {
+                    text.set(value.clone());
+                    Update::DRAW
+                })
+            })
+            .collect();
+
+        Some(MenuTemplate::from_items("path_completions", items))
+    }
+}
@@ -0,0 +1,177 @@
+//! Performance counters
+//!
+//! Off unless [enable]d (fileman's `--perf` flag), in which case
+//! the file list records frame times, listing throughput, loaded thumbnails and
+//! the depth of its icon/thumbnail queue. Rates are taken over one-second windows;
+//! [snapshot] returns the last complete window.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Length of the window rates are computed over
+const WINDOW: Duration = Duration::from_secs(1);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static COUNTERS: Mutex<Option<Counters>> = Mutex::new(None);
+
+/// Counters of the last complete window
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PerfSnapshot {
+    /// Frames drawn per second
+    pub frames_per_sec: f64,
+    /// Average time spent drawing a frame
+    pub frame_time: Duration,
+    /// Slowest frame
+    pub max_frame_time: Duration,
+    /// Entries per second of the last directory listing
+    pub entries_per_sec: f64,
+    /// Entries in the last directory listing
+    pub last_listing_entries: usize,
+    /// Time the last directory listing took
+    pub last_listing_time: Duration,
+    /// Thumbnails loaded per second
+    pub thumbnails_per_sec: f64,
+    /// Icon and thumbnail loads waiting to run
+    pub load_queue_depth: usize,
+    /// Icon and thumbnail loads running
+    pub loads_running: usize,
+}
+
+struct Counters {
+    window_start: Instant,
+    frames: u32,
+    frame_time: Duration,
+    max_frame_time: Duration,
+    thumbnails: u32,
+    /// Directory being listed and when listing started
+    listing: Option<(PathBuf, Instant)>,
+    last: PerfSnapshot,
+}
+
+impl Counters {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            frames: 0,
+            frame_time: Duration::ZERO,
+            max_frame_time: Duration::ZERO,
+            thumbnails: 0,
+            listing: None,
+            last: PerfSnapshot::default(),
+        }
+    }
+
+    /// Close the window once it is complete.
+    fn roll(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < WINDOW {
+            return;
+        }
+        let seconds = elapsed.as_secs_f64();
+        self.last.frames_per_sec = self.frames as f64 / seconds;
+        self.last.frame_time = self.frame_time / self.frames.max(1);
+        self.last.max_frame_time = self.max_frame_time;
+        self.last.thumbnails_per_sec = self.thumbnails as f64 / seconds;
+        self.window_start = Instant::now();
+        self.frames = 0;
+        self.frame_time = Duration::ZERO;
+        self.max_frame_time = Duration::ZERO;
+        self.thumbnails = 0;
+    }
+}
+
+/// Run `f` with the counters if recording is enabled.
+fn with_counters(f: impl FnOnce(&mut Counters)) {
+    if !is_enabled() {
+        return;
+    }
+    let mut counters = COUNTERS.lock().unwrap_or_else(|e| e.into_inner());
+    let counters = counters.get_or_insert_with(Counters::new);
+    counters.roll();
+    f(counters);
+}
+
+/// Start recording.
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Whether counters are recorded (and the overlay shown).
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Record the time a frame took to draw.
+pub fn record_frame(time: Duration) {
+    with_counters(|c| {
+        c.frames += 1;
+        c.frame_time += time;
+        c.max_frame_time = c.max_frame_time.max(time);
+    });
+}
+
+/// Note that listing `dir` started.
+pub fn listing_started(dir: &Path) {
+    with_counters(|c| c.listing = Some((dir.to_path_buf(), Instant::now())));
+}
+
+/// Note that listing `dir` finished with `entries` entries.
+pub fn listing_finished(dir: &Path, entries: usize) {
+    with_counters(|c| {
+        let Some((_, started)) = c.listing.take_if(|(listed, _)| listed.as_path() == dir) else {
+            return;
+        };
+        let time = started.elapsed();
+        c.last.last_listing_entries = entries;
+        c.last.last_listing_time = time;
+        c.last.entries_per_sec = entries as f64 / time.as_secs_f64().max(f64::EPSILON);
+    });
+}
+
+/// Record a thumbnail loaded into the cache.
+pub fn record_thumbnail() {
+    with_counters(|c| c.thumbnails += 1);
+}
+
+/// Record the current depth of the icon and thumbnail queue.
+pub fn set_load_queue_depth(queued: usize, running: usize) {
+    with_counters(|c| {
+        c.last.load_queue_depth = queued;
+        c.last.loads_running = running;
+    });
+}
+
+/// Counters of the last complete window.
+pub fn snapshot() -> PerfSnapshot {
+    let mut counters = COUNTERS.lock().unwrap_or_else(|e| e.into_inner());
+    let counters = counters.get_or_insert_with(Counters::new);
+    counters.roll();
+    counters.last
+}
+
+impl PerfSnapshot {
+    /// Lines shown in the overlay.
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!(
+                "{:.0} fps, {:.1} ms/frame (max {:.1} ms)",
+                self.frames_per_sec,
+                self.frame_time.as_secs_f64() * 1000.0,
+                self.max_frame_time.as_secs_f64() * 1000.0
+            ),
+            format!(
+                "Listed {} entries in {:.1} ms ({:.0}/s)",
+                self.last_listing_entries,
+                self.last_listing_time.as_secs_f64() * 1000.0,
+                self.entries_per_sec
+            ),
+            format!("{:.0} thumbnails/s", self.thumbnails_per_sec),
+            format!(
+                "Load queue: {} waiting, {} running",
+                self.load_queue_depth, self.loads_running
+            ),
+        ]
+    }
+}
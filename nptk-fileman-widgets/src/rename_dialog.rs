@@ -0,0 +1,387 @@
+//! Rename dialog
+//!
+//! Asks for a new file name, validates it (see [crate::file_names]) and checks for
+//! collisions before handing the result back. Warnings are confirmed by pressing Rename
+//! again; taken names come with free alternatives. By default only the stem is editable so the extension is preserved;
+//! the user can opt into editing the full name, and is asked before the extension is
+//! changed or removed, with a quick fix to keep it.
+
+use crate::file_names::{self, NameWarning};
+use async_trait::async_trait;
+use nptk::core::app::info::AppInfo;
+use nptk::core::signal::eval::EvalSignal;
+use nptk::core::signal::state::StateSignal;
+use nptk::core::vgi::Graphics;
+use nptk::prelude::*;
+use nptk::widgets::text_input::TextInput;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// Async callback deciding whether a candidate path is already taken.
+pub type CollisionCheck =
+    Arc<dyn Fn(PathBuf) -> Pin<Box<dyn Future<Output = bool> + Send>> + Send + Sync>;
+
+/// Split a file name into stem and extension (including the dot).
+///
+/// Leading dots of hidden files are part of the stem, so ".bashrc" has no extension.
+pub fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(idx) if idx > 0 => name.split_at(idx),
+        _ => (name, ""),
+    }
+}
+
+/// Handle to a shown rename dialog.
+#[derive(Clone)]
+pub struct RenameDialogHandle {
+    result: Arc<Mutex<Option<(PathBuf, PathBuf)>>>,
+}
+
+impl RenameDialogHandle {
+    /// Take the `(from, to)` pair once the user confirmed a valid, free name.
+    pub fn try_take(&self) -> Option<(PathBuf, PathBuf)> {
+        self.result.lock().ok().and_then(|mut r| r.take())
+    }
+}
+
+/// Dialog asking for a new name for `path`.
+pub struct RenameDialog {
+    path: PathBuf,
+    collision_check: CollisionCheck,
+}
+
+impl RenameDialog {
+    /// Create a rename dialog for `path`. Collisions are checked on disk by default.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            collision_check: Arc::new(|candidate| {
+                Box::pin(async move { tokio::fs::try_exists(&candidate).await.unwrap_or(false) })
+            }),
+        }
+    }
+
+    /// Replace the collision check (e.g. for remote or virtual locations).
+    pub fn with_collision_check<F, Fut>(mut self, check: F) -> Self
+    where
+        F: Fn(PathBuf) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.collision_check = Arc::new(move |candidate| Box::pin(check(candidate)));
+        self
+    }
+
+    /// Show the dialog as a popup and return a handle to poll for the result.
+    pub fn show(self, context: &AppContext) -> RenameDialogHandle {
+        let content = RenameDialogContent::new(self.path, self.collision_check);
+        let handle = RenameDialogHandle {
+            result: content.result.clone(),
+        };
+        let pos = (300, 200);
+        context
+            .popup_manager
+            .create_popup_at(Box::new(content), "Rename", (420, 170), pos);
+        handle
+    }
+}
+
+/// Which button was pressed, processed in `update()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RenameRequest {
+    Confirm,
+    ToggleExtension,
+    /// Put the original extension back after being asked about changing it
+    KeepOriginalExtension,
+}
+
+struct RenameDialogContent {
+    inner: Container,
+    path: PathBuf,
+    extension: String,
+    // Extension of the current name, which isn't changed without asking
+    original_extension: String,
+    edit_extension: bool,
+    name_text: StateSignal<String>,
+    error_text: StateSignal<String>,
+    request: Arc<Mutex<Option<RenameRequest>>>,
+    collision_check: CollisionCheck,
+    collision_result: Arc<Mutex<Option<(PathBuf, bool)>>>,
+    // Name whose warnings were shown; confirming it again accepts them
+    warned_name: Option<String>,
+    // Whether the last warning was about changing the extension
+    extension_warned: bool,
+    result: Arc<Mutex<Option<(PathBuf, PathBuf)>>>,
+    signals_hooked: bool,
+}
+
+impl RenameDialogContent {
+    fn new(path: PathBuf, collision_check: CollisionCheck) -> Self {
+        let name = path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let is_dir = path.is_dir();
+        let (stem, extension) = if is_dir { (name.as_str(), "") } else { split_extension(&name) };
+        let extension = extension.to_string();
+
+        let name_text = StateSignal::new(stem.to_string());
+        let error_text = StateSignal::new(String::new());
+        let request = Arc::new(Mutex::new(None));
+
+        let mut content = Self {
+            inner: Container::new(vec![]),
+            path,
+            original_extension: extension.clone(),
+            extension,
+            edit_extension: false,
+            name_text,
+            error_text,
+            request,
+            collision_check,
+            collision_result: Arc::new(Mutex::new(None)),
+            warned_name: None,
+            extension_warned: false,
+            result: Arc::new(Mutex::new(None)),
+            signals_hooked: false,
+        };
+        content.rebuild();
+        content
+    }
+
+    fn button(&self, label: &str, request: RenameRequest) -> Button {
+        let pending = self.request.clone();
+        Button::new(Text::new(label.to_string())).with_on_pressed(MaybeSignal::signal(Box::new(
+            EvalSignal::new(move || {
+                if let Ok(mut pending) = pending.lock() {
+                    *pending = Some(request);
+                }
+                Update::DRAW
+            }),
+        )))
+    }
+
+    fn rebuild(&mut self) {
+        let mut name_row: Vec<Box<dyn Widget>> = vec![Box::new(
+            TextInput::new()
+                .with_text_signal(self.name_text.clone())
+                .with_layout_style(LayoutStyle {
+                    size: Vector2::new(Dimension::auto(), Dimension::length(30.0)),
+                    flex_grow: 1.0,
+                    ..Default::default()
+                }),
+        )];
+        if !self.edit_extension && !self.extension.is_empty() {
+            name_row.push(Box::new(Text::new(self.extension.clone())));
+        }
+
+        let mut buttons: Vec<Box<dyn Widget>> = Vec::new();
+        if !self.extension.is_empty() || self.edit_extension {
+            let label = if self.edit_extension { "Keep Extension" } else { "Edit Extension" };
+            buttons.push(Box::new(self.button(label, RenameRequest::ToggleExtension)));
+        }
+        if self.extension_warned {
+            let label = format!("Keep {}", self.original_extension);
+            buttons.push(Box::new(self.button(&label, RenameRequest::KeepOriginalExtension)));
+        }
+        buttons.push(Box::new(
+            Button::new(Text::new("Cancel".to_string())).with_on_pressed(MaybeSignal::value(Update::DRAW)),
+        ));
+        buttons.push(Box::new(self.button("Rename", RenameRequest::Confirm)));
+
+        self.inner = Container::new(vec![
+            Box::new(Container::new(name_row).with_layout_style(LayoutStyle {
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                flex_direction: FlexDirection::Row,
+                align_items: Some(AlignItems::Center),
+                gap: Vector2::new(LengthPercentage::length(4.0), LengthPercentage::length(0.0)),
+                ..Default::default()
+            })),
+            Box::new(Text::new(self.error_text.maybe()).with_font_size(12.0)),
+            Box::new(Container::new(buttons).with_layout_style(LayoutStyle {
+                flex_direction: FlexDirection::Row,
+                gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+                justify_content: Some(JustifyContent::FlexEnd),
+                size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+                ..Default::default()
+            })),
+        ])
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::auto()),
+            flex_direction: FlexDirection::Column,
+            padding: Rect {
+                left: LengthPercentage::length(16.0),
+                right: LengthPercentage::length(16.0),
+                top: LengthPercentage::length(16.0),
+                bottom: LengthPercentage::length(16.0),
+            },
+            gap: Vector2::new(LengthPercentage::length(0.0), LengthPercentage::length(10.0)),
+            ..Default::default()
+        });
+    }
+
+    /// The full new name as currently entered.
+    fn entered_name(&self) -> String {
+        let text = (*self.name_text.get()).clone();
+        if self.edit_extension {
+            text
+        } else {
+            format!("{}{}", text, self.extension)
+        }
+    }
+
+    /// The question to ask before renaming to `name`, if it changes or removes
+    /// the extension.
+    fn extension_question(&self, name: &str) -> Option<String> {
+        let (_, extension) = split_extension(name);
+        if self.original_extension.is_empty() || extension.eq_ignore_ascii_case(&self.original_extension) {
+            return None;
+        }
+        Some(if extension.is_empty() {
+            format!("Are you sure you want to remove the extension {}?", self.original_extension)
+        } else {
+            format!(
+                "Are you sure you want to change the extension from {} to {}?",
+                self.original_extension, extension
+            )
+        })
+    }
+
+    fn target_path(&self, name: &str) -> PathBuf {
+        self.path
+            .parent()
+            .map(|p| p.join(name))
+            .unwrap_or_else(|| Path::new(name).to_path_buf())
+    }
+}
+
+#[async_trait(?Send)]
+impl Widget for RenameDialogContent {
+    fn layout_style(&self, context: &LayoutContext) -> StyleNode {
+        self.inner.layout_style(context)
+    }
+
+    async fn update(&mut self, layout: &LayoutNode, context: AppContext, info: &mut AppInfo) -> Update {
+        let mut update = Update::empty();
+
+        if !self.signals_hooked {
+            context.hook_signal(&mut self.name_text);
+            context.hook_signal(&mut self.error_text);
+            self.signals_hooked = true;
+        }
+
+        let request = self.request.lock().ok().and_then(|mut r| r.take());
+        match request {
+            Some(RenameRequest::ToggleExtension) => {
+                let name = self.entered_name();
+                self.edit_extension = !self.edit_extension;
+                if self.edit_extension {
+                    self.name_text.set(name);
+                } else {
+                    let (stem, extension) = split_extension(&name);
+                    self.extension = extension.to_string();
+                    self.name_text.set(stem.to_string());
+                }
+                self.extension_warned = false;
+                self.rebuild();
+                update.insert(Update::LAYOUT | Update::DRAW);
+            },
+            Some(RenameRequest::KeepOriginalExtension) => {
+                let name = self.entered_name();
+                let (stem, _) = split_extension(&name);
+                self.name_text.set(stem.to_string());
+                self.extension = self.original_extension.clone();
+                self.edit_extension = false;
+                self.extension_warned = false;
+                self.warned_name = None;
+                self.error_text.set(String::new());
+                self.rebuild();
+                update.insert(Update::LAYOUT | Update::DRAW);
+            },
+            Some(RenameRequest::Confirm) => {
+                let name = self.entered_name();
+                let unchanged = self.path.file_name().map(|n| n.to_string_lossy() == name).unwrap_or(false);
+                let dir = self.path.parent().unwrap_or(Path::new("/"));
+                let question = self.extension_question(&name);
+                let extension_warned = self.extension_warned;
+                self.extension_warned = false;
+                match file_names::validate(&name, dir) {
+                    Err(message) => self.error_text.set(message),
+                    Ok(_) if unchanged => self.error_text.set("The name is unchanged".to_string()),
+                    Ok(warnings)
+                        if (!warnings.is_empty() || question.is_some())
+                            && self.warned_name.as_deref() != Some(name.as_str()) =>
+                    {
+                        let messages: Vec<&str> = warnings.into_iter().map(NameWarning::message).collect();
+                        self.error_text.set(match &question {
+                            Some(question) if messages.is_empty() => {
+                                format!("{} Press Rename again to change it", question)
+                            },
+                            Some(question) => {
+                                format!("{} {}; press Rename again to use it", question, messages.join("; "))
+                            },
+                            None => format!("{}; press Rename again to use it", messages.join("; ")),
+                        });
+                        self.extension_warned = question.is_some();
+                        self.warned_name = Some(name);
+                    },
+                    Ok(_) => {
+                        let target = self.target_path(&name);
+                        let check = self.collision_check.clone();
+                        let slot = self.collision_result.clone();
+                        tokio::spawn(async move {
+                            let taken = check(target.clone()).await;
+                            if let Ok(mut slot) = slot.lock() {
+                                *slot = Some((target, taken));
+                            }
+                        });
+                        self.error_text.set("Checking name...".to_string());
+                    },
+                }
+                if self.extension_warned != extension_warned {
+                    self.rebuild();
+                    update.insert(Update::LAYOUT);
+                }
+                update.insert(Update::DRAW);
+            },
+            None => {},
+        }
+
+        let checked = self.collision_result.lock().ok().and_then(|mut r| r.take());
+        if let Some((target, taken)) = checked {
+            if taken {
+                let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                let alternatives: Vec<String> = file_names::alternatives(&target, 2)
+                    .into_iter()
+                    .map(|alternative| format!("\"{}\"", alternative))
+                    .collect();
+                self.error_text.set(format!(
+                    "\"{}\" already exists; {} {} free",
+                    name,
+                    alternatives.join(" and "),
+                    if alternatives.len() == 1 { "is" } else { "are" }
+                ));
+            } else {
+                self.error_text.set(String::new());
+                if let Ok(mut result) = self.result.lock() {
+                    *result = Some((self.path.clone(), target));
+                }
+            }
+            update.insert(Update::DRAW);
+        }
+
+        update |= self.inner.update(layout, context, info).await;
+        update
+    }
+
+    fn render(
+        &mut self,
+        graphics: &mut dyn Graphics,
+        layout: &LayoutNode,
+        info: &mut AppInfo,
+        context: AppContext,
+    ) {
+        self.inner.render(graphics, layout, info, context)
+    }
+}
@@ -0,0 +1,132 @@
+//! Total size of a selection, walked in the background
+//!
+//! Folders in a selection can hold any number of files, so the total is added up
+//! on a blocking thread. Intermediate totals are published while the walk goes
+//! on, and selecting something else cancels the walk and starts a new one.
+//! Symbolic links are counted as links and not followed.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::sizes;
+
+/// How often intermediate totals are published
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(150);
+
+/// Total of the files in a selection, folder contents included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SelectionTotal {
+    /// Bytes in the files found so far
+    pub bytes: u64,
+    /// Files found so far
+    pub files: u64,
+    /// Whether the walk is done, so the total is final
+    pub complete: bool,
+}
+
+impl SelectionTotal {
+    /// The size, followed by "..." while it is still growing.
+    pub fn format(&self) -> String {
+        if self.complete {
+            sizes::format(self.bytes)
+        } else {
+            format!("{}...", sizes::format(self.bytes))
+        }
+    }
+}
+
+struct Walk {
+    selection: Vec<PathBuf>,
+    total: SelectionTotal,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Computes selection totals in the background. Clones share the walk, so the
+/// status bar and the info panel can show the same total without walking twice.
+#[derive(Clone, Default)]
+pub struct SelectionSizer {
+    walk: Arc<Mutex<Option<Walk>>>,
+}
+
+impl SelectionSizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current total of `selection`. A different selection than the last one
+    /// cancels the running walk and starts walking it; `on_progress` is called
+    /// whenever a newer total is in.
+    pub fn total<F>(&self, selection: &[PathBuf], on_progress: F) -> SelectionTotal
+    where
+        F: Fn() + Send + 'static,
+    {
+        let Ok(mut walk) = self.walk.lock() else {
+            return SelectionTotal::default();
+        };
+        if let Some(walk) = walk.as_ref().filter(|w| w.selection == selection) {
+            return walk.total;
+        }
+        if let Some(previous) = walk.take() {
+            previous.cancelled.store(true, Ordering::Relaxed);
+        }
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let total = SelectionTotal {
+            complete: selection.is_empty(),
+            ..Default::default()
+        };
+        *walk = Some(Walk {
+            selection: selection.to_vec(),
+            total,
+            cancelled: cancelled.clone(),
+        });
+        if !selection.is_empty() {
+            let shared = self.walk.clone();
+            let selection = selection.to_vec();
+            tokio::task::spawn_blocking(move || {
+                add_up(selection, &cancelled, |total| {
+                    let Ok(mut walk) = shared.lock() else {
+                        return;
+                    };
+                    if let Some(walk) = walk.as_mut().filter(|w| Arc::ptr_eq(&w.cancelled, &cancelled)) {
+                        walk.total = total;
+                        on_progress();
+                    }
+                });
+            });
+        }
+        total
+    }
+}
+
+/// Walk `selection`, passing intermediate totals and then the final one to
+/// `publish`. Stops without a final total once `cancelled` is set.
+fn add_up(selection: Vec<PathBuf>, cancelled: &AtomicBool, publish: impl Fn(SelectionTotal)) {
+    let mut total = SelectionTotal::default();
+    let mut pending = selection;
+    let mut published = Instant::now();
+    while let Some(path) = pending.pop() {
+        if cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = fs::read_dir(&path) {
+                pending.extend(entries.flatten().map(|entry| entry.path()));
+            }
+        } else {
+            total.files += 1;
+            total.bytes = total.bytes.saturating_add(metadata.len());
+        }
+        if published.elapsed() >= PUBLISH_INTERVAL {
+            publish(total);
+            published = Instant::now();
+        }
+    }
+    total.complete = true;
+    publish(total);
+}
@@ -0,0 +1,71 @@
+//! Formatting of file sizes
+//!
+//! Sizes are shown in binary units (KiB, MiB: powers of 1024) unless SI units
+//! (kB, MB: powers of 1000) are chosen with [set_units]. Everything that shows a
+//! size to the user goes through [format] so the choice applies everywhere.
+
+use humansize::{BINARY, DECIMAL, format_size};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static UNITS: AtomicU8 = AtomicU8::new(SizeUnits::Binary as u8);
+
+/// Units sizes are shown in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SizeUnits {
+    /// KiB, MiB, GiB
+    #[default]
+    Binary,
+    /// kB, MB, GB
+    Si,
+}
+
+impl SizeUnits {
+    pub const ALL: [SizeUnits; 2] = [SizeUnits::Binary, SizeUnits::Si];
+
+    /// Name in the settings file
+    pub fn key(self) -> &'static str {
+        match self {
+            SizeUnits::Binary => "binary",
+            SizeUnits::Si => "si",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|units| units.key() == key)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SizeUnits::Binary => "KiB, MiB (1024)",
+            SizeUnits::Si => "kB, MB (1000)",
+        }
+    }
+
+    /// The units after these (for cycling through them with a button).
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|u| *u == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Show sizes in `units`.
+pub fn set_units(units: SizeUnits) {
+    UNITS.store(units as u8, Ordering::Relaxed);
+}
+
+/// The units sizes are shown in.
+pub fn units() -> SizeUnits {
+    let value = UNITS.load(Ordering::Relaxed);
+    SizeUnits::ALL
+        .into_iter()
+        .find(|units| *units as u8 == value)
+        .unwrap_or_default()
+}
+
+/// Format `bytes` in the units set, e.g. "1.50 MiB".
+pub fn format(bytes: u64) -> String {
+    match units() {
+        SizeUnits::Binary => format_size(bytes, BINARY),
+        SizeUnits::Si => format_size(bytes, DECIMAL),
+    }
+}
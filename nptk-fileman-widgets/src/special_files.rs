@@ -0,0 +1,94 @@
+//! Sparse files and special files (pipes, sockets and devices)
+//!
+//! Special files have no content that could be read like a file's: reading a
+//! pipe blocks until something writes to it, and reading a device can have side
+//! effects. They get icons of their own and are never sniffed, thumbnailed or
+//! previewed. Sparse files take less room on disk than their size says, which is
+//! shown next to their size.
+
+use nptk::services::filesystem::entry::{FileEntry, FileType};
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+
+/// Kind of a file that isn't a regular file, folder or link
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialKind {
+    Fifo,
+    Socket,
+    CharDevice,
+    BlockDevice,
+}
+
+impl SpecialKind {
+    /// Kind of the file described by `metadata`, `None` for ordinary files.
+    pub fn of(metadata: &fs::Metadata) -> Option<Self> {
+        let file_type = metadata.file_type();
+        if file_type.is_fifo() {
+            Some(SpecialKind::Fifo)
+        } else if file_type.is_socket() {
+            Some(SpecialKind::Socket)
+        } else if file_type.is_char_device() {
+            Some(SpecialKind::CharDevice)
+        } else if file_type.is_block_device() {
+            Some(SpecialKind::BlockDevice)
+        } else {
+            None
+        }
+    }
+
+    /// Kind of the file at `path`, following links.
+    pub fn of_path(path: &Path) -> Option<Self> {
+        fs::metadata(path).ok().as_ref().and_then(Self::of)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SpecialKind::Fifo => "Named pipe",
+            SpecialKind::Socket => "Socket",
+            SpecialKind::CharDevice => "Character device",
+            SpecialKind::BlockDevice => "Block device",
+        }
+    }
+
+    /// MIME type of the shared MIME-info database
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            SpecialKind::Fifo => "inode/fifo",
+            SpecialKind::Socket => "inode/socket",
+            SpecialKind::CharDevice => "inode/chardevice",
+            SpecialKind::BlockDevice => "inode/blockdevice",
+        }
+    }
+
+    /// Name of the icon in the icon theme
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            SpecialKind::Fifo => "inode-fifo",
+            SpecialKind::Socket => "inode-socket",
+            SpecialKind::CharDevice => "inode-chardevice",
+            SpecialKind::BlockDevice => "inode-blockdevice",
+        }
+    }
+}
+
+/// Bytes the file described by `metadata` takes on disk.
+pub fn on_disk_size(metadata: &fs::Metadata) -> u64 {
+    // st_blocks counts 512-byte units whatever the block size of the filesystem
+    metadata.blocks().saturating_mul(512)
+}
+
+/// Whether the file described by `metadata` has holes, taking less room on disk
+/// than its size.
+pub fn is_sparse(metadata: &fs::Metadata) -> bool {
+    metadata.is_file() && on_disk_size(metadata) < metadata.len()
+}
+
+/// Special kind of a listed entry. Only entries that aren't files, folders or
+/// links are looked at, so ordinary entries cost no extra `stat`.
+pub fn entry_kind(entry: &FileEntry) -> Option<SpecialKind> {
+    if entry.file_type != FileType::Other {
+        return None;
+    }
+    SpecialKind::of_path(&entry.path)
+}
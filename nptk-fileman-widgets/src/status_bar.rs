@@ -1,22 +1,80 @@
 use nptk::prelude::*;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use async_trait::async_trait;
 use nptk::core::signal::state::StateSignal;
 use nptk::core::vg::kurbo::Shape;
+use crate::accessibility::{AccessibleNode, AccessibleRole, AccessibleSubtree};
+use crate::color_scheme;
+use crate::commands::CommandQueue;
+use crate::focus::{FocusChain, Pane};
+use crate::selection_size::SelectionSizer;
+
+/// How long a temporary message is shown
+const MESSAGE_DURATION: Duration = Duration::from_secs(3);
+/// How long a toast is shown, leaving time to take one of its actions
+const TOAST_DURATION: Duration = Duration::from_secs(8);
+
+type ToastAction = (String, Arc<dyn Fn() + Send + Sync>);
+
+/// A temporary message that can offer actions, such as restoring what the
+/// message reports was removed.
+#[derive(Clone)]
+pub struct Toast {
+    message: String,
+    actions: Vec<ToastAction>,
+}
+
+impl Toast {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Offer a button labelled `label` that calls `run` and dismisses the toast.
+    /// Buttons are shown in the order they are added.
+    pub fn with_action(mut self, label: impl Into<String>, run: impl Fn() + Send + Sync + 'static) -> Self {
+        self.actions.push((label.into(), Arc::new(run)));
+        self
+    }
+}
 
 /// A status bar widget that displays:
-/// 1. Navigation info (path + selection count)
-/// 2. Temporary status messages (with timeout)
+/// 1. Navigation info (path + selection count and size)
+/// 2. Temporary status messages (with timeout), possibly with actions
 /// 3. Hover status tips (from framework)
+/// 4. How many hidden items are left out of the listing, as a button showing them
 pub struct FileStatusBar {
     inner: Container,
     current_path: StateSignal<PathBuf>,
     selected_paths: StateSignal<Vec<PathBuf>>,
     status_text: StateSignal<String>,
     status_message_rx: Option<mpsc::UnboundedReceiver<String>>,
-    status_message_timeout: Option<std::time::Instant>,
+    status_message_timeout: Option<Instant>,
+    // How long the current temporary message is shown
+    status_message_duration: Duration,
+    toast_rx: Option<mpsc::UnboundedReceiver<Toast>>,
+    // Actions of the shown toast, run once their button is pressed
+    actions: Vec<Arc<dyn Fn() + Send + Sync>>,
+    action_labels: Vec<String>,
+    // Indexes into `actions`
+    action_presses: CommandQueue<usize>,
+    // Hidden items left out of the listing, and what shows them
+    hidden_count: Option<StateSignal<usize>>,
+    toggle_hidden: Option<Arc<dyn Fn() + Send + Sync>>,
+    hidden_presses: CommandQueue<()>,
+    // Label of the hidden items button, empty while it isn't shown
+    hidden_label: StateSignal<String>,
     signals_hooked: bool,
+    sizer: SelectionSizer,
+    // Last temporary message, announced by screen readers
+    announcement: String,
+    accessibility: AccessibleSubtree,
+    focus: Option<FocusChain>,
 }
 
 impl FileStatusBar {
@@ -25,22 +83,7 @@ impl FileStatusBar {
         selected_paths: StateSignal<Vec<PathBuf>>,
     ) -> Self {
         let status_text = StateSignal::new("Ready".to_string());
-        let status_text_clone = status_text.clone();
-        
-        let container = Container::new(vec![
-            Box::new(Text::new(status_text_clone.maybe()).with_font_size(14.0)),
-        ])
-        .with_layout_style(LayoutStyle {
-            size: Vector2::new(Dimension::percent(1.0), Dimension::length(24.0)),
-            padding: nptk::core::layout::Rect { 
-                left: LengthPercentage::length(5.0), 
-                right: LengthPercentage::length(5.0), 
-                top: LengthPercentage::length(0.0), 
-                bottom: LengthPercentage::length(0.0) 
-            },
-            align_items: Some(AlignItems::Center),
-            ..Default::default()
-        });
+        let container = Self::container(&status_text, Vec::new(), None);
 
         Self {
             inner: container,
@@ -49,19 +92,135 @@ impl FileStatusBar {
             status_text,
             status_message_rx: None,
             status_message_timeout: None,
+            status_message_duration: MESSAGE_DURATION,
+            toast_rx: None,
+            actions: Vec::new(),
+            action_labels: Vec::new(),
+            action_presses: CommandQueue::new(),
+            hidden_count: None,
+            toggle_hidden: None,
+            hidden_presses: CommandQueue::new(),
+            hidden_label: StateSignal::new(String::new()),
             signals_hooked: false,
+            sizer: SelectionSizer::new(),
+            announcement: String::new(),
+            accessibility: AccessibleSubtree::new(),
+            focus: None,
         }
     }
 
+    /// Handle to the accessibility tree of the status bar. Temporary messages
+    /// (operation results, errors) are announced.
+    pub fn accessibility(&self) -> AccessibleSubtree {
+        self.accessibility.clone()
+    }
+
     pub fn with_message_receiver(mut self, rx: mpsc::UnboundedReceiver<String>) -> Self {
         self.status_message_rx = Some(rx);
         self
     }
+
+    /// Show the toasts sent to `rx`, like other temporary messages but for
+    /// longer and with their actions.
+    pub fn with_toast_receiver(mut self, rx: mpsc::UnboundedReceiver<Toast>) -> Self {
+        self.toast_rx = Some(rx);
+        self
+    }
+
+    /// Join the window's focus chain (shows a focus ring while focused).
+    pub fn with_focus_chain(mut self, focus: FocusChain) -> Self {
+        self.focus = Some(focus);
+        self
+    }
+
+    /// Show "(+12 hidden)" while `count` hidden items are left out of the
+    /// listing; pressing it calls `toggle`, which is to show them.
+    pub fn with_hidden_count(mut self, count: StateSignal<usize>, toggle: impl Fn() + Send + Sync + 'static) -> Self {
+        self.hidden_count = Some(count);
+        self.toggle_hidden = Some(Arc::new(toggle));
+        self
+    }
+
+    /// Share the selection size computation, e.g. with the info panel.
+    pub fn with_selection_sizer(mut self, sizer: SelectionSizer) -> Self {
+        self.sizer = sizer;
+        self
+    }
     
-    fn update_status_from_navigation(&mut self) {
+    fn container(status_text: &StateSignal<String>, actions: Vec<Button>, hidden: Option<Button>) -> Container {
+        let mut children: Vec<Box<dyn Widget>> = vec![
+            Box::new(Text::new(status_text.maybe()).with_font_size(14.0)),
+        ];
+        for action in actions {
+            children.push(Box::new(action));
+        }
+        if let Some(hidden) = hidden {
+            children.push(Box::new(hidden));
+        }
+        Container::new(children)
+        .with_layout_style(LayoutStyle {
+            size: Vector2::new(Dimension::percent(1.0), Dimension::length(24.0)),
+            padding: nptk::core::layout::Rect { 
+                left: LengthPercentage::length(5.0), 
+                right: LengthPercentage::length(5.0), 
+                top: LengthPercentage::length(0.0), 
+                bottom: LengthPercentage::length(0.0) 
+            },
+            gap: Vector2::new(LengthPercentage::length(8.0), LengthPercentage::length(0.0)),
+            align_items: Some(AlignItems::Center),
+            ..Default::default()
+        })
+    }
+
+    /// Show the buttons of a toast's actions next to the message, or remove them.
+    fn show_actions(&mut self, actions: Vec<ToastAction>) {
+        if actions.is_empty() && self.actions.is_empty() {
+            return;
+        }
+        // Presses of the previous toast's buttons must not run this one's actions
+        self.action_presses.take();
+        (self.action_labels, self.actions) = actions.into_iter().unzip();
+        self.rebuild();
+    }
+
+    /// Build the bar again with the buttons of the shown toast and, if any
+    /// hidden items are left out, the button showing them.
+    fn rebuild(&mut self) {
+        let buttons = self
+            .action_labels
+            .iter()
+            .enumerate()
+            .map(|(index, label)| {
+                Button::new(Text::new(label.clone())).with_on_pressed(self.action_presses.on_pressed(index))
+            })
+            .collect();
+        let hidden = (!self.hidden_label.get().is_empty()).then(|| {
+            Button::new(Text::new(self.hidden_label.maybe())).with_on_pressed(self.hidden_presses.on_pressed(()))
+        });
+        self.inner = Self::container(&self.status_text, buttons, hidden);
+    }
+
+    /// Follow the number of hidden items left out; the button comes and goes
+    /// with them. Returns whether the bar had to be built again.
+    fn update_hidden_count(&mut self) -> bool {
+        let count = self.hidden_count.as_ref().map_or(0, |count| *count.get());
+        let label = if count > 0 { format!("(+{} hidden)", count) } else { String::new() };
+        if *self.hidden_label.get() == label {
+            return false;
+        }
+        let shown_before = !self.hidden_label.get().is_empty();
+        self.hidden_label.set(label);
+        if shown_before == (count > 0) {
+            return false;
+        }
+        self.rebuild();
+        true
+    }
+
+    fn update_status_from_navigation(&mut self, context: &nptk::core::app::context::AppContext) {
          // Check if timeout expired for status messages
         if let Some(timeout) = self.status_message_timeout {
-            if timeout.elapsed() > std::time::Duration::from_secs(3) {
+            if timeout.elapsed() > self.status_message_duration {
                 self.status_message_timeout = None;
                 // Timeout expired, fall through to show normal status
             } else {
@@ -72,10 +231,14 @@ impl FileStatusBar {
         // No temporary message - show current path (with selection count if applicable)
         let nav_path = (*self.current_path.get()).clone();
         let path_str = nav_path.to_string_lossy().to_string();
-        let selection_count = (*self.selected_paths.get()).len();
+        let selection = (*self.selected_paths.get()).clone();
+        let selection_count = selection.len();
         
         let status_msg = if selection_count > 0 {
-            format!("{} - {} item(s) selected", path_str, selection_count)
+            // Folders are added up in the background; the total grows as it goes
+            let update_manager = context.update();
+            let total = self.sizer.total(&selection, move || update_manager.insert(Update::DRAW));
+            format!("{} - {} item(s) selected ({})", path_str, selection_count, total.format())
         } else {
             path_str
         };
@@ -106,28 +269,77 @@ impl Widget for FileStatusBar {
             context.hook_signal(&mut self.status_text);
             context.hook_signal(&mut self.current_path);
             context.hook_signal(&mut self.selected_paths);
+            context.hook_signal(&mut self.hidden_label);
+            if let Some(count) = &mut self.hidden_count {
+                context.hook_signal(count);
+            }
             self.signals_hooked = true;
         }
 
+        if self.hidden_presses.take().first().is_some() {
+            if let Some(toggle) = &self.toggle_hidden {
+                toggle();
+            }
+        }
+        if self.update_hidden_count() {
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
         // Poll status messages from operations (these are temporary messages)
         let mut has_active_temporary_message = false;
         if let Some(ref mut rx) = self.status_message_rx {
              while let Ok(msg) = rx.try_recv() {
+                self.announcement = msg.clone();
                 self.status_text.set(msg);
-                self.status_message_timeout = Some(std::time::Instant::now());
+                self.status_message_timeout = Some(Instant::now());
+                self.status_message_duration = MESSAGE_DURATION;
                 has_active_temporary_message = true;
                 update.insert(Update::DRAW);
             }
         }
-        
+        let mut toasts = Vec::new();
+        if let Some(ref mut rx) = self.toast_rx {
+            while let Ok(toast) = rx.try_recv() {
+                toasts.push(toast);
+            }
+        }
+        if let Some(toast) = toasts.pop() {
+            self.announcement = toast.message.clone();
+            self.status_text.set(toast.message);
+            self.status_message_timeout = Some(Instant::now());
+            self.status_message_duration = TOAST_DURATION;
+            has_active_temporary_message = true;
+            self.show_actions(toast.actions);
+            update.insert(Update::LAYOUT | Update::DRAW);
+        } else if has_active_temporary_message && !self.actions.is_empty() {
+            // A newer message replaces the toast along with its actions
+            self.show_actions(Vec::new());
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
+
+        // A toast goes away once one of its actions is taken
+        if let Some(index) = self.action_presses.take().first().copied() {
+            if let Some(run) = self.actions.get(index).cloned() {
+                self.show_actions(Vec::new());
+                run();
+                update.insert(Update::LAYOUT | Update::DRAW);
+            }
+            self.status_message_timeout = None;
+            has_active_temporary_message = false;
+        }
+
         // Check if we have an active temporary message (within timeout)
         if !has_active_temporary_message {
             if let Some(timeout) = self.status_message_timeout {
-                if timeout.elapsed() <= std::time::Duration::from_secs(3) {
+                if timeout.elapsed() <= self.status_message_duration {
                     has_active_temporary_message = true;
                 }
             }
         }
+        if !has_active_temporary_message && !self.actions.is_empty() {
+            self.show_actions(Vec::new());
+            update.insert(Update::LAYOUT | Update::DRAW);
+        }
 
         // Priority: 1) Temporary messages, 2) Framework status bar text (button status tips), 3) Default navigation info
         if !has_active_temporary_message {
@@ -139,7 +351,7 @@ impl Widget for FileStatusBar {
                 update.insert(Update::DRAW);
             } else {
                 // No framework status text - update status from navigation
-                self.update_status_from_navigation();
+                self.update_status_from_navigation(&context);
                 // Check if status text actually changed to trigger draw? 
                 // update_status_from_navigation sets signal, which triggers global update loop if hooked, 
                 // but we might want to be explicit.
@@ -147,6 +359,21 @@ impl Widget for FileStatusBar {
             }
         }
         
+        let mut children = vec![AccessibleNode::new(AccessibleRole::StatusBar, self.announcement.clone()).live()];
+        let hidden_label = (*self.hidden_label.get()).clone();
+        if !hidden_label.is_empty() {
+            children.push(AccessibleNode::new(AccessibleRole::Button, hidden_label));
+        }
+        self.accessibility.set(
+            AccessibleNode::new(AccessibleRole::StatusBar, (*self.status_text.get()).clone()).with_children(children),
+        );
+
+        if let Some(focus) = &self.focus {
+            if focus.focus_on_click(Pane::StatusBar, layout, info) {
+                update.insert(Update::DRAW);
+            }
+        }
+
         update |= self.inner.update(layout, context, info).await;
         update
     }
@@ -160,8 +387,8 @@ impl Widget for FileStatusBar {
     ) {
         // Draw background (optional, could be done via theme/properties)
         let palette = context.palette();
-        let bg = palette.color(nptk::core::theme::ColorRole::Window);
-        let border = palette.color(nptk::core::theme::ColorRole::ThreedShadow1);
+        let bg = color_scheme::color(palette, nptk::core::theme::ColorRole::Window);
+        let border = color_scheme::color(palette, nptk::core::theme::ColorRole::ThreedShadow1);
         
         let rect = nptk::core::vg::kurbo::Rect::new(
             layout.layout.location.x as f64,
@@ -191,7 +418,10 @@ impl Widget for FileStatusBar {
             &border_line.into_path(0.1)
         );
         
-        self.inner.render(graphics, layout, info, context)
+        self.inner.render(graphics, layout, info, context.clone());
+        if let Some(focus) = &self.focus {
+            focus.render_ring(Pane::StatusBar, graphics, layout, context.palette());
+        }
     }
 }
 
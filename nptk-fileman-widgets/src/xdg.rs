@@ -0,0 +1,49 @@
+//! Base directories of the XDG Base Directory Specification
+//!
+//! Each one comes from its environment variable, or the usual folder in the
+//! home directory when that is unset or not an absolute path.
+
+use std::path::PathBuf;
+
+/// The home directory, or `/` without `$HOME`.
+pub fn home() -> PathBuf {
+    std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/"))
+}
+
+fn base_dir(variable: &str, fallback: &str) -> PathBuf {
+    std::env::var_os(variable)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| home().join(fallback))
+}
+
+/// `$XDG_CONFIG_HOME`, `~/.config` by default.
+pub fn config_home() -> PathBuf {
+    base_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_DATA_HOME`, `~/.local/share` by default.
+pub fn data_home() -> PathBuf {
+    base_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// `$XDG_STATE_HOME`, `~/.local/state` by default.
+pub fn state_home() -> PathBuf {
+    base_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// `$XDG_CACHE_HOME`, `~/.cache` by default.
+pub fn cache_home() -> PathBuf {
+    base_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// [data_home] followed by `$XDG_DATA_DIRS` (`/usr/local/share:/usr/share`
+/// by default), most important first.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let system = std::env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    std::iter::once(data_home())
+        .chain(std::env::split_paths(&system).filter(|dir| dir.is_absolute()))
+        .collect()
+}